@@ -0,0 +1,48 @@
+#![no_main]
+
+use galileo_osnma::bitfields::{DsmKroot, DsmPkr, Mack, NmaHeader};
+use galileo_osnma::merkle_tree::MerkleTree;
+use galileo_osnma::tesla::{Chain, Key};
+use galileo_osnma::validation::NotValidated;
+use galileo_osnma::{Gst, PublicKey};
+use libfuzzer_sys::fuzz_target;
+use p256::ecdsa::VerifyingKey;
+
+// Dummy public key and Merkle tree root
+const PUBKEY: [u8; 33] = [
+    3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33, 117, 116, 91, 202, 57, 34, 72, 200, 202, 10,
+    169, 253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218,
+];
+const MERKLE_TREE_ROOT: [u8; 32] = [0x5a; 32];
+
+fuzz_target!(
+    |input: (u8, DsmKroot<'_>, DsmPkr<'_>, Mack<'_, NotValidated>)| {
+        let (nma_header, dsm_kroot, dsm_pkr, mack) = input;
+        let nma_header = NmaHeader::new(nma_header);
+        let _ = format!("{nma_header:?}");
+
+        let _ = format!("{dsm_kroot:?}");
+        let chain = Chain::from_dsm_kroot(dsm_kroot).ok();
+        if let Some(chain) = &chain {
+            let _ = format!("{chain}");
+            let pubkey = VerifyingKey::from_sec1_bytes(&PUBKEY).unwrap();
+            let pubkey = PublicKey::from_p256(pubkey, dsm_kroot.public_key_id()).force_valid();
+            let _ = Key::from_dsm_kroot(nma_header, dsm_kroot, &pubkey);
+        }
+
+        let _ = format!("{dsm_pkr:?}");
+        let _ = MerkleTree::new(MERKLE_TREE_ROOT).validate_pkr(dsm_pkr);
+
+        let _ = format!("{mack:?}");
+        for j in 1..mack.num_tags() {
+            let tag = mack.tag_and_info(j);
+            let _ = (tag.prnd(), tag.adkd(), tag.cop());
+        }
+        if let Some(chain) = chain.filter(|c| {
+            c.key_size_bits() == mack.key_size() && c.tag_size_bits() == mack.tag_size()
+        }) {
+            let key = Key::from_bitslice(mack.key(), Gst::new(1176, 121080), &chain);
+            let _ = format!("{key:?}");
+        }
+    }
+);
@@ -0,0 +1,33 @@
+#![no_main]
+
+use galileo_osnma::storage::SmallStorage;
+use galileo_osnma::types::{InavWord, OsnmaDataMessage};
+use galileo_osnma::{Gst, InavBand, Osnma, PublicKey, Svn};
+use libfuzzer_sys::fuzz_target;
+use p256::ecdsa::VerifyingKey;
+
+// Dummy public key, which does not match the signature of any DSM-KROOT
+const PUBKEY: [u8; 33] = [
+    3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33, 117, 116, 91, 202, 57, 34, 72, 200, 202, 10,
+    169, 253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218,
+];
+
+// Each page is given by an SVN, the number of pages elapsed since the previous
+// page, the band (true for E5b-I), the INAV word and the OSNMA data message.
+type Page = (u8, u8, bool, InavWord, OsnmaDataMessage);
+
+fuzz_target!(|pages: Vec<Page>| {
+    let pubkey = VerifyingKey::from_sec1_bytes(&PUBKEY).unwrap();
+    let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    let mut osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+    let mut gst = Gst::new(1176, 121050);
+    for (svn, elapsed, e5b, word, osnma_data) in pages {
+        let Ok(svn) = Svn::try_from(svn) else {
+            continue;
+        };
+        gst = gst.add_seconds(2 * i32::from(elapsed % 16));
+        let band = if e5b { InavBand::E5B } else { InavBand::E1B };
+        osnma.feed_inav(&word, svn, gst, band);
+        osnma.feed_osnma_band(&osnma_data, svn, gst, band);
+    }
+});
@@ -1,21 +1,84 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use galileo_osnma::{
-    galmon::{navmon::nav_mon_message::GalileoInav, transport::ReadTransport},
+    bitfields::{DsmKroot, DsmPkr, NewPublicKeyType},
+    galmon::transport::ReadTransport,
+    merkle_tree::MerkleTree,
+    navmessage::NavMessageDataOwned,
     storage::FullStorage,
-    types::{BitSlice, NUM_SVNS},
-    Gst, InavBand, Osnma, PublicKey, Svn, Validated, Wn,
+    tesla::{Key, NmaHeader},
+    types::NUM_SVNS,
+    Gst, MerkleTreeNode, Osnma, PublicKey, Svn, Validated,
 };
 use spki::DecodePublicKey;
+use stats::Stats;
 use std::io::Read;
 
+mod stats;
+
 /// Process OSNMA data reading Galmon protobuf from stdin
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    process: ProcessArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the fields of a DSM message
+    Inspect {
+        /// DSM message in hex.
+        #[arg(long)]
+        hex: String,
+        /// DSM ID of the message (0-11 for DSM-KROOT, 12-15 for DSM-PKR).
+        #[arg(long)]
+        dsm_id: u8,
+    },
+    /// Verify the ECDSA signature of a DSM-KROOT message
+    VerifyKroot {
+        /// DSM-KROOT message in hex.
+        #[arg(long)]
+        hex: String,
+        /// NMA header transmitted with the DSM-KROOT, in hex.
+        #[arg(long)]
+        nma_header: String,
+        #[command(flatten)]
+        pubkey: PubkeyArgs,
+    },
+    /// Verify a DSM-PKR message against the Merkle tree root
+    VerifyPkr {
+        /// DSM-PKR message in hex.
+        #[arg(long)]
+        hex: String,
+        /// Merkle tree root in hex.
+        #[arg(long)]
+        merkle_root: String,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ProcessArgs {
     /// Merkle tree root in hex.
     #[arg(long)]
     merkle_root: Option<String>,
+    #[command(flatten)]
+    pubkey: PubkeyArgs,
+    /// Only process slow MAC data.
+    #[arg(long)]
+    slow_mac_only: bool,
+    /// Print statistics when the input stream ends.
+    #[arg(long)]
+    stats: bool,
+    /// Write statistics in JSON format to a file when the input stream ends.
+    #[arg(long)]
+    stats_json: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct PubkeyArgs {
     /// Path to the P-256 public key in PEM format.
     #[arg(long)]
     pubkey: Option<String>,
@@ -25,9 +88,42 @@ struct Args {
     /// ID of the public key.
     #[arg(long)]
     pkid: Option<u8>,
-    /// Only process slow MAC data.
-    #[arg(long)]
-    slow_mac_only: bool,
+}
+
+impl PubkeyArgs {
+    fn is_given(&self) -> bool {
+        self.pubkey.is_some() || self.pubkey_p521.is_some()
+    }
+
+    fn load(&self) -> Result<Option<PublicKey<Validated>>> {
+        if self.pubkey.is_some() && self.pubkey_p521.is_some() {
+            anyhow::bail!("the --pubkey and --pubkey-p521 arguments are mutually exclusive");
+        }
+
+        if self.pubkey.is_some() && self.pkid.is_none() {
+            anyhow::bail!("the --pubkey and --pkid arguments need to be both specified together");
+        }
+
+        if self.pubkey_p521.is_some() && self.pkid.is_none() {
+            anyhow::bail!(
+                "the --pubkey-p521 and --pkid arguments need to be both specified together"
+            );
+        }
+
+        if self.pkid.is_some() && !self.is_given() {
+            anyhow::bail!(
+                "the --pkid argument needs to be used together with --pubkey or --pubkey-p521"
+            );
+        }
+
+        Ok(if let Some(pubkey_path) = &self.pubkey {
+            Some(load_pubkey(pubkey_path, self.pkid.unwrap())?)
+        } else if let Some(pubkey_hex) = &self.pubkey_p521 {
+            Some(load_pubkey_p521(pubkey_hex, self.pkid.unwrap())?)
+        } else {
+            None
+        })
+    }
 }
 
 fn load_pubkey(path: &str, pkid: u8) -> Result<PublicKey<Validated>> {
@@ -44,46 +140,148 @@ fn load_pubkey_p521(hex: &str, pkid: u8) -> Result<PublicKey<Validated>> {
     Ok(PublicKey::from_p521(pubkey, pkid).force_valid())
 }
 
-fn main() -> Result<()> {
-    env_logger::init();
-    let args = Args::parse();
+fn parse_merkle_root(hex: &str) -> Result<MerkleTreeNode> {
+    hex::decode(hex)
+        .context("failed to parse Merkle tree root")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("the Merkle tree root has a wrong length"))
+}
 
-    if args.merkle_root.is_none() && args.pubkey.is_none() && args.pubkey_p521.is_none() {
-        anyhow::bail!("at least either the Merkle tree root or the public key must be specified");
-    }
+// Size in bytes of a DSM block
+const DSM_BLOCK_BYTES: usize = 13;
 
-    if args.pubkey.is_some() && args.pubkey_p521.is_some() {
-        anyhow::bail!("the --pubkey and --pubkey-p521 arguments are mutually exclusive");
+// Parses a DSM-KROOT, checking that its fields are consistent with its length,
+// so that the accessors of DsmKroot do not panic.
+fn parse_dsm_kroot(dsm: &[u8]) -> Result<DsmKroot<'_>> {
+    if dsm.is_empty() {
+        anyhow::bail!("the DSM-KROOT is empty");
+    }
+    let dsm_kroot = DsmKroot(dsm);
+    let blocks = dsm_kroot
+        .number_of_blocks()
+        .context("the DSM-KROOT NB_DK field has a reserved value")?;
+    if dsm.len() != blocks * DSM_BLOCK_BYTES {
+        anyhow::bail!(
+            "the DSM-KROOT has {} bytes, but NB_DK indicates {} blocks",
+            dsm.len(),
+            blocks
+        );
     }
+    dsm_kroot.try_ecdsa_function()?;
+    Ok(dsm_kroot)
+}
 
-    if args.pubkey.is_some() && args.pkid.is_none() {
-        anyhow::bail!("the --pubkey and --pkid arguments need to be both specified together");
+fn parse_dsm_pkr(dsm: &[u8]) -> Result<DsmPkr<'_>> {
+    if dsm.is_empty() {
+        anyhow::bail!("the DSM-PKR is empty");
+    }
+    let dsm_pkr = DsmPkr(dsm);
+    let blocks = dsm_pkr
+        .number_of_blocks()
+        .context("the DSM-PKR NB_DP field has a reserved value")?;
+    if dsm.len() != blocks * DSM_BLOCK_BYTES {
+        anyhow::bail!(
+            "the DSM-PKR has {} bytes, but NB_DP indicates {} blocks",
+            dsm.len(),
+            blocks
+        );
+    }
+    if dsm_pkr.key_size().is_none() {
+        anyhow::bail!("the DSM-PKR NPKT field has a reserved value");
     }
+    Ok(dsm_pkr)
+}
 
-    if args.pubkey_p521.is_some() && args.pkid.is_none() {
-        anyhow::bail!("the --pubkey-p521 and --pkid arguments need to be both specified together");
+fn inspect(hex: &str, dsm_id: u8) -> Result<()> {
+    let dsm = hex::decode(hex).context("failed to parse DSM")?;
+    match dsm_id {
+        0..=11 => println!("{:#?}", parse_dsm_kroot(&dsm)?),
+        12..=15 => println!("{:#?}", parse_dsm_pkr(&dsm)?),
+        _ => anyhow::bail!("the DSM ID must be between 0 and 15"),
     }
+    Ok(())
+}
 
-    if args.pkid.is_some() && args.pubkey.is_none() && args.pubkey_p521.is_none() {
+fn verify_kroot(hex: &str, nma_header: &str, pubkey: &PubkeyArgs) -> Result<()> {
+    let pubkey = pubkey
+        .load()?
+        .context("the public key must be specified with --pubkey or --pubkey-p521")?;
+    let dsm = hex::decode(hex).context("failed to parse DSM-KROOT")?;
+    let dsm_kroot = parse_dsm_kroot(&dsm)?;
+    let nma_header =
+        <[u8; 1]>::try_from(hex::decode(nma_header).context("failed to parse NMA header")?)
+            .map_err(|_| anyhow::anyhow!("the NMA header must be one byte long"))?;
+    let nma_header = NmaHeader::new(nma_header[0]);
+    if dsm_kroot.public_key_id() != pubkey.public_key_id() {
         anyhow::bail!(
-            "the --pkid argument needs to be used together with --pubkey or --pubkey-p521"
+            "the DSM-KROOT uses PKID={}, but the public key has PKID={}",
+            dsm_kroot.public_key_id(),
+            pubkey.public_key_id()
         );
     }
+    let (key, nma_header) = Key::from_dsm_kroot(nma_header, dsm_kroot, &pubkey)
+        .context("DSM-KROOT verification failed")?;
+    println!("DSM-KROOT verified");
+    println!("NMA header: {nma_header}");
+    println!("KROOT: {}", hex::encode(dsm_kroot.try_kroot()?));
+    println!("KROOT GST: {}", key.gst_subframe());
+    Ok(())
+}
 
-    let pubkey = if let Some(pubkey_path) = &args.pubkey {
-        Some(load_pubkey(pubkey_path, args.pkid.unwrap())?)
-    } else if let Some(pubkey_hex) = &args.pubkey_p521 {
-        Some(load_pubkey_p521(pubkey_hex, args.pkid.unwrap())?)
-    } else {
-        None
-    };
+fn verify_pkr(hex: &str, merkle_root: &str) -> Result<()> {
+    let merkle_tree = MerkleTree::new(parse_merkle_root(merkle_root)?);
+    let dsm = hex::decode(hex).context("failed to parse DSM-PKR")?;
+    let dsm_pkr = parse_dsm_pkr(&dsm)?;
+    match dsm_pkr.new_public_key_type() {
+        NewPublicKeyType::OsnmaAlertMessage => {
+            merkle_tree
+                .validate_alert_message(dsm_pkr)
+                .context("DSM-PKR verification failed")?;
+            println!("DSM-PKR verified: OSNMA alert message (OAM)");
+        }
+        _ => {
+            let pubkey = merkle_tree
+                .validate_pkr(dsm_pkr)
+                .context("DSM-PKR verification failed")?;
+            println!(
+                "DSM-PKR verified: {} with PKID={}",
+                dsm_pkr.new_public_key_type(),
+                pubkey.public_key_id()
+            );
+            println!(
+                "public key: {}",
+                hex::encode(dsm_pkr.new_public_key().unwrap())
+            );
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+
+    match &cli.command {
+        Some(Command::Inspect { hex, dsm_id }) => inspect(hex, *dsm_id),
+        Some(Command::VerifyKroot {
+            hex,
+            nma_header,
+            pubkey,
+        }) => verify_kroot(hex, nma_header, pubkey),
+        Some(Command::VerifyPkr { hex, merkle_root }) => verify_pkr(hex, merkle_root),
+        None => process(&cli.process),
+    }
+}
+
+fn process(args: &ProcessArgs) -> Result<()> {
+    if args.merkle_root.is_none() && !args.pubkey.is_given() {
+        anyhow::bail!("at least either the Merkle tree root or the public key must be specified");
+    }
+
+    let pubkey = args.pubkey.load()?;
 
     let mut osnma: Osnma<FullStorage> = if let Some(merkle) = &args.merkle_root {
-        let merkle = hex::decode(merkle)
-            .context("failed to parse Merkle tree root")?
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("the Merkle tree root has a wrong length"))?;
-        Osnma::from_merkle_tree(merkle, pubkey, args.slow_mac_only)
+        Osnma::from_merkle_tree(parse_merkle_root(merkle)?, pubkey, args.slow_mac_only)
     } else {
         // Here pubkey shouldn't be None, because Merkle tree is None and we
         // have checked that at least one of both is not None.
@@ -91,135 +289,57 @@ fn main() -> Result<()> {
     };
 
     let mut read = ReadTransport::new(std::io::stdin());
-    let mut timing_parameters: [Option<[u8; 18]>; NUM_SVNS] = [None; NUM_SVNS];
-    let mut ced_and_status_data: [Option<[u8; 69]>; NUM_SVNS] = [None; NUM_SVNS];
-    let mut current_subframe = None;
-    let mut last_tow_mod_30 = 0;
+    let mut timing_parameters: [Option<NavMessageDataOwned>; NUM_SVNS] = [None; NUM_SVNS];
+    let mut ced_and_status_data: [Option<NavMessageDataOwned>; NUM_SVNS] = [None; NUM_SVNS];
+
+    let mut stats = Stats::new();
 
     while let Some(packet) = read.read_packet()? {
-        if let Some(
-            inav @ GalileoInav {
-                contents: inav_word,
-                reserved1: osnma_data,
-                sigid: Some(sigid),
-                ..
-            },
-        ) = &packet.gi
-        {
-            // This is needed because sometimes we can see a TOW of 604801
+        if let Some(inav) = &packet.gi {
+            // Same handling of TOW overflow as in Osnma::feed_galmon
             let secs_in_week = 604800;
-            let mut tow = inav.gnss_tow % secs_in_week;
-            let wn = Wn::try_from(inav.gnss_wn).unwrap()
-                + Wn::try_from(inav.gnss_tow / secs_in_week).unwrap();
-
-            // Fix bug in Galmon data:
-            //
-            // Often, the E1B word 16 starting at TOW = 29 mod 30 will have the
-            // TOW of the previous word 16 in the subframe, which starts at TOW
-            // = 15 mod 30. We detect this condition by looking at the last tow
-            // mod 30 that we saw and fixing if needed.
-            if tow % 30 == 15 && last_tow_mod_30 >= 19 {
-                log::debug!(
-                    "fixing wrong TOW for SVN {}; tow = {}, last tow mod 30 = {}",
-                    inav.gnss_sv,
-                    tow,
-                    last_tow_mod_30
-                );
-                tow += 29 - 15; // wn rollover is not possible by this addition
-            }
-            last_tow_mod_30 = tow % 30;
-
-            let gst = Gst::new(wn, tow);
-            if let Some(current) = current_subframe {
-                if current > gst.gst_subframe() {
-                    // Avoid processing INAV words that are in a previous subframe
-                    log::warn!(
-                        "dropping INAV word from previous subframe (current subframe {:?}, \
-			 this INAV word {:?} SVN {} band {})",
-                        current,
-                        gst,
-                        inav.gnss_sv,
-                        sigid
-                    );
-                    continue;
-                }
-            }
-            current_subframe = Some(gst.gst_subframe());
-            let svn = Svn::try_from(inav.gnss_sv).unwrap();
-            let band = match sigid {
-                1 => InavBand::E1B,
-                5 => InavBand::E5B,
-                _ => {
-                    log::error!("INAV word received on non-INAV band: sigid = {}", sigid);
-                    continue;
-                }
-            };
-
-            // The OSNMA SIS ICD says that OSNMA is not provided in INAV Dummy
-            // Messages or Alert Pages. The OSNMA field in these pages may not
-            // contain all zeros, but is invalid and should be discarded.
-            //
-            // Here we drop INAV words that are Dummy Messages. There is no way
-            // for us to filter for Alert Pages in Galmon data (the page type
-            // bit is not present), so hopefully these pages don't make it here.
-            let inav_word_type = inav_word[0] >> 2;
-            if inav_word_type == 63 {
-                log::debug!(
-                    "discarding dummy INAV word from {} {:?} at {:?}",
-                    svn,
-                    band,
-                    gst
-                );
-                continue;
+            if let Ok(wn) = u16::try_from(inav.gnss_wn + inav.gnss_tow / secs_in_week) {
+                stats.page(Gst::new(wn, inav.gnss_tow % secs_in_week));
             }
 
-            osnma.feed_inav(inav_word[..].try_into().unwrap(), svn, gst, band);
-            if let Some(osnma_data) = osnma_data {
-                osnma.feed_osnma(osnma_data[..].try_into().unwrap(), svn, gst);
+            osnma.feed_galmon(&packet);
+            while let Some(event) = osnma.poll_event() {
+                stats.event(&event);
             }
 
             for svn in Svn::iter() {
-                let idx = usize::from(svn) - 1;
+                let idx = svn.index();
                 if let Some(data) = osnma.get_ced_and_status(svn) {
-                    let mut data_bytes = [0u8; 69];
-                    let a = BitSlice::from_slice_mut(&mut data_bytes);
-                    let b = data.data();
-                    a[..b.len()].copy_from_bitslice(b);
-                    if !ced_and_status_data[idx]
-                        .map(|d| d == data_bytes)
-                        .unwrap_or(false)
-                    {
-                        log::info!(
-                            "new CED and status for {} authenticated \
-                                    (authbits = {}, GST = {:?})",
-                            svn,
-                            data.authbits(),
-                            data.gst()
-                        );
-                        ced_and_status_data[idx] = Some(data_bytes);
+                    if !ced_and_status_data[idx].is_some_and(|d| d.data() == data.data()) {
+                        log::info!("new CED and status for {} authenticated: {}", svn, data);
+                        if let Some(health) = osnma.get_health(svn) {
+                            log::info!("authenticated health of {}: {}", svn, health);
+                        }
+                        ced_and_status_data[idx] = Some(data.into_owned());
+                        let num_authenticated =
+                            ced_and_status_data.iter().filter(|d| d.is_some()).count();
+                        stats.ced_and_status_authenticated(svn, num_authenticated);
                     }
                 }
                 if let Some(data) = osnma.get_timing_parameters(svn) {
-                    let mut data_bytes = [0u8; 18];
-                    let a = BitSlice::from_slice_mut(&mut data_bytes);
-                    let b = data.data();
-                    a[..b.len()].copy_from_bitslice(b);
-                    if !timing_parameters[idx]
-                        .map(|d| d == data_bytes)
-                        .unwrap_or(false)
-                    {
-                        log::info!(
-                            "new timing parameters for {} authenticated (authbits = {}, GST = {:?})",
-			    svn,
-                            data.authbits(),
-                            data.gst()
-			);
-                        timing_parameters[idx] = Some(data_bytes);
+                    if !timing_parameters[idx].is_some_and(|d| d.data() == data.data()) {
+                        log::info!("new timing parameters for {} authenticated: {}", svn, data);
+                        timing_parameters[idx] = Some(data.into_owned());
+                        stats.timing_parameters_authenticated(svn);
                     }
                 }
             }
         }
     }
 
+    if args.stats {
+        print!("{stats}");
+    }
+    if let Some(path) = &args.stats_json {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("failed to create statistics file {path}"))?;
+        stats.write_json(std::io::BufWriter::new(file))?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,219 @@
+use galileo_osnma::{diagnostics::DiagnosticKind, events::Event, types::NUM_SVNS, Gst, Svn};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+// Number of satellites with authenticated ephemeris, clock and status data
+// needed for a position fix.
+const FIX_SATELLITES: usize = 4;
+
+/// Statistics about the processing of a stream of Galmon data.
+///
+/// These are printed when the input stream ends, so that galmon-osnma can be
+/// used to characterize recorded datasets.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pages: u64,
+    subframes: u64,
+    first_gst: Option<Gst>,
+    last_gst: Option<Gst>,
+    ced_and_status: [u64; NUM_SVNS],
+    timing_parameters: [u64; NUM_SVNS],
+    first_authentication: Option<Gst>,
+    first_fix: Option<Gst>,
+    kroots_verified: u64,
+    tesla_keys_validated: u64,
+    audit_discrepancies: u64,
+    diagnostics: BTreeMap<&'static str, u64>,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats {
+            pages: 0,
+            subframes: 0,
+            first_gst: None,
+            last_gst: None,
+            ced_and_status: [0; NUM_SVNS],
+            timing_parameters: [0; NUM_SVNS],
+            first_authentication: None,
+            first_fix: None,
+            kroots_verified: 0,
+            tesla_keys_validated: 0,
+            audit_discrepancies: 0,
+            diagnostics: BTreeMap::new(),
+        }
+    }
+
+    /// Records an INAV page with the GST at its start.
+    pub fn page(&mut self, gst: Gst) {
+        self.pages += 1;
+        self.first_gst.get_or_insert(gst);
+        if !self
+            .last_gst
+            .is_some_and(|last| gst.gst_subframe() <= last.gst_subframe())
+        {
+            self.subframes += 1;
+        }
+        if !self.last_gst.is_some_and(|last| gst <= last) {
+            self.last_gst = Some(gst);
+        }
+    }
+
+    /// Records a new authentication of the ephemeris, clock and status data
+    /// of a satellite.
+    ///
+    /// The number of satellites that currently have authenticated ephemeris,
+    /// clock and status data is given in `num_authenticated`.
+    pub fn ced_and_status_authenticated(&mut self, svn: Svn, num_authenticated: usize) {
+        self.ced_and_status[svn.index()] += 1;
+        self.first_authentication = self.first_authentication.or(self.last_gst);
+        if num_authenticated >= FIX_SATELLITES {
+            self.first_fix = self.first_fix.or(self.last_gst);
+        }
+    }
+
+    /// Records a new authentication of the timing parameters of a satellite.
+    pub fn timing_parameters_authenticated(&mut self, svn: Svn) {
+        self.timing_parameters[svn.index()] += 1;
+        self.first_authentication = self.first_authentication.or(self.last_gst);
+    }
+
+    /// Records an event produced by the OSNMA black box.
+    pub fn event(&mut self, event: &Event) {
+        match event {
+            Event::Diagnostic(diagnostic) => {
+                *self
+                    .diagnostics
+                    .entry(diagnostic_category(diagnostic.kind()))
+                    .or_default() += 1;
+            }
+            Event::KrootVerified { .. } => self.kroots_verified += 1,
+            Event::TeslaKeyValidated { .. } => self.tesla_keys_validated += 1,
+            Event::AuditDiscrepancy { .. } => self.audit_discrepancies += 1,
+            Event::ChainTransitionScheduled { .. } => (),
+            Event::AuthStateChanged { .. } => (),
+            Event::DsmPkrVerified { .. } => (),
+            Event::TagAuthenticated { .. } => (),
+            Event::TagFailed { .. } => (),
+        }
+    }
+
+    // Seconds elapsed from the first page received until gst.
+    fn elapsed(&self, gst: Option<Gst>) -> Option<i64> {
+        let seconds = |gst: Gst| i64::from(gst.wn()) * 604800 + i64::from(gst.tow());
+        Some(seconds(gst?) - seconds(self.first_gst?))
+    }
+
+    fn svns(&self) -> impl Iterator<Item = Svn> + '_ {
+        Svn::iter().filter(|svn| {
+            self.ced_and_status[svn.index()] != 0 || self.timing_parameters[svn.index()] != 0
+        })
+    }
+
+    /// Writes the statistics in JSON format.
+    pub fn write_json<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        let gst = |gst: Option<Gst>| {
+            gst.map_or("null".to_string(), |g| {
+                format!("{{\"wn\": {}, \"tow\": {}}}", g.wn(), g.tow())
+            })
+        };
+        let seconds = |s: Option<i64>| s.map_or("null".to_string(), |s| s.to_string());
+        writeln!(w, "{{")?;
+        writeln!(w, "  \"pages\": {},", self.pages)?;
+        writeln!(w, "  \"subframes\": {},", self.subframes)?;
+        writeln!(w, "  \"first_gst\": {},", gst(self.first_gst))?;
+        writeln!(w, "  \"last_gst\": {},", gst(self.last_gst))?;
+        writeln!(
+            w,
+            "  \"time_to_first_authentication\": {},",
+            seconds(self.elapsed(self.first_authentication))
+        )?;
+        writeln!(
+            w,
+            "  \"time_to_first_authenticated_fix\": {},",
+            seconds(self.elapsed(self.first_fix))
+        )?;
+        writeln!(w, "  \"kroots_verified\": {},", self.kroots_verified)?;
+        writeln!(
+            w,
+            "  \"tesla_keys_validated\": {},",
+            self.tesla_keys_validated
+        )?;
+        writeln!(
+            w,
+            "  \"audit_discrepancies\": {},",
+            self.audit_discrepancies
+        )?;
+        write!(w, "  \"authentications\": {{")?;
+        for (j, svn) in self.svns().enumerate() {
+            let separator = if j == 0 { "" } else { "," };
+            write!(
+                w,
+                "{separator}\n    \"{svn}\": {{\"ced_and_status\": {}, \"timing_parameters\": {}}}",
+                self.ced_and_status[svn.index()],
+                self.timing_parameters[svn.index()]
+            )?;
+        }
+        writeln!(w, "\n  }},")?;
+        write!(w, "  \"diagnostics\": {{")?;
+        for (j, (category, count)) in self.diagnostics.iter().enumerate() {
+            let separator = if j == 0 { "" } else { "," };
+            write!(w, "{separator}\n    \"{category}\": {count}")?;
+        }
+        writeln!(w, "\n  }}")?;
+        writeln!(w, "}}")
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = |s: Option<i64>| s.map_or("never".to_string(), |s| format!("{s} s"));
+        writeln!(f, "INAV pages: {}", self.pages)?;
+        writeln!(f, "subframes: {}", self.subframes)?;
+        if let (Some(first), Some(last)) = (self.first_gst, self.last_gst) {
+            writeln!(f, "GST span: {first} to {last}")?;
+        }
+        writeln!(
+            f,
+            "time to first authentication: {}",
+            seconds(self.elapsed(self.first_authentication))
+        )?;
+        writeln!(
+            f,
+            "time to first authenticated fix: {}",
+            seconds(self.elapsed(self.first_fix))
+        )?;
+        writeln!(f, "KROOTs verified: {}", self.kroots_verified)?;
+        writeln!(f, "TESLA keys validated: {}", self.tesla_keys_validated)?;
+        writeln!(f, "audit discrepancies: {}", self.audit_discrepancies)?;
+        writeln!(f, "authentications (CED and status / timing parameters):")?;
+        for svn in self.svns() {
+            writeln!(
+                f,
+                "  {svn}: {} / {}",
+                self.ced_and_status[svn.index()],
+                self.timing_parameters[svn.index()]
+            )?;
+        }
+        writeln!(f, "diagnostics:")?;
+        for (category, count) in &self.diagnostics {
+            writeln!(f, "  {category}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+fn diagnostic_category(kind: DiagnosticKind) -> &'static str {
+    match kind {
+        DiagnosticKind::WordMismatch { .. } => "word_mismatch",
+        DiagnosticKind::IncompleteSubframe { .. } => "incomplete_subframe",
+        DiagnosticKind::MissingKey => "missing_key",
+        DiagnosticKind::KeyValidationFailed => "key_validation_failed",
+        DiagnosticKind::OldKey => "old_key",
+        DiagnosticKind::ReservedAdkd { .. } => "reserved_adkd",
+        DiagnosticKind::FutureKroot { .. } => "future_kroot",
+        DiagnosticKind::AuthenticationStalled { .. } => "authentication_stalled",
+        DiagnosticKind::GstRejected { .. } => "gst_rejected",
+    }
+}
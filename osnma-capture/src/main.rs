@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate};
+use clap::Parser;
+use flate2::{write::GzEncoder, Compression};
+use galileo_osnma::{
+    archive::ArchiveWriter,
+    bitfields::DsmHeader,
+    dsm::CollectDsm,
+    galmon::{transport::ReadTransport, GalmonState},
+    subframe::{CollectSubframe, RawSubframe},
+    Gst,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Archive raw OSNMA material reading Galmon protobuf from stdin
+///
+/// The INAV pages, the HKROOT and MACK messages of the subframes, and the DSM
+/// messages are written to gzip-compressed daily archives. The format of the
+/// archives is documented in the galileo_osnma::archive module. Days are
+/// counted in GST.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about)]
+struct Args {
+    /// Directory in which the archives are written.
+    #[arg(long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+type Writer = ArchiveWriter<GzEncoder<BufWriter<File>>>;
+
+/// Archive for one day.
+struct DailyArchive {
+    date: NaiveDate,
+    path: PathBuf,
+    writer: Writer,
+}
+
+impl DailyArchive {
+    fn create(dir: &Path, date: NaiveDate) -> Result<DailyArchive> {
+        // If the archive for this day already exists (for instance, because
+        // the capture has been restarted), a new archive with a numbered
+        // suffix is created.
+        for n in 0.. {
+            let suffix = if n == 0 {
+                String::new()
+            } else {
+                format!("-{n}")
+            };
+            let path = dir.join(format!("osnma-{date}{suffix}.osnmaraw.gz"));
+            let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to create {}", path.display()))
+                }
+            };
+            log::info!("writing archive {}", path.display());
+            let writer =
+                ArchiveWriter::new(GzEncoder::new(BufWriter::new(file), Compression::default()))?;
+            return Ok(DailyArchive { date, path, writer });
+        }
+        unreachable!()
+    }
+
+    fn finish(self) -> Result<()> {
+        self.writer
+            .into_inner()
+            .finish()
+            .with_context(|| format!("failed to finish {}", self.path.display()))?;
+        log::info!("finished archive {}", self.path.display());
+        Ok(())
+    }
+}
+
+fn gst_date(gst: Gst) -> NaiveDate {
+    const SECS_IN_DAY: u64 = 86400;
+    let days = u64::from(gst.wn()) * 7 + u64::from(gst.tow()) / SECS_IN_DAY;
+    NaiveDate::from_ymd_opt(1999, 8, 22).unwrap() + Days::new(days)
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let mut read = ReadTransport::new(std::io::stdin());
+    let mut galmon = GalmonState::new();
+    let mut subframes = CollectSubframe::new();
+    let mut dsms = CollectDsm::new();
+    let mut archive: Option<DailyArchive> = None;
+    let mut current_subframe = None;
+
+    while let Some(packet) = read.read_packet()? {
+        let Some(page) = galmon.process(&packet) else {
+            continue;
+        };
+        let date = gst_date(page.gst);
+        if archive.as_ref().map(|a| a.date) != Some(date) {
+            if let Some(archive) = archive.take() {
+                archive.finish()?;
+            }
+            archive = Some(DailyArchive::create(&args.output_dir, date)?);
+        }
+        // cannot panic, since archive has been set above
+        let writer = &mut archive.as_mut().unwrap().writer;
+
+        // Flush the archive once per subframe, so that the data written so
+        // far can be read if the capture is interrupted.
+        let subframe = page.band.e1b_page_gst(page.gst).gst_subframe();
+        if current_subframe != Some(subframe) {
+            writer.flush()?;
+            current_subframe = Some(subframe);
+        }
+
+        writer.write_page(&page)?;
+        let Some(osnma) = page.osnma else {
+            continue;
+        };
+        if let Some((hkroot, mack, gst)) = subframes.feed_band(osnma, page.svn, page.gst, page.band)
+        {
+            writer.write_subframe(&RawSubframe {
+                svn: page.svn,
+                gst,
+                hkroot,
+                mack,
+            })?;
+            // cannot panic, since the slices have the correct size
+            let dsm_header = DsmHeader(hkroot[1..2].try_into().unwrap());
+            let dsm_block = hkroot[2..].try_into().unwrap();
+            if let Some(dsm) = dsms.feed(dsm_header, dsm_block) {
+                log::info!("archiving DSM ID {} completed by {}", dsm.id(), page.svn);
+                writer.write_dsm(&dsm, page.svn, gst)?;
+            }
+        }
+    }
+
+    if let Some(archive) = archive {
+        archive.finish()?;
+    }
+    Ok(())
+}
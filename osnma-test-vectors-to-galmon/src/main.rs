@@ -92,6 +92,7 @@ fn csv_line_to_pages(line: &str, start_gst: Gst) -> Result<Vec<NavMonMessage>> {
                 spare,
                 crc,
                 ssp,
+                osnma_auth: None,
             });
             let type_ = Type::GalileoInavType.into();
             let source_id = 0;
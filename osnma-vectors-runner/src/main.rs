@@ -0,0 +1,375 @@
+use anyhow::{Context, Result};
+use bitvec::vec::BitVec;
+use chrono::NaiveDateTime;
+use clap::Parser;
+use galileo_osnma::{
+    navmessage::AuthLatency,
+    storage::FullStorage,
+    types::{BitSlice, InavWord, OsnmaDataMessage, NUM_SVNS},
+    Gst, InavBand, Osnma, PublicKey, Svn, Validated,
+};
+use spki::DecodePublicKey;
+use std::{fmt, fs, io::Read, path::PathBuf};
+
+/// Run OSNMA test vectors and check scenario expectations
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Input CSV files.
+    ///
+    /// If several files are given, they are processed in order as consecutive
+    /// steps of the same scenario.
+    #[arg(required = true)]
+    input_files: Vec<PathBuf>,
+    /// Merkle tree root in hex.
+    #[arg(long)]
+    merkle_root: Option<String>,
+    /// Path to the P-256 public key in PEM format.
+    #[arg(long)]
+    pubkey: Option<String>,
+    /// P-521 public key in hexadecimal format (SEC1 encoding).
+    #[arg(long)]
+    pubkey_p521: Option<String>,
+    /// ID of the public key.
+    #[arg(long)]
+    pkid: Option<u8>,
+    /// Only process slow MAC data.
+    #[arg(long)]
+    slow_mac_only: bool,
+    /// Expect some navigation data to be authenticated.
+    #[arg(long)]
+    expect_auth: bool,
+    /// Expect no navigation data to be authenticated.
+    #[arg(long)]
+    expect_no_auth: bool,
+    /// Expect navigation data to be authenticated before a GST.
+    ///
+    /// The GST is given as a date and time, such as 2023-10-06T18:30:01.
+    #[arg(long, value_parser = parse_gst)]
+    expect_auth_before: Vec<Gst>,
+    /// Expect navigation data to be authenticated after a GST.
+    ///
+    /// The GST is given as a date and time, such as 2023-10-06T18:30:01. This
+    /// can be used to check that authentication continues after a TESLA chain
+    /// renewal or a public key renewal.
+    #[arg(long, value_parser = parse_gst)]
+    expect_auth_after: Vec<Gst>,
+    /// Expect no navigation data to be authenticated after a GST.
+    ///
+    /// The GST is given as a date and time, such as 2023-10-06T18:30:01. This
+    /// can be used to check that an OSNMA Alert Message causes the deletion of
+    /// the cryptographic material.
+    #[arg(long, value_parser = parse_gst)]
+    expect_no_auth_after: Vec<Gst>,
+}
+
+fn load_pubkey(path: &str, pkid: u8) -> Result<PublicKey<Validated>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut pem = String::new();
+    file.read_to_string(&mut pem)?;
+    let pubkey = p256::ecdsa::VerifyingKey::from_public_key_pem(&pem)?;
+    Ok(PublicKey::from_p256(pubkey, pkid).force_valid())
+}
+
+fn load_pubkey_p521(hex: &str, pkid: u8) -> Result<PublicKey<Validated>> {
+    let pubkey = hex::decode(hex)?;
+    let pubkey = p521::ecdsa::VerifyingKey::from_sec1_bytes(&pubkey)?;
+    Ok(PublicKey::from_p521(pubkey, pkid).force_valid())
+}
+
+fn datetime_to_gst(dt: NaiveDateTime) -> Gst {
+    let t = dt.and_utc().timestamp();
+    let gst_epoch_t = 935280000; // 1999-08-22 00:00:00 GST
+    let gst_seconds = t - gst_epoch_t;
+    const SECS_IN_WEEK: i64 = 24 * 3600 * 7;
+    let wn = gst_seconds / SECS_IN_WEEK;
+    let tow = gst_seconds - wn * SECS_IN_WEEK;
+    Gst::new(wn.try_into().unwrap(), tow.try_into().unwrap())
+}
+
+fn parse_gst(s: &str) -> Result<Gst> {
+    let dt = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")?;
+    Ok(datetime_to_gst(dt))
+}
+
+fn filename_to_gst(filename: &str) -> Result<Gst> {
+    let dt = NaiveDateTime::parse_from_str(filename, "%d_%b_%Y_GST_%H_%M_%S.csv")?;
+    Ok(datetime_to_gst(dt))
+}
+
+#[derive(Debug, Clone)]
+struct Page {
+    svn: Svn,
+    gst: Gst,
+    word: InavWord,
+    osnma: OsnmaDataMessage,
+    alert: bool,
+}
+
+fn csv_line_to_pages(line: &str, start_gst: Gst) -> Result<Vec<Page>> {
+    let mut gst = start_gst;
+    let mut fields = line.split(',');
+    let wrong_format = || anyhow::anyhow!("CSV has wrong format");
+    let svn: u8 = fields.next().ok_or_else(wrong_format)?.parse()?;
+    let svn = Svn::try_from(svn).map_err(|_| anyhow::anyhow!("CSV contains invalid SVN"))?;
+    // skip length CSV field
+    fields.next().ok_or_else(wrong_format)?;
+    let data = hex::decode(fields.next().ok_or_else(wrong_format)?)?;
+    const PAGE_SIZE: usize = 240 / 8;
+    if data.len() % PAGE_SIZE != 0 {
+        return Err(wrong_format());
+    }
+    let pages = data
+        .chunks_exact(PAGE_SIZE)
+        .map(|page| {
+            let page_gst = gst;
+            gst = gst.add_seconds(2);
+            let page = BitSlice::from_slice(page);
+            let mut word = BitVec::from_bitslice(&page[2..2 + 112]);
+            word.extend_from_bitslice(&page[122..122 + 16]);
+            word.force_align();
+            word.set_uninitialized(false);
+            let mut osnma = BitVec::from_bitslice(&page[120 + 2 + 16..120 + 2 + 16 + 40]);
+            osnma.force_align();
+            Page {
+                svn,
+                gst: page_gst,
+                word: word.into_vec().try_into().unwrap(),
+                osnma: osnma.into_vec().try_into().unwrap(),
+                alert: page[1],
+            }
+        })
+        .collect();
+    Ok(pages)
+}
+
+fn read_csv(path: &PathBuf) -> Result<Vec<Vec<Page>>> {
+    let filename = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("input path does not contain a filename"))?
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("input filename does not contain valid UTF-8"))?;
+    let start_gst = filename_to_gst(filename)?;
+    let csv = fs::read_to_string(path)?;
+    let mut csv = csv.lines();
+    // discard csv header
+    csv.next()
+        .ok_or_else(|| anyhow::anyhow!("input file is empty"))?;
+    csv.map(|line| csv_line_to_pages(line, start_gst)).collect()
+}
+
+/// Keeps track of the authentication events produced by the OSNMA black box.
+struct AuthTracker {
+    // Last authenticated CED and status and timing parameters for each SVN.
+    last: [[Option<AuthLatency>; 2]; NUM_SVNS],
+    // GST at which each authentication happened.
+    events: Vec<Gst>,
+}
+
+impl AuthTracker {
+    fn new() -> AuthTracker {
+        AuthTracker {
+            last: [[None; 2]; NUM_SVNS],
+            events: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, osnma: &Osnma<FullStorage>) {
+        for svn in Svn::iter() {
+            let data = [
+                osnma.get_ced_and_status(svn),
+                osnma.get_timing_parameters(svn),
+            ];
+            let last = &mut self.last[svn.index()];
+            for (last, (data, name)) in last
+                .iter_mut()
+                .zip(data.iter().zip(["CED and status", "timing parameters"]))
+            {
+                if let Some(data) = data {
+                    let latency = data.latency();
+                    if *last != Some(latency) {
+                        log::info!(
+                            "new {} for {} authenticated (authbits = {}, GST = {:?})",
+                            name,
+                            svn,
+                            data.authbits(),
+                            latency.authentication_gst()
+                        );
+                        self.events.push(latency.authentication_gst());
+                        *last = Some(latency);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Expectation {
+    Auth,
+    NoAuth,
+    AuthBefore(Gst),
+    AuthAfter(Gst),
+    NoAuthAfter(Gst),
+}
+
+impl Expectation {
+    fn check(&self, events: &[Gst]) -> bool {
+        match self {
+            Expectation::Auth => !events.is_empty(),
+            Expectation::NoAuth => events.is_empty(),
+            Expectation::AuthBefore(gst) => events.iter().any(|e| e < gst),
+            Expectation::AuthAfter(gst) => events.iter().any(|e| e > gst),
+            Expectation::NoAuthAfter(gst) => !events.iter().any(|e| e > gst),
+        }
+    }
+}
+
+struct DisplayGst(Gst);
+
+impl fmt::Display for DisplayGst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WN {} TOW {}", self.0.wn(), self.0.tow())
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Auth => write!(f, "navigation data is authenticated"),
+            Expectation::NoAuth => write!(f, "no navigation data is authenticated"),
+            Expectation::AuthBefore(gst) => write!(
+                f,
+                "navigation data is authenticated before {}",
+                DisplayGst(*gst)
+            ),
+            Expectation::AuthAfter(gst) => write!(
+                f,
+                "navigation data is authenticated after {}",
+                DisplayGst(*gst)
+            ),
+            Expectation::NoAuthAfter(gst) => write!(
+                f,
+                "no navigation data is authenticated after {}",
+                DisplayGst(*gst)
+            ),
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    if args.merkle_root.is_none() && args.pubkey.is_none() && args.pubkey_p521.is_none() {
+        anyhow::bail!("at least either the Merkle tree root or the public key must be specified");
+    }
+
+    if args.pubkey.is_some() && args.pubkey_p521.is_some() {
+        anyhow::bail!("the --pubkey and --pubkey-p521 arguments are mutually exclusive");
+    }
+
+    if (args.pubkey.is_some() || args.pubkey_p521.is_some()) != args.pkid.is_some() {
+        anyhow::bail!(
+            "the --pkid argument needs to be used together with --pubkey or --pubkey-p521"
+        );
+    }
+
+    let pubkey = if let Some(pubkey_path) = &args.pubkey {
+        Some(load_pubkey(pubkey_path, args.pkid.unwrap())?)
+    } else if let Some(pubkey_hex) = &args.pubkey_p521 {
+        Some(load_pubkey_p521(pubkey_hex, args.pkid.unwrap())?)
+    } else {
+        None
+    };
+
+    let mut osnma: Osnma<FullStorage> = if let Some(merkle) = &args.merkle_root {
+        let merkle = hex::decode(merkle)
+            .context("failed to parse Merkle tree root")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("the Merkle tree root has a wrong length"))?;
+        Osnma::from_merkle_tree(merkle, pubkey, args.slow_mac_only)
+    } else {
+        // Here pubkey shouldn't be None, because Merkle tree is None and we
+        // have checked that at least one of both is not None.
+        Osnma::from_pubkey(pubkey.unwrap(), args.slow_mac_only)
+    };
+
+    let mut expectations = Vec::new();
+    if args.expect_auth {
+        expectations.push(Expectation::Auth);
+    }
+    if args.expect_no_auth {
+        expectations.push(Expectation::NoAuth);
+    }
+    expectations.extend(
+        args.expect_auth_before
+            .iter()
+            .map(|&g| Expectation::AuthBefore(g)),
+    );
+    expectations.extend(
+        args.expect_auth_after
+            .iter()
+            .map(|&g| Expectation::AuthAfter(g)),
+    );
+    expectations.extend(
+        args.expect_no_auth_after
+            .iter()
+            .map(|&g| Expectation::NoAuthAfter(g)),
+    );
+
+    let mut tracker = AuthTracker::new();
+    let mut current_subframe = None;
+    for path in &args.input_files {
+        log::info!("processing {}", path.display());
+        let pages = read_csv(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let num_pages = pages.iter().map(|p| p.len()).max().unwrap_or(0);
+        for page_num in 0..num_pages {
+            for page in pages.iter().filter_map(|p| p.get(page_num)) {
+                // Check for new authentications once per subframe
+                let subframe = page.gst.gst_subframe();
+                if current_subframe != Some(subframe) {
+                    tracker.update(&osnma);
+                    current_subframe = Some(subframe);
+                }
+                // OSNMA is not provided in Alert Pages and Dummy Messages
+                let word_type = page.word[0] >> 2;
+                if page.alert || word_type == 63 {
+                    continue;
+                }
+                osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+                osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+        }
+    }
+    tracker.update(&osnma);
+
+    println!("Input files:");
+    for path in &args.input_files {
+        println!("  {}", path.display());
+    }
+    println!("Navigation data authentications: {}", tracker.events.len());
+    if let (Some(first), Some(last)) = (tracker.events.iter().min(), tracker.events.iter().max()) {
+        println!("First authentication: {}", DisplayGst(*first));
+        println!("Last authentication: {}", DisplayGst(*last));
+    }
+    if expectations.is_empty() {
+        return Ok(());
+    }
+    println!("Expectations:");
+    let mut failed = 0;
+    for expectation in &expectations {
+        let pass = expectation.check(&tracker.events);
+        if !pass {
+            failed += 1;
+        }
+        println!("  [{}] {}", if pass { "PASS" } else { "FAIL" }, expectation);
+    }
+    if failed == 0 {
+        println!("Result: PASS");
+        Ok(())
+    } else {
+        println!("Result: FAIL");
+        anyhow::bail!("{} of {} expectations failed", failed, expectations.len());
+    }
+}
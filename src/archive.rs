@@ -0,0 +1,469 @@
+//! Archive of raw OSNMA material.
+//!
+//! This module contains a writer and a reader for a simple binary format that
+//! is used to archive the raw OSNMA material received from the Galileo
+//! constellation: the INAV pages, the HKROOT and MACK messages of the
+//! subframes, and the DSM messages. The format is intended for long-term
+//! archiving of the OSNMA broadcast for research, and archives can be replayed
+//! through the [`Osnma`](crate::Osnma) black box. The `osnma-capture`
+//! application writes archives of this format from a Galmon stream.
+//!
+//! The format does not include compression. Archives are normally compressed
+//! with an external compressor such as gzip, and decompressed before passing
+//! them to the [`ArchiveReader`].
+//!
+//! # Format
+//!
+//! All the integers are written in big-endian byte order. An archive starts
+//! with the 8-byte magic `OSNMARAW` followed by a one-byte format version,
+//! which is currently 1. The rest of the archive is a sequence of records.
+//! Each record starts with the following common fields:
+//!
+//! 1. Record type (1 byte).
+//! 2. SVN of the satellite (1 byte).
+//! 3. Week number of the GST (2 bytes).
+//! 4. Time of week of the GST, in seconds (4 bytes).
+//!
+//! The common fields are followed by the fields of each record type:
+//!
+//! * Record type 1, INAV page. The GST is the start of the page transmission.
+//!   1. Band (1 byte). 0 for E1-B and 1 for E5b-I.
+//!   2. Flags (1 byte). The LSB is set if the page contains OSNMA data. The
+//!      remaining bits are zero.
+//!   3. INAV word (16 bytes).
+//!   4. OSNMA data message (5 bytes). This is all zeros if the page does not
+//!      contain OSNMA data.
+//! * Record type 2, subframe. The GST is the start of the subframe.
+//!   1. HKROOT message (15 bytes).
+//!   2. MACK message (60 bytes).
+//! * Record type 3, DSM. The GST and SVN are those of the subframe that
+//!   completed the DSM.
+//!   1. DSM ID (1 byte).
+//!   2. Number of DSM blocks (1 byte).
+//!   3. DSM data (13 bytes per block).
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::archive::ArchiveReader;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey, Validated};
+//! use std::io::BufReader;
+//!
+//! fn replay(pubkey: PublicKey<Validated>) -> std::io::Result<()> {
+//!     let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!     let file = std::fs::File::open("osnma-2024-01-01.osnmaraw")?;
+//!     let mut read = ArchiveReader::new(BufReader::new(file))?;
+//!     while let Some(record) = read.read_record()? {
+//!         if let Some(page) = record.page() {
+//!             osnma.feed_batch([page]);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::dsm::Dsm;
+use crate::subframe::RawSubframe;
+use crate::types::{
+    HkrootMessage, InavBand, InavPage, InavWord, MackMessage, OsnmaDataMessage, DSM_BLOCK_BYTES,
+    HKROOT_MESSAGE_BYTES, INAV_WORD_BYTES, MACK_MESSAGE_BYTES, MAX_DSM_BLOCKS,
+};
+use crate::{Gst, Svn};
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Magic at the start of an archive.
+pub const ARCHIVE_MAGIC: [u8; 8] = *b"OSNMARAW";
+
+/// Version of the archive format.
+pub const ARCHIVE_VERSION: u8 = 1;
+
+const RECORD_PAGE: u8 = 1;
+const RECORD_SUBFRAME: u8 = 2;
+const RECORD_DSM: u8 = 3;
+
+const FLAG_OSNMA: u8 = 1;
+
+/// Record of an archive.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ArchiveRecord {
+    /// INAV page.
+    Page {
+        /// SVN of the satellite that transmitted the page.
+        svn: Svn,
+        /// GST at the start of the page transmission.
+        gst: Gst,
+        /// Band in which the page was received.
+        band: InavBand,
+        /// INAV word.
+        word: InavWord,
+        /// OSNMA data message, if the page contains OSNMA data.
+        osnma: Option<OsnmaDataMessage>,
+    },
+    /// HKROOT and MACK messages of a subframe.
+    Subframe {
+        /// SVN of the satellite that transmitted the subframe.
+        svn: Svn,
+        /// GST at the start of the subframe.
+        gst: Gst,
+        /// HKROOT message.
+        hkroot: HkrootMessage,
+        /// MACK message.
+        mack: MackMessage,
+    },
+    /// DSM message.
+    Dsm {
+        /// SVN of the satellite that transmitted the subframe that completed
+        /// the DSM.
+        svn: Svn,
+        /// GST at the start of the subframe that completed the DSM.
+        gst: Gst,
+        /// DSM ID.
+        id: u8,
+        /// DSM data.
+        data: Vec<u8>,
+    },
+}
+
+impl ArchiveRecord {
+    /// Gives the SVN of the record.
+    pub fn svn(&self) -> Svn {
+        match self {
+            ArchiveRecord::Page { svn, .. }
+            | ArchiveRecord::Subframe { svn, .. }
+            | ArchiveRecord::Dsm { svn, .. } => *svn,
+        }
+    }
+
+    /// Gives the GST of the record.
+    pub fn gst(&self) -> Gst {
+        match self {
+            ArchiveRecord::Page { gst, .. }
+            | ArchiveRecord::Subframe { gst, .. }
+            | ArchiveRecord::Dsm { gst, .. } => *gst,
+        }
+    }
+
+    /// Gives the INAV page of the record.
+    ///
+    /// If the record is an INAV page, the page is returned, so that it can be
+    /// fed into the OSNMA black box with
+    /// [`Osnma::feed_batch`](crate::Osnma::feed_batch). Otherwise, this
+    /// returns `None`.
+    pub fn page(&self) -> Option<InavPage<'_>> {
+        match self {
+            ArchiveRecord::Page {
+                svn,
+                gst,
+                band,
+                word,
+                osnma,
+            } => Some(InavPage {
+                word,
+                osnma: osnma.as_ref(),
+                svn: *svn,
+                gst: *gst,
+                band: *band,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Writer of archives.
+///
+/// This wraps around a [`Write`] `W` and writes records with the format
+/// described in the [module documentation](crate::archive).
+#[derive(Debug, Clone)]
+pub struct ArchiveWriter<W> {
+    write: W,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Constructs a new writer using a [`Write`] `write`.
+    ///
+    /// The archive header is written to `write`.
+    pub fn new(mut write: W) -> std::io::Result<ArchiveWriter<W>> {
+        write.write_all(&ARCHIVE_MAGIC)?;
+        write.write_all(&[ARCHIVE_VERSION])?;
+        Ok(ArchiveWriter { write })
+    }
+
+    fn write_common(&mut self, record_type: u8, svn: Svn, gst: Gst) -> std::io::Result<()> {
+        self.write.write_all(&[record_type, u8::from(svn)])?;
+        self.write.write_all(&gst.wn().to_be_bytes())?;
+        self.write.write_all(&gst.tow().to_be_bytes())
+    }
+
+    /// Writes an INAV page.
+    pub fn write_page(&mut self, page: &InavPage) -> std::io::Result<()> {
+        self.write_common(RECORD_PAGE, page.svn, page.gst)?;
+        let band = match page.band {
+            InavBand::E1B => 0,
+            InavBand::E5B => 1,
+        };
+        let flags = if page.osnma.is_some() { FLAG_OSNMA } else { 0 };
+        self.write.write_all(&[band, flags])?;
+        self.write.write_all(page.word)?;
+        self.write
+            .write_all(page.osnma.unwrap_or(&OsnmaDataMessage::default()))
+    }
+
+    /// Writes the HKROOT and MACK messages of a subframe.
+    pub fn write_subframe(&mut self, subframe: &RawSubframe) -> std::io::Result<()> {
+        self.write_common(RECORD_SUBFRAME, subframe.svn, subframe.gst)?;
+        self.write.write_all(subframe.hkroot)?;
+        self.write.write_all(subframe.mack)
+    }
+
+    /// Writes a DSM message.
+    ///
+    /// The `svn` and `gst` should be those of the subframe that completed the
+    /// DSM.
+    pub fn write_dsm(&mut self, dsm: &Dsm, svn: Svn, gst: Gst) -> std::io::Result<()> {
+        let data = dsm.data();
+        if data.len() % DSM_BLOCK_BYTES != 0 || data.len() > MAX_DSM_BLOCKS * DSM_BLOCK_BYTES {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("DSM has invalid length {}", data.len()),
+            ));
+        }
+        self.write_common(RECORD_DSM, svn, gst)?;
+        // the number of blocks fits in a u8 due to the check above
+        self.write
+            .write_all(&[dsm.id(), (data.len() / DSM_BLOCK_BYTES) as u8])?;
+        self.write.write_all(data)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.write.flush()
+    }
+
+    /// Gives a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.write
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+/// Reader of archives.
+///
+/// This wraps around a [`Read`] `R` and can be used to read the records of an
+/// archive with the format described in the [module
+/// documentation](crate::archive).
+#[derive(Debug, Clone)]
+pub struct ArchiveReader<R> {
+    read: R,
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// Constructs a new reader using a [`Read`] `read`.
+    ///
+    /// The archive header is read from `read`. An error of kind
+    /// [`ErrorKind::InvalidData`] is returned if the header is not valid or
+    /// if the format version is not supported.
+    pub fn new(mut read: R) -> std::io::Result<ArchiveReader<R>> {
+        let mut header = [0; ARCHIVE_MAGIC.len() + 1];
+        read.read_exact(&mut header)?;
+        if header[..ARCHIVE_MAGIC.len()] != ARCHIVE_MAGIC {
+            return Err(invalid_data("not an OSNMA archive".to_string()));
+        }
+        let version = header[ARCHIVE_MAGIC.len()];
+        if version != ARCHIVE_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported archive version {version}"
+            )));
+        }
+        Ok(ArchiveReader { read })
+    }
+
+    fn read_array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut data = [0; N];
+        self.read.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Tries to read a record.
+    ///
+    /// If the read is successful, the record is returned. If EOF is reached at
+    /// the start of a record, `None` is returned. If a record cannot be parsed,
+    /// an error of kind [`ErrorKind::InvalidData`] is returned. If EOF is
+    /// reached in the middle of a record, which can happen with an archive
+    /// that is still being written, an error of kind
+    /// [`ErrorKind::UnexpectedEof`] is returned.
+    pub fn read_record(&mut self) -> std::io::Result<Option<ArchiveRecord>> {
+        let mut record_type = [0];
+        loop {
+            match self.read.read(&mut record_type) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let [svn, wn @ .., t0, t1, t2, t3] = self.read_array::<7>()?;
+        let svn = Svn::try_from(svn).map_err(|_| invalid_data(format!("invalid SVN {svn}")))?;
+        let gst = Gst::new(u16::from_be_bytes(wn), u32::from_be_bytes([t0, t1, t2, t3]));
+        match record_type[0] {
+            RECORD_PAGE => {
+                let [band, flags] = self.read_array()?;
+                let band = match band {
+                    0 => InavBand::E1B,
+                    1 => InavBand::E5B,
+                    _ => return Err(invalid_data(format!("invalid band {band}"))),
+                };
+                let word = self.read_array::<INAV_WORD_BYTES>()?;
+                let osnma: OsnmaDataMessage = self.read_array()?;
+                Ok(Some(ArchiveRecord::Page {
+                    svn,
+                    gst,
+                    band,
+                    word,
+                    osnma: (flags & FLAG_OSNMA != 0).then_some(osnma),
+                }))
+            }
+            RECORD_SUBFRAME => {
+                let hkroot = self.read_array::<HKROOT_MESSAGE_BYTES>()?;
+                let mack = self.read_array::<MACK_MESSAGE_BYTES>()?;
+                Ok(Some(ArchiveRecord::Subframe {
+                    svn,
+                    gst,
+                    hkroot,
+                    mack,
+                }))
+            }
+            RECORD_DSM => {
+                let [id, blocks] = self.read_array()?;
+                let blocks = usize::from(blocks);
+                if blocks > MAX_DSM_BLOCKS {
+                    return Err(invalid_data(format!("DSM has too many blocks ({blocks})")));
+                }
+                let mut data = vec![0; blocks * DSM_BLOCK_BYTES];
+                self.read.read_exact(&mut data)?;
+                Ok(Some(ArchiveRecord::Dsm { svn, gst, id, data }))
+            }
+            x => Err(invalid_data(format!("invalid record type {x}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitfields::DsmHeader;
+    use crate::dsm::CollectDsm;
+    use crate::types::DsmBlock;
+
+    #[test]
+    fn write_read() {
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1177, 172800);
+        let word = [0x12; INAV_WORD_BYTES];
+        let osnma_data = [0x34; 5];
+        let hkroot = [0x56; HKROOT_MESSAGE_BYTES];
+        let mack = [0x78; MACK_MESSAGE_BYTES];
+
+        let mut write = ArchiveWriter::new(Vec::new()).unwrap();
+        write
+            .write_page(&InavPage {
+                word: &word,
+                osnma: Some(&osnma_data),
+                svn,
+                gst,
+                band: InavBand::E1B,
+            })
+            .unwrap();
+        write
+            .write_page(&InavPage {
+                word: &word,
+                osnma: None,
+                svn,
+                gst: gst.add_seconds(1),
+                band: InavBand::E5B,
+            })
+            .unwrap();
+        write
+            .write_subframe(&RawSubframe {
+                svn,
+                gst,
+                hkroot: &hkroot,
+                mack: &mack,
+            })
+            .unwrap();
+        // A DSM-PKR with 13 blocks and DSM ID 12
+        let mut collect = CollectDsm::new();
+        let mut dsm = None;
+        for block_id in 0..13u8 {
+            let header = [(12 << 4) | block_id];
+            let mut block: DsmBlock = [block_id; DSM_BLOCK_BYTES];
+            block[0] = 0x70;
+            if let Some(d) = collect.feed(DsmHeader(&header), &block) {
+                dsm = Some((d.id(), d.data().to_vec()));
+                write.write_dsm(&d, svn, gst).unwrap();
+            }
+        }
+        let (dsm_id, dsm_data) = dsm.unwrap();
+        let archive = write.into_inner();
+        assert_eq!(&archive[..9], b"OSNMARAW\x01");
+
+        let mut read = ArchiveReader::new(&archive[..]).unwrap();
+        let record = read.read_record().unwrap().unwrap();
+        assert_eq!(record.svn(), svn);
+        assert_eq!(record.gst(), gst);
+        let page = record.page().unwrap();
+        assert_eq!(page.word, &word);
+        assert_eq!(page.osnma, Some(&osnma_data));
+        assert_eq!(page.band, InavBand::E1B);
+        let record = read.read_record().unwrap().unwrap();
+        let page = record.page().unwrap();
+        assert_eq!(page.osnma, None);
+        assert_eq!(page.gst, gst.add_seconds(1));
+        assert_eq!(page.band, InavBand::E5B);
+        assert_eq!(
+            read.read_record().unwrap(),
+            Some(ArchiveRecord::Subframe {
+                svn,
+                gst,
+                hkroot,
+                mack
+            })
+        );
+        let record = read.read_record().unwrap().unwrap();
+        assert!(record.page().is_none());
+        assert_eq!(
+            record,
+            ArchiveRecord::Dsm {
+                svn,
+                gst,
+                id: dsm_id,
+                data: dsm_data
+            }
+        );
+        assert_eq!(read.read_record().unwrap(), None);
+
+        // Truncated archive
+        let mut read = ArchiveReader::new(&archive[..archive.len() - 1]).unwrap();
+        for _ in 0..3 {
+            read.read_record().unwrap();
+        }
+        assert_eq!(
+            read.read_record().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // Wrong magic
+        assert_eq!(
+            ArchiveReader::new(&b"OSNMARAX\x01"[..]).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Audit of validated tags.
+//
+// The auditor selects a random subset of the MACK messages whose tags have
+// been validated, and schedules them to be validated again when the next TESLA
+// key is processed, using a key freshly derived from this newer key. A
+// discrepancy between both validations indicates memory corruption or a logic
+// error.
+
+use crate::{Gst, Svn};
+
+// Maximum number of pending audits. If more audits are scheduled, the oldest
+// pending audit is discarded.
+const PENDING_AUDITS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Auditor {
+    // MACK messages are selected for audit if the output of the PRNG is
+    // smaller than this threshold. Audits are disabled if it is zero.
+    threshold: u32,
+    rng_state: u32,
+    pending: [Option<PendingAudit>; PENDING_AUDITS],
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct PendingAudit {
+    pub(crate) prna: Svn,
+    pub(crate) gst_mack: Gst,
+    pub(crate) cid: u8,
+    // Bitmask of the tags that were validated
+    pub(crate) tags: u16,
+}
+
+impl Auditor {
+    pub(crate) fn new() -> Auditor {
+        Auditor {
+            threshold: 0,
+            // Any non-zero seed is valid for xorshift
+            rng_state: 0x2545_f491,
+            pending: [None; PENDING_AUDITS],
+        }
+    }
+
+    pub(crate) fn set_fraction(&mut self, fraction: f32) {
+        self.threshold = if fraction >= 1.0 {
+            u32::MAX
+        } else if fraction > 0.0 {
+            (fraction * u32::MAX as f32) as u32
+        } else {
+            // This also handles NaN
+            self.pending = [None; PENDING_AUDITS];
+            0
+        };
+    }
+
+    // xorshift32 PRNG. It does not need to be cryptographically secure, since
+    // audits are not a security mechanism.
+    fn next_random(&mut self) -> u32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.rng_state = x;
+        x
+    }
+
+    pub(crate) fn schedule(&mut self, audit: PendingAudit) {
+        if self.threshold == 0 || self.next_random() > self.threshold {
+            return;
+        }
+        log::debug!(
+            "scheduling audit of {} MACK at {:?} (tags {:#06x})",
+            audit.prna,
+            audit.gst_mack,
+            audit.tags
+        );
+        let slot = match self.pending.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => {
+                // Discard the oldest pending audit
+                let (slot, _) = self
+                    .pending
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(slot, a)| a.map(|a| (slot, a.gst_mack)))
+                    .min_by(|(_, a), (_, b)| a.cmp_rollover(b))
+                    .unwrap();
+                slot
+            }
+        };
+        self.pending[slot] = Some(audit);
+    }
+
+    // Takes the oldest pending audit for a MACK message transmitted before
+    // `gst_mack`.
+    pub(crate) fn take_due(&mut self, gst_mack: Gst) -> Option<PendingAudit> {
+        self.pending
+            .iter_mut()
+            .filter(|a| a.is_some_and(|a| a.gst_mack.is_before(gst_mack)))
+            .min_by(|a, b| {
+                let (a, b) = (a.unwrap().gst_mack, b.unwrap().gst_mack);
+                a.cmp_rollover(&b)
+            })?
+            .take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn audit(tow: u32) -> PendingAudit {
+        PendingAudit {
+            prna: Svn::try_from(11).unwrap(),
+            gst_mack: Gst::new(1176, tow),
+            cid: 1,
+            tags: 0b101,
+        }
+    }
+
+    #[test]
+    fn fraction() {
+        let mut auditor = Auditor::new();
+        auditor.schedule(audit(0));
+        assert_eq!(auditor.take_due(Gst::new(1176, 30)), None);
+
+        auditor.set_fraction(1.0);
+        for j in 0..PENDING_AUDITS + 2 {
+            auditor.schedule(audit(30 * u32::try_from(j).unwrap()));
+        }
+        // The two oldest audits have been discarded, and only the audits
+        // before the GST are due
+        assert_eq!(auditor.take_due(Gst::new(1176, 60)), None);
+        assert_eq!(auditor.take_due(Gst::new(1176, 120)), Some(audit(60)));
+        assert_eq!(auditor.take_due(Gst::new(1176, 120)), Some(audit(90)));
+        assert_eq!(auditor.take_due(Gst::new(1176, 120)), None);
+
+        // Approximately half of the MACKs are audited
+        auditor.set_fraction(0.5);
+        let scheduled = (0..1000)
+            .filter(|&j| {
+                auditor.schedule(audit(j * 30));
+                auditor.take_due(Gst::new(1177, 0)).is_some()
+            })
+            .count();
+        assert!((400..600).contains(&scheduled));
+    }
+}
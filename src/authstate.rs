@@ -0,0 +1,175 @@
+// Tracker of the authentication state of the navigation data.
+//
+// The tracker evaluates once per subframe the authentication state of the
+// CED and health status data and the timing parameters of each satellite, and
+// produces an Event::AuthStateChanged when the state changes. A new state
+// needs to be observed in a number of consecutive subframes before the change
+// is notified, so that the events do not flicker when a tag is occasionally
+// lost.
+
+use crate::events::{AuthState, AuthStatePolicy, Event};
+use crate::navmessage::NavDataKind;
+use crate::types::NUM_SVNS;
+use crate::{Gst, Svn};
+
+#[derive(Debug, Clone)]
+pub(crate) struct AuthStateTracker {
+    // The tracker is disabled if this is None.
+    policy: Option<AuthStatePolicy>,
+    // GST of the last subframe in which the states were evaluated.
+    last_subframe: Option<Gst>,
+    // State for each satellite and each of the kinds of navigation data
+    // CedAndStatus and TimingParameters.
+    states: [[TrackedState; 2]; NUM_SVNS],
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TrackedState {
+    // Last notified state.
+    state: AuthState,
+    // State different from the notified one that has been observed in the
+    // last consecutive `count` subframes.
+    candidate: AuthState,
+    count: u16,
+}
+
+impl TrackedState {
+    const INITIAL: TrackedState = TrackedState {
+        state: AuthState::Unauthenticated,
+        candidate: AuthState::Unauthenticated,
+        count: 0,
+    };
+}
+
+const KINDS: [NavDataKind; 2] = [NavDataKind::CedAndStatus, NavDataKind::TimingParameters];
+
+impl AuthStateTracker {
+    pub(crate) fn new() -> AuthStateTracker {
+        AuthStateTracker {
+            policy: None,
+            last_subframe: None,
+            states: [[TrackedState::INITIAL; 2]; NUM_SVNS],
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: Option<AuthStatePolicy>) {
+        *self = AuthStateTracker::new();
+        self.policy = policy;
+    }
+
+    // Called for each subframe of OSNMA data received. The function
+    // `data_gst` gives the GST of the most recent authenticated navigation
+    // data of each kind for a satellite.
+    pub(crate) fn check(
+        &mut self,
+        gst: Gst,
+        data_gst: impl Fn(Svn, NavDataKind) -> Option<Gst>,
+        mut notify: impl FnMut(Event),
+    ) {
+        let Some(policy) = self.policy else {
+            return;
+        };
+        let gst = gst.gst_subframe();
+        if self.last_subframe.is_some_and(|last| !gst.is_after(last)) {
+            return;
+        }
+        self.last_subframe = Some(gst);
+        for svn in Svn::iter() {
+            for kind in KINDS {
+                let tracked = &mut self.states[svn.index()][kind as usize];
+                let observed = match data_gst(svn, kind) {
+                    Some(data)
+                        if gst.subframes_difference(data) <= i32::from(policy.stale_after()) =>
+                    {
+                        AuthState::Authenticated
+                    }
+                    // Only data that was authenticated can become stale.
+                    _ if tracked.state == AuthState::Unauthenticated => AuthState::Unauthenticated,
+                    _ => AuthState::Stale,
+                };
+                if observed == tracked.state {
+                    tracked.count = 0;
+                    continue;
+                }
+                if observed == tracked.candidate {
+                    tracked.count = tracked.count.saturating_add(1);
+                } else {
+                    tracked.candidate = observed;
+                    tracked.count = 1;
+                }
+                if tracked.count >= policy.debounce() {
+                    tracked.state = observed;
+                    tracked.count = 0;
+                    notify(Event::AuthStateChanged {
+                        svn,
+                        kind,
+                        state: observed,
+                        gst,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tracker() {
+        let gst = Gst::new(1176, 121080);
+        let svn = Svn::try_from(12).unwrap();
+        let policy = AuthStatePolicy::DEFAULT
+            .with_debounce(2)
+            .with_stale_after(3);
+        let mut tracker = AuthStateTracker::new();
+        let check = |tracker: &mut AuthStateTracker, subframes, data: Option<Gst>| {
+            let mut events = Vec::new();
+            tracker.check(
+                gst.add_subframes(subframes),
+                |s, kind| data.filter(|_| s == svn && kind == NavDataKind::CedAndStatus),
+                |event| events.push(event),
+            );
+            events
+        };
+        let changed = |subframes, state| {
+            vec![Event::AuthStateChanged {
+                svn,
+                kind: NavDataKind::CedAndStatus,
+                state,
+                gst: gst.add_subframes(subframes),
+            }]
+        };
+
+        // Disabled by default
+        assert!(check(&mut tracker, 0, Some(gst)).is_empty());
+
+        tracker.set_policy(Some(policy));
+        assert!(check(&mut tracker, 0, None).is_empty());
+        // Authentication is notified after being observed twice
+        assert!(check(&mut tracker, 1, Some(gst.add_subframes(1))).is_empty());
+        // Evaluated only once per subframe
+        assert!(check(&mut tracker, 1, Some(gst.add_subframes(1))).is_empty());
+        assert_eq!(
+            check(&mut tracker, 2, Some(gst.add_subframes(2))),
+            changed(2, AuthState::Authenticated)
+        );
+        // A single missing authentication does not change the state
+        assert!(check(&mut tracker, 3, None).is_empty());
+        assert!(check(&mut tracker, 4, Some(gst.add_subframes(4))).is_empty());
+        // The data becomes stale
+        assert!(check(&mut tracker, 8, Some(gst.add_subframes(4))).is_empty());
+        assert_eq!(
+            check(&mut tracker, 9, Some(gst.add_subframes(4))),
+            changed(9, AuthState::Stale)
+        );
+        assert!(check(&mut tracker, 10, None).is_empty());
+        // Recovery
+        assert!(check(&mut tracker, 11, Some(gst.add_subframes(11))).is_empty());
+        assert_eq!(
+            check(&mut tracker, 12, Some(gst.add_subframes(12))),
+            changed(12, AuthState::Authenticated)
+        );
+    }
+}
@@ -7,8 +7,11 @@
 pub use crate::tesla::NmaHeader;
 use crate::tesla::{AdkdCheckError, Key, MacseqCheckError};
 use crate::types::{
-    BitSlice, MackMessage, MerkleTreeNode, Towh, MACK_MESSAGE_BYTES, MERKLE_TREE_NODE_BYTES,
+    dsm_kroot_blocks, dsm_pkr_blocks, key_size_bits, tag_size_bits, BitSlice, MackLayout,
+    MackMessage, MerkleTreeNode, Towh, MERKLE_TREE_NODE_BYTES, NUM_SVNS, TAG_INFO_BITS,
 };
+#[cfg(feature = "arbitrary")]
+use crate::types::{DSM_BLOCK_BYTES, KEY_SIZES_BITS, MACK_MESSAGE_BYTES, TAG_SIZES_BITS};
 use crate::validation::{NotValidated, Validated};
 use crate::{Gst, Svn, Wn};
 use bitvec::prelude::*;
@@ -59,6 +62,36 @@ pub enum ChainAndPubkeyStatus {
     AlertMessage,
 }
 
+/// Formats the NMAS field as in `"NMAS=2 (operational)"`.
+impl fmt::Display for NmaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NmaStatus::Reserved => "NMAS=0 (reserved)",
+            NmaStatus::Test => "NMAS=1 (test)",
+            NmaStatus::Operational => "NMAS=2 (operational)",
+            NmaStatus::DontUse => "NMAS=3 (don't use)",
+        }
+        .fmt(f)
+    }
+}
+
+/// Formats the CPKS field as in `"CPKS=2 (end of chain, EOC)"`.
+impl fmt::Display for ChainAndPubkeyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainAndPubkeyStatus::Reserved => "CPKS=0 (reserved)",
+            ChainAndPubkeyStatus::Nominal => "CPKS=1 (nominal)",
+            ChainAndPubkeyStatus::EndOfChain => "CPKS=2 (end of chain, EOC)",
+            ChainAndPubkeyStatus::ChainRevoked => "CPKS=3 (chain revoked, CREV)",
+            ChainAndPubkeyStatus::NewPublicKey => "CPKS=4 (new public key, NPK)",
+            ChainAndPubkeyStatus::PublicKeyRevoked => "CPKS=5 (public key revoked, PKREV)",
+            ChainAndPubkeyStatus::NewMerkleTree => "CPKS=6 (new Merkle tree, NMT)",
+            ChainAndPubkeyStatus::AlertMessage => "CPKS=7 (alert message, AM)",
+        }
+        .fmt(f)
+    }
+}
+
 /// DSM header.
 ///
 /// The DSM header found in the second byte of an HKROOT message.
@@ -88,6 +121,16 @@ pub enum DsmType {
     Pkr,
 }
 
+impl fmt::Display for DsmType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DsmType::Kroot => "DSM-KROOT",
+            DsmType::Pkr => "DSM-PKR",
+        }
+        .fmt(f)
+    }
+}
+
 impl<'a> DsmHeader<'a> {
     fn bits(&self) -> &BitSlice {
         BitSlice::from_slice(self.0)
@@ -137,6 +180,40 @@ pub struct DsmPkr<'a>(
     pub &'a [u8],
 );
 
+/// Version of the OSNMA SIS ICD.
+///
+/// This selects the rules used to interpret the fields of the OSNMA messages.
+/// By default, the rules of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf)
+/// are used. The rules of the OSNMA SIS ICD v1.0 can be used to process data
+/// recorded during the OSNMA test phase.
+///
+/// The OSNMA SIS ICD v1.1 introduced the OSNMA Alert Message (NPKT = 4) and the
+/// New Merkle Tree (CPKS = 6) and Alert Message (CPKS = 7) values of the chain
+/// and public key status. Under the OSNMA SIS ICD v1.0 these values are
+/// reserved.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum IcdVersion {
+    /// OSNMA SIS ICD v1.0.
+    V1_0,
+    /// OSNMA SIS ICD v1.1.
+    ///
+    /// This is the default version.
+    #[default]
+    V1_1,
+}
+
+/// Formats the ICD version as in `"OSNMA SIS ICD v1.1"`.
+impl fmt::Display for IcdVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IcdVersion::V1_0 => "OSNMA SIS ICD v1.0",
+            IcdVersion::V1_1 => "OSNMA SIS ICD v1.1",
+        }
+        .fmt(f)
+    }
+}
+
 /// New Public Key Type (NPKT).
 ///
 /// This represents the values of the New Public Key Type (NPKT) field in the
@@ -152,6 +229,16 @@ pub enum NewPublicKeyType {
     Reserved,
 }
 
+impl fmt::Display for NewPublicKeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NewPublicKeyType::EcdsaKey(ecdsa) => ecdsa.fmt(f),
+            NewPublicKeyType::OsnmaAlertMessage => "OSNMA alert message (OAM)".fmt(f),
+            NewPublicKeyType::Reserved => "reserved NPKT".fmt(f),
+        }
+    }
+}
+
 impl<'a> DsmPkr<'a> {
     fn bits(&self) -> &BitSlice {
         BitSlice::from_slice(self.0)
@@ -165,11 +252,7 @@ impl<'a> DsmPkr<'a> {
     ///
     /// If the NB_DP field contains a reserved value, `None` is returned.
     pub fn number_of_blocks(&self) -> Option<usize> {
-        let v = self.bits()[..4].load_be::<u8>();
-        match v {
-            7..=10 => Some(usize::from(v) + 6),
-            _ => None, // reserved value
-        }
+        dsm_pkr_blocks(self.bits()[..4].load_be::<u8>())
     }
 
     /// Gives the value of the Message ID (MID) field.
@@ -205,6 +288,18 @@ impl<'a> DsmPkr<'a> {
         }
     }
 
+    /// Gives the value of the New Public Key Type (NPKT) field according to
+    /// some version of the OSNMA SIS ICD.
+    ///
+    /// This is the same as [`DsmPkr::new_public_key_type`], except that the
+    /// OSNMA Alert Message is a reserved value under [`IcdVersion::V1_0`].
+    pub fn new_public_key_type_icd(&self, icd: IcdVersion) -> NewPublicKeyType {
+        match (self.new_public_key_type(), icd) {
+            (NewPublicKeyType::OsnmaAlertMessage, IcdVersion::V1_0) => NewPublicKeyType::Reserved,
+            (npkt, _) => npkt,
+        }
+    }
+
     /// Gives the value of the New Public Key ID (NPKID) field.
     pub fn new_public_key_id(&self) -> u8 {
         self.bits()[1036..1040].load_be::<u8>()
@@ -229,18 +324,21 @@ impl<'a> DsmPkr<'a> {
     /// Gives a slice containing the New Public Key field.
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// field does not fit in the DSM-PKR, `None` is returned.
     pub fn new_public_key(&self) -> Option<&[u8]> {
-        self.key_size().map(|s| &self.0[1040 / 8..1040 / 8 + s])
+        self.key_size()
+            .and_then(|s| self.0.get(1040 / 8..1040 / 8 + s))
     }
 
     /// Gives a slice containing the padding field.
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// field does not fit in the DSM-PKR, `None` is returned.
     pub fn padding(&self) -> Option<&[u8]> {
         if let (Some(ks), Some(nb)) = (self.key_size(), self.number_of_blocks()) {
-            Some(&self.0[1040 / 8 + ks..nb * 104 / 8])
+            self.0.get(1040 / 8 + ks..nb * 104 / 8)
         } else {
             None
         }
@@ -252,9 +350,11 @@ impl<'a> DsmPkr<'a> {
     /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
     ///
     /// If the size of the New Public Key field cannot be determined because
-    /// some other fields contain reserved values, `None` is returned.
+    /// some other fields contain reserved values, or if the New Public Key
+    /// field does not fit in the DSM-PKR, `None` is returned.
     pub fn merkle_tree_leaf(&self) -> Option<&[u8]> {
-        self.key_size().map(|s| &self.0[1032 / 8..1040 / 8 + s])
+        self.key_size()
+            .and_then(|s| self.0.get(1032 / 8..1040 / 8 + s))
     }
 
     /// Checks the contents of the padding field.
@@ -330,6 +430,17 @@ pub enum HashFunction {
     Reserved,
 }
 
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashFunction::Sha256 => "SHA-256",
+            HashFunction::Sha3_256 => "SHA3-256",
+            HashFunction::Reserved => "reserved hash function",
+        }
+        .fmt(f)
+    }
+}
+
 /// MAC function.
 ///
 /// This represents the values of the MAC Function (MF) field of the DSM-KROOT
@@ -345,6 +456,17 @@ pub enum MacFunction {
     Reserved,
 }
 
+impl fmt::Display for MacFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacFunction::HmacSha256 => "HMAC-SHA-256",
+            MacFunction::CmacAes => "CMAC-AES",
+            MacFunction::Reserved => "reserved MAC function",
+        }
+        .fmt(f)
+    }
+}
+
 /// ECDSA function.
 ///
 /// This represents the key types available for ECDSA signatures. See Table 15
@@ -358,6 +480,16 @@ pub enum EcdsaFunction {
     P521Sha512,
 }
 
+impl fmt::Display for EcdsaFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EcdsaFunction::P256Sha256 => "ECDSA P-256/SHA-256",
+            EcdsaFunction::P521Sha512 => "ECDSA P-521/SHA-512",
+        }
+        .fmt(f)
+    }
+}
+
 impl<'a> DsmKroot<'a> {
     fn bits(&self) -> &BitSlice {
         BitSlice::from_slice(self.0)
@@ -371,11 +503,7 @@ impl<'a> DsmKroot<'a> {
     ///
     /// If the NB_DK field contains a reserved value, `None` is returned.
     pub fn number_of_blocks(&self) -> Option<usize> {
-        let v = self.bits()[..4].load_be::<u8>();
-        match v {
-            1..=8 => Some(usize::from(v) + 6),
-            _ => None, // reserved value
-        }
+        dsm_kroot_blocks(self.bits()[..4].load_be::<u8>())
     }
 
     /// Gives the value of the PKID (public key ID) field.
@@ -415,22 +543,7 @@ impl<'a> DsmKroot<'a> {
     /// If the KS field contains a reserved value, `None` is returned.
     pub fn key_size(&self) -> Option<usize> {
         // note that all the key sizes are a multiple of 8 bits
-        let size = match self.bits()[16..20].load_be::<u8>() {
-            0 => Some(96),
-            1 => Some(104),
-            2 => Some(112),
-            3 => Some(120),
-            4 => Some(128),
-            5 => Some(160),
-            6 => Some(192),
-            7 => Some(224),
-            8 => Some(256),
-            _ => None,
-        };
-        if let Some(s) = size {
-            debug_assert!(s % 8 == 0);
-        }
-        size
+        key_size_bits(self.bits()[16..20].load_be::<u8>())
     }
 
     /// Gives the MAC tag size in bits.
@@ -441,14 +554,7 @@ impl<'a> DsmKroot<'a> {
     ///
     /// If the TS field contains a reserved value, `None` is returned.
     pub fn tag_size(&self) -> Option<usize> {
-        match self.bits()[20..24].load_be::<u8>() {
-            5 => Some(20),
-            6 => Some(24),
-            7 => Some(28),
-            8 => Some(32),
-            9 => Some(40),
-            _ => None,
-        }
+        tag_size_bits(self.bits()[20..24].load_be::<u8>())
     }
 
     /// Gives the value of the MACLT (MAC look-up table) field.
@@ -485,13 +591,25 @@ impl<'a> DsmKroot<'a> {
     /// # Panics
     ///
     /// Panics if the key size field in the DSM-KROOT message contains a reserved
-    /// value.
+    /// value. See [`DsmKroot::try_kroot`] for a non-panicking version.
+    #[deprecated(note = "use DsmKroot::try_kroot instead")]
     pub fn kroot(&self) -> &[u8] {
-        let size = self
-            .key_size()
-            .expect("attempted to extract kroot of DSM with reserved key size");
+        self.try_kroot()
+            .expect("attempted to extract kroot of DSM with reserved key size")
+    }
+
+    /// Tries to return a slice reference to the KROOT in the DSM-KROOT message.
+    ///
+    /// This is the contents of the KROOT field. The length of the returned slice
+    /// depends on the TESLA key size. An error is returned if the key size
+    /// field contains a reserved value or if the KROOT does not fit in the
+    /// DSM-KROOT message.
+    pub fn try_kroot(&self) -> Result<&[u8], DsmKrootError> {
+        let size = self.key_size().ok_or(DsmKrootError::ReservedKeySize)?;
         let size_bytes = size / 8;
-        &self.0[13..13 + size_bytes]
+        self.0
+            .get(13..13 + size_bytes)
+            .ok_or(DsmKrootError::TooShort)
     }
 
     /// Returns the ECDSA function used by this DSM-KROOT message.
@@ -503,12 +621,27 @@ impl<'a> DsmKroot<'a> {
     ///
     /// Panics if the ECDSA function cannot be guessed because the size of
     /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
+    /// See [`DsmKroot::try_ecdsa_function`] for a non-panicking version.
+    #[deprecated(note = "use DsmKroot::try_ecdsa_function instead")]
     pub fn ecdsa_function(&self) -> EcdsaFunction {
+        match self.try_ecdsa_function() {
+            Ok(function) => function,
+            Err(err) => panic!("failed to guess ECDSA function of DSM-KROOT: {err}"),
+        }
+    }
+
+    /// Tries to return the ECDSA function used by this DSM-KROOT message.
+    ///
+    /// The ECDSA function is guessed from the size of the ECDSA signature in
+    /// the message. If the size of the message does not correspond to a
+    /// signature of 512 bits (for P-256) or 1056 bits (for P-521), this returns
+    /// [`DsmKrootError::UnknownEcdsaFunction`].
+    pub fn try_ecdsa_function(&self) -> Result<EcdsaFunction, DsmKrootError> {
         // Although the ICD is not clear about this, we can guess the
         // ECDSA function in use from the size of the DSM-KROOT
         let total_len = self.0.len();
         let fixed_len = 13;
-        let kroot_len = self.kroot().len();
+        let kroot_len = self.try_kroot()?.len();
         let remaining_len = total_len - fixed_len - kroot_len;
         let b = 13; // block size
         let p256_bytes = 64; // 512 bits
@@ -516,15 +649,11 @@ impl<'a> DsmKroot<'a> {
         let p256_padding = (b - (kroot_len + p256_bytes) % b) % b;
         let p521_padding = (b - (kroot_len + p521_bytes) % b) % b;
         if remaining_len == p256_bytes + p256_padding {
-            EcdsaFunction::P256Sha256
+            Ok(EcdsaFunction::P256Sha256)
         } else if remaining_len == p521_bytes + p521_padding {
-            EcdsaFunction::P521Sha512
+            Ok(EcdsaFunction::P521Sha512)
         } else {
-            panic!(
-                "failed to guess ECDSA function with DSM-KROOT total len = {}\
-                    and kroot len = {}",
-                total_len, kroot_len
-            );
+            Err(DsmKrootError::UnknownEcdsaFunction)
         }
     }
 
@@ -537,29 +666,66 @@ impl<'a> DsmKroot<'a> {
     ///
     /// Panics if the ECDSA function cannot be guessed because the size of
     /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
+    /// See [`DsmKroot::try_digital_signature`] for a non-panicking version.
+    #[deprecated(note = "use DsmKroot::try_digital_signature instead")]
     pub fn digital_signature(&self) -> &[u8] {
-        let size = match self.ecdsa_function() {
+        match self.try_digital_signature() {
+            Ok(signature) => signature,
+            Err(err) => panic!("failed to extract ECDSA signature of DSM-KROOT: {err}"),
+        }
+    }
+
+    /// Tries to return a slice reference to the ECDSA signature in the
+    /// DSM-KROOT message.
+    ///
+    /// This is the contents of the digital signature (DS) field. The length of
+    /// the returned slice depend on the ECDSA function in use. An error is
+    /// returned if the ECDSA function cannot be guessed.
+    pub fn try_digital_signature(&self) -> Result<&[u8], DsmKrootError> {
+        let size = match self.try_ecdsa_function()? {
             EcdsaFunction::P256Sha256 => 64,
             EcdsaFunction::P521Sha512 => 132,
         };
-        let start = 13 + self.kroot().len();
-        &self.0[start..start + size]
+        let start = 13 + self.try_kroot()?.len();
+        // try_ecdsa_function has checked that the signature fits
+        Ok(&self.0[start..start + size])
     }
 
     /// Gives the contents of the DSM-KROOT padding (P_DK) field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ECDSA function cannot be guessed because the size of
+    /// the signature is neither 512 bits (for P-256) nor 1056 bits (for P-521).
+    /// See [`DsmKroot::try_padding`] for a non-panicking version.
+    #[deprecated(note = "use DsmKroot::try_padding instead")]
     pub fn padding(&self) -> &[u8] {
-        let start = 13 + self.kroot().len() + self.digital_signature().len();
-        &self.0[start..]
+        match self.try_padding() {
+            Ok(padding) => padding,
+            Err(err) => panic!("failed to extract padding of DSM-KROOT: {err}"),
+        }
+    }
+
+    /// Tries to give the contents of the DSM-KROOT padding (P_DK) field.
+    ///
+    /// An error is returned if the ECDSA function cannot be guessed, since
+    /// then the start of the padding is unknown.
+    pub fn try_padding(&self) -> Result<&[u8], DsmKrootError> {
+        let start = 13 + self.try_kroot()?.len() + self.try_digital_signature()?.len();
+        Ok(&self.0[start..])
     }
 
     // message for digital signature verification
-    fn signature_message(&self, nma_header: NmaHeader<NotValidated>) -> ([u8; 209], usize) {
+    fn signature_message(
+        &self,
+        nma_header: NmaHeader<NotValidated>,
+    ) -> Result<([u8; 209], usize), DsmKrootError> {
         let mut m = [0; 209];
         m[0] = nma_header.data();
-        let end = 13 + self.kroot().len();
+        let end = 13 + self.try_kroot()?.len();
         // we skip the NB_DK and PKID fields in self.0
         m[1..end].copy_from_slice(&self.0[1..end]);
-        (m, end)
+        Ok((m, end))
     }
 
     /// Checks the contents of the padding field.
@@ -568,15 +734,21 @@ impl<'a> DsmKroot<'a> {
     /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
     ///
     /// If the contents are correct, this returns `true`. Otherwise, this
-    /// returns `false`.
+    /// returns `false`. This also returns `false` if the padding cannot be
+    /// extracted because the DSM-KROOT message is malformed.
     pub fn check_padding(&self, nma_header: NmaHeader<NotValidated>) -> bool {
-        let (message, size) = self.signature_message(nma_header);
+        let (Ok((message, size)), Ok(signature), Ok(padding)) = (
+            self.signature_message(nma_header),
+            self.try_digital_signature(),
+            self.try_padding(),
+        ) else {
+            return false;
+        };
         let message = &message[..size];
         let mut hash = Sha256::new();
         hash.update(message);
-        hash.update(self.digital_signature());
+        hash.update(signature);
         let hash = hash.finalize();
-        let padding = self.padding();
         let truncated = &hash[..padding.len()];
         truncated == padding
     }
@@ -597,7 +769,7 @@ impl<'a> DsmKroot<'a> {
         nma_header: NmaHeader<NotValidated>,
         pubkey: &p256::ecdsa::VerifyingKey,
     ) -> bool {
-        assert_eq!(self.ecdsa_function(), EcdsaFunction::P256Sha256);
+        assert_eq!(self.try_ecdsa_function(), Ok(EcdsaFunction::P256Sha256));
         self.check_signature(nma_header, pubkey)
     }
 
@@ -618,7 +790,7 @@ impl<'a> DsmKroot<'a> {
         nma_header: NmaHeader<NotValidated>,
         pubkey: &p521::ecdsa::VerifyingKey,
     ) -> bool {
-        assert_eq!(self.ecdsa_function(), EcdsaFunction::P521Sha512);
+        assert_eq!(self.try_ecdsa_function(), Ok(EcdsaFunction::P521Sha512));
         self.check_signature(nma_header, pubkey)
     }
 
@@ -632,19 +804,25 @@ impl<'a> DsmKroot<'a> {
     //
     // # Panics
     //
-    // The function panics if the ECDSA signature cannot be serialized, which
-    // can happen if the chosen type parameters do not match the signature
-    // length in the DSM-KROOT message.
+    // The function panics if the chosen type parameters do not match the
+    // signature length in the DSM-KROOT message.
     fn check_signature<VK, C>(&self, nma_header: NmaHeader<NotValidated>, pubkey: &VK) -> bool
     where
         VK: Verifier<Signature<C>>,
         C: PrimeCurve,
         SignatureSize<C>: crypto_common::generic_array::ArrayLength<u8>,
     {
-        let (message, size) = self.signature_message(nma_header);
+        let (Ok((message, size)), Ok(signature)) = (
+            self.signature_message(nma_header),
+            self.try_digital_signature(),
+        ) else {
+            return false;
+        };
         let message = &message[..size];
-        let signature = Signature::from_bytes(self.digital_signature().into())
-            .expect("error serializing ECDSA signature");
+        // The signature is invalid if its r or s values are out of range
+        let Ok(signature) = Signature::from_bytes(signature.into()) else {
+            return false;
+        };
         pubkey.verify(message, &signature).is_ok()
     }
 }
@@ -663,13 +841,44 @@ impl fmt::Debug for DsmKroot<'_> {
             .field("kroot_wn", &self.kroot_wn())
             .field("kroot_towh", &self.kroot_towh())
             .field("alpha", &self.alpha())
-            .field("kroot", &self.kroot())
-            .field("digital_signature", &self.digital_signature())
-            .field("padding", &self.padding())
+            .field("kroot", &self.try_kroot())
+            .field("digital_signature", &self.try_digital_signature())
+            .field("padding", &self.try_padding())
             .finish()
     }
 }
 
+/// DSM-KROOT error.
+///
+/// This enum lists the errors that can happen when extracting the variable
+/// size fields of a DSM-KROOT message with methods such as
+/// [`DsmKroot::try_kroot`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DsmKrootError {
+    /// The key size (KS) field contains a reserved value.
+    ReservedKeySize,
+    /// The DSM-KROOT message is too short to contain the KROOT.
+    TooShort,
+    /// The ECDSA function cannot be guessed, because the size of the message
+    /// does not correspond to a P-256 or P-521 signature.
+    UnknownEcdsaFunction,
+}
+
+impl fmt::Display for DsmKrootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DsmKrootError::ReservedKeySize => "reserved key size in DSM-KROOT".fmt(f),
+            DsmKrootError::TooShort => "DSM-KROOT too short to contain the KROOT".fmt(f),
+            DsmKrootError::UnknownEcdsaFunction => {
+                "DSM-KROOT size does not match any ECDSA function".fmt(f)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DsmKrootError {}
+
 /// MACK message.
 ///
 /// The MACK message, as defined in Figure 8 of the
@@ -690,6 +899,7 @@ pub struct Mack<'a, V> {
     data: &'a BitSlice,
     key_size: usize,
     tag_size: usize,
+    valid_tags: usize,
     _validated: V,
 }
 
@@ -700,14 +910,29 @@ impl<'a> Mack<'a, NotValidated> {
     /// the MACK message. The `key_size` in bits and `tag_size` in bits should
     /// be taken from the parameters of the current TESLA chain. The MACK
     /// message is marked as [`NotValidated`].
-    pub fn new(data: &MackMessage, key_size: usize, tag_size: usize) -> Mack<NotValidated> {
+    pub fn new(data: &MackMessage, key_size: usize, tag_size: usize) -> Mack<'_, NotValidated> {
         Mack {
             data: BitSlice::from_slice(data),
             key_size,
             tag_size,
+            valid_tags: MackLayout::new(key_size, tag_size).num_tags(),
             _validated: NotValidated {},
         }
     }
+
+    // Marks the MACK message as validated without checking its MACSEQ and
+    // ADKD fields. This is used by tests that need a validated MACK message
+    // whose MACSEQ field is not known.
+    #[cfg(test)]
+    pub(crate) fn force_valid(self) -> Mack<'a, Validated> {
+        Mack {
+            data: self.data,
+            key_size: self.key_size,
+            tag_size: self.tag_size,
+            valid_tags: self.valid_tags,
+            _validated: Validated {},
+        }
+    }
 }
 
 impl<'a, V> Mack<'a, V> {
@@ -759,7 +984,25 @@ impl<'a, V> Mack<'a, V> {
     ///
     /// The number of tags is computed according to the tag size.
     pub fn num_tags(&self) -> usize {
-        (8 * MACK_MESSAGE_BYTES - self.key_size()) / (self.tag_size() + 16)
+        self.layout().num_tags()
+    }
+
+    /// Returns the number of tags in the MACK message that can be used.
+    ///
+    /// This counts tag0, so the usable tags are those with indices between 0
+    /// and `self.num_valid_tags() - 1`. It is equal to [`Mack::num_tags`],
+    /// except for MACK messages validated with
+    /// [`AdkdCheckPolicy::Truncate`], in which it excludes the first tag
+    /// whose ADKD field is not correct and all the tags that follow it.
+    pub fn num_valid_tags(&self) -> usize {
+        self.valid_tags
+    }
+
+    /// Gives the layout of the MACK message.
+    ///
+    /// The layout is determined by the key size and the tag size.
+    pub fn layout(&self) -> MackLayout {
+        MackLayout::new(self.key_size(), self.tag_size())
     }
 
     /// Gives the Key field of the MACK message.
@@ -767,11 +1010,46 @@ impl<'a, V> Mack<'a, V> {
     /// This fields contains a TESLA key. See Figure 8 in the
     /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
     pub fn key(&self) -> &BitSlice {
-        let start = (self.tag_size() + 16) * self.num_tags();
+        let start = self.layout().key_start();
         &self.data[start..start + self.key_size()]
     }
 }
 
+/// Policy for ADKD fields that do not match the MAC look-up table.
+///
+/// This defines what [`Mack::validate_with_policy`] does when the ADKD field of
+/// a tag does not match the MAC look-up table. The only tags that can be
+/// skipped are those with a reserved ADKD value, which may be used by future
+/// versions of the OSNMA SIS ICD. These tags are never used for authentication,
+/// so skipping them does not weaken the validation of the other tags in the
+/// MACK message.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum AdkdCheckPolicy {
+    /// The whole MACK message is rejected if any ADKD field does not match
+    /// the MAC look-up table.
+    ///
+    /// This is the default policy.
+    #[default]
+    Strict,
+    /// Tags with a reserved ADKD value that do not match the MAC look-up table
+    /// are skipped, and the MACK message is accepted if all the other tags
+    /// match.
+    ///
+    /// A tag with a defined ADKD value that does not match the MAC look-up
+    /// table still causes the whole MACK message to be rejected.
+    SkipTag,
+    /// The MACK message is accepted, but only the tags preceding the first
+    /// ADKD field that does not match the MAC look-up table can be used.
+    ///
+    /// The tags that precede the first incorrect ADKD field have been checked
+    /// against the MAC look-up table, so they can still be used for
+    /// authentication. This increases the number of tags that are
+    /// authenticated on subframes with errors, at the cost of departing from
+    /// the strict validation described in the OSNMA SIS ICD. The number of
+    /// usable tags is given by [`Mack::num_valid_tags`].
+    Truncate,
+}
+
 /// MACK validation error
 ///
 /// This enum lists the possible errors that can happen when a MACK message
@@ -840,7 +1118,7 @@ impl<'a, V: Clone> Mack<'a, V> {
     /// Panics if `n` is not between 1 and `self.num_tags() - 1`.
     pub fn tag_and_info(&self, n: usize) -> TagAndInfo<'_, V> {
         assert!(0 < n && n < self.num_tags());
-        let size = self.tag_size() + 16;
+        let size = self.tag_size() + TAG_INFO_BITS;
         TagAndInfo {
             data: &self.data[size * n..size * (n + 1)],
             _validated: self._validated.clone(),
@@ -866,17 +1144,57 @@ impl<'a, V: Clone> Mack<'a, V> {
     /// If the validation is successful, this returns a copy of `self` with the
     /// validation type parameter `V` set to `Validated`. Otherwise, an error
     /// indicating which check was not satisfied is returned.
+    ///
+    /// This uses [`AdkdCheckPolicy::Strict`]. See
+    /// [`Mack::validate_with_policy`] for other policies.
     pub fn validate(
         &self,
         key: &'_ Key<Validated>,
         prna: Svn,
         gst_mack: Gst,
+    ) -> Result<Mack<'a, Validated>, MackValidationError> {
+        self.validate_with_policy(key, prna, gst_mack, AdkdCheckPolicy::Strict)
+    }
+
+    /// Try to validate the MACK message using an ADKD check policy.
+    ///
+    /// This is the same as [`Mack::validate`], but the ADKD fields that do not
+    /// match the MAC look-up table are handled according to `policy`.
+    pub fn validate_with_policy(
+        &self,
+        key: &'_ Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        policy: AdkdCheckPolicy,
     ) -> Result<Mack<'a, Validated>, MackValidationError> {
         key.validate_macseq(self, prna, gst_mack)?;
 
+        let mut valid_tags = self.num_tags();
         for j in 1..self.num_tags() {
             let tag = self.tag_and_info(j);
+            let reserved = tag.adkd() == Adkd::Reserved;
             if let Err(e) = key.chain().validate_adkd(j, tag, prna, gst_mack) {
+                if policy == AdkdCheckPolicy::SkipTag && reserved {
+                    log::warn!(
+                        "skipping {} tag{} at {:?} with reserved ADKD ({})",
+                        prna,
+                        j,
+                        gst_mack,
+                        e
+                    );
+                    continue;
+                }
+                if policy == AdkdCheckPolicy::Truncate {
+                    log::warn!(
+                        "truncating {} MACK at {:?} before tag{} ({})",
+                        prna,
+                        gst_mack,
+                        j,
+                        e
+                    );
+                    valid_tags = j;
+                    break;
+                }
                 return Err(MackValidationError::WrongAdkd {
                     tag_index: j,
                     error: e,
@@ -887,6 +1205,7 @@ impl<'a, V: Clone> Mack<'a, V> {
             data: self.data,
             key_size: self.key_size,
             tag_size: self.tag_size,
+            valid_tags,
             _validated: Validated {},
         })
     }
@@ -935,6 +1254,17 @@ pub enum Prnd {
     Reserved,
 }
 
+/// Formats the PRND field as in `"PRND=11 (E11)"`.
+impl fmt::Display for Prnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prnd::GalileoSvid(svid) => write!(f, "PRND={svid} (E{svid:02})"),
+            Prnd::GalileoConstellation => "PRND=255 (Galileo constellation)".fmt(f),
+            Prnd::Reserved => "PRND reserved".fmt(f),
+        }
+    }
+}
+
 impl TryFrom<Prnd> for u8 {
     type Error = ();
     fn try_from(value: Prnd) -> Result<u8, ()> {
@@ -963,9 +1293,22 @@ pub enum Adkd {
     Reserved,
 }
 
+/// Formats the ADKD field as in `"ADKD=0 (I/NAV ephemeris, clock and status)"`.
+impl fmt::Display for Adkd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Adkd::InavCed => "ADKD=0 (I/NAV ephemeris, clock and status)",
+            Adkd::InavTiming => "ADKD=4 (I/NAV timing parameters)",
+            Adkd::SlowMac => "ADKD=12 (Slow MAC I/NAV ephemeris, clock and status)",
+            Adkd::Reserved => "ADKD reserved",
+        }
+        .fmt(f)
+    }
+}
+
 impl<'a, V> TagAndInfo<'a, V> {
     /// Gives the tag field.
-    pub fn tag(&self) -> &BitSlice {
+    pub fn tag(&self) -> &'a BitSlice {
         &self.data[..self.data.len() - 16]
     }
 
@@ -980,7 +1323,7 @@ impl<'a, V> TagAndInfo<'a, V> {
     pub fn prnd(&self) -> Prnd {
         let len = self.data.len();
         match self.data[len - 16..len - 8].load_be::<u8>() {
-            n @ 1..=36 => Prnd::GalileoSvid(n),
+            n if (1..=NUM_SVNS).contains(&usize::from(n)) => Prnd::GalileoSvid(n),
             255 => Prnd::GalileoConstellation,
             _ => Prnd::Reserved,
         }
@@ -1017,6 +1360,39 @@ impl<V: fmt::Debug> fmt::Debug for TagAndInfo<'_, V> {
     }
 }
 
+// The Arbitrary implementations generate messages whose length is consistent
+// with their contents, as is guaranteed by CollectDsm and by the chain
+// parameters when the messages are obtained from the signal in space. Inputs
+// that cannot give such a message are rejected with IncorrectFormat.
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for DsmKroot<'a> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let nb_dk = u.peek_bytes(1).ok_or(arbitrary::Error::NotEnoughData)?[0] >> 4;
+        let blocks = dsm_kroot_blocks(nb_dk).ok_or(arbitrary::Error::IncorrectFormat)?;
+        Ok(DsmKroot(u.bytes(blocks * DSM_BLOCK_BYTES)?))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for DsmPkr<'a> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let nb_dp = u.peek_bytes(1).ok_or(arbitrary::Error::NotEnoughData)?[0] >> 4;
+        let blocks = dsm_pkr_blocks(nb_dp).ok_or(arbitrary::Error::IncorrectFormat)?;
+        Ok(DsmPkr(u.bytes(blocks * DSM_BLOCK_BYTES)?))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Mack<'a, NotValidated> {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let key_size = *u.choose(&KEY_SIZES_BITS)?;
+        let tag_size = *u.choose(&TAG_SIZES_BITS)?;
+        let data = u.bytes(MACK_MESSAGE_BYTES)?.try_into().unwrap();
+        Ok(Mack::new(data, key_size, tag_size))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1032,6 +1408,31 @@ mod test {
             nma_header.chain_and_pubkey_status(),
             ChainAndPubkeyStatus::Nominal
         );
+        assert_eq!(
+            nma_header.to_string(),
+            "NMAS=1 (test), CID=1, CPKS=1 (nominal)"
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Adkd::InavCed.to_string(),
+            "ADKD=0 (I/NAV ephemeris, clock and status)"
+        );
+        assert_eq!(
+            Adkd::InavTiming.to_string(),
+            "ADKD=4 (I/NAV timing parameters)"
+        );
+        assert_eq!(Prnd::GalileoSvid(5).to_string(), "PRND=5 (E05)");
+        assert_eq!(
+            ChainAndPubkeyStatus::EndOfChain.to_string(),
+            "CPKS=2 (end of chain, EOC)"
+        );
+        assert_eq!(
+            NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256).to_string(),
+            "ECDSA P-256/SHA-256"
+        );
     }
 
     #[test]
@@ -1149,22 +1550,51 @@ mod test {
         );
         assert_eq!(dsm.padding(), Some(&hex!("24 e0 22 2c 90 80")[..]));
         assert!(dsm.check_padding(&merkle_tree_root));
-    }
+
+        assert_eq!(
+            dsm.new_public_key_type_icd(IcdVersion::V1_0),
+            NewPublicKeyType::EcdsaKey(EcdsaFunction::P256Sha256)
+        );
+
+        // Modify the NPKT to OAM, which is reserved in ICD v1.0
+        let mut oam = dsm.0.to_vec();
+        oam[129] = 0x42;
+        let oam = DsmPkr(&oam);
+        assert_eq!(
+            oam.new_public_key_type_icd(IcdVersion::V1_1),
+            NewPublicKeyType::OsnmaAlertMessage
+        );
+        assert_eq!(
+            oam.new_public_key_type_icd(IcdVersion::V1_0),
+            NewPublicKeyType::Reserved
+        );
+
+        // Modify the NPKT to P521, which does not fit in 13 blocks
+        let mut dsm = dsm.0.to_vec();
+        dsm[129] = 0x32;
+        let dsm = DsmPkr(&dsm);
+        assert_eq!(dsm.new_public_key(), None);
+        assert_eq!(dsm.padding(), None);
+        assert_eq!(dsm.merkle_tree_leaf(), None);
+        assert!(!dsm.check_padding(&merkle_tree_root));
+    }
+
+    // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
+    const DSM_KROOT: [u8; 104] = hex!(
+        "
+        22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+        e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+        28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+        5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+        3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+        ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+        35 c0 21 b0 41 73 93 b5"
+    );
 
     #[test]
+    #[allow(deprecated)]
     fn dsm_kroot() {
-        // DSM-KROOT broadcast on 2022-03-07 9:00 UTC
-        let dsm = hex!(
-            "
-            22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
-            e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
-            28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
-            5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
-            3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
-            ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
-            35 c0 21 b0 41 73 93 b5"
-        );
-        let dsm = DsmKroot(&dsm);
+        let dsm = DsmKroot(&DSM_KROOT);
         assert_eq!(dsm.number_of_blocks(), Some(8));
         assert_eq!(dsm.public_key_id(), 2);
         assert_eq!(dsm.kroot_chain_id(), 1);
@@ -1193,6 +1623,71 @@ mod test {
         assert_eq!(dsm.padding(), hex!("29 89 77 35 c0 21 b0 41 73 93 b5"));
         let nma_header = NmaHeader::new(0x52);
         assert!(dsm.check_padding(nma_header));
+
+        // A signature with r = 0 and s = 0 is rejected
+        let pubkey = p256::ecdsa::VerifyingKey::from_sec1_bytes(&hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39 22
+             48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        ))
+        .unwrap();
+        let mut zero_signature = dsm.0.to_vec();
+        zero_signature[29..93].fill(0);
+        assert!(!DsmKroot(&zero_signature).check_signature_p256(nma_header, &pubkey));
+    }
+
+    #[test]
+    fn dsm_kroot_try_getters() {
+        let dsm = DsmKroot(&DSM_KROOT);
+        assert_eq!(
+            dsm.try_kroot(),
+            Ok(&hex!("84 1e 1d e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3")[..])
+        );
+        assert_eq!(dsm.try_ecdsa_function(), Ok(EcdsaFunction::P256Sha256));
+        assert_eq!(dsm.try_digital_signature().map(<[u8]>::len), Ok(64));
+        assert_eq!(
+            dsm.try_padding(),
+            Ok(&hex!("29 89 77 35 c0 21 b0 41 73 93 b5")[..])
+        );
+
+        // Modify the key size to 96 bits, which gives a size that does not
+        // correspond to any ECDSA function
+        let mut unknown_ecdsa = DSM_KROOT;
+        unknown_ecdsa[2] = 0x09;
+        let dsm = DsmKroot(&unknown_ecdsa);
+        assert_eq!(dsm.try_kroot().map(<[u8]>::len), Ok(12));
+        assert_eq!(
+            dsm.try_ecdsa_function(),
+            Err(DsmKrootError::UnknownEcdsaFunction)
+        );
+        assert_eq!(
+            dsm.try_digital_signature(),
+            Err(DsmKrootError::UnknownEcdsaFunction)
+        );
+        assert_eq!(dsm.try_padding(), Err(DsmKrootError::UnknownEcdsaFunction));
+        assert!(!dsm.check_padding(NmaHeader::new(0x52)));
+
+        // Reserved key size
+        let mut reserved_ks = DSM_KROOT;
+        reserved_ks[2] = 0x9f;
+        let dsm = DsmKroot(&reserved_ks);
+        assert_eq!(dsm.key_size(), None);
+        assert_eq!(dsm.try_kroot(), Err(DsmKrootError::ReservedKeySize));
+        assert_eq!(
+            dsm.try_ecdsa_function(),
+            Err(DsmKrootError::ReservedKeySize)
+        );
+        assert_eq!(
+            dsm.try_digital_signature(),
+            Err(DsmKrootError::ReservedKeySize)
+        );
+        assert_eq!(dsm.try_padding(), Err(DsmKrootError::ReservedKeySize));
+
+        // Message truncated in the middle of the KROOT
+        let dsm = DsmKroot(&DSM_KROOT[..20]);
+        assert_eq!(dsm.key_size(), Some(128));
+        assert_eq!(dsm.try_kroot(), Err(DsmKrootError::TooShort));
+        assert_eq!(dsm.try_ecdsa_function(), Err(DsmKrootError::TooShort));
+        assert_eq!(dsm.try_padding(), Err(DsmKrootError::TooShort));
     }
 
     #[test]
@@ -0,0 +1,303 @@
+use crate::bitfields::{EcdsaFunction, HashFunction, MacFunction};
+use crate::storage::{FullStorage, SmallStorage, StaticStorage};
+use core::fmt;
+use typenum::Unsigned;
+
+/// Build information.
+///
+/// This reports the version of the galileo-osnma crate, the cargo features
+/// that have been enabled when building it, the cryptographic functions that
+/// it supports, and the storage presets that it provides. It is obtained with
+/// [`build_info`].
+///
+/// The `Display` implementation of `BuildInfo` gives a one-line capability
+/// fingerprint that can be included in support requests. Its format is stable
+/// and consists of `key=value` fields separated by spaces, where lists of
+/// values are separated by commas. For example:
+///
+/// ```text
+/// galileo-osnma version=0.8.0 features=p521,slowmac ecdsa=P-256,P-521 hash=SHA-256,SHA3-256 mac=HMAC-SHA-256,CMAC-AES storage=FullStorage:36x13,SmallStorage:12x3
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::{build_info, Feature};
+///
+/// let info = build_info();
+/// if !info.has_feature(Feature::P521) {
+///     println!("P-521 public keys are not supported by this build");
+/// }
+/// println!("{info}");
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BuildInfo {
+    _private: (),
+}
+
+/// Returns the build information of the galileo-osnma crate.
+///
+/// See [`BuildInfo`].
+pub fn build_info() -> BuildInfo {
+    BuildInfo { _private: () }
+}
+
+/// Cargo feature of the galileo-osnma crate.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Feature {
+    /// `p521` feature: support for ECDSA P-521 public keys.
+    P521,
+    /// `slowmac` feature: Slow MAC (ADKD=12) processing.
+    SlowMac,
+    /// `galmon` feature: Galmon transport protocol support.
+    Galmon,
+    /// `std` feature: support for the Rust standard library.
+    Std,
+    /// `simulator` feature: simulated OSNMA broadcast for testing.
+    Simulator,
+    /// `cross-auth` feature: cross-authentication expectations.
+    CrossAuth,
+    /// `nav-diagnostics` feature: diagnostic information about the navigation
+    /// data.
+    NavDiagnostics,
+    /// `provenance` feature: provenance of the INAV words of the navigation
+    /// data.
+    Provenance,
+    /// `reduced-ced` feature: storage of the reduced CED.
+    ReducedCed,
+    /// `unique-authbits` feature: counting of the authentication bits of tags
+    /// from distinct sources.
+    UniqueAuthBits,
+}
+
+impl Feature {
+    const ALL: [Feature; 10] = [
+        Feature::P521,
+        Feature::SlowMac,
+        Feature::Galmon,
+        Feature::Std,
+        Feature::Simulator,
+        Feature::CrossAuth,
+        Feature::NavDiagnostics,
+        Feature::Provenance,
+        Feature::ReducedCed,
+        Feature::UniqueAuthBits,
+    ];
+
+    /// Returns the name of the cargo feature.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::P521 => "p521",
+            Feature::SlowMac => "slowmac",
+            Feature::Galmon => "galmon",
+            Feature::Std => "std",
+            Feature::Simulator => "simulator",
+            Feature::CrossAuth => "cross-auth",
+            Feature::NavDiagnostics => "nav-diagnostics",
+            Feature::Provenance => "provenance",
+            Feature::ReducedCed => "reduced-ced",
+            Feature::UniqueAuthBits => "unique-authbits",
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        match self {
+            Feature::P521 => cfg!(feature = "p521"),
+            Feature::SlowMac => cfg!(feature = "slowmac"),
+            Feature::Galmon => cfg!(feature = "galmon"),
+            Feature::Std => cfg!(feature = "std"),
+            Feature::Simulator => cfg!(feature = "simulator"),
+            Feature::CrossAuth => cfg!(feature = "cross-auth"),
+            Feature::NavDiagnostics => cfg!(feature = "nav-diagnostics"),
+            Feature::Provenance => cfg!(feature = "provenance"),
+            Feature::ReducedCed => cfg!(feature = "reduced-ced"),
+            Feature::UniqueAuthBits => cfg!(feature = "unique-authbits"),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// Storage preset.
+///
+/// This describes one of the [`StaticStorage`] implementations provided by the
+/// [storage](crate::storage) module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct StoragePreset {
+    name: &'static str,
+    num_sats: usize,
+    nav_message_depth: usize,
+    mack_depth: usize,
+}
+
+impl StoragePreset {
+    fn new<S: StaticStorage>(name: &'static str) -> StoragePreset {
+        StoragePreset {
+            name,
+            num_sats: S::NUM_SATS,
+            nav_message_depth: S::NavMessageDepth::USIZE,
+            mack_depth: S::MackDepth::USIZE,
+        }
+    }
+
+    /// Returns the name of the type that implements the storage.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the number of satellites stored in parallel.
+    ///
+    /// See [`StaticStorage::NUM_SATS`].
+    pub fn num_sats(&self) -> usize {
+        self.num_sats
+    }
+
+    /// Returns the number of navigation message subframes stored.
+    ///
+    /// See [`StaticStorage::NavMessageDepth`].
+    pub fn nav_message_depth(&self) -> usize {
+        self.nav_message_depth
+    }
+
+    /// Returns the number of MACK message subframes stored.
+    ///
+    /// See [`StaticStorage::MackDepth`].
+    pub fn mack_depth(&self) -> usize {
+        self.mack_depth
+    }
+
+    /// Returns `true` if the storage is large enough to process Slow MAC.
+    ///
+    /// Slow MAC requires a history of at least 12 subframes of MACK messages.
+    /// This always returns `false` if the `slowmac` feature is disabled.
+    pub fn supports_slowmac(&self) -> bool {
+        cfg!(feature = "slowmac") && self.mack_depth >= 12
+    }
+}
+
+impl fmt::Display for StoragePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}x{}",
+            self.name, self.num_sats, self.nav_message_depth
+        )
+    }
+}
+
+const HASH_FUNCTIONS: [HashFunction; 2] = [HashFunction::Sha256, HashFunction::Sha3_256];
+const MAC_FUNCTIONS: [MacFunction; 2] = [MacFunction::HmacSha256, MacFunction::CmacAes];
+
+impl BuildInfo {
+    /// Returns the version of the galileo-osnma crate.
+    pub fn version(&self) -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    /// Returns `true` if a cargo feature has been enabled.
+    pub fn has_feature(&self, feature: Feature) -> bool {
+        feature.is_enabled()
+    }
+
+    /// Returns an iterator over the enabled cargo features.
+    pub fn features(&self) -> impl Iterator<Item = Feature> {
+        Feature::ALL.into_iter().filter(|f| f.is_enabled())
+    }
+
+    /// Returns an iterator over the supported ECDSA functions.
+    ///
+    /// ECDSA P-521 is only supported if the `p521` feature is enabled.
+    pub fn ecdsa_functions(&self) -> impl Iterator<Item = EcdsaFunction> {
+        [EcdsaFunction::P256Sha256, EcdsaFunction::P521Sha512]
+            .into_iter()
+            .filter(|f| *f != EcdsaFunction::P521Sha512 || cfg!(feature = "p521"))
+    }
+
+    /// Returns an iterator over the supported hash functions.
+    pub fn hash_functions(&self) -> impl Iterator<Item = HashFunction> {
+        HASH_FUNCTIONS.into_iter()
+    }
+
+    /// Returns an iterator over the supported MAC functions.
+    pub fn mac_functions(&self) -> impl Iterator<Item = MacFunction> {
+        MAC_FUNCTIONS.into_iter()
+    }
+
+    /// Returns an iterator over the storage presets.
+    pub fn storage_presets(&self) -> impl Iterator<Item = StoragePreset> {
+        [
+            StoragePreset::new::<FullStorage>("FullStorage"),
+            StoragePreset::new::<SmallStorage>("SmallStorage"),
+        ]
+        .into_iter()
+    }
+}
+
+fn write_list<T>(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    items: impl Iterator<Item = T>,
+    name: impl Fn(&T, &mut fmt::Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    write!(f, " {key}=")?;
+    for (j, item) in items.enumerate() {
+        if j != 0 {
+            f.write_str(",")?;
+        }
+        name(&item, f)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "galileo-osnma version={}", self.version())?;
+        write_list(f, "features", self.features(), |x, f| x.fmt(f))?;
+        write_list(f, "ecdsa", self.ecdsa_functions(), |x, f| {
+            f.write_str(match x {
+                EcdsaFunction::P256Sha256 => "P-256",
+                EcdsaFunction::P521Sha512 => "P-521",
+            })
+        })?;
+        write_list(f, "hash", self.hash_functions(), |x, f| x.fmt(f))?;
+        write_list(f, "mac", self.mac_functions(), |x, f| x.fmt(f))?;
+        write_list(f, "storage", self.storage_presets(), |x, f| x.fmt(f))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let features: Vec<_> = build_info().features().map(|f| f.name()).collect();
+        let ecdsa = if cfg!(feature = "p521") {
+            "P-256,P-521"
+        } else {
+            "P-256"
+        };
+        assert_eq!(
+            build_info().to_string(),
+            format!(
+                "galileo-osnma version={} features={} ecdsa={} \
+                 hash=SHA-256,SHA3-256 mac=HMAC-SHA-256,CMAC-AES \
+                 storage=FullStorage:36x13,SmallStorage:12x3",
+                env!("CARGO_PKG_VERSION"),
+                features.join(","),
+                ecdsa
+            )
+        );
+    }
+
+    #[test]
+    fn storage_presets() {
+        let presets: Vec<_> = build_info().storage_presets().collect();
+        assert_eq!(presets[0].supports_slowmac(), cfg!(feature = "slowmac"));
+        assert!(!presets[1].supports_slowmac());
+    }
+}
@@ -0,0 +1,252 @@
+//! Decoding of the CED and health status data.
+//!
+//! This module contains the [`Ced`] struct, which decodes the clock and
+//! ephemeris data (CED) and health status data (ADKD=0 and 12) into the
+//! [`Ephemeris`], the [`Clock`] correction parameters and the
+//! [`SatelliteHealth`], converting the fields to engineering units. It is
+//! obtained with [`NavMessageData::decode_ced`](crate::navmessage::NavMessageData::decode_ced)
+//! from authenticated data, so that applications do not need to parse the 549
+//! bits of the CED and health status data themselves.
+//!
+//! The fields and their scale factors are described in Section 5.1 of the
+//! [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+//! The angles are converted from semi-circles to radians.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{InavBand, Osnma, Svn};
+//!
+//! fn print_clock(osnma: &Osnma<FullStorage>, svn: Svn) {
+//!     let Some(ced) = osnma.get_ced_and_status(svn).and_then(|d| d.decode_ced()) else {
+//!         return;
+//!     };
+//!     if ced.health().is_healthy(InavBand::E1B) {
+//!         println!("{svn} clock bias: {} s", ced.clock().af0);
+//!     }
+//! }
+//! ```
+
+use crate::health::SatelliteHealth;
+use crate::types::{BitSlice, CED_AND_STATUS_BITS};
+use bitvec::prelude::*;
+use core::f64::consts::PI;
+use core::ops::Range;
+
+/// Ephemeris parameters.
+///
+/// The angles are given in radians and the angular rates in radians per
+/// second.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ephemeris {
+    /// Issue of data of the navigation batch (IODnav).
+    pub iodnav: u16,
+    /// Ephemeris reference time (toe), in seconds.
+    pub toe: u32,
+    /// Mean anomaly at reference time (M0), in radians.
+    pub m0: f64,
+    /// Eccentricity (e).
+    pub e: f64,
+    /// Square root of the semi-major axis (sqrtA), in m^(1/2).
+    pub sqrt_a: f64,
+    /// Longitude of ascending node of orbital plane at weekly epoch (OMEGA0),
+    /// in radians.
+    pub omega0: f64,
+    /// Inclination angle at reference time (i0), in radians.
+    pub i0: f64,
+    /// Argument of perigee (omega), in radians.
+    pub omega: f64,
+    /// Rate of change of inclination angle (idot), in radians per second.
+    pub idot: f64,
+    /// Rate of change of right ascension (OMEGADOT), in radians per second.
+    pub omega_dot: f64,
+    /// Mean motion difference from computed value (Delta n), in radians per
+    /// second.
+    pub delta_n: f64,
+    /// Amplitude of the cosine harmonic correction term to the argument of
+    /// latitude (Cuc), in radians.
+    pub cuc: f64,
+    /// Amplitude of the sine harmonic correction term to the argument of
+    /// latitude (Cus), in radians.
+    pub cus: f64,
+    /// Amplitude of the cosine harmonic correction term to the orbit radius
+    /// (Crc), in meters.
+    pub crc: f64,
+    /// Amplitude of the sine harmonic correction term to the orbit radius
+    /// (Crs), in meters.
+    pub crs: f64,
+    /// Amplitude of the cosine harmonic correction term to the angle of
+    /// inclination (Cic), in radians.
+    pub cic: f64,
+    /// Amplitude of the sine harmonic correction term to the angle of
+    /// inclination (Cis), in radians.
+    pub cis: f64,
+}
+
+/// Clock correction parameters.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    /// Clock correction data reference time of week (toc), in seconds.
+    pub toc: u32,
+    /// Clock bias correction coefficient (af0), in seconds.
+    pub af0: f64,
+    /// Clock drift correction coefficient (af1), in s/s.
+    pub af1: f64,
+    /// Clock drift rate correction coefficient (af2), in s/s^2.
+    pub af2: f64,
+    /// E1-E5a broadcast group delay (BGD(E1,E5a)), in seconds.
+    pub bgd_e1_e5a: f64,
+    /// E1-E5b broadcast group delay (BGD(E1,E5b)), in seconds.
+    pub bgd_e1_e5b: f64,
+}
+
+/// Decoded CED and health status data (ADKD=0 and 12).
+///
+/// The GST at which the data was transmitted is not included. It can be
+/// obtained with [`NavMessageData::gst`](crate::navmessage::NavMessageData::gst).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ced {
+    ephemeris: Ephemeris,
+    clock: Clock,
+    health: SatelliteHealth,
+}
+
+// Location of the words in the CED and health status data.
+const WORD1: usize = 0;
+const WORD2: usize = 120;
+const WORD3: usize = 240;
+const WORD4: usize = 362;
+const WORD5: usize = 482;
+
+// Gives 2^exp as an f64. This does not use f64::powi, which is not available
+// in no_std.
+fn pow2(exp: i32) -> f64 {
+    f64::from_bits(((1023 + exp) as u64) << 52)
+}
+
+fn unsigned(data: &BitSlice, start: usize, range: Range<usize>, exp: i32) -> f64 {
+    f64::from(data[start + range.start..start + range.end].load_be::<u32>()) * pow2(exp)
+}
+
+fn signed(data: &BitSlice, start: usize, range: Range<usize>, exp: i32) -> f64 {
+    f64::from(data[start + range.start..start + range.end].load_be::<i32>()) * pow2(exp)
+}
+
+// Same as signed, but converts semi-circles to radians.
+fn angle(data: &BitSlice, start: usize, range: Range<usize>, exp: i32) -> f64 {
+    signed(data, start, range, exp) * PI
+}
+
+impl Ced {
+    /// Decodes the CED and health status data.
+    ///
+    /// The `data` should be the data of a
+    /// [`NavMessageData`](crate::navmessage::NavMessageData) obtained with
+    /// [`Osnma::get_ced_and_status`](crate::Osnma::get_ced_and_status).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is shorter than the 549 bits of the CED and health
+    /// status data.
+    pub fn from_ced_and_status(data: &BitSlice) -> Ced {
+        assert!(data.len() >= CED_AND_STATUS_BITS);
+        // The ranges are relative to the start of each word in the CED and
+        // health status data, which excludes the word type.
+        let ephemeris = Ephemeris {
+            iodnav: data[WORD1..WORD1 + 10].load_be(),
+            toe: data[WORD1 + 10..WORD1 + 24].load_be::<u32>() * 60,
+            m0: angle(data, WORD1, 24..56, -31),
+            e: unsigned(data, WORD1, 56..88, -33),
+            sqrt_a: unsigned(data, WORD1, 88..120, -19),
+            omega0: angle(data, WORD2, 10..42, -31),
+            i0: angle(data, WORD2, 42..74, -31),
+            omega: angle(data, WORD2, 74..106, -31),
+            idot: angle(data, WORD2, 106..120, -43),
+            omega_dot: angle(data, WORD3, 10..34, -43),
+            delta_n: angle(data, WORD3, 34..50, -43),
+            cuc: signed(data, WORD3, 50..66, -29),
+            cus: signed(data, WORD3, 66..82, -29),
+            crc: signed(data, WORD3, 82..98, -5),
+            crs: signed(data, WORD3, 98..114, -5),
+            cic: signed(data, WORD4, 16..32, -29),
+            cis: signed(data, WORD4, 32..48, -29),
+        };
+        let clock = Clock {
+            toc: data[WORD4 + 48..WORD4 + 62].load_be::<u32>() * 60,
+            af0: signed(data, WORD4, 62..93, -34),
+            af1: signed(data, WORD4, 93..114, -46),
+            af2: signed(data, WORD4, 114..120, -59),
+            bgd_e1_e5a: signed(data, WORD5, 41..51, -32),
+            bgd_e1_e5b: signed(data, WORD5, 51..61, -32),
+        };
+        Ced {
+            ephemeris,
+            clock,
+            health: SatelliteHealth::from_ced_and_status(data),
+        }
+    }
+
+    /// Gives the ephemeris parameters.
+    pub fn ephemeris(&self) -> &Ephemeris {
+        &self.ephemeris
+    }
+
+    /// Gives the clock correction parameters.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Gives the satellite health.
+    pub fn health(&self) -> &SatelliteHealth {
+        &self.health
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let mut data = bitarr![u8, Msb0; 0; CED_AND_STATUS_BITS];
+        data[0..10].store_be(42u16); // IODnav
+        data[10..24].store_be(100u16); // toe
+        data[24..56].store_be(1u32 << 30); // M0
+        data[56..88].store_be(1u32 << 23); // e
+        data[88..120].store_be(5440u32 << 19); // sqrtA
+        data[130..162].store_be(-(1i32 << 30)); // OMEGA0
+        data[250..274].store_be(-1i32); // OMEGADOT
+        data[322..338].store_be(-32i16); // Crc
+        data[410..424].store_be(200u16); // toc
+        data[424..455].store_be(-(1i32 << 20)); // af0
+        data[476..482].store_be(31u8); // af2
+        data[533..543].store_be(-4i16); // BGD(E1,E5b)
+        data[354..362].store_be(107u8); // SISA
+        data[543..545].store_be(1u8); // E5bHS
+        let ced = Ced::from_ced_and_status(&data[..CED_AND_STATUS_BITS]);
+
+        let ephemeris = ced.ephemeris();
+        assert_eq!(ephemeris.iodnav, 42);
+        assert_eq!(ephemeris.toe, 6000);
+        assert_eq!(ephemeris.m0, PI / 2.0);
+        assert_eq!(ephemeris.e, pow2(-10));
+        assert_eq!(ephemeris.sqrt_a, 5440.0);
+        assert_eq!(ephemeris.omega0, -PI / 2.0);
+        assert_eq!(ephemeris.i0, 0.0);
+        assert_eq!(ephemeris.omega_dot, -pow2(-43) * PI);
+        assert_eq!(ephemeris.crc, -1.0);
+
+        let clock = ced.clock();
+        assert_eq!(clock.toc, 12000);
+        assert_eq!(clock.af0, -pow2(-14));
+        assert_eq!(clock.af1, 0.0);
+        assert_eq!(clock.af2, 31.0 * pow2(-59));
+        assert_eq!(clock.bgd_e1_e5a, 0.0);
+        assert_eq!(clock.bgd_e1_e5b, -pow2(-30));
+
+        assert_eq!(ced.health().sisa_index(), 107);
+        assert!(ced.health().is_healthy(crate::InavBand::E1B));
+        assert!(!ced.health().is_healthy(crate::InavBand::E5B));
+    }
+}
@@ -0,0 +1,372 @@
+//! Diagnostics.
+//!
+//! This module contains the [`Diagnostic`] struct, which describes a problem
+//! found while processing the data fed into the OSNMA black box, together with
+//! a [`Hint`] that gives its most likely cause and how to fix it. The hints are
+//! included in the corresponding log messages, and diagnostics can also be
+//! received programmatically by installing a [`DiagnosticHook`] with
+//! [`Osnma::set_diagnostic_hook`](crate::Osnma::set_diagnostic_hook).
+//!
+//! Many of the problems reported as diagnostics happen occasionally during
+//! normal operation (for instance, when a satellite rises or sets in the middle
+//! of a subframe). They usually only indicate a problem with the receiver or
+//! the way in which data is fed into the OSNMA black box if they happen
+//! repeatedly.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::diagnostics::Diagnostic;
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use galileo_osnma::storage::FullStorage;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! fn report(diagnostic: &Diagnostic) {
+//!     println!("{diagnostic}");
+//! }
+//!
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//! osnma.set_diagnostic_hook(Some(report));
+//! ```
+
+use crate::gstguard::GstRejection;
+use crate::{Gst, Svn};
+use core::fmt;
+
+/// Hook that receives diagnostics.
+///
+/// See [`Osnma::set_diagnostic_hook`](crate::Osnma::set_diagnostic_hook).
+pub type DiagnosticHook = fn(&Diagnostic);
+
+/// Diagnostic.
+///
+/// A diagnostic describes a problem found while processing the data fed into
+/// the OSNMA black box. See the [module documentation](self).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Diagnostic {
+    kind: DiagnosticKind,
+    svn: Option<Svn>,
+    gst: Gst,
+}
+
+/// Kind of diagnostic.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DiagnosticKind {
+    /// An INAV word differs from the navigation data of the satellite that has
+    /// already been authenticated.
+    ///
+    /// What happens to the word depends on the
+    /// [`MismatchPolicy`](crate::navmessage::MismatchPolicy).
+    WordMismatch {
+        /// Word type of the INAV word.
+        word_type: u8,
+    },
+    /// The OSNMA data of a subframe was not received completely.
+    ///
+    /// A subframe lasts 30 seconds and contains 15 pages. The HKROOT and MACK
+    /// messages can only be used if the OSNMA data of all the pages has been
+    /// received.
+    IncompleteSubframe {
+        /// Number of pages with OSNMA data received in the subframe.
+        received_pages: u8,
+    },
+    /// A MACK message could not be processed because no valid TESLA key is
+    /// available yet.
+    MissingKey,
+    /// The TESLA key transmitted in a MACK message could not be validated.
+    KeyValidationFailed,
+    /// The TESLA key transmitted in a MACK message is older than the current
+    /// valid TESLA key.
+    OldKey,
+    /// Tags with a reserved ADKD value have appeared consistently in the MACK
+    /// messages.
+    ///
+    /// See [`ReservedAdkdStats`](crate::navmessage::ReservedAdkdStats).
+    ReservedAdkd {
+        /// Value of the ADKD field of the last tag with a reserved ADKD.
+        adkd: u8,
+    },
+    /// A verified DSM-KROOT indicates a chain in force that only starts
+    /// being applicable in the future.
+    ///
+    /// The KROOT is not used, since either the DSM-KROOT has been received
+    /// ahead of time or the GST given to the OSNMA black box is wrong.
+    FutureKroot {
+        /// GST at which the chain starts being applicable.
+        start_applicability: Gst,
+    },
+    /// A verified DSM-KROOT uses a MAC look-up table that is not supported by
+    /// this library.
+    ///
+    /// The KROOT is not used. This happens when a new MAC look-up table entry
+    /// is introduced in an update of the OSNMA SIS ICD, and it is fixed by
+    /// updating this library.
+    UnsupportedMaclt {
+        /// MAC look-up table ID given in the DSM-KROOT.
+        maclt: u8,
+    },
+    /// No tags have been validated for some time, even though OSNMA data is
+    /// being received.
+    ///
+    /// This is only reported if the watchdog has been enabled with
+    /// [`Osnma::set_watchdog`](crate::Osnma::set_watchdog). It is reported
+    /// again each time that the watchdog period elapses without any tags
+    /// being validated.
+    AuthenticationStalled {
+        /// Number of subframes since tags were last validated, or since the
+        /// watchdog last reported a diagnostic.
+        subframes: u16,
+        /// Probable cause.
+        cause: StallCause,
+    },
+    /// Data has been rejected because its GST regresses or jumps forward
+    /// unrealistically.
+    ///
+    /// This is only reported if the GST guard has been enabled with
+    /// [`Osnma::set_gst_guard`](crate::Osnma::set_gst_guard). See the
+    /// [gstguard](crate::gstguard) module.
+    GstRejected {
+        /// Reason why the GST has been rejected.
+        rejection: GstRejection,
+    },
+}
+
+/// Probable cause of stalled authentication.
+///
+/// See [`DiagnosticKind::AuthenticationStalled`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StallCause {
+    /// No DSM-KROOT has been verified, so there is no valid TESLA key.
+    NoKroot,
+    /// DSM-KROOTs have been received, but they are signed with a public key
+    /// that the OSNMA black box does not have.
+    WrongPublicKeyId {
+        /// Public key ID of the last DSM-KROOT received.
+        pkid: u8,
+    },
+    /// TESLA keys or KROOTs have been rejected because they do not match the
+    /// GST given to the OSNMA black box.
+    TimeOffset,
+    /// TESLA keys are being validated, but only Slow MAC (ADKD=12) tags are
+    /// processed.
+    SlowMacOnly,
+    /// TESLA keys are being validated, but there is no navigation data to
+    /// which the tags can be applied.
+    MissingNavigationData,
+}
+
+/// Likely cause of a diagnostic and remediation hint.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Hint {
+    /// The satellite has started broadcasting a new batch of navigation data,
+    /// or the receiver is assigning words to the wrong satellite or subframe.
+    NewNavigationData,
+    /// Pages are being lost, due to weak signal or to a receiver TOW offset.
+    PageLoss,
+    /// The DSM-KROOT has not been received and verified yet.
+    WaitForKroot,
+    /// The GST given to the OSNMA black box is wrong.
+    ReceiverTowOffset,
+    /// A new feature has been introduced in an update of the OSNMA SIS ICD,
+    /// or the OSNMA data is corrupted.
+    IcdUpdate,
+    /// The DSM-KROOT is signed with a public key that has not been loaded.
+    WrongPublicKey,
+    /// The OSNMA black box has been constructed with `only_slowmac` set to
+    /// `true`.
+    SlowMacOnly,
+    /// The INAV words are not being fed into the OSNMA black box, or they are
+    /// incomplete.
+    MissingNavigationData,
+    /// The time solution of the receiver is jumping.
+    ReceiverTimeJump,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(kind: DiagnosticKind, svn: Option<Svn>, gst: Gst) -> Diagnostic {
+        Diagnostic { kind, svn, gst }
+    }
+
+    /// Gives the kind of diagnostic.
+    pub fn kind(&self) -> DiagnosticKind {
+        self.kind
+    }
+
+    /// Gives the SVN of the satellite that the diagnostic refers to.
+    ///
+    /// This is `None` if the diagnostic does not refer to a particular
+    /// satellite.
+    pub fn svn(&self) -> Option<Svn> {
+        self.svn
+    }
+
+    /// Gives the GST at the start of the subframe that the diagnostic refers to.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the likely cause of the diagnostic.
+    pub fn hint(&self) -> Hint {
+        match self.kind {
+            DiagnosticKind::WordMismatch { .. } => Hint::NewNavigationData,
+            DiagnosticKind::IncompleteSubframe { .. } => Hint::PageLoss,
+            DiagnosticKind::MissingKey => Hint::WaitForKroot,
+            DiagnosticKind::KeyValidationFailed
+            | DiagnosticKind::OldKey
+            | DiagnosticKind::FutureKroot { .. } => Hint::ReceiverTowOffset,
+            DiagnosticKind::ReservedAdkd { .. } | DiagnosticKind::UnsupportedMaclt { .. } => {
+                Hint::IcdUpdate
+            }
+            DiagnosticKind::AuthenticationStalled { cause, .. } => match cause {
+                StallCause::NoKroot => Hint::WaitForKroot,
+                StallCause::WrongPublicKeyId { .. } => Hint::WrongPublicKey,
+                StallCause::TimeOffset => Hint::ReceiverTowOffset,
+                StallCause::SlowMacOnly => Hint::SlowMacOnly,
+                StallCause::MissingNavigationData => Hint::MissingNavigationData,
+            },
+            DiagnosticKind::GstRejected { .. } => Hint::ReceiverTimeJump,
+        }
+    }
+
+    pub(crate) fn report(self, hook: Option<DiagnosticHook>) {
+        if let Some(hook) = hook {
+            hook(&self);
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::WordMismatch { word_type } => write!(
+                f,
+                "INAV word type {word_type} differs from authenticated data"
+            ),
+            DiagnosticKind::IncompleteSubframe { received_pages } => write!(
+                f,
+                "incomplete subframe ({received_pages} of 15 pages with OSNMA data received)"
+            ),
+            DiagnosticKind::MissingKey => "no valid TESLA key available".fmt(f),
+            DiagnosticKind::KeyValidationFailed => {
+                "TESLA key in MACK could not be validated".fmt(f)
+            }
+            DiagnosticKind::OldKey => "TESLA key in MACK is older than the current key".fmt(f),
+            DiagnosticKind::ReservedAdkd { adkd } => {
+                write!(f, "tags with reserved ADKD={adkd} received consistently")
+            }
+            DiagnosticKind::FutureKroot {
+                start_applicability,
+            } => write!(
+                f,
+                "DSM-KROOT for chain in force starts applicability in the future ({:?})",
+                start_applicability
+            ),
+            DiagnosticKind::UnsupportedMaclt { maclt } => {
+                write!(f, "DSM-KROOT uses unsupported MAC look-up table {maclt}")
+            }
+            DiagnosticKind::AuthenticationStalled { subframes, cause } => {
+                write!(f, "no tags validated in {subframes} subframes ({cause})")
+            }
+            DiagnosticKind::GstRejected { rejection } => {
+                write!(f, "data rejected due to {rejection}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for StallCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StallCause::NoKroot => "no KROOT verified".fmt(f),
+            StallCause::WrongPublicKeyId { pkid } => {
+                write!(f, "DSM-KROOT uses unavailable public key id {pkid}")
+            }
+            StallCause::TimeOffset => "TESLA keys rejected".fmt(f),
+            StallCause::SlowMacOnly => "only Slow MAC processed".fmt(f),
+            StallCause::MissingNavigationData => "no navigation data to authenticate".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hint::NewNavigationData => {
+                "likely cause: the satellite has started broadcasting new navigation data; \
+                 if this happens often, check that the receiver assigns each word to the \
+                 correct SVN and GST"
+            }
+            Hint::PageLoss => {
+                "likely cause: pages lost due to weak signal or to the satellite rising or \
+                 setting; if this happens for all the satellites, check for a receiver TOW \
+                 offset, since the GST of each page must be the GST at its start"
+            }
+            Hint::WaitForKroot => {
+                "likely cause: the DSM-KROOT has not been verified yet; this takes several \
+                 minutes after startup and requires a valid ECDSA public key"
+            }
+            Hint::ReceiverTowOffset => {
+                "likely cause: receiver TOW offset; the GST of each page must be the GST at \
+                 its start, with an accuracy better than the OSNMA time synchronization \
+                 requirement"
+            }
+            Hint::IcdUpdate => {
+                "likely cause: a new ADKD or MAC look-up table defined in an update of the \
+                 OSNMA SIS ICD; update this library, or check for data corruption if the \
+                 value changes often"
+            }
+            Hint::WrongPublicKey => {
+                "likely cause: wrong ECDSA public key; load the public key whose ID matches the \
+                 PKID of the DSM-KROOT, or a Merkle tree root so that it can be obtained from a \
+                 DSM-PKR"
+            }
+            Hint::SlowMacOnly => {
+                "likely cause: only Slow MAC is processed, and Slow MAC tags are seldom \
+                 transmitted; disable only_slowmac if the receiver time uncertainty allows it"
+            }
+            Hint::MissingNavigationData => {
+                "likely cause: the INAV words are not fed into the OSNMA black box, or pages \
+                 are being lost; check that all the words of each subframe are fed"
+            }
+            Hint::ReceiverTimeJump => {
+                "likely cause: the receiver time solution has jumped; if this happens often, \
+                 check the time solution of the receiver and the GST given to each page"
+            }
+        }
+        .fmt(f)
+    }
+}
+
+/// Formats the diagnostic including its SVN, GST and hint.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(svn) = self.svn {
+            write!(f, "{svn} ")?;
+        }
+        write!(f, "{}: {} ({})", self.gst, self.kind, self.hint())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let diagnostic = Diagnostic::new(
+            DiagnosticKind::IncompleteSubframe { received_pages: 7 },
+            Some(Svn::try_from(11).unwrap()),
+            Gst::new(1176, 121080),
+        );
+        assert_eq!(diagnostic.hint(), Hint::PageLoss);
+        assert!(diagnostic.to_string().starts_with(
+            "E11 WN=1176 TOW=121080: incomplete subframe \
+             (7 of 15 pages with OSNMA data received) (likely cause: pages lost"
+        ));
+    }
+}
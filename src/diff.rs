@@ -0,0 +1,230 @@
+//! State differences.
+//!
+//! This module contains the [`Difference`] enum, which describes a difference
+//! between the states of two [`Osnma`](crate::Osnma) black boxes. The
+//! differences are obtained with [`Osnma::diff`](crate::Osnma::diff). This is
+//! intended for debugging, by comparing the state of a modified build of this
+//! crate with the state of a reference build after both have processed the
+//! same input. It is also possible to compare the state of two `Osnma` black
+//! boxes that are known to have diverged, such as a black box restored from a
+//! state file and another one that has been running continuously.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use galileo_osnma::storage::SmallStorage;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let reference = Osnma::<SmallStorage>::from_pubkey(pubkey.clone(), false);
+//! let modified = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+//!
+//! // Feed the same data into both OSNMA black boxes
+//! // ...
+//!
+//! for difference in reference.diff(&modified) {
+//!     println!("{difference}");
+//! }
+//! ```
+
+use crate::navmessage::{CollectNavMessage, NavDataKind, StoredNavData};
+use crate::storage::Storage;
+use crate::tesla::Key;
+use crate::validation::Validated;
+use crate::{Gst, Svn};
+use core::fmt;
+
+/// Difference between the states of two OSNMA black boxes.
+///
+/// The two black boxes compared by [`Osnma::diff`](crate::Osnma::diff) are
+/// called left (the black box on which the method is called) and right (the
+/// black box given as argument).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Difference {
+    /// Navigation data is stored for a satellite and subframe in only one of
+    /// the black boxes.
+    NavDataMissing {
+        /// Kind of the navigation data.
+        kind: NavDataKind,
+        /// SVN of the satellite.
+        svn: Svn,
+        /// GST at the start of the subframe in which the data is stored.
+        gst: Gst,
+        /// Black box that does not store the data.
+        missing_in: Side,
+    },
+    /// The navigation data stored for a satellite and subframe has different
+    /// contents in each of the black boxes.
+    NavDataContents {
+        /// Kind of the navigation data.
+        kind: NavDataKind,
+        /// SVN of the satellite.
+        svn: Svn,
+        /// GST at the start of the subframe in which the data is stored.
+        gst: Gst,
+    },
+    /// The navigation data stored for a satellite and subframe has the same
+    /// contents in both black boxes, but a different number of
+    /// authentication bits.
+    Authbits {
+        /// Kind of the navigation data.
+        kind: NavDataKind,
+        /// SVN of the satellite.
+        svn: Svn,
+        /// GST at the start of the subframe in which the data is stored.
+        gst: Gst,
+        /// Authentication bits in the left black box.
+        left: u16,
+        /// Authentication bits in the right black box.
+        right: u16,
+    },
+    /// The TESLA keys of the chain in force are different.
+    ///
+    /// The keys are `None` if no TESLA key has been validated yet. See
+    /// [`Osnma::latest_tesla_key`](crate::Osnma::latest_tesla_key).
+    TeslaKey {
+        /// TESLA key in the left black box.
+        left: Option<Key<Validated>>,
+        /// TESLA key in the right black box.
+        right: Option<Key<Validated>>,
+    },
+    /// The public key IDs of the current ECDSA public keys are different.
+    ///
+    /// The IDs are `None` if no public key is available.
+    PublicKeyId {
+        /// Public key ID in the left black box.
+        left: Option<u8>,
+        /// Public key ID in the right black box.
+        right: Option<u8>,
+    },
+}
+
+/// Side of a comparison of two OSNMA black boxes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Side {
+    /// Black box on which [`Osnma::diff`](crate::Osnma::diff) is called.
+    Left,
+    /// Black box given as argument to [`Osnma::diff`](crate::Osnma::diff).
+    Right,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Left => "left".fmt(f),
+            Side::Right => "right".fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::NavDataMissing {
+                kind,
+                svn,
+                gst,
+                missing_in,
+            } => write!(f, "{svn} {kind} at {gst} missing in {missing_in}"),
+            Difference::NavDataContents { kind, svn, gst } => {
+                write!(f, "{svn} {kind} at {gst} have different contents")
+            }
+            Difference::Authbits {
+                kind,
+                svn,
+                gst,
+                left,
+                right,
+            } => write!(
+                f,
+                "{svn} {kind} at {gst} have different authentication bits \
+                 (left {left}, right {right})"
+            ),
+            Difference::TeslaKey { left, right } => {
+                f.write_str("TESLA keys are different (left ")?;
+                fmt_key(f, left)?;
+                f.write_str(", right ")?;
+                fmt_key(f, right)?;
+                f.write_str(")")
+            }
+            Difference::PublicKeyId { left, right } => {
+                f.write_str("public key IDs are different (left ")?;
+                fmt_option(f, left)?;
+                f.write_str(", right ")?;
+                fmt_option(f, right)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn fmt_key(f: &mut fmt::Formatter<'_>, key: &Option<Key<Validated>>) -> fmt::Result {
+    match key {
+        Some(key) => write!(
+            f,
+            "CID {} at {}",
+            key.chain().chain_id(),
+            key.gst_subframe()
+        ),
+        None => f.write_str("none"),
+    }
+}
+
+fn fmt_option(f: &mut fmt::Formatter<'_>, value: &Option<u8>) -> fmt::Result {
+    match value {
+        Some(value) => write!(f, "{value}"),
+        None => f.write_str("none"),
+    }
+}
+
+fn same_entry(a: &StoredNavData, b: &StoredNavData) -> bool {
+    a.kind == b.kind && a.gst == b.gst && a.svn == b.svn && a.retained == b.retained
+}
+
+fn missing(entry: &StoredNavData, missing_in: Side) -> Difference {
+    Difference::NavDataMissing {
+        kind: entry.kind,
+        svn: entry.svn,
+        gst: entry.gst,
+        missing_in,
+    }
+}
+
+// Compares the navigation data stored in two navigation message stores.
+pub(crate) fn navdata<'a, S: Storage, T: Storage>(
+    left: &'a CollectNavMessage<S>,
+    right: &'a CollectNavMessage<T>,
+) -> impl Iterator<Item = Difference> + 'a {
+    let different = left.stored().filter_map(move |l| {
+        let Some(r) = right.stored().find(|r| same_entry(&l, r)) else {
+            return Some(missing(&l, Side::Right));
+        };
+        if l.bits != r.bits {
+            Some(Difference::NavDataContents {
+                kind: l.kind,
+                svn: l.svn,
+                gst: l.gst,
+            })
+        } else if l.authbits != r.authbits {
+            Some(Difference::Authbits {
+                kind: l.kind,
+                svn: l.svn,
+                gst: l.gst,
+                left: l.authbits,
+                right: r.authbits,
+            })
+        } else {
+            None
+        }
+    });
+    let missing_left = right
+        .stored()
+        .filter(move |r| !left.stored().any(|l| same_entry(&l, r)))
+        .map(|r| missing(&r, Side::Left));
+    different.chain(missing_left)
+}
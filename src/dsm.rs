@@ -1,13 +1,13 @@
 //! DSM message collection.
 //!
 //! This module contains the [`CollectDsm`] struct, which is used to collect all
-//! the DSM blocks of a DSM message and recompose the message.
+//! the DSM blocks of a DSM message and recompose the message. Several DSM
+//! messages with different DSM IDs can be collected concurrently.
 
 use crate::bitfields::{DsmHeader, DsmType};
-use crate::types::{DsmBlock, DSM_BLOCK_BYTES};
+use crate::types::{dsm_kroot_blocks, dsm_pkr_blocks, DsmBlock, DSM_BLOCK_BYTES, MAX_DSM_BLOCKS};
 
-const MAX_DSM_BLOCKS: usize = 16;
-const MAX_DSM_BYTES: usize = MAX_DSM_BLOCKS * DSM_BLOCK_BYTES;
+pub(crate) const MAX_DSM_BYTES: usize = MAX_DSM_BLOCKS * DSM_BLOCK_BYTES;
 
 /// DSM message.
 ///
@@ -38,85 +38,170 @@ impl Dsm<'_> {
     }
 }
 
+/// Number of DSM messages that [`CollectDsm`] collects concurrently.
+///
+/// DSM-KROOT and DSM-PKR messages with different DSM IDs can be transmitted at
+/// the same time by different satellites, and a DSM-KROOT for a new chain can
+/// be transmitted at the same time as the DSM-KROOT of the chain in force.
+pub const DSM_SLOTS: usize = 4;
+
 /// DSM message collector.
 ///
 /// This struct collects DSM blocks and produces a complete DSM message when all
-/// the blocks of the message have been collected. Only one DSM message at a
-/// time can be collected.
+/// the blocks of the message have been collected. Up to [`DSM_SLOTS`] DSM
+/// messages with different DSM IDs are collected concurrently, and each of
+/// them is produced as soon as it is complete. If the blocks of more DSM
+/// messages are received, the DSM message that has not received blocks for
+/// the longest time is discarded.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct CollectDsm {
+    slots: [DsmSlot; DSM_SLOTS],
+    // Number of blocks fed, used to find the least recently used slot.
+    blocks_fed: u64,
+}
+
+// Collection of a single DSM message.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct DsmSlot {
     dsm: [u8; MAX_DSM_BYTES],
     block_valid: [bool; MAX_DSM_BLOCKS],
     done: bool,
-    dsm_type: Option<DsmType>,
-    dsm_id: u8,
+    dsm_id: Option<u8>,
+    dsm_type: DsmType,
+    last_fed: u64,
 }
 
 impl CollectDsm {
     /// Constructs a new, empty DSM collector.
     pub fn new() -> CollectDsm {
         CollectDsm {
-            dsm: [0; MAX_DSM_BYTES],
-            block_valid: [false; MAX_DSM_BLOCKS],
-            done: false,
-            dsm_type: None,
-            dsm_id: 0,
+            slots: core::array::from_fn(|_| DsmSlot::new()),
+            blocks_fed: 0,
         }
     }
 
-    fn reset(&mut self) {
-        self.block_valid = [false; MAX_DSM_BLOCKS];
-        self.done = false;
-    }
-
     /// Feed a new block into the DSM collector.
     ///
-    /// If this block completes the DSM message, the recomposed message will be
+    /// If this block completes a DSM message, the recomposed message will be
     /// returned. Otherwise, this returns `None`. The DSM message is represented
     /// as a slice of bytes, owned by the `CollectDsm`.
     ///
     /// The `header` parameter contains the DSM header of the block, and the
     /// `block` parameter contains the 13-byte DSM block.
     ///
-    /// If the block fed corresponds to a new DSM ID, the old data is discarded
-    /// and the collection for the new DSM begins.
-    pub fn feed(&mut self, header: DsmHeader, block: &DsmBlock) -> Option<Dsm> {
+    /// If the block fed corresponds to a new DSM ID, the collection for the new
+    /// DSM begins, discarding the DSM that has not received blocks for the
+    /// longest time if [`DSM_SLOTS`] DSMs are already being collected. A
+    /// complete DSM message is only returned once. It is collected and returned
+    /// again after another DSM message is completed, or if one of its blocks
+    /// changes (which means that its DSM ID has been reused).
+    pub fn feed(&mut self, header: DsmHeader, block: &DsmBlock) -> Option<Dsm<'_>> {
         log::trace!("feeding header = {:?}, block = {:02x?}", header, block);
-        if header.dsm_id() != self.dsm_id || self.dsm_type.is_none() {
-            log::info!(
-                "new DSM id = {} (had id = {}). resetting",
-                header.dsm_id(),
-                self.dsm_id
-            );
-            self.reset();
-            self.dsm_id = header.dsm_id();
-            self.dsm_type = Some(header.dsm_type());
+        self.blocks_fed += 1;
+        let dsm_id = header.dsm_id();
+        let idx = match self.slots.iter().position(|s| s.dsm_id == Some(dsm_id)) {
+            Some(idx) => idx,
+            None => {
+                // Take an empty slot, or otherwise the least recently used one
+                let (idx, slot) = self
+                    .slots
+                    .iter_mut()
+                    .enumerate()
+                    .min_by_key(|(_, s)| s.dsm_id.map(|_| s.last_fed))
+                    .unwrap();
+                match slot.dsm_id {
+                    Some(old_id) => {
+                        log::info!("new DSM id = {} (discarding id = {})", dsm_id, old_id)
+                    }
+                    None => log::info!("new DSM id = {}", dsm_id),
+                }
+                slot.reset(dsm_id, header.dsm_type());
+                idx
+            }
+        };
+        let slot = &mut self.slots[idx];
+        slot.last_fed = self.blocks_fed;
+        let size = slot.feed(header.dsm_block_id(), block)?;
+        // Collect the other complete DSMs again, so that they are returned
+        // again once they are complete. They might be usable now (for
+        // instance, a DSM-KROOT that can be verified with the public key of
+        // a DSM-PKR).
+        for (j, other) in self.slots.iter_mut().enumerate() {
+            if j != idx && other.done {
+                other.restart();
+            }
         }
-        // cannot panic, since the above ensures that self.dsm_type is
-        // not None
-        let dsm_type = self.dsm_type.unwrap();
-        if self.done {
-            log::trace!("current DSM is complete. nothing to do");
-            return None;
+        let slot = &self.slots[idx];
+        let dsm = &slot.dsm[..size];
+        log::trace!("DSM contents {:02x?}", dsm);
+        Some(Dsm {
+            id: dsm_id,
+            dsm_type: slot.dsm_type,
+            data: dsm,
+        })
+    }
+
+    /// Gives the DSM IDs of the DSM messages that are being collected.
+    ///
+    /// This includes the DSM messages that are complete and have already
+    /// been returned by [`CollectDsm::feed`].
+    pub fn dsm_ids(&self) -> impl Iterator<Item = u8> + '_ {
+        self.slots.iter().filter_map(|s| s.dsm_id)
+    }
+}
+
+impl Default for CollectDsm {
+    fn default() -> CollectDsm {
+        CollectDsm::new()
+    }
+}
+
+impl DsmSlot {
+    fn new() -> DsmSlot {
+        DsmSlot {
+            dsm: [0; MAX_DSM_BYTES],
+            block_valid: [false; MAX_DSM_BLOCKS],
+            done: false,
+            dsm_id: None,
+            dsm_type: DsmType::Kroot,
+            last_fed: 0,
         }
-        self.store_block(header.dsm_block_id(), block);
-        if let Some(size) = self.done_and_size(dsm_type) {
+    }
+
+    fn reset(&mut self, dsm_id: u8, dsm_type: DsmType) {
+        self.restart();
+        self.dsm_id = Some(dsm_id);
+        self.dsm_type = dsm_type;
+    }
+
+    fn restart(&mut self) {
+        self.block_valid = [false; MAX_DSM_BLOCKS];
+        self.done = false;
+    }
+
+    // Returns the size of the DSM if the block completes it.
+    fn feed(&mut self, block_id: u8, block: &DsmBlock) -> Option<usize> {
+        let dsm_id = self.dsm_id.unwrap();
+        if self.done {
+            let idx = usize::from(block_id) * DSM_BLOCK_BYTES;
+            if self.block_valid[usize::from(block_id)]
+                && self.dsm[idx..idx + DSM_BLOCK_BYTES] == block[..]
+            {
+                log::trace!("DSM with id = {} is complete. nothing to do", dsm_id);
+                return None;
+            }
             log::info!(
-                "completed DSM with id = {}, size = {} bytes",
-                self.dsm_id,
-                size
+                "block {} of complete DSM with id = {} has changed. restarting",
+                block_id,
+                dsm_id
             );
-            let dsm = &self.dsm[..size];
-            log::trace!("DSM contents {:02x?}", dsm);
-            self.done = true;
-            Some(Dsm {
-                id: self.dsm_id,
-                dsm_type,
-                data: dsm,
-            })
-        } else {
-            None
+            self.restart();
         }
+        self.store_block(block_id, block);
+        let size = self.done_and_size()?;
+        log::info!("completed DSM with id = {}, size = {} bytes", dsm_id, size);
+        self.done = true;
+        Some(size)
     }
 
     fn store_block(&mut self, block_id: u8, block: &DsmBlock) {
@@ -142,14 +227,14 @@ impl CollectDsm {
         }
     }
 
-    fn done_and_size(&self, dsm_type: DsmType) -> Option<usize> {
+    fn done_and_size(&self) -> Option<usize> {
         if !self.block_valid[0] {
             log::trace!("first block not yet present. DSM size unknown");
             return None;
         }
         // If first block is present, we can read the NB field
         let nb = self.dsm[0] >> 4;
-        if let Some(n) = Self::number_of_blocks(dsm_type, nb) {
+        if let Some(n) = Self::number_of_blocks(self.dsm_type, nb) {
             let missing = self.block_valid[..n].iter().filter(|&x| !x).count();
             log::trace!("DSM size = {} blocks. missing {} blocks", n, missing);
             if missing == 0 {
@@ -159,37 +244,16 @@ impl CollectDsm {
             }
         } else {
             // An invalid DSM with a reserved value as NB can never
-            // be complete. It will be cancelled once a new DSM id
-            // arrives.
+            // be complete. It will be discarded once its slot is
+            // needed for another DSM.
             None
         }
     }
 
     fn number_of_blocks(dsm_type: DsmType, nb: u8) -> Option<usize> {
         let a = match dsm_type {
-            DsmType::Pkr => {
-                match nb {
-                    7 => Some(13),
-                    8 => Some(14),
-                    9 => Some(15),
-                    10 => Some(16),
-                    _ => None, // reserved value
-                }
-            }
-            DsmType::Kroot => {
-                // TODO: use DsmKroot::number_of_blocks instead of this
-                match nb {
-                    1 => Some(7),
-                    2 => Some(8),
-                    3 => Some(9),
-                    4 => Some(10),
-                    5 => Some(11),
-                    6 => Some(12),
-                    7 => Some(13),
-                    8 => Some(14),
-                    _ => None, // reserved value
-                }
-            }
+            DsmType::Pkr => dsm_pkr_blocks(nb),
+            DsmType::Kroot => dsm_kroot_blocks(nb),
         };
         if a.is_none() {
             log::error!("reserved NB value {} for dsm_type = {:?}", nb, dsm_type);
@@ -198,12 +262,6 @@ impl CollectDsm {
     }
 }
 
-impl Default for CollectDsm {
-    fn default() -> CollectDsm {
-        CollectDsm::new()
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -237,7 +295,6 @@ mod test {
             );
             if j != hkroots.len() - 1 {
                 assert!(ret.is_none());
-                assert!(!collect.done);
             } else {
                 let dsm = ret.unwrap();
                 assert_eq!(dsm.id(), 2);
@@ -254,7 +311,116 @@ mod test {
                          35 c0 21 b0 41 73 93 b5"
                     )[..]
                 );
-                assert!(collect.done);
+            }
+        }
+        // The complete DSM is not returned again
+        let hkroot = hkroots[0];
+        assert!(collect
+            .feed(
+                DsmHeader(hkroot[1..2].try_into().unwrap()),
+                hkroot[2..].try_into().unwrap(),
+            )
+            .is_none());
+    }
+
+    // Blocks of a DSM-KROOT with id = 1 and 7 blocks (NB_DK = 1)
+    fn kroot_block(block_id: u8) -> ([u8; 1], DsmBlock) {
+        let mut block = [block_id; DSM_BLOCK_BYTES];
+        if block_id == 0 {
+            block[0] = 0x10;
+        }
+        ([0x10 | block_id], block)
+    }
+
+    // Blocks of a DSM-PKR with id = 12 and 13 blocks (NB_DP = 7)
+    fn pkr_block(block_id: u8) -> ([u8; 1], DsmBlock) {
+        let mut block = [0x80 | block_id; DSM_BLOCK_BYTES];
+        if block_id == 0 {
+            block[0] = 0x70;
+        }
+        ([0xc0 | block_id], block)
+    }
+
+    #[test]
+    fn interleaved() {
+        let mut collect = CollectDsm::new();
+        let mut feed = |(header, block): ([u8; 1], DsmBlock)| {
+            collect
+                .feed(DsmHeader(&header), &block)
+                .map(|dsm| (dsm.id(), dsm.dsm_type(), dsm.data().len()))
+        };
+        // The PKR blocks are interleaved with the KROOT blocks, which
+        // complete first
+        for block_id in 0..6 {
+            assert_eq!(feed(kroot_block(block_id)), None);
+            assert_eq!(feed(pkr_block(block_id)), None);
+        }
+        assert_eq!(
+            feed(kroot_block(6)),
+            Some((1, DsmType::Kroot, 7 * DSM_BLOCK_BYTES))
+        );
+        // The complete KROOT is not returned again
+        assert_eq!(feed(kroot_block(0)), None);
+        for block_id in (6..12).rev() {
+            assert_eq!(feed(pkr_block(block_id)), None);
+        }
+        assert_eq!(
+            feed(pkr_block(12)),
+            Some((12, DsmType::Pkr, 13 * DSM_BLOCK_BYTES))
+        );
+        // Once the PKR is complete, the KROOT is collected and returned again
+        for block_id in 0..6 {
+            assert_eq!(feed(kroot_block(block_id)), None);
+        }
+        assert_eq!(
+            feed(kroot_block(6)),
+            Some((1, DsmType::Kroot, 7 * DSM_BLOCK_BYTES))
+        );
+    }
+
+    #[test]
+    fn slots() {
+        let mut collect = CollectDsm::new();
+        // Start collecting more DSMs than slots. The KROOT with id = 1 is
+        // the least recently used.
+        let (header, block) = kroot_block(0);
+        assert!(collect.feed(DsmHeader(&header), &block).is_none());
+        for dsm_id in 2..=u8::try_from(DSM_SLOTS).unwrap() {
+            let (mut header, block) = kroot_block(1);
+            header[0] = (dsm_id << 4) | 1;
+            assert!(collect.feed(DsmHeader(&header), &block).is_none());
+        }
+        let (header, block) = pkr_block(0);
+        assert!(collect.feed(DsmHeader(&header), &block).is_none());
+        assert_eq!(collect.dsm_ids().count(), DSM_SLOTS);
+        assert!(!collect.dsm_ids().any(|id| id == 1));
+        assert!(collect.dsm_ids().any(|id| id == 12));
+        // The KROOT needs to be collected from the start
+        for block_id in 1..7 {
+            let (header, block) = kroot_block(block_id);
+            assert!(collect.feed(DsmHeader(&header), &block).is_none());
+        }
+        let (header, block) = kroot_block(0);
+        assert!(collect.feed(DsmHeader(&header), &block).is_some());
+    }
+
+    #[test]
+    fn reused_dsm_id() {
+        let mut collect = CollectDsm::new();
+        for block_id in 0..7 {
+            let (header, block) = kroot_block(block_id);
+            let ret = collect.feed(DsmHeader(&header), &block);
+            assert_eq!(ret.is_some(), block_id == 6);
+        }
+        // A block with different contents restarts the collection
+        for block_id in 0..7 {
+            let (header, mut block) = kroot_block(block_id);
+            block[1] = 0xff;
+            let ret = collect.feed(DsmHeader(&header), &block);
+            if block_id == 6 {
+                assert_eq!(ret.unwrap().data()[1], 0xff);
+            } else {
+                assert!(ret.is_none());
             }
         }
     }
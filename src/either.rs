@@ -0,0 +1,665 @@
+use crate::bitfields::{Adkd, AdkdCheckPolicy, IcdVersion};
+use crate::diagnostics::DiagnosticHook;
+use crate::events::{AuthStatePolicy, Event, EventHook};
+#[cfg(feature = "galmon")]
+use crate::galmon::navmon::NavMonMessage;
+use crate::gstguard::{GstGuardPolicy, GstRejectionStats};
+use crate::health::SatelliteHealth;
+#[cfg(feature = "nav-diagnostics")]
+use crate::navmessage::AnomalyTracker;
+#[cfg(feature = "cross-auth")]
+use crate::navmessage::CrossAuthExpectation;
+#[cfg(feature = "reduced-ced")]
+use crate::navmessage::ReducedCed;
+use crate::navmessage::{
+    AdkdFilter, AuthSchedule, ConstellationTags, ConstellationTiming, CopPolicy, MismatchPolicy,
+    NavMessageData, ReservedAdkdStats, TagVerifications,
+};
+use crate::persist::{StateError, STATE_BYTES};
+use crate::status::OsnmaStatus;
+use crate::storage::{FullStorage, SmallStorage, StorageKind};
+use crate::subframe::CollectSubframe;
+use crate::tesla::Key;
+use crate::timing::ClockCheck;
+use crate::types::{InavBand, InavPage, InavWord, OsnmaDataMessage};
+use crate::validation::Validated;
+use crate::{
+    ChainTransition, FeedError, Gst, MerkleTreeNode, NmaDontUsePolicy, NmaState, Osnma,
+    PeerKeyError, PrepositionedChain, PublicKey, Svn,
+};
+
+/// OSNMA black box with a storage size chosen at runtime.
+///
+/// This enum wraps an [`Osnma`] using either [`FullStorage`] or
+/// [`SmallStorage`], so that applications can choose the memory footprint at
+/// startup, for instance from a configuration file. The methods of
+/// `EitherOsnma` forward to the corresponding methods of the wrapped `Osnma`.
+///
+/// This comes at a cost in both memory and code size. The enum holds a
+/// complete `Osnma` for each variant, so its size is that of the
+/// `Osnma<FullStorage>` regardless of the variant in use (it can be put in a
+/// `Box` to avoid reserving this memory statically). Most of the processing
+/// code of `Osnma` is generic over the storage size, so both code paths are
+/// monomorphized and included in the binary. As a reference, a small x86_64
+/// program built with `opt-level = "s"` and LTO is around 59 KiB larger
+/// when it uses `EitherOsnma` than when it uses `Osnma` with a single storage
+/// size. Applications that do not need to choose the storage size at runtime
+/// should use [`Osnma`] directly.
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::{EitherOsnma, PublicKey};
+/// use galileo_osnma::storage::StorageKind;
+/// use p256::ecdsa::VerifyingKey;
+///
+/// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+/// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+/// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+/// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+/// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+/// let low_memory = true; // typically read from a configuration
+/// let kind = if low_memory {
+///     StorageKind::Small
+/// } else {
+///     StorageKind::Full
+/// };
+/// let osnma = EitherOsnma::from_pubkey(kind, pubkey, false);
+/// assert_eq!(osnma.storage_kind(), StorageKind::Small);
+/// ```
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum EitherOsnma {
+    /// OSNMA black box using [`FullStorage`].
+    Full(Osnma<FullStorage>),
+    /// OSNMA black box using [`SmallStorage`].
+    Small(Osnma<SmallStorage>),
+}
+
+macro_rules! forward {
+    ($self:expr, $osnma:ident => $e:expr) => {
+        match $self {
+            EitherOsnma::Full($osnma) => $e,
+            EitherOsnma::Small($osnma) => $e,
+        }
+    };
+}
+
+impl EitherOsnma {
+    /// Constructs a new OSNMA black box using the Merkle tree root.
+    ///
+    /// See [`Osnma::from_merkle_tree`].
+    pub fn from_merkle_tree(
+        kind: StorageKind,
+        merkle_tree_root: MerkleTreeNode,
+        pubkey: Option<PublicKey<Validated>>,
+        only_slowmac: bool,
+    ) -> EitherOsnma {
+        match kind {
+            StorageKind::Full => EitherOsnma::Full(Osnma::from_merkle_tree(
+                merkle_tree_root,
+                pubkey,
+                only_slowmac,
+            )),
+            StorageKind::Small => EitherOsnma::Small(Osnma::from_merkle_tree(
+                merkle_tree_root,
+                pubkey,
+                only_slowmac,
+            )),
+        }
+    }
+
+    /// Constructs a new OSNMA black box using only an ECDSA public key.
+    ///
+    /// See [`Osnma::from_pubkey`].
+    pub fn from_pubkey(
+        kind: StorageKind,
+        pubkey: PublicKey<Validated>,
+        only_slowmac: bool,
+    ) -> EitherOsnma {
+        match kind {
+            StorageKind::Full => EitherOsnma::Full(Osnma::from_pubkey(pubkey, only_slowmac)),
+            StorageKind::Small => EitherOsnma::Small(Osnma::from_pubkey(pubkey, only_slowmac)),
+        }
+    }
+
+    /// Gives the kind of storage used by the OSNMA black box.
+    pub fn storage_kind(&self) -> StorageKind {
+        match self {
+            EitherOsnma::Full(_) => StorageKind::Full,
+            EitherOsnma::Small(_) => StorageKind::Small,
+        }
+    }
+
+    /// Feed an INAV word into the OSNMA black box.
+    ///
+    /// See [`Osnma::feed_inav`].
+    pub fn feed_inav(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        forward!(self, osnma => osnma.feed_inav(word, svn, gst, band))
+    }
+
+    /// Feed an INAV word into the OSNMA black box, reporting any problems.
+    ///
+    /// See [`Osnma::try_feed_inav`].
+    pub fn try_feed_inav(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        forward!(self, osnma => osnma.try_feed_inav(word, svn, gst, band))
+    }
+
+    /// Feed an INAV word whose CRC has been checked into the OSNMA black box.
+    ///
+    /// See [`Osnma::feed_inav_crc_checked`].
+    pub fn feed_inav_crc_checked(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        forward!(self, osnma => osnma.feed_inav_crc_checked(word, svn, gst, band))
+    }
+
+    /// See [`Osnma::try_feed_inav_crc_checked`].
+    pub fn try_feed_inav_crc_checked(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        forward!(self, osnma => osnma.try_feed_inav_crc_checked(word, svn, gst, band))
+    }
+
+    /// Feed the OSNMA data message from an INAV page into the OSNMA black box.
+    ///
+    /// See [`Osnma::feed_osnma`].
+    pub fn feed_osnma(&mut self, osnma_data: &OsnmaDataMessage, svn: Svn, gst: Gst) {
+        forward!(self, osnma => osnma.feed_osnma(osnma_data, svn, gst))
+    }
+
+    /// Feed the OSNMA data message from an INAV page into the OSNMA black box,
+    /// reporting any problems.
+    ///
+    /// See [`Osnma::try_feed_osnma`].
+    pub fn try_feed_osnma(
+        &mut self,
+        osnma_data: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+    ) -> Result<(), FeedError> {
+        forward!(self, osnma => osnma.try_feed_osnma(osnma_data, svn, gst))
+    }
+
+    /// See [`Osnma::feed_osnma_band`].
+    pub fn feed_osnma_band(
+        &mut self,
+        osnma_data: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) {
+        forward!(self, osnma => osnma.feed_osnma_band(osnma_data, svn, gst, band))
+    }
+
+    /// See [`Osnma::try_feed_osnma_band`].
+    pub fn try_feed_osnma_band(
+        &mut self,
+        osnma_data: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        forward!(self, osnma => osnma.try_feed_osnma_band(osnma_data, svn, gst, band))
+    }
+
+    /// Feed a batch of INAV pages into the OSNMA black box.
+    ///
+    /// See [`Osnma::feed_batch`].
+    pub fn feed_batch<'a, I>(&mut self, pages: I)
+    where
+        I: IntoIterator<Item = InavPage<'a>>,
+    {
+        forward!(self, osnma => osnma.feed_batch(pages))
+    }
+
+    /// Feed a Galmon navmon packet into the OSNMA black box.
+    ///
+    /// See [`Osnma::feed_galmon`].
+    #[cfg(feature = "galmon")]
+    pub fn feed_galmon(&mut self, packet: &NavMonMessage) {
+        forward!(self, osnma => osnma.feed_galmon(packet))
+    }
+
+    /// Sets the policy for INAV words that differ from authenticated data.
+    ///
+    /// See [`Osnma::set_mismatch_policy`].
+    pub fn set_mismatch_policy(&mut self, policy: MismatchPolicy) {
+        forward!(self, osnma => osnma.set_mismatch_policy(policy))
+    }
+
+    /// Enables or disables the reconciliation of authentication bits across
+    /// the navigation data history.
+    ///
+    /// See [`Osnma::set_history_reconciliation`].
+    pub fn set_history_reconciliation(&mut self, enabled: bool) {
+        forward!(self, osnma => osnma.set_history_reconciliation(enabled))
+    }
+
+    /// Enables or disables counting only the authentication bits of tags from
+    /// distinct sources.
+    ///
+    /// See [`Osnma::set_unique_tag_authbits`].
+    #[cfg(feature = "unique-authbits")]
+    pub fn set_unique_tag_authbits(&mut self, enabled: bool) {
+        forward!(self, osnma => osnma.set_unique_tag_authbits(enabled))
+    }
+
+    /// Sets the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    ///
+    /// See [`Osnma::set_min_authbits`].
+    pub fn set_min_authbits(&mut self, bits: u16) {
+        forward!(self, osnma => osnma.set_min_authbits(bits))
+    }
+
+    /// Gives the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    pub fn min_authbits(&self) -> u16 {
+        forward!(self, osnma => osnma.min_authbits())
+    }
+
+    /// Sets the policy for authenticated navigation data when the NMA status
+    /// is "don't use".
+    ///
+    /// See [`Osnma::set_nma_dont_use_policy`].
+    pub fn set_nma_dont_use_policy(&mut self, policy: NmaDontUsePolicy) {
+        forward!(self, osnma => osnma.set_nma_dont_use_policy(policy))
+    }
+
+    /// Gives the state of the most recent NMA header.
+    ///
+    /// See [`Osnma::nma_state`].
+    pub fn nma_state(&self) -> Option<NmaState> {
+        forward!(self, osnma => osnma.nma_state())
+    }
+
+    /// Sets the ADKDs that are processed.
+    ///
+    /// See [`Osnma::set_adkd_filter`].
+    pub fn set_adkd_filter(&mut self, filter: AdkdFilter) {
+        forward!(self, osnma => osnma.set_adkd_filter(filter))
+    }
+
+    /// Sets the COP policy for an ADKD.
+    ///
+    /// See [`Osnma::set_cop_policy`].
+    pub fn set_cop_policy(&mut self, adkd: Adkd, policy: CopPolicy) {
+        forward!(self, osnma => osnma.set_cop_policy(adkd, policy))
+    }
+
+    /// Drops the navigation data stored for subframes older than a GST.
+    ///
+    /// See [`Osnma::prune_navdata_older_than`].
+    pub fn prune_navdata_older_than(&mut self, gst: Gst) -> usize {
+        forward!(self, osnma => osnma.prune_navdata_older_than(gst))
+    }
+
+    /// Clears the satellites that have not been received since a GST.
+    ///
+    /// See [`Osnma::clear_satellites_not_seen_since`].
+    pub fn clear_satellites_not_seen_since(&mut self, gst: Gst) -> usize {
+        forward!(self, osnma => osnma.clear_satellites_not_seen_since(gst))
+    }
+
+    /// Sets a hook that receives diagnostics.
+    ///
+    /// See [`Osnma::set_diagnostic_hook`].
+    pub fn set_diagnostic_hook(&mut self, hook: Option<DiagnosticHook>) {
+        forward!(self, osnma => osnma.set_diagnostic_hook(hook))
+    }
+
+    /// Sets a hook that receives events.
+    ///
+    /// See [`Osnma::set_event_hook`].
+    pub fn set_event_hook(&mut self, hook: Option<EventHook>) {
+        forward!(self, osnma => osnma.set_event_hook(hook))
+    }
+
+    /// Enables or disables the events for each tag.
+    ///
+    /// See [`Osnma::set_tag_events`].
+    pub fn set_tag_events(&mut self, enabled: bool) {
+        forward!(self, osnma => osnma.set_tag_events(enabled))
+    }
+
+    /// Gives the most recent validated TESLA key of the chain in force.
+    ///
+    /// See [`Osnma::latest_tesla_key`].
+    pub fn latest_tesla_key(&self) -> Option<Key<Validated>> {
+        forward!(self, osnma => osnma.latest_tesla_key())
+    }
+
+    /// Gives the end-of-chain transition in progress.
+    ///
+    /// See [`Osnma::chain_transition`].
+    pub fn chain_transition(&self) -> Option<ChainTransition> {
+        forward!(self, osnma => osnma.chain_transition())
+    }
+
+    /// Gives the TESLA chain pre-positioned for a chain switch.
+    ///
+    /// See [`Osnma::prepositioned_chain`].
+    pub fn prepositioned_chain(&self) -> Option<PrepositionedChain> {
+        forward!(self, osnma => osnma.prepositioned_chain())
+    }
+
+    /// Imports a validated TESLA key received from a peer.
+    ///
+    /// See [`Osnma::import_tesla_key`].
+    pub fn import_tesla_key(&mut self, key: Key<Validated>, gst: Gst) -> Result<(), PeerKeyError> {
+        forward!(self, osnma => osnma.import_tesla_key(key, gst))
+    }
+
+    /// Sets a TESLA key that has been validated externally.
+    ///
+    /// See [`Osnma::set_tesla_key`].
+    pub fn set_tesla_key(&mut self, key: Key<Validated>) {
+        forward!(self, osnma => osnma.set_tesla_key(key))
+    }
+
+    /// Exports the OSNMA state.
+    ///
+    /// See [`Osnma::export_state`].
+    pub fn export_state(&self, secret: &[u8]) -> Option<[u8; STATE_BYTES]> {
+        forward!(self, osnma => osnma.export_state(secret))
+    }
+
+    /// Imports the OSNMA state.
+    ///
+    /// See [`Osnma::import_state`].
+    pub fn import_state(
+        &mut self,
+        state: &[u8],
+        secret: &[u8],
+        gst: Gst,
+    ) -> Result<(), StateError> {
+        forward!(self, osnma => osnma.import_state(state, secret, gst))
+    }
+
+    /// Saves the OSNMA state to a file.
+    ///
+    /// See [`Osnma::save_to_file`].
+    #[cfg(feature = "std")]
+    pub fn save_to_file<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        secret: &[u8],
+    ) -> std::io::Result<bool> {
+        forward!(self, osnma => osnma.save_to_file(path, secret))
+    }
+
+    /// Loads the OSNMA state from a file.
+    ///
+    /// See [`Osnma::load_from_file`].
+    #[cfg(feature = "std")]
+    pub fn load_from_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+        secret: &[u8],
+        gst: Gst,
+    ) -> std::io::Result<()> {
+        forward!(self, osnma => osnma.load_from_file(path, secret, gst))
+    }
+
+    /// Enables or disables the processing of data from a satellite.
+    ///
+    /// See [`Osnma::set_svn_enabled`].
+    pub fn set_svn_enabled(&mut self, svn: Svn, enabled: bool) {
+        forward!(self, osnma => osnma.set_svn_enabled(svn, enabled))
+    }
+
+    /// Returns `true` if the processing of data from a satellite is enabled.
+    ///
+    /// See [`Osnma::is_svn_enabled`].
+    pub fn is_svn_enabled(&self, svn: Svn) -> bool {
+        forward!(self, osnma => osnma.is_svn_enabled(svn))
+    }
+
+    /// Gives access to the subframe collector.
+    ///
+    /// See [`Osnma::subframe_collector`].
+    pub fn subframe_collector(&self) -> &CollectSubframe {
+        forward!(self, osnma => osnma.subframe_collector())
+    }
+
+    /// Try to get authenticated CED and health status data for a satellite.
+    ///
+    /// See [`Osnma::get_ced_and_status`].
+    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        forward!(self, osnma => osnma.get_ced_and_status(svn))
+    }
+
+    /// Try to get authenticated timing parameters for a satellite.
+    ///
+    /// See [`Osnma::get_timing_parameters`].
+    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        forward!(self, osnma => osnma.get_timing_parameters(svn))
+    }
+
+    /// Try to get the reduced CED of a satellite.
+    ///
+    /// See [`Osnma::get_reduced_ced`].
+    ///
+    /// This is only available with the `reduced-ced` feature.
+    #[cfg(feature = "reduced-ced")]
+    pub fn get_reduced_ced(&self, svn: Svn) -> Option<ReducedCed> {
+        forward!(self, osnma => osnma.get_reduced_ced(svn))
+    }
+
+    /// Try to get the authenticated timing parameters of the constellation.
+    ///
+    /// See [`Osnma::get_constellation_timing`].
+    pub fn get_constellation_timing(&self) -> Option<ConstellationTiming<'_>> {
+        forward!(self, osnma => osnma.get_constellation_timing())
+    }
+
+    /// Try to get authenticated CED and health status data for a satellite as
+    /// it was at some GST.
+    ///
+    /// See [`Osnma::get_ced_and_status_at`].
+    pub fn get_ced_and_status_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        forward!(self, osnma => osnma.get_ced_and_status_at(svn, gst))
+    }
+
+    /// Try to get authenticated timing parameters for a satellite as they were
+    /// at some GST.
+    ///
+    /// See [`Osnma::get_timing_parameters_at`].
+    pub fn get_timing_parameters_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        forward!(self, osnma => osnma.get_timing_parameters_at(svn, gst))
+    }
+
+    /// Try to get the authenticated health of a satellite.
+    ///
+    /// See [`Osnma::get_health`].
+    pub fn get_health(&self, svn: Svn) -> Option<SatelliteHealth> {
+        forward!(self, osnma => osnma.get_health(svn))
+    }
+
+    /// Sets the policy for ADKD fields that do not match the MAC look-up table.
+    ///
+    /// See [`Osnma::set_adkd_check_policy`].
+    pub fn set_adkd_check_policy(&mut self, policy: AdkdCheckPolicy) {
+        forward!(self, osnma => osnma.set_adkd_check_policy(policy))
+    }
+
+    /// Sets the version of the OSNMA SIS ICD used to interpret the OSNMA data.
+    ///
+    /// See [`Osnma::set_icd_version`].
+    pub fn set_icd_version(&mut self, version: IcdVersion) {
+        forward!(self, osnma => osnma.set_icd_version(version))
+    }
+
+    /// Enables or disables the watchdog for stalled authentication.
+    ///
+    /// See [`Osnma::set_watchdog`].
+    pub fn set_watchdog(&mut self, subframes: Option<u16>) {
+        forward!(self, osnma => osnma.set_watchdog(subframes))
+    }
+
+    /// Enables or disables the authentication state change events.
+    ///
+    /// See [`Osnma::set_auth_state_events`].
+    pub fn set_auth_state_events(&mut self, policy: Option<AuthStatePolicy>) {
+        forward!(self, osnma => osnma.set_auth_state_events(policy))
+    }
+
+    /// Enables or disables the GST sanity checks.
+    ///
+    /// See [`Osnma::set_gst_guard`].
+    pub fn set_gst_guard(&mut self, policy: Option<GstGuardPolicy>) {
+        forward!(self, osnma => osnma.set_gst_guard(policy))
+    }
+
+    /// Gives the statistics of the data rejected due to its GST.
+    ///
+    /// See [`Osnma::gst_rejections`].
+    pub fn gst_rejections(&self) -> GstRejectionStats {
+        forward!(self, osnma => osnma.gst_rejections())
+    }
+
+    /// Gives the statistics of tags with reserved ADKD values.
+    ///
+    /// See [`Osnma::reserved_adkd_stats`].
+    pub fn reserved_adkd_stats(&self) -> &ReservedAdkdStats {
+        forward!(self, osnma => osnma.reserved_adkd_stats())
+    }
+
+    /// Sets the fraction of MACK messages whose tags are audited.
+    ///
+    /// See [`Osnma::set_audit_fraction`].
+    pub fn set_audit_fraction(&mut self, fraction: f32) {
+        forward!(self, osnma => osnma.set_audit_fraction(fraction))
+    }
+
+    /// Takes the oldest event from the event queue.
+    ///
+    /// See [`Osnma::poll_event`].
+    pub fn poll_event(&mut self) -> Option<Event> {
+        forward!(self, osnma => osnma.poll_event())
+    }
+
+    /// Takes all the events from the event queue.
+    ///
+    /// See [`Osnma::events`].
+    pub fn events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        core::iter::from_fn(move || self.poll_event())
+    }
+
+    /// Gives the number of events discarded because the event queue was full.
+    ///
+    /// See [`Osnma::dropped_events`].
+    pub fn dropped_events(&self) -> u32 {
+        forward!(self, osnma => osnma.dropped_events())
+    }
+
+    /// Gives the most recent constellation-related tags.
+    ///
+    /// See [`Osnma::constellation_tags`].
+    pub fn constellation_tags(&self) -> &ConstellationTags {
+        forward!(self, osnma => osnma.constellation_tags())
+    }
+
+    /// Gives the most recent tag verification results.
+    ///
+    /// See [`Osnma::tag_verifications`].
+    pub fn tag_verifications(&self) -> &TagVerifications {
+        forward!(self, osnma => osnma.tag_verifications())
+    }
+
+    /// Gives the number of tags that have failed to authenticate the
+    /// navigation data of a satellite.
+    pub fn failed_tags(&self, svn: Svn) -> u32 {
+        forward!(self, osnma => osnma.failed_tags(svn))
+    }
+
+    /// Gives the anomalies that may indicate spoofing.
+    ///
+    /// See [`Osnma::anomalies`].
+    ///
+    /// This is only available with the `nav-diagnostics` feature.
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn anomalies(&self) -> &AnomalyTracker {
+        forward!(self, osnma => osnma.anomalies())
+    }
+
+    /// Clears the anomalies.
+    ///
+    /// See [`Osnma::clear_anomalies`].
+    ///
+    /// This is only available with the `nav-diagnostics` feature.
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn clear_anomalies(&mut self) {
+        forward!(self, osnma => osnma.clear_anomalies())
+    }
+
+    /// Gives the GST of the last subframe in which a MACK message was received
+    /// from a satellite.
+    pub fn last_mack_gst(&self, svn: Svn) -> Option<Gst> {
+        forward!(self, osnma => osnma.last_mack_gst(svn))
+    }
+
+    /// Gives a snapshot of the authentication status of each satellite.
+    pub fn status(&self) -> OsnmaStatus {
+        forward!(self, osnma => osnma.status())
+    }
+
+    /// Compare the GNSS time against the host clock.
+    ///
+    /// See [`Osnma::check_host_clock`].
+    pub fn check_host_clock(&self, gst: Gst, host_utc: f64, threshold: f64) -> Option<ClockCheck> {
+        forward!(self, osnma => osnma.check_host_clock(gst, host_utc, threshold))
+    }
+
+    /// Estimate the authentication schedule for a satellite.
+    ///
+    /// See [`Osnma::auth_schedule`].
+    pub fn auth_schedule(&self, svn: Svn, adkd: Adkd) -> Option<AuthSchedule> {
+        forward!(self, osnma => osnma.auth_schedule(svn, adkd))
+    }
+
+    /// Gives the cross-authentication expectation for a satellite.
+    ///
+    /// See [`Osnma::cross_auth_expectation`].
+    ///
+    /// This is only available with the `cross-auth` feature.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_expectation(&self, svn: Svn, adkd: Adkd) -> Option<CrossAuthExpectation> {
+        forward!(self, osnma => osnma.cross_auth_expectation(svn, adkd))
+    }
+
+    /// Returns an iterator over the satellites whose navigation data is
+    /// expected to be authenticated by tags transmitted by a satellite.
+    ///
+    /// See [`Osnma::cross_auth_targets`].
+    ///
+    /// This is only available with the `cross-auth` feature.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_targets(&self, prna: Svn, adkd: Adkd) -> impl Iterator<Item = Svn> + '_ {
+        Svn::iter().filter(move |&svn| {
+            self.cross_auth_expectation(svn, adkd)
+                .is_some_and(|expectation| expectation.is_authenticated_by(prna))
+        })
+    }
+}
+
+impl From<Osnma<FullStorage>> for EitherOsnma {
+    fn from(value: Osnma<FullStorage>) -> EitherOsnma {
+        EitherOsnma::Full(value)
+    }
+}
+
+impl From<Osnma<SmallStorage>> for EitherOsnma {
+    fn from(value: Osnma<SmallStorage>) -> EitherOsnma {
+        EitherOsnma::Small(value)
+    }
+}
@@ -0,0 +1,418 @@
+//! Events.
+//!
+//! This module contains the [`Event`] enum, which describes the relevant
+//! events that happen while processing the data fed into the OSNMA black box.
+//! The [`Osnma`](crate::Osnma) black box keeps the most recent events in a
+//! fixed-capacity queue, from which they can be obtained with
+//! [`Osnma::poll_event`](crate::Osnma::poll_event). This is intended for
+//! applications that run in `no_std` environments, where it is not convenient
+//! to handle the events as they happen.
+//!
+//! The capacity of the queue is given by
+//! [`Storage::EventQueueDepth`](crate::storage::Storage::EventQueueDepth).
+//! If the queue is full, the oldest event is discarded to make space for a new
+//! one. All the events in the queue can be taken at once with
+//! [`Osnma::events`](crate::Osnma::events). Applications that need to react to
+//! the events as they happen can install an [`EventHook`] with
+//! [`Osnma::set_event_hook`](crate::Osnma::set_event_hook).
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::events::Event;
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use galileo_osnma::storage::SmallStorage;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+//!
+//! // Feed data into the OSNMA black box
+//! // ...
+//!
+//! for event in osnma.events() {
+//!     match event {
+//!         Event::KrootVerified { cid, .. } => println!("verified KROOT of chain {cid}"),
+//!         Event::Diagnostic(diagnostic) => println!("{diagnostic}"),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use crate::bitfields::Adkd;
+use crate::diagnostics::{Diagnostic, DiagnosticHook};
+use crate::navmessage::NavDataKind;
+use crate::storage::StaticStorageTypenum;
+use crate::{Gst, Svn};
+use core::fmt;
+use generic_array::GenericArray;
+
+/// Hook that receives events.
+///
+/// See [`Osnma::set_event_hook`](crate::Osnma::set_event_hook).
+pub type EventHook = fn(&Event);
+
+/// OSNMA event.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Event {
+    /// A diagnostic has been reported.
+    ///
+    /// See the [diagnostics](crate::diagnostics) module.
+    Diagnostic(Diagnostic),
+    /// A DSM-KROOT has been verified.
+    KrootVerified {
+        /// Chain ID (CID) of the TESLA chain of the KROOT.
+        cid: u8,
+        /// GST at the start of the subframe in which the DSM-KROOT was
+        /// completed.
+        gst: Gst,
+    },
+    /// The GST at which the next TESLA chain comes into force has become
+    /// known during an end-of-chain transition.
+    ///
+    /// See [`Osnma::chain_transition`](crate::Osnma::chain_transition).
+    ChainTransitionScheduled {
+        /// Chain ID (CID) of the chain that is ending.
+        cid: u8,
+        /// Chain ID of the next chain.
+        next_cid: u8,
+        /// GST at which the next chain comes into force.
+        switch_gst: Gst,
+    },
+    /// A DSM-PKR has been verified against the Merkle tree, and its public
+    /// key has been stored.
+    DsmPkrVerified {
+        /// Public key ID (PKID) of the public key.
+        pkid: u8,
+        /// GST at the start of the subframe in which the DSM-PKR was
+        /// completed.
+        gst: Gst,
+    },
+    /// A new TESLA key has been validated.
+    TeslaKeyValidated {
+        /// GST at the start of the subframe in which the key was transmitted.
+        gst: Gst,
+    },
+    /// A tag has been validated.
+    ///
+    /// This event is only produced if enabled with
+    /// [`Osnma::set_tag_events`](crate::Osnma::set_tag_events).
+    TagAuthenticated {
+        /// SVN of the satellite whose navigation data is authenticated by
+        /// the tag (PRND).
+        prnd: Svn,
+        /// SVN of the satellite that transmitted the tag (PRNA).
+        prna: Svn,
+        /// ADKD of the tag.
+        adkd: Adkd,
+        /// GST at the start of the subframe in which the tag was transmitted.
+        gst: Gst,
+    },
+    /// A tag has failed validation.
+    ///
+    /// This event is only produced if enabled with
+    /// [`Osnma::set_tag_events`](crate::Osnma::set_tag_events).
+    TagFailed {
+        /// SVN of the satellite whose navigation data is authenticated by
+        /// the tag (PRND).
+        prnd: Svn,
+        /// SVN of the satellite that transmitted the tag (PRNA).
+        prna: Svn,
+        /// ADKD of the tag.
+        adkd: Adkd,
+        /// GST at the start of the subframe in which the tag was transmitted.
+        gst: Gst,
+    },
+    /// Tags that were validated have failed validation when audited.
+    ///
+    /// See [`Osnma::set_audit_fraction`](crate::Osnma::set_audit_fraction).
+    AuditDiscrepancy {
+        /// SVN of the satellite that transmitted the MACK message.
+        prna: Svn,
+        /// GST at the start of the subframe in which the MACK message was
+        /// transmitted.
+        gst: Gst,
+        /// Bitmask of the tags that failed the audit.
+        ///
+        /// Bit `j` corresponds to the tag with index `j` in the MACK message,
+        /// where tag0 corresponds to bit 0.
+        tags: u16,
+    },
+    /// The authentication state of the navigation data of a satellite has
+    /// changed.
+    ///
+    /// See [`Osnma::set_auth_state_events`](crate::Osnma::set_auth_state_events).
+    AuthStateChanged {
+        /// SVN of the satellite.
+        svn: Svn,
+        /// Kind of navigation data.
+        kind: NavDataKind,
+        /// New authentication state.
+        state: AuthState,
+        /// GST at the start of the subframe in which the change was notified.
+        gst: Gst,
+    },
+}
+
+/// Formats the event as in `"TESLA key of WN=1176 TOW=121080 validated"`.
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Diagnostic(diagnostic) => diagnostic.fmt(f),
+            Event::KrootVerified { cid, gst } => write!(f, "KROOT of CID={cid} verified at {gst}"),
+            Event::ChainTransitionScheduled {
+                cid,
+                next_cid,
+                switch_gst,
+            } => write!(
+                f,
+                "switch from chain CID={cid} to CID={next_cid} scheduled at {switch_gst}"
+            ),
+            Event::DsmPkrVerified { pkid, gst } => {
+                write!(f, "DSM-PKR of PKID={pkid} verified at {gst}")
+            }
+            Event::TeslaKeyValidated { gst } => write!(f, "TESLA key of {gst} validated"),
+            Event::TagAuthenticated {
+                prnd,
+                prna,
+                adkd,
+                gst,
+            } => write!(f, "{adkd} tag for {prnd} from {prna} at {gst} validated"),
+            Event::TagFailed {
+                prnd,
+                prna,
+                adkd,
+                gst,
+            } => write!(
+                f,
+                "{adkd} tag for {prnd} from {prna} at {gst} failed validation"
+            ),
+            Event::AuditDiscrepancy { prna, gst, tags } => {
+                write!(
+                    f,
+                    "audit of {prna} MACK at {gst} failed for tags {tags:#06x}"
+                )
+            }
+            Event::AuthStateChanged {
+                svn,
+                kind,
+                state,
+                gst,
+            } => write!(f, "{svn} {kind} {state} at {gst}"),
+        }
+    }
+}
+
+/// Authentication state of the navigation data of a satellite.
+///
+/// This is used in [`Event::AuthStateChanged`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AuthState {
+    /// No authenticated navigation data is available.
+    Unauthenticated,
+    /// Recent authenticated navigation data is available.
+    Authenticated,
+    /// The authenticated navigation data has become too old.
+    ///
+    /// See [`AuthStatePolicy::with_stale_after`].
+    Stale,
+}
+
+impl fmt::Display for AuthState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthState::Unauthenticated => "unauthenticated".fmt(f),
+            AuthState::Authenticated => "authenticated".fmt(f),
+            AuthState::Stale => "stale".fmt(f),
+        }
+    }
+}
+
+/// Policy for the authentication state change events.
+///
+/// This controls when [`Event::AuthStateChanged`] events are produced. See
+/// [`Osnma::set_auth_state_events`](crate::Osnma::set_auth_state_events).
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::events::AuthStatePolicy;
+///
+/// // Data becomes stale after 5 minutes
+/// let policy = AuthStatePolicy::DEFAULT.with_stale_after(10);
+/// assert_eq!(policy.stale_after(), 10);
+/// assert_eq!(policy.debounce(), 2);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthStatePolicy {
+    debounce: u16,
+    stale_after: u16,
+}
+
+impl AuthStatePolicy {
+    /// Default policy.
+    ///
+    /// A change of state is notified once it has been observed in 2
+    /// consecutive subframes. Authenticated navigation data becomes stale
+    /// when it is older than 20 subframes (10 minutes).
+    pub const DEFAULT: AuthStatePolicy = AuthStatePolicy {
+        debounce: 2,
+        stale_after: 20,
+    };
+
+    /// Returns a policy with a different debounce.
+    ///
+    /// A change of state is notified only once the new state has been
+    /// observed in `subframes` consecutive subframes. Values of zero and one
+    /// notify the change in the first subframe in which it is observed.
+    pub const fn with_debounce(self, subframes: u16) -> AuthStatePolicy {
+        AuthStatePolicy {
+            debounce: subframes,
+            ..self
+        }
+    }
+
+    /// Returns a policy with a different staleness threshold.
+    ///
+    /// Authenticated navigation data becomes stale when the GST of the data
+    /// (see [`NavMessageData::gst`](crate::navmessage::NavMessageData::gst))
+    /// is more than `subframes` subframes old.
+    pub const fn with_stale_after(self, subframes: u16) -> AuthStatePolicy {
+        AuthStatePolicy {
+            stale_after: subframes,
+            ..self
+        }
+    }
+
+    /// Gives the debounce in subframes.
+    pub fn debounce(&self) -> u16 {
+        self.debounce
+    }
+
+    /// Gives the staleness threshold in subframes.
+    pub fn stale_after(&self) -> u16 {
+        self.stale_after
+    }
+}
+
+impl Default for AuthStatePolicy {
+    fn default() -> AuthStatePolicy {
+        AuthStatePolicy::DEFAULT
+    }
+}
+
+// Fixed-capacity queue of events. When the queue is full, the oldest event is
+// overwritten.
+#[derive(Debug, Clone)]
+pub(crate) struct EventQueue<N: StaticStorageTypenum> {
+    events: GenericArray<Option<Event>, N>,
+    read_pointer: usize,
+    len: usize,
+    dropped: u32,
+}
+
+impl<N: StaticStorageTypenum> EventQueue<N> {
+    pub(crate) fn new() -> EventQueue<N> {
+        EventQueue {
+            events: GenericArray::default(),
+            read_pointer: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: Event) {
+        let capacity = self.events.len();
+        if capacity == 0 {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        let write_pointer = (self.read_pointer + self.len) % capacity;
+        self.events[write_pointer] = Some(event);
+        if self.len == capacity {
+            // The oldest event has been overwritten
+            self.read_pointer = (self.read_pointer + 1) % capacity;
+            self.dropped = self.dropped.saturating_add(1);
+        } else {
+            self.len += 1;
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.read_pointer].take();
+        self.read_pointer = (self.read_pointer + 1) % self.events.len();
+        self.len -= 1;
+        event
+    }
+
+    pub(crate) fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+// Delivers diagnostics to the diagnostic hook and diagnostics and other events
+// to the event hook and the event queue.
+#[derive(Debug, Clone)]
+pub(crate) struct Notifier<N: StaticStorageTypenum> {
+    pub(crate) diagnostic_hook: Option<DiagnosticHook>,
+    pub(crate) event_hook: Option<EventHook>,
+    pub(crate) events: EventQueue<N>,
+}
+
+impl<N: StaticStorageTypenum> Notifier<N> {
+    pub(crate) fn new() -> Notifier<N> {
+        Notifier {
+            diagnostic_hook: None,
+            event_hook: None,
+            events: EventQueue::new(),
+        }
+    }
+
+    pub(crate) fn report(&mut self, diagnostic: Diagnostic) {
+        diagnostic.report(self.diagnostic_hook);
+        self.notify(Event::Diagnostic(diagnostic));
+    }
+
+    pub(crate) fn notify(&mut self, event: Event) {
+        if let Some(hook) = self.event_hook {
+            hook(&event);
+        }
+        self.events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queue() {
+        let mut queue = EventQueue::<typenum::U4>::new();
+        assert_eq!(queue.pop(), None);
+        let event = |tow| Event::TeslaKeyValidated {
+            gst: Gst::new(1176, tow),
+        };
+        for j in 0..6 {
+            queue.push(event(30 * j));
+        }
+        // The two oldest events have been dropped
+        assert_eq!(queue.dropped(), 2);
+        for j in 2..6 {
+            assert_eq!(queue.pop(), Some(event(30 * j)));
+        }
+        assert_eq!(queue.pop(), None);
+        queue.push(event(300));
+        assert_eq!(queue.pop(), Some(event(300)));
+        assert_eq!(
+            event(121080).to_string(),
+            "TESLA key of WN=1176 TOW=121080 validated"
+        );
+    }
+}
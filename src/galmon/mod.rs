@@ -4,6 +4,26 @@
 //! [Galmon transport protocol](https://github.com/berthubert/galmon#internals).
 //! The reader can be used to obtain INAV frames and OSNMA data from the
 //! [Galmon](https://github.com/berthubert/galmon) tools, such as `ubxtool`.
+//!
+//! The navmon packets can be fed directly into the OSNMA black box with
+//! [`Osnma::feed_galmon`](crate::Osnma::feed_galmon).
+//!
+//! The navmon packets can also be written back annotated with the OSNMA
+//! authentication status of the navigation data, using
+//! [`WriteTransport::write_authenticated`](transport::WriteTransport::write_authenticated).
+//! The annotations are stored in an extension field of the `GalileoInav`
+//! message that is not present in the upstream `navmon.proto`, so that they
+//! are ignored by the Galmon tools that do not know about them.
+
+use crate::inav::InavWordType;
+use crate::storage::Storage;
+use crate::types::{InavPage, InavWord, OsnmaDataMessage};
+use crate::verifier::Verifier;
+use crate::{Gst, InavBand, Osnma, Svn, Wn};
+use navmon::{
+    nav_mon_message::{GalileoInav, OsnmaAuthentication},
+    NavMonMessage,
+};
 
 pub mod navmon {
     //! Galmon `navmon` protocol buffers definition.
@@ -20,6 +40,10 @@ pub mod navmon {
 pub mod transport {
     //! Galmon transport protocol.
     use super::navmon::NavMonMessage;
+    use super::stats::SourceStatistics;
+    use crate::storage::Storage;
+    use crate::verifier::Verifier;
+    use crate::{Osnma, Svn};
     use bytes::BytesMut;
     use prost::Message;
     use std::io::{ErrorKind, Read, Write};
@@ -27,11 +51,13 @@ pub mod transport {
     /// Reader for the Galmon transport protocol.
     ///
     /// This wraps around a [`Read`] `R` and can be used to read navmon packets
-    /// from `R`.
+    /// from `R`. Statistics of the sources of the packets are kept, and can be
+    /// obtained with [`ReadTransport::source_statistics`].
     #[derive(Debug, Clone)]
     pub struct ReadTransport<R> {
         read: R,
         buffer: BytesMut,
+        stats: SourceStatistics,
     }
 
     impl<R: Read> ReadTransport<R> {
@@ -40,7 +66,16 @@ pub mod transport {
             let default_cap = 2048;
             let mut buffer = BytesMut::with_capacity(default_cap);
             buffer.resize(default_cap, 0);
-            ReadTransport { read, buffer }
+            ReadTransport {
+                read,
+                buffer,
+                stats: SourceStatistics::new(),
+            }
+        }
+
+        /// Gives the statistics of the sources of the packets read so far.
+        pub fn source_statistics(&self) -> &SourceStatistics {
+            &self.stats
         }
 
         /// Tries to read a navmon packet.
@@ -84,6 +119,7 @@ pub mod transport {
                     return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
                 }
             };
+            self.stats.update(&frame);
             Ok(Some(frame))
         }
     }
@@ -107,6 +143,35 @@ pub mod transport {
             WriteTransport { write, buffer }
         }
 
+        /// Tries to write a navmon packet annotated with OSNMA authentication
+        /// results.
+        ///
+        /// If the packet contains a Galileo INAV page, the authentication
+        /// status given by [`osnma_authentication`](super::osnma_authentication)
+        /// for the satellite that transmitted the page is attached to the
+        /// `GalileoInav` message before writing the packet. Other packets are
+        /// written unmodified.
+        ///
+        /// If the write is successful, the number of bytes written is returned.
+        pub fn write_authenticated<S: Storage, V: Verifier>(
+            &mut self,
+            packet: &NavMonMessage,
+            osnma: &Osnma<S, V>,
+        ) -> std::io::Result<usize> {
+            let svn = packet
+                .gi
+                .as_ref()
+                .and_then(|gi| Svn::try_from(gi.gnss_sv).ok());
+            let Some(svn) = svn else {
+                return self.write_packet(packet);
+            };
+            let mut packet = packet.clone();
+            if let Some(gi) = &mut packet.gi {
+                gi.osnma_auth = Some(super::osnma_authentication(osnma, svn));
+            }
+            self.write_packet(&packet)
+        }
+
         /// Tries to write a navmon packet.
         ///
         /// If the write is successful, the number of bytes writte is returned.
@@ -155,6 +220,23 @@ pub mod transport {
             }
         }
 
+        #[test]
+        fn source_statistics() {
+            let packets = &data::GALMON_PACKETS[..];
+            let mut transport = ReadTransport::new(packets);
+            while transport.read_packet().unwrap().is_some() {}
+            let stats = transport.source_statistics();
+            let total: u64 = stats.iter().map(|(_, s)| s.packets()).sum();
+            assert_eq!(total, 17);
+            for (_, source) in stats.iter() {
+                assert!(source.inav_packets() <= source.packets());
+                if source.inav_packets() > 0 {
+                    assert!(source.skew_min() <= source.skew_mean());
+                    assert!(source.skew_mean() <= source.skew_max());
+                }
+            }
+        }
+
         #[test]
         fn bad_magic() {
             let packets = &data::GALMON_PACKETS[2..];
@@ -184,5 +266,407 @@ pub mod transport {
             assert_eq!(&write.write, packets);
             assert_eq!(total_size, packets.len());
         }
+
+        #[test]
+        fn write_authenticated() {
+            use crate::storage::SmallStorage;
+            use crate::PublicKey;
+            use p256::ecdsa::VerifyingKey;
+
+            let pubkey = [
+                3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33, 117, 116, 91, 202, 57, 34, 72,
+                200, 202, 10, 169, 253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218,
+            ];
+            let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+            let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+            let osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+
+            let packets = &data::GALMON_PACKETS[..];
+            let mut read = ReadTransport::new(packets);
+            let mut write = WriteTransport::new(Vec::new());
+            while let Some(packet) = read.read_packet().unwrap() {
+                write.write_authenticated(&packet, &osnma).unwrap();
+            }
+            let buffer = write.write;
+            let mut read_back = ReadTransport::new(&buffer[..]);
+            let mut inav_packets = 0;
+            while let Some(packet) = read_back.read_packet().unwrap() {
+                if let Some(gi) = &packet.gi {
+                    // Nothing has been authenticated, so all the annotation
+                    // fields are empty
+                    assert_eq!(gi.osnma_auth, Some(Default::default()));
+                    inav_packets += 1;
+                }
+            }
+            assert!(inav_packets > 0);
+        }
+    }
+}
+
+pub mod stats {
+    //! Galmon source statistics.
+    //!
+    //! Aggregated Galmon feeds contain packets from many stations, which are
+    //! identified by the `source_id` field of each navmon packet. A station
+    //! whose clock is wrong, or whose packets arrive late, can disturb the
+    //! collection of subframes of the other stations. The [`SourceStatistics`]
+    //! struct keeps per-station statistics that help to identify these
+    //! stations.
+    use super::navmon::NavMonMessage;
+    use crate::timing::gst_to_unix;
+    use crate::{Gst, Wn};
+    use std::collections::BTreeMap;
+
+    /// Statistics of the packets of a Galmon source.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SourceStats {
+        packets: u64,
+        inav_packets: u64,
+        last_seen: f64,
+        last_gst: Option<Gst>,
+        skew_sum: f64,
+        skew_min: f64,
+        skew_max: f64,
+        skew_last: f64,
+    }
+
+    impl SourceStats {
+        fn new() -> SourceStats {
+            SourceStats {
+                packets: 0,
+                inav_packets: 0,
+                last_seen: 0.0,
+                last_gst: None,
+                skew_sum: 0.0,
+                skew_min: f64::INFINITY,
+                skew_max: f64::NEG_INFINITY,
+                skew_last: 0.0,
+            }
+        }
+
+        /// Gives the number of packets received from this source.
+        pub fn packets(&self) -> u64 {
+            self.packets
+        }
+
+        /// Gives the number of Galileo INAV packets received from this source.
+        pub fn inav_packets(&self) -> u64 {
+            self.inav_packets
+        }
+
+        /// Gives the local UTC timestamp of the last packet received from this
+        /// source, as seconds since the Unix epoch.
+        pub fn last_seen(&self) -> f64 {
+            self.last_seen
+        }
+
+        /// Gives the GST of the last Galileo INAV packet received from this
+        /// source.
+        pub fn last_gst(&self) -> Option<Gst> {
+            self.last_gst
+        }
+
+        /// Gives the timestamp skew of the last Galileo INAV packet, in seconds.
+        ///
+        /// The timestamp skew is the difference between the local UTC
+        /// timestamp of the packet and the GST of the INAV page, both
+        /// converted to seconds since the Unix epoch. The GST is converted
+        /// without applying the GST-UTC offset, so the skew of a station with a
+        /// correct clock is the latency of the station minus the current
+        /// number of leap seconds (18 seconds since 2017).
+        ///
+        /// This returns `None` if no Galileo INAV packets have been received.
+        pub fn skew(&self) -> Option<f64> {
+            (self.inav_packets > 0).then_some(self.skew_last)
+        }
+
+        /// Gives the mean timestamp skew of the Galileo INAV packets, in
+        /// seconds.
+        ///
+        /// See [`SourceStats::skew`].
+        pub fn skew_mean(&self) -> Option<f64> {
+            (self.inav_packets > 0).then(|| self.skew_sum / self.inav_packets as f64)
+        }
+
+        /// Gives the minimum timestamp skew of the Galileo INAV packets, in
+        /// seconds.
+        ///
+        /// See [`SourceStats::skew`].
+        pub fn skew_min(&self) -> Option<f64> {
+            (self.inav_packets > 0).then_some(self.skew_min)
+        }
+
+        /// Gives the maximum timestamp skew of the Galileo INAV packets, in
+        /// seconds.
+        ///
+        /// See [`SourceStats::skew`].
+        pub fn skew_max(&self) -> Option<f64> {
+            (self.inav_packets > 0).then_some(self.skew_max)
+        }
+
+        fn update(&mut self, packet: &NavMonMessage) {
+            self.packets += 1;
+            self.last_seen =
+                packet.local_utc_seconds as f64 + packet.local_utc_nanoseconds as f64 * 1e-9;
+            let Some(inav) = &packet.gi else {
+                return;
+            };
+            // This is needed because sometimes we can see a TOW of 604801
+            let secs_in_week = 604800;
+            let Ok(wn) = Wn::try_from(inav.gnss_wn + inav.gnss_tow / secs_in_week) else {
+                return;
+            };
+            let gst = Gst::new(wn, inav.gnss_tow % secs_in_week);
+            let skew = self.last_seen - gst_to_unix(gst) as f64;
+            self.inav_packets += 1;
+            self.last_gst = Some(gst);
+            self.skew_last = skew;
+            self.skew_sum += skew;
+            self.skew_min = self.skew_min.min(skew);
+            self.skew_max = self.skew_max.max(skew);
+        }
+    }
+
+    /// Statistics of the sources of a Galmon feed.
+    ///
+    /// This keeps a [`SourceStats`] for each `source_id` seen in the navmon
+    /// packets. The statistics of the packets read by a
+    /// [`ReadTransport`](super::transport::ReadTransport) can be obtained with
+    /// [`ReadTransport::source_statistics`](super::transport::ReadTransport::source_statistics).
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct SourceStatistics {
+        sources: BTreeMap<u64, SourceStats>,
+    }
+
+    impl SourceStatistics {
+        /// Constructs new, empty, source statistics.
+        pub fn new() -> SourceStatistics {
+            SourceStatistics::default()
+        }
+
+        /// Updates the statistics with a navmon packet.
+        pub fn update(&mut self, packet: &NavMonMessage) {
+            self.sources
+                .entry(packet.source_id)
+                .or_insert_with(SourceStats::new)
+                .update(packet);
+        }
+
+        /// Gives the statistics of a source.
+        ///
+        /// This returns `None` if no packets from the source with ID
+        /// `source_id` have been seen.
+        pub fn get(&self, source_id: u64) -> Option<&SourceStats> {
+            self.sources.get(&source_id)
+        }
+
+        /// Returns an iterator over the source IDs and their statistics, in
+        /// increasing order of source ID.
+        pub fn iter(&self) -> impl Iterator<Item = (u64, &SourceStats)> {
+            self.sources.iter().map(|(&id, stats)| (id, stats))
+        }
+    }
+}
+
+/// Gives the OSNMA authentication status of the navigation data of a
+/// satellite.
+///
+/// The status is obtained from the most recent authenticated CED and health
+/// status data and timing parameters for the satellite with SVN `svn` in
+/// `osnma`. This is the annotation attached to the packets written with
+/// [`WriteTransport::write_authenticated`](transport::WriteTransport::write_authenticated).
+pub fn osnma_authentication<S: Storage, V: Verifier>(
+    osnma: &Osnma<S, V>,
+    svn: Svn,
+) -> OsnmaAuthentication {
+    let ced = osnma.get_ced_and_status(svn);
+    let timing = osnma.get_timing_parameters(svn);
+    OsnmaAuthentication {
+        ced_authbits: ced.map(|data| data.authbits().into()),
+        ced_wn: ced.map(|data| data.gst().wn().into()),
+        ced_tow: ced.map(|data| data.gst().tow()),
+        timing_authbits: timing.map(|data| data.authbits().into()),
+        timing_wn: timing.map(|data| data.gst().wn().into()),
+        timing_tow: timing.map(|data| data.gst().tow()),
+    }
+}
+
+/// Preprocessing of Galmon INAV data.
+///
+/// This keeps the state required to fix some problems with the Galmon data
+/// before feeding it into the OSNMA black box. It is used by
+/// [`Osnma::feed_galmon`](crate::Osnma::feed_galmon), and it can also be used
+/// to obtain the INAV pages contained in navmon packets, for instance to
+/// archive them.
+#[derive(Debug, Clone, Default)]
+pub struct GalmonState {
+    current_subframe: Option<Gst>,
+    last_tow_mod_30: u32,
+}
+
+impl GalmonState {
+    /// Constructs a new Galmon preprocessing state.
+    pub fn new() -> GalmonState {
+        GalmonState::default()
+    }
+
+    /// Extracts the INAV page contained in a navmon packet.
+    ///
+    /// Returns `None` if the packet does not contain a Galileo INAV page or if
+    /// the page should be discarded.
+    pub fn process<'a>(&mut self, packet: &'a NavMonMessage) -> Option<InavPage<'a>> {
+        let inav @ GalileoInav {
+            contents: inav_word,
+            reserved1: osnma_data,
+            sigid: Some(sigid),
+            ..
+        } = packet.gi.as_ref()?
+        else {
+            return None;
+        };
+
+        // This is needed because sometimes we can see a TOW of 604801
+        let secs_in_week = 604800;
+        let mut tow = inav.gnss_tow % secs_in_week;
+        let Ok(wn) = Wn::try_from(inav.gnss_wn + inav.gnss_tow / secs_in_week) else {
+            log::error!("invalid Galmon week number {}", inav.gnss_wn);
+            return None;
+        };
+
+        // Fix bug in Galmon data:
+        //
+        // Often, the E1B word 16 starting at TOW = 29 mod 30 will have the
+        // TOW of the previous word 16 in the subframe, which starts at TOW
+        // = 15 mod 30. We detect this condition by looking at the last tow
+        // mod 30 that we saw and fixing if needed.
+        if tow % 30 == 15 && self.last_tow_mod_30 >= 19 {
+            log::debug!(
+                "fixing wrong TOW for SVN {}; tow = {}, last tow mod 30 = {}",
+                inav.gnss_sv,
+                tow,
+                self.last_tow_mod_30
+            );
+            tow += 29 - 15; // wn rollover is not possible by this addition
+        }
+        self.last_tow_mod_30 = tow % 30;
+
+        let band = match sigid {
+            1 => InavBand::E1B,
+            5 => InavBand::E5B,
+            _ => {
+                log::error!("INAV word received on non-INAV band: sigid = {}", sigid);
+                return None;
+            }
+        };
+
+        let gst = Gst::new(wn, tow);
+        let subframe = band.e1b_page_gst(gst).gst_subframe();
+        if let Some(current) = self.current_subframe {
+            if current > subframe {
+                // Avoid processing INAV words that are in a previous subframe
+                log::warn!(
+                    "dropping INAV word from previous subframe (current subframe {:?}, \
+                     this INAV word {:?} SVN {} band {})",
+                    current,
+                    gst,
+                    inav.gnss_sv,
+                    sigid
+                );
+                return None;
+            }
+        }
+        self.current_subframe = Some(subframe);
+        let Ok(svn) = Svn::try_from(inav.gnss_sv) else {
+            log::error!("invalid Galmon SVN {}", inav.gnss_sv);
+            return None;
+        };
+        let Ok(word) = <&InavWord>::try_from(&inav_word[..]) else {
+            log::error!("INAV word has wrong length {}", inav_word.len());
+            return None;
+        };
+
+        // The OSNMA SIS ICD says that OSNMA is not provided in INAV Dummy
+        // Messages or Alert Pages. The OSNMA field in these pages may not
+        // contain all zeros, but is invalid and should be discarded.
+        //
+        // Here we drop INAV words that are Dummy Messages. There is no way
+        // for us to filter for Alert Pages in Galmon data (the page type
+        // bit is not present), so hopefully these pages don't make it here.
+        if InavWordType::of(word) == InavWordType::Dummy {
+            log::debug!(
+                "discarding dummy INAV word from {} {:?} at {:?}",
+                svn,
+                band,
+                gst
+            );
+            return None;
+        }
+
+        let osnma = match osnma_data {
+            Some(osnma_data) => match <&OsnmaDataMessage>::try_from(&osnma_data[..]) {
+                Ok(osnma) => Some(osnma),
+                Err(_) => {
+                    log::error!("OSNMA data has wrong length {}", osnma_data.len());
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Some(InavPage {
+            word,
+            osnma,
+            svn,
+            gst,
+            band,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn packet(tow: u32, sigid: u32, word_type: u8) -> NavMonMessage {
+        let mut contents = vec![0; 16];
+        contents[0] = word_type << 2;
+        NavMonMessage {
+            gi: Some(GalileoInav {
+                gnss_wn: 1177,
+                gnss_tow: tow,
+                gnss_sv: 12,
+                contents,
+                sigid: Some(sigid),
+                reserved1: Some(vec![0x52, 0, 0, 0, 0]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn process() {
+        let mut state = GalmonState::new();
+        let p = packet(175789, 1, 2);
+        let page = state.process(&p).unwrap();
+        assert_eq!(page.svn, Svn::try_from(12).unwrap());
+        assert_eq!(page.gst, Gst::new(1177, 175789));
+        assert_eq!(page.band, InavBand::E1B);
+        assert_eq!(page.osnma, Some(&[0x52, 0, 0, 0, 0]));
+        // TOW of word 16 at 29 mod 30 is fixed
+        let p = packet(175785, 1, 16);
+        let page = state.process(&p).unwrap();
+        assert_eq!(page.gst, Gst::new(1177, 175799));
+        // Word from a previous subframe is dropped
+        assert!(state.process(&packet(175769, 5, 2)).is_none());
+        // Dummy word is discarded
+        assert!(state.process(&packet(175801, 5, 63)).is_none());
+        // Non-INAV band
+        assert!(state.process(&packet(175803, 2, 2)).is_none());
+        // TOW of 604801
+        let p = packet(604801, 5, 2);
+        let page = state.process(&p).unwrap();
+        assert_eq!(page.gst, Gst::new(1178, 1));
+        assert_eq!(page.band, InavBand::E5B);
     }
 }
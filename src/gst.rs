@@ -1,3 +1,6 @@
+use core::cmp::Ordering;
+use core::fmt;
+
 /// Galileo week number.
 pub type Wn = u16;
 /// Time of week.
@@ -8,10 +11,49 @@ pub type Tow = u32;
 const SECS_IN_WEEK: Tow = 24 * 3600 * 7;
 const SECS_PER_SUBFRAME: Tow = 30;
 
+/// Number of weeks after which the Galileo week number rolls over.
+///
+/// The week number is broadcast in the INAV message using 12 bits, so it
+/// rolls over to zero after week 4095.
+pub const WN_ROLLOVER: Wn = 4096;
+
+/// Policy for the rollover of the week number in GST arithmetic.
+///
+/// See [`Gst::checked_add_seconds`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum WnRollover {
+    /// The week number wraps around modulo [`WN_ROLLOVER`], as the week
+    /// number broadcast in the INAV message.
+    ///
+    /// This is the policy used by the OSNMA black box to process the GSTs of
+    /// the data that is fed.
+    #[default]
+    Wrap,
+    /// The week number does not wrap around.
+    ///
+    /// The arithmetic fails if the week number goes below zero or above
+    /// `Wn::MAX`. This is useful for week numbers that have been extended
+    /// beyond the 12 bits broadcast, for instance by counting the rollovers.
+    ///
+    /// This is the policy used by [`Gst::add_seconds`] and
+    /// [`Gst::add_subframes`], which panic instead of failing.
+    Extended,
+}
+
 /// GST (Galileo System Time)
 ///
 /// The Galileo System Time, stored as a week number and a time of week.
 ///
+/// The week number broadcast in the INAV message rolls over after
+/// [`WN_ROLLOVER`] weeks. [`Gst::checked_add_seconds`] can wrap the week
+/// number as specified by a [`WnRollover`] policy, and
+/// [`Gst::difference_seconds`] and [`Gst::cmp_rollover`] give the shortest
+/// difference modulo the rollover period. The `Ord` implementation compares
+/// the week number and time of week lexicographically without considering
+/// the rollover. GSTs that can be on different sides of a rollover must be
+/// compared with [`Gst::cmp_rollover`], [`Gst::is_after`] or
+/// [`Gst::is_before`] instead.
+///
 /// # Examples
 /// ```
 /// use galileo_osnma::Gst;
@@ -26,6 +68,13 @@ pub struct Gst {
     tow: Tow,
 }
 
+/// Formats a GST as `WN=<week number> TOW=<time of week>`.
+impl fmt::Display for Gst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WN={} TOW={}", self.wn, self.tow)
+    }
+}
+
 impl Gst {
     /// Constructs a new GST from a week number and TOW.
     ///
@@ -52,6 +101,14 @@ impl Gst {
     ///
     /// The GST corresponding to the sum is returned.
     ///
+    /// The week number does not wrap around after [`WN_ROLLOVER`] weeks. Use
+    /// [`Gst::checked_add_seconds`] with [`WnRollover::Wrap`] for arithmetic
+    /// across a rollover.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the week number of the sum is out of range.
+    ///
     /// # Examples
     ///
     /// ```
@@ -65,22 +122,72 @@ impl Gst {
     /// assert_eq!(gst_next_page.add_seconds(-2), gst);
     /// ```
     pub fn add_seconds(&self, seconds: i32) -> Self {
-        let secs_in_week = SECS_IN_WEEK.try_into().unwrap();
-        let weeks = seconds / secs_in_week;
-        let seconds = seconds - weeks * secs_in_week;
-        let mut tow = i32::try_from(self.tow).unwrap() + seconds;
-        let mut wn = self.wn + u16::try_from(weeks).unwrap();
-        if tow < 0 {
-            wn -= 1;
-            tow += secs_in_week;
-        } else if tow >= secs_in_week {
-            wn += 1;
-            tow -= secs_in_week;
+        self.checked_add_seconds(seconds.into(), WnRollover::Extended)
+            .expect("week number out of range")
+    }
+
+    // Same as add_seconds, but the week number wraps around after
+    // WN_ROLLOVER weeks.
+    pub(crate) fn wrapping_add_seconds(&self, seconds: i32) -> Self {
+        self.checked_add_seconds(seconds.into(), WnRollover::Wrap)
+            .unwrap()
+    }
+
+    /// Adds `seconds` seconds to the GST using a rollover policy.
+    ///
+    /// The GST corresponding to the sum is returned. With
+    /// [`WnRollover::Wrap`], the week number of the result is normalized (see
+    /// [`Gst::normalize`]). With [`WnRollover::Extended`], this returns `None`
+    /// if the week number of the result is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, WnRollover};
+    ///
+    /// let gst = Gst::new(4095, 604770);
+    /// assert_eq!(
+    ///     gst.checked_add_seconds(60, WnRollover::Wrap),
+    ///     Some(Gst::new(0, 30))
+    /// );
+    /// assert_eq!(
+    ///     gst.checked_add_seconds(60, WnRollover::Extended),
+    ///     Some(Gst::new(4096, 30))
+    /// );
+    /// assert_eq!(Gst::new(0, 0).checked_add_seconds(-1, WnRollover::Extended), None);
+    /// ```
+    pub fn checked_add_seconds(&self, seconds: i64, rollover: WnRollover) -> Option<Self> {
+        // The computation is done in i64 to handle arbitrary week numbers and
+        // offsets (including offsets of more than one week backwards) without
+        // overflow.
+        let secs_in_week = i64::from(SECS_IN_WEEK);
+        let total =
+            (i64::from(self.wn) * secs_in_week + i64::from(self.tow)).checked_add(seconds)?;
+        let wn = total.div_euclid(secs_in_week);
+        let wn = match rollover {
+            WnRollover::Wrap => wn.rem_euclid(WN_ROLLOVER.into()),
+            WnRollover::Extended => wn,
         };
-        assert!((0..secs_in_week).contains(&tow));
+        Some(Gst {
+            wn: wn.try_into().ok()?,
+            tow: total.rem_euclid(secs_in_week).try_into().unwrap(),
+        })
+    }
+
+    /// Returns the GST with the week number reduced modulo [`WN_ROLLOVER`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::Gst;
+    ///
+    /// assert_eq!(Gst::new(4097, 300).normalize(), Gst::new(1, 300));
+    /// assert_eq!(Gst::new(1177, 300).normalize(), Gst::new(1177, 300));
+    /// ```
+    pub fn normalize(&self) -> Self {
         Gst {
-            tow: tow.try_into().unwrap(),
-            wn,
+            wn: self.wn % WN_ROLLOVER,
+            tow: self.tow,
         }
     }
 
@@ -104,6 +211,12 @@ impl Gst {
         self.add_seconds(subframes * i32::try_from(SECS_PER_SUBFRAME).unwrap())
     }
 
+    // Same as add_subframes, but the week number wraps around after
+    // WN_ROLLOVER weeks.
+    pub(crate) fn wrapping_add_subframes(&self, subframes: i32) -> Self {
+        self.wrapping_add_seconds(subframes * i32::try_from(SECS_PER_SUBFRAME).unwrap())
+    }
+
     /// Returns the GST at the start of the subframe that contains `self`.
     ///
     /// The GST returned has the same week number as `self` and its time
@@ -143,14 +256,179 @@ impl Gst {
         self.tow % SECS_PER_SUBFRAME == 0
     }
 
+    // Returns the difference in weeks between other and self, taking into
+    // account the week number rollover. The difference is in the range
+    // [-WN_ROLLOVER / 2, WN_ROLLOVER / 2).
+    fn weeks_difference(&self, other: Gst) -> i32 {
+        let rollover = i32::from(WN_ROLLOVER);
+        (i32::from(self.wn) - i32::from(other.wn) + rollover / 2).rem_euclid(rollover)
+            - rollover / 2
+    }
+
+    /// Returns the number of GST seconds elapsed between `other` and `self`.
+    ///
+    /// The week number rollover is taken into account, so the difference is
+    /// the shortest one modulo [`WN_ROLLOVER`] weeks. The result is positive
+    /// if `self` is after `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::Gst;
+    ///
+    /// let gst = Gst::new(1177, 175767);
+    /// assert_eq!(gst.add_seconds(60).difference_seconds(gst), 60);
+    /// assert_eq!(gst.difference_seconds(gst.add_seconds(60)), -60);
+    ///
+    /// let before_rollover = Gst::new(4095, 604770);
+    /// let after_rollover = Gst::new(0, 30);
+    /// assert_eq!(after_rollover.difference_seconds(before_rollover), 60);
+    /// ```
+    pub fn difference_seconds(&self, other: Gst) -> i64 {
+        i64::from(self.weeks_difference(other)) * i64::from(SECS_IN_WEEK)
+            + (i64::from(self.tow) - i64::from(other.tow))
+    }
+
     /// Returns the difference in subframes between `other` and `self`.
     ///
     /// The returned value is equal to the number of GST seconds elapsed between
-    /// `self` and `other`, divided by 30.
+    /// `self` and `other`, divided by 30. The week number rollover is taken
+    /// into account as in [`Gst::difference_seconds`].
     pub fn subframes_difference(&self, other: Gst) -> i32 {
-        (i32::from(self.wn) - i32::from(other.wn))
-            * i32::try_from(SECS_IN_WEEK / SECS_PER_SUBFRAME).unwrap()
+        self.weeks_difference(other) * i32::try_from(SECS_IN_WEEK / SECS_PER_SUBFRAME).unwrap()
             + (i32::try_from(self.tow).unwrap() - i32::try_from(other.tow).unwrap())
                 / i32::try_from(SECS_PER_SUBFRAME).unwrap()
     }
+
+    /// Compares two GSTs taking into account the week number rollover.
+    ///
+    /// This gives the sign of [`Gst::difference_seconds`], so a GST shortly
+    /// after a rollover is considered greater than a GST shortly before it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::cmp::Ordering;
+    /// use galileo_osnma::Gst;
+    ///
+    /// let before_rollover = Gst::new(4095, 604770);
+    /// let after_rollover = Gst::new(0, 30);
+    /// assert_eq!(after_rollover.cmp_rollover(&before_rollover), Ordering::Greater);
+    /// ```
+    pub fn cmp_rollover(&self, other: &Gst) -> Ordering {
+        self.difference_seconds(*other).cmp(&0)
+    }
+
+    /// Returns `true` if `self` is after `other`, taking into account the week
+    /// number rollover.
+    ///
+    /// See [`Gst::cmp_rollover`].
+    pub fn is_after(&self, other: Gst) -> bool {
+        self.cmp_rollover(&other) == Ordering::Greater
+    }
+
+    /// Returns `true` if `self` is before `other`, taking into account the
+    /// week number rollover.
+    ///
+    /// See [`Gst::cmp_rollover`].
+    pub fn is_before(&self, other: Gst) -> bool {
+        self.cmp_rollover(&other) == Ordering::Less
+    }
+
+    /// Gives the latest of two GSTs, taking into account the week number
+    /// rollover.
+    ///
+    /// See [`Gst::cmp_rollover`].
+    pub fn latest(self, other: Gst) -> Gst {
+        if other.is_after(self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(Gst::new(1176, 121080).to_string(), "WN=1176 TOW=121080");
+    }
+
+    #[test]
+    fn week_rollover() {
+        let end_of_week = Gst::new(1234, SECS_IN_WEEK - 30);
+        let next_week = end_of_week.add_subframes(1);
+        assert_eq!(next_week, Gst::new(1235, 0));
+        assert_eq!(next_week.add_subframes(-1), end_of_week);
+        assert_eq!(next_week.subframes_difference(end_of_week), 1);
+        assert_eq!(end_of_week.subframes_difference(next_week), -1);
+        assert!(next_week > end_of_week);
+
+        assert_eq!(
+            Gst::new(1234, 1).add_seconds(-2),
+            Gst::new(1233, SECS_IN_WEEK - 1)
+        );
+        assert_eq!(
+            Gst::new(1234, SECS_IN_WEEK - 1).add_seconds(2),
+            Gst::new(1235, 1)
+        );
+
+        // Offsets larger than one week
+        let gst = Gst::new(1234, 300);
+        let secs_in_week = i32::try_from(SECS_IN_WEEK).unwrap();
+        assert_eq!(gst.add_seconds(secs_in_week), Gst::new(1235, 300));
+        assert_eq!(gst.add_seconds(-secs_in_week), Gst::new(1233, 300));
+        assert_eq!(
+            gst.add_seconds(-secs_in_week - 301),
+            Gst::new(1232, SECS_IN_WEEK - 1)
+        );
+        assert_eq!(
+            gst.add_seconds(-2 * secs_in_week)
+                .add_seconds(2 * secs_in_week),
+            gst
+        );
+    }
+
+    #[test]
+    fn wn_rollover() {
+        let last_subframe = Gst::new(WN_ROLLOVER - 1, SECS_IN_WEEK - 30);
+        let first_subframe = last_subframe.wrapping_add_subframes(1);
+        assert_eq!(first_subframe, Gst::new(0, 0));
+        assert_eq!(first_subframe.wrapping_add_subframes(-1), last_subframe);
+        // add_subframes does not wrap
+        assert_eq!(last_subframe.add_subframes(1), Gst::new(WN_ROLLOVER, 0));
+        assert_eq!(first_subframe.subframes_difference(last_subframe), 1);
+        assert_eq!(last_subframe.subframes_difference(first_subframe), -1);
+        assert_eq!(first_subframe.difference_seconds(last_subframe), 30);
+        assert!(first_subframe.is_after(last_subframe));
+        assert!(last_subframe.is_before(first_subframe));
+        assert_eq!(first_subframe.latest(last_subframe), first_subframe);
+        assert_eq!(last_subframe.latest(first_subframe), first_subframe);
+        assert_eq!(
+            first_subframe.cmp_rollover(&first_subframe),
+            Ordering::Equal
+        );
+
+        // Differences are taken modulo the rollover period
+        let gst = Gst::new(100, 0);
+        assert_eq!(gst.subframes_difference(Gst::new(4000, 0)), 196 * 20160);
+        assert_eq!(Gst::new(WN_ROLLOVER + 100, 0).difference_seconds(gst), 0);
+
+        assert_eq!(
+            last_subframe.checked_add_seconds(30, WnRollover::Extended),
+            Some(Gst::new(WN_ROLLOVER, 0))
+        );
+        assert_eq!(
+            Gst::new(Wn::MAX, SECS_IN_WEEK - 1).checked_add_seconds(1, WnRollover::Extended),
+            None
+        );
+        assert_eq!(
+            Gst::new(0, 0).checked_add_seconds(-1, WnRollover::Wrap),
+            Some(Gst::new(WN_ROLLOVER - 1, SECS_IN_WEEK - 1))
+        );
+        assert_eq!(Gst::new(4097, 300).normalize(), Gst::new(1, 300));
+    }
 }
@@ -0,0 +1,367 @@
+//! GST sanity checks.
+//!
+//! Some GNSS receivers occasionally give a wrong GST for the data they
+//! produce, for instance jumping back tens of seconds for a short while.
+//! Feeding this data into the OSNMA black box disturbs the collection of
+//! subframes and navigation data, since these assume that the data is fed in
+//! chronological order.
+//!
+//! This module contains the [`GstGuard`], which tracks the GST of the data fed
+//! and rejects the data whose GST regresses or jumps forward unrealistically
+//! with respect to the latest accepted GST. The limits are defined by a
+//! [`GstGuardPolicy`]. A GST that is outside the limits is quarantined: the
+//! data is rejected, but if the data that follows is consistent with the new
+//! GST for some time, the new GST is accepted. This lets the guard follow
+//! legitimate jumps, such as when the receiver corrects its time or resumes
+//! tracking after an outage, while rejecting short glitches.
+//!
+//! The guard is disabled by default. It can be enabled with
+//! [`Osnma::set_gst_guard`](crate::Osnma::set_gst_guard), which enables it
+//! both in the [`CollectSubframe`](crate::subframe::CollectSubframe) and the
+//! [`CollectNavMessage`](crate::navmessage::CollectNavMessage) of the black
+//! box. Each rejected input is reported as a
+//! [`DiagnosticKind::GstRejected`](crate::diagnostics::DiagnosticKind::GstRejected)
+//! diagnostic and counted in the [`GstRejectionStats`].
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::gstguard::{GstGuard, GstGuardPolicy, GstRejection};
+//! use galileo_osnma::Gst;
+//!
+//! let mut guard = GstGuard::new(GstGuardPolicy::DEFAULT);
+//! let gst = Gst::new(1177, 175767);
+//! assert_eq!(guard.check(gst), Ok(()));
+//! assert_eq!(guard.check(gst.add_seconds(2)), Ok(()));
+//! // The receiver jumps back 40 seconds
+//! assert_eq!(guard.check(gst.add_seconds(-40)), Err(GstRejection::Regression));
+//! assert_eq!(guard.check(gst.add_seconds(4)), Ok(()));
+//! assert_eq!(guard.stats().regressions(), 1);
+//! ```
+
+use crate::Gst;
+use core::fmt;
+
+/// Policy of a [`GstGuard`].
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::gstguard::GstGuardPolicy;
+///
+/// // Allow forward jumps of up to 10 minutes
+/// let policy = GstGuardPolicy::DEFAULT.with_max_jump(600);
+/// assert_eq!(policy.max_jump(), 600);
+/// assert_eq!(policy.max_regression(), 4);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct GstGuardPolicy {
+    max_regression: u32,
+    max_jump: u32,
+    confirmation: u32,
+}
+
+impl GstGuardPolicy {
+    /// Default policy.
+    ///
+    /// The GST can regress by up to 4 seconds, which covers pages of the same
+    /// epoch fed in any order and the 1 second offset of the E5b-I page
+    /// timing. The GST can jump forward by up to 120 seconds. A new GST is
+    /// accepted after 30 seconds of data consistent with it.
+    pub const DEFAULT: GstGuardPolicy = GstGuardPolicy {
+        max_regression: 4,
+        max_jump: 120,
+        confirmation: 30,
+    };
+
+    /// Returns a policy with a different maximum regression.
+    ///
+    /// Data whose GST is more than `seconds` seconds earlier than the latest
+    /// accepted GST is rejected.
+    pub const fn with_max_regression(self, seconds: u32) -> GstGuardPolicy {
+        GstGuardPolicy {
+            max_regression: seconds,
+            ..self
+        }
+    }
+
+    /// Returns a policy with a different maximum jump.
+    ///
+    /// Data whose GST is more than `seconds` seconds later than the latest
+    /// accepted GST is rejected.
+    pub const fn with_max_jump(self, seconds: u32) -> GstGuardPolicy {
+        GstGuardPolicy {
+            max_jump: seconds,
+            ..self
+        }
+    }
+
+    /// Returns a policy with a different confirmation time.
+    ///
+    /// A quarantined GST is accepted once the data that follows it has been
+    /// consistent with it for `seconds` seconds. A value of zero means that
+    /// the data is rejected only once, and the GST is accepted with the next
+    /// consistent data.
+    pub const fn with_confirmation(self, seconds: u32) -> GstGuardPolicy {
+        GstGuardPolicy {
+            confirmation: seconds,
+            ..self
+        }
+    }
+
+    /// Gives the maximum regression in seconds.
+    pub fn max_regression(&self) -> u32 {
+        self.max_regression
+    }
+
+    /// Gives the maximum forward jump in seconds.
+    pub fn max_jump(&self) -> u32 {
+        self.max_jump
+    }
+
+    /// Gives the confirmation time in seconds.
+    pub fn confirmation(&self) -> u32 {
+        self.confirmation
+    }
+
+    // Returns an error if gst is not consistent with reference.
+    fn check(&self, gst: Gst, reference: Gst) -> Result<(), GstRejection> {
+        let elapsed = gst.difference_seconds(reference);
+        if elapsed < -i64::from(self.max_regression) {
+            Err(GstRejection::Regression)
+        } else if elapsed > i64::from(self.max_jump) {
+            Err(GstRejection::Jump)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for GstGuardPolicy {
+    fn default() -> GstGuardPolicy {
+        GstGuardPolicy::DEFAULT
+    }
+}
+
+/// Reason why the GST of some data has been rejected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GstRejection {
+    /// The GST is earlier than the latest accepted GST by more than the
+    /// maximum regression.
+    Regression,
+    /// The GST is later than the latest accepted GST by more than the maximum
+    /// jump.
+    Jump,
+}
+
+impl fmt::Display for GstRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GstRejection::Regression => "GST regression".fmt(f),
+            GstRejection::Jump => "GST jump".fmt(f),
+        }
+    }
+}
+
+/// Statistics of the data rejected by a [`GstGuard`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct GstRejectionStats {
+    regressions: u32,
+    jumps: u32,
+    accepted_jumps: u32,
+}
+
+impl GstRejectionStats {
+    /// Returns the number of inputs rejected because their GST regressed.
+    pub fn regressions(&self) -> u32 {
+        self.regressions
+    }
+
+    /// Returns the number of inputs rejected because their GST jumped forward.
+    pub fn jumps(&self) -> u32 {
+        self.jumps
+    }
+
+    /// Returns the total number of inputs rejected.
+    pub fn rejected(&self) -> u32 {
+        self.regressions.saturating_add(self.jumps)
+    }
+
+    /// Returns the number of times that a quarantined GST has been accepted.
+    pub fn accepted_jumps(&self) -> u32 {
+        self.accepted_jumps
+    }
+
+    pub(crate) fn merge(&self, other: &GstRejectionStats) -> GstRejectionStats {
+        GstRejectionStats {
+            regressions: self.regressions.saturating_add(other.regressions),
+            jumps: self.jumps.saturating_add(other.jumps),
+            accepted_jumps: self.accepted_jumps.saturating_add(other.accepted_jumps),
+        }
+    }
+}
+
+/// GST sanity guard.
+///
+/// The guard keeps the latest accepted GST, and checks the GST of new data
+/// against it according to a [`GstGuardPolicy`]. See the
+/// [module documentation](crate::gstguard).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GstGuard {
+    policy: GstGuardPolicy,
+    reference: Option<Gst>,
+    // First and latest GST of the quarantined data.
+    quarantine: Option<(Gst, Gst)>,
+    stats: GstRejectionStats,
+}
+
+impl GstGuard {
+    /// Constructs a new guard with the policy `policy`.
+    ///
+    /// The first GST checked by the guard is always accepted.
+    pub fn new(policy: GstGuardPolicy) -> GstGuard {
+        GstGuard {
+            policy,
+            reference: None,
+            quarantine: None,
+            stats: GstRejectionStats::default(),
+        }
+    }
+
+    /// Gives the policy of the guard.
+    pub fn policy(&self) -> GstGuardPolicy {
+        self.policy
+    }
+
+    /// Gives the latest accepted GST.
+    pub fn latest(&self) -> Option<Gst> {
+        self.reference
+    }
+
+    /// Gives the statistics of the rejected data.
+    pub fn stats(&self) -> &GstRejectionStats {
+        &self.stats
+    }
+
+    /// Checks the GST of new data.
+    ///
+    /// If the GST is accepted, it becomes the latest accepted GST if it is
+    /// later than it. If the GST is rejected, the reason is returned, and the
+    /// data should be discarded.
+    pub fn check(&mut self, gst: Gst) -> Result<(), GstRejection> {
+        let Some(reference) = self.reference else {
+            self.reference = Some(gst);
+            return Ok(());
+        };
+        let Err(rejection) = self.policy.check(gst, reference) else {
+            self.reference = Some(reference.latest(gst));
+            self.quarantine = None;
+            return Ok(());
+        };
+        let (first, latest, confirmed) = match self.quarantine {
+            Some((first, latest)) if self.policy.check(gst, latest).is_ok() => {
+                let latest = latest.latest(gst);
+                let elapsed = latest.difference_seconds(first);
+                (
+                    first,
+                    latest,
+                    elapsed >= i64::from(self.policy.confirmation),
+                )
+            }
+            _ => (gst, gst, false),
+        };
+        if confirmed {
+            log::warn!(
+                "accepting GST {} after {} ({} was the latest accepted GST)",
+                gst,
+                rejection,
+                reference
+            );
+            self.reference = Some(latest);
+            self.quarantine = None;
+            self.stats.accepted_jumps = self.stats.accepted_jumps.saturating_add(1);
+            return Ok(());
+        }
+        log::debug!(
+            "rejecting GST {} due to {} (latest accepted GST is {})",
+            gst,
+            rejection,
+            reference
+        );
+        self.quarantine = Some((first, latest));
+        match rejection {
+            GstRejection::Regression => {
+                self.stats.regressions = self.stats.regressions.saturating_add(1)
+            }
+            GstRejection::Jump => self.stats.jumps = self.stats.jumps.saturating_add(1),
+        }
+        Err(rejection)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quarantine() {
+        let policy = GstGuardPolicy::DEFAULT.with_confirmation(10);
+        let mut guard = GstGuard::new(policy);
+        let gst = Gst::new(1177, 604790);
+        assert_eq!(guard.check(gst), Ok(()));
+        // Pages of the same epoch and E5b-I pages can come out of order
+        assert_eq!(guard.check(gst.add_seconds(2)), Ok(()));
+        assert_eq!(guard.check(gst.add_seconds(1)), Ok(()));
+        // Week rollover
+        assert_eq!(guard.check(gst.add_seconds(12)), Ok(()));
+        assert_eq!(guard.latest(), Some(Gst::new(1178, 2)));
+
+        // Glitch backwards
+        let gst = gst.add_seconds(12);
+        assert_eq!(
+            guard.check(gst.add_seconds(-40)),
+            Err(GstRejection::Regression)
+        );
+        assert_eq!(guard.check(gst.add_seconds(2)), Ok(()));
+
+        // Permanent jump forwards, accepted after 10 seconds
+        let jump = gst.add_seconds(3600);
+        for t in (0..10).step_by(2) {
+            assert_eq!(guard.check(jump.add_seconds(t)), Err(GstRejection::Jump));
+        }
+        assert_eq!(guard.check(jump.add_seconds(10)), Ok(()));
+        assert_eq!(guard.check(jump.add_seconds(12)), Ok(()));
+        assert_eq!(guard.latest(), Some(jump.add_seconds(12)));
+
+        assert_eq!(guard.stats().regressions(), 1);
+        assert_eq!(guard.stats().jumps(), 5);
+        assert_eq!(guard.stats().rejected(), 6);
+        assert_eq!(guard.stats().accepted_jumps(), 1);
+    }
+
+    #[test]
+    fn wn_rollover() {
+        let policy = GstGuardPolicy::DEFAULT.with_confirmation(10);
+        let mut guard = GstGuard::new(policy);
+        let gst = Gst::new(4095, 604798);
+        assert_eq!(guard.check(gst), Ok(()));
+        assert_eq!(guard.check(Gst::new(0, 0)), Ok(()));
+        // A late page from before the rollover does not move the latest GST
+        // backwards
+        assert_eq!(guard.check(gst.wrapping_add_seconds(1)), Ok(()));
+        assert_eq!(guard.latest(), Some(Gst::new(0, 0)));
+
+        // Permanent jump forwards before the rollover, confirmed after it
+        let mut guard = GstGuard::new(policy);
+        assert_eq!(guard.check(Gst::new(4095, 590000)), Ok(()));
+        let jump = Gst::new(4095, 604796);
+        for t in (0..10).step_by(2) {
+            assert_eq!(
+                guard.check(jump.wrapping_add_seconds(t)),
+                Err(GstRejection::Jump)
+            );
+        }
+        assert_eq!(guard.check(jump.wrapping_add_seconds(10)), Ok(()));
+        assert_eq!(guard.latest(), Some(Gst::new(0, 6)));
+    }
+}
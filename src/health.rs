@@ -0,0 +1,312 @@
+//! Satellite health.
+//!
+//! This module contains the [`SatelliteHealth`] struct, which decodes the
+//! signal health status (HS), data validity status (DVS) and signal-in-space
+//! accuracy (SISA) fields from the CED and health status data (ADKD=0 and 12).
+//! Since it is obtained with [`Osnma::get_health`](crate::Osnma::get_health)
+//! from authenticated data only, it can be used to decide whether a satellite
+//! can be used for an authenticated position fix.
+//!
+//! The meaning of these fields is described in the
+//! [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::{InavBand, Osnma, PublicKey, Svn};
+//! use galileo_osnma::storage::FullStorage;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!
+//! let usable = Svn::iter()
+//!     .filter(|&svn| {
+//!         osnma
+//!             .get_health(svn)
+//!             .is_some_and(|health| health.is_healthy(InavBand::E1B))
+//!     })
+//!     .count();
+//! assert_eq!(usable, 0);
+//! ```
+
+use crate::types::{BitSlice, InavBand};
+use bitvec::prelude::*;
+use core::fmt;
+
+// Location of the SISA field in the CED and health status data. It is in bits
+// 120..128 of word type 3.
+const SISA_START: usize = 240 + 120 - 6;
+// Location of the E5bHS field in the CED and health status data. The E5bHS,
+// E1BHS, E5bDVS and E1BDVS fields are in bits 67..73 of word type 5.
+const HS_DVS_START: usize = 482 + 67 - 6;
+
+/// Signal health status.
+///
+/// This is the value of the E1BHS and E5bHS fields.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SignalHealth {
+    /// Signal OK (value 0).
+    Ok,
+    /// Signal out of service (value 1).
+    OutOfService,
+    /// Signal will be out of service (value 2).
+    WillBeOutOfService,
+    /// Signal component currently in test (value 3).
+    InTest,
+}
+
+impl From<u8> for SignalHealth {
+    fn from(value: u8) -> SignalHealth {
+        match value & 3 {
+            0 => SignalHealth::Ok,
+            1 => SignalHealth::OutOfService,
+            2 => SignalHealth::WillBeOutOfService,
+            _ => SignalHealth::InTest,
+        }
+    }
+}
+
+impl fmt::Display for SignalHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalHealth::Ok => "signal OK".fmt(f),
+            SignalHealth::OutOfService => "signal out of service".fmt(f),
+            SignalHealth::WillBeOutOfService => "signal will be out of service".fmt(f),
+            SignalHealth::InTest => "signal component currently in test".fmt(f),
+        }
+    }
+}
+
+/// Data validity status.
+///
+/// This is the value of the E1BDVS and E5bDVS fields.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DataValidity {
+    /// Navigation data valid (value 0).
+    Valid,
+    /// Working without guarantee (value 1).
+    WithoutGuarantee,
+}
+
+impl From<bool> for DataValidity {
+    fn from(value: bool) -> DataValidity {
+        if value {
+            DataValidity::WithoutGuarantee
+        } else {
+            DataValidity::Valid
+        }
+    }
+}
+
+impl fmt::Display for DataValidity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataValidity::Valid => "navigation data valid".fmt(f),
+            DataValidity::WithoutGuarantee => "working without guarantee".fmt(f),
+        }
+    }
+}
+
+/// Health of a satellite.
+///
+/// This contains the signal health status and data validity status of the E1-B
+/// and E5b signals, transmitted in word type 5 of the I/NAV message, and the
+/// SISA index for E1/E5b, transmitted in word type 3. It is obtained from the
+/// CED and health status data (ADKD=0 and 12) returned by
+/// [`Osnma::get_ced_and_status`](crate::Osnma::get_ced_and_status).
+///
+/// The GST at which the data was transmitted is not included. It can be
+/// obtained with [`NavMessageData::gst`](crate::navmessage::NavMessageData::gst).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SatelliteHealth {
+    sisa: u8,
+    e5b_hs: SignalHealth,
+    e1b_hs: SignalHealth,
+    e5b_dvs: DataValidity,
+    e1b_dvs: DataValidity,
+    nma_dont_use: bool,
+}
+
+/// SISA index value that indicates No Accuracy Prediction Available (NAPA).
+pub const SISA_NAPA: u8 = 255;
+
+impl SatelliteHealth {
+    /// Decodes the satellite health from the CED and health status data.
+    ///
+    /// The `data` should be the data of a
+    /// [`NavMessageData`](crate::navmessage::NavMessageData) obtained with
+    /// [`Osnma::get_ced_and_status`](crate::Osnma::get_ced_and_status).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is shorter than the 549 bits of the CED and health
+    /// status data.
+    pub fn from_ced_and_status(data: &BitSlice) -> SatelliteHealth {
+        let hs_dvs = &data[HS_DVS_START..HS_DVS_START + 6];
+        SatelliteHealth {
+            sisa: data[SISA_START..SISA_START + 8].load_be(),
+            e5b_hs: SignalHealth::from(hs_dvs[..2].load_be::<u8>()),
+            e1b_hs: SignalHealth::from(hs_dvs[2..4].load_be::<u8>()),
+            e5b_dvs: DataValidity::from(hs_dvs[4]),
+            e1b_dvs: DataValidity::from(hs_dvs[5]),
+            nma_dont_use: false,
+        }
+    }
+
+    /// Returns `true` if the NMA status was "don't use" when the health was
+    /// obtained.
+    ///
+    /// This flag is only set by [`Osnma::get_health`](crate::Osnma::get_health)
+    /// when the [`NmaDontUsePolicy::Flag`](crate::NmaDontUsePolicy::Flag) policy
+    /// is in use. See
+    /// [`NavMessageData::nma_dont_use`](crate::navmessage::NavMessageData::nma_dont_use).
+    pub fn nma_dont_use(&self) -> bool {
+        self.nma_dont_use
+    }
+
+    pub(crate) fn with_nma_dont_use(self, nma_dont_use: bool) -> SatelliteHealth {
+        SatelliteHealth {
+            nma_dont_use,
+            ..self
+        }
+    }
+
+    /// Gives the signal health status of a signal.
+    ///
+    /// This is the E1BHS field for [`InavBand::E1B`] and the E5bHS field for
+    /// [`InavBand::E5B`].
+    pub fn signal_health(&self, band: InavBand) -> SignalHealth {
+        match band {
+            InavBand::E1B => self.e1b_hs,
+            InavBand::E5B => self.e5b_hs,
+        }
+    }
+
+    /// Gives the data validity status of a signal.
+    ///
+    /// This is the E1BDVS field for [`InavBand::E1B`] and the E5bDVS field for
+    /// [`InavBand::E5B`].
+    pub fn data_validity(&self, band: InavBand) -> DataValidity {
+        match band {
+            InavBand::E1B => self.e1b_dvs,
+            InavBand::E5B => self.e5b_dvs,
+        }
+    }
+
+    /// Gives the SISA index for E1/E5b.
+    pub fn sisa_index(&self) -> u8 {
+        self.sisa
+    }
+
+    /// Gives the SISA for E1/E5b, in meters.
+    ///
+    /// The SISA index is converted to meters as described in the
+    /// [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+    /// If the SISA index is spare or NAPA, this returns `None`.
+    pub fn sisa(&self) -> Option<f64> {
+        let sisa = f64::from(self.sisa);
+        let cm = match self.sisa {
+            0..=49 => sisa,
+            50..=74 => 50.0 + 2.0 * (sisa - 50.0),
+            75..=99 => 100.0 + 4.0 * (sisa - 75.0),
+            100..=125 => 200.0 + 16.0 * (sisa - 100.0),
+            _ => return None,
+        };
+        Some(cm / 100.0)
+    }
+
+    /// Returns `true` if the satellite is healthy for a signal.
+    ///
+    /// The satellite is healthy for a signal if its signal health status is OK,
+    /// its navigation data is valid, and the SISA is not NAPA.
+    pub fn is_healthy(&self, band: InavBand) -> bool {
+        self.signal_health(band) == SignalHealth::Ok
+            && self.data_validity(band) == DataValidity::Valid
+            && self.sisa != SISA_NAPA
+    }
+}
+
+/// Formats the health as in `"E1-B: signal OK, navigation data valid; E5b:
+/// ...; SISA index 107"`.
+impl fmt::Display for SatelliteHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "E1-B: {}, {}; E5b: {}, {}; SISA index {}",
+            self.e1b_hs, self.e1b_dvs, self.e5b_hs, self.e5b_dvs, self.sisa
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inav::{Word3, Word5, CED_AND_STATUS_LAYOUT};
+    use crate::types::{InavWord, CED_AND_STATUS_BITS};
+
+    #[test]
+    fn decode() {
+        let mut word3: InavWord = [0; 16];
+        let bits = BitSlice::from_slice_mut(&mut word3);
+        bits[..6].store_be(3u8);
+        bits[120..128].store_be(107u8);
+        let mut word5: InavWord = [0; 16];
+        let bits = BitSlice::from_slice_mut(&mut word5);
+        bits[..6].store_be(5u8);
+        bits[67..69].store_be(1u8);
+        bits[71..72].store_be(1u8);
+
+        let mut data = bitarr![u8, Msb0; 0; CED_AND_STATUS_BITS];
+        let word3 = Word3::new(&word3).unwrap();
+        let word5 = Word5::new(&word5).unwrap();
+        data[CED_AND_STATUS_LAYOUT[2].data_bits()].copy_from_bitslice(word3.adkd_data());
+        data[CED_AND_STATUS_LAYOUT[4].data_bits()].copy_from_bitslice(word5.adkd_data());
+
+        let health = SatelliteHealth::from_ced_and_status(&data[..CED_AND_STATUS_BITS]);
+        assert_eq!(health.sisa_index(), word3.sisa());
+        assert_eq!(health.sisa(), Some(3.12));
+        assert_eq!(
+            health.signal_health(InavBand::E5B),
+            SignalHealth::OutOfService
+        );
+        assert_eq!(health.signal_health(InavBand::E1B), SignalHealth::Ok);
+        assert_eq!(
+            health.data_validity(InavBand::E5B),
+            DataValidity::WithoutGuarantee
+        );
+        assert_eq!(health.data_validity(InavBand::E1B), DataValidity::Valid);
+        assert!(health.is_healthy(InavBand::E1B));
+        assert!(!health.is_healthy(InavBand::E5B));
+        assert_eq!(
+            health.to_string(),
+            "E1-B: signal OK, navigation data valid; \
+             E5b: signal out of service, working without guarantee; SISA index 107"
+        );
+    }
+
+    #[test]
+    fn sisa() {
+        let health = |sisa| SatelliteHealth {
+            sisa,
+            e5b_hs: SignalHealth::Ok,
+            e1b_hs: SignalHealth::Ok,
+            e5b_dvs: DataValidity::Valid,
+            e1b_dvs: DataValidity::Valid,
+            nma_dont_use: false,
+        };
+        assert_eq!(health(0).sisa(), Some(0.0));
+        assert_eq!(health(49).sisa(), Some(0.49));
+        assert_eq!(health(50).sisa(), Some(0.5));
+        assert_eq!(health(75).sisa(), Some(1.0));
+        assert_eq!(health(125).sisa(), Some(6.0));
+        assert_eq!(health(126).sisa(), None);
+        assert_eq!(health(SISA_NAPA).sisa(), None);
+        assert!(health(125).is_healthy(InavBand::E1B));
+        assert!(!health(SISA_NAPA).is_healthy(InavBand::E1B));
+    }
+}
@@ -0,0 +1,295 @@
+//! Reader for hex dumps of OSNMA subframes.
+//!
+//! This module contains a reader for text files in which each line contains
+//! the data transmitted by one satellite during one subframe, written in
+//! hexadecimal. This format is used in several published OSNMA datasets, and
+//! the reader allows to replay these datasets through the
+//! [`Osnma`](crate::Osnma) black box without converting them to the Galmon
+//! transport protocol.
+//!
+//! Each line contains the following fields, separated by whitespace or by
+//! commas:
+//!
+//! 1. The SVN of the satellite, either as a number or in the usual `Exx`
+//!    format (for instance, `E11`).
+//! 2. The week number of the GST at the start of the subframe.
+//! 3. The time of week of the GST at the start of the subframe. It must be a
+//!    multiple of 30 seconds.
+//! 4. The HKROOT message, as 15 bytes in hex.
+//! 5. The MACK message, as 60 bytes in hex.
+//! 6. The 15 INAV words of the subframe, in transmission order, as 240 bytes in
+//!    hex.
+//!
+//! Empty lines and lines starting with `#` are ignored. The INAV words are
+//! assumed to have been received in the E1-B band.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::hexdump::HexDumpReader;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey, Validated};
+//! use std::io::BufReader;
+//!
+//! fn process(pubkey: PublicKey<Validated>) -> std::io::Result<()> {
+//!     let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!     let file = std::fs::File::open("osnma_dataset.txt")?;
+//!     let mut read = HexDumpReader::new(BufReader::new(file));
+//!     while let Some(subframe) = read.read_subframe()? {
+//!         osnma.feed_batch(subframe.pages());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::types::{
+    HkrootMessage, InavBand, InavPage, InavWord, MackMessage, OsnmaDataMessage,
+    HKROOT_SECTION_BYTES, INAV_WORD_BYTES, MACK_SECTION_BYTES,
+};
+use crate::{Gst, Svn};
+use std::io::{BufRead, Error, ErrorKind};
+
+const WORDS_PER_SUBFRAME: usize = 15;
+const SECONDS_PER_PAGE: i32 = 2;
+
+/// Subframe read from a hex dump.
+///
+/// This contains the HKROOT message, MACK message and INAV words transmitted
+/// by a satellite during a subframe.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HexDumpSubframe {
+    svn: Svn,
+    gst: Gst,
+    hkroot: HkrootMessage,
+    mack: MackMessage,
+    words: [InavWord; WORDS_PER_SUBFRAME],
+    osnma: [OsnmaDataMessage; WORDS_PER_SUBFRAME],
+}
+
+impl HexDumpSubframe {
+    /// Gives the SVN of the satellite that transmitted the subframe.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Gives the GST at the start of the subframe.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the HKROOT message of the subframe.
+    pub fn hkroot(&self) -> &HkrootMessage {
+        &self.hkroot
+    }
+
+    /// Gives the MACK message of the subframe.
+    pub fn mack(&self) -> &MackMessage {
+        &self.mack
+    }
+
+    /// Gives the INAV words of the subframe, in transmission order.
+    pub fn words(&self) -> &[InavWord; WORDS_PER_SUBFRAME] {
+        &self.words
+    }
+
+    /// Gives the INAV pages of the subframe.
+    ///
+    /// The pages are given in chronological order, and can be fed into the
+    /// OSNMA black box with [`Osnma::feed_batch`](crate::Osnma::feed_batch).
+    pub fn pages(&self) -> impl Iterator<Item = InavPage<'_>> + Clone {
+        self.words
+            .iter()
+            .zip(self.osnma.iter())
+            .zip(0..)
+            .map(|((word, osnma), j)| InavPage {
+                word,
+                osnma: Some(osnma),
+                svn: self.svn,
+                gst: self.gst.wrapping_add_seconds(j * SECONDS_PER_PAGE),
+                band: InavBand::E1B,
+            })
+    }
+}
+
+/// Reader for hex dumps of OSNMA subframes.
+///
+/// This wraps around a [`BufRead`] `R` and can be used to read the subframes
+/// in a hex dump with the format described in the [module
+/// documentation](crate::hexdump).
+#[derive(Debug, Clone)]
+pub struct HexDumpReader<R> {
+    read: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> HexDumpReader<R> {
+    /// Constructs a new reader using a [`BufRead`] `read`.
+    pub fn new(read: R) -> HexDumpReader<R> {
+        HexDumpReader {
+            read,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    /// Tries to read a subframe.
+    ///
+    /// If the read is successful, the subframe is returned. If EOF is reached,
+    /// `None` is returned. If a line cannot be parsed, an error of kind
+    /// [`ErrorKind::InvalidData`] which indicates the line number is
+    /// returned. For other errors, the error of the underlying reader is
+    /// returned.
+    pub fn read_subframe(&mut self) -> std::io::Result<Option<HexDumpSubframe>> {
+        loop {
+            self.line.clear();
+            if self.read.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            return parse_line(line).map(Some).map_err(|e| {
+                log::error!("could not parse hex dump line {}: {}", self.line_number, e);
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", self.line_number, e),
+                )
+            });
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<HexDumpSubframe, String> {
+    let mut fields = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|f| !f.is_empty());
+    let mut next_field = |name: &str| fields.next().ok_or(format!("missing {name} field"));
+    let svn = next_field("SVN")?;
+    let svn = svn
+        .strip_prefix('E')
+        .unwrap_or(svn)
+        .parse::<u8>()
+        .ok()
+        .and_then(|svn| Svn::try_from(svn).ok())
+        .ok_or(format!("invalid SVN {svn}"))?;
+    let wn = next_field("WN")?
+        .parse()
+        .map_err(|e| format!("invalid WN: {e}"))?;
+    let tow = next_field("TOW")?
+        .parse()
+        .map_err(|e| format!("invalid TOW: {e}"))?;
+    if tow >= 7 * 24 * 3600 || tow % 30 != 0 {
+        return Err(format!("TOW {tow} is not the start of a subframe"));
+    }
+    let gst = Gst::new(wn, tow);
+    let hkroot: HkrootMessage = parse_hex(next_field("HKROOT")?, "HKROOT")?;
+    let mack: MackMessage = parse_hex(next_field("MACK")?, "MACK")?;
+    let words_data: [u8; WORDS_PER_SUBFRAME * INAV_WORD_BYTES] =
+        parse_hex(next_field("INAV words")?, "INAV words")?;
+    if fields.next().is_some() {
+        return Err("too many fields".to_string());
+    }
+
+    let mut words = [[0; INAV_WORD_BYTES]; WORDS_PER_SUBFRAME];
+    let mut osnma = [[0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES]; WORDS_PER_SUBFRAME];
+    for (j, (word, osnma)) in words.iter_mut().zip(osnma.iter_mut()).enumerate() {
+        word.copy_from_slice(&words_data[j * INAV_WORD_BYTES..(j + 1) * INAV_WORD_BYTES]);
+        osnma[..HKROOT_SECTION_BYTES]
+            .copy_from_slice(&hkroot[j * HKROOT_SECTION_BYTES..(j + 1) * HKROOT_SECTION_BYTES]);
+        osnma[HKROOT_SECTION_BYTES..]
+            .copy_from_slice(&mack[j * MACK_SECTION_BYTES..(j + 1) * MACK_SECTION_BYTES]);
+    }
+    Ok(HexDumpSubframe {
+        svn,
+        gst,
+        hkroot,
+        mack,
+        words,
+        osnma,
+    })
+}
+
+pub(crate) fn parse_hex<const N: usize>(field: &str, name: &str) -> Result<[u8; N], String> {
+    if field.len() != 2 * N {
+        return Err(format!(
+            "{name} has {} hex digits, but {} are expected",
+            field.len(),
+            2 * N
+        ));
+    }
+    let mut data = [0; N];
+    for (byte, digits) in data.iter_mut().zip(field.as_bytes().chunks_exact(2)) {
+        *byte = core::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or(format!("{name} contains invalid hex digits"))?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{HKROOT_MESSAGE_BYTES, MACK_MESSAGE_BYTES};
+
+    fn line(svn: &str, tow: u32) -> String {
+        let hkroot: String = (0..HKROOT_MESSAGE_BYTES)
+            .map(|j| format!("{j:02x}"))
+            .collect();
+        let mack: String = (0..MACK_MESSAGE_BYTES)
+            .map(|j| format!("{:02x}", 0x80 + j))
+            .collect();
+        let words: String = (0..WORDS_PER_SUBFRAME * INAV_WORD_BYTES)
+            .map(|j| format!("{:02x}", j % 256))
+            .collect();
+        format!("{svn} 1177 {tow} {hkroot},{mack}, {words}\n")
+    }
+
+    #[test]
+    fn read() {
+        let data = format!(
+            "# OSNMA dataset\n\n{}{}",
+            line("E11", 172800),
+            line("12", 172830)
+        );
+        let mut read = HexDumpReader::new(data.as_bytes());
+        let subframe = read.read_subframe().unwrap().unwrap();
+        assert_eq!(subframe.svn(), Svn::try_from(11).unwrap());
+        assert_eq!(subframe.gst(), Gst::new(1177, 172800));
+        assert_eq!(subframe.hkroot()[14], 14);
+        assert_eq!(subframe.mack()[0], 0x80);
+        let pages = subframe.pages().collect::<Vec<_>>();
+        assert_eq!(pages.len(), WORDS_PER_SUBFRAME);
+        assert_eq!(pages[1].gst, Gst::new(1177, 172802));
+        assert_eq!(pages[1].word[0], 16);
+        assert_eq!(pages[1].osnma, Some(&[1, 0x84, 0x85, 0x86, 0x87]));
+        assert_eq!(pages[14].gst, Gst::new(1177, 172828));
+        let subframe = read.read_subframe().unwrap().unwrap();
+        assert_eq!(subframe.svn(), Svn::try_from(12).unwrap());
+        assert_eq!(subframe.gst(), Gst::new(1177, 172830));
+        assert!(read.read_subframe().unwrap().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let good = line("E11", 172800);
+        for bad in [
+            good.replace("E11", "E37"),
+            good.replace("172800", "172801"),
+            good.replace(",", ",0"),
+            good.replace(" 1177", " 1177 0"),
+            good.replacen(" 0001", " 0g01", 1),
+            "E11 1177 172800\n".to_string(),
+        ] {
+            let data = format!("{good}{bad}");
+            let mut read = HexDumpReader::new(data.as_bytes());
+            assert!(read.read_subframe().is_ok());
+            let err = read.read_subframe().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(err.to_string().starts_with("line 2: "), "{err}");
+        }
+    }
+}
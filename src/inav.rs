@@ -0,0 +1,525 @@
+//! INAV words.
+//!
+//! This module contains the [`InavWordType`] enum, which gives the type of an
+//! INAV word, and typed views of the INAV words that are relevant to OSNMA
+//! ([`Word1`] to [`Word10`] and [`Word16`]). The layout of these words is
+//! described in Section 4.3.5 of the
+//! [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+//!
+//! The module also contains the layout of the navigation data authenticated by
+//! each ADKD, which is defined in Section 5.5 of the
+//! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+//! See [`CED_AND_STATUS_LAYOUT`] and [`TIMING_PARAMETERS_LAYOUT`].
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::inav::{InavWordType, Word4};
+//!
+//! let mut word = [0; 16];
+//! word[0] = 4 << 2; // word type 4
+//! word[1] = 42; // IODnav = 42
+//! assert_eq!(InavWordType::of(&word), InavWordType::Word4);
+//! let word4 = Word4::new(&word).unwrap();
+//! assert_eq!(word4.iodnav(), 42);
+//! ```
+
+use crate::types::{BitSlice, InavWord};
+use crate::{Tow, Wn};
+use bitvec::prelude::*;
+use core::fmt;
+use core::ops::Range;
+
+/// INAV word type.
+///
+/// This represents the value of the 6-bit word type field at the start of an
+/// INAV word. The word types that are relevant to OSNMA have their own
+/// variants, and the rest of the values are represented by
+/// [`InavWordType::Other`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InavWordType {
+    /// Spare word (word type 0).
+    Spare,
+    /// Ephemeris (1/4) (word type 1).
+    Word1,
+    /// Ephemeris (2/4) (word type 2).
+    Word2,
+    /// Ephemeris (3/4) and SISA (word type 3).
+    Word3,
+    /// SVID, ephemeris (4/4), and clock correction parameters (word type 4).
+    Word4,
+    /// Ionospheric correction, BGD, signal health and data validity status and
+    /// GST (word type 5).
+    Word5,
+    /// GST-UTC conversion parameters (word type 6).
+    Word6,
+    /// Almanac for SVID1 (1/2), almanac reference time and almanac reference
+    /// week number (word type 7).
+    Word7,
+    /// Almanac for SVID1 (2/2) and SVID2 (1/2) (word type 8).
+    Word8,
+    /// Almanac for SVID2 (2/2) and SVID3 (1/2) (word type 9).
+    Word9,
+    /// Almanac for SVID3 (2/2) and GST-GPS conversion parameters (word type
+    /// 10).
+    Word10,
+    /// Reduced clock and ephemeris data (CED) parameters (word type 16).
+    Word16,
+    /// Dummy message (word type 63).
+    Dummy,
+    /// Any other word type.
+    Other(u8),
+}
+
+impl InavWordType {
+    /// Gives the word type of an INAV word.
+    pub fn of(word: &InavWord) -> InavWordType {
+        InavWordType::from(BitSlice::from_slice(word)[..6].load_be::<u8>())
+    }
+}
+
+impl From<u8> for InavWordType {
+    fn from(value: u8) -> InavWordType {
+        match value {
+            0 => InavWordType::Spare,
+            1 => InavWordType::Word1,
+            2 => InavWordType::Word2,
+            3 => InavWordType::Word3,
+            4 => InavWordType::Word4,
+            5 => InavWordType::Word5,
+            6 => InavWordType::Word6,
+            7 => InavWordType::Word7,
+            8 => InavWordType::Word8,
+            9 => InavWordType::Word9,
+            10 => InavWordType::Word10,
+            16 => InavWordType::Word16,
+            63 => InavWordType::Dummy,
+            x => InavWordType::Other(x),
+        }
+    }
+}
+
+impl From<InavWordType> for u8 {
+    fn from(value: InavWordType) -> u8 {
+        match value {
+            InavWordType::Spare => 0,
+            InavWordType::Word1 => 1,
+            InavWordType::Word2 => 2,
+            InavWordType::Word3 => 3,
+            InavWordType::Word4 => 4,
+            InavWordType::Word5 => 5,
+            InavWordType::Word6 => 6,
+            InavWordType::Word7 => 7,
+            InavWordType::Word8 => 8,
+            InavWordType::Word9 => 9,
+            InavWordType::Word10 => 10,
+            InavWordType::Word16 => 16,
+            InavWordType::Dummy => 63,
+            InavWordType::Other(x) => x,
+        }
+    }
+}
+
+/// Formats the word type as in `"word type 4"`.
+impl fmt::Display for InavWordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "word type {}", u8::from(*self))
+    }
+}
+
+/// Location of the bits of an INAV word in the navigation data of an ADKD.
+///
+/// The navigation data authenticated by an ADKD is formed by concatenating
+/// some bits of several INAV words. This struct indicates which bits of an INAV
+/// word are used, and where they are placed in the navigation data.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AdkdWordLayout {
+    word_type: InavWordType,
+    word_start: usize,
+    data_start: usize,
+    len: usize,
+}
+
+impl AdkdWordLayout {
+    const fn new(
+        word_type: InavWordType,
+        word_start: usize,
+        data_start: usize,
+        len: usize,
+    ) -> AdkdWordLayout {
+        AdkdWordLayout {
+            word_type,
+            word_start,
+            data_start,
+            len,
+        }
+    }
+
+    /// Gives the type of the INAV word.
+    pub fn word_type(&self) -> InavWordType {
+        self.word_type
+    }
+
+    /// Gives the range of bits used in the INAV word.
+    pub fn word_bits(&self) -> Range<usize> {
+        self.word_start..self.word_start + self.len
+    }
+
+    /// Gives the range of bits that the word occupies in the navigation data.
+    pub fn data_bits(&self) -> Range<usize> {
+        self.data_start..self.data_start + self.len
+    }
+
+    /// Extracts the bits used by the ADKD from an INAV word.
+    pub fn extract<'a>(&self, word: &'a BitSlice) -> &'a BitSlice {
+        &word[self.word_bits()]
+    }
+}
+
+/// Layout of the CED and health status navigation data (ADKD=0 and 12).
+///
+/// This is formed by word types 1 to 5.
+pub const CED_AND_STATUS_LAYOUT: [AdkdWordLayout; 5] = [
+    AdkdWordLayout::new(InavWordType::Word1, 6, 0, 120),
+    AdkdWordLayout::new(InavWordType::Word2, 6, 120, 120),
+    AdkdWordLayout::new(InavWordType::Word3, 6, 240, 122),
+    AdkdWordLayout::new(InavWordType::Word4, 6, 362, 120),
+    AdkdWordLayout::new(InavWordType::Word5, 6, 482, 67),
+];
+
+/// Layout of the timing parameters navigation data (ADKD=4).
+///
+/// This is formed by the GST-UTC conversion parameters in word type 6 and the
+/// GST-GPS conversion parameters in word type 10.
+pub const TIMING_PARAMETERS_LAYOUT: [AdkdWordLayout; 2] = [
+    AdkdWordLayout::new(InavWordType::Word6, 6, 0, 99),
+    AdkdWordLayout::new(InavWordType::Word10, 86, 99, 42),
+];
+
+macro_rules! word_view {
+    ($name:ident, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// This is a typed view of an INAV word, which gives access to its
+        /// fields.
+        #[derive(Copy, Clone, Eq, PartialEq, Hash)]
+        pub struct $name<'a>(&'a BitSlice);
+
+        impl<'a> $name<'a> {
+            /// Constructs the typed view of an INAV word.
+            ///
+            /// If the word type of `word` does not match, this returns `None`.
+            pub fn new(word: &'a InavWord) -> Option<$name<'a>> {
+                (InavWordType::of(word) == InavWordType::$name)
+                    .then(|| $name(BitSlice::from_slice(word)))
+            }
+
+            /// Gives the bits of the INAV word.
+            pub fn bits(&self) -> &'a BitSlice {
+                self.0
+            }
+        }
+
+        impl fmt::Debug for $name<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($name))
+                    .field(&format_args!("{:x}", self.0))
+                    .finish()
+            }
+        }
+    };
+    ($name:ident, $doc:expr, $layout:expr) => {
+        word_view!($name, $doc);
+
+        impl<'a> $name<'a> {
+            /// Gives the bits of the word that are part of the navigation data
+            /// authenticated by OSNMA.
+            pub fn adkd_data(&self) -> &'a BitSlice {
+                $layout.extract(self.0)
+            }
+        }
+    };
+}
+
+word_view!(
+    Word1,
+    "INAV word type 1: ephemeris (1/4).",
+    CED_AND_STATUS_LAYOUT[0]
+);
+word_view!(
+    Word2,
+    "INAV word type 2: ephemeris (2/4).",
+    CED_AND_STATUS_LAYOUT[1]
+);
+word_view!(
+    Word3,
+    "INAV word type 3: ephemeris (3/4) and SISA.",
+    CED_AND_STATUS_LAYOUT[2]
+);
+word_view!(
+    Word4,
+    "INAV word type 4: SVID, ephemeris (4/4), and clock correction parameters.",
+    CED_AND_STATUS_LAYOUT[3]
+);
+word_view!(
+    Word5,
+    "INAV word type 5: ionospheric correction, BGD, signal health and data \
+     validity status and GST.",
+    CED_AND_STATUS_LAYOUT[4]
+);
+word_view!(
+    Word6,
+    "INAV word type 6: GST-UTC conversion parameters.",
+    TIMING_PARAMETERS_LAYOUT[0]
+);
+word_view!(
+    Word7,
+    "INAV word type 7: almanac for SVID1 (1/2), almanac reference time and \
+     almanac reference week number."
+);
+word_view!(
+    Word8,
+    "INAV word type 8: almanac for SVID1 (2/2) and SVID2 (1/2)."
+);
+word_view!(
+    Word9,
+    "INAV word type 9: almanac for SVID2 (2/2) and SVID3 (1/2)."
+);
+word_view!(
+    Word10,
+    "INAV word type 10: almanac for SVID3 (2/2) and GST-GPS conversion parameters.",
+    TIMING_PARAMETERS_LAYOUT[1]
+);
+word_view!(
+    Word16,
+    "INAV word type 16: reduced clock and ephemeris data (CED) parameters."
+);
+
+macro_rules! iodnav {
+    ($($name:ident),*) => {
+        $(
+            impl $name<'_> {
+                /// Gives the issue of data of the navigation batch (IODnav).
+                pub fn iodnav(&self) -> u16 {
+                    self.0[6..16].load_be()
+                }
+            }
+        )*
+    };
+}
+
+iodnav!(Word1, Word2, Word3, Word4);
+
+macro_rules! iod_almanac {
+    ($($name:ident),*) => {
+        $(
+            impl $name<'_> {
+                /// Gives the issue of data of the almanacs (IODa).
+                pub fn iod_almanac(&self) -> u8 {
+                    self.0[6..10].load_be()
+                }
+            }
+        )*
+    };
+}
+
+iod_almanac!(Word7, Word8, Word9, Word10);
+
+impl Word1<'_> {
+    /// Gives the ephemeris reference time (toe), in seconds.
+    pub fn toe(&self) -> u32 {
+        self.0[16..30].load_be::<u32>() * 60
+    }
+}
+
+impl Word3<'_> {
+    /// Gives the signal-in-space accuracy (SISA) index for E1/E5b.
+    pub fn sisa(&self) -> u8 {
+        self.0[120..128].load_be()
+    }
+}
+
+impl Word4<'_> {
+    /// Gives the satellite identification (SVID) field.
+    pub fn svid(&self) -> u8 {
+        self.0[16..22].load_be()
+    }
+
+    /// Gives the clock correction data reference time of week (toc), in
+    /// seconds.
+    pub fn toc(&self) -> u32 {
+        self.0[54..68].load_be::<u32>() * 60
+    }
+}
+
+impl Word5<'_> {
+    /// Gives the E5b signal health status (E5bHS) field.
+    pub fn e5b_hs(&self) -> u8 {
+        self.0[67..69].load_be()
+    }
+
+    /// Gives the E1-B signal health status (E1BHS) field.
+    pub fn e1b_hs(&self) -> u8 {
+        self.0[69..71].load_be()
+    }
+
+    /// Gives the E5b data validity status (E5bDVS) field.
+    pub fn e5b_dvs(&self) -> bool {
+        self.0[71]
+    }
+
+    /// Gives the E1-B data validity status (E1BDVS) field.
+    pub fn e1b_dvs(&self) -> bool {
+        self.0[72]
+    }
+
+    /// Gives the GST week number.
+    pub fn wn(&self) -> Wn {
+        self.0[73..85].load_be()
+    }
+
+    /// Gives the GST time of week, in seconds.
+    pub fn tow(&self) -> Tow {
+        self.0[85..105].load_be()
+    }
+}
+
+impl Word6<'_> {
+    /// Gives the GST time of week, in seconds.
+    pub fn tow(&self) -> Tow {
+        self.0[105..125].load_be()
+    }
+}
+
+impl Word10<'_> {
+    /// Gives the E5b signal health status (E5bHS) field.
+    pub fn e5b_hs(&self) -> u8 {
+        self.0[82..84].load_be()
+    }
+
+    /// Gives the E1-B signal health status (E1BHS) field.
+    pub fn e1b_hs(&self) -> u8 {
+        self.0[84..86].load_be()
+    }
+}
+
+// The reduced CED parameters are two's complement integers, which are
+// sign-extended by load_be.
+impl Word16<'_> {
+    /// Gives the difference between the reduced and nominal semi-major axis
+    /// (ΔAred) field, in units of 2^8 meters.
+    pub fn delta_a_red(&self) -> i8 {
+        self.0[6..11].load_be()
+    }
+
+    /// Gives the reduced eccentricity vector component x (exred) field, in
+    /// units of 2^-22.
+    pub fn ex_red(&self) -> i16 {
+        self.0[11..24].load_be()
+    }
+
+    /// Gives the reduced eccentricity vector component y (eyred) field, in
+    /// units of 2^-22.
+    pub fn ey_red(&self) -> i16 {
+        self.0[24..37].load_be()
+    }
+
+    /// Gives the difference between the reduced and nominal inclination angle
+    /// (Δi0red) field, in units of 2^-22 semi-circles.
+    pub fn delta_i0_red(&self) -> i32 {
+        self.0[37..54].load_be()
+    }
+
+    /// Gives the reduced longitude of ascending node (Ω0red) field, in units
+    /// of 2^-22 semi-circles.
+    pub fn omega0_red(&self) -> i32 {
+        self.0[54..77].load_be()
+    }
+
+    /// Gives the reduced mean argument of latitude (λ0red) field, in units of
+    /// 2^-22 semi-circles.
+    pub fn lambda0_red(&self) -> i32 {
+        self.0[77..100].load_be()
+    }
+
+    /// Gives the reduced satellite clock bias correction coefficient (af0red)
+    /// field, in units of 2^-26 seconds.
+    pub fn af0_red(&self) -> i32 {
+        self.0[100..122].load_be()
+    }
+
+    /// Gives the reduced satellite clock drift correction coefficient
+    /// (af1red) field, in units of 2^-35 s/s.
+    pub fn af1_red(&self) -> i8 {
+        self.0[122..128].load_be()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{CED_AND_STATUS_BITS, TIMING_PARAMETERS_BITS};
+
+    #[test]
+    fn word_type() {
+        for value in 0..64 {
+            assert_eq!(u8::from(InavWordType::from(value)), value);
+        }
+        assert_eq!(InavWordType::from(16), InavWordType::Word16);
+        assert_eq!(InavWordType::from(17), InavWordType::Other(17));
+        assert_eq!(InavWordType::Dummy.to_string(), "word type 63");
+    }
+
+    #[test]
+    fn layouts() {
+        // The layouts cover the navigation data without gaps
+        for (layout, bits) in [
+            (&CED_AND_STATUS_LAYOUT[..], CED_AND_STATUS_BITS),
+            (&TIMING_PARAMETERS_LAYOUT[..], TIMING_PARAMETERS_BITS),
+        ] {
+            let mut start = 0;
+            for word in layout {
+                assert_eq!(word.data_bits().start, start);
+                assert!(word.word_bits().end <= 128);
+                start = word.data_bits().end;
+            }
+            assert_eq!(start, bits);
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let mut word = [0; 16];
+        let bits = BitSlice::from_slice_mut(&mut word);
+        bits[..6].store_be(5u8);
+        bits[67..69].store_be(2u8);
+        bits[72..73].store_be(1u8);
+        bits[73..85].store_be(1234u16);
+        bits[85..105].store_be(345600u32);
+        assert!(Word4::new(&word).is_none());
+        let word5 = Word5::new(&word).unwrap();
+        assert_eq!(word5.e5b_hs(), 2);
+        assert_eq!(word5.e1b_hs(), 0);
+        assert!(!word5.e5b_dvs());
+        assert!(word5.e1b_dvs());
+        assert_eq!(word5.wn(), 1234);
+        assert_eq!(word5.tow(), 345600);
+        assert_eq!(word5.adkd_data().len(), 67);
+        assert_eq!(word5.adkd_data(), &word5.bits()[6..73]);
+
+        let mut word = [0; 16];
+        let bits = BitSlice::from_slice_mut(&mut word);
+        bits[..6].store_be(16u8);
+        bits[6..11].store_be(0x1fu8);
+        bits[54..77].store_be(0x3f_ffffu32);
+        bits[100..122].store_be(0x1f_ffffu32);
+        bits[122..128].store_be(0x20u8);
+        let word16 = Word16::new(&word).unwrap();
+        assert_eq!(word16.delta_a_red(), -1);
+        assert_eq!(word16.ex_red(), 0);
+        assert_eq!(word16.omega0_red(), 0x3f_ffff);
+        assert_eq!(word16.lambda0_red(), 0);
+        assert_eq!(word16.af0_red(), 0x1f_ffff);
+        assert_eq!(word16.af1_red(), -32);
+    }
+}
@@ -0,0 +1,339 @@
+//! Reader for JSON records of OSNMA subframes.
+//!
+//! Some community services publish the data transmitted by each Galileo
+//! satellite during each subframe as JSON records, over HTTP streams or
+//! WebSockets. This module contains a parser for these records and a reader
+//! for newline-delimited JSON streams, which allow to feed these sources into
+//! the [`Osnma`](crate::Osnma) black box without converting them to the Galmon
+//! transport protocol.
+//!
+//! Each record is a JSON object with the following members:
+//!
+//! * `svn`. The SVN of the satellite, either as a number or as a string in the
+//!   usual `Exx` format (for instance, `"E11"`).
+//! * `wn`. The week number of the GST at the start of the subframe.
+//! * `tow`. The time of week of the GST at the start of the subframe. It must
+//!   be a multiple of 30 seconds.
+//! * `band`. The band in which the subframe was received, either `"E1B"` or
+//!   `"E5B"`. This member is optional and defaults to `"E1B"`.
+//! * `words`. An array with the 15 INAV words of the subframe, in transmission
+//!   order, each as 16 bytes in hex. The words that have not been received
+//!   are given as `null`.
+//! * `osnma`. An array with the 15 OSNMA data messages of the subframe, each
+//!   as 5 bytes in hex, or `null` for the pages that have not been received
+//!   or that do not carry OSNMA data. This member is optional.
+//!
+//! Other members are ignored. For example:
+//!
+//! ```text
+//! {"svn": "E11", "wn": 1177, "tow": 172800, "words": ["...", null, ...], "osnma": [...]}
+//! ```
+//!
+//! Records obtained by other means, such as the text messages of a WebSocket,
+//! can be parsed with [`JsonSubframe::parse`].
+//!
+//! This module is only available if the `json` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::json::JsonReader;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey, Validated};
+//! use std::io::BufReader;
+//! use std::net::TcpStream;
+//!
+//! fn process(pubkey: PublicKey<Validated>) -> std::io::Result<()> {
+//!     let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!     let stream = TcpStream::connect("localhost:10000")?;
+//!     let mut read = JsonReader::new(BufReader::new(stream));
+//!     while let Some(subframe) = read.read_subframe()? {
+//!         osnma.feed_batch(subframe.pages());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::hexdump::parse_hex;
+use crate::types::{InavBand, InavPage, InavWord, OsnmaDataMessage, INAV_WORD_BYTES};
+use crate::{Gst, Svn};
+use serde_json::{Map, Value};
+use std::io::{BufRead, Error, ErrorKind};
+
+const WORDS_PER_SUBFRAME: usize = 15;
+const SECONDS_PER_PAGE: i32 = 2;
+
+/// Subframe read from a JSON record.
+///
+/// This contains the INAV words and OSNMA data messages transmitted by a
+/// satellite during a subframe, as given by the record.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct JsonSubframe {
+    svn: Svn,
+    gst: Gst,
+    band: InavBand,
+    words: [Option<InavWord>; WORDS_PER_SUBFRAME],
+    osnma: [Option<OsnmaDataMessage>; WORDS_PER_SUBFRAME],
+}
+
+impl JsonSubframe {
+    /// Parses a JSON record.
+    ///
+    /// The record should have the format described in the [module
+    /// documentation](crate::json). If it cannot be parsed, an error of kind
+    /// [`ErrorKind::InvalidData`] is returned.
+    pub fn parse(record: &str) -> std::io::Result<JsonSubframe> {
+        parse_record(record).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Gives the SVN of the satellite that transmitted the subframe.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Gives the GST at the start of the subframe.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the band in which the subframe was received.
+    pub fn band(&self) -> InavBand {
+        self.band
+    }
+
+    /// Gives the INAV words of the subframe, in transmission order.
+    ///
+    /// The words that were not received are `None`.
+    pub fn words(&self) -> &[Option<InavWord>; WORDS_PER_SUBFRAME] {
+        &self.words
+    }
+
+    /// Gives the INAV pages of the subframe.
+    ///
+    /// The pages whose INAV word was not received are skipped. The pages are
+    /// given in chronological order, and can be fed into the OSNMA black box
+    /// with [`Osnma::feed_batch`](crate::Osnma::feed_batch).
+    pub fn pages(&self) -> impl Iterator<Item = InavPage<'_>> + Clone {
+        // The first E5b-I page of a subframe starts 1 second before the
+        // subframe (see InavBand::e1b_page_gst)
+        let offset = match self.band {
+            InavBand::E1B => 0,
+            InavBand::E5B => -1,
+        };
+        self.words
+            .iter()
+            .zip(self.osnma.iter())
+            .zip(0..)
+            .filter_map(move |((word, osnma), j)| {
+                Some(InavPage {
+                    word: word.as_ref()?,
+                    osnma: osnma.as_ref(),
+                    svn: self.svn,
+                    gst: self.gst.wrapping_add_seconds(j * SECONDS_PER_PAGE + offset),
+                    band: self.band,
+                })
+            })
+    }
+}
+
+/// Reader for newline-delimited JSON records of OSNMA subframes.
+///
+/// This wraps around a [`BufRead`] `R` and can be used to read the subframes
+/// in a stream that contains one JSON record with the format described in the
+/// [module documentation](crate::json) per line.
+#[derive(Debug, Clone)]
+pub struct JsonReader<R> {
+    read: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> JsonReader<R> {
+    /// Constructs a new reader using a [`BufRead`] `read`.
+    pub fn new(read: R) -> JsonReader<R> {
+        JsonReader {
+            read,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    /// Tries to read a subframe.
+    ///
+    /// If the read is successful, the subframe is returned. Empty lines are
+    /// skipped. If EOF is reached, `None` is returned. If a line cannot be
+    /// parsed, an error of kind [`ErrorKind::InvalidData`] which indicates the
+    /// line number is returned. For other errors, the error of the underlying
+    /// reader is returned.
+    pub fn read_subframe(&mut self) -> std::io::Result<Option<JsonSubframe>> {
+        loop {
+            self.line.clear();
+            if self.read.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            return parse_record(line).map(Some).map_err(|e| {
+                log::error!(
+                    "could not parse JSON record in line {}: {}",
+                    self.line_number,
+                    e
+                );
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", self.line_number, e),
+                )
+            });
+        }
+    }
+}
+
+fn parse_record(record: &str) -> Result<JsonSubframe, String> {
+    let record: Value = serde_json::from_str(record).map_err(|e| format!("invalid JSON: {e}"))?;
+    let record = record
+        .as_object()
+        .ok_or("record is not a JSON object".to_string())?;
+    let member = |name: &str| record.get(name).ok_or(format!("missing {name} member"));
+    let svn = member("svn")?;
+    let svn = match svn {
+        Value::Number(n) => n.as_u64().and_then(|n| u8::try_from(n).ok()),
+        Value::String(s) => s.strip_prefix('E').unwrap_or(s).parse::<u8>().ok(),
+        _ => None,
+    }
+    .and_then(|svn| Svn::try_from(svn).ok())
+    .ok_or(format!("invalid SVN {svn}"))?;
+    let wn = member("wn")?;
+    let wn = wn
+        .as_u64()
+        .and_then(|wn| u16::try_from(wn).ok())
+        .ok_or(format!("invalid WN {wn}"))?;
+    let tow = member("tow")?;
+    let tow = tow
+        .as_u64()
+        .and_then(|tow| u32::try_from(tow).ok())
+        .ok_or(format!("invalid TOW {tow}"))?;
+    if tow >= 7 * 24 * 3600 || tow % 30 != 0 {
+        return Err(format!("TOW {tow} is not the start of a subframe"));
+    }
+    let gst = Gst::new(wn, tow);
+    let band = match record.get("band") {
+        None => InavBand::E1B,
+        Some(band) => match band.as_str() {
+            Some("E1B") => InavBand::E1B,
+            Some("E5B") => InavBand::E5B,
+            _ => return Err(format!("invalid band {band}")),
+        },
+    };
+    let words = parse_hex_array::<INAV_WORD_BYTES>(record, "words")?
+        .ok_or("missing words member".to_string())?;
+    let osnma = parse_hex_array(record, "osnma")?.unwrap_or([None; WORDS_PER_SUBFRAME]);
+    Ok(JsonSubframe {
+        svn,
+        gst,
+        band,
+        words,
+        osnma,
+    })
+}
+
+// Parses an array of 15 hex strings or nulls. Returns None if the member is
+// not present.
+fn parse_hex_array<const N: usize>(
+    record: &Map<String, Value>,
+    name: &str,
+) -> Result<Option<[Option<[u8; N]>; WORDS_PER_SUBFRAME]>, String> {
+    let Some(array) = record.get(name) else {
+        return Ok(None);
+    };
+    let array = array
+        .as_array()
+        .ok_or(format!("{name} member is not an array"))?;
+    if array.len() != WORDS_PER_SUBFRAME {
+        return Err(format!(
+            "{name} has {} elements, but {WORDS_PER_SUBFRAME} are expected",
+            array.len()
+        ));
+    }
+    let mut data = [None; WORDS_PER_SUBFRAME];
+    for (item, value) in data.iter_mut().zip(array) {
+        *item = match value {
+            Value::Null => None,
+            Value::String(s) => Some(parse_hex(s, name)?),
+            _ => return Err(format!("{name} contains an element that is not a string")),
+        };
+    }
+    Ok(Some(data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(svn: &str, tow: u32, band: &str) -> String {
+        let words: Vec<String> = (0..WORDS_PER_SUBFRAME)
+            .map(|j| match j {
+                1 => "null".to_string(),
+                _ => format!("\"{}\"", format!("{j:02x}").repeat(INAV_WORD_BYTES)),
+            })
+            .collect();
+        let osnma: Vec<String> = (0..WORDS_PER_SUBFRAME)
+            .map(|j| format!("\"{}\"", format!("{:02x}", 0x80 + j).repeat(5)))
+            .collect();
+        format!(
+            "{{\"svn\": {svn}, \"wn\": 1177, \"tow\": {tow},{band} \"words\": [{}], \
+             \"osnma\": [{}], \"source\": \"test\"}}\n",
+            words.join(", "),
+            osnma.join(", ")
+        )
+    }
+
+    #[test]
+    fn read() {
+        let data = format!(
+            "{}\n{}",
+            record("\"E11\"", 172800, ""),
+            record("12", 172830, " \"band\": \"E5B\",")
+        );
+        let mut read = JsonReader::new(data.as_bytes());
+        let subframe = read.read_subframe().unwrap().unwrap();
+        assert_eq!(subframe.svn(), Svn::try_from(11).unwrap());
+        assert_eq!(subframe.gst(), Gst::new(1177, 172800));
+        assert_eq!(subframe.band(), InavBand::E1B);
+        assert!(subframe.words()[1].is_none());
+        let pages = subframe.pages().collect::<Vec<_>>();
+        assert_eq!(pages.len(), WORDS_PER_SUBFRAME - 1);
+        assert_eq!(pages[1].gst, Gst::new(1177, 172804));
+        assert_eq!(pages[1].word, &[2; INAV_WORD_BYTES]);
+        assert_eq!(pages[1].osnma, Some(&[0x82; 5]));
+        let subframe = read.read_subframe().unwrap().unwrap();
+        assert_eq!(subframe.svn(), Svn::try_from(12).unwrap());
+        assert_eq!(subframe.band(), InavBand::E5B);
+        assert_eq!(subframe.pages().next().unwrap().gst, Gst::new(1177, 172829));
+        assert!(read.read_subframe().unwrap().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let good = record("\"E11\"", 172800, "");
+        for bad in [
+            good.replace("\"E11\"", "37"),
+            good.replace("172800", "172801"),
+            good.replace("\"wn\": 1177,", ""),
+            good.replace("\"osnma\": [", "\"osnma\": [null, "),
+            good.replacen("\"0000", "\"0g00", 1),
+            good.replace("\"svn\"", "\"band\": \"E6\", \"svn\""),
+            "[1, 2, 3]\n".to_string(),
+            "{\"svn\": 11\n".to_string(),
+        ] {
+            let data = format!("{good}{bad}");
+            let mut read = JsonReader::new(data.as_bytes());
+            assert!(read.read_subframe().is_ok());
+            let err = read.read_subframe().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(err.to_string().starts_with("line 2: "), "{err}");
+        }
+    }
+}
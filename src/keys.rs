@@ -0,0 +1,631 @@
+//! Loader of the OSNMA key material files.
+//!
+//! The European GNSS Service Centre distributes the OSNMA cryptographic
+//! material needed by receivers in the
+//! [GSC Products](https://www.gsc-europa.eu/gsc-products/OSNMA/PKI) website.
+//! This module contains loaders for these files, which give the values needed
+//! to construct an [`Osnma`] black box, so that applications do not need to
+//! extract them by hand. The following files are supported:
+//!
+//! * The Merkle tree XML files, named as
+//!   `OSNMA_MerkleTree_<date>_PKID_<n>.xml`. These are loaded with
+//!   [`MerkleTreeFile`], which checks that the tree nodes and the public keys
+//!   listed in the file are consistent with the Merkle tree root.
+//! * The public key XML files, named as `OSNMA_PublicKey_<date>_PKID_<n>.xml`.
+//!   These are loaded with [`parse_public_key_xml`].
+//! * The public key PEM and CRT files, which contain the public key as a
+//!   `PUBLIC KEY` or as an X.509 `CERTIFICATE`, respectively. These are loaded
+//!   with [`parse_public_key_pem`]. These files do not contain the PKID, so it
+//!   must be given separately, or be taken from the file name with
+//!   [`pkid_from_file_name`].
+//!
+//! The function [`load_public_key`] loads a public key file in any of these
+//! formats.
+//!
+//! The public keys loaded from these files are given as
+//! [`Validated`], since the files are considered a trustworthy source, in the
+//! same way as when [`PublicKey::force_valid`] is used. A public key can be
+//! checked against the Merkle tree with [`MerkleTreeFile::contains`].
+//!
+//! This module is only available if the `std` feature is enabled. The loaders
+//! only parse the small subset of XML, PEM and DER used by these files.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::keys::{self, MerkleTreeFile};
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::Osnma;
+//!
+//! let merkle_tree = MerkleTreeFile::load("OSNMA_MerkleTree_20240115100000_PKID_1.xml")?;
+//! let pubkey = keys::load_public_key("OSNMA_PublicKey_20240115100000_newPKID_1.crt", None)?;
+//! assert!(merkle_tree.contains(&pubkey));
+//! let osnma = merkle_tree.osnma::<FullStorage>(Some(pubkey), false);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::merkle_tree::{FullMerkleTree, MerkleTree, PublicKey};
+use crate::storage::Storage;
+use crate::types::{MerkleTreeNode, VerifyingKey};
+use crate::validation::{NotValidated, Validated};
+use crate::Osnma;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+const MERKLE_TREE_LEAVES: usize = 16;
+const MERKLE_TREE_DEPTH: usize = 4;
+
+// NPKT values, as in the DSM-PKR.
+const NPKT_P256: u8 = 1;
+const NPKT_P521: u8 = 3;
+
+// Object identifiers of the elliptic curves (including the DER tag and length).
+const OID_PRIME256V1: [u8; 10] = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_SECP521R1: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x23];
+
+/// OSNMA Merkle tree XML file.
+///
+/// This contains the Merkle tree root, the public keys listed in the file, and
+/// the applicability data of the file, if present.
+#[derive(Debug, Clone)]
+pub struct MerkleTreeFile {
+    tree: FullMerkleTree,
+    public_keys: Vec<PublicKey<Validated>>,
+    applicability: Option<String>,
+}
+
+impl MerkleTreeFile {
+    /// Parses the contents of a Merkle tree XML file.
+    ///
+    /// The Merkle tree is computed from the nodes in level 0 listed in the
+    /// file, and checked against the root (the node in level 4). The public
+    /// keys listed in the file are checked against the nodes in level 0. An
+    /// error is returned if any of these checks fails.
+    ///
+    /// The P-521 public keys are skipped if the `p521` feature is not enabled.
+    pub fn parse(xml: &str) -> std::io::Result<MerkleTreeFile> {
+        let tree = element(xml, "MerkleTree").ok_or_else(|| invalid("missing MerkleTree"))?;
+        let mut leaf_nodes = [None; MERKLE_TREE_LEAVES];
+        let mut root = None;
+        for node in elements(tree, "TreeNode") {
+            let level = parse_number(node, "j")?;
+            let index = parse_number(node, "i")?;
+            let value: MerkleTreeNode = parse_hex(required(node, "x_ji")?)?
+                .try_into()
+                .map_err(|_| invalid("x_ji does not have 256 bits"))?;
+            match level {
+                0 if index < MERKLE_TREE_LEAVES => leaf_nodes[index] = Some(value),
+                MERKLE_TREE_DEPTH if index == 0 => root = Some(value),
+                _ if level < MERKLE_TREE_DEPTH && index < MERKLE_TREE_LEAVES >> level => (),
+                _ => return Err(invalid(format!("invalid tree node x_{level},{index}"))),
+            }
+        }
+        let mut nodes = [[0; 32]; MERKLE_TREE_LEAVES];
+        for (j, (node, value)) in nodes.iter_mut().zip(leaf_nodes).enumerate() {
+            *node = value.ok_or_else(|| invalid(format!("missing tree node x_0,{j}")))?;
+        }
+        let root = root.ok_or_else(|| invalid("missing Merkle tree root"))?;
+        let tree_full = FullMerkleTree::from_leaf_nodes(&nodes);
+        if tree_full.root() != &root {
+            return Err(invalid(
+                "the Merkle tree root does not match the tree nodes",
+            ));
+        }
+
+        let mut public_keys = Vec::new();
+        for key in elements(tree, "PublicKey") {
+            let index = parse_number(key, "i")?;
+            let pkid = parse_pkid(key)?;
+            let point = parse_hex(required(key, "point")?)?;
+            let npkt = npkt(key, &point)?;
+            let leaf_node = tree_full
+                .node(0, index)
+                .ok_or_else(|| invalid(format!("invalid public key index {index}")))?;
+            if &leaf_hash(npkt, pkid, &point) != leaf_node {
+                return Err(invalid(format!(
+                    "public key {index} does not match the Merkle tree"
+                )));
+            }
+            if npkt == NPKT_P521 && cfg!(not(feature = "p521")) {
+                continue;
+            }
+            public_keys.push(pubkey_from_sec1(npkt, &point, pkid)?);
+        }
+
+        Ok(MerkleTreeFile {
+            tree: tree_full,
+            public_keys,
+            applicability: applicability(xml),
+        })
+    }
+
+    /// Loads a Merkle tree XML file.
+    ///
+    /// See [`MerkleTreeFile::parse`].
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<MerkleTreeFile> {
+        MerkleTreeFile::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Gives the Merkle tree root.
+    ///
+    /// This is the value that should be given to
+    /// [`Osnma::from_merkle_tree`].
+    pub fn root(&self) -> MerkleTreeNode {
+        *self.tree.root()
+    }
+
+    /// Gives the Merkle tree.
+    pub fn merkle_tree(&self) -> MerkleTree {
+        self.tree.merkle_tree()
+    }
+
+    /// Gives the complete Merkle tree, with all its nodes.
+    pub fn full_merkle_tree(&self) -> &FullMerkleTree {
+        &self.tree
+    }
+
+    /// Gives the public keys listed in the file.
+    pub fn public_keys(&self) -> &[PublicKey<Validated>] {
+        &self.public_keys
+    }
+
+    /// Gives the public key with a PKID, if it is listed in the file.
+    pub fn public_key(&self, pkid: u8) -> Option<&PublicKey<Validated>> {
+        self.public_keys
+            .iter()
+            .find(|key| key.public_key_id() == pkid)
+    }
+
+    /// Gives the applicability data of the file, if present.
+    ///
+    /// This is the text of the first element in the file whose name starts by
+    /// `applicability`.
+    pub fn applicability(&self) -> Option<&str> {
+        self.applicability.as_deref()
+    }
+
+    /// Checks if a public key belongs to the Merkle tree.
+    ///
+    /// This returns `true` if the key, together with its PKID, is one of the
+    /// leaves of the Merkle tree.
+    pub fn contains(&self, pubkey: &PublicKey<Validated>) -> bool {
+        let (npkt, point) = pubkey_sec1(pubkey);
+        let hash = leaf_hash(npkt, pubkey.public_key_id(), &point);
+        (0..MERKLE_TREE_LEAVES).any(|j| self.tree.node(0, j) == Some(&hash))
+    }
+
+    /// Constructs an OSNMA black box using the Merkle tree root of the file.
+    ///
+    /// This calls [`Osnma::from_merkle_tree`]. The public key can be one of
+    /// the keys listed in the file, as given by
+    /// [`MerkleTreeFile::public_key`], or a key loaded from a public key
+    /// file.
+    pub fn osnma<S: Storage>(
+        &self,
+        pubkey: Option<PublicKey<Validated>>,
+        only_slowmac: bool,
+    ) -> Osnma<S> {
+        Osnma::from_merkle_tree(self.root(), pubkey, only_slowmac)
+    }
+}
+
+/// Parses the contents of a public key XML file.
+///
+/// The PKID is taken from the file.
+pub fn parse_public_key_xml(xml: &str) -> std::io::Result<PublicKey<Validated>> {
+    let key = element(xml, "PublicKey").ok_or_else(|| invalid("missing PublicKey"))?;
+    let pkid = parse_pkid(key)?;
+    let point = parse_hex(required(key, "point")?)?;
+    pubkey_from_sec1(npkt(key, &point)?, &point, pkid)
+}
+
+/// Parses the contents of a public key PEM or CRT file.
+///
+/// The PEM file can contain either a `PUBLIC KEY` or a `CERTIFICATE`. The
+/// public key is given the PKID `pkid`.
+pub fn parse_public_key_pem(pem: &str, pkid: u8) -> std::io::Result<PublicKey<Validated>> {
+    let (label, der) = parse_pem(pem)?;
+    let spki = match label {
+        "PUBLIC KEY" => der_sequence(&der)?,
+        "CERTIFICATE" => {
+            let certificate = der_sequence(&der)?;
+            let mut tbs = der_sequence(certificate)?;
+            // Skip the optional version
+            if tbs.first() == Some(&0xa0) {
+                tbs = der_tlv(tbs)?.2;
+            }
+            // Skip the serial number, signature, issuer, validity and subject
+            for _ in 0..5 {
+                tbs = der_tlv(tbs)?.2;
+            }
+            der_sequence(tbs)?
+        }
+        _ => return Err(invalid(format!("unsupported PEM label {label}"))),
+    };
+    let (tag, algorithm, rest) = der_tlv(spki)?;
+    let (bitstring_tag, bitstring, _) = der_tlv(rest)?;
+    if tag != 0x30 || bitstring_tag != 0x03 || bitstring.first() != Some(&0) {
+        return Err(invalid("malformed SubjectPublicKeyInfo"));
+    }
+    let point = &bitstring[1..];
+    let npkt = if algorithm.ends_with(&OID_PRIME256V1) {
+        NPKT_P256
+    } else if algorithm.ends_with(&OID_SECP521R1) {
+        NPKT_P521
+    } else {
+        return Err(invalid("the public key is not a P-256 or P-521 ECDSA key"));
+    };
+    pubkey_from_sec1(npkt, point, pkid)
+}
+
+/// Loads a public key file.
+///
+/// The file can be either a public key XML file, a PEM file or a CRT file. The
+/// PKID is taken from the contents of XML files. For PEM and CRT files, the
+/// PKID is given by `pkid` or, if it is `None`, taken from the file name with
+/// [`pkid_from_file_name`].
+pub fn load_public_key<P: AsRef<Path>>(
+    path: P,
+    pkid: Option<u8>,
+) -> std::io::Result<PublicKey<Validated>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)?;
+    if contents.trim_start().starts_with('<') {
+        return parse_public_key_xml(&contents);
+    }
+    let pkid = pkid
+        .or_else(|| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(pkid_from_file_name)
+        })
+        .ok_or_else(|| invalid("the PKID of the public key is unknown"))?;
+    parse_public_key_pem(&contents, pkid)
+}
+
+/// Obtains the PKID from the name of an OSNMA key material file.
+///
+/// The files distributed by the European GNSS Service Centre contain
+/// `PKID_<n>` in their names. If the name does not contain a valid PKID,
+/// `None` is returned.
+pub fn pkid_from_file_name(name: &str) -> Option<u8> {
+    let start = name.find("PKID_")? + "PKID_".len();
+    let digits = name[start..].split(|c: char| !c.is_ascii_digit()).next()?;
+    digits.parse().ok().filter(|&pkid| pkid < 16)
+}
+
+fn invalid<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+// Gives the contents of all the elements with a given name.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        if !rest.starts_with(|c: char| c == '>' || c == '/' || c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        if rest[..end].ends_with('/') {
+            found.push("");
+            rest = &rest[end + 1..];
+            continue;
+        }
+        rest = &rest[end + 1..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        found.push(rest[..end].trim());
+        rest = &rest[end + close.len()..];
+    }
+    found
+}
+
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    elements(xml, name).into_iter().next()
+}
+
+fn required<'a>(xml: &'a str, name: &str) -> std::io::Result<&'a str> {
+    element(xml, name).ok_or_else(|| invalid(format!("missing {name}")))
+}
+
+fn parse_number(xml: &str, name: &str) -> std::io::Result<usize> {
+    required(xml, name)?
+        .parse()
+        .map_err(|_| invalid(format!("invalid {name}")))
+}
+
+fn parse_pkid(xml: &str) -> std::io::Result<u8> {
+    let pkid = parse_number(xml, "PKID")?;
+    u8::try_from(pkid)
+        .ok()
+        .filter(|&pkid| pkid < 16)
+        .ok_or_else(|| invalid(format!("invalid PKID {pkid}")))
+}
+
+fn parse_hex(field: &str) -> std::io::Result<Vec<u8>> {
+    let digits: Vec<u8> = field.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(invalid("odd number of hex digits"));
+    }
+    digits
+        .chunks_exact(2)
+        .map(|digits| {
+            core::str::from_utf8(digits)
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| invalid("invalid hex digits"))
+        })
+        .collect()
+}
+
+// Gives the text of the first element whose name starts by "applicability".
+fn applicability(xml: &str) -> Option<String> {
+    let mut offset = 0;
+    while let Some(start) = xml[offset..].find('<') {
+        let tag = &xml[offset + start..];
+        let name_len = tag[1..]
+            .find(|c: char| c == '>' || c == '/' || c.is_ascii_whitespace())
+            .unwrap_or(tag.len() - 1);
+        let name = &tag[1..1 + name_len];
+        if name.len() >= 13 && name[..13].eq_ignore_ascii_case("applicability") {
+            return element(tag, name)
+                .filter(|text| !text.is_empty())
+                .map(String::from);
+        }
+        offset += start + 1;
+    }
+    None
+}
+
+// Gives the NPKT of a public key in an XML file, using the PKType if present,
+// or the key length otherwise.
+fn npkt(xml: &str, point: &[u8]) -> std::io::Result<u8> {
+    match element(xml, "PKType") {
+        Some(pktype) if pktype.contains("P-256") => Ok(NPKT_P256),
+        Some(pktype) if pktype.contains("P-521") => Ok(NPKT_P521),
+        Some(pktype) => Err(invalid(format!("unsupported PKType {pktype}"))),
+        None => match point.len() {
+            33 | 65 => Ok(NPKT_P256),
+            67 | 133 => Ok(NPKT_P521),
+            len => Err(invalid(format!("invalid public key length {len}"))),
+        },
+    }
+}
+
+fn leaf_hash(npkt: u8, pkid: u8, point: &[u8]) -> MerkleTreeNode {
+    let mut leaf = Vec::with_capacity(point.len() + 1);
+    leaf.push((npkt << 4) | pkid);
+    leaf.extend_from_slice(point);
+    MerkleTree::hash_leaf(&leaf)
+}
+
+fn pubkey_from_sec1(npkt: u8, point: &[u8], pkid: u8) -> std::io::Result<PublicKey<Validated>> {
+    let pubkey: PublicKey<NotValidated> = match npkt {
+        NPKT_P256 => PublicKey::from_p256(
+            p256::ecdsa::VerifyingKey::from_sec1_bytes(point)
+                .map_err(|_| invalid("invalid P-256 public key"))?,
+            pkid,
+        ),
+        #[cfg(feature = "p521")]
+        NPKT_P521 => PublicKey::from_p521(
+            p521::ecdsa::VerifyingKey::from_sec1_bytes(point)
+                .map_err(|_| invalid("invalid P-521 public key"))?,
+            pkid,
+        ),
+        #[cfg(not(feature = "p521"))]
+        NPKT_P521 => return Err(invalid("P-521 support disabled")),
+        _ => unreachable!(),
+    };
+    Ok(pubkey.force_valid())
+}
+
+// Gives the NPKT and the SEC1 compressed form of a public key, as they are
+// included in the leaves of the Merkle tree.
+fn pubkey_sec1(pubkey: &PublicKey<Validated>) -> (u8, Vec<u8>) {
+    match pubkey.verifying_key() {
+        VerifyingKey::P256(key) => (NPKT_P256, key.to_encoded_point(true).as_bytes().to_vec()),
+        #[cfg(feature = "p521")]
+        VerifyingKey::P521(key) => (NPKT_P521, key.to_encoded_point(true).as_bytes().to_vec()),
+    }
+}
+
+// Gives the label and the decoded contents of the first block of a PEM file.
+fn parse_pem(pem: &str) -> std::io::Result<(&str, Vec<u8>)> {
+    let start = pem
+        .find("-----BEGIN ")
+        .ok_or_else(|| invalid("missing PEM header"))?;
+    let rest = &pem[start + "-----BEGIN ".len()..];
+    let label_end = rest
+        .find("-----")
+        .ok_or_else(|| invalid("malformed PEM header"))?;
+    let label = &rest[..label_end];
+    let rest = &rest[label_end + "-----".len()..];
+    let end = rest
+        .find(&format!("-----END {label}-----"))
+        .ok_or_else(|| invalid("missing PEM footer"))?;
+    Ok((label, base64_decode(&rest[..end])?))
+}
+
+fn base64_decode(text: &str) -> std::io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(invalid("invalid base64 data")),
+        };
+        acc = (acc << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((acc >> bits) as u8);
+        }
+    }
+    Ok(data)
+}
+
+// Splits a DER TLV into its tag, its contents, and the data that follows it.
+fn der_tlv(data: &[u8]) -> std::io::Result<(u8, &[u8], &[u8])> {
+    let malformed = || invalid("malformed DER data");
+    let (&tag, data) = data.split_first().ok_or_else(malformed)?;
+    let (&len, mut data) = data.split_first().ok_or_else(malformed)?;
+    let len = if len & 0x80 == 0 {
+        usize::from(len)
+    } else {
+        let num_bytes = usize::from(len & 0x7f);
+        if num_bytes == 0 || num_bytes > 4 || data.len() < num_bytes {
+            return Err(malformed());
+        }
+        let len = data[..num_bytes]
+            .iter()
+            .fold(0, |len, &b| (len << 8) | usize::from(b));
+        data = &data[num_bytes..];
+        len
+    };
+    if data.len() < len {
+        return Err(malformed());
+    }
+    Ok((tag, &data[..len], &data[len..]))
+}
+
+fn der_sequence(data: &[u8]) -> std::io::Result<&[u8]> {
+    match der_tlv(data)? {
+        (0x30, contents, _) => Ok(contents),
+        _ => Err(invalid("expected DER SEQUENCE")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    // P-256 key in SEC1 compressed form, matching PEM and CERTIFICATE.
+    const POINT: [u8; 33] =
+        hex!("02be2d7920e017bf10eed19ec1a1a096e2a5c1da0e61aea1992ce1bcddae3773af");
+
+    const PEM: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEvi15IOAXvxDu0Z7BoaCW4qXB2g5h
+rqGZLOG83a43c68VdN7plwfNVxHVZsuUp8RG8Ql4+tpx67zwDJzyK+JV5g==
+-----END PUBLIC KEY-----
+";
+
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBfzCCASWgAwIBAgIUM+fceHCPpp+sOSIl1i2jlQjmTbcwCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKT1NOTUEgdGVzdDAeFw0yNjEwMTcwMTU1MzFaFw0zNjEwMTQw
+MTU1MzFaMBUxEzARBgNVBAMMCk9TTk1BIHRlc3QwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAAS+LXkg4Be/EO7RnsGhoJbipcHaDmGuoZks4bzdrjdzrxV03umXB81X
+EdVmy5SnxEbxCXj62nHrvPAMnPIr4lXmo1MwUTAdBgNVHQ4EFgQUhUZkkf7pn7Bo
+61ROufmTcMQmUlwwHwYDVR0jBBgwFoAUhUZkkf7pn7Bo61ROufmTcMQmUlwwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiBVGEHm/vUZMoLuruDcfyNI
+27qKB5l09cOJ2tzLZualwgIhAK+n4QnbdKnDg5Dx7i30hNM1PD2oFvneoE2KSp15
+WyPl
+-----END CERTIFICATE-----
+";
+
+    fn hex_string(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{b:02X}")).collect()
+    }
+
+    // Builds a Merkle tree XML file in which the leaf 3 is the key POINT with
+    // PKID 2.
+    fn merkle_tree_xml() -> (FullMerkleTree, String) {
+        let mut leaf_key = vec![0x12];
+        leaf_key.extend_from_slice(&POINT);
+        let others: Vec<[u8; 4]> = (0..16).map(|j| [0x10, j, j, j]).collect();
+        let mut leaves: [&[u8]; 16] = core::array::from_fn(|j| &others[j][..]);
+        leaves[3] = &leaf_key;
+        let tree = FullMerkleTree::from_leaves(&leaves);
+        let mut xml = String::from(
+            "<?xml version=\"1.0\"?>\n<signalData>\n<header>\n\
+             <applicabilityDate>2026-10-17</applicabilityDate>\n</header>\n\
+             <body>\n<MerkleTree>\n<N>16</N>\n<HashFunction>SHA-256</HashFunction>\n\
+             <PublicKey>\n<i>3</i>\n<PKID>2</PKID>\n<lengthInBits>264</lengthInBits>\n",
+        );
+        xml += &format!(
+            "<point>{}</point>\n<PKType>ECDSA P-256/SHA-256</PKType>\n</PublicKey>\n",
+            hex_string(&POINT)
+        );
+        for j in 0..=MERKLE_TREE_DEPTH {
+            for i in 0..MERKLE_TREE_LEAVES >> j {
+                xml += &format!(
+                    "<TreeNode><j>{j}</j><i>{i}</i><lengthInBits>256</lengthInBits>\
+                     <x_ji>{}</x_ji></TreeNode>\n",
+                    hex_string(tree.node(j, i).unwrap())
+                );
+            }
+        }
+        xml += "</MerkleTree>\n</body>\n</signalData>\n";
+        (tree, xml)
+    }
+
+    fn assert_point(pubkey: &PublicKey<Validated>, pkid: u8) {
+        assert_eq!(pubkey.public_key_id(), pkid);
+        assert_eq!(pubkey_sec1(pubkey), (NPKT_P256, POINT.to_vec()));
+    }
+
+    #[test]
+    fn merkle_tree_file() {
+        let (tree, xml) = merkle_tree_xml();
+        let file = MerkleTreeFile::parse(&xml).unwrap();
+        assert_eq!(file.root(), *tree.root());
+        assert_eq!(file.full_merkle_tree(), &tree);
+        assert_eq!(file.applicability(), Some("2026-10-17"));
+        assert_eq!(file.public_keys().len(), 1);
+        assert_point(file.public_key(2).unwrap(), 2);
+        assert!(file.public_key(1).is_none());
+        assert!(file.contains(file.public_key(2).unwrap()));
+        assert!(!file.contains(&parse_public_key_pem(PEM, 1).unwrap()));
+
+        // Wrong root
+        let root = hex_string(tree.root());
+        let bad = xml.replace(&root, &format!("{}00", &root[..62]));
+        assert!(MerkleTreeFile::parse(&bad).is_err());
+        // Public key that does not match its leaf
+        let bad = xml.replace("<PKID>2</PKID>", "<PKID>3</PKID>");
+        assert!(MerkleTreeFile::parse(&bad).is_err());
+    }
+
+    #[test]
+    fn public_key_files() {
+        assert_point(&parse_public_key_pem(PEM, 1).unwrap(), 1);
+        assert_point(&parse_public_key_pem(CERTIFICATE, 2).unwrap(), 2);
+        assert!(parse_public_key_pem(&PEM.replace("MFkw", "MFkx"), 1).is_err());
+        let xml = format!(
+            "<signalData><body><PublicKey><PKID>4</PKID>\
+             <point>{}</point></PublicKey></body></signalData>",
+            hex_string(&POINT)
+        );
+        assert_point(&parse_public_key_xml(&xml).unwrap(), 4);
+    }
+
+    #[test]
+    fn pkid_file_name() {
+        assert_eq!(
+            pkid_from_file_name("OSNMA_MerkleTree_20231213105954_PKID_1.xml"),
+            Some(1)
+        );
+        assert_eq!(
+            pkid_from_file_name("OSNMA_PublicKey_20231213105954_newPKID_12.crt"),
+            Some(12)
+        );
+        assert_eq!(pkid_from_file_name("OSNMA_PublicKey_PKID_16.pem"), None);
+        assert_eq!(pkid_from_file_name("pubkey.pem"), None);
+    }
+}
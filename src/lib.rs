@@ -20,11 +20,40 @@
 //! [osnma-longan-nano](https://github.com/daniestevez/galileo-osnma/tree/main/osnma-longan-nano)
 //! crate.
 //!
+//! The [`prelude`] module re-exports the most commonly used types, including
+//! the ECDSA verifying key types of the p256 and p521 crates. These crates are
+//! also re-exported as `galileo_osnma::p256` and `galileo_osnma::p521`.
+//!
+//! ## Terminology
+//!
+//! The documentation and the `Display` implementations of the types in this
+//! crate follow the terminology of the
+//! [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf)
+//! and the
+//! [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+//! The most important terms are the following:
+//! * A *word* is the 128-bit unit of I/NAV navigation data. It is transmitted
+//!   in two halves, in the even and odd pages of a 2-second page pair, and its
+//!   first 6 bits give its word type. Word types 1 to 5 contain the
+//!   ephemeris, clock and status (CED) data, and word types 6 and 10 contain
+//!   the timing parameters.
+//! * A *subframe* has a duration of 30 seconds and contains 15 words. The
+//!   40-bit OSNMA data messages of the 15 pages of a subframe are concatenated
+//!   to form the HKROOT and MACK messages.
+//! * The *HKROOT* message contains the NMA header and a block of the DSM
+//!   (digital signature message). A DSM-KROOT carries the TESLA root key, and
+//!   a DSM-PKR carries a new ECDSA public key.
+//! * The *MACK* message contains the tags (MACs) that authenticate the
+//!   navigation data, and a TESLA key of the chain. The ADKD field of each tag
+//!   indicates which navigation data it authenticates.
+//!
 //! ## Logging
 //!
 //! The galileo-osnma crate makes extensive use of the
 //! [log](https://docs.rs/log/latest/log/) crate to log events related to the
-//! processing of the messages and the cryptographic functions.
+//! processing of the messages and the cryptographic functions. The
+//! [`logging`] module gives stable names for the log targets of each subsystem
+//! and helpers to control their log verbosity.
 //!
 //! ## Galmon integration
 //!
@@ -42,38 +71,177 @@
 //! [quick start instructions](https://github.com/daniestevez/galileo-osnma#quick-start-using-galmon)
 //! about how to use this tool.
 //!
+//! ## u-blox integration
+//!
+//! The [`ubx`] module converts the Galileo I/NAV data given by u-blox
+//! receivers in the RXM-SFRBX messages of the UBX protocol into INAV words and
+//! OSNMA data messages, using the GST given by the NAV-TIMEGAL messages to
+//! timestamp the pages. It can be used together with a UBX parser such as the
+//! [ublox](https://crates.io/crates/ublox) crate. When this crate is built
+//! with the `ublox` feature, the [`ubx`] module also accepts the message types
+//! of the ublox crate directly.
+//!
+//! ## Septentrio integration
+//!
+//! When this crate is built with the `sbf` feature, an `sbf` module is
+//! available, which reads the Septentrio Binary Format (SBF) and extracts the
+//! INAV words and OSNMA data messages from the GALRawINAV blocks.
+//!
 //! ## Features
 //!
 //! When built with the default features, the crate does not require
 //! `std`. Additionally, the crate supports the following features:
+//! * `alloc`. This enables `storage::DynStorage`, a storage that is allocated
+//!   on the heap and grows with the number of satellites in view. It requires
+//!   an allocator but not `std`, and it is enabled by `std`.
+//! * `cross-auth`. This enables `Osnma::cross_auth_expectation` and
+//!   `Osnma::cross_auth_targets`, which give the satellites whose navigation
+//!   data is expected to be cross-authenticated by the tags of each satellite.
+//!   It is enabled by default. Embedded receivers that do not need it can
+//!   disable it to reduce the memory used by the OSNMA black box.
+//! * `embedded-io`. This enables a `serial` module, which implements a simple
+//!   line-based protocol to feed data and report the authentication status
+//!   over a serial port implementing the `embedded-io` traits. It can be used
+//!   to port the osnma-longan-nano demo to other microcontrollers.
 //! * `galmon`. This enables support for reading the Galmon transport protocol
-//!    and requires `std`.
+//!   and requires `std`.
+//! * `json`. This enables a `json` module, which reads JSON records of OSNMA
+//!   subframes such as those published by some community services, and
+//!   requires `std`.
+//! * `nav-diagnostics`. This enables the tracking of the anomalies in the
+//!   navigation data given by `Osnma::anomalies`. It is not enabled by default
+//!   because this information increases the memory used by the OSNMA black box
+//!   considerably, which is relevant for embedded receivers using a small
+//!   storage.
+//! * `provenance`. This enables `NavMessageData::provenance`, which gives the
+//!   band and GST in which each of the INAV words of the authenticated
+//!   navigation data was received. It is enabled by default. Embedded receivers
+//!   that do not need it can disable it to reduce the memory used by the OSNMA
+//!   black box.
+//! * `reduced-ced`. This enables `Osnma::get_reduced_ced`, which gives the
+//!   reduced CED transmitted in the INAV word type 16. It is enabled by
+//!   default. Embedded receivers that do not need it can disable it to reduce
+//!   the memory used by the OSNMA black box.
+//! * `sbf`. This enables an `sbf` module, which reads the Septentrio Binary
+//!   Format and extracts the INAV words and OSNMA data from the GALRawINAV
+//!   blocks. It requires `std`.
 //! * `p521`. This enables support for ECDSA P-521 public keys. These public keys
-//!    defined in the OSNMA ICD, but currently only ECDSA P-256 keys are used in
-//!    the signal-in-space.
+//!   defined in the OSNMA ICD, but currently only ECDSA P-256 keys are used in
+//!   the signal-in-space.
+//! * `signer`. This enables a `signer` module, which generates TESLA chains,
+//!   MACK messages and signed DSM-KROOTs, so that OSNMA data can be produced
+//!   for test benches. It requires an allocator but not `std`.
+//! * `slowmac`. This enables the processing of Slow MAC (ADKD=12) tags. It is
+//!   enabled by default. Embedded receivers that will never use Slow MAC can
+//!   disable it to reduce code size. The MACK messages are still validated
+//!   against the MAC Look-up Table, but their ADKD=12 tags are skipped.
+//! * `std`. This enables a `hexdump` module, which reads hex dumps of OSNMA
+//!   subframes such as those used in some published OSNMA datasets, an
+//!   `archive` module, which writes and reads archives of raw OSNMA material,
+//!   a `navrecords` module, which writes the authenticated navigation data as
+//!   text records, a `keys` module, which loads the Merkle tree and public key
+//!   files distributed by the European GNSS Service Centre, a `merge` module,
+//!   which merges the data received by several receivers, the methods to save
+//!   and load the OSNMA state to files, and the implementations of
+//!   `std::error::Error` for the error types.
+//! * `simulator`. This enables a `simulator` module, which simulates the
+//!   OSNMA broadcast for testing, and requires `std`.
+//! * `testing`. This enables a `testing` module, which contains a small
+//!   storage, test vectors and helpers to construct [`Osnma`] black boxes,
+//!   intended for writing tests in other crates.
+//! * `ublox`. This enables the methods of [`UbxFrontEnd`](ubx::UbxFrontEnd)
+//!   that take the RXM-SFRBX and NAV-TIMEGPS message types of the
+//!   [ublox](https://crates.io/crates/ublox) crate. The ublox crate requires a
+//!   more recent Rust version than the minimum supported Rust version of this
+//!   crate.
+//! * `unique-authbits`. This enables `Osnma::set_unique_tag_authbits`, which
+//!   counts only the authentication bits of tags from distinct sources. It
+//!   increases the size of the storage of the navigation data, since the
+//!   sources of the tags that authenticate each navigation data entry need to
+//!   be remembered.
+//! * `wasm`. This enables a `wasm` module, which contains JavaScript bindings
+//!   for running the [`Osnma`] black box in a web browser using WebAssembly.
+//!   It requires `std`.
 
 #![warn(missing_docs)]
 #![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod archive;
+mod audit;
+mod authstate;
 pub mod bitfields;
+mod build_info;
+pub use build_info::{build_info, BuildInfo, Feature, StoragePreset};
+pub mod ced;
+pub mod diagnostics;
+pub mod diff;
 pub mod dsm;
+mod either;
+pub use either::EitherOsnma;
+pub mod events;
 #[cfg(feature = "galmon")]
 pub mod galmon;
 mod gst;
-pub use gst::{Gst, Tow, Wn};
+pub mod gstguard;
+pub mod health;
+pub use gst::{Gst, Tow, Wn, WnRollover, WN_ROLLOVER};
+#[cfg(feature = "std")]
+pub mod hexdump;
+pub mod inav;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod keys;
+pub mod logging;
 pub mod mack;
 pub mod maclt;
+#[cfg(feature = "std")]
+pub mod merge;
 pub mod merkle_tree;
 pub use merkle_tree::PublicKey;
 pub mod navmessage;
+#[cfg(feature = "std")]
+pub mod navrecords;
 mod osnma;
-pub use osnma::Osnma;
+pub use osnma::{
+    ChainTransition, FeedError, NmaDontUsePolicy, NmaState, Osnma, OsnmaBuilder, PeerKeyError,
+    PrepositionedChain, SubframeHook,
+};
+pub mod persist;
+pub mod prelude;
+pub mod report;
+pub mod rtcm;
+#[cfg(feature = "sbf")]
+pub mod sbf;
+#[cfg(feature = "embedded-io")]
+pub mod serial;
+#[cfg(feature = "signer")]
+pub mod signer;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+pub mod status;
 pub mod storage;
 pub mod subframe;
 mod svn;
 pub use svn::{Svn, SvnError};
 pub mod tesla;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod timing;
 pub mod types;
 pub use types::{InavBand, MerkleTreeNode};
+pub mod ubx;
 pub mod validation;
 pub use validation::Validated;
+pub mod verifier;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod watchdog;
+
+pub use p256;
+#[cfg(feature = "p521")]
+pub use p521;
@@ -0,0 +1,384 @@
+//! Logging helpers.
+//!
+//! The galileo_osnma crate logs events using the
+//! [log](https://docs.rs/log/latest/log/) crate. Each of the subsystems of the
+//! crate logs with its own target, which is the module path of the subsystem
+//! (for instance, `galileo_osnma::dsm`). The [`Subsystem`] enum lists these
+//! subsystems and gives their target names, which are considered stable.
+//!
+//! This module contains two helpers to control the log verbosity of each
+//! subsystem:
+//!
+//! * [`FilterSpec`] is a builder for filter strings in the format used by
+//!   [env_logger](https://docs.rs/env_logger/latest/env_logger/) and other
+//!   loggers that follow the same convention, such as the `RUST_LOG`
+//!   environment variable.
+//!
+//! * [`SubsystemFilter`] wraps any [`Log`] implementation and filters its
+//!   records according to a log level for each subsystem that can be changed at
+//!   runtime. This is useful in platforms where the logger does not support
+//!   filtering by target, such as in embedded systems.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::logging::{FilterSpec, Subsystem};
+//! use log::LevelFilter;
+//!
+//! let filter = FilterSpec::new()
+//!     .default_level(LevelFilter::Info)
+//!     .level(Subsystem::Navmessage, LevelFilter::Warn)
+//!     .level(Subsystem::Dsm, LevelFilter::Debug);
+//! assert_eq!(
+//!     filter.to_string(),
+//!     "info,galileo_osnma::dsm=debug,galileo_osnma::navmessage=warn"
+//! );
+//! // With env_logger, this can be used as
+//! // env_logger::Builder::new().parse_filters(&filter.to_string()).init();
+//! ```
+
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Subsystem of the galileo_osnma crate.
+///
+/// Each subsystem logs using a target whose name is given by
+/// [`Subsystem::target`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Subsystem {
+    /// DSM message collection ([`dsm`](crate::dsm) module).
+    Dsm,
+    /// Galmon transport protocol (`galmon` module).
+    Galmon,
+    /// MACK message storage ([`mack`](crate::mack) module).
+    Mack,
+    /// Navigation message storage and tag processing
+    /// ([`navmessage`](crate::navmessage) module).
+    Navmessage,
+    /// OSNMA black box ([`Osnma`](crate::Osnma)).
+    Osnma,
+    /// Subframe collection ([`subframe`](crate::subframe) module).
+    Subframe,
+    /// TESLA keys and chains ([`tesla`](crate::tesla) module).
+    Tesla,
+}
+
+const NUM_SUBSYSTEMS: usize = 7;
+
+impl Subsystem {
+    const ALL: [Subsystem; NUM_SUBSYSTEMS] = [
+        Subsystem::Dsm,
+        Subsystem::Galmon,
+        Subsystem::Mack,
+        Subsystem::Navmessage,
+        Subsystem::Osnma,
+        Subsystem::Subframe,
+        Subsystem::Tesla,
+    ];
+
+    /// Iterate over all the subsystems.
+    pub fn iter() -> impl Iterator<Item = Subsystem> {
+        Self::ALL.into_iter()
+    }
+
+    /// Gives the log target used by the subsystem.
+    pub fn target(&self) -> &'static str {
+        match self {
+            Subsystem::Dsm => "galileo_osnma::dsm",
+            Subsystem::Galmon => "galileo_osnma::galmon",
+            Subsystem::Mack => "galileo_osnma::mack",
+            Subsystem::Navmessage => "galileo_osnma::navmessage",
+            Subsystem::Osnma => "galileo_osnma::osnma",
+            Subsystem::Subframe => "galileo_osnma::subframe",
+            Subsystem::Tesla => "galileo_osnma::tesla",
+        }
+    }
+
+    /// Returns the subsystem that a log target belongs to.
+    ///
+    /// Targets of submodules of a subsystem (such as
+    /// `galileo_osnma::galmon::transport`) belong to that subsystem. If the
+    /// target does not belong to any subsystem, `None` is returned.
+    pub fn from_target(target: &str) -> Option<Subsystem> {
+        Self::iter().find(|s| {
+            target
+                .strip_prefix(s.target())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        })
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|s| s == self).unwrap()
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.target().fmt(f)
+    }
+}
+
+/// Builder for env_logger filter strings.
+///
+/// This builds a filter string in the format used by
+/// [env_logger](https://docs.rs/env_logger/latest/env_logger/), with a default
+/// log level and a log level for some subsystems. The filter string is obtained
+/// with the `Display` implementation of `FilterSpec`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct FilterSpec {
+    default: Option<LevelFilter>,
+    levels: [Option<LevelFilter>; NUM_SUBSYSTEMS],
+}
+
+impl FilterSpec {
+    /// Creates a new filter specification without any log levels.
+    pub fn new() -> FilterSpec {
+        FilterSpec::default()
+    }
+
+    /// Sets the default log level.
+    ///
+    /// This log level applies to all targets that do not have a specific log
+    /// level, including those outside the galileo_osnma crate.
+    pub fn default_level(mut self, level: LevelFilter) -> FilterSpec {
+        self.default = Some(level);
+        self
+    }
+
+    /// Sets the log level for a subsystem.
+    pub fn level(mut self, subsystem: Subsystem, level: LevelFilter) -> FilterSpec {
+        self.levels[subsystem.index()] = Some(level);
+        self
+    }
+
+    /// Sets the same log level for all the subsystems.
+    pub fn all_subsystems(mut self, level: LevelFilter) -> FilterSpec {
+        self.levels = [Some(level); NUM_SUBSYSTEMS];
+        self
+    }
+}
+
+fn level_name(level: LevelFilter) -> &'static str {
+    match level {
+        LevelFilter::Off => "off",
+        LevelFilter::Error => "error",
+        LevelFilter::Warn => "warn",
+        LevelFilter::Info => "info",
+        LevelFilter::Debug => "debug",
+        LevelFilter::Trace => "trace",
+    }
+}
+
+impl fmt::Display for FilterSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        if let Some(level) = self.default {
+            f.write_str(level_name(level))?;
+            first = false;
+        }
+        for (subsystem, level) in Subsystem::iter().zip(self.levels.iter()) {
+            if let Some(level) = level {
+                if !first {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}={}", subsystem.target(), level_name(*level))?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Logger wrapper with per-subsystem log levels.
+///
+/// This wraps a [`Log`] implementation `L` and forwards to it only the records
+/// whose level is enabled for their subsystem. The log level of each subsystem
+/// can be changed at runtime with [`SubsystemFilter::set_level`], even after
+/// the `SubsystemFilter` has been installed as the global logger. Records with
+/// targets that do not belong to any subsystem use the default level, which can
+/// be changed with [`SubsystemFilter::set_default_level`].
+///
+/// Note that the global maximum log level set with
+/// [`log::set_max_level`] still applies.
+#[derive(Debug)]
+pub struct SubsystemFilter<L> {
+    inner: L,
+    default: AtomicU8,
+    levels: [AtomicU8; NUM_SUBSYSTEMS],
+}
+
+fn level_to_u8(level: LevelFilter) -> u8 {
+    match level {
+        LevelFilter::Off => 0,
+        LevelFilter::Error => 1,
+        LevelFilter::Warn => 2,
+        LevelFilter::Info => 3,
+        LevelFilter::Debug => 4,
+        LevelFilter::Trace => 5,
+    }
+}
+
+fn level_from_u8(level: u8) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+impl<L> SubsystemFilter<L> {
+    /// Creates a new filter wrapping the logger `inner`.
+    ///
+    /// Initially, all the subsystems and the default level are set to
+    /// `level`.
+    pub const fn new(inner: L, level: LevelFilter) -> SubsystemFilter<L> {
+        // level_to_u8 is not const, so we need to do this here
+        let level = level as u8;
+        SubsystemFilter {
+            inner,
+            default: AtomicU8::new(level),
+            levels: [
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+                AtomicU8::new(level),
+            ],
+        }
+    }
+
+    /// Sets the log level of a subsystem.
+    pub fn set_level(&self, subsystem: Subsystem, level: LevelFilter) {
+        self.levels[subsystem.index()].store(level_to_u8(level), Ordering::Relaxed);
+    }
+
+    /// Gives the log level of a subsystem.
+    pub fn level(&self, subsystem: Subsystem) -> LevelFilter {
+        level_from_u8(self.levels[subsystem.index()].load(Ordering::Relaxed))
+    }
+
+    /// Sets the log level for targets that do not belong to any subsystem.
+    pub fn set_default_level(&self, level: LevelFilter) {
+        self.default.store(level_to_u8(level), Ordering::Relaxed);
+    }
+
+    /// Gives the log level for targets that do not belong to any subsystem.
+    pub fn default_level(&self) -> LevelFilter {
+        level_from_u8(self.default.load(Ordering::Relaxed))
+    }
+
+    /// Gives a reference to the wrapped logger.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn target_level(&self, target: &str) -> LevelFilter {
+        match Subsystem::from_target(target) {
+            Some(subsystem) => self.level(subsystem),
+            None => self.default_level(),
+        }
+    }
+}
+
+impl<L: Log> Log for SubsystemFilter<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.target_level(metadata.target()) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use log::Level;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn from_target() {
+        for subsystem in Subsystem::iter() {
+            assert_eq!(Subsystem::from_target(subsystem.target()), Some(subsystem));
+        }
+        assert_eq!(
+            Subsystem::from_target("galileo_osnma::galmon::transport"),
+            Some(Subsystem::Galmon)
+        );
+        assert_eq!(Subsystem::from_target("galileo_osnma::dsmx"), None);
+        assert_eq!(Subsystem::from_target("other_crate"), None);
+    }
+
+    #[test]
+    fn filter_spec() {
+        assert_eq!(FilterSpec::new().to_string(), "");
+        assert_eq!(
+            FilterSpec::new()
+                .level(Subsystem::Tesla, LevelFilter::Trace)
+                .to_string(),
+            "galileo_osnma::tesla=trace"
+        );
+        assert_eq!(
+            FilterSpec::new()
+                .default_level(LevelFilter::Warn)
+                .all_subsystems(LevelFilter::Off)
+                .level(Subsystem::Osnma, LevelFilter::Info)
+                .to_string(),
+            "warn,galileo_osnma::dsm=off,galileo_osnma::galmon=off,\
+             galileo_osnma::mack=off,galileo_osnma::navmessage=off,\
+             galileo_osnma::osnma=info,galileo_osnma::subframe=off,\
+             galileo_osnma::tesla=off"
+        );
+    }
+
+    #[derive(Default)]
+    struct CountLogger(AtomicUsize);
+
+    impl Log for CountLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, _: &Record) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn subsystem_filter() {
+        let filter = SubsystemFilter::new(CountLogger::default(), LevelFilter::Info);
+        filter.set_level(Subsystem::Dsm, LevelFilter::Trace);
+        filter.set_level(Subsystem::Navmessage, LevelFilter::Off);
+        assert_eq!(filter.level(Subsystem::Dsm), LevelFilter::Trace);
+        assert_eq!(filter.level(Subsystem::Tesla), LevelFilter::Info);
+        let log = |target, level| {
+            filter.log(
+                &Record::builder()
+                    .target(target)
+                    .level(level)
+                    .args(format_args!("test"))
+                    .build(),
+            )
+        };
+        log("galileo_osnma::dsm", Level::Trace);
+        log("galileo_osnma::navmessage", Level::Error);
+        log("galileo_osnma::tesla", Level::Info);
+        log("galileo_osnma::tesla", Level::Debug);
+        log("other_crate", Level::Warn);
+        assert_eq!(filter.inner().0.load(Ordering::Relaxed), 3);
+    }
+}
@@ -5,7 +5,7 @@
 
 use crate::bitfields::NmaStatus;
 use crate::gst::Gst;
-use crate::storage::StaticStorage;
+use crate::storage::{SatelliteSlots, Storage};
 use crate::types::MackMessage;
 use crate::Svn;
 use generic_array::GenericArray;
@@ -15,29 +15,37 @@ use typenum::Unsigned;
 ///
 /// This struct is a container that stores a history of MACK messages, so that
 /// they can be used when the TESLA keys corresponding to their tags become
-/// available. The storage size is statically allocated, and as new messages are
-/// stored, the older ones are deleted.
+/// available. The storage size is given by the [`Storage`] type parameter `S`,
+/// and as new messages are stored, the older ones are deleted.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct MackStorage<S: StaticStorage> {
-    macks: GenericArray<Option<Mack>, S::MackDepthSats>,
+pub struct MackStorage<S: Storage> {
+    macks: S::MackSlots<Option<Mack>>,
     gsts: GenericArray<Option<Gst>, S::MackDepth>,
     write_pointer: usize,
 }
 
 #[doc(hidden)]
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-// This is pub only because it appears in the definition of StaticStorageTypenum
+// This is pub only because it appears in the definition of MackStorage
 pub struct Mack {
     message: MackMessage,
     svn: Svn,
     nma_status: NmaStatus,
+    // CID in the NMA header of the subframe, if known.
+    cid: Option<u8>,
 }
 
-impl<S: StaticStorage> MackStorage<S> {
+impl<S: Storage> MackStorage<S> {
     /// Creates a new, empty store of MACK messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage type `S` does not follow the consistency rules
+    /// described in [`Storage`].
     pub fn new() -> MackStorage<S> {
+        S::assert_consistent();
         MackStorage {
-            macks: GenericArray::default(),
+            macks: Default::default(),
             gsts: GenericArray::default(),
             write_pointer: 0,
         }
@@ -53,21 +61,101 @@ impl<S: StaticStorage> MackStorage<S> {
     /// tracking.
     ///
     /// The `gst` parameter gives the GST at the start of the subframe when the
-    /// MACK message was transmitted.
+    /// MACK message was transmitted. If the GST does not correspond to the start
+    /// of a subframe, the start of the subframe containing it is used. This makes
+    /// MACK messages collected from E1B and E5b, whose page timings are offset,
+    /// map to the same storage entry.
     ///
     /// The `nma_status` gives the NMA Status in the subframe where the MACK
     /// message was transmitted.
-    pub fn store(&mut self, mack: &MackMessage, svn: Svn, gst: Gst, nma_status: NmaStatus) {
-        self.adjust_write_pointer(gst);
-        for location in self.current_macks_as_mut().iter_mut() {
-            if location.is_none() {
-                log::trace!("storing MACK {:02x?} for {} and GST {:?}", mack, svn, gst);
-                *location = Some(Mack {
-                    message: *mack,
-                    svn,
-                    nma_status,
-                });
-                return;
+    ///
+    /// A MACK message for a subframe older than the most recent stored
+    /// subframe is still stored if its subframe is present in the storage.
+    ///
+    /// The function returns `true` if the MACK message has been stored. If a
+    /// MACK message for the same SVN and subframe is already stored (for
+    /// instance, because it has been received in both E1B and E5b), the new
+    /// MACK message is not stored and the function returns `false`. If both
+    /// messages differ, a warning is logged.
+    pub fn store(&mut self, mack: &MackMessage, svn: Svn, gst: Gst, nma_status: NmaStatus) -> bool {
+        self.store_inner(mack, svn, gst, nma_status, None)
+    }
+
+    /// Store a MACK message together with the chain ID of its subframe.
+    ///
+    /// This is the same as [`MackStorage::store`], but it also stores the CID
+    /// of the NMA header of the subframe where the MACK message was
+    /// transmitted. It can be obtained with [`MackStorage::chain_id`] to
+    /// select the TESLA chain that generated the MACK message when two chains
+    /// are broadcast during a chain renewal.
+    pub fn store_with_chain_id(
+        &mut self,
+        mack: &MackMessage,
+        svn: Svn,
+        gst: Gst,
+        nma_status: NmaStatus,
+        cid: u8,
+    ) -> bool {
+        self.store_inner(mack, svn, gst, nma_status, Some(cid))
+    }
+
+    fn store_inner(
+        &mut self,
+        mack: &MackMessage,
+        svn: Svn,
+        gst: Gst,
+        nma_status: NmaStatus,
+        cid: Option<u8>,
+    ) -> bool {
+        let gst = gst.gst_subframe();
+        let Some(gst_idx) = self.gst_index_for_store(gst) else {
+            log::warn!(
+                "MACK {:02x?} for {} and GST {:?} is too old to be stored",
+                mack,
+                svn,
+                gst
+            );
+            return false;
+        };
+        // A growable storage makes room for a new satellite if all the
+        // locations for this subframe are taken by other satellites.
+        if self
+            .macks(gst_idx)
+            .iter()
+            .all(|location| location.as_ref().is_some_and(|m| m.svn != svn))
+            && self.macks.grow()
+        {
+            log::debug!("grew MACK storage to {} satellites", self.macks.num_sats());
+        }
+        for location in self.macks_as_mut(gst_idx).iter_mut() {
+            match location {
+                Some(stored) if stored.svn == svn => {
+                    if stored.message == *mack && stored.nma_status == nma_status {
+                        log::trace!("MACK for {} and GST {:?} already stored", svn, gst);
+                    } else {
+                        log::warn!(
+                            "conflicting MACK for {} and GST {:?}: \
+                             stored = {:02x?}, just received = {:02x?}; \
+                             keeping the stored MACK",
+                            svn,
+                            gst,
+                            stored.message,
+                            mack
+                        );
+                    }
+                    return false;
+                }
+                Some(_) => (),
+                None => {
+                    log::trace!("storing MACK {:02x?} for {} and GST {:?}", mack, svn, gst);
+                    *location = Some(Mack {
+                        message: *mack,
+                        svn,
+                        nma_status,
+                        cid,
+                    });
+                    return true;
+                }
             }
         }
         log::warn!(
@@ -76,18 +164,37 @@ impl<S: StaticStorage> MackStorage<S> {
             svn,
             gst
         );
+        false
     }
 
-    fn current_macks_as_mut(&mut self) -> &mut [Option<Mack>] {
-        &mut self.macks[self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+    fn macks(&self, gst_idx: usize) -> &[Option<Mack>] {
+        let num_sats = self.macks.num_sats();
+        &self.macks[gst_idx * num_sats..(gst_idx + 1) * num_sats]
     }
 
-    fn adjust_write_pointer(&mut self, gst: Gst) {
-        // If write pointer points to a valid GST which is distinct
-        // from the current, we advance the write pointer and erase
-        // everything at the new write pointer location.
-        if let Some(g) = self.gsts[self.write_pointer] {
-            if g != gst {
+    fn macks_as_mut(&mut self, gst_idx: usize) -> &mut [Option<Mack>] {
+        let num_sats = self.macks.num_sats();
+        &mut self.macks[gst_idx * num_sats..(gst_idx + 1) * num_sats]
+    }
+
+    fn find_gst(&self, gst: Gst) -> Option<usize> {
+        self.gsts
+            .iter()
+            .enumerate()
+            .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })
+    }
+
+    // Returns the index where MACKs for the given GST should be stored.
+    //
+    // If the GST is newer than the GST at the write pointer, we advance the
+    // write pointer and erase everything at the new write pointer location. If
+    // it is older, we look for it in the storage, returning None if it is not
+    // present.
+    fn gst_index_for_store(&mut self, gst: Gst) -> Option<usize> {
+        match self.gsts[self.write_pointer] {
+            Some(g) if g == gst => Some(self.write_pointer),
+            Some(g) if gst.is_before(g) => self.find_gst(gst),
+            Some(g) => {
                 log::trace!(
                     "got a new GST {:?} (current GST is {:?}); \
                              advancing write pointer",
@@ -95,10 +202,15 @@ impl<S: StaticStorage> MackStorage<S> {
                     g
                 );
                 self.write_pointer = (self.write_pointer + 1) % S::MackDepth::USIZE;
-                self.current_macks_as_mut().fill(None);
+                self.macks_as_mut(self.write_pointer).fill(None);
+                self.gsts[self.write_pointer] = Some(gst);
+                Some(self.write_pointer)
+            }
+            None => {
+                self.gsts[self.write_pointer] = Some(gst);
+                Some(self.write_pointer)
             }
         }
-        self.gsts[self.write_pointer] = Some(gst);
     }
 
     /// Try to retrieve a MACK message.
@@ -114,26 +226,177 @@ impl<S: StaticStorage> MackStorage<S> {
     /// The `gst` parameter refers to the GST at the start of the subframe when the
     /// MACK message was transmitted.
     pub fn get(&self, svn: Svn, gst: Gst) -> Option<(&MackMessage, NmaStatus)> {
-        let gst_idx =
-            self.gsts
-                .iter()
-                .enumerate()
-                .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })?;
-        self.macks[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
+        let gst_idx = self.find_gst(gst)?;
+        self.macks(gst_idx).iter().find_map(|x| match x {
+            Some(Mack {
+                svn: s,
+                message,
+                nma_status,
+                ..
+            }) if *s == svn => Some((message, *nma_status)),
+            _ => None,
+        })
+    }
+
+    /// Gives the chain ID of a stored MACK message.
+    ///
+    /// This gives the CID of the NMA header of the subframe where the MACK
+    /// message for `svn` and `gst` was transmitted. If the MACK message is not
+    /// stored or it was stored without a chain ID (see
+    /// [`MackStorage::store_with_chain_id`]), this returns `None`.
+    pub fn chain_id(&self, svn: Svn, gst: Gst) -> Option<u8> {
+        let gst_idx = self.find_gst(gst)?;
+        self.macks(gst_idx)
             .iter()
-            .find_map(|x| match x {
-                Some(Mack {
-                    svn: s,
-                    message,
-                    nma_status,
-                }) if *s == svn => Some((message, *nma_status)),
-                _ => None,
+            .flatten()
+            .find(|m| m.svn == svn)
+            .and_then(|m| m.cid)
+    }
+
+    /// Iterate over all the stored MACK messages.
+    ///
+    /// Returns an iterator over all the MACK messages that are currently held
+    /// in the storage. The items of the iterator are tuples containing the SVN
+    /// of the satellite that transmitted the MACK message, the GST at the start
+    /// of the subframe when the MACK message was transmitted, the MACK message,
+    /// and its corresponding NMA Status. The MACK messages are given in order of
+    /// increasing GST.
+    pub fn iter(&self) -> impl Iterator<Item = (Svn, Gst, &MackMessage, NmaStatus)> + '_ {
+        let depth = S::MackDepth::USIZE;
+        (1..=depth).flat_map(move |j| {
+            let gst_idx = (self.write_pointer + j) % depth;
+            let macks = self.macks(gst_idx);
+            self.gsts[gst_idx].into_iter().flat_map(move |gst| {
+                macks
+                    .iter()
+                    .flatten()
+                    .map(move |m| (m.svn, gst, &m.message, m.nma_status))
             })
+        })
     }
 }
 
-impl<S: StaticStorage> Default for MackStorage<S> {
+impl<S: Storage> Default for MackStorage<S> {
     fn default() -> MackStorage<S> {
         MackStorage::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::SmallStorage;
+    use crate::types::MACK_MESSAGE_BYTES;
+
+    #[test]
+    fn duplicates() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let svn = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1234, 300);
+        let mack = [0x5a; MACK_MESSAGE_BYTES];
+        assert!(storage.store(&mack, svn, gst, NmaStatus::Test));
+        // Same MACK received on another band with a GST inside the subframe
+        assert!(!storage.store(&mack, svn, gst.add_seconds(1), NmaStatus::Test));
+        // Conflicting MACK
+        let other = [0xa5; MACK_MESSAGE_BYTES];
+        assert!(!storage.store(&other, svn, gst, NmaStatus::Test));
+        assert_eq!(storage.get(svn, gst), Some((&mack, NmaStatus::Test)));
+        // Only one entry for this SVN should have been used
+        assert_eq!(storage.macks.iter().flatten().count(), 1);
+    }
+
+    #[test]
+    fn late_mack() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let svn0 = Svn::try_from(1).unwrap();
+        let svn1 = Svn::try_from(2).unwrap();
+        let gst0 = Gst::new(1234, 300);
+        let gst1 = gst0.add_subframes(1);
+        let mack0 = [0; MACK_MESSAGE_BYTES];
+        let mack1 = [1; MACK_MESSAGE_BYTES];
+        assert!(storage.store(&mack0, svn0, gst0, NmaStatus::Test));
+        assert!(storage.store(&mack1, svn0, gst1, NmaStatus::Test));
+        // MACK for the previous subframe arriving late
+        assert!(storage.store(&mack0, svn1, gst0, NmaStatus::Test));
+        assert_eq!(storage.get(svn1, gst0), Some((&mack0, NmaStatus::Test)));
+        assert_eq!(storage.get(svn0, gst1), Some((&mack1, NmaStatus::Test)));
+        // MACK for a subframe that is no longer in the storage
+        assert!(!storage.store(&mack0, svn1, gst0.add_subframes(-1), NmaStatus::Test));
+        assert_eq!(storage.get(svn0, gst0), Some((&mack0, NmaStatus::Test)));
+    }
+
+    #[test]
+    fn iter() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        assert_eq!(storage.iter().count(), 0);
+        let svn0 = Svn::try_from(1).unwrap();
+        let svn1 = Svn::try_from(2).unwrap();
+        let gst0 = Gst::new(1234, 300);
+        let mack0 = [0; MACK_MESSAGE_BYTES];
+        let mack1 = [1; MACK_MESSAGE_BYTES];
+        let mack2 = [2; MACK_MESSAGE_BYTES];
+        storage.store(&mack0, svn0, gst0, NmaStatus::Test);
+        storage.store(&mack1, svn1, gst0, NmaStatus::Operational);
+        storage.store(&mack2, svn0, gst0.add_subframes(1), NmaStatus::Test);
+        let expected = [
+            (svn0, gst0, &mack0, NmaStatus::Test),
+            (svn1, gst0, &mack1, NmaStatus::Operational),
+            (svn0, gst0.add_subframes(1), &mack2, NmaStatus::Test),
+        ];
+        assert!(storage.iter().eq(expected));
+        // The oldest subframe is dropped when a new one arrives (SmallStorage
+        // only holds 2 subframes).
+        storage.store(&mack0, svn1, gst0.add_subframes(2), NmaStatus::Test);
+        let expected = [
+            (svn0, gst0.add_subframes(1), &mack2, NmaStatus::Test),
+            (svn1, gst0.add_subframes(2), &mack0, NmaStatus::Test),
+        ];
+        assert!(storage.iter().eq(expected));
+    }
+
+    #[test]
+    fn week_rollover() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let svn = Svn::try_from(1).unwrap();
+        let gst0 = Gst::new(1234, 7 * 24 * 3600 - 30);
+        let gst1 = gst0.add_subframes(1);
+        assert_eq!(gst1, Gst::new(1235, 0));
+        let mack0 = [0; MACK_MESSAGE_BYTES];
+        let mack1 = [1; MACK_MESSAGE_BYTES];
+        assert!(storage.store(&mack0, svn, gst0, NmaStatus::Test));
+        assert!(storage.store(&mack1, svn, gst1, NmaStatus::Test));
+        assert_eq!(storage.get(svn, gst0), Some((&mack0, NmaStatus::Test)));
+        assert_eq!(storage.get(svn, gst1), Some((&mack1, NmaStatus::Test)));
+        // A late MACK from the previous week is still accepted
+        let svn1 = Svn::try_from(2).unwrap();
+        assert!(storage.store(&mack0, svn1, gst0, NmaStatus::Test));
+        assert!(storage
+            .iter()
+            .map(|(_, gst, _, _)| gst)
+            .eq([gst0, gst0, gst1]));
+    }
+
+    #[test]
+    fn wn_rollover() {
+        let mut storage = MackStorage::<SmallStorage>::new();
+        let svn = Svn::try_from(1).unwrap();
+        let gst0 = Gst::new(4095, 604770);
+        let gst1 = gst0.wrapping_add_subframes(1);
+        assert_eq!(gst1, Gst::new(0, 0));
+        let mack0 = [0; MACK_MESSAGE_BYTES];
+        let mack1 = [1; MACK_MESSAGE_BYTES];
+        let mack2 = [2; MACK_MESSAGE_BYTES];
+        assert!(storage.store(&mack0, svn, gst0, NmaStatus::Test));
+        assert!(storage.store(&mack1, svn, gst1, NmaStatus::Test));
+        assert_eq!(storage.get(svn, gst0), Some((&mack0, NmaStatus::Test)));
+        assert_eq!(storage.get(svn, gst1), Some((&mack1, NmaStatus::Test)));
+        // A late MACK from before the rollover is still accepted
+        let svn1 = Svn::try_from(2).unwrap();
+        assert!(storage.store(&mack0, svn1, gst0, NmaStatus::Test));
+        // Storage keeps advancing after the rollover
+        let gst2 = gst1.wrapping_add_subframes(1);
+        assert!(storage.store(&mack2, svn, gst2, NmaStatus::Test));
+        assert_eq!(storage.get(svn, gst2), Some((&mack2, NmaStatus::Test)));
+        assert_eq!(storage.get(svn, gst1), Some((&mack1, NmaStatus::Test)));
+    }
+}
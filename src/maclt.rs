@@ -183,6 +183,19 @@ pub fn get_maclt_entry(maclt: u8, msg: usize, num_tag: usize) -> Result<MacLTSlo
     Ok(entry)
 }
 
+/// Returns the number of tags of a MAC Look-up Table entry.
+///
+/// This function returns the value of the 'nt' column of the MAC Look-up Table
+/// for a particular `maclt` ID. This is the number of tags in each MACK
+/// message, including tag0. If the ID does not exist in the table, an error is
+/// returned.
+pub fn get_maclt_nt(maclt: u8) -> Result<usize, MacLTError> {
+    let Some(entry) = MACLT.iter().find(|&x| x.id == maclt) else {
+        return Err(MacLTError::InvalidMaclt);
+    };
+    Ok(entry.nt.into())
+}
+
 /// Returns an iterator over the indices corresponding to FLX entries.
 ///
 /// This function returns an iterator over the indices corresponding to FLX
@@ -279,6 +292,14 @@ mod test {
         assert_eq!(get_maclt_entry(34, 0, 6), Err(MacLTError::InvalidTagNumber));
     }
 
+    #[test]
+    fn nt() {
+        assert_eq!(get_maclt_nt(28), Ok(10));
+        assert_eq!(get_maclt_nt(34), Ok(6));
+        assert_eq!(get_maclt_nt(41), Ok(4));
+        assert_eq!(get_maclt_nt(26), Err(MacLTError::InvalidMaclt));
+    }
+
     #[test]
     #[should_panic]
     fn lookup_wrong_msg() {
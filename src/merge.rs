@@ -0,0 +1,670 @@
+//! Merging of the data received by several receivers.
+//!
+//! Stations that feed data from several receivers into the same OSNMA black
+//! box receive most INAV pages more than once. This module contains the
+//! [`Merger`], which collects the copies of each page received by the
+//! different sources, identified by the SVN, GST and band of the page, and
+//! gives a single copy of each page, which can be fed into an [`Osnma`] black
+//! box with [`Merger::feed_into`].
+//!
+//! When the copies of a page differ, the INAV word and the OSNMA data message
+//! are chosen separately by voting: the contents received by the largest
+//! number of sources are used, and ties are broken in favour of the copy that
+//! was received first. Differences between the copies are not expected, since
+//! all the receivers track the same signals, so they are reported as
+//! [`Discrepancy`] values, which are a potential indicator of spoofing (or of
+//! a malfunctioning receiver).
+//!
+//! Since the sources deliver their data with different latencies, each page is
+//! held until all the sources have delivered it, or until the GST of the data
+//! fed into the merger is [`Merger::with_delay`] seconds later than the page.
+//! The pages of each satellite are given in chronological order.
+//!
+//! The [`MergeReader`] can be used to read data from several blocking sources
+//! at the same time. With the `galmon` feature, `MergeReader::from_galmon`
+//! reads Galmon streams from several stations, and `Merger::feed_galmon`
+//! feeds their packets into the merger.
+//!
+//! This module is only available if the `std` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::merge::Merger;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Gst, InavBand, Osnma, PublicKey, Svn};
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//! let mut merger = Merger::new(3);
+//! let svn = Svn::try_from(11).unwrap();
+//! let gst = Gst::new(1177, 175770);
+//! // Receiver 1 gets a different INAV word than receivers 0 and 2
+//! merger.feed_inav(0, &[0; 16], svn, gst, InavBand::E1B);
+//! merger.feed_inav(1, &[1; 16], svn, gst, InavBand::E1B);
+//! merger.feed_inav(2, &[0; 16], svn, gst, InavBand::E1B);
+//! merger.flush();
+//! merger.feed_into(&mut osnma);
+//! for discrepancy in merger.discrepancies() {
+//!     println!("{discrepancy}");
+//! }
+//! ```
+
+use crate::storage::Storage;
+use crate::types::{InavBand, InavWord, OsnmaDataMessage};
+use crate::verifier::Verifier;
+use crate::{Gst, Osnma, Svn};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::mpsc;
+
+#[cfg(feature = "galmon")]
+use crate::galmon::{navmon::NavMonMessage, transport::ReadTransport, GalmonState};
+
+// Default number of seconds that a page is held waiting for other sources.
+const DEFAULT_DELAY: u32 = 4;
+
+// Pages are sorted by time first, so that they are given in chronological
+// order. The time is the number of seconds elapsed since the origin of the
+// merger, which keeps the order across the week number rollover, unlike the
+// Ord implementation of Gst. The SVN and the band are stored as integers
+// because they do not implement Ord.
+type PageKey = (i64, u8, u8);
+
+fn page_key(origin: Gst, svn: Svn, gst: Gst, band: InavBand) -> PageKey {
+    let band = match band {
+        InavBand::E1B => 0,
+        InavBand::E5B => 1,
+    };
+    (gst.difference_seconds(origin), u8::from(svn), band)
+}
+
+/// Merged INAV page.
+///
+/// This contains the INAV word and OSNMA data message of a page as chosen by
+/// the [`Merger`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MergedPage {
+    /// INAV word.
+    ///
+    /// This is `None` if only the OSNMA data message of the page was
+    /// received.
+    pub word: Option<InavWord>,
+    /// OSNMA data message.
+    ///
+    /// This is `None` if no source received the OSNMA data message of the
+    /// page.
+    pub osnma: Option<OsnmaDataMessage>,
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the page transmission.
+    pub gst: Gst,
+    /// Band in which the page was received.
+    pub band: InavBand,
+    /// Number of sources that received the page.
+    pub num_sources: usize,
+}
+
+impl MergedPage {
+    /// Feeds the page into an OSNMA black box.
+    pub fn feed_into<S: Storage, V: Verifier>(&self, osnma: &mut Osnma<S, V>) {
+        if let Some(word) = &self.word {
+            osnma.feed_inav(word, self.svn, self.gst, self.band);
+        }
+        if let Some(data) = &self.osnma {
+            osnma.feed_osnma_band(data, self.svn, self.gst, self.band);
+        }
+    }
+}
+
+/// Field of an INAV page.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PageField {
+    /// INAV word.
+    InavWord,
+    /// OSNMA data message.
+    OsnmaData,
+}
+
+impl fmt::Display for PageField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageField::InavWord => "INAV word".fmt(f),
+            PageField::OsnmaData => "OSNMA data".fmt(f),
+        }
+    }
+}
+
+/// Discrepancy between the copies of a page received by different sources.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Discrepancy {
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the page transmission.
+    pub gst: Gst,
+    /// Band in which the page was received.
+    pub band: InavBand,
+    /// Field of the page whose copies differ.
+    pub field: PageField,
+    /// Sources whose copy was chosen by voting.
+    pub majority: Vec<usize>,
+    /// Sources whose copy differs from the one chosen by voting.
+    pub dissenting: Vec<usize>,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} at {} in {}: sources {:?} disagree with sources {:?}",
+            self.svn, self.field, self.gst, self.band, self.dissenting, self.majority
+        )
+    }
+}
+
+/// Statistics of the [`Merger`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct MergeStats {
+    /// Number of copies of pages fed into the merger.
+    pub received: u64,
+    /// Number of merged pages given by the merger.
+    pub merged: u64,
+    /// Number of copies that were received after the page had already been
+    /// given by the merger, and were discarded.
+    pub late: u64,
+    /// Number of discrepancies found.
+    pub discrepancies: u64,
+}
+
+#[derive(Debug, Clone)]
+struct PendingPage {
+    svn: Svn,
+    gst: Gst,
+    band: InavBand,
+    // Copies received, in order of arrival.
+    words: Vec<(usize, InavWord)>,
+    osnma: Vec<(usize, OsnmaDataMessage)>,
+    sources: Vec<usize>,
+}
+
+impl PendingPage {
+    fn new(svn: Svn, gst: Gst, band: InavBand) -> PendingPage {
+        PendingPage {
+            svn,
+            gst,
+            band,
+            words: Vec::new(),
+            osnma: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    fn add<T>(copies: &mut Vec<(usize, T)>, source: usize, value: T) {
+        // A repeated copy from the same source replaces the previous one.
+        match copies.iter_mut().find(|(s, _)| *s == source) {
+            Some(copy) => copy.1 = value,
+            None => copies.push((source, value)),
+        }
+    }
+}
+
+// Chooses the value received by the most sources, breaking ties in favour of
+// the first value received. Returns the value and the sources that voted for
+// it and against it.
+fn vote<T: PartialEq + Copy>(copies: &[(usize, T)]) -> Option<(T, Vec<usize>, Vec<usize>)> {
+    let count = |value: &T| copies.iter().filter(|(_, v)| v == value).count();
+    let mut winner = copies.first()?.1;
+    let mut winner_count = count(&winner);
+    for (_, value) in copies {
+        let n = count(value);
+        if n > winner_count {
+            winner = *value;
+            winner_count = n;
+        }
+    }
+    let (majority, dissenting) = copies.iter().partition::<Vec<_>, _>(|(_, v)| *v == winner);
+    Some((
+        winner,
+        majority.into_iter().map(|&(s, _)| s).collect(),
+        dissenting.into_iter().map(|&(s, _)| s).collect(),
+    ))
+}
+
+/// Merger of the data received by several sources.
+///
+/// See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Merger {
+    num_sources: usize,
+    delay: u32,
+    pending: BTreeMap<PageKey, PendingPage>,
+    // Pages given recently, which are used to detect late copies.
+    given: BTreeSet<PageKey>,
+    // GST from which the times of the page keys are measured.
+    origin: Option<Gst>,
+    latest_gst: Option<Gst>,
+    ready: VecDeque<MergedPage>,
+    discrepancies: VecDeque<Discrepancy>,
+    stats: MergeStats,
+    #[cfg(feature = "galmon")]
+    galmon: Vec<GalmonState>,
+}
+
+impl Merger {
+    /// Constructs a new merger for `num_sources` sources.
+    ///
+    /// The sources are identified by the indices `0..num_sources`. Data from
+    /// other sources can be fed too, but pages are only given before the delay
+    /// elapses if they have been received by all of the sources
+    /// `0..num_sources`.
+    pub fn new(num_sources: usize) -> Merger {
+        Merger {
+            num_sources,
+            delay: DEFAULT_DELAY,
+            pending: BTreeMap::new(),
+            given: BTreeSet::new(),
+            origin: None,
+            latest_gst: None,
+            ready: VecDeque::new(),
+            discrepancies: VecDeque::new(),
+            stats: MergeStats::default(),
+            #[cfg(feature = "galmon")]
+            galmon: Vec::new(),
+        }
+    }
+
+    /// Sets the number of seconds that a page is held waiting for the sources
+    /// that have not delivered it yet.
+    ///
+    /// The delay is measured in GST, with respect to the most recent GST fed
+    /// into the merger. It should be larger than the difference in latency
+    /// between the sources. The default is 4 seconds.
+    pub fn with_delay(mut self, seconds: u32) -> Merger {
+        self.delay = seconds;
+        self
+    }
+
+    /// Feeds an INAV word received by a source.
+    pub fn feed_inav(
+        &mut self,
+        source: usize,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) {
+        if let Some(page) = self.pending_page(source, svn, gst, band) {
+            PendingPage::add(&mut page.words, source, *word);
+        }
+        self.update();
+    }
+
+    /// Feeds an OSNMA data message received by a source.
+    pub fn feed_osnma(
+        &mut self,
+        source: usize,
+        osnma: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) {
+        if let Some(page) = self.pending_page(source, svn, gst, band) {
+            PendingPage::add(&mut page.osnma, source, *osnma);
+        }
+        self.update();
+    }
+
+    /// Feeds a navmon packet received by a source.
+    ///
+    /// The packets of each source are preprocessed separately, as in
+    /// [`Osnma::feed_galmon`].
+    #[cfg(feature = "galmon")]
+    pub fn feed_galmon(&mut self, source: usize, packet: &NavMonMessage) {
+        if self.galmon.len() <= source {
+            self.galmon.resize_with(source + 1, GalmonState::new);
+        }
+        if let Some(page) = self.galmon[source].process(packet) {
+            self.feed_inav(source, page.word, page.svn, page.gst, page.band);
+            if let Some(osnma) = page.osnma {
+                self.feed_osnma(source, osnma, page.svn, page.gst, page.band);
+            }
+        }
+    }
+
+    /// Gives all the pending pages, without waiting for the remaining sources.
+    ///
+    /// This should be called when the input ends.
+    pub fn flush(&mut self) {
+        while let Some((key, page)) = self.pending.pop_first() {
+            self.given.insert(key);
+            self.merge(page);
+        }
+    }
+
+    /// Returns the next merged page, if any.
+    pub fn poll(&mut self) -> Option<MergedPage> {
+        self.ready.pop_front()
+    }
+
+    /// Feeds all the merged pages into an OSNMA black box.
+    pub fn feed_into<S: Storage, V: Verifier>(&mut self, osnma: &mut Osnma<S, V>) {
+        while let Some(page) = self.poll() {
+            page.feed_into(osnma);
+        }
+    }
+
+    /// Returns an iterator that removes the discrepancies found so far.
+    pub fn discrepancies(&mut self) -> impl Iterator<Item = Discrepancy> + '_ {
+        self.discrepancies.drain(..)
+    }
+
+    /// Gives the statistics of the merger.
+    pub fn stats(&self) -> &MergeStats {
+        &self.stats
+    }
+
+    fn pending_page(
+        &mut self,
+        source: usize,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Option<&mut PendingPage> {
+        self.stats.received += 1;
+        if self.pending.is_empty() && self.given.is_empty() {
+            // The origin can be moved when no keys refer to it
+            self.origin = Some(gst);
+        }
+        let origin = *self.origin.get_or_insert(gst);
+        let key = page_key(origin, svn, gst, band);
+        let expired = self
+            .latest_gst
+            .is_some_and(|latest| latest.difference_seconds(gst) >= i64::from(self.delay));
+        if self.given.contains(&key) || (expired && !self.pending.contains_key(&key)) {
+            log::debug!("discarding late copy of {svn} {gst} {band} from source {source}");
+            self.stats.late += 1;
+            return None;
+        }
+        self.latest_gst = Some(self.latest_gst.map_or(gst, |latest| latest.latest(gst)));
+        let page = self
+            .pending
+            .entry(key)
+            .or_insert_with(|| PendingPage::new(svn, gst, band));
+        if !page.sources.contains(&source) {
+            page.sources.push(source);
+        }
+        Some(page)
+    }
+
+    // Gives the pages that have been received by all the sources or whose
+    // delay has elapsed, in chronological order.
+    fn update(&mut self) {
+        let (Some(latest), Some(origin)) = (self.latest_gst, self.origin) else {
+            return;
+        };
+        let latest_time = latest.difference_seconds(origin);
+        let delay = i64::from(self.delay);
+        while let Some(entry) = self.pending.first_entry() {
+            let page = entry.get();
+            let complete = (0..self.num_sources).all(|s| page.sources.contains(&s));
+            if !complete && latest.difference_seconds(page.gst) < delay {
+                break;
+            }
+            let (key, page) = entry.remove_entry();
+            self.given.insert(key);
+            self.merge(page);
+        }
+        // Pages older than the delay are discarded as late without looking
+        // them up in the given pages.
+        self.given
+            .retain(|&(time, _, _)| latest_time - time < delay);
+    }
+
+    fn merge(&mut self, page: PendingPage) {
+        let word = self.vote(&page, PageField::InavWord, &page.words);
+        let osnma = self.vote(&page, PageField::OsnmaData, &page.osnma);
+        self.stats.merged += 1;
+        self.ready.push_back(MergedPage {
+            word,
+            osnma,
+            svn: page.svn,
+            gst: page.gst,
+            band: page.band,
+            num_sources: page.sources.len(),
+        });
+    }
+
+    fn vote<T: PartialEq + Copy>(
+        &mut self,
+        page: &PendingPage,
+        field: PageField,
+        copies: &[(usize, T)],
+    ) -> Option<T> {
+        let (value, majority, dissenting) = vote(copies)?;
+        if !dissenting.is_empty() {
+            let discrepancy = Discrepancy {
+                svn: page.svn,
+                gst: page.gst,
+                band: page.band,
+                field,
+                majority,
+                dissenting,
+            };
+            log::warn!("cross-receiver discrepancy (potential spoofing): {discrepancy}");
+            self.stats.discrepancies += 1;
+            self.discrepancies.push_back(discrepancy);
+        }
+        Some(value)
+    }
+}
+
+/// Reader of several blocking sources.
+///
+/// This reads items of type `T` from several sources at the same time, using
+/// a thread for each source, and gives the items in the order in which they
+/// are read, together with the index of their source. This is convenient to
+/// feed a [`Merger`] with sources that block, such as TCP connections to
+/// several stations.
+#[derive(Debug)]
+pub struct MergeReader<T> {
+    receiver: mpsc::Receiver<(usize, std::io::Result<Option<T>>)>,
+    active: usize,
+}
+
+impl<T: Send + 'static> MergeReader<T> {
+    /// Constructs a new reader.
+    ///
+    /// Each of the `sources` is a function that reads the next item, and
+    /// returns `None` when the source has ended. The index of each source is
+    /// its position in `sources`. A source whose read fails is closed after
+    /// reporting the error.
+    pub fn new<F>(sources: impl IntoIterator<Item = F>) -> MergeReader<T>
+    where
+        F: FnMut() -> std::io::Result<Option<T>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut active = 0;
+        for (source, mut read) in sources.into_iter().enumerate() {
+            let sender = sender.clone();
+            std::thread::spawn(move || loop {
+                let item = read();
+                let done = !matches!(item, Ok(Some(_)));
+                if sender.send((source, item)).is_err() || done {
+                    break;
+                }
+            });
+            active += 1;
+        }
+        MergeReader { receiver, active }
+    }
+
+    /// Reads the next item from any of the sources.
+    ///
+    /// This blocks until an item is available. It returns `None` when all
+    /// the sources have ended. If the read of a source fails, its error is
+    /// returned, and the other sources can still be read.
+    pub fn read(&mut self) -> std::io::Result<Option<(usize, T)>> {
+        while self.active > 0 {
+            let Ok((source, item)) = self.receiver.recv() else {
+                break;
+            };
+            match item {
+                Ok(Some(item)) => return Ok(Some((source, item))),
+                Ok(None) => self.active -= 1,
+                Err(e) => {
+                    self.active -= 1;
+                    log::error!("source {source} failed: {e}");
+                    return Err(e);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "galmon")]
+impl MergeReader<NavMonMessage> {
+    /// Constructs a reader of several Galmon streams.
+    ///
+    /// The packets read can be fed into a [`Merger`] with
+    /// [`Merger::feed_galmon`].
+    pub fn from_galmon<R: std::io::Read + Send + 'static>(
+        transports: impl IntoIterator<Item = ReadTransport<R>>,
+    ) -> MergeReader<NavMonMessage> {
+        MergeReader::new(
+            transports
+                .into_iter()
+                .map(|mut transport| move || transport.read_packet()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn svn(n: u8) -> Svn {
+        Svn::try_from(n).unwrap()
+    }
+
+    #[test]
+    fn voting() {
+        let gst = Gst::new(1177, 175770);
+        let mut merger = Merger::new(3);
+        merger.feed_inav(0, &[1; 16], svn(11), gst, InavBand::E1B);
+        merger.feed_osnma(0, &[7; 5], svn(11), gst, InavBand::E1B);
+        merger.feed_inav(1, &[2; 16], svn(11), gst, InavBand::E1B);
+        merger.feed_osnma(1, &[7; 5], svn(11), gst, InavBand::E1B);
+        // Still waiting for source 2
+        assert!(merger.poll().is_none());
+        merger.feed_inav(2, &[2; 16], svn(11), gst, InavBand::E1B);
+        let page = merger.poll().unwrap();
+        assert_eq!(page.word, Some([2; 16]));
+        assert_eq!(page.osnma, Some([7; 5]));
+        assert_eq!(page.num_sources, 3);
+        assert!(merger.poll().is_none());
+        let discrepancies: Vec<_> = merger.discrepancies().collect();
+        assert_eq!(
+            discrepancies,
+            [Discrepancy {
+                svn: svn(11),
+                gst,
+                band: InavBand::E1B,
+                field: PageField::InavWord,
+                majority: vec![1, 2],
+                dissenting: vec![0],
+            }]
+        );
+
+        // A late copy is discarded
+        merger.feed_inav(2, &[2; 16], svn(11), gst, InavBand::E1B);
+        assert!(merger.poll().is_none());
+        assert_eq!(
+            *merger.stats(),
+            MergeStats {
+                received: 6,
+                merged: 1,
+                late: 1,
+                discrepancies: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn delay() {
+        let gst = Gst::new(1177, 175770);
+        let mut merger = Merger::new(2).with_delay(4);
+        // Source 1 does not receive E11, and has a larger latency
+        merger.feed_inav(0, &[1; 16], svn(11), gst, InavBand::E1B);
+        merger.feed_inav(0, &[3; 16], svn(19), gst, InavBand::E1B);
+        merger.feed_inav(0, &[4; 16], svn(19), gst.add_seconds(2), InavBand::E1B);
+        merger.feed_inav(1, &[3; 16], svn(19), gst, InavBand::E1B);
+        // E19 has been received by both sources, but it is held after E11 to
+        // keep the chronological order
+        assert!(merger.poll().is_none());
+        merger.feed_inav(0, &[5; 16], svn(19), gst.add_seconds(4), InavBand::E1B);
+        // The delay of E11 has elapsed, which also gives E19
+        let pages: Vec<_> = core::iter::from_fn(|| merger.poll()).collect();
+        assert_eq!(pages.len(), 2);
+        assert!(pages.iter().all(|p| p.gst == gst));
+        assert_eq!(pages[0].num_sources, 1);
+        assert_eq!(pages[1].num_sources, 2);
+        merger.flush();
+        let pages: Vec<_> = core::iter::from_fn(|| merger.poll()).collect();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].gst, gst.add_seconds(2));
+        assert_eq!(pages[1].word, Some([5; 16]));
+        assert_eq!(merger.discrepancies().count(), 0);
+    }
+
+    #[test]
+    fn wn_rollover() {
+        let before = Gst::new(4095, 604798);
+        let after = Gst::new(0, 0);
+        let mut merger = Merger::new(2).with_delay(4);
+        merger.feed_inav(0, &[1; 16], svn(11), before, InavBand::E1B);
+        merger.feed_inav(0, &[2; 16], svn(19), after, InavBand::E1B);
+        // The page before the rollover is given first, even though the page
+        // after the rollover is still waiting for source 1
+        merger.feed_inav(1, &[1; 16], svn(11), before, InavBand::E1B);
+        let page = merger.poll().unwrap();
+        assert_eq!(page.gst, before);
+        assert_eq!(page.num_sources, 2);
+        assert!(merger.poll().is_none());
+        merger.feed_inav(1, &[3; 16], svn(19), after.add_seconds(2), InavBand::E1B);
+        merger.feed_inav(1, &[2; 16], svn(19), after, InavBand::E1B);
+        let page = merger.poll().unwrap();
+        assert_eq!(page.gst, after);
+        assert_eq!(page.num_sources, 2);
+        assert!(merger.poll().is_none());
+        // A late copy of the page before the rollover is discarded
+        merger.feed_inav(1, &[1; 16], svn(11), before, InavBand::E1B);
+        merger.flush();
+        let pages: Vec<_> = core::iter::from_fn(|| merger.poll()).collect();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].gst, after.add_seconds(2));
+        assert_eq!(merger.stats().late, 1);
+    }
+
+    #[test]
+    fn reader() {
+        let sources = (0..3).map(|n| {
+            let mut items = (0..n).collect::<Vec<u32>>().into_iter();
+            move || Ok(items.next())
+        });
+        let mut reader = MergeReader::new(sources);
+        let mut items = Vec::new();
+        while let Some(item) = reader.read().unwrap() {
+            items.push(item);
+        }
+        items.sort();
+        assert_eq!(items, [(1, 0), (2, 0), (2, 1)]);
+    }
+}
@@ -9,6 +9,8 @@ use crate::validation::{NotValidated, Validated};
 use core::fmt;
 use sha2::{Digest, Sha256};
 
+const MERKLE_TREE_DEPTH: usize = 4;
+
 /// Merkle tree.
 ///
 /// This struct represents the OSNMA Merkle tree.
@@ -25,6 +27,11 @@ impl MerkleTree {
         MerkleTree { root }
     }
 
+    /// Gives the root of the Merkle tree.
+    pub fn root(&self) -> &MerkleTreeNode {
+        &self.root
+    }
+
     /// Validates a DSM-PKR containing a public key against this Merkle tree.
     ///
     /// This function checks that the public key in the DSM-PKR message belongs
@@ -72,7 +79,6 @@ impl MerkleTree {
         };
         let mut id = dsm_pkr.message_id();
         let mut node = Self::hash_leaf(leaf);
-        const MERKLE_TREE_DEPTH: usize = 4;
         for j in 0..MERKLE_TREE_DEPTH {
             let is_left = id & 1 == 0;
             let itn = dsm_pkr.intermediate_tree_node(j);
@@ -90,7 +96,7 @@ impl MerkleTree {
         }
     }
 
-    fn hash_leaf(leaf: &[u8]) -> MerkleTreeNode {
+    pub(crate) fn hash_leaf(leaf: &[u8]) -> MerkleTreeNode {
         let mut hash = Sha256::new();
         hash.update(leaf);
         hash.finalize().into()
@@ -132,6 +138,108 @@ impl MerkleTree {
     }
 }
 
+const MERKLE_TREE_LEAVES: usize = 16;
+
+/// Complete Merkle tree.
+///
+/// This struct holds all the nodes of the OSNMA Merkle tree, as opposed to
+/// [`MerkleTree`], which only holds its root. The complete tree is not needed
+/// to validate DSM-PKR messages, but it can be used by test tooling to
+/// generate the intermediate tree nodes that are transmitted in the DSM-PKR
+/// for any message ID.
+///
+/// The nodes are numbered as in Section 6.2 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+/// The node x<sub>j,i</sub> is in level `j` (where level 0 are the hashes of
+/// the leaves and level 4 is the root) and has index `i` within its level.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FullMerkleTree {
+    // Levels are stored consecutively, starting by level 0 (16 nodes) and
+    // ending by level 4 (the root).
+    nodes: [MerkleTreeNode; 2 * MERKLE_TREE_LEAVES - 1],
+}
+
+impl FullMerkleTree {
+    /// Creates a complete Merkle tree from its leaves.
+    ///
+    /// Each of the 16 leaves m<sub>i</sub> is the concatenation of the NPKT,
+    /// NPKID and NPK fields of the corresponding public key, as in the
+    /// DSM-PKR message.
+    pub fn from_leaves(leaves: &[&[u8]; MERKLE_TREE_LEAVES]) -> FullMerkleTree {
+        let mut nodes = [[0; 32]; MERKLE_TREE_LEAVES];
+        for (node, leaf) in nodes.iter_mut().zip(leaves.iter()) {
+            *node = MerkleTree::hash_leaf(leaf);
+        }
+        FullMerkleTree::from_leaf_nodes(&nodes)
+    }
+
+    /// Creates a complete Merkle tree from the nodes in level 0.
+    ///
+    /// The nodes x<sub>0,i</sub> are the hashes of the leaves, and are listed
+    /// in the Merkle tree XML files distributed by the European GNSS Service
+    /// Centre.
+    pub fn from_leaf_nodes(leaf_nodes: &[MerkleTreeNode; MERKLE_TREE_LEAVES]) -> FullMerkleTree {
+        let mut nodes = [[0; 32]; 2 * MERKLE_TREE_LEAVES - 1];
+        nodes[..MERKLE_TREE_LEAVES].copy_from_slice(leaf_nodes);
+        let mut level_start = 0;
+        let mut level_len = MERKLE_TREE_LEAVES;
+        while level_len > 1 {
+            let next_start = level_start + level_len;
+            for i in 0..level_len / 2 {
+                nodes[next_start + i] = MerkleTree::calc_node(
+                    &nodes[level_start + 2 * i],
+                    &nodes[level_start + 2 * i + 1],
+                );
+            }
+            level_start = next_start;
+            level_len /= 2;
+        }
+        FullMerkleTree { nodes }
+    }
+
+    /// Returns the node x<sub>j,i</sub> of the tree.
+    ///
+    /// If `level` is greater than 4 or `index` is outside of the range of the
+    /// level, `None` is returned.
+    pub fn node(&self, level: usize, index: usize) -> Option<&MerkleTreeNode> {
+        if level > MERKLE_TREE_DEPTH || index >= MERKLE_TREE_LEAVES >> level {
+            return None;
+        }
+        let level_start = 2 * MERKLE_TREE_LEAVES - ((2 * MERKLE_TREE_LEAVES) >> level);
+        Some(&self.nodes[level_start + index])
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> &MerkleTreeNode {
+        &self.nodes[2 * MERKLE_TREE_LEAVES - 2]
+    }
+
+    /// Returns a [`MerkleTree`] that holds only the root of this tree.
+    pub fn merkle_tree(&self) -> MerkleTree {
+        MerkleTree::new(*self.root())
+    }
+
+    /// Returns the intermediate tree nodes for a message ID.
+    ///
+    /// This gives the 4 intermediate tree nodes that are transmitted in a
+    /// DSM-PKR with the message ID `message_id`, in the same order as they are
+    /// transmitted. If the message ID is not smaller than 16, `None` is
+    /// returned.
+    pub fn path_for(&self, message_id: u8) -> Option<[MerkleTreeNode; MERKLE_TREE_DEPTH]> {
+        let mut index = usize::from(message_id);
+        if index >= MERKLE_TREE_LEAVES {
+            return None;
+        }
+        let mut path = [[0; 32]; MERKLE_TREE_DEPTH];
+        for (level, node) in path.iter_mut().enumerate() {
+            // The sibling of the current node
+            *node = *self.node(level, index ^ 1).unwrap();
+            index >>= 1;
+        }
+        Some(path)
+    }
+}
+
 /// Errors produced during validation of the DSM-PKR using the Merkle tree.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum PkrError {
@@ -310,4 +418,59 @@ mod test {
         let mtree = merkle_tree();
         assert_eq!(mtree.validate_pkr(dsm).unwrap_err(), PkrError::Invalid);
     }
+
+    #[test]
+    fn full_tree_path() {
+        // DSM-PKR broadcast on 2023-12-12 12:00 UTC (message ID 0), used here
+        // only as a template
+        let dsm_buf = hex!(
+            "
+            70 01 63 1b dc ed 79 d4 31 7b c2 87 0e e3 89 5b
+            d5 9c f2 b6 ea 51 6f ab bf df 1d 73 96 26 14 6f
+            fe 31 6f a9 28 5f 5a 1e 44 04 24 13 bd af 18 aa
+            3c f6 84 72 33 97 d7 b8 32 5a ec a1 eb ca 9f 0f
+            64 99 05 42 4c be 48 2a 1a 32 b0 10 64 f8 5d 0c
+            36 df 03 8e 52 ce 12 8e 7e c5 f3 23 e1 65 b1 82
+            a7 15 37 bd b0 10 97 2e b4 a3 b9 0b aa cd 14 94
+            1e f4 0d a2 cb 2b 82 d3 78 b3 15 c0 08 de ce fd
+            8e 11 03 74 a9 25 cf a0 ff 18 05 e5 c5 a5 8f db
+            a3 1b f0 14 5d 5b 5b e2 f0 62 d3 f8 bb 2e e9 8f
+            0f 6d b0 e8 23 c5 e7 5e 78"
+        );
+        let template = DsmPkr(&dsm_buf);
+        let leaf = template.merkle_tree_leaf().unwrap();
+
+        // Check the node numbering using the real x_{0,0} and x_{0,1}
+        let mut leaf_nodes = [[0; 32]; MERKLE_TREE_LEAVES];
+        leaf_nodes[0] = MerkleTree::hash_leaf(leaf);
+        leaf_nodes[1] = *template.intermediate_tree_node(0);
+        let full = FullMerkleTree::from_leaf_nodes(&leaf_nodes);
+        assert_eq!(full.path_for(0).unwrap()[0], leaf_nodes[1]);
+        assert_eq!(
+            full.node(1, 0),
+            Some(&MerkleTree::calc_node(&leaf_nodes[0], &leaf_nodes[1]))
+        );
+        assert_eq!(full.node(4, 0), Some(full.root()));
+        assert_eq!(full.node(4, 1), None);
+        assert_eq!(full.path_for(16), None);
+
+        // Place the public key in each of the leaves and generate the
+        // corresponding DSM-PKR
+        for message_id in 0..16 {
+            let mut leaves: [&[u8]; MERKLE_TREE_LEAVES] = [&[0xff]; MERKLE_TREE_LEAVES];
+            leaves[usize::from(message_id)] = leaf;
+            let full = FullMerkleTree::from_leaves(&leaves);
+            let mut dsm = dsm_buf;
+            dsm[0] = (dsm[0] & 0xf0) | message_id;
+            for (j, node) in full.path_for(message_id).unwrap().iter().enumerate() {
+                dsm[1 + 32 * j..1 + 32 * (j + 1)].copy_from_slice(node);
+            }
+            let mtree = full.merkle_tree();
+            assert!(mtree.validate_pkr(DsmPkr(&dsm)).is_ok());
+            assert_eq!(
+                merkle_tree().validate_pkr(DsmPkr(&dsm)).unwrap_err(),
+                PkrError::Invalid
+            );
+        }
+    }
 }
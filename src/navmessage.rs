@@ -5,31 +5,326 @@
 //! the [`Osnma`](crate::Osnma) black box, but it can also be used directly
 //! if finer control is needed.
 
-use crate::bitfields::{Adkd, Mack, NmaStatus};
-use crate::storage::StaticStorage;
+use crate::bitfields::{Adkd, Mack, NmaStatus, Prnd};
+use crate::ced::Ced;
+use crate::diagnostics::{Diagnostic, DiagnosticKind, Hint};
+use crate::gstguard::{GstGuard, GstGuardPolicy, GstRejectionStats};
+#[cfg(feature = "reduced-ced")]
+use crate::inav::Word16;
+use crate::inav::{AdkdWordLayout, InavWordType, CED_AND_STATUS_LAYOUT, TIMING_PARAMETERS_LAYOUT};
+use crate::storage::{SatelliteSlots, Storage};
 use crate::tesla::Key;
-use crate::types::{BitSlice, InavBand, InavWord};
+use crate::types::{
+    BitSlice, BitWindow, CedBits, InavBand, InavWord, NavDataBits, TimingBits, MAX_TAG_SIZE_BITS,
+    NUM_SVNS,
+};
 use crate::validation::Validated;
-use crate::{Gst, Svn};
+use crate::{FeedError, Gst, Svn};
 use bitvec::prelude::*;
+use core::fmt;
 use generic_array::GenericArray;
 use typenum::Unsigned;
 
-// Minimum equivalent tag for authentication. Initially defined as 80 bits.
-// Changed to 40 bits as of 2024-01-15:
+/// Minimum equivalent tag length for authentication, in bits.
+///
+/// This is the minimum required by the OSNMA guidelines. A higher threshold can
+/// be set with [`CollectNavMessage::set_min_authbits`].
+// Initially defined as 80 bits. Changed to 40 bits as of 2024-01-15:
 // https://www.gsc-europa.eu/news/updated-documentation-and-cryptographic-material-in-preparation-for-the-galileo-osnma-initial
-const MIN_AUTHBITS: u16 = 40;
+pub const MIN_AUTHBITS: u16 = 40;
+
+/// Maximum age of authenticated words kept by [`MismatchPolicy::KeepOld`].
+///
+/// If an authenticated word has not been received for this number of
+/// subframes, a different word of the same type replaces it. This matches the
+/// maximum value of the COP field, so the old data could not be used to
+/// validate any tag by the time it is replaced.
+pub const KEEP_OLD_MAX_AGE: u8 = 15;
 
 /// Navigation message store.
 ///
 /// This struct is used to store and classify the navigation message data, and
 /// to authenticate it using MAC tags and their corresponding TESLA keys.
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-pub struct CollectNavMessage<S: StaticStorage> {
-    ced_and_status: GenericArray<CedAndStatus, S::NavMessageDepthSats>,
-    timing_parameters: GenericArray<TimingParameters, S::NavMessageDepthSats>,
+pub struct CollectNavMessage<S: Storage> {
+    ced_and_status: S::NavMessageSlots<CedAndStatus>,
+    timing_parameters: S::NavMessageSlots<TimingParameters>,
     gsts: GenericArray<Option<Gst>, S::NavMessageDepth>,
     write_pointer: usize,
+    // Most recent COP value received in a validated tag for each SVN and
+    // each of the ADKDs InavCed, InavTiming, and SlowMac.
+    cops: [[Option<u8>; 3]; NUM_SVNS],
+    // Cross-authentication expectation for each SVN (as PRND) and each of the
+    // ADKDs InavCed, InavTiming, and SlowMac.
+    #[cfg(feature = "cross-auth")]
+    cross_auth: [[Option<CrossAuthExpectation>; 3]; NUM_SVNS],
+    mismatch_policy: MismatchPolicy,
+    reconcile_history: bool,
+    unique_tag_authbits: bool,
+    min_authbits: u16,
+    adkd_filter: AdkdFilter,
+    // COP policies for the ADKDs InavCed, InavTiming, and SlowMac.
+    cop_policies: [CopPolicy; 3],
+    constellation_tags: ConstellationTags,
+    tag_verifications: TagVerifications,
+    failed_tags: [u32; NUM_SVNS],
+    #[cfg(feature = "nav-diagnostics")]
+    anomalies: AnomalyTracker,
+    reserved_adkd_stats: ReservedAdkdStats,
+    gst_guard: Option<GstGuard>,
+}
+
+/// Policy for INAV words that differ from authenticated data.
+///
+/// This defines what [`CollectNavMessage`] does when it receives an INAV word
+/// whose contents differ from those of the same word type in the stored
+/// navigation data of the satellite, and this navigation data has already been
+/// authenticated. This happens legitimately when the satellite starts
+/// broadcasting a new batch of navigation data, but it can also be caused by an
+/// attacker injecting forged words in order to prevent authentication.
+///
+/// The policy is set with [`CollectNavMessage::set_mismatch_policy`]. When the
+/// stored data has not been authenticated yet, the new word always replaces the
+/// stored word, regardless of the policy.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum MismatchPolicy {
+    /// The new word replaces the stored word, and the authentication of the
+    /// stored navigation data is discarded.
+    ///
+    /// This is the default policy.
+    #[default]
+    TakeNew,
+    /// The new word is discarded, and the stored authenticated navigation
+    /// data is kept.
+    ///
+    /// The new navigation data is only stored once the stored copy of the word
+    /// has not been received for [`KEEP_OLD_MAX_AGE`] subframes, which
+    /// indicates that the satellite has stopped broadcasting the old data. This
+    /// policy should only be used if the navigation data is not expected to
+    /// change often.
+    KeepOld,
+    /// The new word replaces the stored word, but a copy of the authenticated
+    /// navigation data is retained.
+    ///
+    /// The retained copy is returned by
+    /// [`CollectNavMessage::get_ced_and_status`] and
+    /// [`CollectNavMessage::get_timing_parameters`] until the new navigation
+    /// data is authenticated. The retained copy uses a storage slot of
+    /// another satellite, so it might be discarded if there are not enough
+    /// free slots.
+    Quarantine,
+}
+
+/// Set of ADKDs that are processed.
+///
+/// This is a mask of the ADKDs whose navigation data is collected and whose
+/// tags are validated by [`CollectNavMessage`]. The filter is set with
+/// [`CollectNavMessage::set_adkd_filter`]. Tags with an ADKD that is not in the
+/// filter are ignored without computing their MAC, and navigation data that is
+/// only authenticated by ADKDs that are not in the filter is not stored.
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::navmessage::AdkdFilter;
+/// use galileo_osnma::bitfields::Adkd;
+///
+/// // Filter for a receiver that only uses OSNMA for positioning
+/// let filter = AdkdFilter::ALL.without(Adkd::InavTiming);
+/// assert!(filter.contains(Adkd::InavCed));
+/// assert!(!filter.contains(Adkd::InavTiming));
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AdkdFilter(u16);
+
+impl AdkdFilter {
+    /// Filter containing all the ADKDs.
+    ///
+    /// This is the default filter.
+    ///
+    /// If the `slowmac` feature is disabled, this does not contain
+    /// [`Adkd::SlowMac`].
+    #[cfg(feature = "slowmac")]
+    pub const ALL: AdkdFilter = AdkdFilter::NONE
+        .with(Adkd::InavCed)
+        .with(Adkd::InavTiming)
+        .with(Adkd::SlowMac);
+
+    /// Filter containing all the ADKDs.
+    ///
+    /// This is the default filter.
+    ///
+    /// If the `slowmac` feature is disabled, this does not contain
+    /// [`Adkd::SlowMac`].
+    #[cfg(not(feature = "slowmac"))]
+    pub const ALL: AdkdFilter = AdkdFilter::NONE.with(Adkd::InavCed).with(Adkd::InavTiming);
+
+    /// Filter containing no ADKDs.
+    pub const NONE: AdkdFilter = AdkdFilter(0);
+
+    const fn bit(adkd: Adkd) -> u16 {
+        match adkd {
+            Adkd::InavCed => 1 << 0,
+            Adkd::InavTiming => 1 << 4,
+            Adkd::SlowMac => 1 << 12,
+            Adkd::Reserved => 0,
+        }
+    }
+
+    /// Returns a filter that also contains `adkd`.
+    ///
+    /// Reserved ADKDs cannot be added to the filter, since they are never
+    /// processed.
+    pub const fn with(self, adkd: Adkd) -> AdkdFilter {
+        AdkdFilter(self.0 | Self::bit(adkd))
+    }
+
+    /// Returns a filter that does not contain `adkd`.
+    pub const fn without(self, adkd: Adkd) -> AdkdFilter {
+        AdkdFilter(self.0 & !Self::bit(adkd))
+    }
+
+    /// Returns `true` if the filter contains `adkd`.
+    pub fn contains(&self, adkd: Adkd) -> bool {
+        self.0 & Self::bit(adkd) != 0
+    }
+
+    fn collects_ced_and_status(&self) -> bool {
+        self.contains(Adkd::InavCed) || self.contains(Adkd::SlowMac)
+    }
+
+    fn collects_timing_parameters(&self) -> bool {
+        self.contains(Adkd::InavTiming)
+    }
+}
+
+impl Default for AdkdFilter {
+    fn default() -> AdkdFilter {
+        AdkdFilter::ALL
+    }
+}
+
+/// Policy for using tags according to their COP and the data age.
+///
+/// The COP (cut-off point) field of a tag indicates the number of subframes
+/// during which the navigation data it authenticates has not changed. The
+/// navigation data stored by [`CollectNavMessage`] is assembled from INAV
+/// words received over several subframes, so by default a tag is only used if
+/// the oldest of these words is within the COP of the tag. Using tags with
+/// older words is safe, because the tag only validates if the words are
+/// still current, but it wastes MAC computations when they are not.
+///
+/// The policy can be set separately for each ADKD with
+/// [`CollectNavMessage::set_cop_policy`]. For instance, the timing parameters
+/// change rarely, so allowing older words for ADKD=4 improves their
+/// availability without weakening the policy for ADKD=0.
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::navmessage::CopPolicy;
+///
+/// // Allow words up to 10 subframes older than the COP
+/// let policy = CopPolicy::DEFAULT.with_age_margin(10);
+/// assert_eq!(policy.min_cop(), 1);
+/// assert_eq!(policy.age_margin(), 10);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CopPolicy {
+    min_cop: u8,
+    age_margin: u8,
+}
+
+impl CopPolicy {
+    /// Default policy.
+    ///
+    /// Tags with any non-zero COP are used, and the navigation data words
+    /// must be within the COP.
+    pub const DEFAULT: CopPolicy = CopPolicy {
+        min_cop: 1,
+        age_margin: 0,
+    };
+
+    /// Returns a policy with a different minimum COP.
+    ///
+    /// Tags whose COP is smaller than `min_cop` are not used. Tags with a COP
+    /// of zero (dummy tags) are always checked regardless of this value, since
+    /// they do not authenticate any data. A `min_cop` of zero is treated as 1.
+    pub const fn with_min_cop(self, min_cop: u8) -> CopPolicy {
+        CopPolicy {
+            min_cop: if min_cop == 0 { 1 } else { min_cop },
+            age_margin: self.age_margin,
+        }
+    }
+
+    /// Returns a policy with a different age margin.
+    ///
+    /// Tags are used if the age in subframes of the oldest navigation data
+    /// word is smaller than the COP plus `age_margin`.
+    pub const fn with_age_margin(self, age_margin: u8) -> CopPolicy {
+        CopPolicy {
+            min_cop: self.min_cop,
+            age_margin,
+        }
+    }
+
+    /// Gives the minimum COP of the tags that are used.
+    pub fn min_cop(&self) -> u8 {
+        self.min_cop
+    }
+
+    /// Gives the number of subframes by which the navigation data words can
+    /// be older than the COP.
+    pub fn age_margin(&self) -> u8 {
+        self.age_margin
+    }
+
+    fn allows(&self, cop: u8, max_age: u8) -> bool {
+        cop >= self.min_cop && u16::from(max_age) < u16::from(cop) + u16::from(self.age_margin)
+    }
+}
+
+impl Default for CopPolicy {
+    fn default() -> CopPolicy {
+        CopPolicy::DEFAULT
+    }
+}
+
+// Results of the validation of the tags of a MACK message. Bit j of each
+// bitmask corresponds to tag j, where tag0 is bit 0. Dummy tags are not
+// included.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct TagResults {
+    pub(crate) validated: u16,
+    pub(crate) failed: u16,
+}
+
+impl TagResults {
+    fn record(&mut self, tag_idx: usize, valid: bool) {
+        if valid {
+            self.validated |= 1 << tag_idx;
+        } else {
+            self.failed |= 1 << tag_idx;
+        }
+    }
+}
+
+/// Kind of navigation data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NavDataKind {
+    /// CED and health status data (ADKD=0 and 12).
+    CedAndStatus,
+    /// Timing parameters (ADKD=4).
+    TimingParameters,
+}
+
+impl fmt::Display for NavDataKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NavDataKind::CedAndStatus => "CED and status".fmt(f),
+            NavDataKind::TimingParameters => "timing parameters".fmt(f),
+        }
+    }
 }
 
 /// Authenticated navigation message data.
@@ -41,7 +336,14 @@ pub struct CollectNavMessage<S: StaticStorage> {
 pub struct NavMessageData<'a> {
     data: &'a BitSlice,
     authbits: u16,
+    #[cfg(feature = "unique-authbits")]
+    unique_authbits: u16,
+    adkd_authbits: [u16; 3],
     gst: Gst,
+    latency: AuthLatency,
+    #[cfg(feature = "provenance")]
+    provenance: NavDataProvenance,
+    nma_dont_use: bool,
 }
 
 impl<'a> NavMessageData<'a> {
@@ -58,6 +360,38 @@ impl<'a> NavMessageData<'a> {
         self.authbits
     }
 
+    /// Returns the number of authentication bits corresponding to this data
+    /// that come from tags from distinct sources.
+    ///
+    /// This is the sum of the length in bits of the tags that have
+    /// authenticated this message, counting only the first tag from each
+    /// authenticating PRN and subframe. See
+    /// [`CollectNavMessage::set_unique_tag_authbits`].
+    ///
+    /// This is only available with the `unique-authbits` feature.
+    #[cfg(feature = "unique-authbits")]
+    pub fn unique_authbits(&self) -> u16 {
+        self.unique_authbits
+    }
+
+    /// Returns the number of authentication bits corresponding to this data
+    /// that come from tags with a given ADKD.
+    ///
+    /// This is the sum of the length in bits of the tags with ADKD `adkd` that
+    /// have authenticated this message. For instance, applications that
+    /// require the CED and health status data to be authenticated by Slow MAC
+    /// can check that `adkd_authbits(Adkd::SlowMac)` is at least 40. For
+    /// reserved ADKDs this returns zero.
+    pub fn adkd_authbits(&self, adkd: Adkd) -> u16 {
+        adkd_index(adkd).map_or(0, |idx| self.adkd_authbits[idx])
+    }
+
+    /// Returns `true` if this data has been authenticated only by Slow MAC
+    /// (ADKD=12) tags.
+    pub fn is_slowmac_only(&self) -> bool {
+        self.adkd_authbits(Adkd::SlowMac) == self.authbits
+    }
+
     /// Returns the GST that corresponds to this navigation data.
     ///
     /// The GST is defined as the starting GST of the subframe where the most
@@ -65,796 +399,4782 @@ impl<'a> NavMessageData<'a> {
     pub fn gst(&self) -> Gst {
         self.gst
     }
-}
 
-impl<S: StaticStorage> CollectNavMessage<S> {
-    /// Constructs a new, empty navigation message storage.
-    pub fn new() -> CollectNavMessage<S> {
-        CollectNavMessage {
-            ced_and_status: GenericArray::default(),
-            timing_parameters: GenericArray::default(),
-            gsts: GenericArray::default(),
-            write_pointer: 0,
-        }
+    /// Returns the authentication latency of this navigation data.
+    ///
+    /// This refers to the moment in which the data was first authenticated,
+    /// which is when the tags that have authenticated it accumulated enough
+    /// authentication bits.
+    pub fn latency(&self) -> AuthLatency {
+        self.latency
     }
 
-    /// Feed an INAV word into the navigation message storage.
+    /// Returns the provenance of this navigation data.
     ///
-    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
-    /// the INAV word. This should be obtained from the PRN used for tracking.
+    /// This gives the INAV words that compose the navigation data, together
+    /// with the band and GST in which they were received. See
+    /// [`NavDataProvenance`].
     ///
-    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
+    /// This is only available with the `provenance` feature.
+    #[cfg(feature = "provenance")]
+    pub fn provenance(&self) -> &NavDataProvenance {
+        &self.provenance
+    }
+
+    /// Returns `true` if the NMA status was "don't use" when this navigation
+    /// data was obtained.
     ///
-    /// The `band` parameter indicates the band in which the INAV word was received.
-    pub fn feed(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
-        log::trace!(
-            "feeding INAV word = {:02x?} for {} GST {:?}",
-            word,
-            svn,
-            gst
-        );
-        let gst = gst.gst_subframe();
-        self.adjust_write_pointer(gst);
+    /// This flag is only set by the [`Osnma`](crate::Osnma) getters when the
+    /// [`NmaDontUsePolicy::Flag`](crate::NmaDontUsePolicy::Flag) policy
+    /// is in use. In that case, the data should not be used for positioning
+    /// even though its tags have been validated.
+    pub fn nma_dont_use(&self) -> bool {
+        self.nma_dont_use
+    }
 
-        // CED
-        //
-        // Search for best location to place this SVN
-        let ced = self
-            .current_ced_as_mut()
-            .iter_mut()
-            .max_by_key(|x| match x.svn {
-                Some(s) if s == svn => u16::from(u8::MAX) + 2,
-                None => u16::from(u8::MAX) + 1,
-                _ => u16::from(x.max_age()),
-            })
-            .unwrap();
-        log::trace!(
-            "selected CED store with SVN {:?} and age {}",
-            ced.svn,
-            ced.max_age()
-        );
-        ced.feed(word, svn);
+    pub(crate) fn with_nma_dont_use(self, nma_dont_use: bool) -> NavMessageData<'a> {
+        NavMessageData {
+            nma_dont_use,
+            ..self
+        }
+    }
+}
 
-        // Timing parameters
-        //
-        // Search for best location to place this SVN
-        let timing_parameters = self
-            .current_timing_parameters_as_mut()
-            .iter_mut()
-            .max_by_key(|x| match x.svn {
-                Some(s) if s == svn => u16::from(u8::MAX) + 2,
-                None => u16::from(u8::MAX) + 1,
-                _ => u16::from(x.max_age()),
-            })
-            .unwrap();
-        log::trace!(
-            "selected timing parameters store with SVN {:?} and age {}",
-            timing_parameters.svn,
-            timing_parameters.max_age(),
-        );
-        timing_parameters.feed(word, svn, band);
+/// Formats a summary of the authenticated data, as in
+/// `"549 bits at WN=1176 TOW=121050 authenticated with 80 tag bits, latency 30 s (...)"`.
+impl fmt::Display for NavMessageData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bits at {} authenticated with {} tag bits, latency {}",
+            self.data.len(),
+            self.gst,
+            self.authbits,
+            self.latency
+        )
     }
+}
 
-    fn adjust_write_pointer(&mut self, gst: Gst) {
-        // If write pointer points to a valid GST which is distinct from the
-        // current, we advance the write pointer and copy the old CED and status
-        // and timing parameters to the new write pointer location. We increase
-        // the stale counter of the copy.
-        if let Some(g) = self.gsts[self.write_pointer] {
-            if g != gst {
-                log::trace!(
-                    "got a new GST {:?} (current GST is {:?}); \
-                     advancing write pointer",
-                    gst,
-                    g
-                );
-                let new_pointer = (self.write_pointer + 1) % S::NavMessageDepth::USIZE;
-                self.ced_and_status.copy_within(
-                    self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS,
-                    new_pointer * S::NUM_SATS,
-                );
-                self.timing_parameters.copy_within(
-                    self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS,
-                    new_pointer * S::NUM_SATS,
-                );
-                self.write_pointer = new_pointer;
-                self.increase_age();
-                if log::log_enabled!(log::Level::Debug) {
-                    log::debug!("advanced write pointer to {:?}", gst);
-                    log::debug!("CedAndStatus contents:");
-                    for elem in self.ced_and_status
-                        [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
-                        .iter()
-                    {
-                        log::debug!(
-                            "SVN {:?}: age {:?} authbits {}",
-                            elem.svn,
-                            elem.age,
-                            elem.authbits
-                        );
-                    }
-                    log::debug!("TimingParameters contents:");
-                    for elem in self.timing_parameters
-                        [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
-                        .iter()
-                    {
-                        log::debug!(
-                            "SVN {:?}: age {:?} authbits {}",
-                            elem.svn,
-                            elem.age,
-                            elem.authbits
-                        );
-                    }
-                }
-            }
-        }
-        self.gsts[self.write_pointer] = Some(gst);
+impl NavMessageData<'_> {
+    /// Decodes the CED and health status data.
+    ///
+    /// This converts the fields of the CED and health status data (ADKD=0 and
+    /// 12) into engineering units. See the [ced](crate::ced) module. If the
+    /// navigation data is not CED and health status data, such as the data
+    /// obtained with [`CollectNavMessage::get_timing_parameters`], this
+    /// returns `None`.
+    pub fn decode_ced(&self) -> Option<Ced> {
+        (self.data.len() == CED_AND_STATUS_BITS).then(|| Ced::from_ced_and_status(self.data))
     }
 
-    fn current_ced_as_mut(&mut self) -> &mut [CedAndStatus] {
-        &mut self.ced_and_status
-            [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+    /// Copies the navigation data into a [`NavMessageDataOwned`].
+    ///
+    /// This is useful to keep the data after the borrow of the
+    /// [`CollectNavMessage`] ends.
+    pub fn into_owned(self) -> NavMessageDataOwned {
+        self.into()
     }
+}
 
-    fn current_timing_parameters_as_mut(&mut self) -> &mut [TimingParameters] {
-        &mut self.timing_parameters
-            [self.write_pointer * S::NUM_SATS..(self.write_pointer + 1) * S::NUM_SATS]
+/// Reduced clock and ephemeris data (reduced CED).
+///
+/// This holds the most recent INAV word type 16 received from a satellite,
+/// which contains the reduced CED parameters. The reduced CED is not covered
+/// by any of the ADKDs defined in the OSNMA ICD, so it is **not
+/// authenticated**. It is stored so that it can be used for consistency checks
+/// against the authenticated CED.
+#[cfg(feature = "reduced-ced")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ReducedCed {
+    word: InavWord,
+    gst: Gst,
+}
+
+#[cfg(feature = "reduced-ced")]
+impl ReducedCed {
+    /// Gives the INAV word type 16 that contains the reduced CED.
+    pub fn word16(&self) -> Word16<'_> {
+        Word16::new(&self.word).unwrap()
     }
 
-    fn increase_age(&mut self) {
-        for ced in self.current_ced_as_mut().iter_mut() {
-            for age in ced.age.iter_mut() {
-                *age = age.saturating_add(1);
-            }
-        }
-        for timing_parameters in self.current_timing_parameters_as_mut().iter_mut() {
-            for age in timing_parameters.age.iter_mut() {
-                *age = age.saturating_add(1);
-            }
-        }
+    /// Gives the GST at the start of the subframe in which the reduced CED
+    /// was received.
+    pub fn gst(&self) -> Gst {
+        self.gst
     }
+}
 
-    /// Try to get authenticated CED and health status data for a satellite.
-    ///
-    /// This will try to retrieve the most recent authenticated CED and health
-    /// status data (ADKD=0 and 12) for the satellite with SVN `svn` that is
-    /// available in the OSNMA storage. If the storage does not contain any
-    /// authenticated CED and health status data for this SVN, this returns
-    /// `None`.
-    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData> {
-        // Search in order of decreasing Gst
-        for j in 0..S::NavMessageDepth::USIZE {
-            let gst_idx =
-                (S::NavMessageDepth::USIZE + self.write_pointer - j) % S::NavMessageDepth::USIZE;
-            for item in
-                self.ced_and_status[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS].iter()
-            {
-                if item.svn == Some(svn) && item.authbits >= MIN_AUTHBITS {
-                    let age: i32 = item.min_age().into();
-                    let gst = self.gsts[gst_idx].unwrap().add_subframes(-age);
-                    return Some(NavMessageData {
-                        data: item.message_bits(),
-                        authbits: item.authbits,
-                        gst,
-                    });
-                }
-            }
-        }
-        None
+/// Authenticated navigation message data (owned version).
+///
+/// This struct holds the same information as [`NavMessageData`], but it owns a
+/// copy of the navigation data instead of borrowing it from a
+/// [`CollectNavMessage`]. It can be obtained with
+/// [`NavMessageData::into_owned`] or with the `From` implementation, and it can
+/// be stored or sent to other threads freely.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NavMessageDataOwned {
+    data: [u8; MAX_NAVDATA_BYTES],
+    len_bits: usize,
+    authbits: u16,
+    #[cfg(feature = "unique-authbits")]
+    unique_authbits: u16,
+    adkd_authbits: [u16; 3],
+    gst: Gst,
+    latency: AuthLatency,
+    #[cfg(feature = "provenance")]
+    provenance: NavDataProvenance,
+    nma_dont_use: bool,
+}
+
+impl NavMessageDataOwned {
+    /// Returns the navigation data as a `BitSlice`.
+    pub fn data(&self) -> &BitSlice {
+        &BitSlice::from_slice(&self.data)[..self.len_bits]
     }
 
-    /// Try to get authenticated timing parameters for a satellite.
+    /// Returns the number of authentication bits corresponding to this data.
     ///
-    /// This will try to retrieve the most recent timing parameters data
-    /// (ADKD=4) for the satellite with SNV`svn` that is available in the OSNMA
-    /// storage. If the storage does not contain any authenticated timing
-    /// parameters data for this SVN, this returns `None`.
-    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData> {
-        // Search in order of decreasing Gst
-        for j in 0..S::NavMessageDepth::USIZE {
-            let gst_idx =
-                (S::NavMessageDepth::USIZE + self.write_pointer - j) % S::NavMessageDepth::USIZE;
-            for item in
-                self.timing_parameters[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS].iter()
-            {
-                if item.svn == Some(svn) && item.authbits >= MIN_AUTHBITS {
-                    let age: i32 = item.min_age().into();
-                    let gst = self.gsts[gst_idx].unwrap().add_subframes(-age);
-                    return Some(NavMessageData {
-                        data: item.message_bits(),
-                        authbits: item.authbits,
-                        gst,
-                    });
-                }
-            }
-        }
-        None
+    /// See [`NavMessageData::authbits`].
+    pub fn authbits(&self) -> u16 {
+        self.authbits
     }
 
-    fn find_ced_and_status(&mut self, svn: Svn, gst: Gst) -> Option<&CedAndStatus> {
-        let gst_idx = self.find_gst(gst)?;
-        self.ced_and_status[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
-            .iter()
-            .find(|item| item.svn == Some(svn))
+    /// Returns the number of authentication bits corresponding to this data
+    /// that come from tags from distinct sources.
+    ///
+    /// See [`NavMessageData::unique_authbits`].
+    #[cfg(feature = "unique-authbits")]
+    pub fn unique_authbits(&self) -> u16 {
+        self.unique_authbits
     }
 
-    fn find_timing_parameters(&mut self, svn: Svn, gst: Gst) -> Option<&TimingParameters> {
-        let gst_idx = self.find_gst(gst)?;
-        self.timing_parameters[gst_idx * S::NUM_SATS..(gst_idx + 1) * S::NUM_SATS]
-            .iter()
-            .find(|item| item.svn == Some(svn))
+    /// Returns the number of authentication bits corresponding to this data
+    /// that come from tags with a given ADKD.
+    ///
+    /// See [`NavMessageData::adkd_authbits`].
+    pub fn adkd_authbits(&self, adkd: Adkd) -> u16 {
+        self.as_nav_message_data().adkd_authbits(adkd)
     }
 
-    fn ced_and_status_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
-        self.ced_and_status.iter_mut().map(|x| {
-            let y: &mut dyn AuthBits = x;
-            y
-        })
+    /// Returns `true` if this data has been authenticated only by Slow MAC
+    /// (ADKD=12) tags.
+    ///
+    /// See [`NavMessageData::is_slowmac_only`].
+    pub fn is_slowmac_only(&self) -> bool {
+        self.as_nav_message_data().is_slowmac_only()
     }
 
-    fn timing_parameters_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
-        self.timing_parameters.iter_mut().map(|x| {
-            let y: &mut dyn AuthBits = x;
-            y
-        })
+    /// Returns the GST that corresponds to this navigation data.
+    ///
+    /// See [`NavMessageData::gst`].
+    pub fn gst(&self) -> Gst {
+        self.gst
     }
 
-    fn find_gst(&self, gst: Gst) -> Option<usize> {
-        assert!(gst.is_subframe());
-        self.gsts
-            .iter()
-            .enumerate()
-            .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })
+    /// Returns the authentication latency of this navigation data.
+    ///
+    /// See [`NavMessageData::latency`].
+    pub fn latency(&self) -> AuthLatency {
+        self.latency
     }
 
-    /// Process a MACK message.
+    /// Returns the provenance of this navigation data.
     ///
-    /// This processes a MACK message, authenticating stored navigation data as
-    /// possible. The `key` should be the TESLA key with which the tags in the
-    /// MACK message `mack` have been generated (recall that this key is
-    /// transmitted in the next subframe with respect to the MACK message). The
-    /// `prna` is the authenticating PRN, which is the SVN that has transmitted
-    /// the MACK message. The `gst_mack` parameter should be the GST
-    /// corresponding to the start of the subframe when the MACK message was
-    /// transmitted. The `nma_status` parameter should be the value of the NMA
-    /// status field in the current NMA header. The NMA header does not need to
-    /// be validated using the DSM-KROOT, since a forged or incorrect NMA header
-    /// will simply make tag validation fail.
+    /// See [`NavMessageData::provenance`].
+    #[cfg(feature = "provenance")]
+    pub fn provenance(&self) -> &NavDataProvenance {
+        &self.provenance
+    }
+
+    /// Returns `true` if the NMA status was "don't use" when this navigation
+    /// data was obtained.
     ///
-    /// This function ignores the ADKD=12 (Slow MAC) tags in the MACK message,
-    /// since they do not correspond to `key`.
-    pub fn process_mack(
-        &mut self,
-        mack: Mack<Validated>,
-        key: &Key<Validated>,
+    /// See [`NavMessageData::nma_dont_use`].
+    pub fn nma_dont_use(&self) -> bool {
+        self.nma_dont_use
+    }
+
+    /// Returns a [`NavMessageData`] that borrows the data from `self`.
+    pub fn as_nav_message_data(&self) -> NavMessageData<'_> {
+        NavMessageData {
+            data: self.data(),
+            authbits: self.authbits,
+            #[cfg(feature = "unique-authbits")]
+            unique_authbits: self.unique_authbits,
+            adkd_authbits: self.adkd_authbits,
+            gst: self.gst,
+            latency: self.latency,
+            #[cfg(feature = "provenance")]
+            provenance: self.provenance,
+            nma_dont_use: self.nma_dont_use,
+        }
+    }
+}
+
+impl fmt::Display for NavMessageDataOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_nav_message_data().fmt(f)
+    }
+}
+
+impl From<NavMessageData<'_>> for NavMessageDataOwned {
+    fn from(value: NavMessageData<'_>) -> NavMessageDataOwned {
+        let mut data = [0; MAX_NAVDATA_BYTES];
+        BitSlice::from_slice_mut(&mut data)[..value.data.len()].copy_from_bitslice(value.data);
+        NavMessageDataOwned {
+            data,
+            len_bits: value.data.len(),
+            authbits: value.authbits,
+            #[cfg(feature = "unique-authbits")]
+            unique_authbits: value.unique_authbits,
+            adkd_authbits: value.adkd_authbits,
+            gst: value.gst,
+            latency: value.latency,
+            #[cfg(feature = "provenance")]
+            provenance: value.provenance,
+            nma_dont_use: value.nma_dont_use,
+        }
+    }
+}
+
+/// Provenance of an INAV word.
+///
+/// This indicates when and in which band an INAV word that forms part of a set
+/// of authenticated navigation data was received. If the same word has been
+/// received several times, this refers to the most recent reception, or to the
+/// first copy received in a subframe if the word was received in both bands
+/// during the subframe.
+#[cfg(feature = "provenance")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WordProvenance {
+    word_type: InavWordType,
+    gst: Gst,
+    band: InavBand,
+    crc_checked: bool,
+}
+
+#[cfg(feature = "provenance")]
+impl WordProvenance {
+    /// Returns the type of the INAV word.
+    pub fn word_type(&self) -> InavWordType {
+        self.word_type
+    }
+
+    /// Returns the GST at the start of the INAV page in which the word was
+    /// received.
+    ///
+    /// This is the GST that was given when the word was fed. Note that for
+    /// words received in E5b-I it is 1 second later than for the E1-B page
+    /// that carries the same word.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Returns the band in which the word was received.
+    pub fn band(&self) -> InavBand {
+        self.band
+    }
+
+    /// Returns `true` if the receiver indicated that the CRC of the page
+    /// containing the word had been checked.
+    pub fn crc_checked(&self) -> bool {
+        self.crc_checked
+    }
+}
+
+/// Formats the provenance as in `"Word1 in E1-B at WN=1176 TOW=121052"`.
+#[cfg(feature = "provenance")]
+impl fmt::Display for WordProvenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} in {} at {}", self.word_type, self.band, self.gst)
+    }
+}
+
+/// Provenance of a set of authenticated navigation data.
+///
+/// This lists the INAV words that compose a set of authenticated navigation
+/// data, in the order in which they appear in the navigation data (see
+/// [`CED_AND_STATUS_LAYOUT`] and [`TIMING_PARAMETERS_LAYOUT`]), together with
+/// the GST and band in which each of them was received. Integrity monitoring
+/// layers can use this to correlate measurement epochs with the version of the
+/// navigation data that has been authenticated. It is obtained with
+/// [`NavMessageData::provenance`].
+#[cfg(feature = "provenance")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NavDataProvenance {
+    words: [Option<WordProvenance>; CED_AND_STATUS_WORDS],
+}
+
+#[cfg(feature = "provenance")]
+impl NavDataProvenance {
+    fn new(layout: &[AdkdWordLayout], sources: &[Option<WordSource>]) -> NavDataProvenance {
+        let mut words = [None; CED_AND_STATUS_WORDS];
+        for ((word, layout), source) in words.iter_mut().zip(layout).zip(sources) {
+            *word = source.map(|source| WordProvenance {
+                word_type: layout.word_type(),
+                gst: source.gst,
+                band: source.band,
+                crc_checked: source.crc_checked,
+            });
+        }
+        NavDataProvenance { words }
+    }
+
+    /// Returns an iterator over the provenance of the INAV words.
+    pub fn words(&self) -> impl Iterator<Item = &WordProvenance> {
+        self.words.iter().flatten()
+    }
+
+    /// Returns the provenance of an INAV word of a given type.
+    ///
+    /// This returns `None` if the navigation data does not contain this word
+    /// type.
+    pub fn word(&self, word_type: InavWordType) -> Option<&WordProvenance> {
+        self.words().find(|w| w.word_type == word_type)
+    }
+
+    /// Returns the GST at the start of the page of the most recently received
+    /// word.
+    ///
+    /// This returns `None` only if the provenance is empty.
+    pub fn latest_gst(&self) -> Option<Gst> {
+        self.words().map(|w| w.gst).reduce(Gst::latest)
+    }
+}
+
+/// Authentication latency.
+///
+/// This gives the interval between the moment in which a set of navigation data
+/// was complete and the moment in which it became authenticated. These
+/// moments are measured in subframes, since both the navigation data and
+/// the OSNMA data are processed with subframe granularity.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthLatency {
+    data_gst: Gst,
+    authentication_gst: Gst,
+}
+
+impl AuthLatency {
+    /// Returns the GST at which the navigation data was complete.
+    ///
+    /// This is the GST at the start of the subframe in which the most recently
+    /// received word of the navigation data was transmitted.
+    pub fn data_gst(&self) -> Gst {
+        self.data_gst
+    }
+
+    /// Returns the GST at which the navigation data became authenticated.
+    ///
+    /// This is the GST at the start of the subframe in which the TESLA key used
+    /// to validate the tag that authenticated the navigation data was
+    /// transmitted.
+    pub fn authentication_gst(&self) -> Gst {
+        self.authentication_gst
+    }
+
+    /// Returns the authentication latency in seconds.
+    ///
+    /// This is the number of seconds elapsed between
+    /// [`AuthLatency::data_gst`] and [`AuthLatency::authentication_gst`].
+    /// Since both the navigation data subframe and the TESLA key subframe
+    /// are processed once they are complete, this is also the time elapsed
+    /// between the end of both subframes.
+    pub fn seconds(&self) -> u32 {
+        let subframes = self.authentication_gst.subframes_difference(self.data_gst);
+        u32::try_from(subframes).unwrap_or(0) * 30
+    }
+}
+
+/// Formats the latency as in `"30 s (data at WN=1176 TOW=121050, authenticated at WN=1176 TOW=121080)"`.
+impl fmt::Display for AuthLatency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} s (data at {}, authenticated at {})",
+            self.seconds(),
+            self.data_gst,
+            self.authentication_gst
+        )
+    }
+}
+
+/// Authentication schedule estimate.
+///
+/// This gives an estimate, based on the COP (cut-off point) field of the most
+/// recently validated tags, of when the navigation data of a satellite can be
+/// authenticated next, and of when the navigation data that is currently stored
+/// needs to be refreshed by receiving its words again. Receivers can use this
+/// information to plan the allocation of their tracking channels.
+///
+/// The estimate is obtained with [`CollectNavMessage::auth_schedule`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct AuthSchedule {
+    cop: u8,
+    next_authentication: Option<Gst>,
+    refresh_deadline: Option<Gst>,
+}
+
+impl AuthSchedule {
+    fn new(cop: u8, adkd: Adkd, gst_current: Gst, max_age: Option<u8>) -> AuthSchedule {
+        if cop == 0 {
+            // Dummy tags: the navigation data is never authenticated
+            return AuthSchedule {
+                cop,
+                next_authentication: None,
+                refresh_deadline: None,
+            };
+        }
+        // The tags for the data in the current subframe are transmitted in the
+        // next subframe, and the key for them in the subframe after that (or 11
+        // subframes later for Slow MAC).
+        let key_delay = if adkd == Adkd::SlowMac { 12 } else { 2 };
+        match max_age {
+            Some(age) if age.saturating_add(1) <= cop => AuthSchedule {
+                cop,
+                next_authentication: Some(gst_current.wrapping_add_subframes(key_delay)),
+                refresh_deadline: Some(
+                    gst_current.wrapping_add_subframes(i32::from(cop - 1 - age)),
+                ),
+            },
+            _ => AuthSchedule {
+                // The stored data is incomplete or too old for the COP, so we
+                // need to wait until it is received again in the next subframe.
+                cop,
+                next_authentication: Some(gst_current.wrapping_add_subframes(key_delay + 1)),
+                refresh_deadline: None,
+            },
+        }
+    }
+
+    /// Gives the COP value in which the estimate is based.
+    ///
+    /// This is the COP of the most recently validated tag for this satellite
+    /// and ADKD.
+    pub fn cop(&self) -> u8 {
+        self.cop
+    }
+
+    /// Gives the next GST at which the navigation data is expected to become
+    /// authenticable.
+    ///
+    /// This is the GST at the start of the subframe in which the TESLA key
+    /// needed to authenticate the data is transmitted, assuming that the
+    /// navigation data and the tags are received without losses. If the COP is
+    /// zero, the navigation data cannot be authenticated, since the tags are
+    /// dummy tags, and `None` is returned.
+    pub fn next_authentication(&self) -> Option<Gst> {
+        self.next_authentication
+    }
+
+    /// Gives the last GST at which the stored navigation data can be
+    /// authenticated without receiving its words again.
+    ///
+    /// This is the GST at the start of the last subframe in which the
+    /// currently stored navigation data is young enough to be authenticated by
+    /// a tag with the COP value given by [`AuthSchedule::cop`]. If the
+    /// navigation data needs to be refreshed already, because it is incomplete
+    /// or too old, `None` is returned.
+    pub fn refresh_deadline(&self) -> Option<Gst> {
+        self.refresh_deadline
+    }
+}
+
+/// Cross-authentication expectation.
+///
+/// This gives a prediction, based on the tags with a non-zero COP (cut-off
+/// point) that have been received for a satellite, of which satellites are
+/// expected to transmit tags that authenticate its navigation data in the
+/// upcoming subframes. The tag sequences defined by the MAC look-up table
+/// repeat every one or two subframes, so the authenticating satellites (PRNAs)
+/// that have transmitted tags for this satellite (the PRND) in the last two
+/// subframes in which it was authenticated are expected to keep doing so.
+///
+/// Since the tags of the PRNAs authenticate navigation data of the PRND that
+/// is up to COP subframes old, a receiver that tracks any of the PRNAs only
+/// needs to receive the navigation data of the PRND once in a while to get it
+/// authenticated. Receivers can use this information to prioritize the
+/// satellites they track in order to maximize the authenticated coverage.
+///
+/// The expectation is obtained with
+/// [`CollectNavMessage::cross_auth_expectation`].
+#[cfg(feature = "cross-auth")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CrossAuthExpectation {
+    prnd: Svn,
+    gst: Gst,
+    cop: u8,
+    // Bitmasks of PRNAs (bit j corresponds to the SVN with index j) that have
+    // transmitted tags in the subframe starting at gst and in the previous
+    // subframe.
+    current: u64,
+    previous: u64,
+}
+
+#[cfg(feature = "cross-auth")]
+impl CrossAuthExpectation {
+    fn new(prnd: Svn, prna: Svn, gst: Gst, cop: u8) -> CrossAuthExpectation {
+        CrossAuthExpectation {
+            prnd,
+            gst,
+            cop,
+            current: Self::bit(prna),
+            previous: 0,
+        }
+    }
+
+    fn bit(svn: Svn) -> u64 {
+        1 << svn.index()
+    }
+
+    fn record(&mut self, prna: Svn, gst: Gst, cop: u8) {
+        let bit = Self::bit(prna);
+        match gst.subframes_difference(self.gst) {
+            0 => self.current |= bit,
+            -1 => self.previous |= bit,
+            1 => {
+                self.previous = self.current;
+                self.current = bit;
+            }
+            d if d > 1 => {
+                self.previous = 0;
+                self.current = bit;
+            }
+            _ => {
+                // Tags from older subframes do not update the expectation
+                return;
+            }
+        }
+        if !gst.is_before(self.gst) {
+            self.gst = gst;
+            self.cop = cop;
+        }
+    }
+
+    /// Gives the SVN of the satellite (PRND) whose navigation data is
+    /// authenticated.
+    pub fn prnd(&self) -> Svn {
+        self.prnd
+    }
+
+    /// Gives the GST at the start of the most recent subframe in which tags
+    /// for the PRND have been received.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the COP of the most recently received tag for the PRND.
+    ///
+    /// The navigation data of the PRND needs to have been received at most
+    /// this number of subframes before the subframe in which a tag is
+    /// transmitted in order for the tag to authenticate it.
+    pub fn cop(&self) -> u8 {
+        self.cop
+    }
+
+    /// Returns an iterator over the satellites (PRNAs) that are expected to
+    /// transmit tags for the PRND.
+    ///
+    /// This includes the PRND itself if it transmits tags for its own
+    /// navigation data.
+    pub fn prnas(&self) -> impl Iterator<Item = Svn> {
+        let prnas = self.prnas_mask();
+        Svn::iter().filter(move |&svn| prnas & Self::bit(svn) != 0)
+    }
+
+    /// Returns `true` if the satellite `prna` is expected to transmit tags for
+    /// the PRND.
+    pub fn is_authenticated_by(&self, prna: Svn) -> bool {
+        self.prnas_mask() & Self::bit(prna) != 0
+    }
+
+    /// Returns `true` if some satellite other than the PRND is expected to
+    /// transmit tags for the PRND.
+    pub fn is_cross_authenticated(&self) -> bool {
+        self.prnas_mask() & !Self::bit(self.prnd) != 0
+    }
+
+    fn prnas_mask(&self) -> u64 {
+        self.current | self.previous
+    }
+}
+
+/// Authenticated timing parameters of the Galileo constellation.
+///
+/// The timing parameters (ADKD=4) are the same for all the satellites of the
+/// constellation, so the authenticated timing parameters of different
+/// satellites can be merged. This gives the most recent timing parameters,
+/// together with the satellites whose most recent authenticated timing
+/// parameters are identical to them and the combined authentication bits of
+/// all these satellites.
+///
+/// The constellation timing parameters are obtained with
+/// [`CollectNavMessage::get_constellation_timing`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ConstellationTiming<'a> {
+    data: NavMessageData<'a>,
+    svn: Svn,
+    authbits: u16,
+    // Bitmask of SVNs (bit j corresponds to the SVN with index j) that back
+    // the timing parameters.
+    svns: u64,
+}
+
+impl<'a> ConstellationTiming<'a> {
+    /// Gives the most recent authenticated timing parameters.
+    ///
+    /// The authentication bits of this [`NavMessageData`] only refer to the
+    /// satellite given by [`ConstellationTiming::svn`]. The combined
+    /// authentication bits are given by [`ConstellationTiming::authbits`].
+    pub fn data(&self) -> NavMessageData<'a> {
+        self.data
+    }
+
+    /// Gives the SVN of the satellite from which [`ConstellationTiming::data`]
+    /// has been taken.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Returns the combined number of authentication bits of the timing
+    /// parameters.
+    ///
+    /// This is the sum of the authentication bits of the timing parameters of
+    /// all the satellites given by [`ConstellationTiming::svns`].
+    pub fn authbits(&self) -> u16 {
+        self.authbits
+    }
+
+    /// Returns an iterator over the satellites whose most recent authenticated
+    /// timing parameters are identical to [`ConstellationTiming::data`].
+    pub fn svns(&self) -> impl Iterator<Item = Svn> {
+        let svns = self.svns;
+        Svn::iter().filter(move |svn| svns & (1 << svn.index()) != 0)
+    }
+
+    /// Gives the number of satellites whose most recent authenticated timing
+    /// parameters are identical to [`ConstellationTiming::data`].
+    pub fn num_svns(&self) -> usize {
+        self.svns.count_ones() as usize
+    }
+
+    pub(crate) fn with_nma_dont_use(self, nma_dont_use: bool) -> ConstellationTiming<'a> {
+        ConstellationTiming {
+            data: self.data.with_nma_dont_use(nma_dont_use),
+            ..self
+        }
+    }
+}
+
+/// Number of constellation-related tags kept by [`ConstellationTags`].
+pub const CONSTELLATION_TAGS_DEPTH: usize = 8;
+
+/// Tag for Galileo constellation-related data.
+///
+/// This is a tag with PRND = 255, which authenticates data related to the
+/// whole Galileo constellation rather than to a particular satellite. The OSNMA
+/// SIS ICD does not define any ADKD for this kind of data yet, so these tags
+/// cannot be authenticated by [`CollectNavMessage`]. Instead, the most recent
+/// ones are kept and can be obtained with
+/// [`CollectNavMessage::constellation_tags`], so that they can be inspected and
+/// validated with [`ConstellationTag::validate`] once the data they
+/// authenticate is known.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ConstellationTag {
+    tag: BitArr!(for MAX_TAG_SIZE_BITS, in u8, Msb0),
+    tag_bits: u8,
+    adkd: u8,
+    cop: u8,
+    prna: Svn,
+    gst_mack: Gst,
+    ctr: u8,
+    nma_status: NmaStatus,
+}
+
+impl ConstellationTag {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        tag: &BitSlice,
+        adkd: u8,
+        cop: u8,
         prna: Svn,
         gst_mack: Gst,
+        ctr: u8,
         nma_status: NmaStatus,
-    ) {
-        log::info!("{} tag0 at {:?} COP = {}", prna, gst_mack, mack.cop());
-        let gst_navmessage = gst_mack.add_seconds(-30);
-        if mack.cop() == 0 {
-            Self::validate_dummy_tag(
-                key,
-                mack.tag0(),
-                Adkd::InavCed,
-                gst_mack,
-                u8::from(prna),
-                prna,
-                0,
-                nma_status,
-                CED_AND_STATUS_BITS,
-            );
-        } else if let Some(&navdata) = self.find_ced_and_status(prna, gst_navmessage) {
-            if navdata.max_age().saturating_add(1) <= mack.cop() {
-                // Try to validate tag0
-                Self::validate_tag(
-                    key,
-                    mack.tag0(),
-                    Adkd::InavCed,
-                    gst_mack,
-                    u8::from(prna),
-                    prna,
-                    0,
-                    nma_status,
-                    &navdata,
-                    self.ced_and_status_iter_authbits_mut(),
-                );
+    ) -> ConstellationTag {
+        let mut tag_array = BitArray::ZERO;
+        tag_array[..tag.len()].copy_from_bitslice(tag);
+        ConstellationTag {
+            tag: tag_array,
+            tag_bits: tag.len().try_into().unwrap(),
+            adkd,
+            cop,
+            prna,
+            gst_mack,
+            ctr,
+            nma_status,
+        }
+    }
+
+    /// Gives the tag.
+    pub fn tag(&self) -> &BitSlice {
+        &self.tag[..usize::from(self.tag_bits)]
+    }
+
+    /// Gives the value of the ADKD field in the Tag-Info section.
+    ///
+    /// The raw value of the field is returned, since none of the ADKDs defined
+    /// in [`Adkd`] are used for constellation-related data.
+    pub fn adkd(&self) -> u8 {
+        self.adkd
+    }
+
+    /// Gives the value of the COP field in the Tag-Info section.
+    pub fn cop(&self) -> u8 {
+        self.cop
+    }
+
+    /// Gives the SVN of the satellite that transmitted the tag (PRNA).
+    pub fn prna(&self) -> Svn {
+        self.prna
+    }
+
+    /// Gives the GST at the start of the subframe in which the tag was
+    /// transmitted.
+    pub fn gst(&self) -> Gst {
+        self.gst_mack
+    }
+
+    /// Gives the index of the tag in its MACK message (CTR).
+    ///
+    /// The first tag after the tag0 has `ctr = 1`.
+    pub fn ctr(&self) -> u8 {
+        self.ctr
+    }
+
+    /// Tries to validate the tag and its corresponding data.
+    ///
+    /// The `key` must be the TESLA key transmitted in the subframe after the
+    /// tag (or 11 subframes after the tag if the ADKD uses the Slow MAC
+    /// delay), and `navdata` must be the constellation-related data
+    /// transmitted in the subframe before the tag, given as [`CedBits`] or
+    /// [`TimingBits`] according to its ADKD. The NMA status of the NMA header at the time of the tag is
+    /// used.
+    ///
+    /// This returns `true` if the validation was successful. Otherwise, it
+    /// returns `false`.
+    pub fn validate<'a>(&self, key: &Key<Validated>, navdata: impl Into<NavDataBits<'a>>) -> bool {
+        key.validate_tag(
+            self.tag(),
+            self.gst_mack,
+            255,
+            self.prna,
+            self.ctr,
+            self.nma_status,
+            navdata,
+        )
+    }
+}
+
+/// Formats the tag as in `"PRND=255 tag3 ADKD=5 COP=1 by E11 at WN=1176 TOW=121080"`.
+impl fmt::Display for ConstellationTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PRND=255 tag{} ADKD={} COP={} by {} at {}",
+            self.ctr, self.adkd, self.cop, self.prna, self.gst_mack
+        )
+    }
+}
+
+/// Statistics of tags with reserved ADKD values.
+///
+/// Tags with a reserved ADKD value cannot be authenticated. They can appear in
+/// flexible slots of the MAC look-up table, or in fixed slots if the MACK
+/// message was validated with
+/// [`AdkdCheckPolicy::SkipTag`](crate::bitfields::AdkdCheckPolicy::SkipTag).
+/// If they appear consistently, this probably means that a new ADKD has been
+/// introduced in an update of the OSNMA SIS ICD. The statistics are obtained
+/// with [`CollectNavMessage::reserved_adkd_stats`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ReservedAdkdStats {
+    tags: u32,
+    consecutive_macks: u32,
+    last_adkd: Option<u8>,
+}
+
+impl ReservedAdkdStats {
+    /// Gives the total number of tags with a reserved ADKD value.
+    pub fn tags(&self) -> u32 {
+        self.tags
+    }
+
+    /// Gives the number of consecutive MACK messages containing tags with a
+    /// reserved ADKD value.
+    ///
+    /// This is reset to zero each time that a MACK message without this kind
+    /// of tags is processed.
+    pub fn consecutive_macks(&self) -> u32 {
+        self.consecutive_macks
+    }
+
+    /// Gives the value of the ADKD field of the last tag with a reserved ADKD
+    /// value.
+    pub fn last_adkd(&self) -> Option<u8> {
+        self.last_adkd
+    }
+
+    fn update(&mut self, mack: &Mack<Validated>) {
+        let mut found = false;
+        for j in 1..mack.num_valid_tags() {
+            let tag = mack.tag_and_info(j);
+            if tag.adkd() == Adkd::Reserved {
+                found = true;
+                self.tags = self.tags.saturating_add(1);
+                self.last_adkd = Some(tag.tag_info()[8..12].load_be());
+            }
+        }
+        self.consecutive_macks = if found {
+            self.consecutive_macks.saturating_add(1)
+        } else {
+            0
+        };
+    }
+}
+
+/// Count of anomalous events.
+///
+/// This gives how many times an event has happened, and the GSTs of its first
+/// and last occurrences. See [`AnomalyTracker`].
+#[cfg(feature = "nav-diagnostics")]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct AnomalyCount {
+    count: u32,
+    first: Option<Gst>,
+    last: Option<Gst>,
+}
+
+#[cfg(feature = "nav-diagnostics")]
+impl AnomalyCount {
+    /// Gives the number of occurrences of the event.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Gives the GST of the first occurrence of the event.
+    ///
+    /// This is `None` if the event has not happened.
+    pub fn first(&self) -> Option<Gst> {
+        self.first
+    }
+
+    /// Gives the GST of the last occurrence of the event.
+    ///
+    /// This is `None` if the event has not happened.
+    pub fn last(&self) -> Option<Gst> {
+        self.last
+    }
+
+    fn record(&mut self, gst: Gst) {
+        self.count = self.count.saturating_add(1);
+        self.first.get_or_insert(gst);
+        self.last = Some(self.last.map_or(gst, |last| last.latest(gst)));
+    }
+}
+
+/// Anomalies detected for a satellite.
+///
+/// See [`AnomalyTracker`].
+#[cfg(feature = "nav-diagnostics")]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SatelliteAnomalies {
+    word_mismatches: AnomalyCount,
+    failed_tags: AnomalyCount,
+    macseq_failures: AnomalyCount,
+}
+
+#[cfg(feature = "nav-diagnostics")]
+impl SatelliteAnomalies {
+    /// Gives the INAV words received from the satellite that differed from its
+    /// authenticated navigation data.
+    ///
+    /// The GSTs are those of the subframes in which the words were received.
+    pub fn word_mismatches(&self) -> &AnomalyCount {
+        &self.word_mismatches
+    }
+
+    /// Gives the tags that failed to authenticate the navigation data of the
+    /// satellite.
+    ///
+    /// These are the tags whose PRND is the satellite, regardless of which
+    /// satellite transmitted them. The GSTs are those of the subframes in which
+    /// the tags were transmitted.
+    pub fn failed_tags(&self) -> &AnomalyCount {
+        &self.failed_tags
+    }
+
+    /// Gives the MACK messages transmitted by the satellite whose MACSEQ field
+    /// could not be verified.
+    ///
+    /// The GSTs are those of the subframes in which the MACK messages were
+    /// transmitted.
+    pub fn macseq_failures(&self) -> &AnomalyCount {
+        &self.macseq_failures
+    }
+
+    /// Returns `true` if any anomaly has been detected for the satellite.
+    pub fn any(&self) -> bool {
+        self.word_mismatches.count != 0
+            || self.failed_tags.count != 0
+            || self.macseq_failures.count != 0
+    }
+}
+
+/// Tracker of anomalies that may indicate spoofing.
+///
+/// This counts, for each satellite, the INAV words that differ from
+/// authenticated navigation data, the tags that fail verification, and the
+/// MACK messages whose MACSEQ verification fails. Some of these events can
+/// happen legitimately (for instance, the navigation data changes when a
+/// satellite starts broadcasting a new batch), but a sustained rate of them is
+/// a strong indication of an attacker injecting forged data. The tracker is
+/// obtained with [`CollectNavMessage::anomalies`].
+#[cfg(feature = "nav-diagnostics")]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AnomalyTracker {
+    satellites: [SatelliteAnomalies; NUM_SVNS],
+}
+
+#[cfg(feature = "nav-diagnostics")]
+impl Default for AnomalyTracker {
+    fn default() -> AnomalyTracker {
+        AnomalyTracker {
+            satellites: [SatelliteAnomalies::default(); NUM_SVNS],
+        }
+    }
+}
+
+#[cfg(feature = "nav-diagnostics")]
+impl AnomalyTracker {
+    /// Gives the anomalies detected for a satellite.
+    pub fn satellite(&self, svn: Svn) -> &SatelliteAnomalies {
+        &self.satellites[svn.index()]
+    }
+
+    /// Returns an iterator over the satellites for which any anomaly has been
+    /// detected.
+    pub fn iter(&self) -> impl Iterator<Item = (Svn, &SatelliteAnomalies)> {
+        Svn::iter()
+            .zip(self.satellites.iter())
+            .filter(|(_, anomalies)| anomalies.any())
+    }
+
+    /// Clears all the anomalies.
+    pub fn clear(&mut self) {
+        *self = AnomalyTracker::default();
+    }
+
+    fn record_word_mismatch(&mut self, svn: Svn, gst: Gst) {
+        self.satellites[svn.index()].word_mismatches.record(gst);
+    }
+
+    fn record_failed_tag(&mut self, svn: Svn, gst: Gst) {
+        self.satellites[svn.index()].failed_tags.record(gst);
+    }
+
+    fn record_macseq_failure(&mut self, svn: Svn, gst: Gst) {
+        self.satellites[svn.index()].macseq_failures.record(gst);
+    }
+}
+
+/// Store of constellation-related tags.
+///
+/// This keeps the [`CONSTELLATION_TAGS_DEPTH`] most recent
+/// [`ConstellationTag`]s received. It is obtained with
+/// [`CollectNavMessage::constellation_tags`].
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ConstellationTags {
+    tags: [Option<ConstellationTag>; CONSTELLATION_TAGS_DEPTH],
+    write_pointer: usize,
+}
+
+impl ConstellationTags {
+    /// Returns an iterator over the stored tags, starting by the oldest one.
+    pub fn iter(&self) -> impl Iterator<Item = &ConstellationTag> {
+        let (newer, older) = self.tags.split_at(self.write_pointer);
+        older.iter().chain(newer.iter()).flatten()
+    }
+
+    /// Gives the most recent stored tag.
+    pub fn latest(&self) -> Option<&ConstellationTag> {
+        self.iter().last()
+    }
+
+    fn store(&mut self, tag: ConstellationTag) {
+        log::info!(
+            "storing {} (no ADKD defined for constellation-related data)",
+            tag
+        );
+        self.tags[self.write_pointer] = Some(tag);
+        self.write_pointer = (self.write_pointer + 1) % CONSTELLATION_TAGS_DEPTH;
+    }
+}
+
+/// Number of tag verification results kept by [`TagVerifications`].
+pub const TAG_VERIFICATIONS_DEPTH: usize = 32;
+
+/// Result of the verification of a tag.
+///
+/// This records which tag of a MACK message has been verified against the
+/// navigation data of which satellite, and whether the verification was
+/// successful. The most recent results are obtained with
+/// [`CollectNavMessage::tag_verifications`]. Dummy tags and tags that could
+/// not be verified because the corresponding navigation data was not available
+/// are not included.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TagVerification {
+    prna: Svn,
+    prnd: Svn,
+    adkd: Adkd,
+    gst: Gst,
+    tag_index: u8,
+    valid: bool,
+}
+
+impl TagVerification {
+    /// Gives the SVN of the satellite that transmitted the tag (PRNA).
+    pub fn prna(&self) -> Svn {
+        self.prna
+    }
+
+    /// Gives the SVN of the satellite whose navigation data is authenticated
+    /// by the tag (PRND).
+    pub fn prnd(&self) -> Svn {
+        self.prnd
+    }
+
+    /// Gives the ADKD of the tag.
+    pub fn adkd(&self) -> Adkd {
+        self.adkd
+    }
+
+    /// Gives the GST at the start of the subframe in which the MACK message
+    /// containing the tag was transmitted.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the index of the tag in the MACK message.
+    ///
+    /// The tag0 has index 0.
+    pub fn tag_index(&self) -> u8 {
+        self.tag_index
+    }
+
+    /// Returns `true` if the tag has been verified successfully.
+    ///
+    /// In this case the tag has authenticated the navigation data of the PRND
+    /// given by [`TagVerification::prnd`].
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+/// Formats the tag verification as in
+/// `"E19 tag3 ADKD=4 (I/NAV timing parameters) for E11 at WN=1176 TOW=120930 valid"`.
+impl fmt::Display for TagVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tag{} {} for {} at {} {}",
+            self.prna,
+            self.tag_index,
+            self.adkd,
+            self.prnd,
+            self.gst,
+            if self.valid { "valid" } else { "failed" }
+        )
+    }
+}
+
+/// Store of tag verification results.
+///
+/// This keeps the [`TAG_VERIFICATIONS_DEPTH`] most recent
+/// [`TagVerification`]s. It is obtained with
+/// [`CollectNavMessage::tag_verifications`].
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct TagVerifications {
+    entries: [Option<TagVerification>; TAG_VERIFICATIONS_DEPTH],
+    write_pointer: usize,
+}
+
+impl TagVerifications {
+    /// Returns an iterator over the stored results, starting by the oldest
+    /// one.
+    pub fn iter(&self) -> impl Iterator<Item = &TagVerification> {
+        let (newer, older) = self.entries.split_at(self.write_pointer);
+        older.iter().chain(newer.iter()).flatten()
+    }
+
+    /// Gives the most recent stored result.
+    pub fn latest(&self) -> Option<&TagVerification> {
+        self.iter().last()
+    }
+
+    fn store(&mut self, verification: TagVerification) {
+        self.entries[self.write_pointer] = Some(verification);
+        self.write_pointer = (self.write_pointer + 1) % TAG_VERIFICATIONS_DEPTH;
+    }
+}
+
+impl<S: Storage> CollectNavMessage<S> {
+    /// Constructs a new, empty navigation message storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage type `S` does not follow the consistency rules
+    /// described in [`Storage`].
+    pub fn new() -> CollectNavMessage<S> {
+        S::assert_consistent();
+        CollectNavMessage {
+            ced_and_status: Default::default(),
+            timing_parameters: Default::default(),
+            gsts: GenericArray::default(),
+            write_pointer: 0,
+            cops: [[None; 3]; NUM_SVNS],
+            #[cfg(feature = "cross-auth")]
+            cross_auth: [[None; 3]; NUM_SVNS],
+            mismatch_policy: MismatchPolicy::default(),
+            reconcile_history: false,
+            unique_tag_authbits: false,
+            min_authbits: MIN_AUTHBITS,
+            adkd_filter: AdkdFilter::ALL,
+            cop_policies: [CopPolicy::DEFAULT; 3],
+            constellation_tags: ConstellationTags::default(),
+            tag_verifications: TagVerifications::default(),
+            failed_tags: [0; NUM_SVNS],
+            #[cfg(feature = "nav-diagnostics")]
+            anomalies: AnomalyTracker::default(),
+            reserved_adkd_stats: ReservedAdkdStats::default(),
+            gst_guard: None,
+        }
+    }
+
+    /// Sets the policy for INAV words that differ from authenticated data.
+    ///
+    /// See [`MismatchPolicy`].
+    pub fn set_mismatch_policy(&mut self, policy: MismatchPolicy) {
+        self.mismatch_policy = policy;
+    }
+
+    /// Gives the policy for INAV words that differ from authenticated data.
+    pub fn mismatch_policy(&self) -> MismatchPolicy {
+        self.mismatch_policy
+    }
+
+    /// Enables or disables the reconciliation of authentication bits across
+    /// the subframes in the storage.
+    ///
+    /// The navigation data of a satellite is stored separately for each
+    /// subframe in the storage. Each stored copy accumulates the
+    /// authentication bits of the tags that have been validated since it was
+    /// stored, so the same navigation data can appear at several GSTs with
+    /// different authentication bits. This happens for instance if the stored
+    /// data is replaced by a corrupted word and then restored by a correct
+    /// copy of the word, since replacing the data resets its authentication.
+    ///
+    /// By default, [`CollectNavMessage::get_ced_and_status`] and
+    /// [`CollectNavMessage::get_timing_parameters`] return the most recent
+    /// copy that is authenticated on its own, which can be an older duplicate
+    /// of the most recent data. When reconciliation is enabled, the
+    /// authentication bits of all the copies of the same navigation data are
+    /// taken into account, so the most recent copy is returned with the
+    /// maximum authentication bits of all its copies.
+    pub fn set_history_reconciliation(&mut self, enabled: bool) {
+        self.reconcile_history = enabled;
+    }
+
+    /// Enables or disables counting only the authentication bits of tags from
+    /// distinct sources.
+    ///
+    /// The source of a tag is the pair formed by the authenticating PRN (the
+    /// satellite that transmitted the tag) and the GST of the subframe in
+    /// which the tag was transmitted. By default, the authentication bits of
+    /// all the validated tags that authenticate some navigation data are
+    /// counted towards the minimum of 40 bits required by the OSNMA
+    /// guidelines. This includes tags from the same source, such as the tag0
+    /// and a tag with PRND equal to PRNA in the same MACK message, or an
+    /// ADKD=0 and an ADKD=12 tag for the same data. These tags do not provide
+    /// independent authentication evidence, since they are computed with the
+    /// same TESLA key or transmitted by the same satellite at the same time.
+    ///
+    /// When this option is enabled, only the first validated tag from each
+    /// source counts towards the minimum, and the navigation data is
+    /// considered authenticated when the bits of these tags reach it. Both
+    /// counts are available in [`NavMessageData::authbits`] and
+    /// [`NavMessageData::unique_authbits`] regardless of this option.
+    ///
+    /// This is only available with the `unique-authbits` feature, since
+    /// keeping track of the sources of the tags increases the size of the
+    /// storage of the navigation data.
+    #[cfg(feature = "unique-authbits")]
+    pub fn set_unique_tag_authbits(&mut self, enabled: bool) {
+        self.unique_tag_authbits = enabled;
+    }
+
+    /// Returns `true` if only the authentication bits of tags from distinct
+    /// sources are counted.
+    ///
+    /// See [`CollectNavMessage::set_unique_tag_authbits`].
+    #[cfg(feature = "unique-authbits")]
+    pub fn unique_tag_authbits(&self) -> bool {
+        self.unique_tag_authbits
+    }
+
+    /// Sets the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    ///
+    /// The default is [`MIN_AUTHBITS`], which is the minimum required by the
+    /// OSNMA guidelines. Values smaller than this are raised to
+    /// [`MIN_AUTHBITS`]. Applications that require more confidence can
+    /// increase the threshold, at the cost of a longer time to authenticate.
+    /// The latency of the navigation data (see [`NavMessageData::latency`])
+    /// still gives the time at which [`MIN_AUTHBITS`] were reached.
+    pub fn set_min_authbits(&mut self, bits: u16) {
+        self.min_authbits = bits.max(MIN_AUTHBITS);
+    }
+
+    /// Gives the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    ///
+    /// See [`CollectNavMessage::set_min_authbits`].
+    pub fn min_authbits(&self) -> u16 {
+        self.min_authbits
+    }
+
+    /// Sets the ADKDs that are processed.
+    ///
+    /// By default, all the ADKDs are processed. Receivers that only use
+    /// OSNMA for positioning or only for timing can remove the ADKDs they do
+    /// not need with this function. The INAV words that are only
+    /// authenticated by the removed ADKDs are not stored, and the tags with
+    /// these ADKDs are ignored without computing their MAC. See
+    /// [`AdkdFilter`].
+    ///
+    /// The navigation data that is already stored is not modified, so this
+    /// should be called before feeding any data.
+    pub fn set_adkd_filter(&mut self, filter: AdkdFilter) {
+        self.adkd_filter = filter;
+    }
+
+    /// Gives the ADKDs that are processed.
+    pub fn adkd_filter(&self) -> AdkdFilter {
+        self.adkd_filter
+    }
+
+    /// Sets the COP policy for an ADKD.
+    ///
+    /// By default, all the ADKDs use [`CopPolicy::DEFAULT`]. Setting the
+    /// policy for a reserved ADKD has no effect. See [`CopPolicy`].
+    pub fn set_cop_policy(&mut self, adkd: Adkd, policy: CopPolicy) {
+        if let Some(idx) = adkd_index(adkd) {
+            self.cop_policies[idx] = policy;
+        }
+    }
+
+    /// Gives the COP policy for an ADKD.
+    ///
+    /// For reserved ADKDs this returns [`CopPolicy::DEFAULT`].
+    pub fn cop_policy(&self, adkd: Adkd) -> CopPolicy {
+        adkd_index(adkd).map_or(CopPolicy::DEFAULT, |idx| self.cop_policies[idx])
+    }
+
+    /// Returns `true` if the reconciliation of authentication bits across the
+    /// subframes in the storage is enabled.
+    ///
+    /// See [`CollectNavMessage::set_history_reconciliation`].
+    pub fn history_reconciliation(&self) -> bool {
+        self.reconcile_history
+    }
+
+    /// Sets the policy used to check the GST of the INAV words.
+    ///
+    /// If a policy is given, the INAV words whose GST regresses or jumps
+    /// forward unrealistically are rejected, as described in the
+    /// [gstguard](crate::gstguard) module. If `None` is given, the checks are
+    /// disabled. The checks are disabled by default.
+    ///
+    /// Setting a policy resets the statistics of the rejected words.
+    pub fn set_gst_guard(&mut self, policy: Option<GstGuardPolicy>) {
+        self.gst_guard = policy.map(GstGuard::new);
+    }
+
+    /// Returns the statistics of the INAV words rejected due to their GST.
+    ///
+    /// See [`CollectNavMessage::set_gst_guard`].
+    pub fn gst_rejections(&self) -> GstRejectionStats {
+        self.gst_guard
+            .as_ref()
+            .map(|guard| *guard.stats())
+            .unwrap_or_default()
+    }
+
+    /// Feed an INAV word into the navigation message storage.
+    ///
+    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
+    /// the INAV word. This should be obtained from the PRN used for tracking.
+    ///
+    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
+    ///
+    /// The `band` parameter indicates the band in which the INAV word was
+    /// received. The subframe to which the word belongs is determined taking
+    /// into account the 1 second offset of the E5b-I page timing. See
+    /// [`InavBand::e1b_page_gst`]. The words received in either band are used
+    /// to assemble both the CED and health status data (ADKD=0 and 12) and the
+    /// timing parameters (ADKD=4), so a receiver that only tracks E5b can
+    /// authenticate its navigation data, provided that the OSNMA data
+    /// messages of E1-B are obtained by other means. The word type 10 is
+    /// transmitted in E5b-I in the subframes in which E1-B does not transmit
+    /// it, so when both bands are received, the copy received in E1-B is used
+    /// for the timing parameters.
+    pub fn feed(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        let source = WordSource {
+            band,
+            #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+            gst,
+            crc_checked: false,
+        };
+        let _ = self.feed_with_diagnostics(word, svn, gst, source, |_| {});
+    }
+
+    /// Feed an INAV word whose CRC has been checked.
+    ///
+    /// This is the same as [`CollectNavMessage::feed`], but indicates that the
+    /// receiver has checked that the CRC of the INAV page containing the word
+    /// is correct. INAV words whose CRC is not correct should not be fed.
+    ///
+    /// The same word is usually received in both the E1-B and E5b-I bands
+    /// during a subframe. If both copies are identical, the word is only
+    /// stored once. If they differ, one of them has probably been corrupted.
+    /// In this case the copy whose CRC has been checked is preferred, and
+    /// otherwise the first copy is kept, so that a corrupted copy does not
+    /// discard the authentication of the stored data.
+    pub fn feed_crc_checked(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        let source = WordSource {
+            band,
+            #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+            gst,
+            crc_checked: true,
+        };
+        let _ = self.feed_with_diagnostics(word, svn, gst, source, |_| {});
+    }
+
+    // Same as feed, but a DiagnosticKind::WordMismatch is reported each time
+    // that the word differs from authenticated data, and a
+    // DiagnosticKind::GstRejected is reported if the GST is rejected. These
+    // problems, as well as the eviction of the data of another satellite to
+    // make room for the word, are also returned as an error.
+    pub(crate) fn feed_with_diagnostics(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        source: WordSource,
+        mut report: impl FnMut(Diagnostic),
+    ) -> Result<(), FeedError> {
+        log::trace!(
+            "feeding INAV word = {:02x?} for {} GST {:?}",
+            word,
+            svn,
+            gst
+        );
+        let page_gst = source.band.e1b_page_gst(gst);
+        let gst = page_gst.gst_subframe();
+        if let Some(guard) = &mut self.gst_guard {
+            if let Err(rejection) = guard.check(page_gst) {
+                let diagnostic =
+                    Diagnostic::new(DiagnosticKind::GstRejected { rejection }, Some(svn), gst);
+                log::debug!("{}", diagnostic);
+                report(diagnostic);
+                return Err(FeedError::GstRejected(rejection));
+            }
+        }
+        self.adjust_write_pointer(gst);
+        self.make_room(svn);
+        let policy = self.mismatch_policy;
+        let current = self.current_range();
+        let mut mismatch = false;
+        let mut evicted = None;
+        if self.adkd_filter.collects_ced_and_status() {
+            let (m, e) = Self::feed_entries(
+                &mut self.ced_and_status[current.clone()],
+                word,
+                svn,
+                source,
+                policy,
+            );
+            mismatch |= m;
+            evicted = evicted.or(e);
+        }
+        if self.adkd_filter.collects_timing_parameters() {
+            let (m, e) = Self::feed_entries(
+                &mut self.timing_parameters[current],
+                word,
+                svn,
+                source,
+                policy,
+            );
+            mismatch |= m;
+            evicted = evicted.or(e);
+        }
+        if mismatch {
+            #[cfg(feature = "nav-diagnostics")]
+            self.anomalies.record_word_mismatch(svn, gst);
+            let word_type = InavWordType::of(word).into();
+            let diagnostic =
+                Diagnostic::new(DiagnosticKind::WordMismatch { word_type }, Some(svn), gst);
+            if policy == MismatchPolicy::TakeNew {
+                log::debug!("{}; replacing authenticated data", diagnostic);
+            }
+            report(diagnostic);
+            return Err(FeedError::WordMismatch { word_type });
+        }
+        match evicted {
+            Some(evicted) => Err(FeedError::StorageEviction { evicted }),
+            None => Ok(()),
+        }
+    }
+
+    // Returns true if the word differs from authenticated data, and the SVN
+    // of the satellite whose data has been evicted to store the word, if any.
+    fn feed_entries<T: NavDataEntry>(
+        entries: &mut [T],
+        word: &InavWord,
+        svn: Svn,
+        source: WordSource,
+        policy: MismatchPolicy,
+    ) -> (bool, Option<Svn>) {
+        // Search for best location to place this SVN
+        let idx = Self::select_entry(entries, |x| match x.svn() {
+            Some(s) if s == svn && !x.is_retained() => u16::from(u8::MAX) + 2,
+            None => u16::from(u8::MAX) + 1,
+            _ => u16::from(x.max_age()),
+        });
+        let entry = &mut entries[idx];
+        log::trace!(
+            "selected {} store with SVN {:?} and age {}",
+            T::NAME,
+            entry.svn(),
+            entry.max_age(),
+        );
+        let was_authenticated = entry.svn() == Some(svn) && entry.is_authenticated();
+        let evicted = entry.svn().filter(|&s| s != svn);
+        if let Some(evicted) = evicted {
+            log::debug!(
+                "evicting {} for {} to store data for {}",
+                T::NAME,
+                evicted,
+                svn
+            );
+        }
+        if entry.feed_word(word, svn, source, policy) {
+            let is_authenticated = entry.is_authenticated();
+            if is_authenticated {
+                // The retained copies for this SVN are no longer needed
+                for other in entries.iter_mut() {
+                    if other.is_retained() && other.svn() == Some(svn) {
+                        log::debug!("releasing retained {} for {}", T::NAME, svn);
+                        other.reset();
+                    }
+                }
+            }
+            return (was_authenticated && !is_authenticated, evicted);
+        }
+        match policy {
+            MismatchPolicy::TakeNew => unreachable!(),
+            MismatchPolicy::KeepOld => {
+                log::warn!(
+                    "{} word for {} differs from authenticated data; discarding word ({})",
+                    T::NAME,
+                    svn,
+                    Hint::NewNavigationData
+                );
+            }
+            MismatchPolicy::Quarantine => {
+                log::warn!(
+                    "{} word for {} differs from authenticated data; \
+                     retaining authenticated data ({})",
+                    T::NAME,
+                    svn,
+                    Hint::NewNavigationData
+                );
+                let mut retained = *entry;
+                retained.set_retained();
+                entry.feed_word(word, svn, source, MismatchPolicy::TakeNew);
+                let retained_idx = Self::select_entry(entries, |x| match x.svn() {
+                    Some(s) if s == svn && !x.is_retained() => 0,
+                    Some(s) if s == svn => u16::from(u8::MAX) + 2,
+                    None => u16::from(u8::MAX) + 1,
+                    _ => u16::from(x.max_age()),
+                });
+                if retained_idx != idx {
+                    entries[retained_idx] = retained;
+                } else {
+                    log::warn!("no storage available to retain {} for {}", T::NAME, svn);
+                }
+            }
+        }
+        (true, None)
+    }
+
+    fn select_entry<T>(entries: &[T], key: impl Fn(&T) -> u16) -> usize {
+        entries
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, x)| key(x))
+            .unwrap()
+            .0
+    }
+
+    fn num_sats(&self) -> usize {
+        self.ced_and_status.num_sats()
+    }
+
+    fn slot_range(&self, slot: usize) -> core::ops::Range<usize> {
+        let num_sats = self.num_sats();
+        slot * num_sats..(slot + 1) * num_sats
+    }
+
+    fn current_range(&self) -> core::ops::Range<usize> {
+        self.slot_range(self.write_pointer)
+    }
+
+    // If the storage can grow, this adds a location for a new satellite when
+    // the current subframe does not have a location for this SVN and all the
+    // locations are taken by other satellites.
+    fn make_room(&mut self, svn: Svn) {
+        let current = self.current_range();
+        if (Self::is_full(&self.ced_and_status[current.clone()], svn)
+            || Self::is_full(&self.timing_parameters[current], svn))
+            && self.ced_and_status.grow()
+        {
+            self.timing_parameters.grow();
+            log::debug!(
+                "grew navigation message storage to {} satellites",
+                self.num_sats()
+            );
+        }
+    }
+
+    fn is_full<T: NavDataEntry>(entries: &[T], svn: Svn) -> bool {
+        entries
+            .iter()
+            .all(|x| x.svn().is_some_and(|s| s != svn || x.is_retained()))
+    }
+
+    fn adjust_write_pointer(&mut self, gst: Gst) {
+        // If write pointer points to a valid GST which is distinct from the
+        // current, we advance the write pointer and copy the old CED and status
+        // and timing parameters to the new write pointer location. We increase
+        // the stale counter of the copy.
+        if let Some(g) = self.gsts[self.write_pointer] {
+            if g != gst {
+                log::trace!(
+                    "got a new GST {:?} (current GST is {:?}); \
+                     advancing write pointer",
+                    gst,
+                    g
+                );
+                let new_pointer = (self.write_pointer + 1) % S::NavMessageDepth::USIZE;
+                let current = self.current_range();
+                let new_start = new_pointer * self.num_sats();
+                self.ced_and_status.copy_within(current.clone(), new_start);
+                self.timing_parameters.copy_within(current, new_start);
+                self.write_pointer = new_pointer;
+                self.increase_age();
+                if log::log_enabled!(log::Level::Debug) {
+                    log::debug!("advanced write pointer to {:?}", gst);
+                    log::debug!("CedAndStatus contents:");
+                    for elem in self.ced_and_status[self.current_range()].iter() {
+                        log::debug!(
+                            "SVN {:?}: age {:?} authbits {}",
+                            elem.svn,
+                            elem.age,
+                            elem.authbits
+                        );
+                    }
+                    log::debug!("TimingParameters contents:");
+                    for elem in self.timing_parameters[self.current_range()].iter() {
+                        log::debug!(
+                            "SVN {:?}: age {:?} authbits {}",
+                            elem.svn,
+                            elem.age,
+                            elem.authbits
+                        );
+                    }
+                }
+            }
+        }
+        self.gsts[self.write_pointer] = Some(gst);
+    }
+
+    fn current_ced_as_mut(&mut self) -> &mut [CedAndStatus] {
+        let current = self.current_range();
+        &mut self.ced_and_status[current]
+    }
+
+    fn current_timing_parameters_as_mut(&mut self) -> &mut [TimingParameters] {
+        let current = self.current_range();
+        &mut self.timing_parameters[current]
+    }
+
+    fn increase_age(&mut self) {
+        for ced in self.current_ced_as_mut().iter_mut() {
+            for age in ced.age.iter_mut() {
+                *age = age.saturating_add(1);
+            }
+        }
+        for timing_parameters in self.current_timing_parameters_as_mut().iter_mut() {
+            for age in timing_parameters.age.iter_mut() {
+                *age = age.saturating_add(1);
+            }
+        }
+    }
+
+    /// Try to get authenticated CED and health status data for a satellite.
+    ///
+    /// This will try to retrieve the most recent authenticated CED and health
+    /// status data (ADKD=0 and 12) for the satellite with SVN `svn` that is
+    /// available in the OSNMA storage. If the storage does not contain any
+    /// authenticated CED and health status data for this SVN, this returns
+    /// `None`.
+    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.get_navdata(&self.ced_and_status, svn)
+    }
+
+    /// Try to get authenticated timing parameters for a satellite.
+    ///
+    /// This will try to retrieve the most recent timing parameters data
+    /// (ADKD=4) for the satellite with SNV`svn` that is available in the OSNMA
+    /// storage. If the storage does not contain any authenticated timing
+    /// parameters data for this SVN, this returns `None`.
+    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.get_navdata(&self.timing_parameters, svn)
+    }
+
+    /// Try to get the reduced CED of a satellite.
+    ///
+    /// This gives the most recent reduced CED (INAV word type 16) received
+    /// from the satellite with SVN `svn`, if it is still held in the storage
+    /// together with the CED and health status data of that satellite. The
+    /// reduced CED is not authenticated by OSNMA. See [`ReducedCed`].
+    ///
+    /// This is only available with the `reduced-ced` feature.
+    #[cfg(feature = "reduced-ced")]
+    pub fn get_reduced_ced(&self, svn: Svn) -> Option<ReducedCed> {
+        self.ced_and_status[self.current_range()]
+            .iter()
+            .find(|entry| entry.svn == Some(svn) && !entry.retained)
+            .and_then(|entry| entry.reduced_ced)
+    }
+
+    /// Try to get the authenticated timing parameters of the constellation.
+    ///
+    /// The timing parameters (ADKD=4) are common to all the satellites, so
+    /// this merges the most recent authenticated timing parameters of all the
+    /// satellites. The timing parameters received most recently are chosen.
+    /// If several satellites give different timing parameters received in the
+    /// same subframe, the ones backed by the largest combined number of
+    /// authentication bits are chosen. See [`ConstellationTiming`].
+    ///
+    /// If the storage does not contain any authenticated timing parameters,
+    /// this returns `None`.
+    pub fn get_constellation_timing(&self) -> Option<ConstellationTiming<'_>> {
+        let latest = || Svn::iter().filter_map(|svn| Some((svn, self.get_timing_parameters(svn)?)));
+        let newest = latest().map(|(_, data)| data.gst()).reduce(Gst::latest)?;
+        let mut best: Option<ConstellationTiming> = None;
+        for (svn, data) in latest().filter(|(_, data)| data.gst() == newest) {
+            if best.is_some_and(|best| best.data.data() == data.data()) {
+                continue;
+            }
+            let mut timing = ConstellationTiming {
+                data,
+                svn,
+                authbits: 0,
+                svns: 0,
+            };
+            for (other_svn, other) in latest() {
+                if other.data() == data.data() {
+                    timing.authbits = timing.authbits.saturating_add(other.authbits());
+                    timing.svns |= 1 << other_svn.index();
+                }
+            }
+            if best.map_or(true, |best| timing.authbits > best.authbits) {
+                best = Some(timing);
+            }
+        }
+        best
+    }
+
+    /// Try to get authenticated CED and health status data for a satellite as
+    /// it was at some GST.
+    ///
+    /// This is the same as [`CollectNavMessage::get_ced_and_status`], but
+    /// only the navigation data stored in subframes starting at or before
+    /// `gst` is considered. This gives the most recent authenticated data
+    /// among the data that had been received at `gst`, provided that it is
+    /// still retained in the storage.
+    pub fn get_ced_and_status_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.get_navdata_at(&self.ced_and_status, svn, Some(gst))
+    }
+
+    /// Try to get authenticated timing parameters for a satellite as they were
+    /// at some GST.
+    ///
+    /// This is the same as [`CollectNavMessage::get_timing_parameters`], but
+    /// only the navigation data stored in subframes starting at or before
+    /// `gst` is considered. See [`CollectNavMessage::get_ced_and_status_at`].
+    pub fn get_timing_parameters_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.get_navdata_at(&self.timing_parameters, svn, Some(gst))
+    }
+
+    /// Returns an iterator over all the authenticated navigation data in the
+    /// storage.
+    ///
+    /// The iterator gives the SVN of the satellite, the kind of the
+    /// navigation data, the GST at the start of the subframe in which the
+    /// data is stored, and the data. The storage keeps a copy of the
+    /// navigation data of each satellite for each subframe, so the same data
+    /// is given once for each subframe in which it was stored. This can be
+    /// used to reconstruct which data was authenticated at each epoch. The
+    /// data is given in order of increasing subframe GST.
+    pub fn authenticated_data(
+        &self,
+    ) -> impl Iterator<Item = (Svn, NavDataKind, Gst, NavMessageData<'_>)> + '_ {
+        (0..S::NavMessageDepth::USIZE)
+            .map(move |j| (self.write_pointer + 1 + j) % S::NavMessageDepth::USIZE)
+            .filter_map(move |gst_idx| self.gsts[gst_idx].map(|gst| (gst_idx, gst)))
+            .flat_map(move |(gst_idx, gst)| {
+                let ced =
+                    self.slot_navdata(&self.ced_and_status, gst_idx, NavDataKind::CedAndStatus);
+                let timing = self.slot_navdata(
+                    &self.timing_parameters,
+                    gst_idx,
+                    NavDataKind::TimingParameters,
+                );
+                ced.chain(timing)
+                    .map(move |(svn, kind, data)| (svn, kind, gst, data))
+            })
+    }
+
+    fn slot_navdata<'a, T: NavDataEntry>(
+        &'a self,
+        entries: &'a [T],
+        gst_idx: usize,
+        kind: NavDataKind,
+    ) -> impl Iterator<Item = (Svn, NavDataKind, NavMessageData<'a>)> + 'a {
+        entries[self.slot_range(gst_idx)]
+            .iter()
+            .filter_map(move |item| {
+                let data = self.navdata_item(entries, gst_idx, item)?;
+                Some((item.svn()?, kind, data))
+            })
+    }
+
+    fn get_navdata<'a, T: NavDataEntry>(
+        &self,
+        entries: &'a [T],
+        svn: Svn,
+    ) -> Option<NavMessageData<'a>> {
+        self.get_navdata_at(entries, svn, None)
+    }
+
+    // Searches the most recent authenticated data for svn, only considering
+    // the subframes that start at or before until if it is not None.
+    fn get_navdata_at<'a, T: NavDataEntry>(
+        &self,
+        entries: &'a [T],
+        svn: Svn,
+        until: Option<Gst>,
+    ) -> Option<NavMessageData<'a>> {
+        // Search in order of decreasing Gst
+        for j in 0..S::NavMessageDepth::USIZE {
+            let gst_idx =
+                (S::NavMessageDepth::USIZE + self.write_pointer - j) % S::NavMessageDepth::USIZE;
+            if let (Some(until), Some(gst)) = (until, self.gsts[gst_idx]) {
+                if gst.is_after(until) {
+                    continue;
+                }
+            }
+            // Retained copies are only used if there is no other authenticated
+            // data in this subframe
+            let slot = &entries[self.slot_range(gst_idx)];
+            let items = slot.iter().filter(|item| !item.is_retained());
+            for item in items.chain(slot.iter().filter(|item| item.is_retained())) {
+                if item.svn() != Some(svn) {
+                    continue;
+                }
+                if let Some(data) = self.navdata_item(entries, gst_idx, item) {
+                    return Some(data);
+                }
+            }
+        }
+        None
+    }
+
+    // Returns the navigation data of item, which is stored in the subframe
+    // gst_idx, if it is authenticated.
+    fn navdata_item<'a, T: NavDataEntry>(
+        &self,
+        entries: &'a [T],
+        gst_idx: usize,
+        item: &'a T,
+    ) -> Option<NavMessageData<'a>> {
+        let unique = self.unique_tag_authbits;
+        let (authbits, latency) = if self.reconcile_history {
+            Self::reconciled_authbits(entries, item, unique)
+        } else {
+            (item.authbits_count(), item.counted_latency(unique))
+        };
+        if authbits.counted(unique) < self.min_authbits {
+            return None;
+        }
+        let latency = latency?;
+        let age: i32 = item.min_age().into();
+        let gst = self.gsts[gst_idx]?.wrapping_add_subframes(-age);
+        Some(NavMessageData {
+            data: item.message_bits().as_bitslice(),
+            authbits: authbits.all,
+            #[cfg(feature = "unique-authbits")]
+            unique_authbits: authbits.unique,
+            adkd_authbits: authbits.adkd,
+            gst,
+            latency,
+            #[cfg(feature = "provenance")]
+            provenance: item.provenance(),
+            nma_dont_use: false,
+        })
+    }
+
+    // Gives the maximum authentication bits of all the stored copies of the
+    // navigation data in `item`, and the latency of the copy that has them.
+    // The bits counted towards MIN_AUTHBITS are used to compare the copies.
+    fn reconciled_authbits<T: NavDataEntry>(
+        entries: &[T],
+        item: &T,
+        unique: bool,
+    ) -> (AuthBitsCount, Option<AuthLatency>) {
+        entries
+            .iter()
+            .filter(|other| {
+                other.svn() == item.svn() && other.message_bits() == item.message_bits()
+            })
+            .map(|other| (other.authbits_count(), other.counted_latency(unique)))
+            .max_by_key(|&(authbits, _)| authbits.counted(unique))
+            .unwrap_or((item.authbits_count(), item.counted_latency(unique)))
+    }
+
+    /// Estimate the authentication schedule for a satellite.
+    ///
+    /// This uses the COP field of the most recently validated tag for the
+    /// satellite with SVN `svn` and the ADKD `adkd` to estimate when its
+    /// navigation data can be authenticated and when the stored navigation data
+    /// needs to be refreshed. See [`AuthSchedule`].
+    ///
+    /// If no validated tag has been seen yet for this SVN and ADKD, or if
+    /// `adkd` is a reserved value, this returns `None`.
+    pub fn auth_schedule(&self, svn: Svn, adkd: Adkd) -> Option<AuthSchedule> {
+        let cop = self.cops[svn.index()][adkd_index(adkd)?]?;
+        let gst_current = self.gsts[self.write_pointer]?;
+        let current = self.current_range();
+        let max_age = match adkd {
+            Adkd::InavCed | Adkd::SlowMac => self.ced_and_status[current]
+                .iter()
+                .find(|item| item.svn == Some(svn) && !item.retained)
+                .map(|item| item.max_age()),
+            Adkd::InavTiming => self.timing_parameters[current]
+                .iter()
+                .find(|item| item.svn == Some(svn) && !item.retained)
+                .map(|item| item.max_age()),
+            Adkd::Reserved => unreachable!(),
+        };
+        // An age of u8::MAX indicates that some word has never been received
+        let max_age = max_age.filter(|&age| age != u8::MAX);
+        Some(AuthSchedule::new(cop, adkd, gst_current, max_age))
+    }
+
+    /// Gives the cross-authentication expectation for a satellite.
+    ///
+    /// This uses the tags with ADKD `adkd` and a non-zero COP that have been
+    /// received for the satellite with SVN `svn` to predict which satellites
+    /// are expected to transmit tags that authenticate its navigation data in
+    /// the upcoming subframes. See [`CrossAuthExpectation`].
+    ///
+    /// If no such tags have been received yet, or if `adkd` is a reserved
+    /// value, this returns `None`.
+    ///
+    /// This is only available with the `cross-auth` feature.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_expectation(&self, svn: Svn, adkd: Adkd) -> Option<CrossAuthExpectation> {
+        self.cross_auth[svn.index()][adkd_index(adkd)?]
+    }
+
+    /// Returns an iterator over the satellites whose navigation data is
+    /// expected to be authenticated by tags transmitted by a satellite.
+    ///
+    /// This gives the PRNDs of the [`CrossAuthExpectation`]s for the ADKD
+    /// `adkd` in which the satellite with SVN `prna` is an expected PRNA. A
+    /// receiver tracking `prna` can get the navigation data of these
+    /// satellites authenticated by receiving it within the COP.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_targets(&self, prna: Svn, adkd: Adkd) -> impl Iterator<Item = Svn> + '_ {
+        let idx = adkd_index(adkd);
+        Svn::iter().filter(move |&svn| {
+            idx.and_then(|idx| self.cross_auth[svn.index()][idx])
+                .is_some_and(|expectation| expectation.is_authenticated_by(prna))
+        })
+    }
+
+    /// Gives the most recent tag verification results.
+    ///
+    /// This records the result of each tag verified by
+    /// [`CollectNavMessage::process_mack`] and
+    /// `CollectNavMessage::process_mack_slowmac`, giving which tags have
+    /// authenticated the navigation data of which satellites. See
+    /// [`TagVerification`].
+    pub fn tag_verifications(&self) -> &TagVerifications {
+        &self.tag_verifications
+    }
+
+    /// Gives the number of tags that have failed to authenticate the
+    /// navigation data of a satellite.
+    ///
+    /// This counts all the failed tags whose PRND is `svn` since the storage
+    /// was constructed, unlike [`CollectNavMessage::tag_verifications`],
+    /// which only keeps the most recent results.
+    pub fn failed_tags(&self, svn: Svn) -> u32 {
+        self.failed_tags[svn.index()]
+    }
+
+    /// Gives the anomalies that may indicate spoofing.
+    ///
+    /// See [`AnomalyTracker`]. This is only available with the
+    /// `nav-diagnostics` feature.
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn anomalies(&self) -> &AnomalyTracker {
+        &self.anomalies
+    }
+
+    /// Clears the anomalies.
+    ///
+    /// This does not reset the counts given by
+    /// [`CollectNavMessage::failed_tags`].
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn clear_anomalies(&mut self) {
+        self.anomalies.clear();
+    }
+
+    // Records a MACK message transmitted by a satellite whose MACSEQ
+    // verification failed. This is called by the Osnma black box, which is in
+    // charge of validating the MACK messages.
+    #[cfg_attr(not(feature = "nav-diagnostics"), allow(unused_variables))]
+    pub(crate) fn record_macseq_failure(&mut self, svn: Svn, gst_mack: Gst) {
+        #[cfg(feature = "nav-diagnostics")]
+        self.anomalies.record_macseq_failure(svn, gst_mack);
+    }
+
+    /// Gives the most recent constellation-related tags.
+    ///
+    /// These are the tags with PRND = 255 contained in the MACK messages
+    /// processed by [`CollectNavMessage::process_mack`]. See
+    /// [`ConstellationTag`].
+    pub fn constellation_tags(&self) -> &ConstellationTags {
+        &self.constellation_tags
+    }
+
+    /// Gives the statistics of tags with reserved ADKD values.
+    ///
+    /// These are counted in the MACK messages processed by
+    /// [`CollectNavMessage::process_mack`]. See [`ReservedAdkdStats`].
+    pub fn reserved_adkd_stats(&self) -> &ReservedAdkdStats {
+        &self.reserved_adkd_stats
+    }
+
+    #[cfg_attr(not(feature = "cross-auth"), allow(unused_variables))]
+    fn record_cop(&mut self, svn: Svn, adkd: Adkd, cop: u8, prna: Svn, gst_mack: Gst) {
+        if let Some(idx) = adkd_index(adkd) {
+            self.cops[svn.index()][idx] = Some(cop);
+            #[cfg(feature = "cross-auth")]
+            if cop != 0 {
+                // Dummy tags do not authenticate any data, so they are not
+                // included in the cross-authentication expectation
+                match &mut self.cross_auth[svn.index()][idx] {
+                    Some(expectation) => expectation.record(prna, gst_mack, cop),
+                    entry => *entry = Some(CrossAuthExpectation::new(svn, prna, gst_mack, cop)),
+                }
+            }
+        }
+    }
+
+    fn find_ced_and_status(&mut self, svn: Svn, gst: Gst) -> Option<&CedAndStatus> {
+        let gst_idx = self.find_gst(gst)?;
+        self.ced_and_status[self.slot_range(gst_idx)]
+            .iter()
+            .find(|item| item.svn == Some(svn) && !item.retained)
+    }
+
+    fn find_timing_parameters(&mut self, svn: Svn, gst: Gst) -> Option<&TimingParameters> {
+        let gst_idx = self.find_gst(gst)?;
+        self.timing_parameters[self.slot_range(gst_idx)]
+            .iter()
+            .find(|item| item.svn == Some(svn) && !item.retained)
+    }
+
+    fn ced_and_status_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
+        self.ced_and_status.iter_mut().map(|x| {
+            let y: &mut dyn AuthBits = x;
+            y
+        })
+    }
+
+    fn timing_parameters_iter_authbits_mut(&mut self) -> impl Iterator<Item = &mut dyn AuthBits> {
+        self.timing_parameters.iter_mut().map(|x| {
+            let y: &mut dyn AuthBits = x;
+            y
+        })
+    }
+
+    fn find_gst(&self, gst: Gst) -> Option<usize> {
+        assert!(gst.is_subframe());
+        self.gsts
+            .iter()
+            .enumerate()
+            .find_map(|(j, &g)| if g == Some(gst) { Some(j) } else { None })
+    }
+
+    /// Process a MACK message.
+    ///
+    /// This processes a MACK message, authenticating stored navigation data as
+    /// possible. The `key` should be the TESLA key with which the tags in the
+    /// MACK message `mack` have been generated (recall that this key is
+    /// transmitted in the next subframe with respect to the MACK message). The
+    /// `prna` is the authenticating PRN, which is the SVN that has transmitted
+    /// the MACK message. The `gst_mack` parameter should be the GST
+    /// corresponding to the start of the subframe when the MACK message was
+    /// transmitted. The `nma_status` parameter should be the value of the NMA
+    /// status field in the current NMA header. The NMA header does not need to
+    /// be validated using the DSM-KROOT, since a forged or incorrect NMA header
+    /// will simply make tag validation fail.
+    ///
+    /// This function ignores the ADKD=12 (Slow MAC) tags in the MACK message,
+    /// since they do not correspond to `key`.
+    pub fn process_mack(
+        &mut self,
+        mack: Mack<Validated>,
+        key: &Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+    ) {
+        self.process_mack_with_results(mack, key, prna, gst_mack, nma_status);
+    }
+
+    // Same as process_mack, but returns the results of the validation of the
+    // tags.
+    pub(crate) fn process_mack_with_results(
+        &mut self,
+        mack: Mack<Validated>,
+        key: &Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+    ) -> TagResults {
+        let mut results = TagResults::default();
+        log::info!("{} tag0 at {:?} COP = {}", prna, gst_mack, mack.cop());
+        self.reserved_adkd_stats.update(&mack);
+        let gst_navmessage = gst_mack.wrapping_add_seconds(-30);
+        if !self.adkd_filter.contains(Adkd::InavCed) {
+            log::debug!("{} tag0 ignored by ADKD filter", prna);
+        } else if mack.cop() == 0 {
+            if Self::validate_dummy_tag(
+                key,
+                mack.tag0(),
+                Adkd::InavCed,
+                gst_mack,
+                u8::from(prna),
+                prna,
+                0,
+                nma_status,
+                CED_AND_STATUS_BITS,
+            ) {
+                self.record_cop(prna, Adkd::InavCed, 0, prna, gst_mack);
+            }
+        } else if let Some(&navdata) = self.find_ced_and_status(prna, gst_navmessage) {
+            if self
+                .cop_policy(Adkd::InavCed)
+                .allows(mack.cop(), navdata.max_age())
+            {
+                // Try to validate tag0
+                let valid = Self::validate_tag(
+                    key,
+                    mack.tag0(),
+                    Adkd::InavCed,
+                    gst_mack,
+                    u8::from(prna),
+                    prna,
+                    0,
+                    nma_status,
+                    &navdata,
+                    self.ced_and_status_iter_authbits_mut(),
+                );
+                self.record_tag(&mut results, prna, prna, Adkd::InavCed, gst_mack, 0, valid);
+                if valid {
+                    self.record_cop(prna, Adkd::InavCed, mack.cop(), prna, gst_mack);
+                }
+            }
+        }
+
+        // Try to validate InavCed and InavTiming tags
+        for j in 1..mack.num_valid_tags() {
+            let tag = mack.tag_and_info(j);
+            log::info!(
+                "{} tag{} {:?} at {:?} COP = {} PRND = {:?}",
+                prna,
+                j,
+                tag.adkd(),
+                gst_mack,
+                tag.cop(),
+                tag.prnd()
+            );
+            if tag.adkd() != Adkd::Reserved && !self.adkd_filter.contains(tag.adkd()) {
+                log::debug!("{} tag{} ignored by ADKD filter", prna, j);
+                continue;
+            }
+            if tag.prnd() == Prnd::GalileoConstellation {
+                // Storing the tag does not need its key, so constellation-related
+                // tags with the Slow MAC delay are also stored here
+                self.constellation_tags.store(ConstellationTag::new(
+                    tag.tag(),
+                    tag.tag_info()[8..12].load_be(),
+                    tag.cop(),
+                    prna,
+                    gst_mack,
+                    j.try_into().unwrap(),
+                    nma_status,
+                ));
+                continue;
+            }
+            let prnd = match u8::try_from(tag.prnd()) {
+                Ok(p) => p,
+                Err(_) => {
+                    log::error!("could not obtain PRND from tag {:?}", tag);
+                    continue;
+                }
+            };
+            match tag.adkd() {
+                Adkd::InavCed => match Svn::try_from(prnd) {
+                    Ok(prnd_svn) => {
+                        if tag.cop() == 0 {
+                            if Self::validate_dummy_tag(
+                                key,
+                                tag.tag(),
+                                tag.adkd(),
+                                gst_mack,
+                                prnd,
+                                prna,
+                                j,
+                                nma_status,
+                                CED_AND_STATUS_BITS,
+                            ) {
+                                self.record_cop(prnd_svn, tag.adkd(), 0, prna, gst_mack);
+                            }
+                        } else if let Some(&navdata) =
+                            self.find_ced_and_status(prnd_svn, gst_navmessage)
+                        {
+                            if self
+                                .cop_policy(tag.adkd())
+                                .allows(tag.cop(), navdata.max_age())
+                            {
+                                let valid = Self::validate_tag(
+                                    key,
+                                    tag.tag(),
+                                    tag.adkd(),
+                                    gst_mack,
+                                    prnd,
+                                    prna,
+                                    j,
+                                    nma_status,
+                                    &navdata,
+                                    self.ced_and_status_iter_authbits_mut(),
+                                );
+                                self.record_tag(
+                                    &mut results,
+                                    prna,
+                                    prnd_svn,
+                                    tag.adkd(),
+                                    gst_mack,
+                                    j,
+                                    valid,
+                                );
+                                if valid {
+                                    self.record_cop(
+                                        prnd_svn,
+                                        tag.adkd(),
+                                        tag.cop(),
+                                        prna,
+                                        gst_mack,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Err(_) => {
+                        log::error!("invalid {} for {}", tag.prnd(), tag.adkd());
+                    }
+                },
+                Adkd::InavTiming => match Svn::try_from(prnd) {
+                    Ok(prnd_svn) => {
+                        if tag.cop() == 0 {
+                            if Self::validate_dummy_tag(
+                                key,
+                                tag.tag(),
+                                tag.adkd(),
+                                gst_mack,
+                                prnd,
+                                prna,
+                                j,
+                                nma_status,
+                                TIMING_PARAMETERS_BITS,
+                            ) {
+                                self.record_cop(prnd_svn, tag.adkd(), 0, prna, gst_mack);
+                            }
+                        } else if let Some(&navdata) =
+                            self.find_timing_parameters(prnd_svn, gst_navmessage)
+                        {
+                            if self
+                                .cop_policy(tag.adkd())
+                                .allows(tag.cop(), navdata.max_age())
+                            {
+                                let valid = Self::validate_tag(
+                                    key,
+                                    tag.tag(),
+                                    tag.adkd(),
+                                    gst_mack,
+                                    prnd,
+                                    prna,
+                                    j,
+                                    nma_status,
+                                    &navdata,
+                                    self.timing_parameters_iter_authbits_mut(),
+                                );
+                                self.record_tag(
+                                    &mut results,
+                                    prna,
+                                    prnd_svn,
+                                    tag.adkd(),
+                                    gst_mack,
+                                    j,
+                                    valid,
+                                );
+                                if valid {
+                                    self.record_cop(
+                                        prnd_svn,
+                                        tag.adkd(),
+                                        tag.cop(),
+                                        prna,
+                                        gst_mack,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Err(_) => {
+                        log::error!("invalid {} for {}", tag.prnd(), tag.adkd());
+                    }
+                },
+                Adkd::SlowMac => {
+                    // Slow MAC is not processed here, because the key doesn't
+                    // have the appropriate extra delay. If the slowmac feature
+                    // is disabled, these tags are simply skipped.
+                }
+                Adkd::Reserved => {
+                    // These are counted in reserved_adkd_stats
+                    log::debug!("reserved ADKD in tag {:?}", tag);
+                }
+            }
+        }
+        results
+    }
+
+    // Validates again the tags of a MACK message that were validated by
+    // process_mack_with_results, given in the bitmask `tags`, without modifying
+    // the storage. Returns the bitmasks of the tags that have been audited
+    // (those whose navigation data is still stored) and of the audited tags
+    // that do not validate now.
+    pub(crate) fn audit_mack(
+        &mut self,
+        mack: &Mack<Validated>,
+        key: &Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+        tags: u16,
+    ) -> (u16, u16) {
+        let gst_navmessage = gst_mack.wrapping_add_seconds(-30);
+        let mut audited = 0;
+        let mut failed = 0;
+        for j in (0..mack.num_valid_tags()).filter(|&j| tags & (1 << j) != 0) {
+            let (tag, prnd, navdata) = if j == 0 {
+                let navdata = self
+                    .find_ced_and_status(prna, gst_navmessage)
+                    .map(|n| n.message_bits());
+                (mack.tag0(), u8::from(prna), navdata)
+            } else {
+                let tag = mack.tag_and_info(j);
+                let Prnd::GalileoSvid(prnd) = tag.prnd() else {
+                    continue;
+                };
+                let Ok(svn) = Svn::try_from(prnd) else {
+                    continue;
+                };
+                let navdata = match tag.adkd() {
+                    Adkd::InavCed => self
+                        .find_ced_and_status(svn, gst_navmessage)
+                        .map(|n| n.message_bits()),
+                    Adkd::InavTiming => self
+                        .find_timing_parameters(svn, gst_navmessage)
+                        .map(|n| n.message_bits()),
+                    _ => None,
+                };
+                (tag.tag(), prnd, navdata)
+            };
+            let Some(navdata) = navdata else {
+                continue;
+            };
+            audited |= 1 << j;
+            let valid = match j {
+                0 => key.validate_tag0(tag, gst_mack, prna, nma_status, navdata),
+                _ => key.validate_tag(
+                    tag,
+                    gst_mack,
+                    prnd,
+                    prna,
+                    (j + 1).try_into().unwrap(),
+                    nma_status,
+                    navdata,
+                ),
+            };
+            if !valid {
+                failed |= 1 << j;
+            }
+        }
+        (audited, failed)
+    }
+
+    /// Process the Slow MAC (ADKD=12) tags in a MACK message.
+    ///
+    /// This processes a MACK message, authenticating stored navigation data as
+    /// possible using the Slow MAC tags. The `key` should be the TESLA key with
+    /// which the Slow MAC tags in the MACK message `mack` have been generated
+    /// (recall that this key is transmitted 11 subframes after the MACK
+    /// message). The `prna` is the authenticating PRN, which is the SVN that
+    /// has transmitted the MACK message. The `gst_mack` parameter should be the
+    /// GST corresponding to the start of the subframe when the MACK message was
+    /// transmitted.  The `nma_status` parameter should be the value of the NMA
+    /// status field in the current NMA header. The NMA header does not need to
+    /// be validated using the DSM-KROOT, since a forged or incorrect NMA header
+    /// will simply make tag validation fail.
+    ///
+    /// This function ignores all the other tags in the MACK message, since they
+    /// do not correspond to `key`.
+    ///
+    /// This function is only available if the `slowmac` feature is enabled.
+    #[cfg(feature = "slowmac")]
+    pub fn process_mack_slowmac(
+        &mut self,
+        mack: Mack<Validated>,
+        key: &Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+    ) {
+        self.process_mack_slowmac_with_results(mack, key, prna, gst_mack, nma_status);
+    }
+
+    // Same as process_mack_slowmac, but returns the results of the
+    // validation of the tags.
+    #[cfg(feature = "slowmac")]
+    pub(crate) fn process_mack_slowmac_with_results(
+        &mut self,
+        mack: Mack<Validated>,
+        key: &Key<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        nma_status: NmaStatus,
+    ) -> TagResults {
+        let mut results = TagResults::default();
+        let gst_navmessage = gst_mack.wrapping_add_seconds(-30);
+        for j in 1..mack.num_valid_tags() {
+            let tag = mack.tag_and_info(j);
+            if tag.adkd() != Adkd::SlowMac
+                || tag.prnd() == Prnd::GalileoConstellation
+                || !self.adkd_filter.contains(Adkd::SlowMac)
+            {
+                // Constellation-related tags have already been stored by
+                // process_mack. Slow MAC tags are ignored if they are not in
+                // the ADKD filter.
+                continue;
+            }
+            let prnd = match u8::try_from(tag.prnd()) {
+                Ok(p) => p,
+                Err(_) => {
+                    log::error!("could not obtain PRND from tag {:?}", tag);
+                    continue;
+                }
+            };
+            let prnd_svn = match Svn::try_from(prnd) {
+                Ok(s) => s,
+                Err(_) => {
+                    log::error!("invalid PRND {:?} for Slow MAC tag {:?}", tag.prnd(), tag);
+                    continue;
+                }
+            };
+            if tag.cop() == 0 {
+                if Self::validate_dummy_tag(
+                    key,
+                    tag.tag(),
+                    tag.adkd(),
+                    gst_mack,
+                    prnd,
+                    prna,
+                    j,
+                    nma_status,
+                    CED_AND_STATUS_BITS,
+                ) {
+                    self.record_cop(prnd_svn, tag.adkd(), 0, prna, gst_mack);
+                }
+            } else if let Some(&navdata) = self.find_ced_and_status(prnd_svn, gst_navmessage) {
+                if self
+                    .cop_policy(Adkd::SlowMac)
+                    .allows(tag.cop(), navdata.max_age())
+                {
+                    let valid = Self::validate_tag(
+                        key,
+                        tag.tag(),
+                        tag.adkd(),
+                        gst_mack,
+                        prnd,
+                        prna,
+                        j,
+                        nma_status,
+                        &navdata,
+                        self.ced_and_status_iter_authbits_mut(),
+                    );
+                    self.record_tag(&mut results, prna, prnd_svn, tag.adkd(), gst_mack, j, valid);
+                    if valid {
+                        self.record_cop(prnd_svn, tag.adkd(), tag.cop(), prna, gst_mack);
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_tag(
+        &mut self,
+        results: &mut TagResults,
+        prna: Svn,
+        prnd: Svn,
+        adkd: Adkd,
+        gst_mack: Gst,
+        tag_idx: usize,
+        valid: bool,
+    ) {
+        results.record(tag_idx, valid);
+        if !valid {
+            let failed = &mut self.failed_tags[prnd.index()];
+            *failed = failed.saturating_add(1);
+            #[cfg(feature = "nav-diagnostics")]
+            self.anomalies.record_failed_tag(prnd, gst_mack);
+        }
+        self.tag_verifications.store(TagVerification {
+            prna,
+            prnd,
+            adkd,
+            gst: gst_mack,
+            tag_index: tag_idx.try_into().unwrap(),
+            valid,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn validate_tag<'a>(
+        key: &Key<Validated>,
+        tag: &BitSlice,
+        adkd: Adkd,
+        gst_tag: Gst,
+        prnd: u8,
+        prna: Svn,
+        tag_idx: usize,
+        nma_status: NmaStatus,
+        navdata: &dyn AuthBits,
+        to_add_authbits: impl Iterator<Item = &'a mut dyn AuthBits>,
+    ) -> bool {
+        let ctr = (tag_idx + 1).try_into().unwrap();
+        let ret = match tag_idx {
+            0 => key.validate_tag0(tag, gst_tag, prna, nma_status, navdata.message_bits()),
+            _ => key.validate_tag(
+                tag,
+                gst_tag,
+                prnd,
+                prna,
+                ctr,
+                nma_status,
+                navdata.message_bits(),
+            ),
+        };
+        if ret {
+            log::info!(
+                "E{:02} {:?} at {:?} tag{} correct (auth by {})",
+                prnd,
+                adkd,
+                gst_tag,
+                tag_idx,
+                prna
+            );
+            // The navigation data was transmitted in the subframe before the
+            // tag, and the key is transmitted in the subframe after the tag
+            // (or 11 subframes after the tag for Slow MAC).
+            let age: i32 = navdata.min_age().into();
+            let key_delay = if adkd == Adkd::SlowMac { 11 } else { 1 };
+            let latency = AuthLatency {
+                data_gst: gst_tag.wrapping_add_subframes(-1 - age),
+                authentication_gst: gst_tag.wrapping_add_subframes(key_delay),
+            };
+            // This nma_status is known good because it has been used in the tag
+            // validation, so we can act on it to decide if we can add
+            // authentication bits.
+            if matches!(nma_status, NmaStatus::Operational | NmaStatus::Test) {
+                for to_add in to_add_authbits {
+                    if navdata.svn() == to_add.svn()
+                        && navdata.message_bits() == to_add.message_bits()
+                    {
+                        to_add.add_authbits(tag, latency, adkd, prna, gst_tag);
+                    }
+                }
+            }
+        } else {
+            log::error!(
+                "E{:02} {:?} at {:?} tag{} wrong (auth by {})",
+                prnd,
+                adkd,
+                gst_tag,
+                tag_idx,
+                prna
+            );
+        }
+        ret
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn validate_dummy_tag(
+        key: &Key<Validated>,
+        tag: &BitSlice,
+        adkd: Adkd,
+        gst_tag: Gst,
+        prnd: u8,
+        prna: Svn,
+        tag_idx: usize,
+        nma_status: NmaStatus,
+        navdata_len_bits: usize,
+    ) -> bool {
+        let ctr = (tag_idx + 1).try_into().unwrap();
+        let ret = match tag_idx {
+            0 => key.validate_tag0_dummy(tag, gst_tag, prna, nma_status, navdata_len_bits),
+            _ => {
+                key.validate_tag_dummy(tag, gst_tag, prnd, prna, ctr, nma_status, navdata_len_bits)
+            }
+        };
+        if ret {
+            log::info!(
+                "E{:02} {:?} at {:?} dummy tag{} correct (auth by {})",
+                prnd,
+                adkd,
+                gst_tag,
+                tag_idx,
+                prna
+            );
+        } else {
+            log::error!(
+                "E{:02} {:?} at {:?} dummy tag{} wrong (auth by {})",
+                prnd,
+                adkd,
+                gst_tag,
+                tag_idx,
+                prna
+            );
+        }
+        ret
+    }
+
+    // Gives the navigation data stored for each subframe and satellite. This
+    // is used to compare the state of two instances.
+    pub(crate) fn stored(&self) -> impl Iterator<Item = StoredNavData<'_>> + '_ {
+        let ced = self.stored_entries(&self.ced_and_status, NavDataKind::CedAndStatus);
+        let timing = self.stored_entries(&self.timing_parameters, NavDataKind::TimingParameters);
+        ced.chain(timing)
+    }
+
+    fn stored_entries<'a, T: NavDataEntry>(
+        &'a self,
+        entries: &'a [T],
+        kind: NavDataKind,
+    ) -> impl Iterator<Item = StoredNavData<'a>> + 'a {
+        entries
+            .chunks(self.num_sats())
+            .zip(self.gsts.iter())
+            .filter_map(|(slot, gst)| gst.map(|gst| (slot, gst)))
+            .flat_map(move |(slot, gst)| {
+                slot.iter()
+                    // An age of u8::MAX indicates that no word has been received
+                    .filter(|item| item.min_age() != u8::MAX)
+                    .filter_map(move |item| {
+                        Some(StoredNavData {
+                            kind,
+                            gst,
+                            svn: item.svn()?,
+                            retained: item.is_retained(),
+                            bits: item.message_bits().as_bitslice(),
+                            authbits: item.authbits(),
+                        })
+                    })
+            })
+    }
+
+    /// Resets all the authentication bits to zero.
+    ///
+    /// This function can be called when the NMA status is set to don't use in
+    /// order to discard all the previously generated authentication bits.
+    pub fn reset_authbits(&mut self) {
+        for ced in self.ced_and_status.iter_mut() {
+            ced.reset_authbits();
+        }
+        for timing in self.timing_parameters.iter_mut() {
+            timing.reset_authbits();
+        }
+    }
+
+    /// Drops the navigation data stored for subframes older than a GST.
+    ///
+    /// This clears the navigation data that was stored at the subframes that
+    /// started before `gst`, including its authentication status. The storage
+    /// has a fixed size, so this does not free any memory, but it lets the
+    /// host bound the age of the stored data independently of the rate at
+    /// which new data is received. Data that is still needed to validate
+    /// tags should not be dropped, so `gst` should be at least one subframe
+    /// older than the current subframe.
+    ///
+    /// Returns the number of subframes whose data has been dropped.
+    pub fn prune_older_than(&mut self, gst: Gst) -> usize {
+        let mut pruned = 0;
+        for slot in 0..S::NavMessageDepth::USIZE {
+            if self.gsts[slot].is_some_and(|g| g.is_before(gst)) {
+                log::debug!(
+                    "pruning navigation data of subframe {:?}",
+                    self.gsts[slot].unwrap()
+                );
+                self.gsts[slot] = None;
+                let range = self.slot_range(slot);
+                self.ced_and_status[range.clone()]
+                    .iter_mut()
+                    .for_each(CedAndStatus::reset);
+                self.timing_parameters[range]
+                    .iter_mut()
+                    .for_each(TimingParameters::reset);
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+
+    /// Clears the satellites that have not been received since a GST.
+    ///
+    /// The navigation data of each satellite whose most recent INAV word was
+    /// received in a subframe that started before `gst` is cleared from all
+    /// the subframes in the storage, together with the COP values of its
+    /// validated tags and its cross-authentication expectations. This frees storage entries for other satellites and
+    /// prevents the host from obtaining data of satellites that are no longer
+    /// tracked.
+    ///
+    /// Returns the number of satellites that have been cleared.
+    pub fn clear_satellites_not_seen_since(&mut self, gst: Gst) -> usize {
+        let mut last_seen: [Option<Gst>; NUM_SVNS] = [None; NUM_SVNS];
+        for slot in 0..S::NavMessageDepth::USIZE {
+            let Some(slot_gst) = self.gsts[slot] else {
+                continue;
+            };
+            let range = self.slot_range(slot);
+            let ced = self.ced_and_status[range.clone()]
+                .iter()
+                .map(|item| (item.svn, item.min_age()));
+            let timing = self.timing_parameters[range]
+                .iter()
+                .map(|item| (item.svn, item.min_age()));
+            for (svn, age) in ced.chain(timing) {
+                // An age of u8::MAX indicates that no word has been received
+                if let (Some(svn), true) = (svn, age != u8::MAX) {
+                    let seen = slot_gst.wrapping_add_subframes(-i32::from(age));
+                    let last = &mut last_seen[svn.index()];
+                    if last.map_or(true, |l| seen.is_after(l)) {
+                        *last = Some(seen);
+                    }
+                }
+            }
+        }
+        let is_stale = |svn: Option<Svn>| {
+            svn.is_some_and(|svn| last_seen[svn.index()].map_or(true, |seen| seen.is_before(gst)))
+        };
+        let mut cleared = 0;
+        for svn in Svn::iter() {
+            let stored = self.ced_and_status.iter().any(|item| item.svn == Some(svn))
+                || self
+                    .timing_parameters
+                    .iter()
+                    .any(|item| item.svn == Some(svn));
+            if stored && is_stale(Some(svn)) {
+                log::debug!("clearing navigation data of {}", svn);
+                self.cops[svn.index()] = [None; 3];
+                #[cfg(feature = "cross-auth")]
+                {
+                    self.cross_auth[svn.index()] = [None; 3];
+                }
+                cleared += 1;
+            }
+        }
+        for item in self.ced_and_status.iter_mut() {
+            if is_stale(item.svn) {
+                item.reset();
+            }
+        }
+        for item in self.timing_parameters.iter_mut() {
+            if is_stale(item.svn) {
+                item.reset();
+            }
+        }
+        cleared
+    }
+}
+
+impl<S: Storage> Default for CollectNavMessage<S> {
+    fn default() -> CollectNavMessage<S> {
+        CollectNavMessage::new()
+    }
+}
+
+/// Padding convention for missing navigation data words.
+///
+/// This indicates the value used to fill the bits of the words that have not
+/// been received when validating a tag with [`validate_tag_partial`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Padding {
+    /// Missing bits are filled with zeros.
+    Zeros,
+    /// Missing bits are filled with ones.
+    Ones,
+}
+
+/// Result of the validation of a tag with partial navigation data.
+///
+/// This is returned by [`validate_tag_partial`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PartialTagValidation {
+    required_words: &'static [AdkdWordLayout],
+    missing: u8,
+    valid: bool,
+    padding: Option<Padding>,
+}
+
+impl PartialTagValidation {
+    /// Returns `true` if the tag has been validated.
+    ///
+    /// If some words were missing, this means that the tag was validated by
+    /// filling the missing words with the padding returned by
+    /// [`PartialTagValidation::padding`].
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Returns the padding with which the tag was validated.
+    ///
+    /// If no words were missing or the tag could not be validated, this
+    /// returns `None`.
+    pub fn padding(&self) -> Option<Padding> {
+        self.padding
+    }
+
+    /// Returns an iterator over the INAV word types that make up the
+    /// navigation data authenticated by the tag.
+    pub fn required_words(&self) -> impl Iterator<Item = u8> + '_ {
+        self.required_words
+            .iter()
+            .map(|layout| layout.word_type().into())
+    }
+
+    /// Returns an iterator over the INAV word types that were missing.
+    pub fn missing_words(&self) -> impl Iterator<Item = u8> + '_ {
+        self.required_words
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| self.missing & (1 << j) != 0)
+            .map(|(_, layout)| layout.word_type().into())
+    }
+}
+
+/// Tries to validate a tag using partial navigation data.
+///
+/// This is intended for research into the robustness of OSNMA to the loss of
+/// INAV words. The navigation data `navdata` is given using the same layout as
+/// in [`NavMessageData::data`], as either [`CedBits`] or [`TimingBits`]
+/// according to the ADKD `adkd`, and the INAV word types that have been received
+/// are listed in `received_words`. The bits of the words that have not been
+/// received are ignored. If all the words for the ADKD `adkd` have been
+/// received, the tag is validated normally. Otherwise, the tag validation is
+/// attempted by filling the bits of the missing words with each of the padding
+/// conventions in [`Padding`]. A successful validation with some padding means
+/// that the missing words contained the padding value, so they were not
+/// required to authenticate the data.
+///
+/// The `key`, `tag`, `gst_tag`, `prnd`, `prna`, and `nma_status` parameters
+/// have the same meaning as in [`Key::validate_tag`]. The `tag_idx` parameter
+/// gives the index of the tag in the MACK message (0 for the tag0).
+///
+/// The returned [`PartialTagValidation`] indicates whether the tag was
+/// validated, with which padding, and which words were missing. For the
+/// reserved ADKD, or if `navdata` does not correspond to `adkd`, the tag is not
+/// validated and no words are required.
+#[allow(clippy::too_many_arguments)]
+pub fn validate_tag_partial<'a>(
+    key: &Key<Validated>,
+    tag: &BitSlice,
+    adkd: Adkd,
+    gst_tag: Gst,
+    prnd: u8,
+    prna: Svn,
+    tag_idx: usize,
+    nma_status: NmaStatus,
+    navdata: impl Into<NavDataBits<'a>>,
+    received_words: &[u8],
+) -> PartialTagValidation {
+    let navdata = navdata.into();
+    let layout: &'static [AdkdWordLayout] = match (adkd, navdata) {
+        (Adkd::InavCed | Adkd::SlowMac, NavDataBits::CedAndStatus(_)) => &CED_AND_STATUS_LAYOUT,
+        (Adkd::InavTiming, NavDataBits::TimingParameters(_)) => &TIMING_PARAMETERS_LAYOUT,
+        _ => {
+            return PartialTagValidation {
+                required_words: &[],
+                missing: 0,
+                valid: false,
+                padding: None,
+            }
+        }
+    };
+    let mut missing = 0;
+    for (j, word) in layout.iter().enumerate() {
+        if !received_words.contains(&word.word_type().into()) {
+            missing |= 1 << j;
+        }
+    }
+    let mut buffer = [0u8; CED_AND_STATUS_BYTES];
+    let data = &mut BitSlice::from_slice_mut(&mut buffer)[..navdata.as_bitslice().len()];
+    data.copy_from_bitslice(navdata.as_bitslice());
+    let ctr = (tag_idx + 1).try_into().unwrap();
+    let validate = |data: &BitSlice| {
+        // data has the same length as navdata
+        let data: NavDataBits = match navdata {
+            NavDataBits::CedAndStatus(_) => CedBits::new(data).unwrap().into(),
+            NavDataBits::TimingParameters(_) => TimingBits::new(data).unwrap().into(),
+        };
+        match tag_idx {
+            0 => key.validate_tag0(tag, gst_tag, prna, nma_status, data),
+            _ => key.validate_tag(tag, gst_tag, prnd, prna, ctr, nma_status, data),
+        }
+    };
+    if missing == 0 {
+        return PartialTagValidation {
+            required_words: layout,
+            missing: 0,
+            valid: validate(data),
+            padding: None,
+        };
+    }
+    for padding in [Padding::Zeros, Padding::Ones] {
+        for (j, word) in layout.iter().enumerate() {
+            if missing & (1 << j) != 0 {
+                data[word.data_bits()].fill(padding == Padding::Ones);
+            }
+        }
+        if validate(data) {
+            log::info!(
+                "E{:02} {:?} at {:?} tag{} correct with {:?} padding (auth by {})",
+                prnd,
+                adkd,
+                gst_tag,
+                tag_idx,
+                padding,
+                prna
+            );
+            return PartialTagValidation {
+                required_words: layout,
+                missing,
+                valid: true,
+                padding: Some(padding),
+            };
+        }
+    }
+    PartialTagValidation {
+        required_words: layout,
+        missing,
+        valid: false,
+        padding: None,
+    }
+}
+
+// Navigation data stored for a satellite in a subframe, as given by
+// CollectNavMessage::stored.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct StoredNavData<'a> {
+    pub(crate) kind: NavDataKind,
+    pub(crate) gst: Gst,
+    pub(crate) svn: Svn,
+    pub(crate) retained: bool,
+    pub(crate) bits: &'a BitSlice,
+    pub(crate) authbits: u16,
+}
+
+// Number of tag sources remembered by each navigation data entry to count
+// the authentication bits of tags from distinct sources. The tags from the
+// same source are processed together, except for the Slow MAC tags, so a few
+// sources are enough.
+#[cfg(feature = "unique-authbits")]
+const TAG_SOURCES_DEPTH: usize = 4;
+
+// Authentication bits and latency counting only the first tag from each
+// source (PRNA and GST of the tag).
+#[cfg(feature = "unique-authbits")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct UniqueAuthBits {
+    authbits: u16,
+    latency: Option<AuthLatency>,
+    // Most recent sources of the tags that have been added.
+    tag_sources: [Option<(Svn, Gst)>; TAG_SOURCES_DEPTH],
+}
+
+#[cfg(feature = "unique-authbits")]
+impl UniqueAuthBits {
+    const NEW: UniqueAuthBits = UniqueAuthBits {
+        authbits: 0,
+        latency: None,
+        tag_sources: [None; TAG_SOURCES_DEPTH],
+    };
+
+    fn add(&mut self, len: u16, latency: AuthLatency, prna: Svn, gst_tag: Gst) {
+        let source = Some((prna, gst_tag));
+        if self.tag_sources.contains(&source) {
+            return;
+        }
+        self.tag_sources.rotate_right(1);
+        self.tag_sources[0] = source;
+        self.authbits = self.authbits.saturating_add(len);
+        if self.authbits >= MIN_AUTHBITS && self.latency.is_none() {
+            self.latency = Some(latency);
+        }
+    }
+
+    fn authbits(&self) -> u16 {
+        self.authbits
+    }
+
+    fn latency(&self) -> Option<AuthLatency> {
+        self.latency
+    }
+}
+
+// Without the unique-authbits feature the tag sources are not tracked, so that
+// the navigation data entries are smaller. The unique authentication bits are
+// never counted towards MIN_AUTHBITS in this case.
+#[cfg(not(feature = "unique-authbits"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+struct UniqueAuthBits;
+
+#[cfg(not(feature = "unique-authbits"))]
+impl UniqueAuthBits {
+    const NEW: UniqueAuthBits = UniqueAuthBits;
+
+    fn add(&mut self, _len: u16, _latency: AuthLatency, _prna: Svn, _gst_tag: Gst) {}
+
+    fn authbits(&self) -> u16 {
+        0
+    }
+
+    fn latency(&self) -> Option<AuthLatency> {
+        None
+    }
+}
+
+const CED_AND_STATUS_WORDS: usize = 5;
+const CED_AND_STATUS_BITS: usize = CedBits::LEN;
+const CED_AND_STATUS_BYTES: usize = (CED_AND_STATUS_BITS + 7) / 8;
+
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+// This is pub only because it appears in the definition of CollectNavMessage
+pub struct CedAndStatus {
+    data: [u8; CED_AND_STATUS_BYTES],
+    age: [u8; CED_AND_STATUS_WORDS],
+    // Source of the most recent copy of each word.
+    sources: [Option<WordSource>; CED_AND_STATUS_WORDS],
+    svn: Option<Svn>,
+    authbits: u16,
+    latency: Option<AuthLatency>,
+    unique: UniqueAuthBits,
+    // Authentication bits for each of the ADKDs InavCed, InavTiming, and
+    // SlowMac.
+    adkd_authbits: [u16; 3],
+    // Authenticated copy retained by MismatchPolicy::Quarantine
+    retained: bool,
+    // Most recent word type 16, which is not authenticated.
+    #[cfg(feature = "reduced-ced")]
+    reduced_ced: Option<ReducedCed>,
+}
+
+const TIMING_PARAMETERS_WORDS: usize = 2;
+const TIMING_PARAMETERS_BITS: usize = TimingBits::LEN;
+const TIMING_PARAMETERS_BYTES: usize = (TIMING_PARAMETERS_BITS + 7) / 8;
+
+// Size of the largest navigation data that can be stored in a
+// NavMessageDataOwned.
+const MAX_NAVDATA_BYTES: usize = CED_AND_STATUS_BYTES;
+const _: () = assert!(MAX_NAVDATA_BYTES >= TIMING_PARAMETERS_BYTES);
+
+#[doc(hidden)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+// This is pub only because it appears in the definition of CollectNavMessage
+pub struct TimingParameters {
+    data: [u8; TIMING_PARAMETERS_BYTES],
+    age: [u8; TIMING_PARAMETERS_WORDS],
+    // Source of the most recent copy of each word.
+    sources: [Option<WordSource>; TIMING_PARAMETERS_WORDS],
+    svn: Option<Svn>,
+    authbits: u16,
+    latency: Option<AuthLatency>,
+    unique: UniqueAuthBits,
+    // Authentication bits for each of the ADKDs InavCed, InavTiming, and
+    // SlowMac.
+    adkd_authbits: [u16; 3],
+    // Authenticated copy retained by MismatchPolicy::Quarantine
+    retained: bool,
+}
+
+trait AuthBits {
+    fn svn(&self) -> Option<Svn>;
+    fn message_bits(&self) -> NavDataBits<'_>;
+    fn min_age(&self) -> u8;
+    fn authbits(&self) -> u16;
+    fn latency(&self) -> Option<AuthLatency>;
+    #[cfg(feature = "provenance")]
+    fn provenance(&self) -> NavDataProvenance;
+    fn unique_authbits(&self) -> u16;
+    fn unique_latency(&self) -> Option<AuthLatency>;
+    fn adkd_authbits(&self) -> [u16; 3];
+    fn add_authbits(
+        &mut self,
+        tag: &BitSlice,
+        latency: AuthLatency,
+        adkd: Adkd,
+        prna: Svn,
+        gst_tag: Gst,
+    );
+    fn reset_authbits(&mut self);
+
+    fn authbits_count(&self) -> AuthBitsCount {
+        AuthBitsCount {
+            all: self.authbits(),
+            unique: self.unique_authbits(),
+            adkd: self.adkd_authbits(),
+        }
+    }
+
+    fn counted_latency(&self, unique: bool) -> Option<AuthLatency> {
+        if unique {
+            self.unique_latency()
+        } else {
+            self.latency()
+        }
+    }
+}
+
+// Index used in the arrays that hold a value for each of the ADKDs InavCed,
+// InavTiming, and SlowMac.
+fn adkd_index(adkd: Adkd) -> Option<usize> {
+    match adkd {
+        Adkd::InavCed => Some(0),
+        Adkd::InavTiming => Some(1),
+        Adkd::SlowMac => Some(2),
+        Adkd::Reserved => None,
+    }
+}
+
+// Authentication bits of a navigation data entry, counting all the tags, only
+// the tags from distinct sources, and the tags of each of the ADKDs InavCed,
+// InavTiming, and SlowMac.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct AuthBitsCount {
+    all: u16,
+    unique: u16,
+    adkd: [u16; 3],
+}
+
+impl AuthBitsCount {
+    // Gives the bits that are counted towards MIN_AUTHBITS.
+    fn counted(&self, unique: bool) -> u16 {
+        if unique {
+            self.unique
+        } else {
+            self.all
+        }
+    }
+}
+
+macro_rules! impl_common {
+    ($s:ident, $data_size:expr, $num_words:expr, $num_bits:expr, $layout:expr
+     $(, $(#[$meta:meta])* $field:ident: $init:expr)*) => {
+        impl $s {
+            fn new() -> $s {
+                $s {
+                    data: [0; $data_size],
+                    age: [u8::MAX; $num_words],
+                    sources: [None; $num_words],
+                    authbits: 0,
+                    svn: None,
+                    latency: None,
+                    unique: UniqueAuthBits::NEW,
+                    adkd_authbits: [0; 3],
+                    retained: false,
+                    $($(#[$meta])* $field: $init,)*
+                }
+            }
+
+            fn reset(&mut self) {
+                self.age.fill(u8::MAX);
+                self.sources.fill(None);
+                self.reset_authbits();
+                self.svn = None;
+                self.retained = false;
+                $($(#[$meta])* {
+                    self.$field = $init;
+                })*
+            }
+
+            fn bits(&self) -> &BitSlice {
+                BitSlice::from_slice(&self.data)
+            }
+
+            fn bits_as_mut(&mut self) -> &mut BitSlice {
+                BitSlice::from_slice_mut(&mut self.data)
+            }
+
+            fn max_age(&self) -> u8 {
+                self.age.iter().copied().max().unwrap()
+            }
+
+            // Returns false if the word has not been copied because it differs
+            // from authenticated data and the policy is not TakeNew.
+            fn copy_word(
+                &mut self,
+                dest_range: core::ops::Range<usize>,
+                source: &BitSlice,
+                idx: usize,
+                word_source: WordSource,
+                policy: MismatchPolicy,
+            ) -> bool {
+                let authenticated = self.is_authenticated();
+                let stored_source = self.sources[idx].filter(|_| self.age[idx] == 0);
+                let stored_age = self.age[idx];
+                let svn = self.svn;
+                let dest = &mut self.bits_as_mut()[dest_range];
+                if dest != source {
+                    if let Some(stored_source) = stored_source {
+                        if stored_source.band != word_source.band
+                            && !word_source.is_preferred_over(stored_source)
+                        {
+                            // A different copy of this word has been received
+                            // in the other band during this subframe. One of
+                            // them is probably corrupted, so the new copy is
+                            // discarded.
+                            log::debug!(
+                                concat!(
+                                    stringify!($s),
+                                    " for {} word differs from copy received in {}; \
+                                     discarding copy received in {}"
+                                ),
+                                self.svn.unwrap(),
+                                stored_source.band,
+                                word_source.band
+                            );
+                            return true;
+                        }
+                    }
+                    if authenticated && policy != MismatchPolicy::TakeNew {
+                        if policy != MismatchPolicy::KeepOld
+                            || stored_age < KEEP_OLD_MAX_AGE
+                        {
+                            return false;
+                        }
+                        log::info!(
+                            concat!(
+                                stringify!($s),
+                                " for {} word not received for {} subframes; \
+                                 replacing authenticated data"
+                            ),
+                            svn.unwrap(),
+                            stored_age
+                        );
+                    }
+                    dest.copy_from_bitslice(source);
+                    self.reset_authbits();
+                    self.sources[idx] = Some(word_source);
+                } else {
+                    // Identical copy. Keep track of whether any of the copies
+                    // has been CRC checked.
+                    self.sources[idx] = Some(match stored_source {
+                        Some(s) => WordSource {
+                            band: s.band,
+                            #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+                            gst: s.gst,
+                            crc_checked: s.crc_checked || word_source.crc_checked,
+                        },
+                        None => word_source,
+                    });
+                }
+                self.age[idx] = 0;
+                true
+            }
+
+            fn is_authenticated(&self) -> bool {
+                self.authbits >= MIN_AUTHBITS
+            }
+
+            fn log_word(&self, word_type: InavWordType) {
+                log::trace!(
+                    concat!(stringify!($s), " storing INAV {} for {}"),
+                    word_type,
+                    self.svn.unwrap()
+                );
+            }
+
+            fn log_age(&self) {
+                log::trace!(
+                    concat!(stringify!($s), " for {} age: {:?}"),
+                    self.svn.unwrap(),
+                    &self.age
+                );
+            }
+        }
+
+        impl AuthBits for $s {
+            fn svn(&self) -> Option<Svn> {
+                self.svn
+            }
+
+            fn message_bits(&self) -> NavDataBits<'_> {
+                BitWindow::<$num_bits>::new(&self.bits()[..$num_bits])
+                    .unwrap()
+                    .into()
+            }
+
+            fn min_age(&self) -> u8 {
+                self.age.iter().copied().min().unwrap()
+            }
+
+            fn authbits(&self) -> u16 {
+                self.authbits
+            }
+
+            fn latency(&self) -> Option<AuthLatency> {
+                self.latency
+            }
+
+            #[cfg(feature = "provenance")]
+            fn provenance(&self) -> NavDataProvenance {
+                NavDataProvenance::new(&$layout, &self.sources)
+            }
+
+            fn unique_authbits(&self) -> u16 {
+                self.unique.authbits()
+            }
+
+            fn unique_latency(&self) -> Option<AuthLatency> {
+                self.unique.latency()
+            }
+
+            fn adkd_authbits(&self) -> [u16; 3] {
+                self.adkd_authbits
+            }
+
+            fn add_authbits(
+                &mut self,
+                tag: &BitSlice,
+                latency: AuthLatency,
+                adkd: Adkd,
+                prna: Svn,
+                gst_tag: Gst,
+            ) {
+                let len = tag.len().try_into().unwrap();
+                self.authbits = self.authbits.saturating_add(len);
+                if let Some(idx) = adkd_index(adkd) {
+                    self.adkd_authbits[idx] = self.adkd_authbits[idx].saturating_add(len);
+                }
+                if self.authbits >= MIN_AUTHBITS && self.latency.is_none() {
+                    self.latency = Some(latency);
+                }
+                self.unique.add(len, latency, prna, gst_tag);
+            }
+
+            fn reset_authbits(&mut self) {
+                self.authbits = 0;
+                self.latency = None;
+                self.unique = UniqueAuthBits::NEW;
+                self.adkd_authbits = [0; 3];
+            }
+        }
+
+        impl Default for $s {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl NavDataEntry for $s {
+            const NAME: &'static str = stringify!($s);
+
+            fn max_age(&self) -> u8 {
+                $s::max_age(self)
+            }
+
+            fn is_authenticated(&self) -> bool {
+                $s::is_authenticated(self)
+            }
+
+            fn is_retained(&self) -> bool {
+                self.retained
+            }
+
+            fn set_retained(&mut self) {
+                self.retained = true;
+            }
+
+            fn reset(&mut self) {
+                $s::reset(self)
+            }
+
+            fn feed_word(
+                &mut self,
+                word: &InavWord,
+                svn: Svn,
+                source: WordSource,
+                policy: MismatchPolicy,
+            ) -> bool {
+                $s::feed(self, word, svn, source, policy)
+            }
+        }
+    };
+}
+
+// Common interface of CedAndStatus and TimingParameters used to select where
+// an INAV word is stored.
+trait NavDataEntry: AuthBits + Copy {
+    const NAME: &'static str;
+    fn max_age(&self) -> u8;
+    fn is_authenticated(&self) -> bool;
+    fn is_retained(&self) -> bool;
+    fn set_retained(&mut self);
+    fn reset(&mut self);
+    // Returns false if the word has not been stored because of the mismatch
+    // policy.
+    fn feed_word(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        source: WordSource,
+        policy: MismatchPolicy,
+    ) -> bool;
+}
+
+// Band and GST at the start of the page in which an INAV word was received,
+// and whether its CRC was checked. The GST is only kept with the provenance
+// or reduced-ced features, since it is only used for the provenance and the
+// reduced CED.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct WordSource {
+    pub(crate) band: InavBand,
+    #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+    pub(crate) gst: Gst,
+    pub(crate) crc_checked: bool,
+}
+
+impl WordSource {
+    // A copy of a word whose CRC has been checked is preferred over another
+    // copy whose CRC has not been checked.
+    fn is_preferred_over(&self, other: WordSource) -> bool {
+        self.crc_checked && !other.crc_checked
+    }
+}
+
+impl_common!(
+    CedAndStatus,
+    CED_AND_STATUS_BYTES,
+    CED_AND_STATUS_WORDS,
+    CED_AND_STATUS_BITS,
+    CED_AND_STATUS_LAYOUT,
+    #[cfg(feature = "reduced-ced")]
+    reduced_ced: None
+);
+impl_common!(
+    TimingParameters,
+    TIMING_PARAMETERS_BYTES,
+    TIMING_PARAMETERS_WORDS,
+    TIMING_PARAMETERS_BITS,
+    TIMING_PARAMETERS_LAYOUT
+);
+
+impl CedAndStatus {
+    fn feed(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        source: WordSource,
+        policy: MismatchPolicy,
+    ) -> bool {
+        match self.svn {
+            Some(s) if s == svn => (),
+            None => self.svn = Some(svn),
+            _ => {
+                self.reset();
+                self.svn = Some(svn);
+            }
+        };
+
+        let word_type = InavWordType::of(word);
+        if word_type == InavWordType::Word16 {
+            self.log_word(word_type);
+            #[cfg(feature = "reduced-ced")]
+            {
+                self.reduced_ced = Some(ReducedCed {
+                    word: *word,
+                    gst: source.band.e1b_page_gst(source.gst).gst_subframe(),
+                });
+            }
+            return true;
+        }
+        let word = BitSlice::from_slice(word);
+        let stored = match CED_AND_STATUS_LAYOUT
+            .iter()
+            .position(|layout| layout.word_type() == word_type)
+        {
+            Some(idx) => {
+                self.log_word(word_type);
+                let layout = &CED_AND_STATUS_LAYOUT[idx];
+                self.copy_word(
+                    layout.data_bits(),
+                    layout.extract(word),
+                    idx,
+                    source,
+                    policy,
+                )
+            }
+            None => true,
+        };
+        self.log_age();
+        stored
+    }
+}
+
+impl TimingParameters {
+    fn feed(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        source: WordSource,
+        policy: MismatchPolicy,
+    ) -> bool {
+        match self.svn {
+            Some(s) if s == svn => (),
+            None => self.svn = Some(svn),
+            _ => {
+                self.reset();
+                self.svn = Some(svn);
+            }
+        };
+
+        let word_type = InavWordType::of(word);
+        let word = BitSlice::from_slice(word);
+        let idx = TIMING_PARAMETERS_LAYOUT
+            .iter()
+            .position(|layout| layout.word_type() == word_type);
+        // The word 7/9 and 8/10 pairs alternate in the opposite order in E5b-I,
+        // so word 10 is received in E5b-I in the subframes in which E1-B
+        // transmits word 8. The timing parameters of these subframes are
+        // formed with the word 10 transmitted in E1-B in the previous
+        // subframe, so an E5b-I copy does not replace it. The E5b-I copies
+        // are used when word 10 is not being received in E1-B.
+        if let Some(idx) = idx {
+            if word_type == InavWordType::Word10
+                && source.band == InavBand::E5B
+                && self.age[idx] <= 1
+                && self.sources[idx].is_some_and(|s| s.band == InavBand::E1B)
+            {
+                log::trace!(
+                    "TimingParameters for {} keeping {} received in E1-B",
+                    svn,
+                    word_type
+                );
+                return true;
+            }
+        }
+        let stored = match idx {
+            Some(idx) => {
+                self.log_word(word_type);
+                let layout = &TIMING_PARAMETERS_LAYOUT[idx];
+                self.copy_word(
+                    layout.data_bits(),
+                    layout.extract(word),
+                    idx,
+                    source,
+                    policy,
+                )
+            }
+            None => true,
+        };
+        self.log_age();
+        stored
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::SmallStorage;
+    use crate::testing;
+    use hex_literal::hex;
+
+    #[test]
+    fn auth_schedule() {
+        let gst = Gst::new(1234, 300);
+
+        let schedule = AuthSchedule::new(0, Adkd::InavCed, gst, Some(0));
+        assert_eq!(schedule.next_authentication(), None);
+        assert_eq!(schedule.refresh_deadline(), None);
+
+        let schedule = AuthSchedule::new(1, Adkd::InavCed, gst, Some(0));
+        assert_eq!(schedule.next_authentication(), Some(gst.add_subframes(2)));
+        assert_eq!(schedule.refresh_deadline(), Some(gst));
+
+        let schedule = AuthSchedule::new(5, Adkd::InavTiming, gst, Some(2));
+        assert_eq!(schedule.next_authentication(), Some(gst.add_subframes(2)));
+        assert_eq!(schedule.refresh_deadline(), Some(gst.add_subframes(2)));
+
+        let schedule = AuthSchedule::new(5, Adkd::SlowMac, gst, Some(0));
+        assert_eq!(schedule.next_authentication(), Some(gst.add_subframes(12)));
+
+        // Stale data
+        let schedule = AuthSchedule::new(2, Adkd::InavCed, gst, Some(2));
+        assert_eq!(schedule.next_authentication(), Some(gst.add_subframes(3)));
+        assert_eq!(schedule.refresh_deadline(), None);
+
+        // Incomplete data
+        let schedule = AuthSchedule::new(2, Adkd::InavCed, gst, None);
+        assert_eq!(schedule.next_authentication(), Some(gst.add_subframes(3)));
+        assert_eq!(schedule.refresh_deadline(), None);
+    }
+
+    #[test]
+    fn cop_after_validation() {
+        // Data corresponding to E21 on 2022-03-07 ~9:00 UTC (see tag0 test in
+        // the tesla module)
+        let ced = hex!(
+            "
+            12 07 d0 ec 19 90 2e 00 1f e1 06 aa 04 ed 97 12
+            11 f0 56 1f 49 ea ce 67 88 4d 18 57 81 9f 12 3f
+            f0 37 48 93 42 c3 c2 96 c7 65 c3 83 1a c4 85 40
+            01 7f fd 87 d0 fe 85 ee 31 ff f6 20 0c 68 0b fe
+            48 00 50 14 00"
+        );
+        let ced = BitSlice::from_slice(&ced);
+        let prna = Svn::try_from(21).unwrap();
+        let gst_mack = Gst::new(1176, 121050);
+        let gst_navdata = gst_mack.add_subframes(-1);
+        let key = testing::tesla_key();
+        let chain = testing::chain();
+
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for (j, layout) in CED_AND_STATUS_LAYOUT.iter().enumerate() {
+            let mut word = [0; 16];
+            word[0] = u8::try_from(j + 1).unwrap() << 2;
+            BitSlice::from_slice_mut(&mut word)[layout.word_bits()]
+                .copy_from_bitslice(&ced[layout.data_bits()]);
+            collect.feed(&word, prna, gst_navdata, InavBand::E1B);
+        }
+
+        let mut mack = [0; 60];
+        mack[..5].copy_from_slice(&hex!("8f 54 58 88 71"));
+        mack[6] = 0x0f; // COP = 15
+
+        // A tag that fails to validate does not give a COP
+        let mut wrong_mack = mack;
+        wrong_mack[0] ^= 1;
+        let wrong_mack = Mack::new(&wrong_mack, chain.key_size_bits(), chain.tag_size_bits());
+        collect.process_mack(
+            wrong_mack.force_valid(),
+            &key,
+            prna,
+            gst_mack,
+            NmaStatus::Test,
+        );
+        assert_eq!(collect.failed_tags(prna), 1);
+        assert!(collect.auth_schedule(prna, Adkd::InavCed).is_none());
+
+        let mack = Mack::new(&mack, chain.key_size_bits(), chain.tag_size_bits());
+        collect.process_mack(mack.force_valid(), &key, prna, gst_mack, NmaStatus::Test);
+        assert_eq!(collect.failed_tags(prna), 1);
+        assert_eq!(collect.get_ced_and_status(prna).unwrap().authbits(), 40);
+        assert!(collect.auth_schedule(prna, Adkd::InavCed).is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "cross-auth")]
+    fn cross_auth_expectation() {
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        let svn = |n| Svn::try_from(n).unwrap();
+        let gst = Gst::new(1234, 300);
+
+        // Dummy tags are not included
+        collect.record_cop(svn(1), Adkd::InavCed, 0, svn(2), gst);
+        assert!(collect
+            .cross_auth_expectation(svn(1), Adkd::InavCed)
+            .is_none());
+
+        collect.record_cop(svn(1), Adkd::InavCed, 10, svn(1), gst);
+        collect.record_cop(svn(1), Adkd::InavCed, 10, svn(2), gst);
+        let expectation = collect
+            .cross_auth_expectation(svn(1), Adkd::InavCed)
+            .unwrap();
+        assert_eq!(expectation.prnd(), svn(1));
+        assert_eq!(expectation.gst(), gst);
+        assert_eq!(expectation.cop(), 10);
+        assert!(expectation.prnas().eq([svn(1), svn(2)]));
+        assert!(expectation.is_cross_authenticated());
+        assert!(collect
+            .cross_auth_expectation(svn(1), Adkd::InavTiming)
+            .is_none());
+
+        // PRNAs of the previous subframe are kept
+        let gst = gst.add_subframes(1);
+        collect.record_cop(svn(1), Adkd::InavCed, 8, svn(3), gst);
+        let expectation = collect
+            .cross_auth_expectation(svn(1), Adkd::InavCed)
+            .unwrap();
+        assert_eq!(expectation.gst(), gst);
+        assert_eq!(expectation.cop(), 8);
+        assert!(expectation.prnas().eq([svn(1), svn(2), svn(3)]));
+        assert!(collect
+            .cross_auth_targets(svn(3), Adkd::InavCed)
+            .eq([svn(1)]));
+
+        // PRNAs older than the previous subframe are dropped
+        let gst = gst.add_subframes(2);
+        collect.record_cop(svn(1), Adkd::InavCed, 8, svn(1), gst);
+        let expectation = collect
+            .cross_auth_expectation(svn(1), Adkd::InavCed)
+            .unwrap();
+        assert!(expectation.prnas().eq([svn(1)]));
+        assert!(!expectation.is_cross_authenticated());
+        assert!(collect
+            .cross_auth_targets(svn(3), Adkd::InavCed)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn week_rollover() {
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        let svn = Svn::try_from(1).unwrap();
+        // Word type 1
+        let mut word = [0; 16];
+        word[0] = 1 << 2;
+        let gst0 = Gst::new(1234, 7 * 24 * 3600 - 30);
+        let gst1 = gst0.wrapping_add_subframes(1);
+        collect.feed(&word, svn, gst0.wrapping_add_seconds(3), InavBand::E1B);
+        collect.feed(&word, svn, gst1.wrapping_add_seconds(3), InavBand::E1B);
+        assert_eq!(gst1, Gst::new(1235, 0));
+        let idx0 = collect.find_gst(gst0).unwrap();
+        let idx1 = collect.find_gst(gst1).unwrap();
+        assert_eq!(idx1, collect.write_pointer);
+        assert_ne!(idx0, idx1);
+        // The CED has been carried over to the new subframe
+        let ced = collect
+            .current_ced_as_mut()
+            .iter()
+            .find(|ced| ced.svn == Some(svn))
+            .unwrap();
+        assert_eq!(ced.age[0], 0);
+
+        // Week number rollover
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        let gst0 = Gst::new(4095, 7 * 24 * 3600 - 30);
+        let gst1 = gst0.wrapping_add_subframes(1);
+        let gst2 = gst1.wrapping_add_subframes(1);
+        assert_eq!(gst1, Gst::new(0, 0));
+        collect.feed(&word, svn, gst0.wrapping_add_seconds(3), InavBand::E1B);
+        collect.feed(&word, svn, gst1.wrapping_add_seconds(3), InavBand::E1B);
+        collect.feed(&word, svn, gst2.wrapping_add_seconds(3), InavBand::E1B);
+        let depth = collect.gsts.len();
+        let idx0 = collect.find_gst(gst0).unwrap();
+        let idx1 = collect.find_gst(gst1).unwrap();
+        let idx2 = collect.find_gst(gst2).unwrap();
+        assert_eq!(idx2, collect.write_pointer);
+        assert_eq!(idx1, (idx0 + 1) % depth);
+        assert_eq!(idx2, (idx1 + 1) % depth);
+        let ced = collect
+            .current_ced_as_mut()
+            .iter()
+            .find(|ced| ced.svn == Some(svn))
+            .unwrap();
+        assert_eq!(ced.age[0], 0);
+    }
+
+    #[test]
+    fn mismatch_policy() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, fill: u8| {
+            let mut word = [fill; 16];
+            word[0] = (word_type << 2) | (fill & 3);
+            word
+        };
+        let setup = |policy| {
+            let mut collect = CollectNavMessage::<SmallStorage>::new();
+            collect.set_mismatch_policy(policy);
+            for word_type in 1..=5 {
+                collect.feed(&word(word_type, 0), svn, gst, InavBand::E1B);
+            }
+            // Pretend that the data has been authenticated
+            let latency = AuthLatency {
+                data_gst: gst,
+                authentication_gst: gst.add_subframes(2),
+            };
+            for ced in collect.current_ced_as_mut() {
+                if ced.svn == Some(svn) {
+                    ced.add_authbits(bits![u8, Msb0; 0; 40], latency, Adkd::InavCed, svn, gst);
+                }
+            }
+            assert!(collect.get_ced_and_status(svn).is_some());
+            collect.feed(&word(1, 0xff), svn, gst.add_subframes(1), InavBand::E1B);
+            // Advance until the subframe where the data was authenticated is
+            // no longer in the history
+            for j in 2..=4 {
+                collect.feed(&word(2, 0), svn, gst.add_subframes(j), InavBand::E1B);
             }
+            collect
+        };
+
+        #[cfg_attr(not(feature = "nav-diagnostics"), allow(unused_mut))]
+        let mut collect = setup(MismatchPolicy::TakeNew);
+        assert!(collect.get_ced_and_status(svn).is_none());
+        #[cfg(feature = "nav-diagnostics")]
+        {
+            let mismatches = collect.anomalies().satellite(svn).word_mismatches();
+            assert_eq!(mismatches.count(), 1);
+            assert_eq!(mismatches.first(), Some(gst.add_subframes(1)));
+            assert_eq!(mismatches.last(), Some(gst.add_subframes(1)));
+            assert_eq!(collect.anomalies().iter().count(), 1);
+            collect.clear_anomalies();
+            assert!(!collect.anomalies().satellite(svn).any());
         }
 
-        // Try to validate InavCed and InavTiming tags
-        for j in 1..mack.num_tags() {
-            let tag = mack.tag_and_info(j);
-            log::info!(
-                "{} tag{} {:?} at {:?} COP = {} PRND = {:?}",
-                prna,
-                j,
-                tag.adkd(),
-                gst_mack,
-                tag.cop(),
-                tag.prnd()
-            );
-            let prnd = match u8::try_from(tag.prnd()) {
-                Ok(p) => p,
-                Err(_) => {
-                    log::error!("could not obtain PRND from tag {:?}", tag);
-                    continue;
+        let mut collect = setup(MismatchPolicy::KeepOld);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert!(data.data()[..120].not_any());
+        // The satellite has switched to new data, so the old word 1 is no
+        // longer received and the new one is eventually accepted
+        let is_new = |collect: &mut CollectNavMessage<SmallStorage>| {
+            collect
+                .current_ced_as_mut()
+                .iter()
+                .any(|c| c.svn == Some(svn) && c.bits()[6..120].all())
+        };
+        let mut j = 5;
+        loop {
+            collect.feed(&word(1, 0xff), svn, gst.add_subframes(j), InavBand::E1B);
+            if is_new(&mut collect) {
+                break;
+            }
+            j += 1;
+        }
+        assert_eq!(j, i32::from(KEEP_OLD_MAX_AGE));
+        let current = collect.current_ced_as_mut();
+        let new = current.iter_mut().find(|c| c.svn == Some(svn)).unwrap();
+        assert_eq!(new.authbits, 0);
+        // The new data can be authenticated
+        let gst_new = gst.add_subframes(j);
+        let latency = AuthLatency {
+            data_gst: gst_new,
+            authentication_gst: gst_new.add_subframes(2),
+        };
+        new.add_authbits(bits![u8, Msb0; 0; 40], latency, Adkd::InavCed, svn, gst_new);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert!(data.data()[6..120].all());
+
+        let mut collect = setup(MismatchPolicy::Quarantine);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert!(data.data()[..120].not_any());
+        let current = collect.current_ced_as_mut();
+        assert_eq!(current.iter().filter(|c| c.svn == Some(svn)).count(), 2);
+        let new = current
+            .iter()
+            .find(|c| c.svn == Some(svn) && !c.retained)
+            .unwrap();
+        assert!(new.bits()[6..120].all());
+        assert_eq!(new.authbits, 0);
+        // The retained copy is not used for tag validation
+        assert!(collect
+            .find_ced_and_status(svn, gst.add_subframes(4))
+            .is_some_and(|c| !c.retained));
+    }
+
+    #[test]
+    #[cfg(feature = "nav-diagnostics")]
+    fn anomaly_tracker() {
+        let svn = Svn::try_from(5).unwrap();
+        let gst = Gst::new(1234, 300);
+        let mut tracker = AnomalyTracker::default();
+        assert_eq!(tracker.iter().count(), 0);
+        tracker.record_failed_tag(svn, gst.add_subframes(1));
+        tracker.record_failed_tag(svn, gst);
+        tracker.record_macseq_failure(svn, gst.add_subframes(2));
+        let anomalies = tracker.satellite(svn);
+        assert!(anomalies.any());
+        assert_eq!(anomalies.word_mismatches().count(), 0);
+        assert_eq!(anomalies.word_mismatches().first(), None);
+        let failed = anomalies.failed_tags();
+        assert_eq!(failed.count(), 2);
+        assert_eq!(failed.first(), Some(gst.add_subframes(1)));
+        assert_eq!(failed.last(), Some(gst.add_subframes(1)));
+        assert_eq!(anomalies.macseq_failures().count(), 1);
+        let mut iter = tracker.iter();
+        assert_eq!(iter.next().map(|(s, _)| s), Some(svn));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn dual_band_dedup() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, fill: u8| {
+            let mut word = [fill; 16];
+            word[0] = (word_type << 2) | (fill & 3);
+            word
+        };
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(2),
+        };
+        let setup = || {
+            let mut collect = CollectNavMessage::<SmallStorage>::new();
+            for word_type in 1..=5 {
+                collect.feed(&word(word_type, 0), svn, gst, InavBand::E1B);
+            }
+            for ced in collect.current_ced_as_mut() {
+                if ced.svn == Some(svn) {
+                    ced.add_authbits(bits![u8, Msb0; 0; 40], latency, Adkd::InavCed, svn, gst);
                 }
-            };
-            match tag.adkd() {
-                Adkd::InavCed => match Svn::try_from(prnd) {
-                    Ok(prnd_svn) => {
-                        if tag.cop() == 0 {
-                            Self::validate_dummy_tag(
-                                key,
-                                tag.tag(),
-                                tag.adkd(),
-                                gst_mack,
-                                prnd,
-                                prna,
-                                j,
-                                nma_status,
-                                CED_AND_STATUS_BITS,
-                            );
-                        } else if let Some(&navdata) =
-                            self.find_ced_and_status(prnd_svn, gst_navmessage)
-                        {
-                            if navdata.max_age().saturating_add(1) <= tag.cop() {
-                                Self::validate_tag(
-                                    key,
-                                    tag.tag(),
-                                    tag.adkd(),
-                                    gst_mack,
-                                    prnd,
-                                    prna,
-                                    j,
-                                    nma_status,
-                                    &navdata,
-                                    self.ced_and_status_iter_authbits_mut(),
-                                );
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        log::error!("invalid PRND {:?} for ADKD {:?}", tag.prnd(), tag.adkd());
-                    }
-                },
-                Adkd::InavTiming => match Svn::try_from(prnd) {
-                    Ok(prnd_svn) => {
-                        if tag.cop() == 0 {
-                            Self::validate_dummy_tag(
-                                key,
-                                tag.tag(),
-                                tag.adkd(),
-                                gst_mack,
-                                prnd,
-                                prna,
-                                j,
-                                nma_status,
-                                TIMING_PARAMETERS_BITS,
-                            );
-                        } else if let Some(&navdata) =
-                            self.find_timing_parameters(prnd_svn, gst_navmessage)
-                        {
-                            if navdata.max_age().saturating_add(1) <= tag.cop() {
-                                Self::validate_tag(
-                                    key,
-                                    tag.tag(),
-                                    tag.adkd(),
-                                    gst_mack,
-                                    prnd,
-                                    prna,
-                                    j,
-                                    nma_status,
-                                    &navdata,
-                                    self.timing_parameters_iter_authbits_mut(),
-                                );
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        log::error!("invalid PRND {:?} for ADKD {:?}", tag.prnd(), tag.adkd());
+            }
+            collect
+        };
+        let ced = |collect: &mut CollectNavMessage<SmallStorage>| {
+            *collect
+                .current_ced_as_mut()
+                .iter()
+                .find(|c| c.svn == Some(svn))
+                .unwrap()
+        };
+
+        // An identical copy received in the other band keeps the authbits
+        let mut collect = setup();
+        collect.feed(&word(1, 0), svn, gst, InavBand::E5B);
+        assert_eq!(ced(&mut collect).authbits, 40);
+
+        // A different copy received in the other band in the same subframe
+        // is discarded
+        let mut collect = setup();
+        collect.feed(&word(1, 0xff), svn, gst, InavBand::E5B);
+        let c = ced(&mut collect);
+        assert_eq!(c.authbits, 40);
+        assert!(c.bits()[..120].not_any());
+
+        // Unless it has been CRC checked and the stored copy has not
+        let mut collect = setup();
+        collect.feed_crc_checked(&word(1, 0xff), svn, gst, InavBand::E5B);
+        let c = ced(&mut collect);
+        assert_eq!(c.authbits, 0);
+        assert!(c.bits()[6..120].all());
+
+        // A CRC checked copy is not replaced by an unchecked one
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        collect.feed_crc_checked(&word(1, 0), svn, gst, InavBand::E1B);
+        collect.feed_crc_checked(&word(1, 0), svn, gst, InavBand::E5B);
+        collect.feed(&word(1, 0xff), svn, gst, InavBand::E5B);
+        assert!(ced(&mut collect).bits()[..120].not_any());
+    }
+
+    #[test]
+    fn cop_policy() {
+        let policy = CopPolicy::DEFAULT;
+        assert!(policy.allows(1, 0));
+        assert!(!policy.allows(1, 1));
+        assert!(policy.allows(10, 9));
+        assert!(!policy.allows(10, 10));
+        assert!(!policy.allows(10, u8::MAX));
+
+        let policy = policy.with_min_cop(3).with_age_margin(5);
+        assert!(!policy.allows(2, 0));
+        assert!(policy.allows(3, 7));
+        assert!(!policy.allows(3, 8));
+        assert!(policy.allows(u8::MAX, u8::MAX));
+        assert_eq!(CopPolicy::DEFAULT.with_min_cop(0).min_cop(), 1);
+
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        collect.set_cop_policy(Adkd::InavTiming, policy);
+        collect.set_cop_policy(Adkd::Reserved, policy);
+        assert_eq!(collect.cop_policy(Adkd::InavTiming), policy);
+        assert_eq!(collect.cop_policy(Adkd::InavCed), CopPolicy::DEFAULT);
+        assert_eq!(collect.cop_policy(Adkd::Reserved), CopPolicy::DEFAULT);
+    }
+
+    #[test]
+    fn adkd_filter() {
+        let filter = AdkdFilter::ALL.without(Adkd::InavTiming);
+        assert!(filter.contains(Adkd::InavCed));
+        assert!(!filter.contains(Adkd::InavTiming));
+        assert!(!AdkdFilter::NONE
+            .with(Adkd::Reserved)
+            .contains(Adkd::Reserved));
+        assert_eq!(AdkdFilter::default(), AdkdFilter::ALL);
+        assert_eq!(
+            AdkdFilter::ALL.contains(Adkd::SlowMac),
+            cfg!(feature = "slowmac")
+        );
+
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let mut word = [0; 16];
+        word[0] = 6 << 2;
+        for (filter, ced, timing) in [
+            (AdkdFilter::ALL, true, true),
+            (filter, true, false),
+            (AdkdFilter::NONE.with(Adkd::InavTiming), false, true),
+        ] {
+            let mut collect = CollectNavMessage::<SmallStorage>::new();
+            collect.set_adkd_filter(filter);
+            assert_eq!(collect.adkd_filter(), filter);
+            collect.feed(&word, svn, gst, InavBand::E1B);
+            assert_eq!(
+                collect.ced_and_status.iter().any(|c| c.svn == Some(svn)),
+                ced
+            );
+            assert_eq!(
+                collect.timing_parameters.iter().any(|t| t.svn == Some(svn)),
+                timing
+            );
+        }
+    }
+
+    #[test]
+    fn history_reconciliation() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, data: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word[2] = data;
+            word
+        };
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(1),
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        // The data is authenticated, then replaced by different data, and
+        // then restored
+        for (subframe, data) in [(0, 0), (1, 1), (2, 0)] {
+            for word_type in 1..=5 {
+                collect.feed(
+                    &word(word_type, data),
+                    svn,
+                    gst.add_subframes(subframe),
+                    InavBand::E1B,
+                );
+            }
+            if subframe == 0 {
+                for ced in collect.current_ced_as_mut() {
+                    if ced.svn == Some(svn) {
+                        ced.add_authbits(bits![u8, Msb0; 0; 80], latency, Adkd::InavCed, svn, gst);
                     }
-                },
-                Adkd::SlowMac => {
-                    // Slow MAC is not processed here, because the key doesn't
-                    // have the appropriate extra delay
-                }
-                Adkd::Reserved => {
-                    log::error!("reserved ADKD in tag {:?}", tag);
                 }
             }
         }
+
+        let ced = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(ced.gst(), gst);
+        assert_eq!(ced.authbits(), 80);
+        collect.set_history_reconciliation(true);
+        assert!(collect.history_reconciliation());
+        let ced = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(ced.gst(), gst.add_subframes(2));
+        assert_eq!(ced.authbits(), 80);
+        assert_eq!(ced.latency(), latency);
     }
 
-    /// Process the Slow MAC (ADKD=12) tags in a MACK message.
-    ///
-    /// This processes a MACK message, authenticating stored navigation data as
-    /// possible using the Slow MAC tags. The `key` should be the TESLA key with
-    /// which the Slow MAC tags in the MACK message `mack` have been generated
-    /// (recall that this key is transmitted 11 subframes after the MACK
-    /// message). The `prna` is the authenticating PRN, which is the SVN that
-    /// has transmitted the MACK message. The `gst_mack` parameter should be the
-    /// GST corresponding to the start of the subframe when the MACK message was
-    /// transmitted.  The `nma_status` parameter should be the value of the NMA
-    /// status field in the current NMA header. The NMA header does not need to
-    /// be validated using the DSM-KROOT, since a forged or incorrect NMA header
-    /// will simply make tag validation fail.
-    ///
-    /// This function ignores all the other tags in the MACK message, since they
-    /// do not correspond to `key`.
-    pub fn process_mack_slowmac(
-        &mut self,
-        mack: Mack<Validated>,
-        key: &Key<Validated>,
-        prna: Svn,
-        gst_mack: Gst,
-        nma_status: NmaStatus,
-    ) {
-        let gst_navmessage = gst_mack.add_seconds(-30);
-        for j in 1..mack.num_tags() {
-            let tag = mack.tag_and_info(j);
-            if tag.adkd() != Adkd::SlowMac {
-                continue;
+    #[test]
+    fn history() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, data: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word[2] = data;
+            word
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        // The data is authenticated in the first subframe, and replaced by
+        // different data that is authenticated in the third subframe
+        for (subframe, data) in [(0, 0), (1, 0), (2, 1)] {
+            let gst = gst.add_subframes(subframe);
+            for word_type in 1..=5 {
+                collect.feed(&word(word_type, data), svn, gst, InavBand::E1B);
             }
-            let prnd = match u8::try_from(tag.prnd()) {
-                Ok(p) => p,
-                Err(_) => {
-                    log::error!("could not obtain PRND from tag {:?}", tag);
-                    continue;
-                }
-            };
-            let prnd_svn = match Svn::try_from(prnd) {
-                Ok(s) => s,
-                Err(_) => {
-                    log::error!("invalid PRND {:?} for Slow MAC tag {:?}", tag.prnd(), tag);
-                    continue;
-                }
-            };
-            if tag.cop() == 0 {
-                Self::validate_dummy_tag(
-                    key,
-                    tag.tag(),
-                    tag.adkd(),
-                    gst_mack,
-                    prnd,
-                    prna,
-                    j,
-                    nma_status,
-                    CED_AND_STATUS_BITS,
-                );
-            } else if let Some(&navdata) = self.find_ced_and_status(prnd_svn, gst_navmessage) {
-                if navdata.max_age().saturating_add(1) <= tag.cop() {
-                    Self::validate_tag(
-                        key,
-                        tag.tag(),
-                        tag.adkd(),
-                        gst_mack,
-                        prnd,
-                        prna,
-                        j,
-                        nma_status,
-                        &navdata,
-                        self.ced_and_status_iter_authbits_mut(),
-                    );
+            if subframe != 1 {
+                let latency = AuthLatency {
+                    data_gst: gst,
+                    authentication_gst: gst.add_subframes(1),
+                };
+                for ced in collect.current_ced_as_mut() {
+                    if ced.svn == Some(svn) {
+                        ced.add_authbits(bits![u8, Msb0; 0; 80], latency, Adkd::InavCed, svn, gst);
+                    }
                 }
             }
         }
+
+        assert_eq!(
+            collect.get_ced_and_status(svn).unwrap().gst(),
+            gst.add_subframes(2)
+        );
+        assert_eq!(
+            collect.get_ced_and_status_at(svn, gst.add_subframes(2)),
+            collect.get_ced_and_status(svn)
+        );
+        for subframe in 0..2 {
+            let ced = collect
+                .get_ced_and_status_at(svn, gst.add_subframes(subframe).add_seconds(10))
+                .unwrap();
+            assert_eq!(ced.gst(), gst.add_subframes(subframe));
+            assert!(ced.data().not_any());
+        }
+        assert!(collect
+            .get_ced_and_status_at(svn, gst.add_seconds(-1))
+            .is_none());
+        assert!(collect.get_timing_parameters_at(svn, gst).is_none());
+
+        let history = collect.authenticated_data().collect::<Vec<_>>();
+        assert_eq!(history.len(), 3);
+        for (subframe, (s, kind, slot_gst, data)) in history.into_iter().enumerate() {
+            assert_eq!(s, svn);
+            assert_eq!(kind, NavDataKind::CedAndStatus);
+            assert_eq!(slot_gst, gst.add_subframes(subframe as i32));
+            assert_eq!(data.data().any(), subframe == 2);
+        }
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn validate_tag<'a>(
-        key: &Key<Validated>,
-        tag: &BitSlice,
-        adkd: Adkd,
-        gst_tag: Gst,
-        prnd: u8,
-        prna: Svn,
-        tag_idx: usize,
-        nma_status: NmaStatus,
-        navdata: &dyn AuthBits,
-        to_add_authbits: impl Iterator<Item = &'a mut dyn AuthBits>,
-    ) -> bool {
-        let ctr = (tag_idx + 1).try_into().unwrap();
-        let ret = match tag_idx {
-            0 => key.validate_tag0(tag, gst_tag, prna, nma_status, navdata.message_bits()),
-            _ => key.validate_tag(
-                tag,
-                gst_tag,
-                prnd,
-                prna,
-                ctr,
-                nma_status,
-                navdata.message_bits(),
-            ),
+    #[test]
+    fn constellation_timing() {
+        let svns = [1, 2, 3].map(|svn| Svn::try_from(svn).unwrap());
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, data: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word[2] = data;
+            word
         };
-        if ret {
-            log::info!(
-                "E{:02} {:?} at {:?} tag{} correct (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
-            );
-            // This nma_status is known good because it has been used in the tag
-            // validation, so we can act on it to decide if we can add
-            // authentication bits.
-            if matches!(nma_status, NmaStatus::Operational | NmaStatus::Test) {
-                for to_add in to_add_authbits {
-                    if navdata.svn() == to_add.svn()
-                        && navdata.message_bits() == to_add.message_bits()
-                    {
-                        to_add.add_authbits(tag);
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        assert!(collect.get_constellation_timing().is_none());
+        // In the first subframe, only the first satellite is authenticated.
+        // In the second subframe, the first satellite gives different timing
+        // parameters than the other two satellites.
+        for (subframe, data, authenticated) in
+            [(0, [0, 0, 0], &svns[..1]), (1, [2, 1, 1], &svns[..])]
+        {
+            let gst = gst.add_subframes(subframe);
+            let latency = AuthLatency {
+                data_gst: gst,
+                authentication_gst: gst.add_subframes(1),
+            };
+            for (&svn, data) in svns.iter().zip(data) {
+                for word_type in [6, 10] {
+                    collect.feed(&word(word_type, data), svn, gst, InavBand::E1B);
+                }
+                if !authenticated.contains(&svn) {
+                    continue;
+                }
+                for timing in collect.current_timing_parameters_as_mut() {
+                    if timing.svn == Some(svn) {
+                        timing.add_authbits(
+                            bits![u8, Msb0; 0; 40],
+                            latency,
+                            Adkd::InavTiming,
+                            svn,
+                            gst,
+                        );
                     }
                 }
             }
-        } else {
-            log::error!(
-                "E{:02} {:?} at {:?} tag{} wrong (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
-            );
+            let timing = collect.get_constellation_timing().unwrap();
+            assert_eq!(timing.data().gst(), gst);
+            if subframe == 0 {
+                assert_eq!(timing.svn(), svns[0]);
+                assert_eq!(timing.authbits(), 40);
+                assert_eq!(timing.svns().collect::<Vec<_>>(), [svns[0]]);
+            } else {
+                assert_eq!(timing.svn(), svns[1]);
+                assert_eq!(timing.authbits(), 80);
+                assert_eq!(timing.num_svns(), 2);
+                assert_eq!(timing.svns().collect::<Vec<_>>(), &svns[1..]);
+                assert_eq!(
+                    timing.data().data(),
+                    collect.get_timing_parameters(svns[2]).unwrap().data()
+                );
+            }
         }
-        ret
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn validate_dummy_tag(
-        key: &Key<Validated>,
-        tag: &BitSlice,
-        adkd: Adkd,
-        gst_tag: Gst,
-        prnd: u8,
-        prna: Svn,
-        tag_idx: usize,
-        nma_status: NmaStatus,
-        navdata_len_bits: usize,
-    ) -> bool {
-        let ctr = (tag_idx + 1).try_into().unwrap();
-        let ret = match tag_idx {
-            0 => key.validate_tag0_dummy(tag, gst_tag, prna, nma_status, navdata_len_bits),
-            _ => {
-                key.validate_tag_dummy(tag, gst_tag, prnd, prna, ctr, nma_status, navdata_len_bits)
-            }
+    #[test]
+    fn prune_and_clear() {
+        let svns = [1, 2].map(|svn| Svn::try_from(svn).unwrap());
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word
         };
-        if ret {
-            log::info!(
-                "E{:02} {:?} at {:?} dummy tag{} correct (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
-            );
-        } else {
-            log::error!(
-                "E{:02} {:?} at {:?} dummy tag{} wrong (auth by {})",
-                prnd,
-                adkd,
-                gst_tag,
-                tag_idx,
-                prna
-            );
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(1),
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for subframe in 0..3 {
+            let gst = gst.add_subframes(subframe);
+            for &svn in &svns {
+                // The second satellite is not received after the first
+                // subframe
+                if svn == svns[1] && subframe > 0 {
+                    continue;
+                }
+                for word_type in 1..=5 {
+                    collect.feed(&word(word_type), svn, gst, InavBand::E1B);
+                }
+                for ced in collect.current_ced_as_mut() {
+                    if ced.svn == Some(svn) {
+                        ced.add_authbits(bits![u8, Msb0; 0; 80], latency, Adkd::InavCed, svn, gst);
+                    }
+                }
+            }
         }
-        ret
+        assert!(svns
+            .iter()
+            .all(|&svn| collect.get_ced_and_status(svn).is_some()));
+
+        assert_eq!(collect.clear_satellites_not_seen_since(gst), 0);
+        assert_eq!(
+            collect.clear_satellites_not_seen_since(gst.add_subframes(1)),
+            1
+        );
+        assert!(collect.get_ced_and_status(svns[0]).is_some());
+        assert!(collect.get_ced_and_status(svns[1]).is_none());
+        assert_eq!(
+            collect.clear_satellites_not_seen_since(gst.add_subframes(1)),
+            0
+        );
+
+        assert_eq!(collect.prune_older_than(gst), 0);
+        assert_eq!(collect.prune_older_than(gst.add_subframes(2)), 2);
+        assert_eq!(collect.prune_older_than(gst.add_subframes(2)), 0);
+        assert_eq!(
+            collect.get_ced_and_status(svns[0]).unwrap().gst(),
+            gst.add_subframes(2)
+        );
+        assert_eq!(collect.prune_older_than(gst.add_subframes(3)), 1);
+        assert!(collect.get_ced_and_status(svns[0]).is_none());
+        // Data can be fed after pruning the current subframe
+        collect.feed(&word(1), svns[0], gst.add_subframes(3), InavBand::E1B);
+        assert_eq!(
+            collect.gsts[collect.write_pointer],
+            Some(gst.add_subframes(3))
+        );
     }
 
-    /// Resets all the authentication bits to zero.
-    ///
-    /// This function can be called when the NMA status is set to don't use in
-    /// order to discard all the previously generated authentication bits.
-    pub fn reset_authbits(&mut self) {
-        for ced in self.ced_and_status.iter_mut() {
-            ced.reset_authbits();
+    #[test]
+    #[cfg(feature = "reduced-ced")]
+    fn reduced_ced() {
+        let svn = Svn::try_from(7).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word
+        };
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(1),
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for word_type in 1..=5 {
+            collect.feed(&word(word_type), svn, gst, InavBand::E1B);
         }
-        for timing in self.timing_parameters.iter_mut() {
-            timing.reset_authbits();
+        assert!(collect.get_reduced_ced(svn).is_none());
+        for ced in collect.current_ced_as_mut() {
+            if ced.svn == Some(svn) {
+                ced.add_authbits(bits![u8, Msb0; 0; 80], latency, Adkd::InavCed, svn, gst);
+            }
         }
-    }
-}
+        let mut word16 = word(16);
+        word16[1] = 0x42;
+        collect.feed(&word16, svn, gst.add_seconds(10), InavBand::E1B);
+        let reduced_ced = collect.get_reduced_ced(svn).unwrap();
+        assert_eq!(reduced_ced.gst(), gst);
+        assert_eq!(reduced_ced.word16().bits(), BitSlice::from_slice(&word16));
+        // The reduced CED does not affect the authentication
+        assert_eq!(collect.get_ced_and_status(svn).unwrap().authbits(), 80);
+        assert!(collect.get_reduced_ced(Svn::try_from(8).unwrap()).is_none());
 
-impl<S: StaticStorage> Default for CollectNavMessage<S> {
-    fn default() -> CollectNavMessage<S> {
-        CollectNavMessage::new()
+        // The reduced CED is kept in the next subframe
+        collect.feed(&word(1), svn, gst.add_subframes(1), InavBand::E1B);
+        assert_eq!(collect.get_reduced_ced(svn), Some(reduced_ced));
     }
-}
 
-const CED_AND_STATUS_WORDS: usize = 5;
-const CED_AND_STATUS_BITS: usize = 549;
-const CED_AND_STATUS_BYTES: usize = (CED_AND_STATUS_BITS + 7) / 8;
+    #[test]
+    fn partial_tag() {
+        // Data corresponding to E21 on 2022-03-07 ~9:00 UTC (see tag0 test in
+        // the tesla module)
+        let tag0 = BitSlice::from_slice(&hex!("8f 54 58 88 71"));
+        let gst_tag = Gst::new(1176, 121050);
+        let prna = Svn::try_from(21).unwrap();
+        let key = testing::tesla_key();
+        let navdata = CedBits::new(
+            &BitSlice::from_slice(&hex!(
+                "
+            12 07 d0 ec 19 90 2e 00 1f e1 06 aa 04 ed 97 12
+            11 f0 56 1f 49 ea ce 67 88 4d 18 57 81 9f 12 3f
+            f0 37 48 93 42 c3 c2 96 c7 65 c3 83 1a c4 85 40
+            01 7f fd 87 d0 fe 85 ee 31 ff f6 20 0c 68 0b fe
+            48 00 50 14 00"
+            ))[..549],
+        )
+        .unwrap();
+        let validate = |received_words: &[u8]| {
+            validate_tag_partial(
+                &key,
+                tag0,
+                Adkd::InavCed,
+                gst_tag,
+                21,
+                prna,
+                0,
+                NmaStatus::Test,
+                navdata,
+                received_words,
+            )
+        };
 
-#[doc(hidden)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-// This is pub only because it appears in the definition of StaticStorageTypenum
-pub struct CedAndStatus {
-    data: [u8; CED_AND_STATUS_BYTES],
-    age: [u8; CED_AND_STATUS_WORDS],
-    svn: Option<Svn>,
-    authbits: u16,
-}
+        let result = validate(&[1, 2, 3, 4, 5]);
+        assert!(result.is_valid());
+        assert_eq!(result.padding(), None);
+        assert!(result.required_words().eq([1, 2, 3, 4, 5]));
+        assert_eq!(result.missing_words().count(), 0);
 
-const TIMING_PARAMETERS_WORDS: usize = 2;
-const TIMING_PARAMETERS_BITS: usize = 141;
-const TIMING_PARAMETERS_BYTES: usize = (TIMING_PARAMETERS_BITS + 7) / 8;
+        let result = validate(&[5, 3, 1, 2]);
+        assert!(!result.is_valid());
+        assert_eq!(result.padding(), None);
+        assert!(result.missing_words().eq([4]));
 
-#[doc(hidden)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-// This is pub only because it appears in the definition of StaticStorageTypenum
-pub struct TimingParameters {
-    data: [u8; TIMING_PARAMETERS_BYTES],
-    age: [u8; TIMING_PARAMETERS_WORDS],
-    svn: Option<Svn>,
-    authbits: u16,
-}
+        // Navigation data that does not correspond to the ADKD
+        let result = validate_tag_partial(
+            &key,
+            tag0,
+            Adkd::InavTiming,
+            gst_tag,
+            21,
+            prna,
+            0,
+            NmaStatus::Test,
+            navdata,
+            &[1, 2, 3, 4, 5],
+        );
+        assert!(!result.is_valid());
+        assert_eq!(result.required_words().count(), 0);
+    }
 
-trait AuthBits {
-    fn svn(&self) -> Option<Svn>;
-    fn message_bits(&self) -> &BitSlice;
-    fn add_authbits(&mut self, tag: &BitSlice);
-    fn reset_authbits(&mut self);
-}
+    #[test]
+    fn constellation_tags() {
+        let mut tags = ConstellationTags::default();
+        assert!(tags.latest().is_none());
+        let prna = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1176, 121080);
+        let tag = bits![u8, Msb0; 1, 0, 1, 1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1];
+        for j in 0..CONSTELLATION_TAGS_DEPTH + 2 {
+            tags.store(ConstellationTag::new(
+                tag,
+                5,
+                1,
+                prna,
+                gst.add_subframes(j.try_into().unwrap()),
+                3,
+                NmaStatus::Operational,
+            ));
+        }
+        assert_eq!(tags.iter().count(), CONSTELLATION_TAGS_DEPTH);
+        // The two oldest tags have been overwritten
+        assert!(tags.iter().map(|t| t.gst()).eq(
+            (2..CONSTELLATION_TAGS_DEPTH + 2).map(|j| gst.add_subframes(j.try_into().unwrap()))
+        ));
+        let latest = tags.latest().unwrap();
+        assert_eq!(latest.tag(), tag);
+        assert_eq!(latest.adkd(), 5);
+        assert_eq!(
+            latest.to_string(),
+            "PRND=255 tag3 ADKD=5 COP=1 by E11 at WN=1176 TOW=121350"
+        );
+    }
 
-macro_rules! impl_common {
-    ($s:ident, $data_size:expr, $num_words:expr, $num_bits:expr) => {
-        impl $s {
-            fn new() -> $s {
-                $s {
-                    data: [0; $data_size],
-                    age: [u8::MAX; $num_words],
-                    authbits: 0,
-                    svn: None,
+    #[test]
+    fn latency() {
+        let data_gst = Gst::new(1234, 300);
+        let latency = AuthLatency {
+            data_gst,
+            authentication_gst: data_gst.add_subframes(2),
+        };
+        assert_eq!(latency.seconds(), 60);
+
+        // The latency is recorded when MIN_AUTHBITS is reached
+        let mut ced = CedAndStatus::new();
+        let tag = bits![u8, Msb0; 0; 20];
+        let prna = Svn::try_from(1).unwrap();
+        let gst_tag = data_gst.add_subframes(1);
+        ced.add_authbits(tag, latency, Adkd::InavCed, prna, gst_tag);
+        assert_eq!(ced.latency, None);
+        ced.add_authbits(
+            tag,
+            latency,
+            Adkd::InavCed,
+            Svn::try_from(2).unwrap(),
+            gst_tag,
+        );
+        assert_eq!(ced.latency, Some(latency));
+        // Further tags do not change the latency
+        let later = AuthLatency {
+            data_gst,
+            authentication_gst: data_gst.add_subframes(3),
+        };
+        ced.add_authbits(tag, later, Adkd::InavCed, prna, gst_tag.add_subframes(1));
+        assert_eq!(ced.latency, Some(latency));
+        #[cfg(feature = "unique-authbits")]
+        assert_eq!(ced.unique.latency(), Some(latency));
+        ced.reset_authbits();
+        assert_eq!(ced.latency, None);
+        #[cfg(feature = "unique-authbits")]
+        assert_eq!(ced.unique.latency(), None);
+    }
+
+    #[cfg(feature = "unique-authbits")]
+    #[test]
+    fn unique_tag_authbits() {
+        let svn = Svn::try_from(1).unwrap();
+        let other = Svn::try_from(2).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for word_type in 1..=5 {
+            collect.feed(&word(word_type), svn, gst, InavBand::E1B);
+        }
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(2),
+        };
+        let add_tag = |collect: &mut CollectNavMessage<SmallStorage>, adkd, prna| {
+            let gst_tag = gst.add_subframes(1);
+            for ced in collect.current_ced_as_mut() {
+                if ced.svn == Some(svn) {
+                    ced.add_authbits(bits![u8, Msb0; 0; 20], latency, adkd, prna, gst_tag);
                 }
             }
+        };
+        // Two tags from the same source
+        add_tag(&mut collect, Adkd::InavCed, svn);
+        add_tag(&mut collect, Adkd::SlowMac, svn);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.authbits(), 40);
+        assert_eq!(data.unique_authbits(), 20);
+        assert_eq!(data.adkd_authbits(Adkd::InavCed), 20);
+        assert_eq!(data.adkd_authbits(Adkd::SlowMac), 20);
+        assert!(!data.is_slowmac_only());
+        collect.set_unique_tag_authbits(true);
+        assert!(collect.get_ced_and_status(svn).is_none());
 
-            fn reset(&mut self) {
-                self.age.fill(u8::MAX);
-                self.authbits = 0;
-                self.svn = None;
-            }
+        // A tag from a different source
+        add_tag(&mut collect, Adkd::SlowMac, other);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.authbits(), 60);
+        assert_eq!(data.unique_authbits(), 40);
+        assert_eq!(data.adkd_authbits(Adkd::SlowMac), 40);
+        assert_eq!(data.latency(), latency);
 
-            fn bits(&self) -> &BitSlice {
-                BitSlice::from_slice(&self.data)
-            }
+        // A higher threshold
+        collect.set_min_authbits(80);
+        assert!(collect.get_ced_and_status(svn).is_none());
+        collect.set_unique_tag_authbits(false);
+        collect.set_min_authbits(60);
+        assert_eq!(collect.get_ced_and_status(svn).unwrap().authbits(), 60);
+    }
 
-            fn bits_as_mut(&mut self) -> &mut BitSlice {
-                BitSlice::from_slice_mut(&mut self.data)
+    #[test]
+    fn adkd_authbits() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for word_type in 1..=5 {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            collect.feed(&word, svn, gst, InavBand::E1B);
+        }
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(12),
+        };
+        let add_tag = |collect: &mut CollectNavMessage<SmallStorage>, adkd| {
+            for ced in collect.current_ced_as_mut() {
+                if ced.svn == Some(svn) {
+                    ced.add_authbits(bits![u8, Msb0; 0; 40], latency, adkd, svn, gst);
+                }
             }
+        };
 
-            fn max_age(&self) -> u8 {
-                self.age.iter().copied().max().unwrap()
-            }
+        // Authenticated only by Slow MAC
+        add_tag(&mut collect, Adkd::SlowMac);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.authbits(), 40);
+        assert_eq!(data.adkd_authbits(Adkd::SlowMac), 40);
+        assert_eq!(data.adkd_authbits(Adkd::InavCed), 0);
+        assert_eq!(data.adkd_authbits(Adkd::InavTiming), 0);
+        assert_eq!(data.adkd_authbits(Adkd::Reserved), 0);
+        assert!(data.is_slowmac_only());
+        let owned = NavMessageDataOwned::from(data);
+        assert_eq!(owned.adkd_authbits(Adkd::SlowMac), 40);
+        assert!(owned.is_slowmac_only());
 
-            fn min_age(&self) -> u8 {
-                self.age.iter().copied().min().unwrap()
-            }
+        // Also authenticated by an ADKD=0 tag
+        add_tag(&mut collect, Adkd::InavCed);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.authbits(), 80);
+        assert_eq!(data.adkd_authbits(Adkd::SlowMac), 40);
+        assert_eq!(data.adkd_authbits(Adkd::InavCed), 40);
+        assert!(!data.is_slowmac_only());
 
-            fn copy_word(
-                &mut self,
-                dest_range: core::ops::Range<usize>,
-                source: &BitSlice,
-                idx: usize,
-            ) {
-                self.age[idx] = 0;
-                let dest = &mut self.bits_as_mut()[dest_range];
-                if dest != source {
-                    dest.copy_from_bitslice(source);
-                    self.authbits = 0;
-                }
-            }
+        // The bits of each ADKD are reset together with the total
+        collect.reset_authbits();
+        add_tag(&mut collect, Adkd::InavCed);
+        let data = collect.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.adkd_authbits(Adkd::SlowMac), 0);
+        assert_eq!(data.adkd_authbits(Adkd::InavCed), 40);
+    }
 
-            fn log_word(&self, word_type: u8) {
-                log::trace!(
-                    concat!(stringify!($s), " storing INAV word type {} for {}"),
-                    self.svn.unwrap(),
-                    word_type
-                );
-            }
+    #[test]
+    fn tag_verifications() {
+        let mut verifications = TagVerifications::default();
+        assert_eq!(verifications.iter().count(), 0);
+        assert_eq!(verifications.latest(), None);
+        let gst = Gst::new(1176, 120930);
+        let verification = |n: usize| TagVerification {
+            prna: Svn::try_from(19).unwrap(),
+            prnd: Svn::try_from(11).unwrap(),
+            adkd: Adkd::InavTiming,
+            gst: gst.add_subframes(n.try_into().unwrap()),
+            tag_index: 3,
+            valid: n % 2 == 0,
+        };
+        assert_eq!(
+            verification(0).to_string(),
+            "E19 tag3 ADKD=4 (I/NAV timing parameters) for E11 at WN=1176 TOW=120930 valid"
+        );
+        assert!(verification(1).to_string().ends_with(" failed"));
 
-            fn log_age(&self) {
-                log::trace!(
-                    concat!(stringify!($s), " for {} age: {:?}"),
-                    self.svn.unwrap(),
-                    &self.age
-                );
-            }
+        for n in 0..3 {
+            verifications.store(verification(n));
         }
+        assert!(verifications.iter().copied().eq((0..3).map(verification)));
+        assert_eq!(verifications.latest(), Some(&verification(2)));
 
-        impl AuthBits for $s {
-            fn svn(&self) -> Option<Svn> {
-                self.svn
-            }
+        // Only the most recent results are kept, starting by the oldest one
+        let total = TAG_VERIFICATIONS_DEPTH + 5;
+        for n in 3..total {
+            verifications.store(verification(n));
+        }
+        assert!(verifications
+            .iter()
+            .copied()
+            .eq((total - TAG_VERIFICATIONS_DEPTH..total).map(verification)));
+        assert_eq!(verifications.latest(), Some(&verification(total - 1)));
+    }
 
-            fn message_bits(&self) -> &BitSlice {
-                &self.bits()[..$num_bits]
-            }
+    #[test]
+    fn owned() {
+        let gst = Gst::new(1234, 300);
+        let bytes = [0xa5; CED_AND_STATUS_BYTES];
+        let data = NavMessageData {
+            data: &BitSlice::from_slice(&bytes)[..CED_AND_STATUS_BITS],
+            authbits: 80,
+            #[cfg(feature = "unique-authbits")]
+            unique_authbits: 40,
+            adkd_authbits: [0, 0, 80],
+            gst,
+            latency: AuthLatency {
+                data_gst: gst,
+                authentication_gst: gst.add_subframes(1),
+            },
+            #[cfg(feature = "provenance")]
+            provenance: NavDataProvenance::new(
+                &TIMING_PARAMETERS_LAYOUT,
+                &[Some(WordSource {
+                    band: InavBand::E1B,
+                    gst,
+                    crc_checked: false,
+                })],
+            ),
+            nma_dont_use: true,
+        };
+        let owned = data.into_owned();
+        assert_eq!(owned.data(), data.data());
+        assert_eq!(owned.data().len(), CED_AND_STATUS_BITS);
+        assert_eq!(owned.authbits(), 80);
+        #[cfg(feature = "unique-authbits")]
+        assert_eq!(owned.unique_authbits(), 40);
+        assert_eq!(owned.adkd_authbits(Adkd::SlowMac), 80);
+        assert!(owned.is_slowmac_only());
+        assert_eq!(owned.gst(), gst);
+        assert_eq!(owned.latency(), data.latency());
+        #[cfg(feature = "provenance")]
+        assert_eq!(owned.provenance().words().count(), 1);
+        assert!(owned.nma_dont_use());
+        assert_eq!(owned.as_nav_message_data(), data);
+    }
 
-            fn add_authbits(&mut self, tag: &BitSlice) {
-                self.authbits = self.authbits.saturating_add(tag.len().try_into().unwrap());
+    // Adds authentication bits to the navigation data of a satellite in the
+    // current subframe.
+    fn authenticate(collect: &mut CollectNavMessage<SmallStorage>, svn: Svn, gst: Gst) {
+        let latency = AuthLatency {
+            data_gst: gst,
+            authentication_gst: gst.add_subframes(2),
+        };
+        let tag = bits![u8, Msb0; 0; 40];
+        for ced in collect.current_ced_as_mut() {
+            if ced.svn == Some(svn) {
+                ced.add_authbits(tag, latency, Adkd::InavCed, svn, gst);
             }
-
-            fn reset_authbits(&mut self) {
-                self.authbits = 0;
+        }
+        for timing in collect.current_timing_parameters_as_mut() {
+            if timing.svn == Some(svn) {
+                timing.add_authbits(tag, latency, Adkd::InavTiming, svn, gst);
             }
         }
+    }
 
-        impl Default for $s {
-            fn default() -> Self {
-                Self::new()
-            }
+    #[test]
+    fn e5b_only_ced_and_status() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8| {
+            let mut word = [word_type.wrapping_mul(0x35); 16];
+            word[0] = word_type << 2;
+            word
+        };
+        // Position of the words 1 to 5 in the E1-B and E5b-I nominal
+        // subframes. E5b-I transmits words 1, 3, 5 first and words 2, 4 at
+        // the end of the subframe, and its pages start 1 second earlier, so
+        // the page with word 1 starts in the previous subframe.
+        let e1b = [20, 0, 22, 2, 24];
+        let e5b = [-1, 19, 1, 21, 3];
+        let mut collect_e1b = CollectNavMessage::<SmallStorage>::new();
+        let mut collect_e5b = CollectNavMessage::<SmallStorage>::new();
+        for (j, (&t_e1b, &t_e5b)) in e1b.iter().zip(&e5b).enumerate() {
+            let word = word(j as u8 + 1);
+            collect_e1b.feed(&word, svn, gst.add_seconds(t_e1b), InavBand::E1B);
+            collect_e5b.feed(&word, svn, gst.add_seconds(t_e5b), InavBand::E5B);
         }
-    };
-}
+        authenticate(&mut collect_e1b, svn, gst);
+        authenticate(&mut collect_e5b, svn, gst);
 
-impl_common!(
-    CedAndStatus,
-    CED_AND_STATUS_BYTES,
-    CED_AND_STATUS_WORDS,
-    CED_AND_STATUS_BITS
-);
-impl_common!(
-    TimingParameters,
-    TIMING_PARAMETERS_BYTES,
-    TIMING_PARAMETERS_WORDS,
-    TIMING_PARAMETERS_BITS
-);
+        let data = collect_e5b.get_ced_and_status(svn).unwrap();
+        assert_eq!(data.gst(), gst);
+        assert_eq!(
+            data.data(),
+            collect_e1b.get_ced_and_status(svn).unwrap().data()
+        );
+        for (j, layout) in CED_AND_STATUS_LAYOUT.iter().enumerate() {
+            let word = word(j as u8 + 1);
+            assert_eq!(
+                &data.data()[layout.data_bits()],
+                layout.extract(BitSlice::from_slice(&word))
+            );
+        }
+    }
 
-impl CedAndStatus {
-    fn feed(&mut self, word: &InavWord, svn: Svn) {
-        match self.svn {
-            Some(s) if s == svn => (),
-            None => self.svn = Some(svn),
-            _ => {
-                self.reset();
-                self.svn = Some(svn);
+    #[test]
+    fn e5b_only_timing_parameters() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8, fill: u8| {
+            let mut word = [fill; 16];
+            word[0] = (word_type << 2) | (fill & 3);
+            word
+        };
+        let timing = |word6: &InavWord, word10: &InavWord| {
+            let mut bits = bitarr![u8, Msb0; 0; 141];
+            for (layout, word) in TIMING_PARAMETERS_LAYOUT.iter().zip([word6, word10]) {
+                bits[layout.data_bits()]
+                    .copy_from_bitslice(layout.extract(BitSlice::from_slice(word)));
             }
+            bits
         };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        // Word 10 is received in E5b-I in the subframe before the data is
+        // authenticated, and only word 6 is received in that subframe
+        let word10 = word(10, 0x5a);
+        collect.feed(&word10, svn, gst.add_seconds(7), InavBand::E5B);
+        let gst_next = gst.add_subframes(1);
+        let word6 = word(6, 0xc3);
+        collect.feed(&word6, svn, gst_next.add_seconds(23), InavBand::E5B);
+        authenticate(&mut collect, svn, gst_next);
 
-        let word = BitSlice::from_slice(word);
-        let word_type = word[..6].load_be::<u8>();
-        if (1..=5).contains(&word_type) {
-            self.log_word(word_type);
-        }
-        match word_type {
-            1 => self.copy_word(0..120, &word[6..126], 0),
-            2 => self.copy_word(120..240, &word[6..126], 1),
-            3 => self.copy_word(240..362, &word[6..128], 2),
-            4 => self.copy_word(362..482, &word[6..126], 3),
-            5 => self.copy_word(482..549, &word[6..73], 4),
-            _ => (),
-        };
-        self.log_age();
+        let data = collect.get_timing_parameters(svn).unwrap();
+        assert_eq!(data.gst(), gst_next);
+        assert_eq!(data.authbits(), 40);
+        assert_eq!(data.data(), &timing(&word6, &word10)[..141]);
     }
-}
 
-impl TimingParameters {
-    fn feed(&mut self, word: &InavWord, svn: Svn, band: InavBand) {
-        match self.svn {
-            Some(s) if s == svn => (),
-            None => self.svn = Some(svn),
-            _ => {
-                self.reset();
-                self.svn = Some(svn);
-            }
+    #[test]
+    fn e5b_word10_pairing() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 330);
+        let word = |word_type: u8, fill: u8| {
+            let mut word = [fill; 16];
+            word[0] = (word_type << 2) | (fill & 3);
+            word
         };
+        let layout10 = &TIMING_PARAMETERS_LAYOUT[1];
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        let word6 = word(6, 0);
+        // Word 10 is received in E1-B in this subframe, and in E5b-I in the
+        // next subframe, with different contents
+        let word10_e1b = word(10, 0x5a);
+        let word10_e5b = word(10, 0xa5);
+        collect.feed(&word6, svn, gst.add_seconds(4), InavBand::E1B);
+        collect.feed(&word10_e1b, svn, gst.add_seconds(8), InavBand::E1B);
+        let gst_next = gst.add_subframes(1);
+        collect.feed(&word6, svn, gst_next.add_seconds(4), InavBand::E1B);
+        collect.feed(&word10_e5b, svn, gst_next.add_seconds(7), InavBand::E5B);
+        authenticate(&mut collect, svn, gst_next);
+        let data = collect.get_timing_parameters(svn).unwrap();
+        assert_eq!(data.gst(), gst_next);
+        // The E1-B copy from the previous subframe is kept
+        assert_eq!(
+            &data.data()[layout10.data_bits()],
+            layout10.extract(BitSlice::from_slice(&word10_e1b))
+        );
 
-        let word = BitSlice::from_slice(word);
-        let word_type = word[..6].load_be::<u8>();
-        match (word_type, band) {
-            (6, InavBand::E1B) => {
-                self.log_word(word_type);
-                self.copy_word(0..99, &word[6..105], 0);
-            }
-            (10, InavBand::E1B) => {
-                self.log_word(word_type);
-                self.copy_word(99..141, &word[86..128], 1);
+        // If word 10 is not received in E1-B for a longer time, the E5b-I
+        // copy is used
+        let gst_later = gst.add_subframes(3);
+        collect.feed(
+            &word6,
+            svn,
+            gst.add_subframes(2).add_seconds(4),
+            InavBand::E1B,
+        );
+        collect.feed(&word6, svn, gst_later.add_seconds(4), InavBand::E1B);
+        collect.feed(&word10_e5b, svn, gst_later.add_seconds(7), InavBand::E5B);
+        authenticate(&mut collect, svn, gst_later);
+        let data = collect.get_timing_parameters(svn).unwrap();
+        assert_eq!(data.gst(), gst_later);
+        assert_eq!(
+            &data.data()[layout10.data_bits()],
+            layout10.extract(BitSlice::from_slice(&word10_e5b))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "provenance")]
+    fn provenance() {
+        let svn = Svn::try_from(1).unwrap();
+        let gst = Gst::new(1234, 300);
+        let word = |word_type: u8| {
+            let mut word = [0; 16];
+            word[0] = word_type << 2;
+            word
+        };
+        let mut collect = CollectNavMessage::<SmallStorage>::new();
+        for word_type in 1..=4 {
+            let page_gst = gst.add_seconds(2 * i32::from(word_type));
+            collect.feed(&word(word_type), svn, page_gst, InavBand::E1B);
+        }
+        collect.feed_crc_checked(&word(5), svn, gst.add_seconds(11), InavBand::E5B);
+        // Word 1 is received again in the next subframe
+        let gst_next = gst.add_subframes(1);
+        collect.feed(&word(1), svn, gst_next.add_seconds(3), InavBand::E5B);
+        let latency = AuthLatency {
+            data_gst: gst_next,
+            authentication_gst: gst_next.add_subframes(2),
+        };
+        for ced in collect.current_ced_as_mut() {
+            if ced.svn == Some(svn) {
+                ced.add_authbits(bits![u8, Msb0; 0; 40], latency, Adkd::InavCed, svn, gst);
             }
-            _ => (),
         }
-        self.log_age();
+
+        let data = collect.get_ced_and_status(svn).unwrap();
+        let provenance = data.provenance();
+        assert_eq!(provenance.words().count(), 5);
+        let word1 = provenance.word(InavWordType::Word1).unwrap();
+        assert_eq!(word1.gst(), gst_next.add_seconds(3));
+        assert_eq!(word1.band(), InavBand::E5B);
+        let word2 = provenance.word(InavWordType::Word2).unwrap();
+        assert_eq!(word2.gst(), gst.add_seconds(4));
+        assert_eq!(word2.band(), InavBand::E1B);
+        assert!(!word2.crc_checked());
+        assert!(provenance.word(InavWordType::Word5).unwrap().crc_checked());
+        assert!(provenance.word(InavWordType::Word6).is_none());
+        assert_eq!(provenance.latest_gst(), Some(gst_next.add_seconds(3)));
+        assert_eq!(word2.to_string(), "Word2 in E1-B at WN=1234 TOW=304");
+        assert_eq!(data.into_owned().provenance(), provenance);
     }
 }
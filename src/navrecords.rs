@@ -0,0 +1,248 @@
+//! Text records of authenticated navigation data.
+//!
+//! This module contains a writer of the authenticated navigation data in a
+//! simple line-based text format, inspired by RINEX. One record is written
+//! each time that the navigation data of a satellite is authenticated. The
+//! format is intended to compare the navigation data authenticated by OSNMA
+//! against IGS products and the output of other toolchains.
+//!
+//! # Format
+//!
+//! The file starts with the following header lines:
+//!
+//! ```text
+//! # OSNMA AUTHENTICATED NAVIGATION DATA VERSION 1
+//! # SVN KIND WN_DATA TOW_DATA WN_AUTH TOW_AUTH AUTHBITS NBITS DATA
+//! ```
+//!
+//! Lines starting with `#` are comments. Each of the other lines is a record
+//! with the following fields separated by spaces:
+//!
+//! 1. SVN of the satellite, as in `E12`.
+//! 2. Kind of navigation data. `CED` for CED and health status data (ADKD=0
+//!    and 12), and `TIM` for timing parameters (ADKD=4).
+//! 3. Week number and time of week of the GST of the navigation data (see
+//!    [`NavMessageData::gst`]).
+//! 4. Week number and time of week of the GST at which the navigation data was
+//!    authenticated (see [`AuthLatency::authentication_gst`]).
+//! 5. Number of authentication bits (see [`NavMessageData::authbits`]).
+//! 6. Number of bits of navigation data.
+//! 7. Navigation data in hexadecimal (see [`NavMessageData::data`]). The bits
+//!    are given MSB first. The last byte is padded with zeros.
+//!
+//! The numeric fields are right-aligned to a fixed width, so that the columns
+//! of the records are aligned. For example:
+//!
+//! ```text
+//! E12 TIM 1177 175740 1177 175800    80  141 0123456789abcdef0123456789abcdef0120
+//! ```
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::navrecords::NavRecordWriter;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::Osnma;
+//!
+//! fn export(osnma: &mut Osnma<FullStorage>) -> std::io::Result<()> {
+//!     let file = std::fs::File::create("navdata.txt")?;
+//!     let mut records = NavRecordWriter::new(std::io::BufWriter::new(file))?;
+//!     // Feed data into the OSNMA black box
+//!     // ...
+//!     // Write the records of the data authenticated since the last call
+//!     records.update(osnma)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::navmessage::{AuthLatency, NavDataKind, NavMessageData};
+use crate::storage::Storage;
+use crate::types::{BitSlice, NUM_SVNS};
+use crate::verifier::Verifier;
+use crate::{Gst, Osnma, Svn};
+use bitvec::field::BitField;
+use core::fmt;
+use std::io::Write;
+
+/// Version of the record format.
+pub const NAV_RECORDS_VERSION: u8 = 1;
+
+/// Record of authenticated navigation data.
+///
+/// The [`Display`](fmt::Display) implementation of this struct formats the
+/// record as described in the [module documentation](crate::navrecords),
+/// without a line terminator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NavRecord<'a> {
+    svn: Svn,
+    kind: NavDataKind,
+    gst: Gst,
+    authentication_gst: Gst,
+    authbits: u16,
+    data: &'a BitSlice,
+}
+
+impl<'a> NavRecord<'a> {
+    /// Constructs the record of the authenticated navigation data `data` of
+    /// kind `kind` for the satellite `svn`.
+    pub fn new(svn: Svn, kind: NavDataKind, data: &NavMessageData<'a>) -> NavRecord<'a> {
+        NavRecord {
+            svn,
+            kind,
+            gst: data.gst(),
+            authentication_gst: data.latency().authentication_gst(),
+            authbits: data.authbits(),
+            data: data.data(),
+        }
+    }
+}
+
+impl fmt::Display for NavRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            NavDataKind::CedAndStatus => "CED",
+            NavDataKind::TimingParameters => "TIM",
+        };
+        write!(
+            f,
+            "{} {} {:4} {:6} {:4} {:6} {:5} {:4} ",
+            self.svn,
+            kind,
+            self.gst.wn(),
+            self.gst.tow(),
+            self.authentication_gst.wn(),
+            self.authentication_gst.tow(),
+            self.authbits,
+            self.data.len()
+        )?;
+        for chunk in self.data.chunks(8) {
+            let byte = chunk.load_be::<u8>() << (8 - chunk.len());
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writer of records of authenticated navigation data.
+///
+/// This wraps around a [`Write`] `W` and writes records with the format
+/// described in the [module documentation](crate::navrecords).
+#[derive(Debug, Clone)]
+pub struct NavRecordWriter<W> {
+    write: W,
+    // Authentication latency of the last record written for each satellite and
+    // each of the kinds of navigation data CedAndStatus and TimingParameters.
+    last: [[Option<AuthLatency>; 2]; NUM_SVNS],
+}
+
+impl<W: Write> NavRecordWriter<W> {
+    /// Constructs a new writer using a [`Write`] `write`.
+    ///
+    /// The header lines are written to `write`.
+    pub fn new(mut write: W) -> std::io::Result<NavRecordWriter<W>> {
+        writeln!(
+            write,
+            "# OSNMA AUTHENTICATED NAVIGATION DATA VERSION {NAV_RECORDS_VERSION}"
+        )?;
+        writeln!(
+            write,
+            "# SVN KIND WN_DATA TOW_DATA WN_AUTH TOW_AUTH AUTHBITS NBITS DATA"
+        )?;
+        Ok(NavRecordWriter {
+            write,
+            last: [[None; 2]; NUM_SVNS],
+        })
+    }
+
+    /// Writes a record of authenticated navigation data.
+    ///
+    /// The record is written regardless of whether a record for the same
+    /// authentication has already been written.
+    pub fn write_record(
+        &mut self,
+        svn: Svn,
+        kind: NavDataKind,
+        data: &NavMessageData,
+    ) -> std::io::Result<()> {
+        writeln!(self.write, "{}", NavRecord::new(svn, kind, data))?;
+        self.last[svn.index()][kind as usize] = Some(data.latency());
+        Ok(())
+    }
+
+    /// Writes the records of the navigation data authenticated by an OSNMA
+    /// black box.
+    ///
+    /// For each satellite, the most recent authenticated CED and health status
+    /// data and timing parameters are obtained from `osnma`, and a record is
+    /// written if they have been authenticated since the last record written
+    /// for that satellite. This function should be called periodically, such
+    /// as after feeding each subframe into `osnma`, so that one record is
+    /// written per authentication.
+    ///
+    /// Returns the number of records written.
+    pub fn update<S: Storage, V: Verifier>(
+        &mut self,
+        osnma: &Osnma<S, V>,
+    ) -> std::io::Result<usize> {
+        let mut written = 0;
+        for svn in Svn::iter() {
+            let datasets = [
+                (NavDataKind::CedAndStatus, osnma.get_ced_and_status(svn)),
+                (
+                    NavDataKind::TimingParameters,
+                    osnma.get_timing_parameters(svn),
+                ),
+            ];
+            for (kind, data) in datasets {
+                let Some(data) = data else {
+                    continue;
+                };
+                if self.last[svn.index()][kind as usize] != Some(data.latency()) {
+                    self.write_record(svn, kind, &data)?;
+                    written += 1;
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    /// Flushes the underlying [`Write`].
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.write.flush()
+    }
+
+    /// Returns the underlying [`Write`], consuming the writer.
+    pub fn into_inner(self) -> W {
+        self.write
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitvec::prelude::*;
+
+    #[test]
+    fn record() {
+        let data = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xff];
+        let record = NavRecord {
+            svn: Svn::try_from(12).unwrap(),
+            kind: NavDataKind::TimingParameters,
+            gst: Gst::new(1177, 175740),
+            authentication_gst: Gst::new(1177, 175800),
+            authbits: 80,
+            data: &data.view_bits::<Msb0>()[..67],
+        };
+        assert_eq!(
+            record.to_string(),
+            "E12 TIM 1177 175740 1177 175800    80   67 0123456789abcdefe0"
+        );
+
+        let write = NavRecordWriter::new(Vec::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(write.into_inner()).unwrap(),
+            "# OSNMA AUTHENTICATED NAVIGATION DATA VERSION 1\n\
+             # SVN KIND WN_DATA TOW_DATA WN_AUTH TOW_AUTH AUTHBITS NBITS DATA\n"
+        );
+    }
+}
@@ -1,19 +1,62 @@
+use crate::audit::{Auditor, PendingAudit};
+use crate::authstate::AuthStateTracker;
 use crate::bitfields::{
-    ChainAndPubkeyStatus, DsmHeader, DsmKroot, DsmPkr, DsmType, Mack, NewPublicKeyType, NmaHeader,
-    NmaStatus,
+    Adkd, AdkdCheckPolicy, ChainAndPubkeyStatus, DsmHeader, DsmKroot, DsmPkr, DsmType, IcdVersion,
+    Mack, MackValidationError, NewPublicKeyType, NmaHeader, NmaStatus,
 };
+use crate::diagnostics::{Diagnostic, DiagnosticHook, DiagnosticKind};
+use crate::diff::Difference;
 use crate::dsm::{CollectDsm, Dsm};
+use crate::events::{AuthStatePolicy, Event, EventHook, Notifier};
+#[cfg(feature = "galmon")]
+use crate::galmon::{navmon::NavMonMessage, GalmonState};
+use crate::gstguard::{GstGuardPolicy, GstRejection, GstRejectionStats};
+use crate::health::SatelliteHealth;
+use crate::inav::InavWordType;
 use crate::mack::MackStorage;
 use crate::merkle_tree::MerkleTree;
-use crate::navmessage::{CollectNavMessage, NavMessageData};
-use crate::storage::StaticStorage;
-use crate::subframe::CollectSubframe;
-use crate::tesla::Key;
-use crate::types::{HkrootMessage, InavBand, InavWord, MackMessage, OsnmaDataMessage};
+#[cfg(feature = "nav-diagnostics")]
+use crate::navmessage::AnomalyTracker;
+#[cfg(feature = "cross-auth")]
+use crate::navmessage::CrossAuthExpectation;
+#[cfg(feature = "reduced-ced")]
+use crate::navmessage::ReducedCed;
+use crate::navmessage::{
+    AdkdFilter, AuthSchedule, CollectNavMessage, ConstellationTags, ConstellationTiming, CopPolicy,
+    MismatchPolicy, NavDataKind, NavMessageData, ReservedAdkdStats, TagResults, TagVerifications,
+    WordSource,
+};
+use crate::persist::{StateError, STATE_BYTES};
+use crate::status::OsnmaStatus;
+use crate::storage::Storage;
+use crate::subframe::{CollectSubframe, RawSubframe};
+use crate::tesla::{
+    Chain, ChainError, Key, KrootValidationError, ValidationError, MAX_KEY_DERIVATIONS,
+};
+use crate::timing::{ClockCheck, GstUtcParameters};
+use crate::types::{
+    HkrootMessage, InavBand, InavPage, InavWord, MackMessage, OsnmaDataMessage, NUM_SVNS,
+};
 use crate::validation::{NotValidated, Validated};
+use crate::verifier::{
+    ImmediateVerifier, KeyRequest, KeyResult, KrootFailureTracker, KrootRequest, KrootResult,
+    Verifier,
+};
+use crate::watchdog::Watchdog;
 use crate::{Gst, MerkleTreeNode, PublicKey, Svn};
 
 use core::cmp::Ordering;
+use core::fmt;
+
+// Number of consecutive MACK messages with reserved ADKD tags that trigger a
+// DiagnosticKind::ReservedAdkd diagnostic. This is approximately 5 minutes
+// when tracking 10 satellites.
+const RESERVED_ADKD_CONSECUTIVE_MACKS: u32 = 100;
+
+// Maximum number of subframes by which the start of applicability of a KROOT
+// for the chain in force can be later than the subframe in which the
+// DSM-KROOT was received.
+const KROOT_APPLICABILITY_TOLERANCE_SUBFRAMES: i32 = 1;
 
 /// OSNMA "black box" processing.
 ///
@@ -67,32 +110,473 @@ use core::cmp::Ordering;
 /// # Storage size
 ///
 /// The size of the internal storage used to hold navigation data and MACK
-/// messages is defined by the [`StaticStorage`] type parameter `S`. See the
+/// messages is defined by the [`Storage`] type parameter `S`. See the
 /// [storage](crate::storage) module for a description of how the storage size
 /// is defined.
+///
+/// # Verifier
+///
+/// The verification of the DSM-KROOT ECDSA signature is requested through the
+/// [`Verifier`] type parameter `V`. By default, [`ImmediateVerifier`] is used,
+/// which does this verification synchronously. See the
+/// [verifier](crate::verifier) module for how to run the verification on
+/// another task.
 #[derive(Debug, Clone)]
-pub struct Osnma<S: StaticStorage> {
+pub struct Osnma<S: Storage, V: Verifier = ImmediateVerifier> {
     subframe: CollectSubframe,
-    data: OsnmaDsm<S>,
+    subframe_hook: Option<SubframeHook>,
+    data: OsnmaDsm<S, V>,
+    #[cfg(feature = "galmon")]
+    galmon: GalmonState,
+}
+
+/// Error produced when importing a TESLA key received from a peer.
+///
+/// See [`Osnma::import_tesla_key`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum PeerKeyError {
+    /// The GST of the key is later than the current GST.
+    FutureKey,
+    /// The GST of the key is so much earlier than the current GST that the
+    /// key cannot be used to validate the keys currently broadcast.
+    ///
+    /// The maximum age of the key is given by
+    /// [`MAX_KEY_DERIVATIONS`](crate::tesla::MAX_KEY_DERIVATIONS).
+    StaleKey,
+    /// The key is older than the TESLA key of the same chain that is already
+    /// stored.
+    OldKey,
+    /// The key does not belong to the chain in force.
+    WrongChain,
+    /// The key could not be validated with the TESLA key of the same chain
+    /// that is already stored.
+    Validation(ValidationError),
+}
+
+impl fmt::Display for PeerKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerKeyError::FutureKey => "key GST is in the future".fmt(f),
+            PeerKeyError::StaleKey => "key GST is too old".fmt(f),
+            PeerKeyError::OldKey => "key is older than the stored key".fmt(f),
+            PeerKeyError::WrongChain => "key does not belong to the chain in force".fmt(f),
+            PeerKeyError::Validation(e) => write!(f, "key validation failed ({e})"),
+        }
+    }
+}
+
+impl From<ValidationError> for PeerKeyError {
+    fn from(value: ValidationError) -> PeerKeyError {
+        PeerKeyError::Validation(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PeerKeyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PeerKeyError::Validation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Error produced when feeding data into the OSNMA black box.
+///
+/// See [`Osnma::try_feed_inav`] and [`Osnma::try_feed_osnma`]. Some of these
+/// errors indicate that the data has been discarded, while others indicate a
+/// problem that has been found when storing the data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FeedError {
+    /// The satellite has been disabled with [`Osnma::set_svn_enabled`].
+    ///
+    /// The data has been discarded.
+    SvnDisabled,
+    /// The GST of the data has been rejected by the GST guard.
+    ///
+    /// The data has been discarded. See [`Osnma::set_gst_guard`].
+    GstRejected(GstRejection),
+    /// The INAV word is a dummy message (word type 63).
+    ///
+    /// The word has been discarded, since it does not contain any data.
+    DummyWord,
+    /// The OSNMA data message is all zeros.
+    ///
+    /// The data has been discarded. This happens when the satellite does not
+    /// transmit OSNMA data.
+    NoOsnmaData,
+    /// The INAV word has been stored by evicting the navigation data of
+    /// another satellite.
+    ///
+    /// This happens when the storage does not have room for all the
+    /// satellites in view.
+    StorageEviction {
+        /// SVN of the satellite whose navigation data has been evicted.
+        evicted: Svn,
+    },
+    /// The INAV word differs from the navigation data that has already been
+    /// authenticated.
+    ///
+    /// What happens to the word depends on the
+    /// [`MismatchPolicy`](crate::navmessage::MismatchPolicy).
+    WordMismatch {
+        /// Word type of the INAV word.
+        word_type: u8,
+    },
+    /// The OSNMA data message starts a new subframe, but the OSNMA data of the
+    /// previous subframe was not received completely for a satellite.
+    ///
+    /// The OSNMA data message has been stored, and the incomplete data of
+    /// the previous subframe has been discarded. If the data of several
+    /// satellites was incomplete, only the first of them is given.
+    IncompleteSubframe {
+        /// SVN of the satellite whose subframe was incomplete.
+        svn: Svn,
+        /// GST at the start of the incomplete subframe.
+        gst: Gst,
+        /// Number of pages with OSNMA data received in the subframe.
+        received_pages: u8,
+    },
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::SvnDisabled => "satellite is disabled".fmt(f),
+            FeedError::GstRejected(rejection) => write!(f, "GST rejected ({rejection})"),
+            FeedError::DummyWord => "INAV dummy message".fmt(f),
+            FeedError::NoOsnmaData => "no OSNMA data".fmt(f),
+            FeedError::StorageEviction { evicted } => {
+                write!(f, "navigation data of {evicted} evicted from storage")
+            }
+            FeedError::WordMismatch { word_type } => write!(
+                f,
+                "INAV word type {word_type} differs from authenticated data"
+            ),
+            FeedError::IncompleteSubframe {
+                svn,
+                gst,
+                received_pages,
+            } => write!(
+                f,
+                "incomplete subframe for {svn} at {gst} \
+                 ({received_pages} of 15 pages with OSNMA data received)"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FeedError {}
+
+/// Hook that receives the raw OSNMA data of each subframe.
+///
+/// See [`Osnma::set_subframe_hook`].
+pub type SubframeHook = fn(RawSubframe);
+
+/// End-of-chain transition between TESLA chains.
+///
+/// This describes a renewal of the TESLA chain, which is announced by setting
+/// the CPKS field of the NMA header to end-of-chain (EOC), as described in
+/// Section 5.4.2 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+/// During the EOC period, the DSM-KROOT of the next chain is broadcast, and
+/// the time of its KROOT gives the GST at which the next chain comes into
+/// force. It is obtained with [`Osnma::chain_transition`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ChainTransition {
+    cid: u8,
+    next_cid: Option<u8>,
+    detected: Gst,
+    switch_gst: Option<Gst>,
+    both_chains_tracked: bool,
+}
+
+impl ChainTransition {
+    /// Gives the chain ID (CID) of the chain that is ending.
+    pub fn cid(&self) -> u8 {
+        self.cid
+    }
+
+    /// Gives the chain ID of the next chain.
+    ///
+    /// This is `None` if the DSM-KROOT of the next chain has not been verified
+    /// yet.
+    pub fn next_cid(&self) -> Option<u8> {
+        self.next_cid
+    }
+
+    /// Gives the GST of the subframe in which the end of chain was first
+    /// detected.
+    pub fn detected_gst(&self) -> Gst {
+        self.detected
+    }
+
+    /// Gives the GST at which the next chain comes into force.
+    ///
+    /// This is the GST of the first TESLA key of the next chain, which
+    /// follows its KROOT. It is `None` if the DSM-KROOT of the next chain has
+    /// not been verified yet.
+    pub fn switch_gst(&self) -> Option<Gst> {
+        self.switch_gst
+    }
+
+    /// Gives the number of seconds from `gst` until the next chain comes into
+    /// force.
+    ///
+    /// The value is negative if the switch GST is before `gst`. This returns
+    /// `None` if the switch GST is not known yet.
+    pub fn seconds_to_switch(&self, gst: Gst) -> Option<i64> {
+        const SECS_IN_WEEK: i64 = 604800;
+        self.switch_gst.map(|switch| {
+            (i64::from(switch.wn()) - i64::from(gst.wn())) * SECS_IN_WEEK + i64::from(switch.tow())
+                - i64::from(gst.tow())
+        })
+    }
+
+    /// Returns `true` if TESLA keys of both the ending chain and the next
+    /// chain are stored.
+    ///
+    /// This confirms that the OSNMA black box is ready to continue
+    /// authenticating data after the chain switch.
+    pub fn both_chains_tracked(&self) -> bool {
+        self.both_chains_tracked
+    }
+}
+
+/// Formats the transition as in
+/// `"end of chain CID=1 detected at WN=1300 TOW=0; switch to CID=2 at WN=1300 TOW=86400"`.
+impl fmt::Display for ChainTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "end of chain CID={} detected at {}",
+            self.cid, self.detected
+        )?;
+        match (self.next_cid, self.switch_gst) {
+            (Some(next_cid), Some(switch)) => write!(f, "; switch to CID={next_cid} at {switch}"),
+            _ => "; next chain unknown".fmt(f),
+        }
+    }
+}
+
+/// TESLA chain pre-positioned for a chain switch.
+///
+/// When the DSM-KROOT of a chain that is not in force yet is verified (which
+/// happens during an end-of-chain transition), its KROOT and chain parameters
+/// are stored in advance, so that the first TESLA key of the chain can be
+/// validated as soon as it is broadcast, without having to wait for the
+/// DSM-KROOT to be received again after the chain switch. It is obtained with
+/// [`Osnma::prepositioned_chain`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PrepositionedChain {
+    chain: Chain,
+    kroot_gst: Gst,
+    first_key_gst: Option<Gst>,
+}
+
+impl PrepositionedChain {
+    /// Gives the parameters of the chain.
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Gives the chain ID (CID) of the chain.
+    pub fn cid(&self) -> u8 {
+        self.chain.chain_id()
+    }
+
+    /// Gives the GST of the KROOT of the chain.
+    pub fn kroot_gst(&self) -> Gst {
+        self.kroot_gst
+    }
+
+    /// Gives the GST at which the chain comes into force.
+    ///
+    /// This is the GST of the first TESLA key of the chain, which follows its
+    /// KROOT.
+    pub fn start_applicability(&self) -> Gst {
+        self.kroot_gst.wrapping_add_seconds(30)
+    }
+
+    /// Returns `true` if the chain is in force at `gst`.
+    pub fn is_active(&self, gst: Gst) -> bool {
+        !gst.is_before(self.start_applicability())
+    }
+
+    /// Gives the GST of the first TESLA key of the chain that has been
+    /// validated using the pre-positioned KROOT.
+    ///
+    /// This is `None` if no TESLA keys of the chain have been validated yet.
+    pub fn first_key_gst(&self) -> Option<Gst> {
+        self.first_key_gst
+    }
+}
+
+/// Formats the chain as in
+/// `"CID=2 pre-positioned; in force at WN=1300 TOW=86400; first key WN=1300 TOW=86400"`.
+impl fmt::Display for PrepositionedChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CID={} pre-positioned; in force at {}",
+            self.cid(),
+            self.start_applicability()
+        )?;
+        match self.first_key_gst {
+            Some(gst) => write!(f, "; first key {gst}"),
+            None => "; no keys validated".fmt(f),
+        }
+    }
+}
+
+/// State of the NMA header.
+///
+/// This gives the fields of the most recent NMA header received, which
+/// indicate the NMA status (NMAS), the chain in force (CID) and the chain and
+/// public key status (CPKS). It is obtained with [`Osnma::nma_state`].
+///
+/// The NMA header is broadcast in every subframe, but it is only validated
+/// when it is used to verify the signature of a DSM-KROOT. The NMA header of
+/// the most recent subframe is considered validated only if it is equal to the
+/// NMA header of the last verified DSM-KROOT.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NmaState {
+    header: u8,
+    nma_status: NmaStatus,
+    cid: u8,
+    cpks: ChainAndPubkeyStatus,
+    gst: Gst,
+    validated: bool,
+}
+
+impl NmaState {
+    fn new<V>(nma_header: NmaHeader<V>, icd: IcdVersion, gst: Gst, validated: bool) -> NmaState {
+        NmaState {
+            header: nma_header.data(),
+            nma_status: nma_header.nma_status(),
+            cid: nma_header.chain_id(),
+            cpks: nma_header.chain_and_pubkey_status_icd(icd),
+            gst,
+            validated,
+        }
+    }
+
+    /// Gives the NMA status (NMAS).
+    pub fn nma_status(&self) -> NmaStatus {
+        self.nma_status
+    }
+
+    /// Gives the chain ID (CID).
+    pub fn chain_id(&self) -> u8 {
+        self.cid
+    }
+
+    /// Gives the chain and public key status (CPKS).
+    pub fn chain_and_pubkey_status(&self) -> ChainAndPubkeyStatus {
+        self.cpks
+    }
+
+    /// Gives the GST of the subframe in which the NMA header was received.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Returns `true` if the NMA header has been validated with the signature
+    /// of a DSM-KROOT.
+    ///
+    /// This is the case if the NMA header is equal to the NMA header of the
+    /// last verified DSM-KROOT.
+    pub fn is_validated(&self) -> bool {
+        self.validated
+    }
+
+    /// Returns `true` if the NMA status indicates that the authenticated
+    /// navigation data should not be used.
+    ///
+    /// This is the case when the NMA status is "don't use", and also when it
+    /// has the reserved value, which is handled in the same way.
+    pub fn is_dont_use(&self) -> bool {
+        matches!(self.nma_status, NmaStatus::DontUse | NmaStatus::Reserved)
+    }
+}
+
+/// Formats the NMA state as in
+/// `"NMAS=2 (operational) CID=1 CPKS=1 (nominal) at WN=1176 TOW=121050 (validated)"`.
+impl fmt::Display for NmaState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} CID={} {} at {} ({})",
+            self.nma_status,
+            self.cid,
+            self.cpks,
+            self.gst,
+            if self.validated {
+                "validated"
+            } else {
+                "not validated"
+            }
+        )
+    }
+}
+
+/// Policy for authenticated navigation data when the NMA status is "don't use".
+///
+/// When the NMA status changes to "don't use", the navigation data that was
+/// authenticated previously is still stored in the OSNMA black box. This
+/// policy defines what the getters such as [`Osnma::get_ced_and_status`],
+/// [`Osnma::get_timing_parameters`], [`Osnma::get_constellation_timing`],
+/// [`Osnma::authenticated_data`] and [`Osnma::get_health`] do with this data
+/// while the most recent NMA header indicates "don't use" (see
+/// [`NmaState::is_dont_use`]). The policy is set with
+/// [`Osnma::set_nma_dont_use_policy`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum NmaDontUsePolicy {
+    /// The authenticated navigation data is returned as usual.
+    ///
+    /// This is the default policy.
+    #[default]
+    Ignore,
+    /// The authenticated navigation data is returned with the
+    /// [`NavMessageData::nma_dont_use`] flag set (or
+    /// [`SatelliteHealth::nma_dont_use`] for [`Osnma::get_health`]).
+    Flag,
+    /// No navigation data is returned.
+    Withhold,
 }
 
 // These structures exist only in order to avoid double mutable
 // borrows of Osnma because we take references from CollectSubframe
 // and CollectDsm
 #[derive(Debug, Clone)]
-struct OsnmaDsm<S: StaticStorage> {
+struct OsnmaDsm<S: Storage, V: Verifier> {
     dsm: CollectDsm,
-    data: OsnmaData<S>,
+    data: OsnmaData<S, V>,
 }
 
 #[derive(Debug, Clone)]
-struct OsnmaData<S: StaticStorage> {
+struct OsnmaData<S: Storage, V: Verifier> {
     navmessage: CollectNavMessage<S>,
     mack: MackStorage<S>,
     merkle_tree: Option<MerkleTree>,
     pubkey: PubkeyStore,
     key: KeyStore,
     only_slowmac: bool,
+    svn_enabled: [bool; NUM_SVNS],
+    verifier: V,
+    kroot_failures: KrootFailureTracker,
+    notifier: Notifier<S::EventQueueDepth>,
+    adkd_check_policy: AdkdCheckPolicy,
+    icd_version: IcdVersion,
+    auditor: Auditor,
+    watchdog: Watchdog,
+    auth_states: AuthStateTracker,
+    tag_events: bool,
+    nma_state: Option<NmaState>,
+    nma_dont_use_policy: NmaDontUsePolicy,
+    // GST of the last MACK message received from each SVN.
+    last_mack: [Option<Gst>; NUM_SVNS],
 }
 
 #[derive(Debug, Clone)]
@@ -103,11 +587,23 @@ struct PubkeyStore {
 
 // The KeyStore can hold up to two keys: the TESLA key for the current chain in
 // force, and an additional KROOT for a chain that will become in force in the
-// future.
+// future. The chain of this KROOT is pending until the GST reaches its start
+// of applicability, at which point it becomes the chain in force. The TESLA
+// keys of both chains are validated and stored in parallel, using the CID of
+// the subframe of each MACK message to select its chain, so that the tags of
+// both chains can be processed around the chain switch.
 #[derive(Debug, Clone)]
 struct KeyStore {
     keys: [Option<Key<Validated>>; 2],
     chain_in_force: Option<ChainInForce>,
+    // Chain whose KROOT has been verified, but which is not applicable yet.
+    pending: Option<PendingChain>,
+    // Chain whose KROOT was verified before it became applicable. Unlike
+    // pending, this is kept after the chain comes into force, until the chain
+    // switch is completed, in order to report the validation of its first
+    // TESLA key.
+    prepositioned: Option<PrepositionedChain>,
+    transition: Option<ChainTransition>,
 }
 
 #[derive(Debug, Clone)]
@@ -118,98 +614,1439 @@ struct ChainInForce {
     start_applicability: Option<Gst>,
 }
 
-impl<S: StaticStorage> Osnma<S> {
+#[derive(Debug, Clone, Copy)]
+struct PendingChain {
+    cid: u8,
+    // GST of the first TESLA key of the chain.
+    start_applicability: Gst,
+}
+
+impl<S: Storage> Osnma<S> {
     fn new(
         merkle_tree_root: Option<MerkleTreeNode>,
-        pubkey: Option<PublicKey<Validated>>,
+        pubkey: PubkeyStore,
         only_slowmac: bool,
     ) -> Osnma<S> {
+        if only_slowmac && !cfg!(feature = "slowmac") {
+            log::warn!(
+                "only_slowmac set but Slow MAC support is disabled; \
+                 no tags will be processed"
+            );
+        }
         Osnma {
             subframe: CollectSubframe::new(),
+            subframe_hook: None,
             data: OsnmaDsm {
                 dsm: CollectDsm::new(),
                 data: OsnmaData {
                     navmessage: CollectNavMessage::new(),
                     mack: MackStorage::new(),
                     merkle_tree: merkle_tree_root.map(MerkleTree::new),
-                    pubkey: pubkey
-                        .map_or_else(PubkeyStore::empty, PubkeyStore::from_current_pubkey),
+                    pubkey,
                     key: KeyStore::empty(),
                     only_slowmac,
+                    svn_enabled: [true; NUM_SVNS],
+                    verifier: ImmediateVerifier::new(),
+                    kroot_failures: KrootFailureTracker::new(),
+                    notifier: Notifier::new(),
+                    adkd_check_policy: AdkdCheckPolicy::default(),
+                    icd_version: IcdVersion::default(),
+                    auditor: Auditor::new(),
+                    watchdog: Watchdog::new(),
+                    auth_states: AuthStateTracker::new(),
+                    tag_events: false,
+                    nma_state: None,
+                    nma_dont_use_policy: NmaDontUsePolicy::default(),
+                    last_mack: [None; NUM_SVNS],
                 },
             },
+            #[cfg(feature = "galmon")]
+            galmon: GalmonState::new(),
+        }
+    }
+
+    /// Constructs a new OSNMA black box using the Merkle tree root.
+    ///
+    /// An optional ECDSA public key can be passed in addition to the Merkle
+    /// tree root. If the ECDSA public key is not passed, the OSNMA black box
+    /// will need to obtain the public key from a DSM-PKR message. These
+    /// messages are broadcast only every 6 hours.
+    ///
+    /// If `only_slowmac` is `true`, only ADKD=12 (Slow MAC) will be processed.
+    /// This should be used by receivers which have a larger time uncertainty.
+    /// (See Annex 3 in the
+    /// [OSNMA Receiver Guidelines](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_for_Test_Phase_v1.0.pdf)).
+    /// Slow MAC is only processed if the `slowmac` feature is enabled.
+    pub fn from_merkle_tree(
+        merkle_tree_root: MerkleTreeNode,
+        pubkey: Option<PublicKey<Validated>>,
+        only_slowmac: bool,
+    ) -> Osnma<S> {
+        let mut builder = OsnmaBuilder::new()
+            .with_merkle_tree(merkle_tree_root)
+            .only_slowmac(only_slowmac);
+        if let Some(pubkey) = pubkey {
+            builder = builder.with_pubkey(pubkey);
+        }
+        builder.build()
+    }
+
+    /// Constructs a new OSNMA black box using only an ECDSA public key.
+    ///
+    /// This function is similar to [`Osnma::from_merkle_tree`], but the Merkle
+    /// tree root is not loaded. Therefore, DSM-PKR verification will not be
+    /// done, and only the provided ECDSA public key will be used.
+    ///
+    /// The OSNMA black box will hold the public key `pubkey` and use it to
+    /// try to authenticate the TESLA root key. The public key cannot be changed
+    /// after construction.
+    ///
+    /// If `only_slowmac` is `true`, only ADKD=12 (Slow MAC) will be processed.
+    /// This should be used by receivers which have a larger time uncertainty.
+    /// (See Annex 3 in the
+    /// [OSNMA Receiver Guidelines](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_for_Test_Phase_v1.0.pdf)).
+    /// Slow MAC is only processed if the `slowmac` feature is enabled.
+    pub fn from_pubkey(pubkey: PublicKey<Validated>, only_slowmac: bool) -> Osnma<S> {
+        OsnmaBuilder::new()
+            .with_pubkey(pubkey)
+            .only_slowmac(only_slowmac)
+            .build()
+    }
+}
+
+/// Builder of [`Osnma`] black boxes.
+///
+/// This gathers the configuration of the black box before constructing it.
+/// [`Osnma::from_merkle_tree`] and [`Osnma::from_pubkey`] are shorthands for
+/// the most common configurations. The options that are not set keep the same
+/// defaults as in the corresponding setters of [`Osnma`].
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::gstguard::GstGuardPolicy;
+/// use galileo_osnma::storage::FullStorage;
+/// use galileo_osnma::{OsnmaBuilder, PublicKey};
+/// use p256::ecdsa::VerifyingKey;
+///
+/// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+/// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+/// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+/// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+/// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+/// let osnma = OsnmaBuilder::new()
+///     .with_pubkey(pubkey)
+///     .with_min_authbits(80)
+///     .with_gst_guard(GstGuardPolicy::DEFAULT)
+///     .build::<FullStorage>();
+/// assert_eq!(osnma.min_authbits(), 80);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OsnmaBuilder {
+    merkle_tree_root: Option<MerkleTreeNode>,
+    pubkey: Option<PublicKey<Validated>>,
+    next_pubkey: Option<PublicKey<Validated>>,
+    only_slowmac: bool,
+    tesla_key: Option<Key<Validated>>,
+    min_authbits: Option<u16>,
+    gst_guard: Option<GstGuardPolicy>,
+    event_hook: Option<EventHook>,
+    diagnostic_hook: Option<DiagnosticHook>,
+}
+
+impl OsnmaBuilder {
+    /// Constructs a new builder with the default settings.
+    ///
+    /// A Merkle tree root, a public key or a TESLA key must be added before
+    /// building the black box.
+    pub fn new() -> OsnmaBuilder {
+        OsnmaBuilder::default()
+    }
+
+    /// Sets the Merkle tree root.
+    ///
+    /// The Merkle tree is used to validate the public keys transmitted in the
+    /// DSM-PKR messages. See [`Osnma::from_merkle_tree`].
+    pub fn with_merkle_tree(mut self, merkle_tree_root: MerkleTreeNode) -> OsnmaBuilder {
+        self.merkle_tree_root = Some(merkle_tree_root);
+        self
+    }
+
+    /// Adds a public key.
+    ///
+    /// This can be called several times to load the public key in force
+    /// together with the next public key, so that the DSM-KROOT can be
+    /// verified across a public key renewal. The key with the smallest PKID
+    /// is used as the current public key, and the key with the largest PKID
+    /// as the next public key. Other keys are discarded. If two keys have the
+    /// same PKID, the one added later is used.
+    pub fn with_pubkey(mut self, pubkey: PublicKey<Validated>) -> OsnmaBuilder {
+        let pkid = pubkey.public_key_id();
+        match self.pubkey.as_ref().map(|k| k.public_key_id()) {
+            None => self.pubkey = Some(pubkey),
+            Some(current) if pkid < current => {
+                let previous = self.pubkey.replace(pubkey);
+                if self.next_pubkey.is_none() {
+                    self.next_pubkey = previous;
+                }
+            }
+            Some(current) if pkid == current => self.pubkey = Some(pubkey),
+            Some(_) => {
+                if self
+                    .next_pubkey
+                    .as_ref()
+                    .map_or(true, |k| pkid >= k.public_key_id())
+                {
+                    self.next_pubkey = Some(pubkey);
+                }
+            }
+        }
+        self
+    }
+
+    /// Only processes Slow MAC (ADKD=12).
+    ///
+    /// See [`Osnma::from_merkle_tree`].
+    pub fn only_slowmac(mut self, only_slowmac: bool) -> OsnmaBuilder {
+        self.only_slowmac = only_slowmac;
+        self
+    }
+
+    /// Sets a TESLA key that has been validated externally.
+    ///
+    /// This allows a hot start without waiting for a DSM-KROOT. See
+    /// [`Osnma::set_tesla_key`].
+    pub fn with_tesla_key(mut self, key: Key<Validated>) -> OsnmaBuilder {
+        self.tesla_key = Some(key);
+        self
+    }
+
+    /// Sets the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    ///
+    /// See [`Osnma::set_min_authbits`].
+    pub fn with_min_authbits(mut self, bits: u16) -> OsnmaBuilder {
+        self.min_authbits = Some(bits);
+        self
+    }
+
+    /// Enables the GST sanity checks with a policy.
+    ///
+    /// See [`Osnma::set_gst_guard`].
+    pub fn with_gst_guard(mut self, policy: GstGuardPolicy) -> OsnmaBuilder {
+        self.gst_guard = Some(policy);
+        self
+    }
+
+    /// Sets a hook that receives events.
+    ///
+    /// See [`Osnma::set_event_hook`].
+    pub fn with_event_hook(mut self, hook: EventHook) -> OsnmaBuilder {
+        self.event_hook = Some(hook);
+        self
+    }
+
+    /// Sets a hook that receives diagnostics.
+    ///
+    /// See [`Osnma::set_diagnostic_hook`].
+    pub fn with_diagnostic_hook(mut self, hook: DiagnosticHook) -> OsnmaBuilder {
+        self.diagnostic_hook = Some(hook);
+        self
+    }
+
+    /// Builds the black box.
+    ///
+    /// The storage used by the black box is given by the type parameter `S`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if none of a Merkle tree root, a public key or a TESLA key have
+    /// been set.
+    pub fn build<S: Storage>(&self) -> Osnma<S> {
+        assert!(
+            self.merkle_tree_root.is_some() || self.pubkey.is_some() || self.tesla_key.is_some(),
+            "a Merkle tree root, a public key or a TESLA key is needed"
+        );
+        let pubkeys = PubkeyStore {
+            current: self.pubkey.clone(),
+            next: self.next_pubkey.clone(),
+        };
+        let mut osnma = Osnma::new(self.merkle_tree_root, pubkeys, self.only_slowmac);
+        if let Some(bits) = self.min_authbits {
+            osnma.set_min_authbits(bits);
+        }
+        osnma.set_gst_guard(self.gst_guard);
+        osnma.set_event_hook(self.event_hook);
+        osnma.set_diagnostic_hook(self.diagnostic_hook);
+        if let Some(key) = self.tesla_key {
+            osnma.set_tesla_key(key);
+        }
+        osnma
+    }
+}
+
+impl<S: Storage, V: Verifier> Osnma<S, V> {
+    /// Replaces the verifier used by the OSNMA black box.
+    ///
+    /// This returns an OSNMA black box that uses `verifier` to verify the
+    /// DSM-KROOT ECDSA signature. Any pending verification of the previous
+    /// verifier is discarded. See the [verifier](crate::verifier) module.
+    pub fn with_verifier<W: Verifier>(self, verifier: W) -> Osnma<S, W> {
+        let data = self.data.data;
+        Osnma {
+            subframe: self.subframe,
+            subframe_hook: self.subframe_hook,
+            data: OsnmaDsm {
+                dsm: self.data.dsm,
+                data: OsnmaData {
+                    navmessage: data.navmessage,
+                    mack: data.mack,
+                    merkle_tree: data.merkle_tree,
+                    pubkey: data.pubkey,
+                    key: data.key,
+                    only_slowmac: data.only_slowmac,
+                    svn_enabled: data.svn_enabled,
+                    verifier,
+                    kroot_failures: data.kroot_failures,
+                    notifier: data.notifier,
+                    adkd_check_policy: data.adkd_check_policy,
+                    icd_version: data.icd_version,
+                    auditor: data.auditor,
+                    watchdog: data.watchdog,
+                    auth_states: data.auth_states,
+                    tag_events: data.tag_events,
+                    nma_state: data.nma_state,
+                    nma_dont_use_policy: data.nma_dont_use_policy,
+                    last_mack: data.last_mack,
+                },
+            },
+            #[cfg(feature = "galmon")]
+            galmon: self.galmon,
+        }
+    }
+
+    /// Sets the policy for INAV words that differ from authenticated data.
+    ///
+    /// By default, a new INAV word that differs from the stored authenticated
+    /// navigation data replaces it. See [`MismatchPolicy`] for the other
+    /// possible policies.
+    pub fn set_mismatch_policy(&mut self, policy: MismatchPolicy) {
+        self.data.data.navmessage.set_mismatch_policy(policy);
+    }
+
+    /// Enables or disables the reconciliation of authentication bits across
+    /// the navigation data history.
+    ///
+    /// When enabled, the most recent copy of the navigation data is returned
+    /// with the maximum authentication bits accumulated by all its copies in
+    /// the storage, instead of an older duplicate. It is disabled by
+    /// default. See [`CollectNavMessage::set_history_reconciliation`].
+    pub fn set_history_reconciliation(&mut self, enabled: bool) {
+        self.data
+            .data
+            .navmessage
+            .set_history_reconciliation(enabled);
+    }
+
+    /// Enables or disables counting only the authentication bits of tags from
+    /// distinct sources.
+    ///
+    /// When enabled, only the first validated tag from each pair of
+    /// authenticating PRN and subframe counts towards the minimum
+    /// authentication bits required to consider the navigation data
+    /// authenticated. It is disabled by default. See
+    /// [`CollectNavMessage::set_unique_tag_authbits`].
+    ///
+    /// This is only available with the `unique-authbits` feature.
+    #[cfg(feature = "unique-authbits")]
+    pub fn set_unique_tag_authbits(&mut self, enabled: bool) {
+        self.data.data.navmessage.set_unique_tag_authbits(enabled);
+    }
+
+    /// Sets the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    ///
+    /// By default, the minimum of 40 bits required by the OSNMA guidelines is
+    /// used. Smaller values are raised to this minimum. See
+    /// [`CollectNavMessage::set_min_authbits`].
+    pub fn set_min_authbits(&mut self, bits: u16) {
+        self.data.data.navmessage.set_min_authbits(bits);
+    }
+
+    /// Gives the minimum authentication bits required to consider the
+    /// navigation data authenticated.
+    pub fn min_authbits(&self) -> u16 {
+        self.data.data.navmessage.min_authbits()
+    }
+
+    /// Sets the policy for authenticated navigation data when the NMA status
+    /// is "don't use".
+    ///
+    /// By default, the authenticated navigation data is returned regardless
+    /// of the NMA status. See [`NmaDontUsePolicy`] for the other possible
+    /// policies, and [`Osnma::nma_state`] for how the NMA status is obtained.
+    pub fn set_nma_dont_use_policy(&mut self, policy: NmaDontUsePolicy) {
+        self.data.data.nma_dont_use_policy = policy;
+    }
+
+    /// Gives the state of the most recent NMA header.
+    ///
+    /// This is the NMA header of the most recent subframe, unless a DSM-KROOT
+    /// for the same subframe has been verified, in which case the validated
+    /// NMA header of the DSM-KROOT is given. The NMA header of a subframe may
+    /// not be validated (see [`NmaState::is_validated`]), but a forged NMA
+    /// header can only cause the navigation data to be withheld or flagged as
+    /// "don't use", and the NMA status is also covered by the tags. This
+    /// returns `None` if no subframe has been received yet.
+    pub fn nma_state(&self) -> Option<NmaState> {
+        self.data.data.nma_state
+    }
+
+    /// Sets the ADKDs that are processed.
+    ///
+    /// By default, all the ADKDs are processed. Receivers that are only
+    /// interested in the ephemeris, clock and status data (ADKD=0 and 12) or
+    /// only in the timing parameters (ADKD=4) can use this to skip the
+    /// collection and tag verification of the other data. See
+    /// [`AdkdFilter`] and [`CollectNavMessage::set_adkd_filter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::bitfields::Adkd;
+    /// use galileo_osnma::navmessage::AdkdFilter;
+    /// use galileo_osnma::storage::FullStorage;
+    /// use galileo_osnma::{Osnma, PublicKey};
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// // Only authenticate the data used for positioning
+    /// osnma.set_adkd_filter(AdkdFilter::ALL.without(Adkd::InavTiming));
+    /// ```
+    pub fn set_adkd_filter(&mut self, filter: AdkdFilter) {
+        self.data.data.navmessage.set_adkd_filter(filter);
+    }
+
+    /// Sets the COP policy for an ADKD.
+    ///
+    /// This controls which tags are used according to their COP and to the
+    /// age of the stored navigation data. Since the timing parameters change
+    /// rarely, a larger age margin can be used for ADKD=4 to improve their
+    /// availability. See [`CopPolicy`] and
+    /// [`CollectNavMessage::set_cop_policy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::bitfields::Adkd;
+    /// use galileo_osnma::navmessage::CopPolicy;
+    /// use galileo_osnma::storage::FullStorage;
+    /// use galileo_osnma::{Osnma, PublicKey};
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// osnma.set_cop_policy(Adkd::InavTiming, CopPolicy::DEFAULT.with_age_margin(20));
+    /// ```
+    pub fn set_cop_policy(&mut self, adkd: Adkd, policy: CopPolicy) {
+        self.data.data.navmessage.set_cop_policy(adkd, policy);
+    }
+
+    /// Drops the navigation data stored for subframes older than a GST.
+    ///
+    /// This can be called periodically by the host to bound the age of the
+    /// navigation data in the storage. See
+    /// [`CollectNavMessage::prune_older_than`].
+    ///
+    /// Returns the number of subframes whose data has been dropped.
+    pub fn prune_navdata_older_than(&mut self, gst: Gst) -> usize {
+        self.data.data.navmessage.prune_older_than(gst)
+    }
+
+    /// Clears the satellites that have not been received since a GST.
+    ///
+    /// This can be called periodically by the host to discard the navigation
+    /// data of satellites that are no longer tracked. See
+    /// [`CollectNavMessage::clear_satellites_not_seen_since`].
+    ///
+    /// Returns the number of satellites that have been cleared.
+    pub fn clear_satellites_not_seen_since(&mut self, gst: Gst) -> usize {
+        self.data
+            .data
+            .navmessage
+            .clear_satellites_not_seen_since(gst)
+    }
+
+    /// Sets the policy for ADKD fields that do not match the MAC look-up table.
+    ///
+    /// By default, a MACK message is rejected if any of its ADKD fields does
+    /// not match the MAC look-up table. With [`AdkdCheckPolicy::SkipTag`],
+    /// only the tags with a reserved ADKD value are skipped, so that new ADKDs
+    /// introduced in future versions of the OSNMA SIS ICD do not prevent the
+    /// use of the other tags. With [`AdkdCheckPolicy::Truncate`], the tags
+    /// preceding the first incorrect ADKD field are still used.
+    pub fn set_adkd_check_policy(&mut self, policy: AdkdCheckPolicy) {
+        self.data.data.adkd_check_policy = policy;
+    }
+
+    /// Sets the version of the OSNMA SIS ICD used to interpret the OSNMA data.
+    ///
+    /// By default, the OSNMA SIS ICD v1.1 is used. Setting
+    /// [`IcdVersion::V1_0`] interprets the values of the chain and public key
+    /// status and of the new public key type that were introduced in the
+    /// OSNMA SIS ICD v1.1 as reserved, as they were during the OSNMA test
+    /// phase. This should be called right after constructing the black box,
+    /// before any data is fed. See [`IcdVersion`].
+    pub fn set_icd_version(&mut self, version: IcdVersion) {
+        self.data.data.icd_version = version;
+    }
+
+    /// Enables or disables the watchdog for stalled authentication.
+    ///
+    /// When the watchdog is enabled, a
+    /// [`DiagnosticKind::AuthenticationStalled`] diagnostic is reported if
+    /// OSNMA data keeps being fed into the OSNMA black box but no tags are
+    /// validated during `subframes` subframes. The diagnostic gives the most
+    /// probable cause, such as a missing KROOT, a wrong public key, a receiver
+    /// TOW offset, or `only_slowmac` being set when it is not needed. Passing
+    /// `None` disables the watchdog, which is the default.
+    ///
+    /// Typically, tags are validated in each subframe once the KROOT has been
+    /// verified, so a watchdog period of 20 subframes (10 minutes) allows
+    /// enough time to receive the first DSM-KROOT.
+    pub fn set_watchdog(&mut self, subframes: Option<u16>) {
+        self.data.data.watchdog.set_subframes(subframes);
+    }
+
+    /// Enables or disables the authentication state change events.
+    ///
+    /// When these events are enabled, an [`Event::AuthStateChanged`] event is
+    /// produced each time that the CED and health status data or the timing
+    /// parameters of a satellite change from unauthenticated to
+    /// authenticated, from authenticated to stale, or from stale back to
+    /// authenticated. The states are evaluated once per subframe, and the
+    /// debounce and staleness threshold are given by `policy`. This avoids
+    /// the need to compare the authentication status of all the satellites
+    /// after each subframe. Passing `None` disables these events, which is
+    /// the default.
+    pub fn set_auth_state_events(&mut self, policy: Option<AuthStatePolicy>) {
+        self.data.data.auth_states.set_policy(policy);
+    }
+
+    /// Enables or disables the GST sanity checks.
+    ///
+    /// If a policy is given, the INAV words and OSNMA data messages whose GST
+    /// regresses or jumps forward unrealistically are rejected, and a
+    /// [`DiagnosticKind::GstRejected`] diagnostic is reported for each of them.
+    /// This protects the OSNMA black box against receivers whose GST
+    /// occasionally jumps. See the [gstguard](crate::gstguard) module. Passing
+    /// `None` disables the checks, which is the default.
+    ///
+    /// The policy is applied separately to the INAV words, with
+    /// [`CollectNavMessage::set_gst_guard`], and to the OSNMA data messages,
+    /// with [`CollectSubframe::set_gst_guard`].
+    pub fn set_gst_guard(&mut self, policy: Option<GstGuardPolicy>) {
+        self.subframe.set_gst_guard(policy);
+        self.data.data.navmessage.set_gst_guard(policy);
+    }
+
+    /// Gives the statistics of the data rejected due to its GST.
+    ///
+    /// This adds the statistics of the INAV words and of the OSNMA data
+    /// messages rejected. See [`Osnma::set_gst_guard`].
+    pub fn gst_rejections(&self) -> GstRejectionStats {
+        self.subframe
+            .gst_rejections()
+            .merge(&self.data.data.navmessage.gst_rejections())
+    }
+
+    /// Gives the statistics of tags with reserved ADKD values.
+    ///
+    /// A [`DiagnosticKind::ReservedAdkd`] diagnostic is reported when these
+    /// tags appear in many consecutive MACK messages. See
+    /// [`ReservedAdkdStats`].
+    pub fn reserved_adkd_stats(&self) -> &ReservedAdkdStats {
+        self.data.data.navmessage.reserved_adkd_stats()
+    }
+
+    /// Sets a hook that receives the raw OSNMA data of each subframe.
+    ///
+    /// The hook is called by [`Osnma::feed_osnma`] each time that the HKROOT
+    /// and MACK messages of a satellite are completed, before they are
+    /// processed. This can be used to archive the raw OSNMA data or to feed it
+    /// to external analysis tools. Passing `None` removes the hook.
+    ///
+    /// Alternatively, the raw data of the current subframe can be obtained at
+    /// any time with [`CollectSubframe::completed`] through
+    /// [`Osnma::subframe_collector`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Osnma, PublicKey};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use galileo_osnma::subframe::RawSubframe;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// fn archive(subframe: RawSubframe) {
+    ///     println!(
+    ///         "{} {:?} HKROOT {:02x?} MACK {:02x?}",
+    ///         subframe.svn, subframe.gst, subframe.hkroot, subframe.mack
+    ///     );
+    /// }
+    ///
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// osnma.set_subframe_hook(Some(archive));
+    /// ```
+    pub fn set_subframe_hook(&mut self, hook: Option<SubframeHook>) {
+        self.subframe_hook = hook;
+    }
+
+    /// Sets a hook that receives diagnostics.
+    ///
+    /// The hook is called for problems found while processing the data fed
+    /// into the OSNMA black box, such as INAV words that differ from
+    /// authenticated data, subframes that were not received completely, and
+    /// MACK messages that cannot be processed because there is no valid TESLA
+    /// key. Each [`Diagnostic`] includes a [`Hint`](crate::diagnostics::Hint)
+    /// with its likely cause. These diagnostics are also logged. Passing `None`
+    /// removes the hook. See the [diagnostics](crate::diagnostics) module for
+    /// an example.
+    pub fn set_diagnostic_hook(&mut self, hook: Option<DiagnosticHook>) {
+        self.data.data.notifier.diagnostic_hook = hook;
+    }
+
+    /// Sets a hook that receives events.
+    ///
+    /// The hook is called for each [`Event`] as soon as it happens, in
+    /// addition to storing the event in the event queue. This is convenient
+    /// for applications that need to react to the events immediately. Passing
+    /// `None` removes the hook. See the [events](crate::events) module.
+    pub fn set_event_hook(&mut self, hook: Option<EventHook>) {
+        self.data.data.notifier.event_hook = hook;
+    }
+
+    /// Enables or disables the events for each tag.
+    ///
+    /// When these events are enabled, an [`Event::TagAuthenticated`] or an
+    /// [`Event::TagFailed`] event is produced for each tag whose validation
+    /// is attempted. Dummy tags and tags whose navigation data is not
+    /// available do not produce events. Since many tags are processed in each
+    /// subframe, these events are disabled by default, and enabling them
+    /// typically requires a larger
+    /// [`Storage::EventQueueDepth`] or using an event hook (see
+    /// [`Osnma::set_event_hook`]).
+    pub fn set_tag_events(&mut self, enabled: bool) {
+        self.data.data.tag_events = enabled;
+    }
+
+    /// Sets the fraction of MACK messages whose tags are audited.
+    ///
+    /// In audit mode, a random subset of the MACK messages whose tags have
+    /// been validated is selected. When the next TESLA key is processed, the
+    /// tags of the selected MACK messages are validated again, using a key
+    /// freshly derived from the newer TESLA key. This can detect memory
+    /// corruption or logic errors in long-running systems, at the cost of
+    /// additional processing. A discrepancy between both validations is
+    /// reported with an [`Event::AuditDiscrepancy`].
+    ///
+    /// The `fraction` should be between 0 and 1. A `fraction` of 0, which is
+    /// the default, disables audit mode.
+    pub fn set_audit_fraction(&mut self, fraction: f32) {
+        self.data.data.auditor.set_fraction(fraction);
+    }
+
+    /// Takes the oldest event from the event queue.
+    ///
+    /// Diagnostics and other relevant events are stored in a fixed-capacity
+    /// queue, whose size is given by
+    /// [`Storage::EventQueueDepth`]. This returns `None` if the queue is
+    /// empty. See the [events](crate::events) module.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.data.data.notifier.events.pop()
+    }
+
+    /// Takes all the events from the event queue.
+    ///
+    /// This returns an iterator that gives the events in the queue, from the
+    /// oldest to the newest, removing them from the queue. It is equivalent
+    /// to calling [`Osnma::poll_event`] until it returns `None`.
+    pub fn events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        core::iter::from_fn(move || self.poll_event())
+    }
+
+    /// Gives the number of events discarded because the event queue was full.
+    pub fn dropped_events(&self) -> u32 {
+        self.data.data.notifier.events.dropped()
+    }
+
+    /// Gives the most recent validated TESLA key of the chain in force.
+    ///
+    /// This can be sent to cooperating receivers, which can import it with
+    /// [`Osnma::import_tesla_key`] to start authenticating navigation data
+    /// without having to collect and verify the DSM-KROOT. The key can be
+    /// transported over an untrusted network by using [`Key::export`] and
+    /// [`Key::import`], which authenticate it with a shared secret.
+    ///
+    /// If no TESLA key has been validated yet, this returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, Osnma, PublicKey};
+    /// use galileo_osnma::storage::{FullStorage, SmallStorage};
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// // Receiver that collects the DSM-KROOT and validates the TESLA keys
+    /// let server = Osnma::<FullStorage>::from_pubkey(pubkey.clone(), false);
+    /// // Constrained receiver that gets the TESLA keys from the server
+    /// let mut client = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+    ///
+    /// let gst = Gst::new(1177, 175767);
+    /// if let Some(key) = server.latest_tesla_key() {
+    ///     if let Err(e) = client.import_tesla_key(key, gst) {
+    ///         println!("could not import TESLA key: {e}");
+    ///     }
+    /// }
+    /// ```
+    pub fn latest_tesla_key(&self) -> Option<Key<Validated>> {
+        self.data.data.key.current_key().copied()
+    }
+
+    /// Gives the end-of-chain transition in progress.
+    ///
+    /// If the NMA header of the last verified DSM-KROOT has its CPKS field set
+    /// to end-of-chain, this returns the [`ChainTransition`], which gives the
+    /// GST at which the next chain comes into force once the DSM-KROOT of the
+    /// next chain has been verified. Otherwise, this returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, Osnma, PublicKey};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// let gst = Gst::new(1177, 175767);
+    /// if let Some(transition) = osnma.chain_transition() {
+    ///     if let Some(seconds) = transition.seconds_to_switch(gst) {
+    ///         println!("chain switch in {seconds} s");
+    ///     }
+    ///     if !transition.both_chains_tracked() {
+    ///         println!("next chain not tracked yet");
+    ///     }
+    /// }
+    /// ```
+    pub fn chain_transition(&self) -> Option<ChainTransition> {
+        self.data.data.key.chain_transition()
+    }
+
+    /// Gives the TESLA chain pre-positioned for a chain switch.
+    ///
+    /// If the DSM-KROOT of a chain that is not in force yet has been verified,
+    /// this returns the [`PrepositionedChain`], which gives the parameters of
+    /// the chain, the GST at which it comes into force, and whether its first
+    /// TESLA key has been validated. The pre-positioned chain is kept after it
+    /// comes into force, until a DSM-KROOT whose NMA header indicates the
+    /// completion of the chain switch is verified. Otherwise, this returns
+    /// `None`.
+    pub fn prepositioned_chain(&self) -> Option<PrepositionedChain> {
+        self.data.data.key.prepositioned
+    }
+
+    /// Imports a validated TESLA key received from a peer.
+    ///
+    /// The key is used as if it had been received in a MACK message and
+    /// validated, so the tags that it can validate are processed immediately.
+    /// The `gst` parameter gives the current GST, and is used to check that
+    /// the GST of the key is not in the future, and that it is not so old
+    /// that the key cannot be used to validate the keys currently broadcast
+    /// (see [`MAX_KEY_DERIVATIONS`](crate::tesla::MAX_KEY_DERIVATIONS)).
+    /// Rejecting stale keys prevents a key from being used to authenticate
+    /// replayed navigation data long after it was broadcast.
+    ///
+    /// The key is only imported if it belongs to the chain in force (when
+    /// this chain is known) and if it is newer than the stored TESLA key of
+    /// the same chain. If there is already a TESLA key of the same chain, the
+    /// imported key is checked by validating it with the stored key. An error
+    /// is returned if any of these checks fail. Importing the same key that is
+    /// already stored does nothing and returns `Ok(())`.
+    ///
+    /// See [`Osnma::latest_tesla_key`] for an example.
+    pub fn import_tesla_key(&mut self, key: Key<Validated>, gst: Gst) -> Result<(), PeerKeyError> {
+        self.data.data.import_tesla_key(key, gst)
+    }
+
+    /// Sets a TESLA key that has been validated externally.
+    ///
+    /// This is intended for network-assisted receivers that obtain the current
+    /// TESLA key from a trusted server, so that they can skip the collection
+    /// of the DSM-KROOT. The tags that the key can validate are processed
+    /// immediately, and the TESLA keys broadcast afterwards are validated with
+    /// it, so tag verification begins within one subframe.
+    ///
+    /// Unlike [`Osnma::import_tesla_key`], the key is not checked against the
+    /// current GST nor against the stored TESLA keys. The chain of the key
+    /// becomes the chain in force, and the key replaces any key stored for
+    /// this chain. The caller is responsible for making sure that the key is
+    /// authentic and recent, since a stale key can be used to authenticate
+    /// replayed navigation data.
+    ///
+    /// If the black box does not have a public key, the DSM-KROOT of the next
+    /// TESLA chain cannot be verified, so a new key must be set after each
+    /// chain renewal.
+    pub fn set_tesla_key(&mut self, key: Key<Validated>) {
+        self.data.data.set_tesla_key(key)
+    }
+
+    /// Exports the OSNMA state.
+    ///
+    /// The most recent validated TESLA key of the chain in force, the Merkle
+    /// tree root and the current public key are encoded as described in the
+    /// [persist](crate::persist) module, so that they can be stored in
+    /// non-volatile memory and authentication can be resumed after a restart
+    /// with [`Osnma::import_state`], without having to wait for the DSM-KROOT.
+    /// The state is protected with an HMAC-SHA-256 using `secret`, which must
+    /// be given again to import the state.
+    ///
+    /// If no TESLA key has been validated yet, this returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, Osnma, PublicKey};
+    /// use galileo_osnma::storage::SmallStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// # fn read_flash() -> Option<[u8; galileo_osnma::persist::STATE_BYTES]> { None }
+    /// # fn write_flash(_: &[u8]) {}
+    /// let secret = b"device secret";
+    /// let mut osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+    /// let gst = Gst::new(1177, 175767);
+    /// if let Some(state) = read_flash() {
+    ///     if let Err(e) = osnma.import_state(&state, secret, gst) {
+    ///         // Proceed with a cold start
+    ///         println!("could not import OSNMA state: {e}");
+    ///     }
+    /// }
+    /// // ... process OSNMA data ...
+    /// if let Some(state) = osnma.export_state(secret) {
+    ///     write_flash(&state);
+    /// }
+    /// ```
+    pub fn export_state(&self, secret: &[u8]) -> Option<[u8; STATE_BYTES]> {
+        let key = self.latest_tesla_key()?;
+        let data = &self.data.data;
+        Some(crate::persist::encode(
+            &key,
+            data.merkle_tree.as_ref().map(|tree| tree.root()),
+            data.pubkey.current.as_ref(),
+            secret,
+        ))
+    }
+
+    /// Imports the OSNMA state.
+    ///
+    /// This imports a state exported with [`Osnma::export_state`]. The
+    /// HMAC-SHA-256 of the state is checked using `secret`, and then the
+    /// TESLA key that it contains is imported with
+    /// [`Osnma::import_tesla_key`] using the current GST `gst`. In particular,
+    /// the state is refused if the GST of the saved key is later than `gst`
+    /// or too old to validate the keys currently broadcast, since this
+    /// indicates that either the saved state or the current GST are wrong.
+    ///
+    /// The Merkle tree root and the public key of the state are only used if
+    /// this OSNMA black box does not have a Merkle tree root or a public key
+    /// respectively. The ones given when the black box was constructed are
+    /// never replaced.
+    ///
+    /// If an error is returned, the OSNMA state is not modified, and the
+    /// caller can proceed with a cold start.
+    ///
+    /// See [`Osnma::export_state`] for an example.
+    pub fn import_state(
+        &mut self,
+        state: &[u8],
+        secret: &[u8],
+        gst: Gst,
+    ) -> Result<(), StateError> {
+        let state = crate::persist::decode(state, secret)?;
+        let data = &mut self.data.data;
+        data.import_tesla_key(state.key, gst)?;
+        if data.merkle_tree.is_none() {
+            data.merkle_tree = state.merkle_root.map(MerkleTree::new);
+        }
+        if data.pubkey.current.is_none() {
+            if let Some(pubkey) = state.pubkey {
+                data.pubkey = PubkeyStore::from_current_pubkey(pubkey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the OSNMA state to a file.
+    ///
+    /// The state obtained with [`Osnma::export_state`] is written to the file
+    /// at `path`, so that authentication can be resumed after a restart by
+    /// calling [`Osnma::load_from_file`]. The state is protected using
+    /// `secret`, which must be given again to load the file.
+    ///
+    /// The file is written atomically: the data is first written to a
+    /// temporary file with the `.tmp` suffix, which is then renamed to
+    /// `path`. Therefore, a previous state file is not lost if the process is
+    /// interrupted while saving.
+    ///
+    /// If no TESLA key has been validated yet, no file is written and this
+    /// returns `Ok(false)`. Otherwise, this returns `Ok(true)` if the file has
+    /// been written successfully.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use galileo_osnma::{Gst, Osnma, PublicKey};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let secret = b"secret shared by all the runs";
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// let gst = Gst::new(1177, 175767);
+    /// if let Err(e) = osnma.load_from_file("osnma.state", secret, gst) {
+    ///     println!("could not load OSNMA state: {e}");
+    /// }
+    /// // ... process OSNMA data ...
+    /// osnma.save_to_file("osnma.state", secret)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn save_to_file<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        secret: &[u8],
+    ) -> std::io::Result<bool> {
+        let Some(state) = self.export_state(secret) else {
+            log::debug!("no TESLA key to save");
+            return Ok(false);
+        };
+        crate::persist::write_atomic(path.as_ref(), &state)?;
+        Ok(true)
+    }
+
+    /// Loads the OSNMA state from a file.
+    ///
+    /// This loads a file written by [`Osnma::save_to_file`] and imports it
+    /// with [`Osnma::import_state`]. See the [persist](crate::persist)
+    /// module.
+    ///
+    /// Errors are returned with the kind [`std::io::ErrorKind::InvalidData`]
+    /// if the file is corrupted or if the state cannot be imported. The
+    /// wrapped error is a [`StateError`] or a [`PeerKeyError`] in these cases.
+    /// If the file does not exist, the error with kind
+    /// [`std::io::ErrorKind::NotFound`] is returned. In all these cases, the
+    /// OSNMA state is not modified, and the caller can proceed with a cold
+    /// start.
+    ///
+    /// See [`Osnma::save_to_file`] for an example.
+    #[cfg(feature = "std")]
+    pub fn load_from_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+        secret: &[u8],
+        gst: Gst,
+    ) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+        let data = std::fs::read(path)?;
+        self.import_state(&data, secret, gst)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Compares the state of this OSNMA black box with another one.
+    ///
+    /// This gives the differences between the navigation data stored for
+    /// each satellite and subframe, its authentication bits, the TESLA key of
+    /// the chain in force and the ID of the current public key. It is intended
+    /// for debugging divergences between two black boxes that have processed
+    /// the same input. See the [diff](crate::diff) module.
+    ///
+    /// This black box is the left side of the comparison, and `other` is the
+    /// right side.
+    pub fn diff<'a, T: Storage, W: Verifier>(
+        &'a self,
+        other: &'a Osnma<T, W>,
+    ) -> impl Iterator<Item = Difference> + 'a {
+        let left = &self.data.data;
+        let right = &other.data.data;
+        let (left_key, right_key) = (self.latest_tesla_key(), other.latest_tesla_key());
+        let tesla_key = (left_key != right_key).then_some(Difference::TeslaKey {
+            left: left_key,
+            right: right_key,
+        });
+        let (left_pkid, right_pkid) = (left.pubkey.current_pkid(), right.pubkey.current_pkid());
+        let pkid = (left_pkid != right_pkid).then_some(Difference::PublicKeyId {
+            left: left_pkid,
+            right: right_pkid,
+        });
+        crate::diff::navdata(&left.navmessage, &right.navmessage)
+            .chain(tesla_key)
+            .chain(pkid)
+    }
+
+    /// Gives a reference to the verifier used by the OSNMA black box.
+    pub fn verifier(&self) -> &V {
+        &self.data.data.verifier
+    }
+
+    /// Gives a mutable reference to the verifier used by the OSNMA black box.
+    ///
+    /// This can be used to take pending requests from and submit results to a
+    /// deferred verifier such as
+    /// [`QueuedVerifier`](crate::verifier::QueuedVerifier).
+    pub fn verifier_mut(&mut self) -> &mut V {
+        &mut self.data.data.verifier
+    }
+
+    /// Gives the DSM-KROOT verification failure tracker.
+    ///
+    /// The tracker can be used to detect whether the receiver is possibly
+    /// receiving forged DSM-KROOT messages. See [`KrootFailureTracker`].
+    pub fn kroot_failures(&self) -> &KrootFailureTracker {
+        &self.data.data.kroot_failures
+    }
+
+    /// Feed an INAV word into the OSNMA black box.
+    ///
+    /// The black box will store the navigation data in the INAV word for later
+    /// usage.
+    ///
+    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
+    /// the INAV word. This should be obtained from the PRN used for tracking.
+    ///
+    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
+    ///
+    /// The `band` parameter indicates the band in which the INAV word was received.
+    pub fn feed_inav(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        let _ = self.try_feed_inav(word, svn, gst, band);
+    }
+
+    /// Feed an INAV word into the OSNMA black box, reporting any problems.
+    ///
+    /// This is the same as [`Osnma::feed_inav`], but an error is returned if
+    /// the word has been discarded or if a problem has been found when
+    /// storing it. See [`FeedError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{FeedError, Gst, InavBand, Osnma, PublicKey, Svn};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// let svn = Svn::try_from(11).unwrap();
+    /// let gst = Gst::new(1177, 175767);
+    /// // Word type 63 is a dummy message
+    /// let mut word = [0; 16];
+    /// word[0] = 63 << 2;
+    /// assert_eq!(
+    ///     osnma.try_feed_inav(&word, svn, gst, InavBand::E1B),
+    ///     Err(FeedError::DummyWord)
+    /// );
+    /// ```
+    pub fn try_feed_inav(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        let source = WordSource {
+            band,
+            #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+            gst,
+            crc_checked: false,
+        };
+        self.feed_inav_from_source(word, svn, gst, source)
+    }
+
+    /// Feed an INAV word whose CRC has been checked into the OSNMA black box.
+    ///
+    /// This is the same as [`Osnma::feed_inav`], but indicates that the
+    /// receiver has checked that the CRC of the INAV page containing the word
+    /// is correct. When different copies of the same word are received in the
+    /// E1-B and E5b-I bands during a subframe, a copy whose CRC has been
+    /// checked is preferred. See [`CollectNavMessage::feed_crc_checked`].
+    pub fn feed_inav_crc_checked(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
+        let _ = self.try_feed_inav_crc_checked(word, svn, gst, band);
+    }
+
+    /// Feed an INAV word whose CRC has been checked into the OSNMA black box,
+    /// reporting any problems.
+    ///
+    /// This is the same as [`Osnma::feed_inav_crc_checked`], but an error is
+    /// returned as in [`Osnma::try_feed_inav`].
+    pub fn try_feed_inav_crc_checked(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        let source = WordSource {
+            band,
+            #[cfg(any(feature = "provenance", feature = "reduced-ced"))]
+            gst,
+            crc_checked: true,
+        };
+        self.feed_inav_from_source(word, svn, gst, source)
+    }
+
+    fn feed_inav_from_source(
+        &mut self,
+        word: &InavWord,
+        svn: Svn,
+        gst: Gst,
+        source: WordSource,
+    ) -> Result<(), FeedError> {
+        if InavWordType::of(word) == InavWordType::Dummy {
+            return Err(FeedError::DummyWord);
+        }
+        if !self.is_svn_enabled(svn) {
+            return Err(FeedError::SvnDisabled);
+        }
+        let data = &mut self.data.data;
+        data.navmessage
+            .feed_with_diagnostics(word, svn, gst, source, |d| data.notifier.report(d))
+    }
+
+    /// Feed the OSNMA data message from an INAV page into the OSNMA black box.
+    ///
+    /// The black box will store the data and potentially trigger any new
+    /// cryptographic checks that this data makes possible.
+    ///
+    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
+    /// the INAV word. This should be obtained from the PRN used for tracking.
+    ///
+    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
+    ///
+    /// The OSNMA data message is assumed to have been received in E1-B. Use
+    /// [`Osnma::feed_osnma_band`] for data received in E5b-I.
+    pub fn feed_osnma(&mut self, osnma: &OsnmaDataMessage, svn: Svn, gst: Gst) {
+        self.feed_osnma_band(osnma, svn, gst, InavBand::E1B);
+    }
+
+    /// Feed the OSNMA data message from an INAV page into the OSNMA black box,
+    /// reporting any problems.
+    ///
+    /// This is the same as [`Osnma::feed_osnma`], but an error is returned if
+    /// the data has been discarded or if a problem has been found when
+    /// storing it. See [`FeedError`]. Problems found in the cryptographic
+    /// checks triggered by the data are not returned. They are reported as
+    /// [diagnostics](crate::diagnostics).
+    pub fn try_feed_osnma(
+        &mut self,
+        osnma: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+    ) -> Result<(), FeedError> {
+        self.try_feed_osnma_band(osnma, svn, gst, InavBand::E1B)
+    }
+
+    /// Feed the OSNMA data message from an INAV page received in a particular
+    /// band into the OSNMA black box.
+    ///
+    /// This is the same as [`Osnma::feed_osnma`], but the `band` parameter
+    /// indicates the band in which the INAV page was received. The page timing
+    /// of E5b-I is offset by 1 second with respect to E1-B, which is taken
+    /// into account to determine the subframe to which the data belongs. See
+    /// [`InavBand::e1b_page_gst`].
+    pub fn feed_osnma_band(
+        &mut self,
+        osnma: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) {
+        let _ = self.try_feed_osnma_band(osnma, svn, gst, band);
+    }
+
+    /// Feed the OSNMA data message from an INAV page received in a particular
+    /// band into the OSNMA black box, reporting any problems.
+    ///
+    /// This is the same as [`Osnma::feed_osnma_band`], but an error is
+    /// returned as in [`Osnma::try_feed_osnma`].
+    pub fn try_feed_osnma_band(
+        &mut self,
+        osnma: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<(), FeedError> {
+        if self.collect_osnma(osnma, svn, gst, band)? {
+            self.process_completed_subframe(svn);
+        }
+        Ok(())
+    }
+
+    // Feeds the OSNMA data message into the subframe collector, returning
+    // whether it has completed the subframe of the satellite. The completed
+    // subframe is not processed.
+    fn collect_osnma(
+        &mut self,
+        osnma: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Result<bool, FeedError> {
+        if osnma.iter().all(|&x| x == 0) {
+            // No OSNMA data
+            return Err(FeedError::NoOsnmaData);
+        }
+        if !self.is_svn_enabled(svn) {
+            return Err(FeedError::SvnDisabled);
+        }
+        let notifier = &mut self.data.data.notifier;
+        Ok(self
+            .subframe
+            .feed_with_diagnostics(osnma, svn, gst, band, |d| notifier.report(d))?
+            .is_some())
+    }
+
+    // Processes the subframe completed by a satellite in the current subframe
+    // of the subframe collector.
+    fn process_completed_subframe(&mut self, svn: Svn) {
+        let Some(subframe) = self.subframe.completed(svn) else {
+            return;
+        };
+        if let Some(hook) = self.subframe_hook {
+            hook(subframe);
+        }
+        self.data
+            .process_subframe(subframe.hkroot, subframe.mack, svn, subframe.gst);
+    }
+
+    /// Feed a batch of INAV pages into the OSNMA black box.
+    ///
+    /// This is intended for front-ends that produce all the INAV pages
+    /// received in the same 2-second epoch together. The INAV words and the
+    /// OSNMA data messages of all the pages are stored first, and the
+    /// subframes completed by the batch are processed afterwards, in the order
+    /// in which they were completed. This guarantees that the navigation data
+    /// of the whole epoch is stored before any cryptographic checks triggered
+    /// by the OSNMA data are run. The results are the same as feeding each
+    /// page with [`Osnma::feed_inav`] and [`Osnma::feed_osnma_band`].
+    ///
+    /// The pages are also accepted if they do not belong to the same epoch,
+    /// but they should be given in chronological order. The subframes
+    /// completed by the batch are processed whenever a page of a different
+    /// subframe is found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, InavBand, Osnma, PublicKey, Svn};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use galileo_osnma::types::InavPage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// let gst = Gst::new(1177, 175767);
+    /// let inav = [[0; 16]; 2];
+    /// let osnma_data = [[0; 5]; 2];
+    /// let pages = [11, 12].iter().enumerate().map(|(j, &svn)| InavPage {
+    ///     word: &inav[j],
+    ///     osnma: Some(&osnma_data[j]),
+    ///     svn: Svn::try_from(svn).unwrap(),
+    ///     gst,
+    ///     band: InavBand::E1B,
+    /// });
+    /// osnma.feed_batch(pages);
+    /// ```
+    pub fn feed_batch<'a, I>(&mut self, pages: I)
+    where
+        I: IntoIterator<Item = InavPage<'a>>,
+    {
+        // Satellites that have completed their subframe in this batch, in
+        // order of completion, and GST of that subframe
+        let mut completed = [None; NUM_SVNS];
+        let mut num_completed = 0;
+        let mut completed_gst = None;
+        let mut num_pages = 0;
+        for page in pages {
+            num_pages += 1;
+            let subframe_gst = page.band.e1b_page_gst(page.gst).gst_subframe();
+            if completed_gst.is_some_and(|gst| gst != subframe_gst) {
+                // The completed subframes are processed before any data of
+                // a new subframe is fed, as when feeding page by page. The
+                // subframe collector also discards them when it starts
+                // collecting a new subframe.
+                for &svn in completed[..num_completed].iter().flatten() {
+                    self.process_completed_subframe(svn);
+                }
+                num_completed = 0;
+                completed_gst = None;
+            }
+            self.feed_inav(page.word, page.svn, page.gst, page.band);
+            let Some(osnma) = page.osnma else {
+                continue;
+            };
+            if let Ok(true) = self.collect_osnma(osnma, page.svn, page.gst, page.band) {
+                completed[num_completed] = Some(page.svn);
+                num_completed += 1;
+                completed_gst = Some(subframe_gst);
+            }
+        }
+        for &svn in completed[..num_completed].iter().flatten() {
+            self.process_completed_subframe(svn);
         }
+        log::trace!(
+            "fed batch of {num_pages} INAV pages, which completed {num_completed} subframes"
+        );
     }
 
-    /// Constructs a new OSNMA black box using the Merkle tree root.
+    /// Feed a Galmon navmon packet into the OSNMA black box.
     ///
-    /// An optional ECDSA public key can be passed in addition to the Merkle
-    /// tree root. If the ECDSA public key is not passed, the OSNMA black box
-    /// will need to obtain the public key from a DSM-PKR message. These
-    /// messages are broadcast only every 6 hours.
+    /// If the packet contains a Galileo INAV page, its INAV word and OSNMA
+    /// data message are fed into the black box with [`Osnma::feed_inav`] and
+    /// [`Osnma::feed_osnma_band`]. Other packets are ignored.
     ///
-    /// If `only_slowmac` is `true`, only ADKD=12 (Slow MAC) will be processed.
-    /// This should be used by receivers which have a larger time uncertainty.
-    /// (See Annex 3 in the
-    /// [OSNMA Receiver Guidelines](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_for_Test_Phase_v1.0.pdf)).
-    pub fn from_merkle_tree(
-        merkle_tree_root: MerkleTreeNode,
-        pubkey: Option<PublicKey<Validated>>,
-        only_slowmac: bool,
-    ) -> Osnma<S> {
-        Osnma::new(Some(merkle_tree_root), pubkey, only_slowmac)
-    }
-
-    /// Constructs a new OSNMA black box using only an ECDSA public key.
+    /// Before feeding the data, the same preprocessing done by the
+    /// [galmon-osnma](https://github.com/daniestevez/galileo-osnma/tree/main/galmon-osnma)
+    /// tool is applied. Wrong TOWs that are sometimes present in Galmon data
+    /// are fixed, INAV words belonging to a previous subframe are dropped, and
+    /// INAV Dummy Messages are discarded.
     ///
-    /// This function is similar to [`Osnma::from_merkle_tree`], but the Merkle
-    /// tree root is not loaded. Therefore, DSM-PKR verification will not be
-    /// done, and only the provided ECDSA public key will be used.
+    /// This function is only available when the `galmon` feature is enabled.
     ///
-    /// The OSNMA black box will hold the public key `pubkey` and use it to
-    /// try to authenticate the TESLA root key. The public key cannot be changed
-    /// after construction.
+    /// # Examples
     ///
-    /// If `only_slowmac` is `true`, only ADKD=12 (Slow MAC) will be processed.
-    /// This should be used by receivers which have a larger time uncertainty.
-    /// (See Annex 3 in the
-    /// [OSNMA Receiver Guidelines](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_Receiver_Guidelines_for_Test_Phase_v1.0.pdf)).
-    pub fn from_pubkey(pubkey: PublicKey<Validated>, only_slowmac: bool) -> Osnma<S> {
-        Osnma::new(None, Some(pubkey), only_slowmac)
+    /// ```no_run
+    /// use galileo_osnma::galmon::transport::ReadTransport;
+    /// use galileo_osnma::storage::FullStorage;
+    /// use galileo_osnma::{Osnma, PublicKey, Validated};
+    ///
+    /// fn process(pubkey: PublicKey<Validated>) -> std::io::Result<()> {
+    ///     let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    ///     let mut read = ReadTransport::new(std::io::stdin());
+    ///     while let Some(packet) = read.read_packet()? {
+    ///         osnma.feed_galmon(&packet);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "galmon")]
+    pub fn feed_galmon(&mut self, packet: &NavMonMessage) {
+        if let Some(page) = self.galmon.process(packet) {
+            self.feed_inav(page.word, page.svn, page.gst, page.band);
+            if let Some(osnma) = page.osnma {
+                self.feed_osnma_band(osnma, page.svn, page.gst, page.band);
+            }
+        }
     }
 
-    /// Feed an INAV word into the OSNMA black box.
+    /// Enables or disables the processing of data from a satellite.
     ///
-    /// The black box will store the navigation data in the INAV word for later
-    /// usage.
+    /// When a satellite is disabled, the INAV words and OSNMA data messages
+    /// fed for that satellite are discarded, and the MACK messages that it has
+    /// transmitted are not used to authenticate navigation data. This can be
+    /// used to exclude satellites that are broadcasting inconsistent data, for
+    /// instance because they are undergoing testing. Navigation data that was
+    /// collected before disabling the satellite is kept.
     ///
-    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
-    /// the INAV word. This should be obtained from the PRN used for tracking.
+    /// All the satellites are enabled when the OSNMA black box is constructed.
     ///
-    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
+    /// # Examples
     ///
-    /// The `band` parameter indicates the band in which the INAV word was received.
-    pub fn feed_inav(&mut self, word: &InavWord, svn: Svn, gst: Gst, band: InavBand) {
-        self.data.data.navmessage.feed(word, svn, gst, band);
+    /// ```
+    /// use galileo_osnma::{Osnma, PublicKey, Svn};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// let svn = Svn::try_from(14).unwrap();
+    /// osnma.set_svn_enabled(svn, false);
+    /// assert!(!osnma.is_svn_enabled(svn));
+    /// ```
+    pub fn set_svn_enabled(&mut self, svn: Svn, enabled: bool) {
+        log::info!(
+            "{} processing of {}",
+            if enabled { "enabling" } else { "disabling" },
+            svn
+        );
+        self.data.data.svn_enabled[svn.index()] = enabled;
     }
 
-    /// Feed the OSNMA data message from an INAV page into the OSNMA black box.
+    /// Returns `true` if the processing of data from a satellite is enabled.
     ///
-    /// The black box will store the data and potentially trigger any new
-    /// cryptographic checks that this data makes possible.
+    /// See [`Osnma::set_svn_enabled`].
+    pub fn is_svn_enabled(&self, svn: Svn) -> bool {
+        self.data.data.is_svn_enabled(svn)
+    }
+
+    /// Gives read-only access to the MACK messages stored by the black box.
     ///
-    /// The `svn` parameter corresponds to the SVN of the satellite transmitting
-    /// the INAV word. This should be obtained from the PRN used for tracking.
+    /// This can be used to retrieve the history of MACK messages that have
+    /// been collected, for instance with [`MackStorage::iter`], in order to
+    /// dump them for offline analysis. The number of subframes held in the
+    /// history is given by the [`Storage`] type parameter `S`.
+    pub fn mack_storage(&self) -> &MackStorage<S> {
+        &self.data.data.mack
+    }
+
+    /// Gives access to the subframe collector.
     ///
-    /// The `gst` parameter gives the GST at the start of the INAV page transmission.
-    pub fn feed_osnma(&mut self, osnma: &OsnmaDataMessage, svn: Svn, gst: Gst) {
-        if osnma.iter().all(|&x| x == 0) {
-            // No OSNMA data
-            return;
-        }
-        if let Some((hkroot, mack, subframe_gst)) = self.subframe.feed(osnma, svn, gst) {
-            self.data.process_subframe(hkroot, mack, svn, subframe_gst);
-        }
+    /// The subframe collector can be used to obtain the OSNMA page reception
+    /// statistics of each satellite. See [`CollectSubframe`].
+    pub fn subframe_collector(&self) -> &CollectSubframe {
+        &self.subframe
     }
 
     /// Try to get authenticated CED and health status data for a satellite.
@@ -219,8 +2056,16 @@ impl<S: StaticStorage> Osnma<S> {
     /// available in the OSNMA storage. If the storage does not contain any
     /// authenticated CED and health status data for this SVN, this returns
     /// `None`.
-    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData> {
-        self.data.data.navmessage.get_ced_and_status(svn)
+    ///
+    /// The authentication bits contributed by the ADKD=0 and the ADKD=12 tags
+    /// can be obtained separately with [`NavMessageData::adkd_authbits`], for
+    /// instance to require a minimum number of Slow MAC authentication bits.
+    ///
+    /// If the NMA status is "don't use", the data is returned according to
+    /// the policy set with [`Osnma::set_nma_dont_use_policy`]. The same
+    /// applies to the other getters of authenticated navigation data.
+    pub fn get_ced_and_status(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.apply_nma_dont_use_policy(self.data.data.navmessage.get_ced_and_status(svn))
     }
 
     /// Try to get authenticated timing parameters for a satellite.
@@ -229,20 +2074,295 @@ impl<S: StaticStorage> Osnma<S> {
     /// parameters data (ADKD=4) for the satellite with SVN `svn` that is
     /// available in the OSNMA storage. If the storage does not contain any
     /// authenticated timing parameters data for this SVN, this returns `None`.
-    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData> {
-        self.data.data.navmessage.get_timing_parameters(svn)
+    pub fn get_timing_parameters(&self, svn: Svn) -> Option<NavMessageData<'_>> {
+        self.apply_nma_dont_use_policy(self.data.data.navmessage.get_timing_parameters(svn))
+    }
+
+    /// Try to get the reduced CED of a satellite.
+    ///
+    /// This gives the most recent reduced CED (INAV word type 16) received
+    /// from the satellite with SVN `svn`. The reduced CED is not authenticated
+    /// by OSNMA. See [`CollectNavMessage::get_reduced_ced`].
+    ///
+    /// This is only available with the `reduced-ced` feature.
+    #[cfg(feature = "reduced-ced")]
+    pub fn get_reduced_ced(&self, svn: Svn) -> Option<ReducedCed> {
+        self.data.data.navmessage.get_reduced_ced(svn)
+    }
+
+    /// Try to get the authenticated timing parameters of the constellation.
+    ///
+    /// The timing parameters (ADKD=4) are common to all the satellites, so
+    /// this merges the most recent authenticated timing parameters of all the
+    /// satellites and gives the combined authentication bits that back them.
+    /// See [`CollectNavMessage::get_constellation_timing`].
+    pub fn get_constellation_timing(&self) -> Option<ConstellationTiming<'_>> {
+        let nma_dont_use = self.nma_dont_use_flag()?;
+        self.data
+            .data
+            .navmessage
+            .get_constellation_timing()
+            .map(|timing| timing.with_nma_dont_use(nma_dont_use))
+    }
+
+    /// Try to get authenticated CED and health status data for a satellite as
+    /// it was at some GST.
+    ///
+    /// This only considers the navigation data stored in subframes starting at
+    /// or before `gst`. See [`CollectNavMessage::get_ced_and_status_at`].
+    pub fn get_ced_and_status_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.apply_nma_dont_use_policy(self.data.data.navmessage.get_ced_and_status_at(svn, gst))
+    }
+
+    /// Try to get authenticated timing parameters for a satellite as they were
+    /// at some GST.
+    ///
+    /// This only considers the navigation data stored in subframes starting at
+    /// or before `gst`. See [`CollectNavMessage::get_timing_parameters_at`].
+    pub fn get_timing_parameters_at(&self, svn: Svn, gst: Gst) -> Option<NavMessageData<'_>> {
+        self.apply_nma_dont_use_policy(self.data.data.navmessage.get_timing_parameters_at(svn, gst))
+    }
+
+    fn apply_nma_dont_use_policy<'a>(
+        &self,
+        data: Option<NavMessageData<'a>>,
+    ) -> Option<NavMessageData<'a>> {
+        let nma_dont_use = self.nma_dont_use_flag()?;
+        data.map(|d| d.with_nma_dont_use(nma_dont_use))
+    }
+
+    // Returns None if the authenticated data should be withheld, and
+    // otherwise the value of the NMA "don't use" flag of the data.
+    fn nma_dont_use_flag(&self) -> Option<bool> {
+        let dont_use = self.data.data.nma_state.is_some_and(|s| s.is_dont_use());
+        match (dont_use, self.data.data.nma_dont_use_policy) {
+            (false, _) | (true, NmaDontUsePolicy::Ignore) => Some(false),
+            (true, NmaDontUsePolicy::Flag) => Some(true),
+            (true, NmaDontUsePolicy::Withhold) => None,
+        }
+    }
+
+    /// Returns an iterator over all the authenticated navigation data in the
+    /// OSNMA storage.
+    ///
+    /// The iterator gives the SVN of the satellite, the kind of the
+    /// navigation data, the GST at the start of the subframe in which the data
+    /// is stored, and the data. See [`CollectNavMessage::authenticated_data`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Osnma, PublicKey};
+    /// use galileo_osnma::storage::FullStorage;
+    /// use p256::ecdsa::VerifyingKey;
+    ///
+    /// # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+    /// #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+    /// #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+    /// # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+    /// # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+    /// let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+    /// // ... process OSNMA data ...
+    /// for (svn, kind, gst, data) in osnma.authenticated_data() {
+    ///     println!("{svn} {kind} in subframe {gst}: {data}");
+    /// }
+    /// ```
+    pub fn authenticated_data(
+        &self,
+    ) -> impl Iterator<Item = (Svn, NavDataKind, Gst, NavMessageData<'_>)> + '_ {
+        let nma_dont_use = self.nma_dont_use_flag();
+        self.data
+            .data
+            .navmessage
+            .authenticated_data()
+            .filter(move |_| nma_dont_use.is_some())
+            .map(move |(svn, kind, gst, data)| {
+                (
+                    svn,
+                    kind,
+                    gst,
+                    data.with_nma_dont_use(nma_dont_use == Some(true)),
+                )
+            })
+    }
+
+    /// Try to get the authenticated health of a satellite.
+    ///
+    /// This decodes the signal health status, data validity status and SISA
+    /// of the most recent authenticated CED and health status data (ADKD=0 and
+    /// 12) for the satellite with SVN `svn`. See the [health](crate::health)
+    /// module. If the storage does not contain any authenticated CED and health
+    /// status data for this SVN, this returns `None`.
+    ///
+    /// The policy set with [`Osnma::set_nma_dont_use_policy`] applies as in
+    /// [`Osnma::get_ced_and_status`].
+    pub fn get_health(&self, svn: Svn) -> Option<SatelliteHealth> {
+        self.get_ced_and_status(svn).map(|data| {
+            SatelliteHealth::from_ced_and_status(data.data()).with_nma_dont_use(data.nma_dont_use())
+        })
+    }
+
+    /// Gives the most recent constellation-related tags.
+    ///
+    /// These are the tags with PRND = 255, which authenticate data related to
+    /// the whole Galileo constellation. No ADKD has been defined for this data
+    /// yet, so these tags are stored but not used for authentication. See
+    /// [`ConstellationTag`](crate::navmessage::ConstellationTag).
+    pub fn constellation_tags(&self) -> &ConstellationTags {
+        self.data.data.navmessage.constellation_tags()
+    }
+
+    /// Gives the most recent tag verification results.
+    ///
+    /// This gives which tags have authenticated the navigation data of which
+    /// satellites, and which tags have failed verification. See
+    /// [`CollectNavMessage::tag_verifications`].
+    pub fn tag_verifications(&self) -> &TagVerifications {
+        self.data.data.navmessage.tag_verifications()
+    }
+
+    /// Gives the number of tags that have failed to authenticate the
+    /// navigation data of a satellite.
+    ///
+    /// See [`CollectNavMessage::failed_tags`].
+    pub fn failed_tags(&self, svn: Svn) -> u32 {
+        self.data.data.navmessage.failed_tags(svn)
+    }
+
+    /// Gives the anomalies that may indicate spoofing.
+    ///
+    /// See [`CollectNavMessage::anomalies`].
+    ///
+    /// This is only available with the `nav-diagnostics` feature.
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn anomalies(&self) -> &AnomalyTracker {
+        self.data.data.navmessage.anomalies()
+    }
+
+    /// Clears the anomalies.
+    ///
+    /// See [`CollectNavMessage::clear_anomalies`].
+    ///
+    /// This is only available with the `nav-diagnostics` feature.
+    #[cfg(feature = "nav-diagnostics")]
+    pub fn clear_anomalies(&mut self) {
+        self.data.data.navmessage.clear_anomalies()
+    }
+
+    /// Gives the GST of the last subframe in which a MACK message was received
+    /// from a satellite.
+    pub fn last_mack_gst(&self, svn: Svn) -> Option<Gst> {
+        self.data.data.last_mack[svn.index()]
+    }
+
+    /// Gives a snapshot of the authentication status of each satellite.
+    ///
+    /// This gathers the last authenticated navigation data of each
+    /// satellite, the number of failed tags, the time since the last MACK
+    /// message and the state of the TESLA chain. It is intended for client
+    /// software that displays the authentication status. See the
+    /// [status](crate::status) module.
+    pub fn status(&self) -> OsnmaStatus {
+        OsnmaStatus::new(self)
+    }
+
+    /// Compare the GNSS time against the host clock.
+    ///
+    /// The GST `gst`, which should be computed by the receiver from the
+    /// signals, is converted to UTC with the most recent authenticated GST-UTC
+    /// conversion parameters (ADKD=4) of the constellation (see
+    /// [`Osnma::get_constellation_timing`]), and compared with
+    /// `host_utc`, which gives the UTC time of the host clock at the same
+    /// moment as seconds since the Unix epoch. A warning is logged if the
+    /// divergence is larger than `threshold` seconds. See the
+    /// [timing](crate::timing) module for more details.
+    ///
+    /// If there are no authenticated timing parameters, this returns `None`.
+    pub fn check_host_clock(&self, gst: Gst, host_utc: f64, threshold: f64) -> Option<ClockCheck> {
+        let timing = self.get_constellation_timing()?;
+        let svn = timing.svn();
+        let params = GstUtcParameters::from_timing_parameters(timing.data().data());
+        let check = ClockCheck::new(&params, gst, host_utc, threshold).with_svn(svn);
+        if check.is_alarm() {
+            log::warn!(
+                "host clock diverges by {} s from GST {:?} converted to UTC \
+                 with the authenticated parameters of {}",
+                check.divergence(),
+                gst,
+                svn
+            );
+        }
+        Some(check)
+    }
+
+    /// Estimate the authentication schedule for a satellite.
+    ///
+    /// This uses the COP field of the most recently validated tags to estimate
+    /// when the navigation data of type `adkd` for the satellite with SVN `svn`
+    /// can be authenticated next, and when the stored data needs to be
+    /// refreshed. See [`AuthSchedule`] and
+    /// [`CollectNavMessage::auth_schedule`].
+    pub fn auth_schedule(&self, svn: Svn, adkd: Adkd) -> Option<AuthSchedule> {
+        self.data.data.navmessage.auth_schedule(svn, adkd)
+    }
+
+    /// Gives the cross-authentication expectation for a satellite.
+    ///
+    /// This predicts which satellites are expected to transmit tags with ADKD
+    /// `adkd` that authenticate the navigation data of the satellite with SVN
+    /// `svn` in the upcoming subframes. See [`CrossAuthExpectation`] and
+    /// [`CollectNavMessage::cross_auth_expectation`].
+    ///
+    /// This is only available with the `cross-auth` feature.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_expectation(&self, svn: Svn, adkd: Adkd) -> Option<CrossAuthExpectation> {
+        self.data.data.navmessage.cross_auth_expectation(svn, adkd)
+    }
+
+    /// Returns an iterator over the satellites whose navigation data is
+    /// expected to be authenticated by tags transmitted by a satellite.
+    ///
+    /// See [`CollectNavMessage::cross_auth_targets`].
+    ///
+    /// This is only available with the `cross-auth` feature.
+    #[cfg(feature = "cross-auth")]
+    pub fn cross_auth_targets(&self, prna: Svn, adkd: Adkd) -> impl Iterator<Item = Svn> + '_ {
+        self.data.data.navmessage.cross_auth_targets(prna, adkd)
     }
 }
 
-impl<S: StaticStorage> OsnmaDsm<S> {
+impl<S: Storage, V: Verifier> OsnmaDsm<S, V> {
     fn process_subframe(&mut self, hkroot: &HkrootMessage, mack: &MackMessage, svn: Svn, gst: Gst) {
+        if let Some(result) = self.data.verifier.poll() {
+            self.data.process_kroot_result(result);
+        }
+        if let Some(result) = self.data.verifier.poll_key() {
+            self.data.process_key_result(result);
+        }
+
         let nma_header = NmaHeader::new(hkroot[0]);
-        // Note that the NMA status obtained below is retrieved from a NMA
-        // header which is not validated. However, this NMA status is only
-        // stored and eventually used for tag validation.
-        self.data
-            .mack
-            .store(mack, svn, gst, nma_header.nma_status());
+        self.data.update_nma_state(nma_header, gst, false);
+        // Note that the NMA status and CID obtained below are retrieved from
+        // a NMA header which is not validated. However, the NMA status is only
+        // stored and eventually used for tag validation, and the CID is only
+        // used to select which of the stored TESLA chains generated the MACK
+        // message.
+        let last_mack = &mut self.data.last_mack[svn.index()];
+        *last_mack = Some(last_mack.map_or(gst, |last| last.latest(gst)));
+        let repeated = self.data.mack.get(svn, gst).is_some();
+        self.data.mack.store_with_chain_id(
+            mack,
+            svn,
+            gst,
+            nma_header.nma_status(),
+            nma_header.chain_id(),
+        );
+        if repeated {
+            // This subframe has already been processed for this SVN (for
+            // instance, because it has been received in both E1B and E5b).
+            log::debug!("skipping processing of repeated subframe for {svn} and GST {gst:?}");
+            return;
+        }
 
         let dsm_header = &hkroot[1..2].try_into().unwrap();
         let dsm_header = DsmHeader(dsm_header);
@@ -251,36 +2371,127 @@ impl<S: StaticStorage> OsnmaDsm<S> {
             self.data.process_dsm(dsm, nma_header, gst);
         }
 
-        self.data.validate_key(mack, gst);
+        self.data
+            .validate_key(mack, svn, gst, nma_header.chain_id());
+        self.data.check_watchdog(gst);
+        self.data.check_auth_states(gst);
     }
 }
 
-impl<S: StaticStorage> OsnmaData<S> {
+impl<S: Storage, V: Verifier> OsnmaData<S, V> {
+    fn is_svn_enabled(&self, svn: Svn) -> bool {
+        self.svn_enabled[svn.index()]
+    }
+
+    // Replaces the NMA state if the NMA header is more recent, or if it has
+    // been validated and it belongs to the same subframe. A header that is not
+    // validated inherits the validation of the previous state if it is equal.
+    fn update_nma_state<W>(&mut self, nma_header: NmaHeader<W>, gst: Gst, validated: bool) {
+        let replace = self.nma_state.map_or(true, |state| {
+            gst.is_after(state.gst) || (gst == state.gst && validated && !state.validated)
+        });
+        if !replace {
+            return;
+        }
+        let validated = validated
+            || self
+                .nma_state
+                .is_some_and(|s| s.validated && s.header == nma_header.data());
+        let state = NmaState::new(nma_header, self.icd_version, gst, validated);
+        if state.is_dont_use() && !self.nma_state.is_some_and(|s| s.is_dont_use()) {
+            log::warn!("NMA status changed to don't use: {state}");
+        }
+        self.nma_state = Some(state);
+    }
+
     fn process_dsm(&mut self, dsm: Dsm, nma_header: NmaHeader<NotValidated>, gst: Gst) {
         match dsm.dsm_type() {
-            DsmType::Kroot => self.process_dsm_kroot(DsmKroot(dsm.data()), nma_header, gst),
-            DsmType::Pkr => self.process_dsm_pkr(DsmPkr(dsm.data())),
+            DsmType::Kroot => {
+                self.process_dsm_kroot(dsm.id(), DsmKroot(dsm.data()), nma_header, gst)
+            }
+            DsmType::Pkr => self.process_dsm_pkr(DsmPkr(dsm.data()), gst),
         }
     }
 
     fn process_dsm_kroot(
         &mut self,
+        dsm_id: u8,
         dsm_kroot: DsmKroot,
         nma_header: NmaHeader<NotValidated>,
         gst: Gst,
     ) {
         let pkid = dsm_kroot.public_key_id();
         let Some(pubkey) = self.pubkey.applicable_pubkey(pkid) else {
+            if self.pubkey.current.is_some() {
+                self.watchdog.wrong_public_key(pkid);
+            }
             return;
         };
-        match Key::from_dsm_kroot(nma_header, dsm_kroot, pubkey) {
+        if !self.kroot_failures.should_verify(dsm_id, gst) {
+            return;
+        }
+        let request = KrootRequest::new(dsm_id, nma_header, dsm_kroot, pubkey, gst);
+        match self.verifier.verify_kroot(request) {
+            Some(result) => self.process_kroot_result(result),
+            None => log::debug!("deferred verification of KROOT with public key id {pkid}"),
+        }
+    }
+
+    fn process_kroot_result(&mut self, result: KrootResult) {
+        self.kroot_failures.record(&result);
+        let pkid = result.public_key_id();
+        match *result.result() {
             Ok((key, nma_header)) => {
                 log::info!("verified KROOT with public key id {pkid}");
                 log::info!("current NMA header: {nma_header:?}");
+                if !self
+                    .key
+                    .store_kroot(key, nma_header.chain_id(), result.gst())
+                {
+                    let diagnostic = Diagnostic::new(
+                        DiagnosticKind::FutureKroot {
+                            start_applicability: key.gst_subframe().wrapping_add_seconds(30),
+                        },
+                        None,
+                        result.gst(),
+                    );
+                    log::error!("{}; KROOT {:?} not used", diagnostic, key);
+                    self.notifier.report(diagnostic);
+                    self.watchdog.time_offset();
+                    return;
+                }
                 self.pubkey.make_pkid_current(pkid);
-                self.key.store_kroot(key, nma_header, gst);
+                self.update_nma_state(nma_header, result.gst(), true);
+                self.notifier.notify(Event::KrootVerified {
+                    cid: key.chain().chain_id(),
+                    gst: result.gst(),
+                });
+                if let Some(transition) = self.key.track_transition(
+                    nma_header.chain_id(),
+                    nma_header.chain_and_pubkey_status_icd(self.icd_version),
+                    key.chain().chain_id(),
+                    key.gst_subframe(),
+                    result.gst(),
+                ) {
+                    self.notifier.notify(Event::ChainTransitionScheduled {
+                        cid: transition.cid,
+                        next_cid: transition.next_cid.unwrap(),
+                        switch_gst: transition.switch_gst.unwrap(),
+                    });
+                }
                 self.process_nma_header(nma_header, pkid);
             }
+            Err(KrootValidationError::WrongDsmKrootChain(ChainError::UnsupportedMaclt {
+                maclt,
+            })) => {
+                let diagnostic = Diagnostic::new(
+                    DiagnosticKind::UnsupportedMaclt { maclt },
+                    None,
+                    result.gst(),
+                );
+                log::error!("{}; KROOT not used", diagnostic);
+                self.notifier.report(diagnostic);
+            }
             Err(e) => log::error!("could not verify KROOT: {:?}", e),
         }
     }
@@ -296,7 +2507,7 @@ impl<S: StaticStorage> OsnmaData<S> {
             }
             NmaStatus::DontUse => {
                 log::warn!("NMA status is don't use");
-                match nma_header.chain_and_pubkey_status() {
+                match nma_header.chain_and_pubkey_status_icd(self.icd_version) {
                     ChainAndPubkeyStatus::ChainRevoked => {
                         // current chain is revoked
                         self.key.revoke(nma_header.chain_id());
@@ -320,7 +2531,7 @@ impl<S: StaticStorage> OsnmaData<S> {
             nma_header.nma_status(),
             NmaStatus::Operational | NmaStatus::Test
         );
-        match nma_header.chain_and_pubkey_status() {
+        match nma_header.chain_and_pubkey_status_icd(self.icd_version) {
             ChainAndPubkeyStatus::Reserved => {
                 log::error!("CPKS has a reserved value");
             }
@@ -360,9 +2571,9 @@ impl<S: StaticStorage> OsnmaData<S> {
         self.key = KeyStore::empty();
     }
 
-    fn process_dsm_pkr(&mut self, dsm_pkr: DsmPkr) {
-        match dsm_pkr.new_public_key_type() {
-            NewPublicKeyType::EcdsaKey(_) => self.process_dsm_pkr_npk(dsm_pkr),
+    fn process_dsm_pkr(&mut self, dsm_pkr: DsmPkr, gst: Gst) {
+        match dsm_pkr.new_public_key_type_icd(self.icd_version) {
+            NewPublicKeyType::EcdsaKey(_) => self.process_dsm_pkr_npk(dsm_pkr, gst),
             NewPublicKeyType::OsnmaAlertMessage => self.process_dsm_pkr_alert_message(dsm_pkr),
             NewPublicKeyType::Reserved => {
                 log::error!("reserved NPKT in DSM-PKR: {:?}", dsm_pkr);
@@ -370,7 +2581,7 @@ impl<S: StaticStorage> OsnmaData<S> {
         }
     }
 
-    fn process_dsm_pkr_npk(&mut self, dsm_pkr: DsmPkr) {
+    fn process_dsm_pkr_npk(&mut self, dsm_pkr: DsmPkr, gst: Gst) {
         let Some(merkle_tree) = &self.merkle_tree else {
             log::error!("could not verify public key because Merkle tree is not loaded");
             return;
@@ -378,6 +2589,10 @@ impl<S: StaticStorage> OsnmaData<S> {
         match merkle_tree.validate_pkr(dsm_pkr) {
             Ok(pubkey) => {
                 log::info!("verified public key in DSM-PKR: {dsm_pkr:?}");
+                self.notifier.notify(Event::DsmPkrVerified {
+                    pkid: pubkey.public_key_id(),
+                    gst: gst.gst_subframe(),
+                });
                 self.pubkey.store_new_pubkey(pubkey);
             }
             Err(e) => log::error!("could not verify public key: {e:?}"),
@@ -398,9 +2613,67 @@ impl<S: StaticStorage> OsnmaData<S> {
         }
     }
 
-    fn validate_key(&mut self, mack: &MackMessage, gst: Gst) {
-        let Some(current_key) = self.key.current_key() else {
-            log::info!("no valid TESLA key for the chain in force. unable to validate MACK key");
+    fn import_tesla_key(&mut self, key: Key<Validated>, gst: Gst) -> Result<(), PeerKeyError> {
+        if key.gst_subframe().is_after(gst.gst_subframe()) {
+            return Err(PeerKeyError::FutureKey);
+        }
+        if gst.gst_subframe().subframes_difference(key.gst_subframe()) > MAX_KEY_DERIVATIONS {
+            log::warn!(
+                "refusing to import stale TESLA key {:?} (current GST {:?})",
+                key,
+                gst
+            );
+            return Err(PeerKeyError::StaleKey);
+        }
+        let cid = key.chain().chain_id();
+        if self
+            .key
+            .chain_in_force
+            .as_ref()
+            .is_some_and(|cif| cif.cid != cid)
+        {
+            return Err(PeerKeyError::WrongChain);
+        }
+        let key = match self.key.key_for_chain(cid) {
+            Some(stored) => match stored.gst_subframe().cmp_rollover(&key.gst_subframe()) {
+                Ordering::Equal => return Ok(()),
+                Ordering::Greater => return Err(PeerKeyError::OldKey),
+                Ordering::Less => stored.validate_key(&key)?,
+            },
+            None => key,
+        };
+        log::info!("importing TESLA key {:?} received from peer", key);
+        self.key.store_imported_key(key);
+        self.notifier.notify(Event::TeslaKeyValidated {
+            gst: key.gst_subframe(),
+        });
+        self.process_tags(&key);
+        Ok(())
+    }
+
+    fn set_tesla_key(&mut self, key: Key<Validated>) {
+        log::info!("setting externally validated TESLA key {:?}", key);
+        self.key.set_key(key);
+        self.notifier.notify(Event::TeslaKeyValidated {
+            gst: key.gst_subframe(),
+        });
+        self.process_tags(&key);
+    }
+
+    // Validates the TESLA key of a MACK message transmitted in a subframe whose
+    // NMA header has the chain ID `cid`. During a chain renewal, the keys of
+    // both chains are tracked, so the key of the chain `cid` is used if it is
+    // stored.
+    fn validate_key(&mut self, mack: &MackMessage, svn: Svn, gst: Gst, cid: u8) {
+        self.key.update_chain_in_force(gst);
+        let Some(current_key) = self
+            .key
+            .key_for_chain(cid)
+            .or_else(|| self.key.current_key())
+        else {
+            let diagnostic = Diagnostic::new(DiagnosticKind::MissingKey, Some(svn), gst);
+            log::info!("unable to validate MACK key: {}", diagnostic);
+            self.notifier.report(diagnostic);
             return;
         };
         let mack = Mack::new(
@@ -409,105 +2682,378 @@ impl<S: StaticStorage> OsnmaData<S> {
             current_key.chain().tag_size_bits(),
         );
         let new_key = Key::from_bitslice(mack.key(), gst, current_key.chain());
-        match current_key.gst_subframe().cmp(&new_key.gst_subframe()) {
+        match current_key
+            .gst_subframe()
+            .cmp_rollover(&new_key.gst_subframe())
+        {
             Ordering::Equal => {
                 // we already have this key; nothing to do
             }
             Ordering::Greater => {
+                let diagnostic = Diagnostic::new(DiagnosticKind::OldKey, Some(svn), gst);
                 log::warn!(
-                    "got a key in MACK which is older than our current valid key\
-                            MACK key = {:?}, current valid key = {:?}",
+                    "{}; MACK key = {:?}, current valid key = {:?}",
+                    diagnostic,
                     new_key,
                     current_key
                 );
+                self.notifier.report(diagnostic);
             }
             Ordering::Less => {
                 // attempt to validate the new key
-                match current_key.validate_key(&new_key) {
-                    Ok(new_valid_key) => {
-                        log::info!(
-                            "new TESLA key {:?} successfully validated by {:?}",
-                            new_valid_key,
-                            current_key
-                        );
-                        self.key.store_key(new_valid_key);
-                        self.process_tags(&new_valid_key);
-                    }
-                    Err(e) => log::error!(
-                        "could not validate TESLA key {:?} using {:?}: {:?}",
-                        new_key,
-                        current_key,
-                        e
-                    ),
+                let request = KeyRequest::new(current_key, new_key, svn);
+                match self.verifier.verify_key(request) {
+                    Some(result) => self.process_key_result(result),
+                    None => log::debug!("deferred validation of TESLA key {:?}", new_key),
                 }
             }
         }
     }
 
-    fn process_tags(&mut self, current_key: &Key<Validated>) {
-        let gst_mack = current_key.gst_subframe().add_seconds(-30);
-        let gst_slowmac = gst_mack.add_seconds(-300);
-        // Try to re-generate the key that was used for the MACSEQ of the
-        // Slow MAC MACK. This key might be from a previous chain.
-        let gst_k_slowmac = current_key.gst_subframe().add_seconds(-300);
-        let slowmac_chain_key = self.key.key_past_chain(gst_k_slowmac);
-        let slowmac_key = slowmac_chain_key.and_then(|k| {
-            let derivations = k.gst_subframe().subframes_difference(gst_k_slowmac);
-            if derivations >= 0 {
-                Some(k.derive(derivations.try_into().unwrap()))
-            } else {
-                None
+    fn process_key_result(&mut self, result: KeyResult) {
+        let new_key = result.key();
+        let svn = result.svn();
+        let gst = result.gst();
+        match *result.result() {
+            Ok(new_valid_key) => {
+                // The key store might have changed if the validation was
+                // deferred, so check that the key is still useful. The key
+                // can belong to either of the chains stored.
+                if !self
+                    .key
+                    .key_for_chain(new_valid_key.chain().chain_id())
+                    .is_some_and(|k| k.gst_subframe().is_before(new_valid_key.gst_subframe()))
+                {
+                    log::debug!(
+                        "discarding validated TESLA key {:?}, which is no longer needed",
+                        new_valid_key
+                    );
+                    return;
+                }
+                log::info!(
+                    "new TESLA key {:?} successfully validated by {:?}",
+                    new_valid_key,
+                    result.validating_key()
+                );
+                self.key.store_key(new_valid_key);
+                self.notifier.notify(Event::TeslaKeyValidated {
+                    gst: new_valid_key.gst_subframe(),
+                });
+                self.process_tags(&new_valid_key);
             }
-        });
+            Err(e) => {
+                let diagnostic =
+                    Diagnostic::new(DiagnosticKind::KeyValidationFailed, Some(svn), gst);
+                log::error!(
+                    "could not validate TESLA key {:?} using {:?}: {:?} ({})",
+                    new_key,
+                    result.validating_key(),
+                    e,
+                    diagnostic.hint()
+                );
+                self.notifier.report(diagnostic);
+                self.watchdog.time_offset();
+            }
+        }
+    }
+
+    fn process_tags(&mut self, current_key: &Key<Validated>) {
+        let gst_mack = current_key.gst_subframe().wrapping_add_seconds(-30);
+        self.run_audits(current_key, gst_mack);
+        #[cfg(feature = "slowmac")]
+        let gst_slowmac = gst_mack.wrapping_add_seconds(-300);
+        #[cfg(feature = "slowmac")]
+        let slowmac_key = self.slowmac_key(current_key);
         for svn in Svn::iter() {
+            if !self.is_svn_enabled(svn) {
+                continue;
+            }
             if !self.only_slowmac {
-                if let Some((mack, nma_status)) = self.mack.get(svn, gst_mack) {
+                if let Some((mack, nma_status)) =
+                    Self::mack_for_chain(&self.mack, svn, gst_mack, current_key)
+                {
                     let mack = Mack::new(
                         mack,
                         current_key.chain().key_size_bits(),
                         current_key.chain().tag_size_bits(),
                     );
-                    if let Some(mack) = Self::validate_mack(mack, current_key, svn, gst_mack) {
-                        self.navmessage
-                            .process_mack(mack, current_key, svn, gst_mack, nma_status);
+                    if let Some(mack) = Self::validate_mack(
+                        &mut self.navmessage,
+                        mack,
+                        current_key,
+                        svn,
+                        gst_mack,
+                        self.adkd_check_policy,
+                    ) {
+                        let results = self.navmessage.process_mack_with_results(
+                            mack,
+                            current_key,
+                            svn,
+                            gst_mack,
+                            nma_status,
+                        );
+                        if self.tag_events {
+                            Self::notify_tag_results(
+                                &mut self.notifier,
+                                &mack,
+                                svn,
+                                gst_mack,
+                                results,
+                            );
+                        }
+                        self.check_reserved_adkd(svn, gst_mack);
+                        let validated = results.validated;
+                        if validated != 0 {
+                            self.watchdog.tags_validated(current_key.gst_subframe());
+                            self.auditor.schedule(PendingAudit {
+                                prna: svn,
+                                gst_mack,
+                                cid: current_key.chain().chain_id(),
+                                tags: validated,
+                            });
+                        }
                     };
                 }
             }
 
-            // Try to validate Slow MAC
-            // This needs fetching a tag which is 300 seconds older than for
-            // the other ADKDs
-            if let Some(slowmac_key) = &slowmac_key {
-                if let Some((mack, nma_status)) = self.mack.get(svn, gst_slowmac) {
-                    let mack = Mack::new(
-                        mack,
-                        current_key.chain().key_size_bits(),
-                        current_key.chain().tag_size_bits(),
-                    );
-                    // Note that slowmac_key is used for validation of the MACK, while
-                    // current_key is used for validation of the Slow MAC tags it contains.
-                    if let Some(mack) = Self::validate_mack(mack, slowmac_key, svn, gst_slowmac) {
-                        self.navmessage.process_mack_slowmac(
-                            mack,
-                            current_key,
-                            svn,
-                            gst_slowmac,
-                            nma_status,
-                        );
-                    }
+            // Try to validate Slow MAC
+            // This needs fetching a tag which is 300 seconds older than for
+            // the other ADKDs
+            #[cfg(feature = "slowmac")]
+            if let Some(slowmac_key) = &slowmac_key {
+                if let Some((mack, nma_status)) =
+                    Self::mack_for_chain(&self.mack, svn, gst_slowmac, slowmac_key)
+                {
+                    let mack = Mack::new(
+                        mack,
+                        current_key.chain().key_size_bits(),
+                        current_key.chain().tag_size_bits(),
+                    );
+                    // Note that slowmac_key is used for validation of the MACK, while
+                    // current_key is used for validation of the Slow MAC tags it contains.
+                    if let Some(mack) = Self::validate_mack(
+                        &mut self.navmessage,
+                        mack,
+                        slowmac_key,
+                        svn,
+                        gst_slowmac,
+                        self.adkd_check_policy,
+                    ) {
+                        let results = self.navmessage.process_mack_slowmac_with_results(
+                            mack,
+                            current_key,
+                            svn,
+                            gst_slowmac,
+                            nma_status,
+                        );
+                        if self.tag_events {
+                            Self::notify_tag_results(
+                                &mut self.notifier,
+                                &mack,
+                                svn,
+                                gst_slowmac,
+                                results,
+                            );
+                        }
+                        if results.validated != 0 {
+                            self.watchdog.tags_validated(current_key.gst_subframe());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn notify_tag_results(
+        notifier: &mut Notifier<S::EventQueueDepth>,
+        mack: &Mack<Validated>,
+        prna: Svn,
+        gst_mack: Gst,
+        results: TagResults,
+    ) {
+        for j in 0..mack.num_tags() {
+            let (prnd, adkd) = if j == 0 {
+                (prna, Adkd::InavCed)
+            } else {
+                let tag = mack.tag_and_info(j);
+                let Some(prnd) = u8::try_from(tag.prnd())
+                    .ok()
+                    .and_then(|prnd| Svn::try_from(prnd).ok())
+                else {
+                    continue;
+                };
+                (prnd, tag.adkd())
+            };
+            let event = if results.validated & (1 << j) != 0 {
+                Event::TagAuthenticated {
+                    prnd,
+                    prna,
+                    adkd,
+                    gst: gst_mack,
+                }
+            } else if results.failed & (1 << j) != 0 {
+                Event::TagFailed {
+                    prnd,
+                    prna,
+                    adkd,
+                    gst: gst_mack,
+                }
+            } else {
+                continue;
+            };
+            notifier.notify(event);
+        }
+    }
+
+    // Tries to re-generate the key that was used for the MACSEQ of the Slow
+    // MAC MACK. This key might be from a previous chain.
+    #[cfg(feature = "slowmac")]
+    fn slowmac_key(&self, current_key: &Key<Validated>) -> Option<Key<Validated>> {
+        let gst_k_slowmac = current_key.gst_subframe().wrapping_add_seconds(-300);
+        let slowmac_chain_key = self.key.key_past_chain(gst_k_slowmac);
+        slowmac_chain_key.and_then(|k| {
+            let derivations = k.gst_subframe().subframes_difference(gst_k_slowmac);
+            if derivations >= 0 {
+                Some(k.derive(derivations.try_into().unwrap()))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Validates again the tags of the MACK messages that were selected for
+    // audit, using keys derived from the newer key `current_key`.
+    fn run_audits(&mut self, current_key: &Key<Validated>, gst_mack: Gst) {
+        while let Some(audit) = self.auditor.take_due(gst_mack) {
+            if audit.cid != current_key.chain().chain_id() {
+                log::debug!("skipping audit of MACK from a previous chain");
+                continue;
+            }
+            let Some((mack, nma_status)) = self.mack.get(audit.prna, audit.gst_mack) else {
+                log::debug!("skipping audit of MACK no longer stored");
+                continue;
+            };
+            let derivations = gst_mack.subframes_difference(audit.gst_mack);
+            let key = current_key.derive(derivations.try_into().unwrap());
+            let mack = Mack::new(
+                mack,
+                key.chain().key_size_bits(),
+                key.chain().tag_size_bits(),
+            );
+            let (audited, failed) = match mack.validate_with_policy(
+                &key,
+                audit.prna,
+                audit.gst_mack,
+                self.adkd_check_policy,
+            ) {
+                Ok(mack) => self.navmessage.audit_mack(
+                    &mack,
+                    &key,
+                    audit.prna,
+                    audit.gst_mack,
+                    nma_status,
+                    audit.tags,
+                ),
+                // The whole MACK message fails the audit
+                Err(_) => (audit.tags, audit.tags),
+            };
+            if failed != 0 {
+                log::error!(
+                    "audit of {} MACK at {:?} failed for tags {:#06x}",
+                    audit.prna,
+                    audit.gst_mack,
+                    failed
+                );
+                self.notifier.notify(Event::AuditDiscrepancy {
+                    prna: audit.prna,
+                    gst: audit.gst_mack,
+                    tags: failed,
+                });
+            } else {
+                log::debug!(
+                    "audit of {} MACK at {:?} passed for tags {:#06x}",
+                    audit.prna,
+                    audit.gst_mack,
+                    audited
+                );
+            }
+        }
+    }
+
+    fn check_watchdog(&mut self, gst: Gst) {
+        let have_key = self.key.current_key().is_some();
+        if let Some(kind) = self.watchdog.check(gst, have_key, self.only_slowmac) {
+            let diagnostic = Diagnostic::new(kind, None, gst.gst_subframe());
+            log::warn!("{}", diagnostic);
+            self.notifier.report(diagnostic);
+        }
+    }
+
+    fn check_auth_states(&mut self, gst: Gst) {
+        let navmessage = &self.navmessage;
+        let notifier = &mut self.notifier;
+        self.auth_states.check(
+            gst,
+            |svn, kind| {
+                match kind {
+                    NavDataKind::CedAndStatus => navmessage.get_ced_and_status(svn),
+                    NavDataKind::TimingParameters => navmessage.get_timing_parameters(svn),
                 }
-            }
+                .map(|data| data.gst())
+            },
+            |event| notifier.notify(event),
+        );
+    }
+
+    fn check_reserved_adkd(&mut self, svn: Svn, gst_mack: Gst) {
+        let stats = self.navmessage.reserved_adkd_stats();
+        // Reported only once each time that the threshold is reached
+        if stats.consecutive_macks() == RESERVED_ADKD_CONSECUTIVE_MACKS {
+            let diagnostic = Diagnostic::new(
+                DiagnosticKind::ReservedAdkd {
+                    adkd: stats.last_adkd().unwrap(),
+                },
+                Some(svn),
+                gst_mack,
+            );
+            log::warn!("{}", diagnostic);
+            self.notifier.report(diagnostic);
+        }
+    }
+
+    // Gives the MACK message for `svn` and `gst` if it was generated by the
+    // chain of `key`. During a chain renewal, the MACK messages of both chains
+    // are stored. The MACK messages stored without a chain ID are given for
+    // any chain.
+    fn mack_for_chain<'a>(
+        mack: &'a MackStorage<S>,
+        svn: Svn,
+        gst: Gst,
+        key: &Key<Validated>,
+    ) -> Option<(&'a MackMessage, NmaStatus)> {
+        if mack
+            .chain_id(svn, gst)
+            .is_some_and(|cid| cid != key.chain().chain_id())
+        {
+            log::trace!("skipping MACK for {svn} and GST {gst:?} from another chain");
+            return None;
         }
+        mack.get(svn, gst)
     }
 
     fn validate_mack<'a>(
+        navmessage: &mut CollectNavMessage<S>,
         mack: Mack<'a, NotValidated>,
         key: &Key<Validated>,
         prna: Svn,
         gst_mack: Gst,
+        policy: AdkdCheckPolicy,
     ) -> Option<Mack<'a, Validated>> {
-        match mack.validate(key, prna, gst_mack) {
+        match mack.validate_with_policy(key, prna, gst_mack, policy) {
             Err(e) => {
+                if let MackValidationError::MacseqError(_) = e {
+                    navmessage.record_macseq_failure(prna, gst_mack);
+                }
                 log::error!(
                     "error validating {} {:?} MACK {:?}: {:?}",
                     prna,
@@ -537,6 +3083,10 @@ impl PubkeyStore {
         }
     }
 
+    fn current_pkid(&self) -> Option<u8> {
+        self.current.as_ref().map(|k| k.public_key_id())
+    }
+
     fn check_consistency(&self) {
         // consistency check: if next is Some, current must also be Some
         assert!(self.next.is_none() || self.current.is_some());
@@ -623,7 +3173,7 @@ impl PubkeyStore {
 
     fn revoke(&mut self, new_pkid: u8) {
         let matches = |k: &PublicKey<Validated>| k.public_key_id() < new_pkid;
-        if self.current.as_ref().map_or(false, matches) {
+        if self.current.as_ref().is_some_and(matches) {
             log::warn!(
                 "revoking pubkeys earlier than pkid {new_pkid}: \
                         revoking current pubkey {:?}",
@@ -631,7 +3181,7 @@ impl PubkeyStore {
             );
             self.current = None;
         }
-        if self.next.as_ref().map_or(false, matches) {
+        if self.next.as_ref().is_some_and(matches) {
             log::warn!(
                 "revoking pubkeys earlier than pkid {new_pkid}: \
                         next pubkey {:?}",
@@ -647,12 +3197,26 @@ impl KeyStore {
         KeyStore {
             keys: [None; 2],
             chain_in_force: None,
+            pending: None,
+            prepositioned: None,
+            transition: None,
         }
     }
 
-    fn store_kroot(&mut self, key: Key<Validated>, nma_header: NmaHeader<Validated>, gst: Gst) {
+    // Stores a KROOT that has been verified in a DSM-KROOT completed in the
+    // subframe `gst` and whose NMA header has the chain ID `cid`. Returns false
+    // if the KROOT is not stored because the chain in force according to the
+    // NMA header starts in the future.
+    fn store_kroot(&mut self, key: Key<Validated>, cid: u8, gst: Gst) -> bool {
         let kid = key.chain().chain_id();
-        let cid = nma_header.chain_id();
+        // The GST of the first TESLA key of the chain
+        let start_applicability = key.gst_subframe().wrapping_add_seconds(30);
+        if kid == cid
+            && start_applicability.subframes_difference(gst)
+                > KROOT_APPLICABILITY_TOLERANCE_SUBFRAMES
+        {
+            return false;
+        }
         match (&self.keys[0], &self.keys[1]) {
             (Some(k), _) if k.chain().chain_id() == kid => {
                 // do nothing; we already have a key for the same chain
@@ -681,20 +3245,64 @@ impl KeyStore {
                 }
             }
         }
+        if kid != cid && start_applicability.is_after(gst) {
+            log::info!("chain CID={kid} pending applicability at {start_applicability:?}");
+            self.pending = Some(PendingChain {
+                cid: kid,
+                start_applicability,
+            });
+            if !self.prepositioned.is_some_and(|p| p.cid() == kid) {
+                self.prepositioned = Some(PrepositionedChain {
+                    chain: *key.chain(),
+                    kroot_gst: key.gst_subframe(),
+                    first_key_gst: None,
+                });
+            }
+        }
         // update chain in force
+        let start_applicability = match &self.chain_in_force {
+            Some(cif) if cif.cid != cid => Some(
+                self.pending
+                    .filter(|p| p.cid == cid)
+                    .map_or(gst, |p| p.start_applicability),
+            ),
+            _ => None,
+        };
         self.chain_in_force = Some(ChainInForce {
             cid,
-            start_applicability: self.chain_in_force.as_ref().and_then(|cif| {
-                if cif.cid != cid {
-                    Some(gst)
-                } else {
-                    None
-                }
-            }),
+            start_applicability,
+        });
+        if self.pending.is_some_and(|p| p.cid == cid) {
+            self.pending = None;
+        }
+        true
+    }
+
+    // Makes the pending chain in force if it is applicable at `gst`.
+    fn update_chain_in_force(&mut self, gst: Gst) {
+        let Some(pending) = self.pending else {
+            return;
+        };
+        if pending.start_applicability.is_after(gst) {
+            return;
+        }
+        self.pending = None;
+        if self.key_for_chain(pending.cid).is_none() {
+            return;
+        }
+        log::info!(
+            "chain CID={} in force since {:?}",
+            pending.cid,
+            pending.start_applicability
+        );
+        self.chain_in_force = Some(ChainInForce {
+            cid: pending.cid,
+            start_applicability: Some(pending.start_applicability),
         });
     }
 
     fn store_key(&mut self, key: Key<Validated>) {
+        self.key_validated(&key);
         let id = key.chain().chain_id();
         match (&self.keys[0], &self.keys[1]) {
             (Some(k), _) if k.chain().chain_id() == id => self.keys[0] = Some(key),
@@ -708,6 +3316,136 @@ impl KeyStore {
         }
     }
 
+    // Stores a key that has been validated by a peer. If there is no key for
+    // its chain, the key is stored like a KROOT.
+    fn store_imported_key(&mut self, key: Key<Validated>) {
+        self.key_validated(&key);
+        let cid = key.chain().chain_id();
+        let slot = self
+            .keys
+            .iter()
+            .position(|k| k.is_some_and(|k| k.chain().chain_id() == cid))
+            .or_else(|| self.keys.iter().position(|k| k.is_none()))
+            .unwrap_or_else(|| {
+                // Both slots are occupied by other chains; do not overwrite
+                // the slot for the current chain
+                match &self.chain_in_force {
+                    Some(cif) if self.keys[0].unwrap().chain().chain_id() == cif.cid => 1,
+                    _ => 0,
+                }
+            });
+        self.keys[slot] = Some(key);
+        if self.chain_in_force.is_none() {
+            self.chain_in_force = Some(ChainInForce {
+                cid,
+                start_applicability: None,
+            });
+        }
+    }
+
+    // Stores a key that has been validated externally. The chain of the key
+    // becomes the chain in force, and the key replaces any key stored for
+    // this chain.
+    fn set_key(&mut self, key: Key<Validated>) {
+        let cid = key.chain().chain_id();
+        // This keeps the key of the previous chain in force if there is a free
+        // slot or if the other slot contains another chain
+        self.store_imported_key(key);
+        if self.pending.is_some_and(|p| p.cid == cid) {
+            self.pending = None;
+        }
+        if !self
+            .chain_in_force
+            .as_ref()
+            .is_some_and(|cif| cif.cid == cid)
+        {
+            self.chain_in_force = Some(ChainInForce {
+                cid,
+                start_applicability: None,
+            });
+        }
+    }
+
+    // Records the validation of the first TESLA key of the pre-positioned
+    // chain.
+    fn key_validated(&mut self, key: &Key<Validated>) {
+        if let Some(prepositioned) = &mut self.prepositioned {
+            if prepositioned.cid() == key.chain().chain_id()
+                && prepositioned.first_key_gst.is_none()
+            {
+                log::info!(
+                    "first TESLA key {:?} of pre-positioned chain CID={} validated",
+                    key,
+                    prepositioned.cid()
+                );
+                prepositioned.first_key_gst = Some(key.gst_subframe());
+            }
+        }
+    }
+
+    // Tracks the end-of-chain transition using the NMA header `cid` and `cpks`
+    // of a verified DSM-KROOT, which belongs to the chain `kroot_cid` and has
+    // a KROOT whose GST is `kroot_gst`. The DSM-KROOT was completed in the
+    // subframe `gst`. Returns the transition if the GST at which the next
+    // chain comes into force has just become known.
+    fn track_transition(
+        &mut self,
+        cid: u8,
+        cpks: ChainAndPubkeyStatus,
+        kroot_cid: u8,
+        kroot_gst: Gst,
+        gst: Gst,
+    ) -> Option<ChainTransition> {
+        if cpks != ChainAndPubkeyStatus::EndOfChain {
+            if self.transition.is_some_and(|t| t.next_cid == Some(cid)) {
+                log::info!("TESLA chain switch to CID={cid} completed");
+            }
+            self.transition = None;
+            if self.prepositioned.is_some_and(|p| p.cid() == cid) {
+                self.prepositioned = None;
+            }
+            return None;
+        }
+        let mut transition = match self.transition {
+            Some(t) if t.cid == cid => t,
+            _ => ChainTransition {
+                cid,
+                next_cid: None,
+                detected: gst,
+                switch_gst: None,
+                both_chains_tracked: false,
+            },
+        };
+        let scheduled = kroot_cid != cid && transition.next_cid != Some(kroot_cid);
+        if kroot_cid != cid {
+            transition.next_cid = Some(kroot_cid);
+            transition.switch_gst = Some(kroot_gst.wrapping_add_seconds(30));
+        }
+        self.transition = Some(transition);
+        if scheduled {
+            log::info!("{}", transition);
+            Some(transition)
+        } else {
+            None
+        }
+    }
+
+    fn chain_transition(&self) -> Option<ChainTransition> {
+        self.transition.map(|t| ChainTransition {
+            both_chains_tracked: self.key_for_chain(t.cid).is_some()
+                && t.next_cid
+                    .is_some_and(|next_cid| self.key_for_chain(next_cid).is_some()),
+            ..t
+        })
+    }
+
+    fn key_for_chain(&self, cid: u8) -> Option<&Key<Validated>> {
+        self.keys
+            .iter()
+            .flatten()
+            .find(|&&k| k.chain().chain_id() == cid)
+    }
+
     fn current_key(&self) -> Option<&Key<Validated>> {
         self.chain_in_force.as_ref().and_then(|cif| {
             self.keys
@@ -720,11 +3458,12 @@ impl KeyStore {
     // Similar to current_key but returns a key from the other chain if the
     // requested GST is before the start of applicability of the current
     // chain. This is used to get the key for MACK validation for Slow MAC.
+    #[cfg_attr(not(feature = "slowmac"), allow(dead_code))]
     fn key_past_chain(&self, gst: Gst) -> Option<&Key<Validated>> {
         self.chain_in_force
             .as_ref()
             .and_then(|cif| match cif.start_applicability {
-                Some(gst0) if gst0 > gst => {
+                Some(gst0) if gst0.is_after(gst) => {
                     // Requested time is before the start of the applicability.
                     // Get the key from the other slot (if occupied).
                     self.keys
@@ -737,6 +3476,12 @@ impl KeyStore {
     }
 
     fn revoke(&mut self, cid: u8) {
+        if self.pending.is_some_and(|p| p.cid == cid) {
+            self.pending = None;
+        }
+        if self.prepositioned.is_some_and(|p| p.cid() == cid) {
+            self.prepositioned = None;
+        }
         for k in &mut self.keys {
             if let Some(key) = k {
                 if key.chain().chain_id() == cid {
@@ -747,3 +3492,934 @@ impl KeyStore {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::SmallStorage;
+    use crate::tesla::Chain;
+    use crate::testing;
+    use crate::types::BitSlice;
+    use hex_literal::hex;
+    use p256::ecdsa::VerifyingKey;
+
+    fn test_osnma() -> Osnma<SmallStorage> {
+        // Dummy public key
+        let pubkey = hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+             22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        );
+        let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+        let pubkey = PublicKey::from_p256(pubkey, 2).force_valid();
+        Osnma::from_pubkey(pubkey, false)
+    }
+
+    #[test]
+    fn builder() {
+        let with_pkid = |pkid| {
+            let pubkey = hex!(
+                "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+                 22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+            );
+            let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+            PublicKey::from_p256(pubkey, pkid).force_valid()
+        };
+        let pkids = |osnma: &Osnma<SmallStorage>| {
+            let store = &osnma.data.data.pubkey;
+            (
+                store.current.as_ref().map(|k| k.public_key_id()),
+                store.next.as_ref().map(|k| k.public_key_id()),
+            )
+        };
+
+        let osnma = OsnmaBuilder::new()
+            .with_pubkey(with_pkid(3))
+            .with_pubkey(with_pkid(1))
+            .with_pubkey(with_pkid(2))
+            .build::<SmallStorage>();
+        assert_eq!(pkids(&osnma), (Some(1), Some(3)));
+        assert_eq!(osnma.min_authbits(), 40);
+
+        let osnma = OsnmaBuilder::new()
+            .with_merkle_tree([0; 32])
+            .with_min_authbits(80)
+            .only_slowmac(true)
+            .build::<SmallStorage>();
+        assert_eq!(pkids(&osnma), (None, None));
+        assert!(osnma.data.data.only_slowmac);
+        assert_eq!(osnma.min_authbits(), 80);
+
+        // The threshold cannot be lower than the minimum of the guidelines
+        let osnma = OsnmaBuilder::new()
+            .with_pubkey(with_pkid(1))
+            .with_min_authbits(16)
+            .build::<SmallStorage>();
+        assert_eq!(osnma.min_authbits(), 40);
+    }
+
+    #[test]
+    #[should_panic(expected = "a Merkle tree root, a public key or a TESLA key is needed")]
+    fn builder_without_keys() {
+        OsnmaBuilder::new().build::<SmallStorage>();
+    }
+
+    #[test]
+    fn import_tesla_key() {
+        let chain = testing::chain();
+        let gst = testing::tesla_key_gst();
+        let key = testing::tesla_key();
+        let previous_key = key.one_way_function();
+
+        let mut osnma = test_osnma();
+        assert!(osnma.latest_tesla_key().is_none());
+        assert_eq!(
+            osnma.import_tesla_key(key, gst.add_subframes(-1)),
+            Err(PeerKeyError::FutureKey)
+        );
+        assert_eq!(
+            osnma.import_tesla_key(previous_key, gst.add_subframes(MAX_KEY_DERIVATIONS + 2)),
+            Err(PeerKeyError::StaleKey)
+        );
+        assert_eq!(osnma.import_tesla_key(previous_key, gst), Ok(()));
+        assert_eq!(osnma.latest_tesla_key(), Some(previous_key));
+        assert_eq!(osnma.import_tesla_key(previous_key, gst), Ok(()));
+        assert_eq!(osnma.import_tesla_key(key, gst), Ok(()));
+        assert_eq!(osnma.latest_tesla_key(), Some(key));
+        // Each imported key generates an event
+        for k in [previous_key, key] {
+            assert_eq!(
+                osnma.poll_event(),
+                Some(Event::TeslaKeyValidated {
+                    gst: k.gst_subframe()
+                })
+            );
+        }
+        assert_eq!(osnma.poll_event(), None);
+        assert_eq!(
+            osnma.import_tesla_key(previous_key, gst),
+            Err(PeerKeyError::OldKey)
+        );
+        // A key that does not follow in the TESLA chain is rejected
+        let wrong_key = Key::from_slice(&[0xaa; 16], gst.add_subframes(1), &chain).force_valid();
+        assert_eq!(
+            osnma.import_tesla_key(wrong_key, gst.add_subframes(1)),
+            Err(PeerKeyError::Validation(
+                ValidationError::WrongOneWayFunction
+            ))
+        );
+        // A key from another chain is rejected
+        let mut dsm_kroot_other = testing::DSM_KROOT;
+        dsm_kroot_other[1] = 0x90; // CIDKR = 2
+        let other_chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot_other)).unwrap();
+        let other_key = Key::from_slice(&[0xaa; 16], gst, &other_chain).force_valid();
+        assert_eq!(
+            osnma.import_tesla_key(other_key, gst),
+            Err(PeerKeyError::WrongChain)
+        );
+    }
+
+    #[test]
+    fn set_tesla_key() {
+        let gst = testing::tesla_key_gst();
+        let key = testing::tesla_key();
+        let previous_key = key.one_way_function();
+
+        // A TESLA key is enough to build the black box
+        let mut osnma = OsnmaBuilder::new()
+            .with_tesla_key(key)
+            .build::<SmallStorage>();
+        assert_eq!(osnma.latest_tesla_key(), Some(key));
+        assert_eq!(
+            osnma.poll_event(),
+            Some(Event::TeslaKeyValidated {
+                gst: key.gst_subframe()
+            })
+        );
+        // An older key replaces the stored key
+        osnma.set_tesla_key(previous_key);
+        assert_eq!(osnma.latest_tesla_key(), Some(previous_key));
+        // A key from another chain becomes the chain in force
+        let mut dsm_kroot_other = testing::DSM_KROOT;
+        dsm_kroot_other[1] = 0x90; // CIDKR = 2
+        let other_chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot_other)).unwrap();
+        let other_key = Key::from_slice(&[0xaa; 16], gst, &other_chain).force_valid();
+        osnma.set_tesla_key(other_key);
+        assert_eq!(osnma.latest_tesla_key(), Some(other_key));
+        assert_eq!(osnma.data.data.key.key_for_chain(1), Some(&previous_key));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn save_load_file() {
+        let gst = testing::tesla_key_gst();
+        let key = testing::tesla_key();
+        let secret = b"secret";
+        let path =
+            std::env::temp_dir().join(format!("galileo-osnma-test-{}.state", std::process::id()));
+
+        let mut osnma = test_osnma();
+        assert!(!osnma.save_to_file(&path, secret).unwrap());
+        assert!(!path.exists());
+        assert_eq!(
+            osnma.load_from_file(&path, secret, gst).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        osnma.import_tesla_key(key, gst).unwrap();
+        assert!(osnma.save_to_file(&path, secret).unwrap());
+
+        let mut restored = test_osnma();
+        assert_eq!(
+            restored
+                .load_from_file(&path, b"wrong secret", gst)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        // Time-travel protection
+        for wrong_gst in [
+            gst.add_subframes(-1),
+            gst.add_subframes(MAX_KEY_DERIVATIONS + 1),
+        ] {
+            assert_eq!(
+                restored
+                    .load_from_file(&path, secret, wrong_gst)
+                    .unwrap_err()
+                    .kind(),
+                std::io::ErrorKind::InvalidData
+            );
+            assert!(restored.latest_tesla_key().is_none());
+        }
+        restored
+            .load_from_file(&path, secret, gst.add_subframes(MAX_KEY_DERIVATIONS))
+            .unwrap();
+        assert_eq!(restored.latest_tesla_key(), Some(key));
+
+        let mut data = std::fs::read(&path).unwrap();
+        data[10] ^= 1;
+        std::fs::write(&path, data).unwrap();
+        assert_eq!(
+            test_osnma()
+                .load_from_file(&path, secret, gst)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn export_import_state() {
+        let gst = testing::tesla_key_gst();
+        let key = testing::tesla_key();
+        let secret = b"secret";
+
+        let mut osnma = test_osnma();
+        assert!(osnma.export_state(secret).is_none());
+        osnma.import_tesla_key(key, gst).unwrap();
+        let state = osnma.export_state(secret).unwrap();
+
+        // The public key is restored into a black box that only has the
+        // Merkle tree root
+        let root = [0x5a; 32];
+        let mut restored = Osnma::<SmallStorage>::from_merkle_tree(root, None, false);
+        assert_eq!(
+            restored.import_state(&state, b"wrong secret", gst),
+            Err(StateError::WrongMac)
+        );
+        assert_eq!(
+            restored.import_state(&state, secret, gst.add_subframes(-1)),
+            Err(StateError::Key(PeerKeyError::FutureKey))
+        );
+        assert!(restored.latest_tesla_key().is_none());
+        assert_eq!(restored.data.data.pubkey.current_pkid(), None);
+        restored
+            .import_state(&state, secret, gst.add_subframes(1))
+            .unwrap();
+        assert_eq!(restored.latest_tesla_key(), Some(key));
+        assert_eq!(restored.data.data.pubkey.current_pkid(), Some(2));
+        assert_eq!(
+            restored.data.data.merkle_tree.as_ref().unwrap().root(),
+            &root
+        );
+    }
+
+    #[test]
+    fn kroot_applicability() {
+        let mut dsm_kroot = testing::DSM_KROOT;
+        let chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot)).unwrap();
+        dsm_kroot[1] = 0x90; // CIDKR = 2
+        let next_chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot)).unwrap();
+        let gst = Gst::new(1176, 121080);
+        let start = Gst::new(1176, 3600 * 34);
+        let kroot = Key::from_slice(&[0xaa; 16], Gst::new(1176, 3600 * 30 - 30), &chain);
+        let next_kroot = Key::from_slice(&[0xbb; 16], start.add_seconds(-30), &next_chain);
+        let (kroot, next_kroot) = (kroot.force_valid(), next_kroot.force_valid());
+
+        // A KROOT for the chain in force that is not applicable yet is
+        // refused
+        let mut keys = KeyStore::empty();
+        assert!(!keys.store_kroot(next_kroot, 2, gst));
+        assert!(keys.current_key().is_none());
+        assert!(keys.store_kroot(next_kroot, 2, start.add_subframes(-1)));
+        assert_eq!(keys.current_key(), Some(&next_kroot));
+
+        // The KROOT of the next chain is pending until it is applicable
+        let mut keys = KeyStore::empty();
+        assert!(keys.store_kroot(kroot, 1, gst));
+        assert!(keys.store_kroot(next_kroot, 1, gst));
+        assert_eq!(keys.current_key(), Some(&kroot));
+        keys.update_chain_in_force(start.add_subframes(-1));
+        assert_eq!(keys.current_key(), Some(&kroot));
+        keys.update_chain_in_force(start);
+        assert_eq!(keys.current_key(), Some(&next_kroot));
+        assert_eq!(keys.key_past_chain(start.add_subframes(-1)), Some(&kroot));
+        assert_eq!(keys.key_past_chain(start), Some(&next_kroot));
+        // The start of applicability is kept when the NMA header indicates the
+        // new chain
+        assert!(keys.store_kroot(next_kroot, 2, start.add_subframes(10)));
+        assert_eq!(keys.key_past_chain(start), Some(&next_kroot));
+    }
+
+    #[test]
+    fn wn_rollover() {
+        let mut dsm_kroot = testing::DSM_KROOT;
+        let chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot)).unwrap();
+        dsm_kroot[1] = 0x90; // CIDKR = 2
+        let next_chain = Chain::from_dsm_kroot(DsmKroot(&dsm_kroot)).unwrap();
+        // The next chain comes into force at the week number rollover
+        let start = Gst::new(0, 0);
+        let before = Gst::new(4095, 604770);
+        assert_eq!(start.wrapping_add_subframes(-1), before);
+        let gst = start.wrapping_add_subframes(-10);
+        let kroot = Key::from_slice(&[0xaa; 16], Gst::new(4095, 3600 * 30 - 30), &chain);
+        let next_kroot = Key::from_slice(&[0xbb; 16], before, &next_chain);
+        let (kroot, next_kroot) = (kroot.force_valid(), next_kroot.force_valid());
+
+        let mut keys = KeyStore::empty();
+        assert!(keys.store_kroot(kroot, 1, gst));
+        assert!(keys.store_kroot(next_kroot, 1, gst));
+        assert_eq!(keys.current_key(), Some(&kroot));
+        keys.update_chain_in_force(before);
+        assert_eq!(keys.current_key(), Some(&kroot));
+        keys.update_chain_in_force(start);
+        assert_eq!(keys.current_key(), Some(&next_kroot));
+        assert_eq!(keys.key_past_chain(before), Some(&kroot));
+        assert_eq!(keys.key_past_chain(start), Some(&next_kroot));
+
+        // Import of TESLA keys across the rollover
+        let key = Key::from_slice(&[0xcc; 16], start, &chain).force_valid();
+        let previous_key = key.one_way_function();
+        assert_eq!(previous_key.gst_subframe(), before);
+        let mut osnma = test_osnma();
+        assert_eq!(
+            osnma.import_tesla_key(key, before),
+            Err(PeerKeyError::FutureKey)
+        );
+        assert_eq!(osnma.import_tesla_key(previous_key, before), Ok(()));
+        assert_eq!(osnma.import_tesla_key(key, start), Ok(()));
+        assert_eq!(osnma.latest_tesla_key(), Some(key));
+        assert_eq!(
+            osnma.import_tesla_key(previous_key, start),
+            Err(PeerKeyError::OldKey)
+        );
+    }
+
+    #[test]
+    fn prepositioned_chain() {
+        let chain = testing::chain();
+        let mut next_dsm_kroot = testing::DSM_KROOT;
+        next_dsm_kroot[1] = 0x90; // CIDKR = 2
+        let next_chain = Chain::from_dsm_kroot(DsmKroot(&next_dsm_kroot)).unwrap();
+        let gst = Gst::new(1176, 121080);
+        let start = Gst::new(1176, 3600 * 34);
+        let kroot = Key::from_slice(&[0xaa; 16], Gst::new(1176, 3600 * 30 - 30), &chain);
+        let next_kroot = Key::from_slice(&[0xbb; 16], start.add_seconds(-30), &next_chain);
+        let next_key = Key::from_slice(&[0xcc; 16], start, &next_chain);
+        let (kroot, next_kroot, next_key) = (
+            kroot.force_valid(),
+            next_kroot.force_valid(),
+            next_key.force_valid(),
+        );
+
+        let mut keys = KeyStore::empty();
+        assert!(keys.store_kroot(kroot, 1, gst));
+        assert_eq!(keys.prepositioned, None);
+        assert!(keys.store_kroot(next_kroot, 1, gst));
+        let prepositioned = keys.prepositioned.unwrap();
+        assert_eq!(prepositioned.cid(), 2);
+        assert_eq!(prepositioned.chain(), &next_chain);
+        assert_eq!(prepositioned.start_applicability(), start);
+        assert!(!prepositioned.is_active(gst));
+        assert!(prepositioned.is_active(start));
+        assert_eq!(prepositioned.first_key_gst(), None);
+        assert_eq!(
+            prepositioned.to_string(),
+            "CID=2 pre-positioned; in force at WN=1176 TOW=122400; no keys validated"
+        );
+
+        // The first key of the next chain is validated as soon as it is
+        // broadcast
+        keys.update_chain_in_force(start);
+        assert_eq!(keys.current_key(), Some(&next_kroot));
+        keys.store_key(next_key);
+        let prepositioned = keys.prepositioned.unwrap();
+        assert_eq!(prepositioned.first_key_gst(), Some(start));
+        // The KROOT of the next chain received again does not reset the
+        // pre-positioned chain
+        assert!(keys.store_kroot(next_kroot, 2, start.add_subframes(1)));
+        assert_eq!(keys.prepositioned, Some(prepositioned));
+
+        // The pre-positioned chain is removed when the switch is completed
+        let eoc = ChainAndPubkeyStatus::EndOfChain;
+        keys.track_transition(1, eoc, 2, next_kroot.gst_subframe(), gst);
+        assert!(keys.prepositioned.is_some());
+        let nominal = ChainAndPubkeyStatus::Nominal;
+        keys.track_transition(2, nominal, 2, next_kroot.gst_subframe(), start);
+        assert_eq!(keys.prepositioned, None);
+    }
+
+    #[test]
+    fn dual_chain() {
+        let chain = testing::chain();
+        let mut next_dsm_kroot = testing::DSM_KROOT;
+        next_dsm_kroot[1] = 0x90; // CIDKR = 2
+        let next_chain = Chain::from_dsm_kroot(DsmKroot(&next_dsm_kroot)).unwrap();
+        let start = Gst::new(1176, 3600 * 34);
+        let key_bytes = [0xaa; 16];
+        let next_key_bytes = [0xcc; 16];
+        let key = Key::from_slice(&key_bytes, start, &chain).force_valid();
+        let next_key = Key::from_slice(&next_key_bytes, start, &next_chain).force_valid();
+
+        let mut osnma = test_osnma();
+        let data = &mut osnma.data.data;
+        let gst = start.add_subframes(-1);
+        assert!(data.key.store_kroot(key.one_way_function(), 1, gst));
+        assert!(data.key.store_kroot(next_key.one_way_function(), 1, gst));
+
+        // MACK messages of both chains broadcast in the subframe in which the
+        // next chain comes into force
+        let mack = |bytes: &[u8; 16]| {
+            let mut mack = [0; 60];
+            let start = chain.num_tags() * (chain.tag_size_bits() + 16);
+            BitSlice::from_slice_mut(&mut mack)[start..start + 128]
+                .copy_from_bitslice(BitSlice::from_slice(bytes));
+            mack
+        };
+        let svn = Svn::try_from(11).unwrap();
+        let next_svn = Svn::try_from(12).unwrap();
+        data.validate_key(&mack(&next_key_bytes), next_svn, start, 2);
+        assert_eq!(data.key.current_key(), Some(&next_key));
+        // The key of the previous chain is still validated
+        data.validate_key(&mack(&key_bytes), svn, start, 1);
+        assert_eq!(data.key.key_for_chain(1), Some(&key));
+        assert_eq!(data.key.current_key(), Some(&next_key));
+        for _ in 0..2 {
+            assert_eq!(
+                osnma.poll_event(),
+                Some(Event::TeslaKeyValidated { gst: start })
+            );
+        }
+        assert_eq!(osnma.poll_event(), None);
+
+        // The MACK messages are only processed with the key of their chain
+        let data = &mut osnma.data.data;
+        data.mack
+            .store_with_chain_id(&mack(&key_bytes), svn, start, NmaStatus::Test, 1);
+        data.mack
+            .store(&mack(&key_bytes), next_svn, start, NmaStatus::Test);
+        type Data = OsnmaData<SmallStorage, ImmediateVerifier>;
+        assert!(Data::mack_for_chain(&data.mack, svn, start, &key).is_some());
+        assert!(Data::mack_for_chain(&data.mack, svn, start, &next_key).is_none());
+        assert!(Data::mack_for_chain(&data.mack, next_svn, start, &next_key).is_some());
+    }
+
+    #[test]
+    fn chain_transition() {
+        let mut keys = KeyStore::empty();
+        let gst = Gst::new(1176, 121080);
+        let kroot_gst = Gst::new(1176, 3600 * 36 - 30);
+        assert_eq!(
+            keys.track_transition(1, ChainAndPubkeyStatus::Nominal, 1, kroot_gst, gst),
+            None
+        );
+        assert_eq!(keys.chain_transition(), None);
+
+        // End of chain announced before the next DSM-KROOT is broadcast
+        let eoc = ChainAndPubkeyStatus::EndOfChain;
+        assert_eq!(keys.track_transition(1, eoc, 1, kroot_gst, gst), None);
+        let transition = keys.chain_transition().unwrap();
+        assert_eq!(transition.cid(), 1);
+        assert_eq!(transition.next_cid(), None);
+        assert_eq!(transition.detected_gst(), gst);
+        assert_eq!(transition.seconds_to_switch(gst), None);
+        assert!(!transition.both_chains_tracked());
+
+        // DSM-KROOT of the next chain
+        let later = gst.add_subframes(10);
+        let scheduled = keys.track_transition(1, eoc, 2, kroot_gst, later).unwrap();
+        assert_eq!(scheduled.next_cid(), Some(2));
+        assert_eq!(scheduled.detected_gst(), gst);
+        let switch = Gst::new(1176, 3600 * 36);
+        assert_eq!(scheduled.switch_gst(), Some(switch));
+        assert_eq!(scheduled.seconds_to_switch(later), Some(129600 - 121380));
+        assert_eq!(
+            scheduled.seconds_to_switch(Gst::new(1175, 604799)),
+            Some(129601)
+        );
+        assert_eq!(
+            scheduled.seconds_to_switch(switch.add_seconds(30)),
+            Some(-30)
+        );
+        assert_eq!(keys.chain_transition(), Some(scheduled));
+        // The transition is only scheduled once
+        assert_eq!(keys.track_transition(1, eoc, 2, kroot_gst, later), None);
+        assert_eq!(keys.track_transition(1, eoc, 1, kroot_gst, later), None);
+        assert_eq!(keys.chain_transition(), Some(scheduled));
+
+        // The next chain comes into force
+        assert_eq!(
+            keys.track_transition(2, ChainAndPubkeyStatus::Nominal, 2, kroot_gst, switch),
+            None
+        );
+        assert_eq!(keys.chain_transition(), None);
+    }
+
+    #[test]
+    fn diff() {
+        use crate::diff::Side;
+
+        let mut left = test_osnma();
+        let mut right = test_osnma();
+        assert_eq!(left.diff(&right).next(), None);
+
+        let gst = Gst::new(1177, 175740);
+        let e12 = Svn::try_from(12).unwrap();
+        let e19 = Svn::try_from(19).unwrap();
+        // INAV word type 1
+        let mut word = [0; 16];
+        word[0] = 1 << 2;
+        left.feed_inav(&word, e12, gst, InavBand::E1B);
+        right.feed_inav(&word, e12, gst, InavBand::E1B);
+        assert_eq!(left.diff(&right).next(), None);
+
+        word[5] = 0xff;
+        right.feed_inav(&word, e19, gst, InavBand::E1B);
+        assert_eq!(
+            left.diff(&right).collect::<Vec<_>>(),
+            [Difference::NavDataMissing {
+                kind: NavDataKind::CedAndStatus,
+                svn: e19,
+                gst,
+                missing_in: Side::Left
+            }]
+        );
+
+        left.feed_inav(&word, e12, gst, InavBand::E1B);
+        let differences = left.diff(&right).collect::<Vec<_>>();
+        assert_eq!(
+            differences,
+            [
+                Difference::NavDataContents {
+                    kind: NavDataKind::CedAndStatus,
+                    svn: e12,
+                    gst,
+                },
+                Difference::NavDataMissing {
+                    kind: NavDataKind::CedAndStatus,
+                    svn: e19,
+                    gst,
+                    missing_in: Side::Left
+                }
+            ]
+        );
+        assert_eq!(
+            differences[1].to_string(),
+            format!("E19 CED and status at {gst} missing in left")
+        );
+    }
+
+    #[test]
+    fn gst_guard() {
+        use crate::gstguard::GstRejection;
+
+        let mut osnma = test_osnma();
+        osnma.set_gst_guard(Some(GstGuardPolicy::DEFAULT));
+        let gst = Gst::new(1177, 175740);
+        let svn = Svn::try_from(12).unwrap();
+        let word = [0; 16];
+        let osnma_data = [0xff; 5];
+        osnma.feed_inav(&word, svn, gst, InavBand::E1B);
+        osnma.feed_osnma(&osnma_data, svn, gst);
+        // The receiver jumps back 40 seconds
+        let glitch = gst.add_seconds(-40);
+        osnma.feed_inav(&word, svn, glitch, InavBand::E1B);
+        osnma.feed_osnma(&osnma_data, svn, glitch);
+        let stats = osnma.gst_rejections();
+        assert_eq!(stats.regressions(), 2);
+        assert_eq!(stats.jumps(), 0);
+        let mut rejected = 0;
+        while let Some(event) = osnma.poll_event() {
+            if let Event::Diagnostic(diagnostic) = event {
+                assert_eq!(
+                    diagnostic.kind(),
+                    DiagnosticKind::GstRejected {
+                        rejection: GstRejection::Regression
+                    }
+                );
+                assert_eq!(diagnostic.gst(), glitch.gst_subframe());
+                rejected += 1;
+            }
+        }
+        assert_eq!(rejected, 2);
+        // The glitched data has not been stored
+        assert_eq!(
+            osnma.subframe_collector().current_pages(svn).num_received(),
+            1
+        );
+    }
+
+    #[test]
+    fn try_feed() {
+        use crate::gstguard::GstRejection;
+
+        let mut osnma = test_osnma();
+        let gst = Gst::new(1177, 175740);
+        let svn = Svn::try_from(12).unwrap();
+        // Word type 1
+        let mut word = [0; 16];
+        word[0] = 1 << 2;
+        let osnma_data = [0xff; 5];
+        assert_eq!(osnma.try_feed_inav(&word, svn, gst, InavBand::E1B), Ok(()));
+        assert_eq!(osnma.try_feed_osnma(&osnma_data, svn, gst), Ok(()));
+        assert_eq!(
+            osnma.try_feed_osnma(&[0; 5], svn, gst),
+            Err(FeedError::NoOsnmaData)
+        );
+        let mut dummy = [0; 16];
+        dummy[0] = 63 << 2;
+        assert_eq!(
+            osnma.try_feed_inav(&dummy, svn, gst, InavBand::E1B),
+            Err(FeedError::DummyWord)
+        );
+
+        osnma.set_gst_guard(Some(GstGuardPolicy::DEFAULT));
+        assert_eq!(osnma.try_feed_inav(&word, svn, gst, InavBand::E1B), Ok(()));
+        let glitch = gst.add_seconds(-40);
+        assert_eq!(
+            osnma.try_feed_inav(&word, svn, glitch, InavBand::E1B),
+            Err(FeedError::GstRejected(GstRejection::Regression))
+        );
+
+        // SmallStorage only has room for 12 satellites
+        for j in 1..=12 {
+            let other = Svn::try_from(12 + j).unwrap();
+            let result = osnma.try_feed_inav(&word, other, gst, InavBand::E1B);
+            if j < 12 {
+                assert_eq!(result, Ok(()));
+            } else {
+                assert_eq!(result, Err(FeedError::StorageEviction { evicted: svn }));
+            }
+        }
+
+        // The subframe of the OSNMA data of svn is incomplete
+        let next = gst.add_subframes(1);
+        assert_eq!(
+            osnma.try_feed_osnma(&osnma_data, svn, next),
+            Err(FeedError::IncompleteSubframe {
+                svn,
+                gst,
+                received_pages: 1
+            })
+        );
+
+        osnma.set_svn_enabled(svn, false);
+        assert_eq!(
+            osnma.try_feed_inav(&word, svn, next, InavBand::E1B),
+            Err(FeedError::SvnDisabled)
+        );
+        assert_eq!(
+            osnma.try_feed_osnma(&osnma_data, svn, next),
+            Err(FeedError::SvnDisabled)
+        );
+    }
+
+    #[cfg(feature = "signer")]
+    #[test]
+    fn unsupported_maclt() {
+        use crate::signer::{ChainConfig, SigningKey, TeslaChain};
+        use crate::tesla::{HashFunction, MacFunction};
+        use p256::ecdsa::signature::Signer;
+        use sha2::{Digest, Sha256};
+
+        let signing_key = p256::ecdsa::SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let pubkey = PublicKey::from_p256(*signing_key.verifying_key(), 1).force_valid();
+        let kroot_time = Gst::new(1300, 3600);
+        let config = ChainConfig {
+            chain_id: 1,
+            public_key_id: 1,
+            hash_function: HashFunction::Sha256,
+            mac_function: MacFunction::HmacSha256,
+            key_size_bits: 128,
+            tag_size_bits: 40,
+            mac_lookup_table: 34,
+            alpha: 0x25d3964da3a2,
+            kroot_time,
+        };
+        let chain = TeslaChain::generate(config, &[0x55; 16], 1).unwrap();
+        // NMAS = test, CID = 1, CPKS = nominal
+        let nma_header = 0x52;
+        let mut dsm_kroot = chain
+            .dsm_kroot(nma_header, &SigningKey::P256(signing_key.clone()))
+            .unwrap();
+
+        // Replace the MAC look-up table by one that is not supported and sign
+        // the DSM-KROOT again, so that it passes the signature verification
+        let maclt = 0;
+        dsm_kroot[3] = maclt;
+        let kroot_end = 13 + 16;
+        let signature_end = kroot_end + 64;
+        let mut message = [0; 29];
+        message[0] = nma_header;
+        message[1..].copy_from_slice(&dsm_kroot[1..kroot_end]);
+        let signature: p256::ecdsa::Signature = signing_key.sign(&message);
+        dsm_kroot[kroot_end..signature_end].copy_from_slice(&signature.to_bytes());
+        let hash = Sha256::new()
+            .chain_update(message)
+            .chain_update(signature.to_bytes())
+            .finalize();
+        let padding_len = dsm_kroot.len() - signature_end;
+        dsm_kroot[signature_end..].copy_from_slice(&hash[..padding_len]);
+
+        let mut osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+        let gst = kroot_time.add_subframes(1);
+        osnma
+            .data
+            .data
+            .process_dsm_kroot(1, DsmKroot(&dsm_kroot), NmaHeader::new(nma_header), gst);
+        let Some(Event::Diagnostic(diagnostic)) = osnma.poll_event() else {
+            panic!("no diagnostic reported");
+        };
+        assert_eq!(
+            diagnostic.kind(),
+            DiagnosticKind::UnsupportedMaclt { maclt }
+        );
+        assert_eq!(diagnostic.gst(), gst);
+        // The KROOT has not been used
+        assert_eq!(osnma.poll_event(), None);
+        assert_eq!(osnma.latest_tesla_key(), None);
+    }
+
+    #[cfg(feature = "signer")]
+    #[test]
+    fn icd_version() {
+        use crate::signer::{ChainConfig, SigningKey, TeslaChain};
+        use crate::tesla::{HashFunction, MacFunction};
+
+        let signing_key = p256::ecdsa::SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let pubkey = PublicKey::from_p256(*signing_key.verifying_key(), 1).force_valid();
+        let kroot_time = Gst::new(1300, 3600);
+        let config = ChainConfig {
+            chain_id: 1,
+            public_key_id: 1,
+            hash_function: HashFunction::Sha256,
+            mac_function: MacFunction::HmacSha256,
+            key_size_bits: 128,
+            tag_size_bits: 40,
+            mac_lookup_table: 34,
+            alpha: 0x25d3964da3a2,
+            kroot_time,
+        };
+        let chain = TeslaChain::generate(config, &[0x55; 16], 1).unwrap();
+        // NMAS = test, CID = 1, CPKS = 7, which is an alert message in the
+        // OSNMA SIS ICD v1.1 and reserved in v1.0
+        let nma_header = 0x5e;
+        let dsm_kroot = chain
+            .dsm_kroot(nma_header, &SigningKey::P256(signing_key))
+            .unwrap();
+        let gst = kroot_time.add_subframes(1);
+
+        for (version, cpks) in [
+            (IcdVersion::V1_1, ChainAndPubkeyStatus::AlertMessage),
+            (IcdVersion::V1_0, ChainAndPubkeyStatus::Reserved),
+        ] {
+            let mut osnma = Osnma::<SmallStorage>::from_pubkey(pubkey.clone(), false);
+            osnma.set_icd_version(version);
+            osnma.data.data.process_dsm_kroot(
+                1,
+                DsmKroot(&dsm_kroot),
+                NmaHeader::new(nma_header),
+                gst,
+            );
+            let state = osnma.nma_state().unwrap();
+            assert!(state.is_validated());
+            assert_eq!(state.chain_and_pubkey_status(), cpks);
+            // The alert message deletes the KROOT that has just been verified
+            // and the public key. Under the OSNMA SIS ICD v1.0 they are kept.
+            let kept = version == IcdVersion::V1_0;
+            assert_eq!(osnma.latest_tesla_key(), kept.then(|| chain.kroot()));
+            assert_eq!(osnma.data.data.pubkey.current.is_some(), kept);
+        }
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn disabled_svn() {
+        use crate::simulator::{Simulator, SimulatorConfig};
+        let config = SimulatorConfig {
+            start: Gst::new(1300, 0),
+            duration: 24,
+            ..SimulatorConfig::default()
+        };
+        let svns = config.svns.clone();
+        let disabled = svns[0];
+        let mut simulator = Simulator::new(config);
+        let mut osnma = Osnma::<SmallStorage>::from_pubkey(simulator.public_key(), false);
+        osnma.set_svn_enabled(disabled, false);
+        assert!(!osnma.is_svn_enabled(disabled));
+        let mut feed = |osnma: &mut Osnma<SmallStorage>| {
+            for page in simulator.next_subframe() {
+                osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+                osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+        };
+        for _ in 0..12 {
+            feed(&mut osnma);
+        }
+        // Only the data of the enabled satellites is authenticated
+        assert!(osnma.get_ced_and_status(disabled).is_none());
+        assert!(osnma.get_timing_parameters(disabled).is_none());
+        for &svn in &svns[1..] {
+            assert!(osnma.get_ced_and_status(svn).is_some());
+        }
+
+        osnma.set_svn_enabled(disabled, true);
+        assert!(osnma.is_svn_enabled(disabled));
+        for _ in 0..12 {
+            feed(&mut osnma);
+        }
+        assert!(osnma.get_ced_and_status(disabled).is_some());
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn adkd_check_truncate() {
+        use crate::simulator::{Simulator, SimulatorConfig};
+        for policy in [AdkdCheckPolicy::Strict, AdkdCheckPolicy::Truncate] {
+            let config = SimulatorConfig {
+                start: Gst::new(1300, 0),
+                duration: 15,
+                svns: SimulatorConfig::default().svns[..3].to_vec(),
+                ..SimulatorConfig::default()
+            };
+            let svns = config.svns.clone();
+            let mut simulator = Simulator::new(config);
+            let mut osnma = Osnma::<SmallStorage>::from_pubkey(simulator.public_key(), false);
+            osnma.set_adkd_check_policy(policy);
+            // The MACK messages of the odd subframes use the second sequence of
+            // MAC look-up table 34, in which tag3 is F12S. This is changed to
+            // ADKD=0 in the MACK of svns[0] in subframe 13. This does not
+            // affect the MACSEQ, since the tag is not FLX.
+            let tampered = Gst::new(1300, 13 * 30);
+            for _ in 0..15 {
+                for mut page in simulator.next_subframe() {
+                    // The ADKD field of tag3 is in the high nibble of byte 27
+                    // of the MACK message, which is transmitted in page 6
+                    if page.svn == svns[0] && page.gst == tampered.add_seconds(12) {
+                        page.osnma[4] &= 0x0f;
+                    }
+                    osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+                    osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+                }
+            }
+            let indices = |svn| {
+                osnma
+                    .tag_verifications()
+                    .iter()
+                    .filter(|v| v.prna() == svn && v.gst() == tampered)
+                    .map(|v| {
+                        assert!(v.is_valid());
+                        v.tag_index()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            // The F00E tag4 of the satellites that are not tampered is used.
+            // The Slow MAC tags are not verified yet.
+            assert_eq!(indices(svns[1]), [0, 1, 2, 4]);
+            let expected: &[u8] = match policy {
+                AdkdCheckPolicy::Truncate => &[0, 1, 2],
+                _ => &[],
+            };
+            assert_eq!(indices(svns[0]), expected);
+        }
+    }
+
+    #[cfg(feature = "simulator")]
+    #[test]
+    fn feed_batch() {
+        use crate::simulator::{Simulator, SimulatorConfig};
+        let config = SimulatorConfig {
+            start: Gst::new(1300, 0),
+            duration: 12,
+            page_loss: 0.05,
+            ..SimulatorConfig::default()
+        };
+        let svns = config.svns.clone();
+        let mut simulator = Simulator::new(config);
+        let mut per_page = Osnma::<SmallStorage>::from_pubkey(simulator.public_key(), false);
+        let mut batched = Osnma::<SmallStorage>::from_pubkey(simulator.public_key(), false);
+        // This is fed with batches containing two subframes
+        let mut two_subframes = Osnma::<SmallStorage>::from_pubkey(simulator.public_key(), false);
+        let mut previous = Vec::new();
+        let mut authenticated = 0;
+        for n in 0..12 {
+            let pages = simulator.next_subframe();
+            for page in &pages {
+                per_page.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+                per_page.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+            // The pages of each epoch are consecutive
+            let mut remaining = &pages[..];
+            while let Some(first) = remaining.first() {
+                let len = remaining.iter().take_while(|p| p.gst == first.gst).count();
+                let (epoch, rest) = remaining.split_at(len);
+                batched.feed_batch(epoch.iter().map(|page| InavPage {
+                    word: &page.word,
+                    osnma: Some(&page.osnma),
+                    svn: page.svn,
+                    gst: page.gst,
+                    band: InavBand::E1B,
+                }));
+                remaining = rest;
+            }
+            assert_eq!(batched.diff(&per_page).count(), 0);
+            if n % 2 == 0 {
+                previous = pages;
+            } else {
+                two_subframes.feed_batch(previous.iter().chain(&pages).map(|page| InavPage {
+                    word: &page.word,
+                    osnma: Some(&page.osnma),
+                    svn: page.svn,
+                    gst: page.gst,
+                    band: InavBand::E1B,
+                }));
+                assert_eq!(two_subframes.diff(&per_page).count(), 0);
+                for &svn in &svns {
+                    assert_eq!(
+                        two_subframes.get_ced_and_status(svn),
+                        per_page.get_ced_and_status(svn)
+                    );
+                }
+            }
+            for &svn in &svns {
+                let ced = per_page.get_ced_and_status(svn);
+                assert_eq!(batched.get_ced_and_status(svn), ced);
+                assert_eq!(
+                    batched.get_timing_parameters(svn),
+                    per_page.get_timing_parameters(svn)
+                );
+                authenticated += usize::from(ced.is_some());
+            }
+        }
+        assert!(authenticated > 0);
+    }
+}
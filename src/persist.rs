@@ -0,0 +1,321 @@
+//! Persistent OSNMA state.
+//!
+//! This module contains the format used to save the state of the
+//! [`Osnma`](crate::Osnma) black box, so that authentication can be resumed
+//! quickly after a restart, without having to wait for a DSM-KROOT and
+//! possibly a DSM-PKR. The state is obtained with
+//! [`Osnma::export_state`](crate::Osnma::export_state) as an array of
+//! [`STATE_BYTES`] bytes, which can be stored in any kind of non-volatile
+//! memory, and it is restored with
+//! [`Osnma::import_state`](crate::Osnma::import_state). This does not require
+//! the `std` feature. When the `std` feature is enabled, `Osnma::save_to_file`
+//! and `Osnma::load_from_file` can be used to save the state to a file
+//! atomically.
+//!
+//! # Format
+//!
+//! The state has the following format:
+//!
+//! | Bytes   | Field                                                          |
+//! |---------|----------------------------------------------------------------|
+//! | 0-3     | Magic `OSNM`                                                   |
+//! | 4       | Format version (currently 2)                                   |
+//! | 5-86    | TESLA key exported with [`Key::export`]                        |
+//! | 87      | Flags (bit 0 set if the Merkle tree root is present)           |
+//! | 88-119  | Merkle tree root                                               |
+//! | 120     | NPKT of the public key (1 for P-256, 3 for P-521, 0 if absent) |
+//! | 121     | PKID of the public key                                         |
+//! | 122-188 | Public key in SEC1 compressed form, padded with zeros          |
+//! | 189-220 | HMAC-SHA-256 of bytes 0-188                                    |
+//!
+//! The HMAC is computed with the same secret that is used to export the
+//! TESLA key, so the public key and Merkle tree root are protected against
+//! tampering in the same way as the TESLA key.
+//!
+//! The NMA header is not part of the state, because it is transmitted
+//! together with each MACK message, so it is known as soon as the first
+//! subframe is received.
+
+use crate::merkle_tree::PublicKey;
+use crate::tesla::{Key, KeyImportError, KEY_EXPORT_BYTES};
+use crate::types::{MerkleTreeNode, VerifyingKey, MERKLE_TREE_NODE_BYTES};
+use crate::validation::Validated;
+use crate::PeerKeyError;
+use core::fmt;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+#[cfg(feature = "std")]
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+const MAGIC: [u8; 4] = *b"OSNM";
+const VERSION: u8 = 2;
+const HEADER_BYTES: usize = MAGIC.len() + 1;
+const KEY_START: usize = HEADER_BYTES;
+const FLAGS: usize = KEY_START + KEY_EXPORT_BYTES;
+const MERKLE_ROOT_START: usize = FLAGS + 1;
+const NPKT: usize = MERKLE_ROOT_START + MERKLE_TREE_NODE_BYTES;
+const PKID: usize = NPKT + 1;
+const PUBKEY_START: usize = PKID + 1;
+// Sizes of P-256 and P-521 public keys in SEC1 compressed form.
+const P256_PUBKEY_BYTES: usize = 33;
+const PUBKEY_BYTES: usize = 67;
+const MAC_START: usize = PUBKEY_START + PUBKEY_BYTES;
+const MAC_BYTES: usize = 32;
+const FLAG_MERKLE_ROOT: u8 = 1 << 0;
+// NPKT values of the public keys.
+const NPKT_P256: u8 = 1;
+#[cfg(feature = "p521")]
+const NPKT_P521: u8 = 3;
+
+/// Size in bytes of the OSNMA state.
+pub const STATE_BYTES: usize = MAC_START + MAC_BYTES;
+
+/// Errors produced during the import of the OSNMA state.
+///
+/// This gives the errors that can happen when importing a state using
+/// [`Osnma::import_state`](crate::Osnma::import_state). The state of the
+/// OSNMA black box is not modified when an error happens.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StateError {
+    /// The state does not have the correct size.
+    WrongSize,
+    /// The state does not start with the correct magic.
+    WrongMagic,
+    /// The format version of the state is not supported.
+    UnknownVersion,
+    /// The HMAC-SHA-256 of the state is not correct.
+    ///
+    /// This happens if the state has been modified or corrupted, or if the
+    /// secret is not the one used to export the state.
+    WrongMac,
+    /// The public key in the state is not valid or its type is not supported.
+    WrongPublicKey,
+    /// The TESLA key in the state could not be decoded.
+    KeyImport(KeyImportError),
+    /// The TESLA key in the state was refused by the OSNMA black box.
+    ///
+    /// This typically happens if the state is too old. See
+    /// [`Osnma::import_tesla_key`](crate::Osnma::import_tesla_key).
+    Key(PeerKeyError),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::WrongSize => "state has wrong size".fmt(f),
+            StateError::WrongMagic => "not an OSNMA state".fmt(f),
+            StateError::UnknownVersion => "unknown state format version".fmt(f),
+            StateError::WrongMac => "state MAC is not correct".fmt(f),
+            StateError::WrongPublicKey => "invalid public key in state".fmt(f),
+            StateError::KeyImport(e) => write!(f, "could not import TESLA key ({e})"),
+            StateError::Key(e) => write!(f, "TESLA key refused ({e})"),
+        }
+    }
+}
+
+impl From<KeyImportError> for StateError {
+    fn from(value: KeyImportError) -> StateError {
+        StateError::KeyImport(value)
+    }
+}
+
+impl From<PeerKeyError> for StateError {
+    fn from(value: PeerKeyError) -> StateError {
+        StateError::Key(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StateError::KeyImport(e) => Some(e),
+            StateError::Key(e) => Some(e),
+            StateError::WrongSize
+            | StateError::WrongMagic
+            | StateError::UnknownVersion
+            | StateError::WrongMac
+            | StateError::WrongPublicKey => None,
+        }
+    }
+}
+
+// Contents of a decoded state.
+pub(crate) struct State {
+    pub(crate) key: Key<Validated>,
+    pub(crate) merkle_root: Option<MerkleTreeNode>,
+    pub(crate) pubkey: Option<PublicKey<Validated>>,
+}
+
+// Builds the state containing an exported TESLA key and optionally a Merkle
+// tree root and a public key.
+pub(crate) fn encode(
+    key: &Key<Validated>,
+    merkle_root: Option<&MerkleTreeNode>,
+    pubkey: Option<&PublicKey<Validated>>,
+    secret: &[u8],
+) -> [u8; STATE_BYTES] {
+    let mut data = [0; STATE_BYTES];
+    data[..MAGIC.len()].copy_from_slice(&MAGIC);
+    data[MAGIC.len()] = VERSION;
+    data[KEY_START..FLAGS].copy_from_slice(&key.export(secret));
+    if let Some(root) = merkle_root {
+        data[FLAGS] |= FLAG_MERKLE_ROOT;
+        data[MERKLE_ROOT_START..NPKT].copy_from_slice(root);
+    }
+    if let Some(pubkey) = pubkey {
+        let mut store = |npkt, point: &[u8]| {
+            data[NPKT] = npkt;
+            data[PKID] = pubkey.public_key_id();
+            data[PUBKEY_START..PUBKEY_START + point.len()].copy_from_slice(point);
+        };
+        match pubkey.verifying_key() {
+            VerifyingKey::P256(key) => store(NPKT_P256, key.to_encoded_point(true).as_bytes()),
+            #[cfg(feature = "p521")]
+            VerifyingKey::P521(key) => store(NPKT_P521, key.to_encoded_point(true).as_bytes()),
+        }
+    }
+    let mac = state_mac(secret, &data[..MAC_START]).finalize();
+    data[MAC_START..].copy_from_slice(&mac.into_bytes());
+    data
+}
+
+// Checks the header and HMAC of a state and decodes its contents.
+pub(crate) fn decode(data: &[u8], secret: &[u8]) -> Result<State, StateError> {
+    if data.len() != STATE_BYTES {
+        return Err(StateError::WrongSize);
+    }
+    if data[..MAGIC.len()] != MAGIC {
+        return Err(StateError::WrongMagic);
+    }
+    if data[MAGIC.len()] != VERSION {
+        return Err(StateError::UnknownVersion);
+    }
+    state_mac(secret, &data[..MAC_START])
+        .verify_slice(&data[MAC_START..])
+        .map_err(|_| StateError::WrongMac)?;
+    let key = Key::import(data[KEY_START..FLAGS].try_into().unwrap(), secret)?;
+    let merkle_root = (data[FLAGS] & FLAG_MERKLE_ROOT != 0)
+        .then(|| data[MERKLE_ROOT_START..NPKT].try_into().unwrap());
+    let pkid = data[PKID];
+    let pubkey = match data[NPKT] {
+        0 => None,
+        npkt => Some(decode_pubkey(npkt, pkid, &data[PUBKEY_START..MAC_START])?),
+    };
+    Ok(State {
+        key,
+        merkle_root,
+        pubkey,
+    })
+}
+
+fn decode_pubkey(npkt: u8, pkid: u8, data: &[u8]) -> Result<PublicKey<Validated>, StateError> {
+    let pubkey = match npkt {
+        NPKT_P256 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&data[..P256_PUBKEY_BYTES])
+                .map_err(|_| StateError::WrongPublicKey)?;
+            PublicKey::from_p256(key, pkid)
+        }
+        #[cfg(feature = "p521")]
+        NPKT_P521 => {
+            let key = p521::ecdsa::VerifyingKey::from_sec1_bytes(data)
+                .map_err(|_| StateError::WrongPublicKey)?;
+            PublicKey::from_p521(key, pkid)
+        }
+        _ => return Err(StateError::WrongPublicKey),
+    };
+    Ok(pubkey.force_valid())
+}
+
+fn state_mac(secret: &[u8], data: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    Mac::update(&mut mac, data);
+    mac
+}
+
+// Writes a file atomically.
+//
+// The data is written to a temporary file in the same directory, which is
+// then renamed to `path`. This guarantees that `path` either contains its
+// previous contents or the new data in full, even if the process is
+// interrupted.
+#[cfg(feature = "std")]
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = Path::new(&tmp);
+    let result = File::create(tmp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(tmp, path));
+    if result.is_err() {
+        // Ignore the error, since the file might not exist
+        let _ = fs::remove_file(tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+    use hex_literal::hex;
+
+    fn test_key() -> Key<Validated> {
+        testing::tesla_key()
+    }
+
+    fn test_pubkey() -> PublicKey<Validated> {
+        let pubkey = hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+             22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        );
+        let pubkey = p256::ecdsa::VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+        PublicKey::from_p256(pubkey, 1).force_valid()
+    }
+
+    #[test]
+    fn encode_decode() {
+        let key = test_key();
+        let pubkey = test_pubkey();
+        let root = [0xa5; MERKLE_TREE_NODE_BYTES];
+        let secret = b"shared secret";
+        let data = encode(&key, Some(&root), Some(&pubkey), secret);
+        let state = decode(&data, secret).unwrap();
+        assert_eq!(state.key, key);
+        assert_eq!(state.merkle_root, Some(root));
+        let restored = state.pubkey.unwrap();
+        assert_eq!(restored.public_key_id(), 1);
+        assert!(matches!(
+            (restored.verifying_key(), pubkey.verifying_key()),
+            (VerifyingKey::P256(a), VerifyingKey::P256(b)) if a == b
+        ));
+
+        let data = encode(&key, None, None, secret);
+        let state = decode(&data, secret).unwrap();
+        assert_eq!(state.key, key);
+        assert!(state.merkle_root.is_none());
+        assert!(state.pubkey.is_none());
+
+        assert_eq!(
+            decode(&data[1..], secret).err(),
+            Some(StateError::WrongSize)
+        );
+        assert_eq!(
+            decode(&data, b"other secret").err(),
+            Some(StateError::WrongMac)
+        );
+        for j in MAGIC.len()..STATE_BYTES {
+            let mut corrupted = data;
+            corrupted[j] ^= 1;
+            assert!(decode(&corrupted, secret).is_err());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Prelude with the most commonly used items.
+//!
+//! This module re-exports the types that are needed by most users of the
+//! [`Osnma`] black box, so that they can be imported with a single `use`
+//! statement. It also re-exports the ECDSA verifying key types of the
+//! [p256](https://docs.rs/p256/) and [p521](https://docs.rs/p521/) crates, so
+//! that users do not need to depend on these crates with versions that match
+//! those used by galileo-osnma.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::prelude::*;
+//!
+//! let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//!               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//!               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! let pubkey = P256VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!
+//! let inav: InavWord = [0; INAV_WORD_BYTES];
+//! let svn = Svn::try_from(12).unwrap();
+//! osnma.feed_inav(&inav, svn, Gst::new(1177, 175767), InavBand::E1B);
+//! ```
+
+pub use crate::navmessage::{NavMessageData, NavMessageDataOwned};
+#[cfg(feature = "alloc")]
+pub use crate::storage::DynStorage;
+pub use crate::storage::{FullStorage, SmallStorage, StaticStorage, Storage};
+pub use crate::types::{
+    BitSlice, InavWord, MerkleTreeNode, OsnmaDataMessage, INAV_WORD_BYTES, MERKLE_TREE_NODE_BYTES,
+    NUM_SVNS,
+};
+pub use crate::{Gst, InavBand, Osnma, OsnmaBuilder, PublicKey, Svn, Tow, Validated, Wn};
+pub use p256::ecdsa::VerifyingKey as P256VerifyingKey;
+#[cfg(feature = "p521")]
+pub use p521::ecdsa::VerifyingKey as P521VerifyingKey;
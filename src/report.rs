@@ -0,0 +1,237 @@
+//! Authentication status reports.
+//!
+//! This module contains a compact summary of the authentication status of the
+//! navigation data held by an [`Osnma`] black box, which can be formatted as
+//! text without allocating memory. The text format is the one used by the
+//! [osnma-longan-nano](https://github.com/daniestevez/galileo-osnma/tree/main/osnma-longan-nano)
+//! demo firmware, so that firmware and host-side tools report the status in
+//! the same way.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::report::{AuthReport, Severity};
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//! let report = AuthReport::new(&osnma);
+//! assert_eq!(report.severity(), Severity::Unauthenticated);
+//! assert_eq!(
+//!     report.to_string(),
+//!     "AUTH ADKD=4 NONE\r\nAUTH ADKD=0 NONE\r\n"
+//! );
+//! ```
+
+use crate::navmessage::NavMessageData;
+use crate::storage::Storage;
+use crate::types::NUM_SVNS;
+use crate::verifier::Verifier;
+use crate::{Osnma, Svn, Tow};
+use core::fmt;
+
+/// Authentication status of one kind of navigation data.
+///
+/// This gives the TOW of the authenticated navigation data of each satellite,
+/// if any. It is formatted as a line such as `AUTH ADKD=0 E18 TOW 121080 E27
+/// TOW 121080`, or `AUTH ADKD=0 NONE` if there is no authenticated data. The
+/// line is not terminated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct AuthStatus {
+    adkd: u8,
+    tows: [Option<Tow>; NUM_SVNS],
+}
+
+impl AuthStatus {
+    fn new<'a>(adkd: u8, get: impl Fn(Svn) -> Option<NavMessageData<'a>>) -> AuthStatus {
+        let mut tows = [None; NUM_SVNS];
+        for svn in Svn::iter() {
+            tows[svn.index()] = get(svn).map(|data| data.gst().tow());
+        }
+        AuthStatus { adkd, tows }
+    }
+
+    /// Gives the ADKD used to report the navigation data.
+    ///
+    /// This is 0 for the ephemeris, clock and status data and 4 for the
+    /// timing parameters.
+    pub fn adkd(&self) -> u8 {
+        self.adkd
+    }
+
+    /// Gives the TOW of the authenticated navigation data of a satellite.
+    ///
+    /// The TOW corresponds to the start of the subframe in which the data was
+    /// transmitted. If there is no authenticated data for this satellite, this
+    /// returns `None`.
+    pub fn tow(&self, svn: Svn) -> Option<Tow> {
+        self.tows[svn.index()]
+    }
+
+    /// Returns an iterator over the satellites that have authenticated data.
+    ///
+    /// The iterator gives the SVN and the TOW of the authenticated data of
+    /// each satellite, in increasing SVN order.
+    pub fn authenticated(&self) -> impl Iterator<Item = (Svn, Tow)> + '_ {
+        Svn::iter().filter_map(|svn| self.tow(svn).map(|tow| (svn, tow)))
+    }
+
+    /// Gives the number of satellites that have authenticated data.
+    pub fn num_authenticated(&self) -> usize {
+        self.tows.iter().filter(|tow| tow.is_some()).count()
+    }
+
+    /// Gives a bitmask of the satellites that have authenticated data.
+    ///
+    /// The bit `svn.index()` (counting from the LSB) is set if the satellite
+    /// `svn` has authenticated data.
+    pub fn bitmask(&self) -> u64 {
+        self.tows
+            .iter()
+            .enumerate()
+            .filter(|(_, tow)| tow.is_some())
+            .fold(0, |mask, (j, _)| mask | (1 << j))
+    }
+}
+
+impl fmt::Display for AuthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AUTH ADKD={}", self.adkd)?;
+        if self.num_authenticated() == 0 {
+            return " NONE".fmt(f);
+        }
+        for (svn, tow) in self.authenticated() {
+            write!(f, " {} TOW {}", svn, tow)?;
+        }
+        Ok(())
+    }
+}
+
+/// Severity of an authentication status report.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Both the ephemeris, clock and status data and the timing parameters
+    /// are authenticated for at least one satellite.
+    Nominal,
+    /// Only one of the ephemeris, clock and status data or the timing
+    /// parameters is authenticated for some satellite.
+    Degraded,
+    /// There is no authenticated navigation data.
+    Unauthenticated,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Nominal => "NOMINAL".fmt(f),
+            Severity::Degraded => "DEGRADED".fmt(f),
+            Severity::Unauthenticated => "UNAUTHENTICATED".fmt(f),
+        }
+    }
+}
+
+/// Authentication status report.
+///
+/// This is a snapshot of the authentication status of the timing parameters
+/// (ADKD=4) and of the ephemeris, clock and status data (ADKD=0 and 12) held
+/// by an [`Osnma`] black box. It is formatted as two lines, each terminated by
+/// CRLF, giving the status of the timing parameters and of the ephemeris,
+/// clock and status data, in this order. See [`AuthStatus`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct AuthReport {
+    timing_parameters: AuthStatus,
+    ced_and_status: AuthStatus,
+}
+
+impl AuthReport {
+    /// Constructs a report with the current status of an [`Osnma`] black box.
+    pub fn new<S: Storage, V: Verifier>(osnma: &Osnma<S, V>) -> AuthReport {
+        AuthReport {
+            timing_parameters: AuthStatus::new(4, |svn| osnma.get_timing_parameters(svn)),
+            ced_and_status: AuthStatus::new(0, |svn| osnma.get_ced_and_status(svn)),
+        }
+    }
+
+    /// Gives the authentication status of the timing parameters.
+    pub fn timing_parameters(&self) -> &AuthStatus {
+        &self.timing_parameters
+    }
+
+    /// Gives the authentication status of the ephemeris, clock and status
+    /// data.
+    pub fn ced_and_status(&self) -> &AuthStatus {
+        &self.ced_and_status
+    }
+
+    /// Gives the severity of the report.
+    pub fn severity(&self) -> Severity {
+        match (
+            self.timing_parameters.num_authenticated(),
+            self.ced_and_status.num_authenticated(),
+        ) {
+            (0, 0) => Severity::Unauthenticated,
+            (0, _) | (_, 0) => Severity::Degraded,
+            _ => Severity::Nominal,
+        }
+    }
+}
+
+impl fmt::Display for AuthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\r\n{}\r\n",
+            self.timing_parameters, self.ced_and_status
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let svns = [11, 24].map(|svn| Svn::try_from(svn).unwrap());
+        let mut report = AuthReport {
+            timing_parameters: AuthStatus {
+                adkd: 4,
+                tows: [None; NUM_SVNS],
+            },
+            ced_and_status: AuthStatus {
+                adkd: 0,
+                tows: [None; NUM_SVNS],
+            },
+        };
+        assert_eq!(report.severity(), Severity::Unauthenticated);
+        assert_eq!(
+            report.to_string(),
+            "AUTH ADKD=4 NONE\r\nAUTH ADKD=0 NONE\r\n"
+        );
+
+        report.ced_and_status.tows[svns[0].index()] = Some(121080);
+        report.ced_and_status.tows[svns[1].index()] = Some(121110);
+        assert_eq!(report.severity(), Severity::Degraded);
+        assert_eq!(
+            report.to_string(),
+            "AUTH ADKD=4 NONE\r\nAUTH ADKD=0 E11 TOW 121080 E24 TOW 121110\r\n"
+        );
+        assert_eq!(report.ced_and_status().num_authenticated(), 2);
+        assert_eq!(report.ced_and_status().tow(svns[1]), Some(121110));
+        assert_eq!(report.ced_and_status().bitmask(), (1 << 10) | (1 << 23));
+
+        report.timing_parameters.tows[svns[1].index()] = Some(121080);
+        assert_eq!(report.severity(), Severity::Nominal);
+        assert_eq!(
+            report.timing_parameters().to_string(),
+            "AUTH ADKD=4 E24 TOW 121080"
+        );
+        assert_eq!(Severity::Degraded.to_string(), "DEGRADED");
+    }
+}
@@ -0,0 +1,407 @@
+//! RTCM 3 output of authenticated navigation data.
+//!
+//! This module serializes the navigation data authenticated by OSNMA into
+//! RTCM 3 messages, so that a receiver acting as an OSNMA-verifying gateway
+//! can feed downstream RTK and PPP clients only with authenticated data.
+//!
+//! Two messages are generated:
+//!
+//! * The CED and health status data (ADKD=0 and 12) is serialized into the
+//!   standard RTCM 3 message 1046 (Galileo I/NAV satellite ephemeris data).
+//!   The fields of this message have the same sizes and scale factors as in
+//!   the I/NAV words, so they are copied verbatim from the authenticated data.
+//! * RTCM 3 does not have a standard message for the GST-UTC and GST-GPS
+//!   conversion parameters, so the timing parameters (ADKD=4) are serialized
+//!   into a proprietary message. Its message number must be chosen by the user
+//!   among the proprietary message numbers 4001 to 4095.
+//!
+//! The proprietary message for the timing parameters contains the following
+//! fields, MSB first:
+//!
+//! | Field | Bits |
+//! |-------|------|
+//! | Message number | 12 |
+//! | Galileo satellite ID (SVN) | 6 |
+//! | GST week number of the navigation data | 12 |
+//! | GST time of week of the navigation data | 20 |
+//! | Number of authentication bits (see [`NavMessageData::authbits`]) | 16 |
+//! | Timing parameters (see [`TIMING_PARAMETERS_LAYOUT`](crate::inav::TIMING_PARAMETERS_LAYOUT)) | 141 |
+//! | Reserved | 1 |
+//!
+//! Each message is encapsulated in an RTCM 3 transport frame, which includes
+//! the preamble, the message length and the CRC-24Q.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::rtcm::RtcmGenerator;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::Osnma;
+//!
+//! fn output(osnma: &Osnma<FullStorage>, generator: &mut RtcmGenerator) {
+//!     // Send the frames of the data authenticated since the last call
+//!     generator.update(osnma, |frame| {
+//!         // Write frame.as_bytes() to the downstream clients
+//!         // ...
+//!     });
+//! }
+//!
+//! let mut generator = RtcmGenerator::new(Some(4090));
+//! ```
+
+use crate::navmessage::{AuthLatency, NavDataKind, NavMessageData};
+use crate::storage::Storage;
+use crate::types::{BitSlice, NUM_SVNS};
+use crate::ubx::crc24q;
+use crate::verifier::Verifier;
+use crate::{Gst, Osnma, Svn};
+use bitvec::prelude::*;
+use core::ops::Range;
+
+/// RTCM 3 message number of the Galileo I/NAV satellite ephemeris data.
+pub const MESSAGE_INAV_EPHEMERIS: u16 = 1046;
+/// Range of the RTCM 3 proprietary message numbers.
+pub const PROPRIETARY_MESSAGES: Range<u16> = 4001..4096;
+/// Maximum size of an RTCM 3 frame generated by this module, in bytes.
+pub const MAX_FRAME_BYTES: usize = HEADER_BYTES + EPHEMERIS_BYTES + CRC_BYTES;
+
+const PREAMBLE: u8 = 0xd3;
+const HEADER_BYTES: usize = 3;
+const CRC_BYTES: usize = 3;
+const EPHEMERIS_BYTES: usize = 63;
+const TIMING_BYTES: usize = 26;
+const CED_AND_STATUS_BITS: usize = 549;
+const TIMING_PARAMETERS_BITS: usize = 141;
+
+// Fields of message 1046 that follow the message number, satellite ID and
+// week number, given as ranges of the CED and health status data. The two
+// reserved bits at the end of the message are not included.
+const EPHEMERIS_FIELDS: [Range<usize>; 28] = [
+    0..10,    // IODnav
+    354..362, // SISA
+    226..240, // IDOT
+    410..424, // toc
+    476..482, // af2
+    455..476, // af1
+    424..455, // af0
+    338..354, // Crs
+    274..290, // Delta n
+    24..56,   // M0
+    290..306, // Cuc
+    56..88,   // e
+    306..322, // Cus
+    88..120,  // sqrt(A)
+    10..24,   // toe
+    378..394, // Cic
+    130..162, // OMEGA0
+    394..410, // Cis
+    162..194, // i0
+    322..338, // Crc
+    194..226, // omega
+    250..274, // OMEGADOT
+    523..533, // BGD E1/E5a
+    533..543, // BGD E1/E5b
+    543..545, // E5b signal health status
+    547..548, // E5b data validity status
+    545..547, // E1-B signal health status
+    548..549, // E1-B data validity status
+];
+
+/// RTCM 3 frame.
+///
+/// This contains an RTCM 3 message encapsulated in a transport frame. The
+/// bytes of the frame can be obtained with [`RtcmFrame::as_bytes`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RtcmFrame {
+    bytes: [u8; MAX_FRAME_BYTES],
+    len: usize,
+}
+
+impl RtcmFrame {
+    /// Constructs a message 1046 with the CED and health status data of a
+    /// satellite.
+    ///
+    /// The `data` should have been obtained from
+    /// [`Osnma::get_ced_and_status`]. If `data` does not have the length of
+    /// the CED and health status data, this returns `None`.
+    pub fn inav_ephemeris(svn: Svn, data: &NavMessageData) -> Option<RtcmFrame> {
+        Self::encode_ephemeris(svn, data.gst(), data.data())
+    }
+
+    /// Constructs a proprietary message with the timing parameters of a
+    /// satellite.
+    ///
+    /// The `data` should have been obtained from
+    /// [`Osnma::get_timing_parameters`]. The format of the message is
+    /// described in the [module documentation](crate::rtcm). If `data` does
+    /// not have the length of the timing parameters, this returns `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `message_number` is not in [`PROPRIETARY_MESSAGES`].
+    pub fn timing_parameters(
+        message_number: u16,
+        svn: Svn,
+        data: &NavMessageData,
+    ) -> Option<RtcmFrame> {
+        Self::encode_timing(
+            message_number,
+            svn,
+            data.gst(),
+            data.authbits(),
+            data.data(),
+        )
+    }
+
+    fn encode_ephemeris(svn: Svn, gst: Gst, data: &BitSlice) -> Option<RtcmFrame> {
+        if data.len() != CED_AND_STATUS_BITS {
+            return None;
+        }
+        let mut frame = RtcmFrame::new(EPHEMERIS_BYTES);
+        let mut payload = Payload::new(frame.payload_mut());
+        payload.push(MESSAGE_INAV_EPHEMERIS.into(), 12);
+        payload.push(u8::from(svn).into(), 6);
+        payload.push(u32::from(gst.wn()), 12);
+        for field in EPHEMERIS_FIELDS {
+            payload.push_bits(&data[field]);
+        }
+        frame.finish();
+        Some(frame)
+    }
+
+    fn encode_timing(
+        message_number: u16,
+        svn: Svn,
+        gst: Gst,
+        authbits: u16,
+        data: &BitSlice,
+    ) -> Option<RtcmFrame> {
+        assert!(
+            PROPRIETARY_MESSAGES.contains(&message_number),
+            "the message number must be an RTCM 3 proprietary message number"
+        );
+        if data.len() != TIMING_PARAMETERS_BITS {
+            return None;
+        }
+        let mut frame = RtcmFrame::new(TIMING_BYTES);
+        let mut payload = Payload::new(frame.payload_mut());
+        payload.push(message_number.into(), 12);
+        payload.push(u8::from(svn).into(), 6);
+        payload.push(u32::from(gst.wn()), 12);
+        payload.push(gst.tow(), 20);
+        payload.push(authbits.into(), 16);
+        payload.push_bits(data);
+        frame.finish();
+        Some(frame)
+    }
+
+    fn new(payload_len: usize) -> RtcmFrame {
+        let mut frame = RtcmFrame {
+            bytes: [0; MAX_FRAME_BYTES],
+            len: HEADER_BYTES + payload_len + CRC_BYTES,
+        };
+        frame.bytes[0] = PREAMBLE;
+        // The 6 bits that follow the preamble are reserved and set to zero
+        frame.bytes[1..3].copy_from_slice(&(payload_len as u16).to_be_bytes());
+        frame
+    }
+
+    fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[HEADER_BYTES..self.len - CRC_BYTES]
+    }
+
+    fn finish(&mut self) {
+        let crc_start = self.len - CRC_BYTES;
+        let crc = crc24q(self.bytes[..crc_start].view_bits::<Msb0>().iter().by_vals());
+        self.bytes[crc_start..self.len].copy_from_slice(&crc.to_be_bytes()[1..]);
+    }
+
+    /// Gives the RTCM 3 message number.
+    pub fn message_number(&self) -> u16 {
+        self.bytes[HEADER_BYTES..].view_bits::<Msb0>()[..12].load_be()
+    }
+
+    /// Gives the bytes of the frame.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+// Writer of the fields of an RTCM 3 message.
+struct Payload<'a> {
+    bits: &'a mut BitSlice,
+    position: usize,
+}
+
+impl<'a> Payload<'a> {
+    fn new(bytes: &'a mut [u8]) -> Payload<'a> {
+        Payload {
+            bits: bytes.view_bits_mut(),
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, value: u32, len: usize) {
+        self.bits[self.position..self.position + len].store_be(value);
+        self.position += len;
+    }
+
+    fn push_bits(&mut self, bits: &BitSlice) {
+        self.bits[self.position..self.position + bits.len()].copy_from_bitslice(bits);
+        self.position += bits.len();
+    }
+}
+
+/// Generator of RTCM 3 frames of authenticated navigation data.
+///
+/// This keeps track of the navigation data for which a frame has been
+/// generated, so that [`RtcmGenerator::update`] generates a frame only once
+/// for each authentication.
+#[derive(Debug, Clone)]
+pub struct RtcmGenerator {
+    timing_message: Option<u16>,
+    // Authentication latency of the last frame generated for each satellite
+    // and each of the kinds of navigation data CedAndStatus and
+    // TimingParameters.
+    last: [[Option<AuthLatency>; 2]; NUM_SVNS],
+}
+
+impl RtcmGenerator {
+    /// Constructs a new generator.
+    ///
+    /// The `timing_message` gives the number of the proprietary message used
+    /// for the timing parameters. If it is `None`, no frames are generated
+    /// for the timing parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timing_message` is not in [`PROPRIETARY_MESSAGES`].
+    pub fn new(timing_message: Option<u16>) -> RtcmGenerator {
+        if let Some(message_number) = timing_message {
+            assert!(
+                PROPRIETARY_MESSAGES.contains(&message_number),
+                "the message number must be an RTCM 3 proprietary message number"
+            );
+        }
+        RtcmGenerator {
+            timing_message,
+            last: [[None; 2]; NUM_SVNS],
+        }
+    }
+
+    /// Generates the frames of the navigation data authenticated by an OSNMA
+    /// black box.
+    ///
+    /// For each satellite, the most recent authenticated CED and health status
+    /// data and timing parameters are obtained from `osnma`, and a frame is
+    /// generated and given to the closure `emit` if they have been
+    /// authenticated since the last frame generated for that satellite. This
+    /// function should be called periodically, such as after feeding each
+    /// subframe into `osnma`.
+    ///
+    /// Returns the number of frames generated.
+    pub fn update<S: Storage, V: Verifier>(
+        &mut self,
+        osnma: &Osnma<S, V>,
+        mut emit: impl FnMut(&RtcmFrame),
+    ) -> usize {
+        let mut generated = 0;
+        for svn in Svn::iter() {
+            let datasets = [
+                (NavDataKind::CedAndStatus, osnma.get_ced_and_status(svn)),
+                (
+                    NavDataKind::TimingParameters,
+                    osnma.get_timing_parameters(svn),
+                ),
+            ];
+            for (kind, data) in datasets {
+                let Some(data) = data else {
+                    continue;
+                };
+                let last = &mut self.last[svn.index()][kind as usize];
+                if *last == Some(data.latency()) {
+                    continue;
+                }
+                let frame = match (kind, self.timing_message) {
+                    (NavDataKind::CedAndStatus, _) => RtcmFrame::inav_ephemeris(svn, &data),
+                    (NavDataKind::TimingParameters, Some(message_number)) => {
+                        RtcmFrame::timing_parameters(message_number, svn, &data)
+                    }
+                    (NavDataKind::TimingParameters, None) => None,
+                };
+                if let Some(frame) = frame {
+                    emit(&frame);
+                    generated += 1;
+                }
+                *last = Some(data.latency());
+            }
+        }
+        generated
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check_crc(frame: &RtcmFrame) {
+        let bytes = frame.as_bytes();
+        assert_eq!(bytes[0], PREAMBLE);
+        let len = usize::from(u16::from_be_bytes([bytes[1], bytes[2]]));
+        assert_eq!(bytes.len(), HEADER_BYTES + len + CRC_BYTES);
+        // The CRC of a frame including its CRC is zero
+        assert_eq!(crc24q(bytes.view_bits::<Msb0>().iter().by_vals()), 0);
+    }
+
+    #[test]
+    fn ephemeris() {
+        let svn = Svn::try_from(19).unwrap();
+        let gst = Gst::new(1177, 175770);
+        let mut data = bitarr![u8, Msb0; 0; CED_AND_STATUS_BITS];
+        data[0..10].store_be(42_u16); // IODnav
+        data[354..362].store_be(107_u8); // SISA
+        data[523..533].store_be(0x3ff_u16); // BGD E1/E5a
+        data[548..549].store_be(1_u8); // E1-B DVS
+        let data = &data[..CED_AND_STATUS_BITS];
+        let frame = RtcmFrame::encode_ephemeris(svn, gst, data).unwrap();
+        check_crc(&frame);
+        assert_eq!(frame.message_number(), MESSAGE_INAV_EPHEMERIS);
+        assert_eq!(frame.as_bytes().len(), 69);
+        let payload = &frame.as_bytes()[HEADER_BYTES..].view_bits::<Msb0>()[..504];
+        assert_eq!(payload[12..18].load_be::<u8>(), 19);
+        assert_eq!(payload[18..30].load_be::<u16>(), 1177);
+        assert_eq!(payload[30..40].load_be::<u16>(), 42);
+        assert_eq!(payload[40..48].load_be::<u8>(), 107);
+        assert_eq!(payload[476..486].load_be::<u16>(), 0x3ff);
+        assert_eq!(payload[486..501].count_ones(), 0);
+        assert!(payload[501]);
+        assert_eq!(payload[502..504].count_ones(), 0);
+
+        assert!(RtcmFrame::encode_ephemeris(svn, gst, &data[..141]).is_none());
+    }
+
+    #[test]
+    fn timing() {
+        let svn = Svn::try_from(4).unwrap();
+        let gst = Gst::new(1177, 175740);
+        let data = [0xff; 18];
+        let data = &data.view_bits::<Msb0>()[..TIMING_PARAMETERS_BITS];
+        let frame = RtcmFrame::encode_timing(4090, svn, gst, 80, data).unwrap();
+        check_crc(&frame);
+        assert_eq!(frame.message_number(), 4090);
+        assert_eq!(frame.as_bytes().len(), 32);
+        let payload = &frame.as_bytes()[HEADER_BYTES..].view_bits::<Msb0>()[..208];
+        assert_eq!(payload[12..18].load_be::<u8>(), 4);
+        assert_eq!(payload[18..30].load_be::<u16>(), 1177);
+        assert_eq!(payload[30..50].load_be::<u32>(), 175740);
+        assert_eq!(payload[50..66].load_be::<u16>(), 80);
+        assert_eq!(payload[66..207].count_ones(), TIMING_PARAMETERS_BITS);
+        assert!(!payload[207]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn standard_timing_message() {
+        RtcmGenerator::new(Some(1046));
+    }
+}
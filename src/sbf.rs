@@ -0,0 +1,480 @@
+//! Septentrio SBF front-end.
+//!
+//! This module contains a reader for the Septentrio Binary Format (SBF) output
+//! by Septentrio receivers. It extracts the INAV words and OSNMA data messages
+//! from the GALRawINAV blocks, so that they can be fed into the [`Osnma`] black
+//! box. The other SBF blocks can be read with [`SbfReader::read_block`] but
+//! are not interpreted.
+//!
+//! Each SBF block starts with the sync bytes `$@`, followed by a CRC-16, the
+//! block ID and the block length. The reader synchronizes to the start of the
+//! blocks, and discards the blocks with a wrong CRC.
+//!
+//! The GALRawINAV block contains the 234 bits of a nominal I/NAV page (the even
+//! page part without the tail bits followed by the odd page part), the SVID of
+//! the satellite, the signal in which the page was received, and a time tag in
+//! GPS time which gives the end of the page. The GST at the start of the page
+//! is computed from this time tag. As in the [`ubx`](crate::ubx) module, the
+//! CRC of each page is checked, and pages with a wrong CRC, Alert Pages and
+//! Dummy Messages are discarded.
+//!
+//! This module is only available if the `sbf` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::sbf::SbfReader;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey, Validated};
+//! use std::io::BufReader;
+//!
+//! fn process(pubkey: PublicKey<Validated>) -> std::io::Result<()> {
+//!     let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!     let file = std::fs::File::open("log.sbf")?;
+//!     let mut read = SbfReader::new(BufReader::new(file));
+//!     while let Some(page) = read.read_page()? {
+//!         page.feed(&mut osnma);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::storage::Storage;
+use crate::types::{BitSlice, InavPage, InavWord, OsnmaDataMessage};
+use crate::ubx::{extract_page, page_start};
+use crate::verifier::Verifier;
+use crate::{Gst, InavBand, Osnma, Svn, Tow, Wn};
+use std::io::{ErrorKind, Read};
+
+/// Sync bytes at the start of each SBF block.
+pub const SBF_SYNC: [u8; 2] = *b"$@";
+/// Block number of the GALRawINAV block.
+pub const BLOCK_GALRAWINAV: u16 = 4023;
+/// Signal type of Galileo E1-B in the SBF Source field.
+pub const SIGNAL_E1B: u8 = 17;
+/// Signal type of Galileo E5b-I in the SBF Source field.
+pub const SIGNAL_E5BI: u8 = 21;
+
+const HEADER_BYTES: usize = 8;
+// The CRC covers the block from the ID field
+const CRC_START: usize = 4;
+const CRC16_POLY: u16 = 0x1021;
+const GALRAWINAV_BYTES: usize = 52;
+const NAVBITS_OFFSET: usize = 20;
+const NAVBITS_WORDS: usize = 8;
+const EVEN_PART_BITS: usize = 114;
+const ODD_PART_BITS: usize = 120;
+// Galileo satellites use the SVIDs 71 to 106
+const SVID_GALILEO_OFFSET: u8 = 70;
+const MS_IN_WEEK: u32 = 604_800_000;
+// GST week 0 starts at GPS week 1024
+const GST_WN_OFFSET: u16 = 1024;
+const TOW_DNU: u32 = u32::MAX;
+const WNC_DNU: u16 = u16::MAX;
+
+/// SBF block.
+///
+/// This is returned by [`SbfReader::read_block`]. It refers to the data of the
+/// block, including its header, which is owned by the reader.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SbfBlock<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SbfBlock<'a> {
+    /// Gives the block number.
+    ///
+    /// This is formed by the 13 LSBs of the block ID.
+    pub fn number(&self) -> u16 {
+        self.id() & 0x1fff
+    }
+
+    /// Gives the block revision number.
+    ///
+    /// This is formed by the 3 MSBs of the block ID.
+    pub fn revision(&self) -> u8 {
+        (self.id() >> 13) as u8
+    }
+
+    /// Gives the data of the block, including the header.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Gives the time of week of the time tag of the block, in milliseconds.
+    ///
+    /// This is `None` if the receiver has marked the time tag as
+    /// do-not-use.
+    pub fn tow_ms(&self) -> Option<u32> {
+        Some(self.u32_at(HEADER_BYTES)).filter(|&tow| tow != TOW_DNU)
+    }
+
+    /// Gives the week number of the time tag of the block.
+    ///
+    /// This is the continuous GPS week number. It is `None` if the receiver
+    /// has marked the time tag as do-not-use.
+    pub fn wnc(&self) -> Option<u16> {
+        Some(self.u16_at(HEADER_BYTES + 4)).filter(|&wnc| wnc != WNC_DNU)
+    }
+
+    /// Extracts the I/NAV page of a GALRawINAV block.
+    ///
+    /// If this is a GALRawINAV block that contains a Galileo I/NAV page
+    /// received in E1-B or E5b-I with a correct CRC, the page is
+    /// returned. Otherwise, or if the page should be discarded, this returns
+    /// `None`.
+    pub fn gal_raw_inav(&self) -> Option<SbfPage> {
+        if self.number() != BLOCK_GALRAWINAV {
+            return None;
+        }
+        if self.data.len() < GALRAWINAV_BYTES {
+            log::error!("GALRawINAV block is too short ({} bytes)", self.data.len());
+            return None;
+        }
+        let svid = self.data[HEADER_BYTES + 6];
+        let Some(svn) = svid
+            .checked_sub(SVID_GALILEO_OFFSET)
+            .and_then(|prn| Svn::try_from(prn).ok())
+        else {
+            log::error!("invalid GALRawINAV SVID {}", svid);
+            return None;
+        };
+        let source = self.data[HEADER_BYTES + 9];
+        let band = match source & 0x1f {
+            SIGNAL_E1B => InavBand::E1B,
+            SIGNAL_E5BI => InavBand::E5B,
+            signal => {
+                log::debug!("discarding GALRawINAV for {} signal {}", svn, signal);
+                return None;
+            }
+        };
+        let Some(gst) = self.gst() else {
+            log::debug!("discarding GALRawINAV for {} {}: GST unknown", svn, band);
+            return None;
+        };
+        let gst = page_start(gst, band);
+
+        // NAVBits is formed by 32-bit words, in which the first bit is the
+        // MSB
+        let mut bytes = [0; 4 * NAVBITS_WORDS];
+        for (chunk, j) in bytes.chunks_exact_mut(4).zip(0..) {
+            chunk.copy_from_slice(&self.u32_at(NAVBITS_OFFSET + 4 * j).to_be_bytes());
+        }
+        let bits = BitSlice::from_slice(&bytes);
+        let even = &bits[..EVEN_PART_BITS];
+        let odd = &bits[EVEN_PART_BITS..EVEN_PART_BITS + ODD_PART_BITS];
+        let (word, osnma) = extract_page(even, odd, svn, gst, band)?;
+        Some(SbfPage {
+            word,
+            osnma,
+            svn,
+            gst,
+            band,
+        })
+    }
+
+    // Computes the GST of the time tag. GST is aligned with GPS time.
+    fn gst(&self) -> Option<Gst> {
+        let tow_ms = self.tow_ms()?;
+        let wn = Wn::from(self.wnc()?).checked_sub(GST_WN_OFFSET)?;
+        // Round to the nearest second, which can be in the next week
+        let tow_ms = tow_ms.checked_add(500)?;
+        let wn = wn.checked_add(Wn::try_from(tow_ms / MS_IN_WEEK).ok()?)?;
+        let tow: Tow = (tow_ms % MS_IN_WEEK) / 1000;
+        Some(Gst::new(wn, tow))
+    }
+
+    fn id(&self) -> u16 {
+        self.u16_at(4)
+    }
+
+    fn u16_at(&self, offset: usize) -> u16 {
+        u16::from_le_bytes(self.data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        u32::from_le_bytes(self.data[offset..offset + 4].try_into().unwrap())
+    }
+}
+
+/// Galileo I/NAV page extracted from an SBF GALRawINAV block.
+///
+/// This is produced by [`SbfBlock::gal_raw_inav`] and
+/// [`SbfReader::read_page`]. It owns the INAV word and OSNMA data message of
+/// the page.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SbfPage {
+    /// INAV word.
+    pub word: InavWord,
+    /// OSNMA data message.
+    ///
+    /// This is `None` for pages received in E5b-I, which do not carry OSNMA
+    /// data.
+    pub osnma: Option<OsnmaDataMessage>,
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the page transmission.
+    pub gst: Gst,
+    /// Band in which the page was received.
+    pub band: InavBand,
+}
+
+impl SbfPage {
+    /// Gives the page as an [`InavPage`].
+    ///
+    /// This can be used to feed pages in batches with
+    /// [`Osnma::feed_batch`]. Note that [`Osnma::feed_batch`] does not mark
+    /// the INAV words as CRC checked.
+    pub fn as_inav_page(&self) -> InavPage<'_> {
+        InavPage {
+            word: &self.word,
+            osnma: self.osnma.as_ref(),
+            svn: self.svn,
+            gst: self.gst,
+            band: self.band,
+        }
+    }
+
+    /// Feeds the page into an [`Osnma`] black box.
+    ///
+    /// The INAV word is fed with [`Osnma::feed_inav_crc_checked`], since the
+    /// CRC of the page has been checked, and the OSNMA data message, if
+    /// present, is fed with [`Osnma::feed_osnma_band`].
+    pub fn feed<S: Storage, V: Verifier>(&self, osnma: &mut Osnma<S, V>) {
+        osnma.feed_inav_crc_checked(&self.word, self.svn, self.gst, self.band);
+        if let Some(data) = &self.osnma {
+            osnma.feed_osnma_band(data, self.svn, self.gst, self.band);
+        }
+    }
+}
+
+/// Reader for the Septentrio Binary Format.
+///
+/// This wraps around a [`Read`] `R` and can be used to read SBF blocks from
+/// `R`. The data is read one byte at a time while searching for the start of a
+/// block, so `R` should be buffered.
+#[derive(Debug, Clone)]
+pub struct SbfReader<R> {
+    read: R,
+    buffer: Vec<u8>,
+    crc_errors: u64,
+}
+
+impl<R: Read> SbfReader<R> {
+    /// Constructs a new reader using a [`Read`] `read`.
+    pub fn new(read: R) -> SbfReader<R> {
+        SbfReader {
+            read,
+            buffer: Vec::with_capacity(256),
+            crc_errors: 0,
+        }
+    }
+
+    /// Gives the number of blocks that have been discarded because of a
+    /// wrong CRC.
+    pub fn crc_errors(&self) -> u64 {
+        self.crc_errors
+    }
+
+    /// Tries to read an SBF block.
+    ///
+    /// If the read is successful, the block is returned. The data before the
+    /// start of the block and the blocks with a wrong CRC or an invalid length
+    /// are skipped. If EOF is reached before the start of a block, `None` is
+    /// returned. If EOF is reached in the middle of a block, an error of kind
+    /// [`ErrorKind::UnexpectedEof`] is returned. For other errors, the error
+    /// of the underlying reader is returned.
+    pub fn read_block(&mut self) -> std::io::Result<Option<SbfBlock<'_>>> {
+        loop {
+            if !self.find_sync()? {
+                return Ok(None);
+            }
+            let mut header = [0; HEADER_BYTES];
+            header[..SBF_SYNC.len()].copy_from_slice(&SBF_SYNC);
+            self.read.read_exact(&mut header[SBF_SYNC.len()..])?;
+            let crc = u16::from_le_bytes([header[2], header[3]]);
+            let length = usize::from(u16::from_le_bytes([header[6], header[7]]));
+            if length < HEADER_BYTES || length % 4 != 0 {
+                log::warn!("invalid SBF block length {}", length);
+                continue;
+            }
+            self.buffer.clear();
+            self.buffer.extend_from_slice(&header);
+            self.buffer.resize(length, 0);
+            self.read.read_exact(&mut self.buffer[HEADER_BYTES..])?;
+            if crc16(&self.buffer[CRC_START..]) != crc {
+                log::warn!("wrong CRC in SBF block");
+                self.crc_errors += 1;
+                continue;
+            }
+            return Ok(Some(SbfBlock { data: &self.buffer }));
+        }
+    }
+
+    /// Tries to read a Galileo I/NAV page.
+    ///
+    /// This reads SBF blocks until a GALRawINAV block containing a valid page
+    /// is found (see [`SbfBlock::gal_raw_inav`]), and returns the page. If
+    /// EOF is reached before such a block is found, `None` is returned. Errors
+    /// are returned as in [`SbfReader::read_block`].
+    pub fn read_page(&mut self) -> std::io::Result<Option<SbfPage>> {
+        loop {
+            let Some(block) = self.read_block()? else {
+                return Ok(None);
+            };
+            if let Some(page) = block.gal_raw_inav() {
+                return Ok(Some(page));
+            }
+        }
+    }
+
+    // Reads until the sync bytes are found. Returns false if EOF is reached.
+    fn find_sync(&mut self) -> std::io::Result<bool> {
+        let mut previous = 0;
+        let mut byte = [0];
+        loop {
+            match self.read.read(&mut byte) {
+                Ok(0) => return Ok(false),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            if [previous, byte[0]] == SBF_SYNC {
+                return Ok(true);
+            }
+            previous = byte[0];
+        }
+    }
+}
+
+// CRC-16-CCITT used by SBF
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ CRC16_POLY
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::INAV_WORD_BYTES;
+    use crate::ubx::crc24q;
+    use bitvec::field::BitField;
+
+    // Builds a GALRawINAV block containing a page with a correct CRC
+    fn galrawinav(
+        tow_ms: u32,
+        wnc: u16,
+        svid: u8,
+        source: u8,
+        word: &InavWord,
+        osnma: &OsnmaDataMessage,
+    ) -> Vec<u8> {
+        let mut navbits = [0u8; 4 * NAVBITS_WORDS];
+        let bits = BitSlice::from_slice_mut(&mut navbits);
+        let word = BitSlice::from_slice(word);
+        bits[2..114].copy_from_bitslice(&word[..112]);
+        let odd = &mut bits[EVEN_PART_BITS..];
+        odd.set(0, true);
+        odd[2..18].copy_from_bitslice(&word[112..]);
+        odd[18..58].copy_from_bitslice(BitSlice::from_slice(osnma));
+        let bits = BitSlice::from_slice(&navbits);
+        let crc = crc24q(
+            bits[..EVEN_PART_BITS]
+                .iter()
+                .by_vals()
+                .chain(bits[EVEN_PART_BITS..][..82].iter().by_vals()),
+        );
+        BitSlice::from_slice_mut(&mut navbits)[EVEN_PART_BITS..][82..106].store_be(crc);
+
+        let mut block = Vec::new();
+        block.extend_from_slice(&SBF_SYNC);
+        block.extend_from_slice(&[0, 0]);
+        block.extend_from_slice(&BLOCK_GALRAWINAV.to_le_bytes());
+        block.extend_from_slice(&(GALRAWINAV_BYTES as u16).to_le_bytes());
+        block.extend_from_slice(&tow_ms.to_le_bytes());
+        block.extend_from_slice(&wnc.to_le_bytes());
+        block.extend_from_slice(&[svid, 1, 0, source, 0, 3]);
+        for chunk in navbits.chunks_exact(4) {
+            let word = u32::from_be_bytes(chunk.try_into().unwrap());
+            block.extend_from_slice(&word.to_le_bytes());
+        }
+        let crc = crc16(&block[CRC_START..]);
+        block[2..4].copy_from_slice(&crc.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn crc() {
+        // Check value of CRC-16-CCITT (XMODEM)
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+    }
+
+    #[test]
+    fn read() {
+        // word type 4
+        let mut word = [0x55; INAV_WORD_BYTES];
+        word[0] = 4 << 2;
+        let osnma = [1, 2, 3, 4, 5];
+        // 175772 s after the start of GST week 1177
+        let e1b = galrawinav(175_772_000, 2201, 81, SIGNAL_E1B, &word, &osnma);
+        let e5b = galrawinav(175_772_999, 2201, 81, SIGNAL_E5BI | 0x20, &word, &osnma);
+        let mut bad_crc = e1b.clone();
+        bad_crc[30] ^= 1;
+        let mut data = b"garbage$".to_vec();
+        for block in [&e1b, &bad_crc, &e5b] {
+            data.extend_from_slice(block);
+        }
+        let mut read = SbfReader::new(&data[..]);
+
+        let svn = Svn::try_from(11).unwrap();
+        let page = read.read_page().unwrap().unwrap();
+        assert_eq!(
+            page,
+            SbfPage {
+                word,
+                osnma: Some(osnma),
+                svn,
+                gst: Gst::new(1177, 175770),
+                band: InavBand::E1B,
+            }
+        );
+        let block = read.read_block().unwrap().unwrap();
+        assert_eq!(block.number(), BLOCK_GALRAWINAV);
+        assert_eq!(block.revision(), 0);
+        assert_eq!(block.tow_ms(), Some(175_772_999));
+        assert_eq!(block.wnc(), Some(2201));
+        let page = block.gal_raw_inav().unwrap();
+        assert_eq!(page.osnma, None);
+        assert_eq!(page.gst, Gst::new(1177, 175771));
+        assert_eq!(page.as_inav_page().word, &word);
+        assert!(read.read_block().unwrap().is_none());
+        assert_eq!(read.crc_errors(), 1);
+
+        // Truncated block
+        let mut read = SbfReader::new(&e1b[..40]);
+        assert_eq!(
+            read.read_block().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // GPS satellites, unknown time and dummy messages are ignored
+        for block in [
+            galrawinav(175_772_000, 2201, 11, SIGNAL_E1B, &word, &osnma),
+            galrawinav(TOW_DNU, 2201, 81, SIGNAL_E1B, &word, &osnma),
+            galrawinav(175_772_000, 2201, 81, SIGNAL_E1B, &[0xff; 16], &osnma),
+        ] {
+            let mut read = SbfReader::new(&block[..]);
+            assert!(read.read_page().unwrap().is_none());
+        }
+    }
+}
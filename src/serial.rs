@@ -0,0 +1,452 @@
+//! Serial line interface for embedded ports.
+//!
+//! This module implements a simple line-based protocol that can be used to
+//! feed INAV words and OSNMA data messages into the [`Osnma`] black box over a
+//! serial port and to report the authentication status. It is used by the
+//! [osnma-longan-nano](https://github.com/daniestevez/galileo-osnma/tree/main/osnma-longan-nano)
+//! demo firmware, and it can be reused to port this demo to other
+//! microcontrollers, since only the serial port needs to be supplied as an
+//! implementation of the [`embedded_io::Read`] and [`embedded_io::Write`]
+//! traits.
+//!
+//! The host sends lines terminated by CR (an optional LF following the CR is
+//! ignored). Each line contains the following fields separated by whitespace:
+//!
+//! 1. The SVN of the satellite, as a number.
+//! 2. The week number of the GST at the start of the page.
+//! 3. The time of week of the GST at the start of the page.
+//! 4. The band in which the page was received: `1` for E1-B or `5` for E5b-I.
+//! 5. Either an INAV word as 16 bytes in hex or an OSNMA data message as 5
+//!    bytes in hex.
+//!
+//! The [`SerialInterface`] writes `READY` when it is ready to receive a line.
+//! After processing the line, it echoes its contents (or `ERROR` and a
+//! description of the problem if the line is invalid), and it writes the
+//! authentication status of the timing parameters (ADKD=4) and of the
+//! ephemeris, clock and status data (ADKD=0 and 12) as an
+//! [`AuthReport`]. All the lines written by the interface are terminated by
+//! CRLF.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use galileo_osnma::serial::SerialInterface;
+//! use galileo_osnma::storage::SmallStorage;
+//! use galileo_osnma::{Osnma, PublicKey, Validated};
+//!
+//! fn run<R, W>(pubkey: PublicKey<Validated>, rx: R, tx: W) -> !
+//! where
+//!     R: embedded_io::Read,
+//!     W: embedded_io::Write,
+//! {
+//!     let osnma = Osnma::<SmallStorage>::from_pubkey(pubkey, false);
+//!     let mut interface = SerialInterface::new(osnma, rx, tx);
+//!     loop {
+//!         if interface.spin().is_err() {
+//!             // Handle serial port errors
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::report::AuthReport;
+use crate::storage::Storage;
+use crate::types::{
+    InavWord, OsnmaDataMessage, HKROOT_SECTION_BYTES, INAV_WORD_BYTES, MACK_SECTION_BYTES,
+};
+use crate::{Gst, InavBand, Osnma, Svn};
+use core::fmt;
+use embedded_io::{Read, Write, WriteFmtError};
+
+/// Maximum length of a line received by a [`SerialInterface`].
+pub const MAX_LINE_BYTES: usize = 256;
+
+const OSNMA_BYTES: usize = HKROOT_SECTION_BYTES + MACK_SECTION_BYTES;
+
+/// Line received through the serial port.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Line {
+    /// Line containing an INAV word.
+    Inav {
+        /// SVN of the satellite that transmitted the word.
+        svn: Svn,
+        /// GST at the start of the page.
+        gst: Gst,
+        /// Band in which the page was received.
+        band: InavBand,
+        /// INAV word.
+        word: InavWord,
+    },
+    /// Line containing an OSNMA data message.
+    Osnma {
+        /// SVN of the satellite that transmitted the message.
+        svn: Svn,
+        /// GST at the start of the page.
+        gst: Gst,
+        /// Band in which the page was received.
+        band: InavBand,
+        /// OSNMA data message.
+        data: OsnmaDataMessage,
+    },
+}
+
+impl Line {
+    /// Parses a line.
+    ///
+    /// The line should not include the CR terminator. An error is returned if
+    /// the line does not follow the format described in the [module
+    /// documentation](crate::serial).
+    pub fn parse(line: &[u8]) -> Result<Line, LineError> {
+        let line = core::str::from_utf8(line).map_err(|_| LineError::NotUtf8)?;
+        let mut fields = line.split_whitespace();
+        let mut next_field = || fields.next().ok_or(LineError::MissingField);
+        let svn = next_field()?
+            .parse::<u8>()
+            .ok()
+            .and_then(|svn| Svn::try_from(svn).ok())
+            .ok_or(LineError::InvalidSvn)?;
+        let wn = next_field()?.parse().map_err(|_| LineError::InvalidGst)?;
+        let tow = next_field()?.parse().map_err(|_| LineError::InvalidGst)?;
+        if tow >= 7 * 24 * 3600 {
+            return Err(LineError::InvalidGst);
+        }
+        let gst = Gst::new(wn, tow);
+        let band = match next_field()? {
+            "1" => InavBand::E1B,
+            "5" => InavBand::E5B,
+            _ => return Err(LineError::InvalidBand),
+        };
+        let data = next_field()?;
+        if fields.next().is_some() {
+            return Err(LineError::TooManyFields);
+        }
+        if data.len() == 2 * INAV_WORD_BYTES {
+            Ok(Line::Inav {
+                svn,
+                gst,
+                band,
+                word: decode_hex(data)?,
+            })
+        } else if data.len() == 2 * OSNMA_BYTES {
+            Ok(Line::Osnma {
+                svn,
+                gst,
+                band,
+                data: decode_hex(data)?,
+            })
+        } else {
+            Err(LineError::InvalidData)
+        }
+    }
+
+    /// Feeds the contents of the line into an [`Osnma`] black box.
+    pub fn feed<S: Storage>(&self, osnma: &mut Osnma<S>) {
+        match self {
+            Line::Inav {
+                svn,
+                gst,
+                band,
+                word,
+            } => osnma.feed_inav(word, *svn, *gst, *band),
+            Line::Osnma {
+                svn,
+                gst,
+                band,
+                data,
+            } => osnma.feed_osnma_band(data, *svn, *gst, *band),
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (svn, gst, band, kind) = match self {
+            Line::Inav { svn, gst, band, .. } => (svn, gst, band, "INAV"),
+            Line::Osnma { svn, gst, band, .. } => (svn, gst, band, "OSNMA"),
+        };
+        let band = match band {
+            InavBand::E1B => 1,
+            InavBand::E5B => 5,
+        };
+        write!(
+            f,
+            "{} WN {} TOW {} E{}B {}",
+            svn,
+            gst.wn(),
+            gst.tow(),
+            band,
+            kind
+        )
+    }
+}
+
+fn decode_hex<const N: usize>(field: &str) -> Result<[u8; N], LineError> {
+    let mut data = [0; N];
+    for (byte, digits) in data.iter_mut().zip(field.as_bytes().chunks_exact(2)) {
+        *byte = core::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            .ok_or(LineError::InvalidData)?;
+    }
+    Ok(data)
+}
+
+/// Errors produced when parsing a [`Line`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LineError {
+    /// The line is longer than [`MAX_LINE_BYTES`].
+    TooLong,
+    /// The line is not valid UTF-8.
+    NotUtf8,
+    /// The line does not contain all the fields.
+    MissingField,
+    /// The line contains more fields than expected.
+    TooManyFields,
+    /// The SVN field is not valid.
+    InvalidSvn,
+    /// The WN or TOW fields are not valid.
+    InvalidGst,
+    /// The band field is not valid.
+    InvalidBand,
+    /// The data field is not an INAV word or OSNMA data message in hex.
+    InvalidData,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::TooLong => "line too long".fmt(f),
+            LineError::NotUtf8 => "line is not valid UTF-8".fmt(f),
+            LineError::MissingField => "missing field".fmt(f),
+            LineError::TooManyFields => "too many fields".fmt(f),
+            LineError::InvalidSvn => "invalid SVN".fmt(f),
+            LineError::InvalidGst => "invalid GST".fmt(f),
+            LineError::InvalidBand => "invalid band".fmt(f),
+            LineError::InvalidData => "invalid INAV word or OSNMA data".fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LineError {}
+
+/// Serial line interface to the OSNMA black box.
+///
+/// This implements the protocol described in the [module
+/// documentation](crate::serial) using a serial port given by a reader `R`
+/// and a writer `W`.
+#[derive(Debug)]
+pub struct SerialInterface<S: Storage, R, W> {
+    osnma: Osnma<S>,
+    rx: R,
+    tx: W,
+    buffer: [u8; MAX_LINE_BYTES],
+}
+
+impl<S: Storage, R: Read, W: Write> SerialInterface<S, R, W> {
+    /// Constructs a new serial interface.
+    pub fn new(osnma: Osnma<S>, rx: R, tx: W) -> SerialInterface<S, R, W> {
+        SerialInterface {
+            osnma,
+            rx,
+            tx,
+            buffer: [0; MAX_LINE_BYTES],
+        }
+    }
+
+    /// Gives a reference to the OSNMA black box.
+    pub fn osnma(&self) -> &Osnma<S> {
+        &self.osnma
+    }
+
+    /// Gives a mutable reference to the OSNMA black box.
+    pub fn osnma_mut(&mut self) -> &mut Osnma<S> {
+        &mut self.osnma
+    }
+
+    /// Processes one line.
+    ///
+    /// This writes `READY`, waits for a line to be received, processes it and
+    /// writes the response and the authentication status. Invalid lines are
+    /// reported with an `ERROR` response and do not produce an error in this
+    /// function. An error is only returned if the serial port fails.
+    pub fn spin(&mut self) -> Result<(), SerialError<R::Error, W::Error>> {
+        self.tx
+            .write_all(b"READY\r\n")
+            .map_err(SerialError::Write)?;
+        let line = self
+            .read_line()?
+            .and_then(|len| Line::parse(&self.buffer[..len]));
+        match line {
+            Ok(line) => {
+                write!(self.tx, "{line}\r\n")?;
+                line.feed(&mut self.osnma);
+            }
+            Err(e) => {
+                log::warn!("invalid line received through serial port: {}", e);
+                write!(self.tx, "ERROR {e}\r\n")?;
+            }
+        }
+        write!(self.tx, "{}", AuthReport::new(&self.osnma))?;
+        Ok(())
+    }
+
+    // Reads a line into the buffer, returning its length, or
+    // LineError::TooLong if the line does not fit in the buffer.
+    fn read_line(&mut self) -> Result<Result<usize, LineError>, SerialError<R::Error, W::Error>> {
+        let mut len = 0;
+        loop {
+            let mut c = 0;
+            if self
+                .rx
+                .read(core::slice::from_mut(&mut c))
+                .map_err(SerialError::Read)?
+                == 0
+            {
+                return Err(SerialError::Eof);
+            }
+            match c {
+                // LF
+                b'\n' => (),
+                // CR
+                b'\r' if len > self.buffer.len() => return Ok(Err(LineError::TooLong)),
+                b'\r' => return Ok(Ok(len)),
+                _ => {
+                    if let Some(b) = self.buffer.get_mut(len) {
+                        *b = c;
+                    }
+                    len = len.saturating_add(1);
+                }
+            }
+        }
+    }
+}
+
+/// Errors produced by a [`SerialInterface`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SerialError<R, W> {
+    /// The reader returned an error.
+    Read(R),
+    /// The reader reached EOF.
+    Eof,
+    /// The writer returned an error.
+    Write(W),
+    /// A formatting error happened while writing.
+    Format,
+}
+
+impl<R, W> From<WriteFmtError<W>> for SerialError<R, W> {
+    fn from(value: WriteFmtError<W>) -> SerialError<R, W> {
+        match value {
+            WriteFmtError::Other(e) => SerialError::Write(e),
+            WriteFmtError::FmtError => SerialError::Format,
+        }
+    }
+}
+
+impl<R: fmt::Debug, W: fmt::Debug> fmt::Display for SerialError<R, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerialError::Read(e) => write!(f, "serial port read error ({e:?})"),
+            SerialError::Eof => "serial port reached EOF".fmt(f),
+            SerialError::Write(e) => write!(f, "serial port write error ({e:?})"),
+            SerialError::Format => "formatting error".fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: fmt::Debug, W: fmt::Debug> std::error::Error for SerialError<R, W> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::SmallStorage;
+    use crate::PublicKey;
+    use core::convert::Infallible;
+    use p256::ecdsa::VerifyingKey;
+
+    struct Output(Vec<u8>);
+
+    impl embedded_io::ErrorType for Output {
+        type Error = Infallible;
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    fn test_osnma() -> Osnma<SmallStorage> {
+        let pubkey = hex_literal::hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+             22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        );
+        let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+        let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+        Osnma::from_pubkey(pubkey, false)
+    }
+
+    #[test]
+    fn parse() {
+        let line = Line::parse(b"11 1177 175740 5 0102030405").unwrap();
+        assert_eq!(
+            line,
+            Line::Osnma {
+                svn: Svn::try_from(11).unwrap(),
+                gst: Gst::new(1177, 175740),
+                band: InavBand::E5B,
+                data: [1, 2, 3, 4, 5],
+            }
+        );
+        assert_eq!(line.to_string(), "E11 WN 1177 TOW 175740 E5B OSNMA");
+        let line = Line::parse(b"  12 1177 175742 1 000102030405060708090a0b0c0d0e0f").unwrap();
+        assert_eq!(line.to_string(), "E12 WN 1177 TOW 175742 E1B INAV");
+        for (line, err) in [
+            (&b"11 1177 175740 5"[..], LineError::MissingField),
+            (b"11 1177 175740 5 0102030405 0", LineError::TooManyFields),
+            (b"37 1177 175740 5 0102030405", LineError::InvalidSvn),
+            (b"11 1177 604800 5 0102030405", LineError::InvalidGst),
+            (b"11 1177 175740 2 0102030405", LineError::InvalidBand),
+            (b"11 1177 175740 5 01020304", LineError::InvalidData),
+            (b"11 1177 175740 5 010203040g", LineError::InvalidData),
+            (b"11 1177 175740 5 \xff", LineError::NotUtf8),
+        ] {
+            assert_eq!(Line::parse(line), Err(err));
+        }
+    }
+
+    #[test]
+    fn interface() {
+        let long_line = [b'0'; MAX_LINE_BYTES + 1];
+        let input = [
+            &b"11 1177 175740 5 0102030405\r\n"[..],
+            b"11 1177 175740\r",
+            &long_line,
+            b"\r",
+        ]
+        .concat();
+        let mut interface = SerialInterface::new(test_osnma(), &input[..], Output(Vec::new()));
+        for _ in 0..3 {
+            interface.spin().unwrap();
+        }
+        assert_eq!(interface.spin(), Err(SerialError::Eof));
+        let output = String::from_utf8(interface.tx.0).unwrap();
+        let status = "AUTH ADKD=4 NONE\r\nAUTH ADKD=0 NONE\r\n";
+        assert_eq!(
+            output,
+            format!(
+                "READY\r\nE11 WN 1177 TOW 175740 E5B OSNMA\r\n{status}\
+                 READY\r\nERROR missing field\r\n{status}\
+                 READY\r\nERROR line too long\r\n{status}\
+                 READY\r\n"
+            )
+        );
+    }
+}
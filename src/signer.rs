@@ -0,0 +1,759 @@
+//! Generation of OSNMA data.
+//!
+//! This module contains a [`TeslaChain`], which generates the cryptographic
+//! material broadcast by OSNMA, as opposed to verifying it. Given a
+//! [`ChainConfig`] and a seed key, it generates a TESLA chain, the DSM-KROOT
+//! message signed with an ECDSA private key, and MACK messages containing
+//! valid tags and MACSEQ fields for synthetic navigation data. It is intended
+//! for test benches that need to feed OSNMA data into a receiver, and it is
+//! only available when the crate is built with the `signer` feature, which
+//! requires `alloc`.
+//!
+//! The [`Simulator`](crate::simulator::Simulator) uses this module to generate
+//! its broadcast.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::bitfields::{Adkd, DsmKroot, NmaHeader, NmaStatus};
+//! use galileo_osnma::signer::{ChainConfig, SigningKey, TagSpec, TeslaChain};
+//! use galileo_osnma::tesla::{HashFunction, Key, MacFunction};
+//! use galileo_osnma::types::{BitSlice, CedBits};
+//! use galileo_osnma::{Gst, Svn};
+//!
+//! let config = ChainConfig {
+//!     chain_id: 1,
+//!     public_key_id: 1,
+//!     hash_function: HashFunction::Sha256,
+//!     mac_function: MacFunction::HmacSha256,
+//!     key_size_bits: 128,
+//!     tag_size_bits: 40,
+//!     mac_lookup_table: 34,
+//!     alpha: 0x1234_5678_9abc,
+//!     kroot_time: Gst::new(1300, 3600),
+//! };
+//! let chain = TeslaChain::generate(config, &[0x55; 16], 120).unwrap();
+//!
+//! // Sign the DSM-KROOT and check it as a receiver would do
+//! let signing_key = SigningKey::P256(p256::ecdsa::SigningKey::from_slice(&[1; 32]).unwrap());
+//! let nma_header = 0x52;
+//! let dsm_kroot = chain.dsm_kroot(nma_header, &signing_key).unwrap();
+//! let (kroot, _) = Key::from_dsm_kroot(
+//!     NmaHeader::new(nma_header),
+//!     DsmKroot(&dsm_kroot),
+//!     &signing_key.public_key(),
+//! )
+//! .unwrap();
+//! assert_eq!(kroot, chain.kroot());
+//!
+//! // Generate a MACK message whose tags all authenticate the same data
+//! let prna = Svn::try_from(11).unwrap();
+//! let navdata = [0xa5; 69];
+//! let navdata = &BitSlice::from_slice(&navdata)[..549];
+//! let tags = [0, 1, 2, 3, 4, 5].map(|_| TagSpec {
+//!     prnd: prna,
+//!     adkd: Adkd::InavCed,
+//!     cop: 1,
+//!     navdata: CedBits::new(navdata).unwrap().into(),
+//! });
+//! let gst = Gst::new(1300, 3600);
+//! let mack = chain.mack(prna, gst, NmaStatus::Operational, &tags).unwrap();
+//! ```
+
+use crate::bitfields::{Adkd, DsmKroot, Mack, NmaStatus};
+use crate::tesla::{Chain, ChainError, HashFunction, Key, MacFunction};
+use crate::types::{
+    dsm_kroot_blocks, BitSlice, MackMessage, NavDataBits, DSM_BLOCK_BYTES, KEY_SIZES_BITS,
+    MACK_MESSAGE_BYTES, TAG_SIZES_BITS,
+};
+use crate::validation::Validated;
+use crate::{Gst, PublicKey, Svn};
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+use core::fmt;
+use sha2::{Digest, Sha256};
+use signature::Signer;
+
+// Size of the fixed fields of the DSM-KROOT
+const DSM_KROOT_FIXED_BYTES: usize = 13;
+// Number of subframes between a MACK message and the key of its Slow MAC tags
+const SLOWMAC_DELAY: i32 = 11;
+
+/// TESLA chain configuration.
+///
+/// These are the parameters that are transmitted in the DSM-KROOT message, as
+/// described in Section 3.2.3 of the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ChainConfig {
+    /// Chain ID (CIDKR).
+    ///
+    /// This must be between 0 and 3.
+    pub chain_id: u8,
+    /// ID of the public key used to sign the DSM-KROOT (PKID).
+    ///
+    /// This must be between 0 and 15.
+    pub public_key_id: u8,
+    /// Hash function of the TESLA chain.
+    pub hash_function: HashFunction,
+    /// MAC function used to compute the tags.
+    pub mac_function: MacFunction,
+    /// Size of the TESLA keys in bits.
+    ///
+    /// This must be one of the [`KEY_SIZES_BITS`].
+    pub key_size_bits: usize,
+    /// Size of the tags in bits.
+    ///
+    /// This must be one of the [`TAG_SIZES_BITS`].
+    pub tag_size_bits: usize,
+    /// MAC look-up table.
+    pub mac_lookup_table: u8,
+    /// Chain random parameter alpha.
+    ///
+    /// Only the 48 least significant bits are used.
+    pub alpha: u64,
+    /// Time of applicability of the KROOT.
+    ///
+    /// This must be the start of an hour. The KROOT is the key of the
+    /// subframe that starts 30 seconds earlier.
+    pub kroot_time: Gst,
+}
+
+/// ECDSA private key used to sign the DSM-KROOT.
+///
+/// P-256 signatures are deterministic, as described in RFC 6979. P-521
+/// signatures use a nonce derived from the private key and the message, so
+/// they are also deterministic, but they do not follow RFC 6979.
+#[derive(Clone)]
+pub enum SigningKey {
+    /// ECDSA P-256 private key.
+    P256(p256::ecdsa::SigningKey),
+    /// ECDSA P-521 private key.
+    #[cfg(feature = "p521")]
+    P521(p521::ecdsa::SigningKey),
+}
+
+impl SigningKey {
+    /// Gives the public key corresponding to this private key.
+    ///
+    /// The public key ID is set to zero. It is not relevant to check the
+    /// signature of the DSM-KROOT.
+    pub fn public_key(&self) -> PublicKey<Validated> {
+        match self {
+            SigningKey::P256(key) => PublicKey::from_p256(*key.verifying_key(), 0).force_valid(),
+            #[cfg(feature = "p521")]
+            SigningKey::P521(key) => {
+                PublicKey::from_p521(p521::ecdsa::VerifyingKey::from(key), 0).force_valid()
+            }
+        }
+    }
+
+    fn signature_bytes(&self) -> usize {
+        match self {
+            SigningKey::P256(_) => 64,
+            #[cfg(feature = "p521")]
+            SigningKey::P521(_) => 132,
+        }
+    }
+
+    fn sign(&self, message: &[u8], signature: &mut [u8]) {
+        match self {
+            SigningKey::P256(key) => {
+                let s: p256::ecdsa::Signature = key.sign(message);
+                signature.copy_from_slice(&s.to_bytes());
+            }
+            #[cfg(feature = "p521")]
+            SigningKey::P521(key) => {
+                use p521::ecdsa::signature::RandomizedSigner;
+                let mut rng = NonceRng::new(&key.to_bytes(), message);
+                let s: p521::ecdsa::Signature = key.sign_with_rng(&mut rng, message);
+                signature.copy_from_slice(&s.to_bytes());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The private key is not shown
+        match self {
+            SigningKey::P256(_) => f.write_str("SigningKey::P256"),
+            #[cfg(feature = "p521")]
+            SigningKey::P521(_) => f.write_str("SigningKey::P521"),
+        }
+    }
+}
+
+// Generator of the ECDSA P-521 nonces. The p521 crate only signs
+// deterministically when it has access to the OS random number generator, so
+// the random bytes are generated by hashing the private key and the message
+// with a counter.
+#[cfg(feature = "p521")]
+struct NonceRng {
+    seed: sha2::Sha512,
+    counter: u32,
+    buffer: [u8; 64],
+    used: usize,
+}
+
+#[cfg(feature = "p521")]
+impl NonceRng {
+    fn new(secret: &[u8], message: &[u8]) -> NonceRng {
+        let mut seed = sha2::Sha512::new();
+        seed.update(secret);
+        seed.update(message);
+        NonceRng {
+            seed,
+            counter: 0,
+            buffer: [0; 64],
+            used: 64,
+        }
+    }
+}
+
+#[cfg(feature = "p521")]
+impl p521::ecdsa::signature::rand_core::RngCore for NonceRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_be_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_be_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            if self.used == self.buffer.len() {
+                let mut hash = self.seed.clone();
+                hash.update(self.counter.to_be_bytes());
+                self.buffer.copy_from_slice(&hash.finalize());
+                self.counter += 1;
+                self.used = 0;
+            }
+            *byte = self.buffer[self.used];
+            self.used += 1;
+        }
+    }
+
+    fn try_fill_bytes(
+        &mut self,
+        dest: &mut [u8],
+    ) -> Result<(), p521::ecdsa::signature::rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "p521")]
+impl p521::ecdsa::signature::rand_core::CryptoRng for NonceRng {}
+
+/// Specification of a tag of a MACK message.
+///
+/// See [`TeslaChain::mack`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TagSpec<'a> {
+    /// SVN of the satellite whose navigation data is authenticated by the
+    /// tag (PRND).
+    pub prnd: Svn,
+    /// ADKD of the tag.
+    pub adkd: Adkd,
+    /// Cut-off point (COP) of the tag.
+    pub cop: u8,
+    /// Navigation data authenticated by the tag.
+    ///
+    /// This must be [`CedBits`](crate::types::CedBits) for ADKD=0 and 12, and
+    /// [`TimingBits`](crate::types::TimingBits) for ADKD=4.
+    pub navdata: NavDataBits<'a>,
+}
+
+/// Errors produced by the [`TeslaChain`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SignerError {
+    /// The TESLA key size is not valid.
+    KeySize,
+    /// The tag size is not valid.
+    TagSize,
+    /// The time of applicability of the KROOT is not the start of an hour.
+    KrootTime,
+    /// The chain parameters are not valid.
+    Chain(ChainError),
+    /// The DSM-KROOT does not fit in the number of blocks allowed by the
+    /// NB_DK field.
+    DsmKrootSize,
+    /// A TESLA key that is needed is not covered by the chain.
+    KeyOutOfChain,
+    /// The number of tags does not match the MAC look-up table.
+    NumTags {
+        /// Number of tags in each MACK message, including tag0.
+        expected: usize,
+    },
+    /// A tag has a reserved ADKD.
+    ReservedAdkd,
+    /// The navigation data of a tag does not correspond to its ADKD.
+    NavDataMismatch,
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerError::KeySize => "invalid TESLA key size".fmt(f),
+            SignerError::TagSize => "invalid tag size".fmt(f),
+            SignerError::KrootTime => "KROOT time is not the start of an hour".fmt(f),
+            SignerError::Chain(e) => write!(f, "invalid chain parameters ({e})"),
+            SignerError::DsmKrootSize => "DSM-KROOT is too long".fmt(f),
+            SignerError::KeyOutOfChain => "TESLA key not covered by the chain".fmt(f),
+            SignerError::NumTags { expected } => {
+                write!(f, "wrong number of tags (expected {expected})")
+            }
+            SignerError::ReservedAdkd => "reserved ADKD".fmt(f),
+            SignerError::NavDataMismatch => "navigation data does not match ADKD".fmt(f),
+        }
+    }
+}
+
+impl From<ChainError> for SignerError {
+    fn from(value: ChainError) -> SignerError {
+        SignerError::Chain(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SignerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SignerError::Chain(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Generated TESLA chain.
+///
+/// This holds all the keys of a TESLA chain, from the KROOT to the seed key
+/// used to generate the chain. See the [module documentation](self) for an
+/// example.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TeslaChain {
+    config: ChainConfig,
+    chain: Chain,
+    // keys[j] is the key of the subframe kroot_gst + 30 * j
+    keys: Vec<Key<Validated>>,
+}
+
+impl TeslaChain {
+    /// Generates a TESLA chain.
+    ///
+    /// The chain is generated backwards from `seed_key`, which is the last key
+    /// of the chain, by applying the TESLA one-way function. The chain
+    /// contains `num_keys` keys, including the KROOT, so the seed key is the
+    /// key of the subframe that starts `num_keys - 1` subframes after the
+    /// subframe of the KROOT.
+    ///
+    /// An error is returned if the configuration is not valid or if the size
+    /// of `seed_key` does not match the key size of the configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_keys` is zero.
+    pub fn generate(
+        config: ChainConfig,
+        seed_key: &[u8],
+        num_keys: usize,
+    ) -> Result<TeslaChain, SignerError> {
+        assert!(num_keys > 0);
+        let mut fields = [0; DSM_KROOT_FIXED_BYTES];
+        Self::fill_fields(&mut fields, &config, 0)?;
+        if seed_key.len() * 8 != config.key_size_bits {
+            return Err(SignerError::KeySize);
+        }
+        let chain = Chain::from_dsm_kroot(DsmKroot(&fields))?;
+        let kroot_gst = config.kroot_time.wrapping_add_seconds(-30);
+        let last_gst = kroot_gst.wrapping_add_subframes(i32::try_from(num_keys - 1).unwrap());
+        let mut keys = Vec::with_capacity(num_keys);
+        keys.push(Key::from_slice(seed_key, last_gst, &chain).force_valid());
+        for _ in 1..num_keys {
+            keys.push(keys.last().unwrap().one_way_function());
+        }
+        keys.reverse();
+        Ok(TeslaChain {
+            config,
+            chain,
+            keys,
+        })
+    }
+
+    // Fills the fixed fields of the DSM-KROOT (Figure 7 in the OSNMA SIS ICD
+    // v1.1).
+    fn fill_fields(
+        dsm_kroot: &mut [u8],
+        config: &ChainConfig,
+        nb_dk: u8,
+    ) -> Result<(), SignerError> {
+        let ks = KEY_SIZES_BITS
+            .iter()
+            .position(|&s| s == config.key_size_bits)
+            .ok_or(SignerError::KeySize)?;
+        let ts = TAG_SIZES_BITS
+            .iter()
+            .position(|&s| s == config.tag_size_bits)
+            .ok_or(SignerError::TagSize)?;
+        // TS values start at 5 for 20-bit tags
+        let ts = ts + 5;
+        let time = config.kroot_time;
+        if time.tow() % 3600 != 0 {
+            return Err(SignerError::KrootTime);
+        }
+        let bits = BitSlice::from_slice_mut(dsm_kroot);
+        bits[..4].store_be(nb_dk);
+        bits[4..8].store_be(config.public_key_id & 0xf);
+        bits[8..10].store_be(config.chain_id & 0x3);
+        bits[12..14].store_be::<u8>(match config.hash_function {
+            HashFunction::Sha256 => 0,
+            HashFunction::Sha3_256 => 2,
+        });
+        bits[14..16].store_be::<u8>(match config.mac_function {
+            MacFunction::HmacSha256 => 0,
+            MacFunction::CmacAes => 1,
+        });
+        bits[16..20].store_be(u8::try_from(ks).unwrap());
+        bits[20..24].store_be(u8::try_from(ts).unwrap());
+        bits[24..32].store_be(config.mac_lookup_table);
+        bits[36..48].store_be(time.wn());
+        bits[48..56].store_be(u8::try_from(time.tow() / 3600).unwrap());
+        bits[56..104].store_be(config.alpha & ((1 << 48) - 1));
+        Ok(())
+    }
+
+    /// Gives the configuration of the chain.
+    pub fn config(&self) -> &ChainConfig {
+        &self.config
+    }
+
+    /// Gives the parameters of the chain.
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    /// Gives the KROOT of the chain.
+    pub fn kroot(&self) -> Key<Validated> {
+        self.keys[0]
+    }
+
+    /// Gives the TESLA key transmitted in the subframe that contains `gst`.
+    ///
+    /// This returns `None` if the subframe is not covered by the chain.
+    pub fn key(&self, gst: Gst) -> Option<Key<Validated>> {
+        let idx = gst
+            .gst_subframe()
+            .subframes_difference(self.kroot().gst_subframe());
+        usize::try_from(idx)
+            .ok()
+            .and_then(|idx| self.keys.get(idx))
+            .copied()
+    }
+
+    /// Generates the DSM-KROOT message.
+    ///
+    /// The DSM-KROOT is signed with `signing_key`, using the NMA header
+    /// `nma_header`, as described in Section 6.3 of the
+    /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+    /// The NMA header must be the one transmitted with the DSM-KROOT, since a
+    /// receiver uses it to verify the signature. The padding is filled as
+    /// indicated in Eq. 7 in the ICD.
+    pub fn dsm_kroot(
+        &self,
+        nma_header: u8,
+        signing_key: &SigningKey,
+    ) -> Result<Vec<u8>, SignerError> {
+        let kroot = self.kroot();
+        let kroot_end = DSM_KROOT_FIXED_BYTES + kroot.data().len();
+        let signature_end = kroot_end + signing_key.signature_bytes();
+        let blocks = (signature_end + DSM_BLOCK_BYTES - 1) / DSM_BLOCK_BYTES;
+        let nb_dk = blocks
+            .checked_sub(6)
+            .and_then(|nb| u8::try_from(nb).ok())
+            .filter(|&nb| dsm_kroot_blocks(nb) == Some(blocks))
+            .ok_or(SignerError::DsmKrootSize)?;
+        let mut dsm_kroot = alloc::vec![0; blocks * DSM_BLOCK_BYTES];
+        Self::fill_fields(&mut dsm_kroot, &self.config, nb_dk)?;
+        dsm_kroot[DSM_KROOT_FIXED_BYTES..kroot_end].copy_from_slice(kroot.data());
+
+        let mut message = Vec::with_capacity(kroot_end);
+        message.push(nma_header);
+        // The NB_DK and PKID fields are not included in the message
+        message.extend_from_slice(&dsm_kroot[1..kroot_end]);
+        let (signature, padding) = dsm_kroot[kroot_end..].split_at_mut(signature_end - kroot_end);
+        signing_key.sign(&message, signature);
+        let mut hash = Sha256::new();
+        hash.update(&message);
+        hash.update(&*signature);
+        let hash = hash.finalize();
+        let padding_len = padding.len();
+        padding.copy_from_slice(&hash[..padding_len]);
+        Ok(dsm_kroot)
+    }
+
+    /// Generates a MACK message.
+    ///
+    /// The MACK message is transmitted by the satellite `prna` in the subframe
+    /// that starts at `gst`. The tags are computed as described in Section 6.4
+    /// of the
+    /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf),
+    /// using the NMA status `nma_status`, which must be the one transmitted in
+    /// the NMA header of the subframe. The MACK message contains the TESLA key
+    /// of the subframe, and its MACSEQ field is computed with the key of the
+    /// next subframe.
+    ///
+    /// The `tags` must contain as many elements as tags in the MACK message,
+    /// which is given by [`Chain::num_tags`]. The first element is tag0, whose
+    /// `prnd` and `adkd` fields are ignored, since it always authenticates the
+    /// ADKD=0 data of `prna`. The MAC look-up table is not checked, so MACK
+    /// messages with incorrect ADKDs can be generated for testing.
+    pub fn mack(
+        &self,
+        prna: Svn,
+        gst: Gst,
+        nma_status: NmaStatus,
+        tags: &[TagSpec],
+    ) -> Result<MackMessage, SignerError> {
+        let num_tags = self.chain.num_tags();
+        if tags.len() != num_tags {
+            return Err(SignerError::NumTags { expected: num_tags });
+        }
+        let gst = gst.gst_subframe();
+        let key = self.key(gst).ok_or(SignerError::KeyOutOfChain)?;
+        let next_key = self
+            .key(gst.wrapping_add_subframes(1))
+            .ok_or(SignerError::KeyOutOfChain)?;
+        let tag_bits = self.chain.tag_size_bits();
+        let tag_and_info_bits = tag_bits + 16;
+        let mut mack = [0; MACK_MESSAGE_BYTES];
+        let bits = BitSlice::from_slice_mut(&mut mack);
+        for (j, spec) in tags.iter().enumerate() {
+            let tag_and_info = &mut bits[j * tag_and_info_bits..(j + 1) * tag_and_info_bits];
+            let adkd = if j == 0 { Adkd::InavCed } else { spec.adkd };
+            let timing = matches!(spec.navdata, NavDataBits::TimingParameters(_));
+            if timing != (adkd == Adkd::InavTiming) {
+                return Err(SignerError::NavDataMismatch);
+            }
+            let (prnd, adkd_value, key) = if j == 0 {
+                (None, 0, next_key)
+            } else {
+                let (adkd_value, key) = match spec.adkd {
+                    Adkd::InavCed => (0, next_key),
+                    Adkd::InavTiming => (4, next_key),
+                    Adkd::SlowMac => (
+                        12,
+                        self.key(gst.wrapping_add_subframes(SLOWMAC_DELAY))
+                            .ok_or(SignerError::KeyOutOfChain)?,
+                    ),
+                    Adkd::Reserved => return Err(SignerError::ReservedAdkd),
+                };
+                (Some(u8::from(spec.prnd)), adkd_value, key)
+            };
+            key.compute_tag(
+                &mut tag_and_info[..tag_bits],
+                gst,
+                prnd,
+                prna,
+                u8::try_from(j + 1).unwrap(),
+                nma_status,
+                spec.navdata.as_bitslice(),
+            );
+            if let Some(prnd) = prnd {
+                tag_and_info[tag_bits..tag_bits + 8].store_be(prnd);
+                tag_and_info[tag_bits + 8..tag_bits + 12].store_be::<u8>(adkd_value);
+            }
+            tag_and_info[tag_bits + 12..].store_be(spec.cop);
+        }
+        let key_start = num_tags * tag_and_info_bits;
+        bits[key_start..key_start + self.chain.key_size_bits()]
+            .copy_from_bitslice(BitSlice::from_slice(key.data()));
+
+        // The MAC look-up table has been checked by Chain::from_dsm_kroot
+        let macseq = next_key
+            .compute_macseq(
+                &Mack::new(&mack, self.chain.key_size_bits(), tag_bits),
+                prna,
+                gst,
+            )
+            .unwrap();
+        BitSlice::from_slice_mut(&mut mack)[tag_bits..tag_bits + 12].store_be(macseq);
+        Ok(mack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitfields::NmaHeader;
+    use crate::maclt::{get_maclt_entry, MacLTSlot};
+    use crate::types::{CedBits, TimingBits};
+
+    fn config() -> ChainConfig {
+        ChainConfig {
+            chain_id: 2,
+            public_key_id: 3,
+            hash_function: HashFunction::Sha3_256,
+            mac_function: MacFunction::CmacAes,
+            key_size_bits: 128,
+            tag_size_bits: 40,
+            mac_lookup_table: 34,
+            alpha: 0xfedc_ba98_7654,
+            kroot_time: Gst::new(1300, 7200),
+        }
+    }
+
+    #[test]
+    fn generate() {
+        let chain = TeslaChain::generate(config(), &[0xaa; 16], 10).unwrap();
+        assert_eq!(chain.chain().chain_id(), 2);
+        assert_eq!(chain.chain().hash_function(), HashFunction::Sha3_256);
+        assert_eq!(chain.chain().mac_function(), MacFunction::CmacAes);
+        assert_eq!(chain.chain().alpha(), 0xfedc_ba98_7654);
+        let kroot_gst = Gst::new(1300, 7170);
+        assert_eq!(chain.kroot().gst_subframe(), kroot_gst);
+        let last = chain.key(kroot_gst.add_seconds(9 * 30 + 5)).unwrap();
+        assert_eq!(last.data(), &[0xaa; 16]);
+        assert_eq!(last.derive(9), chain.kroot());
+        assert!(chain.key(kroot_gst.add_subframes(10)).is_none());
+        assert!(chain.key(kroot_gst.add_subframes(-1)).is_none());
+
+        let mut wrong = config();
+        wrong.kroot_time = Gst::new(1300, 7230);
+        assert_eq!(
+            TeslaChain::generate(wrong, &[0xaa; 16], 10),
+            Err(SignerError::KrootTime)
+        );
+        assert_eq!(
+            TeslaChain::generate(config(), &[0xaa; 15], 10),
+            Err(SignerError::KeySize)
+        );
+        wrong = config();
+        wrong.tag_size_bits = 36;
+        assert_eq!(
+            TeslaChain::generate(wrong, &[0xaa; 16], 10),
+            Err(SignerError::TagSize)
+        );
+    }
+
+    #[test]
+    fn dsm_kroot() {
+        let chain = TeslaChain::generate(config(), &[0xaa; 16], 10).unwrap();
+        let keys = [SigningKey::P256(
+            p256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap(),
+        )];
+        #[cfg(feature = "p521")]
+        let keys = {
+            // The private key must be smaller than the order of P-521
+            let mut secret = [7; 66];
+            secret[0] = 1;
+            [
+                keys[0].clone(),
+                SigningKey::P521(p521::ecdsa::SigningKey::from_slice(&secret).unwrap()),
+            ]
+        };
+        for signing_key in &keys {
+            let nma_header = 0x62;
+            let dsm_kroot = chain.dsm_kroot(nma_header, signing_key).unwrap();
+            let dsm = DsmKroot(&dsm_kroot);
+            assert_eq!(dsm.public_key_id(), 3);
+            assert_eq!(
+                dsm.number_of_blocks(),
+                Some(dsm_kroot.len() / DSM_BLOCK_BYTES)
+            );
+            let (kroot, _) =
+                Key::from_dsm_kroot(NmaHeader::new(nma_header), dsm, &signing_key.public_key())
+                    .unwrap();
+            assert_eq!(kroot, chain.kroot());
+            // The signature covers the NMA header
+            assert!(
+                Key::from_dsm_kroot(NmaHeader::new(0x52), dsm, &signing_key.public_key()).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn mack() {
+        let chain = TeslaChain::generate(config(), &[0xaa; 16], 20).unwrap();
+        let prna = Svn::try_from(11).unwrap();
+        let gst = Gst::new(1300, 7200);
+        let ced = &BitSlice::from_slice(&[0xa5; 69])[..549];
+        let timing = &BitSlice::from_slice(&[0x3c; 18])[..141];
+        let msg = usize::try_from(gst.tow() / 30 % 2).unwrap();
+        let tags: Vec<TagSpec> = (0..chain.chain().num_tags())
+            .map(|j| {
+                let adkd = if j == 0 {
+                    Adkd::InavCed
+                } else {
+                    match get_maclt_entry(34, msg, j).unwrap() {
+                        MacLTSlot::Fixed { adkd, .. } => adkd,
+                        MacLTSlot::Flex => Adkd::InavCed,
+                    }
+                };
+                TagSpec {
+                    prnd: prna,
+                    adkd,
+                    cop: 3,
+                    navdata: if adkd == Adkd::InavTiming {
+                        TimingBits::new(timing).unwrap().into()
+                    } else {
+                        CedBits::new(ced).unwrap().into()
+                    },
+                }
+            })
+            .collect();
+        let nma_status = NmaStatus::Test;
+        let mack = chain.mack(prna, gst, nma_status, &tags).unwrap();
+
+        let mack = Mack::new(&mack, 128, 40);
+        let next_key = chain.key(gst.add_subframes(1)).unwrap();
+        let slowmac_key = chain.key(gst.add_subframes(SLOWMAC_DELAY)).unwrap();
+        assert_eq!(
+            mack.key(),
+            BitSlice::from_slice(chain.key(gst).unwrap().data())
+        );
+        let mack = mack.validate(&next_key, prna, gst).unwrap();
+        let ced = CedBits::new(ced).unwrap();
+        let timing = TimingBits::new(timing).unwrap();
+        assert!(next_key.validate_tag0(mack.tag0(), gst, prna, nma_status, ced));
+        for j in 1..mack.num_tags() {
+            let tag = mack.tag_and_info(j);
+            assert_eq!(tag.cop(), 3);
+            let (key, navdata) = match tag.adkd() {
+                Adkd::SlowMac => (&slowmac_key, NavDataBits::from(ced)),
+                Adkd::InavTiming => (&next_key, timing.into()),
+                _ => (&next_key, ced.into()),
+            };
+            assert!(key.validate_tag(
+                tag.tag(),
+                gst,
+                u8::from(prna),
+                prna,
+                u8::try_from(j + 1).unwrap(),
+                nma_status,
+                navdata
+            ));
+        }
+
+        let mut wrong = tags.clone();
+        wrong[0].navdata = timing.into();
+        assert_eq!(
+            chain.mack(prna, gst, nma_status, &wrong),
+            Err(SignerError::NavDataMismatch)
+        );
+        assert_eq!(
+            chain.mack(prna, gst, nma_status, &tags[1..]),
+            Err(SignerError::NumTags { expected: 6 })
+        );
+        // The Slow MAC key is not in the chain
+        assert_eq!(
+            chain.mack(prna, gst.add_subframes(10), nma_status, &tags),
+            Err(SignerError::KeyOutOfChain)
+        );
+    }
+}
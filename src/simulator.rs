@@ -0,0 +1,870 @@
+//! Simulated OSNMA broadcast.
+//!
+//! This module contains a [`Simulator`] that generates the I/NAV words and
+//! OSNMA data messages broadcast by a set of Galileo satellites. It uses a
+//! TESLA chain and an ECDSA P-256 key generated from a seed, so that the
+//! broadcast can be authenticated by an [`Osnma`] black box that uses the
+//! public key given by [`Simulator::public_key`].
+//!
+//! The simulator is intended for long-duration tests (soak tests) of the
+//! [`Osnma`] black box. It can simulate weeks of broadcast, including week
+//! rollovers, changes of the navigation data, page losses and bit errors that
+//! have not been detected by the CRC of the I/NAV pages. It is only available
+//! when the crate is built with the `simulator` feature, which requires `std`
+//! and should only be used for testing.
+//!
+//! The simulated broadcast uses a single TESLA chain with SHA-256,
+//! HMAC-SHA-256, 128-bit keys, 40-bit tags and the MAC look-up table 34. The
+//! DSM-KROOT is signed with ECDSA P-256. The flexible tags of the MAC look-up
+//! table are filled with ADKD=0 cross-authentication tags.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::simulator::{Simulator, SimulatorConfig};
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Gst, Osnma, Svn};
+//!
+//! let config = SimulatorConfig {
+//!     start: Gst::new(1300, 0),
+//!     duration: 20,
+//!     svns: [4, 11, 19].iter().map(|&s| Svn::try_from(s).unwrap()).collect(),
+//!     ..SimulatorConfig::default()
+//! };
+//! let mut simulator = Simulator::new(config);
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(simulator.public_key(), false);
+//! simulator.run(&mut osnma, 20);
+//!
+//! let svn = Svn::try_from(11).unwrap();
+//! let ced = osnma.get_ced_and_status(svn).unwrap();
+//! assert!(simulator.check_ced_and_status(svn, &ced));
+//! ```
+
+use crate::bitfields::{Adkd, NmaStatus};
+use crate::inav::{CED_AND_STATUS_LAYOUT, TIMING_PARAMETERS_LAYOUT};
+use crate::maclt::{get_maclt_entry, AuthObject, MacLTSlot};
+use crate::navmessage::NavMessageData;
+use crate::signer::{self, ChainConfig, TagSpec, TeslaChain};
+use crate::storage::Storage;
+use crate::tesla::{HashFunction, Key, MacFunction};
+use crate::types::{
+    BitSlice, CedBits, InavWord, MackMessage, OsnmaDataMessage, TimingBits, DSM_BLOCK_BYTES,
+    HKROOT_SECTION_BYTES, MACK_SECTION_BYTES,
+};
+use crate::validation::Validated;
+use crate::verifier::Verifier;
+use crate::{Gst, InavBand, Osnma, PublicKey, Svn};
+use bitvec::prelude::*;
+use p256::ecdsa::SigningKey;
+
+const PAGES_PER_SUBFRAME: u32 = 15;
+const PUBLIC_KEY_ID: u8 = 1;
+const CHAIN_ID: u8 = 1;
+const DSM_ID: u8 = 1;
+const KEY_BYTES: usize = 16;
+const TAG_BITS: usize = 40;
+const MACLT: u8 = 34;
+// Key delay of the Slow MAC tags, in subframes
+const SLOWMAC_DELAY: i32 = 11;
+const CED_BITS: usize = 549;
+const TIMING_BITS: usize = 141;
+const CED_BYTES: usize = (CED_BITS + 7) / 8;
+const TIMING_BYTES: usize = (TIMING_BITS + 7) / 8;
+// Word types transmitted in each page of the E1-B nominal subframe. Word types
+// 7 and 8 are replaced by 9 and 10 in odd subframes.
+const E1B_WORD_TYPES: [u8; PAGES_PER_SUBFRAME as usize] =
+    [2, 4, 6, 7, 8, 0, 0, 0, 0, 0, 1, 3, 5, 0, 0];
+
+/// Simulator configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatorConfig {
+    /// GST at the start of the first simulated subframe.
+    ///
+    /// This must be the start of a subframe.
+    pub start: Gst,
+    /// Duration of the simulation in subframes.
+    ///
+    /// The TESLA chain is generated to cover this duration, so the simulator
+    /// panics if it is run for longer.
+    pub duration: u32,
+    /// SVNs of the satellites whose broadcast is simulated.
+    pub svns: Vec<Svn>,
+    /// Probability that each page is lost.
+    pub page_loss: f64,
+    /// Probability that each page contains a bit error.
+    ///
+    /// This simulates bit errors that have not been detected by the CRC of
+    /// the I/NAV page. A random bit of the I/NAV word or of the OSNMA data
+    /// message is flipped.
+    pub bit_error: f64,
+    /// Number of subframes between changes of the navigation data.
+    pub navdata_period: u32,
+    /// NMA status used in the NMA header.
+    pub nma_status: NmaStatus,
+    /// Seed for the generation of the cryptographic material, the navigation
+    /// data, and the page losses and bit errors.
+    pub seed: u64,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> SimulatorConfig {
+        SimulatorConfig {
+            start: Gst::new(1300, 0),
+            duration: 2 * 60 * 24,
+            svns: [2, 4, 5, 9, 11, 12, 19, 26, 31, 33]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            page_loss: 0.0,
+            bit_error: 0.0,
+            navdata_period: 2 * 60 * 3,
+            nma_status: NmaStatus::Operational,
+            seed: 0,
+        }
+    }
+}
+
+/// Simulated I/NAV page.
+///
+/// This contains the data of an E1-B I/NAV page generated by a
+/// [`Simulator`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SimulatedPage {
+    /// SVN of the satellite that transmits the page.
+    pub svn: Svn,
+    /// GST at the start of the page.
+    pub gst: Gst,
+    /// I/NAV word.
+    pub word: InavWord,
+    /// OSNMA data message.
+    pub osnma: OsnmaDataMessage,
+}
+
+/// Simulator statistics.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SimulatorStats {
+    /// Number of subframes simulated.
+    pub subframes: u32,
+    /// Number of pages generated, including those that have been lost.
+    pub pages: u64,
+    /// Number of pages lost.
+    pub lost_pages: u64,
+    /// Number of pages with bit errors.
+    pub corrupted_pages: u64,
+}
+
+/// OSNMA broadcast simulator.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    config: SimulatorConfig,
+    public_key: p256::ecdsa::VerifyingKey,
+    nma_header: u8,
+    dsm_kroot: Vec<u8>,
+    chain: TeslaChain,
+    gst: Gst,
+    rng: Rng,
+    stats: SimulatorStats,
+}
+
+impl Simulator {
+    /// Creates a new simulator.
+    ///
+    /// The ECDSA key, the DSM-KROOT and the whole TESLA chain are generated
+    /// from the seed in the configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.start` is not the start of a subframe, if
+    /// `config.svns` is empty or if `config.navdata_period` is zero.
+    pub fn new(config: SimulatorConfig) -> Simulator {
+        assert!(config.start.is_subframe());
+        assert!(!config.svns.is_empty());
+        assert!(config.navdata_period > 0);
+        let mut rng = Rng::new(config.seed);
+        let signing_key = loop {
+            let mut secret = [0; 32];
+            rng.fill(&mut secret);
+            if let Ok(key) = SigningKey::from_slice(&secret) {
+                break key;
+            }
+        };
+
+        // The KROOT corresponds to the start of the hour before the start of
+        // the simulation
+        let kroot_time = Gst::new(config.start.wn(), config.start.tow() / 3600 * 3600);
+        let kroot_gst = kroot_time.wrapping_add_seconds(-30);
+        let nma_header = (Self::nmas(config.nma_status) << 6) | (CHAIN_ID << 4) | (1 << 1);
+        let chain_config = ChainConfig {
+            chain_id: CHAIN_ID,
+            public_key_id: PUBLIC_KEY_ID,
+            hash_function: HashFunction::Sha256,
+            mac_function: MacFunction::HmacSha256,
+            key_size_bits: 8 * KEY_BYTES,
+            tag_size_bits: TAG_BITS,
+            mac_lookup_table: MACLT,
+            alpha: rng.next_u64() & ((1 << 48) - 1),
+            kroot_time,
+        };
+
+        // Generate the TESLA chain backwards from its last key. The Slow MAC
+        // tags of the last subframe need a key 11 subframes later.
+        let num_keys = usize::try_from(
+            config.start.subframes_difference(kroot_gst)
+                + i32::try_from(config.duration).unwrap()
+                + SLOWMAC_DELAY
+                + 1,
+        )
+        .unwrap();
+        let mut last_key = [0; KEY_BYTES];
+        rng.fill(&mut last_key);
+        let chain = TeslaChain::generate(chain_config, &last_key, num_keys).unwrap();
+        let public_key = *signing_key.verifying_key();
+        let dsm_kroot = chain
+            .dsm_kroot(nma_header, &signer::SigningKey::P256(signing_key))
+            .unwrap();
+
+        Simulator {
+            gst: config.start,
+            config,
+            public_key,
+            nma_header,
+            dsm_kroot,
+            chain,
+            rng,
+            stats: SimulatorStats::default(),
+        }
+    }
+
+    fn nmas(nma_status: NmaStatus) -> u8 {
+        match nma_status {
+            NmaStatus::Reserved => 0,
+            NmaStatus::Test => 1,
+            NmaStatus::Operational => 2,
+            NmaStatus::DontUse => 3,
+        }
+    }
+
+    /// Returns the ECDSA public key used to sign the DSM-KROOT.
+    pub fn public_key(&self) -> PublicKey<Validated> {
+        PublicKey::from_p256(self.public_key, PUBLIC_KEY_ID).force_valid()
+    }
+
+    /// Returns the GST at the start of the next subframe to be simulated.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Returns the simulator statistics.
+    pub fn stats(&self) -> &SimulatorStats {
+        &self.stats
+    }
+
+    /// Returns the TESLA key transmitted in the subframe that starts at `gst`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `gst` is not covered by the TESLA chain of the simulator.
+    pub fn tesla_key(&self, gst: Gst) -> Key<Validated> {
+        self.chain
+            .key(gst)
+            .expect("GST not covered by the TESLA chain")
+    }
+
+    /// Simulates the next subframe.
+    ///
+    /// The pages of all the satellites are returned in chronological order.
+    /// Pages that have been lost are not included.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration of the simulation given in the configuration
+    /// has been exceeded.
+    pub fn next_subframe(&mut self) -> Vec<SimulatedPage> {
+        assert!(
+            self.stats.subframes < self.config.duration,
+            "simulation duration exceeded"
+        );
+        let gst = self.gst;
+        let macks: Vec<MackMessage> = self
+            .config
+            .svns
+            .iter()
+            .enumerate()
+            .map(|(j, &svn)| self.mack(j, svn, gst))
+            .collect();
+        let num_svns = self.config.svns.len();
+        let mut pages = Vec::with_capacity(PAGES_PER_SUBFRAME as usize * num_svns);
+        for page in 0..PAGES_PER_SUBFRAME {
+            let page_gst = gst.wrapping_add_seconds(2 * i32::try_from(page).unwrap());
+            for (j, &svn) in self.config.svns.iter().enumerate() {
+                self.stats.pages += 1;
+                if self.rng.next_f64() < self.config.page_loss {
+                    self.stats.lost_pages += 1;
+                    continue;
+                }
+                let word = self.word(svn, gst, E1B_WORD_TYPES[page as usize]);
+                let mut osnma = [0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+                osnma[0] = self.hkroot(j, gst)[page as usize];
+                let mack_start = page as usize * MACK_SECTION_BYTES;
+                osnma[HKROOT_SECTION_BYTES..]
+                    .copy_from_slice(&macks[j][mack_start..mack_start + MACK_SECTION_BYTES]);
+                let mut page = SimulatedPage {
+                    svn,
+                    gst: page_gst,
+                    word,
+                    osnma,
+                };
+                if self.rng.next_f64() < self.config.bit_error {
+                    self.stats.corrupted_pages += 1;
+                    let total_bits = 8 * (page.word.len() + page.osnma.len());
+                    let bit = usize::try_from(self.rng.next_u64() % total_bits as u64).unwrap();
+                    if bit < 8 * page.word.len() {
+                        page.word[bit / 8] ^= 0x80 >> (bit % 8);
+                    } else {
+                        let bit = bit - 8 * page.word.len();
+                        page.osnma[bit / 8] ^= 0x80 >> (bit % 8);
+                    }
+                }
+                pages.push(page);
+            }
+        }
+        self.gst = gst.wrapping_add_subframes(1);
+        self.stats.subframes += 1;
+        pages
+    }
+
+    /// Runs the simulation for a number of subframes.
+    ///
+    /// The simulated pages are fed into the `osnma` black box with
+    /// [`Osnma::feed_inav`] and [`Osnma::feed_osnma`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration of the simulation given in the configuration
+    /// is exceeded.
+    pub fn run<S: Storage, V: Verifier>(&mut self, osnma: &mut Osnma<S, V>, subframes: u32) {
+        for _ in 0..subframes {
+            for page in self.next_subframe() {
+                osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+                osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+            }
+        }
+    }
+
+    /// Checks authenticated CED and health status data against the broadcast.
+    ///
+    /// Returns `true` if `data`, which should have been obtained with
+    /// [`Osnma::get_ced_and_status`], is equal to the data broadcast by the
+    /// satellite `svn` in the subframe in which the data was received.
+    pub fn check_ced_and_status(&self, svn: Svn, data: &NavMessageData) -> bool {
+        let ced = self.ced_and_status(svn, data.latency().data_gst());
+        data.data() == BitSlice::from_slice(&ced)[..CED_BITS]
+    }
+
+    /// Checks authenticated timing parameters against the broadcast.
+    ///
+    /// Returns `true` if `data`, which should have been obtained with
+    /// [`Osnma::get_timing_parameters`], is equal to the data broadcast by the
+    /// satellite `svn` in the subframe in which the data was received.
+    pub fn check_timing_parameters(&self, svn: Svn, data: &NavMessageData) -> bool {
+        let timing = self.timing_parameters(svn, data.latency().data_gst());
+        data.data() == BitSlice::from_slice(&timing)[..TIMING_BITS]
+    }
+
+    // Index of the batch of navigation data broadcast in the subframe `gst`,
+    // and number of subframes since the batch started.
+    fn navdata_batch(&self, gst: Gst) -> (u64, u32) {
+        let subframes = gst.subframes_difference(self.config.start);
+        let period = i32::try_from(self.config.navdata_period).unwrap();
+        (
+            // The data before the start of the simulation is also a valid
+            // batch, so that tags can be computed for the first subframe
+            (subframes.div_euclid(period) + 1).try_into().unwrap(),
+            subframes.rem_euclid(period).try_into().unwrap(),
+        )
+    }
+
+    fn word(&self, svn: Svn, gst: Gst, mut word_type: u8) -> InavWord {
+        if gst.tow() / 30 % 2 == 1 && (word_type == 7 || word_type == 8) {
+            word_type += 2;
+        }
+        let (batch, _) = self.navdata_batch(gst);
+        // The CED and timing parameters change with each batch of navigation
+        // data. The other words change in each subframe.
+        let variant = if matches!(word_type, 1..=6 | 10) {
+            batch
+        } else {
+            u64::from(gst.wn()) << 32 | u64::from(gst.tow())
+        };
+        let mut rng = Rng::new(
+            self.config.seed
+                ^ (u64::from(u8::from(svn)) << 56)
+                ^ (u64::from(word_type) << 48)
+                ^ mix(variant),
+        );
+        let mut word = [0; 16];
+        rng.fill(&mut word);
+        BitSlice::from_slice_mut(&mut word)[..6].store_be(word_type);
+        word
+    }
+
+    fn ced_and_status(&self, svn: Svn, gst: Gst) -> [u8; CED_BYTES] {
+        let mut ced = [0; CED_BYTES];
+        let bits = BitSlice::from_slice_mut(&mut ced);
+        for layout in &CED_AND_STATUS_LAYOUT {
+            let word = self.word(svn, gst, layout.word_type().into());
+            bits[layout.data_bits()]
+                .copy_from_bitslice(layout.extract(BitSlice::from_slice(&word)));
+        }
+        ced
+    }
+
+    fn timing_parameters(&self, svn: Svn, gst: Gst) -> [u8; TIMING_BYTES] {
+        let mut timing = [0; TIMING_BYTES];
+        let bits = BitSlice::from_slice_mut(&mut timing);
+        for layout in &TIMING_PARAMETERS_LAYOUT {
+            let word = self.word(svn, gst, layout.word_type().into());
+            bits[layout.data_bits()]
+                .copy_from_bitslice(layout.extract(BitSlice::from_slice(&word)));
+        }
+        timing
+    }
+
+    // COP of the tags that authenticate the navigation data of the subframe
+    // `gst`. This is the number of subframes during which the data has not
+    // changed, which guarantees that a receiver does not try to authenticate
+    // data of a previous batch.
+    fn cop(&self, gst: Gst) -> u8 {
+        let (_, age) = self.navdata_batch(gst);
+        u8::try_from(age.saturating_add(1).min(15)).unwrap()
+    }
+
+    fn hkroot(&self, svn_idx: usize, gst: Gst) -> [u8; 15] {
+        let mut hkroot = [0; 15];
+        hkroot[0] = self.nma_header;
+        let kroot_gst = self.chain.kroot().gst_subframe();
+        let subframes = usize::try_from(gst.subframes_difference(kroot_gst)).unwrap();
+        let block = (subframes + svn_idx) % (self.dsm_kroot.len() / DSM_BLOCK_BYTES);
+        hkroot[1] = (DSM_ID << 4) | u8::try_from(block).unwrap();
+        hkroot[2..].copy_from_slice(&self.dsm_kroot[block * DSM_BLOCK_BYTES..][..DSM_BLOCK_BYTES]);
+        hkroot
+    }
+
+    fn mack(&self, svn_idx: usize, prna: Svn, gst: Gst) -> MackMessage {
+        // The tags authenticate the navigation data of the previous subframe
+        let gst_navdata = gst.wrapping_add_subframes(-1);
+        let cop = self.cop(gst_navdata);
+        let msg = usize::try_from(gst.tow() / 30 % 2).unwrap();
+        let tags: Vec<(Svn, Adkd)> = (0..self.chain.chain().num_tags())
+            .map(|j| {
+                if j == 0 {
+                    return (prna, Adkd::InavCed);
+                }
+                let (adkd, object) = match get_maclt_entry(MACLT, msg, j).unwrap() {
+                    MacLTSlot::Fixed { adkd, object } => (adkd, object),
+                    MacLTSlot::Flex => (Adkd::InavCed, AuthObject::CrossAuth),
+                };
+                let prnd = match object {
+                    AuthObject::SelfAuth => prna,
+                    AuthObject::CrossAuth => {
+                        let svns = &self.config.svns;
+                        svns[(svn_idx + j) % svns.len()]
+                    }
+                };
+                (prnd, adkd)
+            })
+            .collect();
+        let navdata: Vec<Vec<u8>> = tags
+            .iter()
+            .map(|&(prnd, adkd)| match adkd {
+                Adkd::InavTiming => self.timing_parameters(prnd, gst_navdata).to_vec(),
+                _ => self.ced_and_status(prnd, gst_navdata).to_vec(),
+            })
+            .collect();
+        let tags: Vec<TagSpec> = tags
+            .iter()
+            .zip(&navdata)
+            .map(|(&(prnd, adkd), navdata)| {
+                let navdata = BitSlice::from_slice(navdata);
+                TagSpec {
+                    prnd,
+                    adkd,
+                    cop,
+                    navdata: match adkd {
+                        Adkd::InavTiming => {
+                            TimingBits::new(&navdata[..TIMING_BITS]).unwrap().into()
+                        }
+                        _ => CedBits::new(&navdata[..CED_BITS]).unwrap().into(),
+                    },
+                }
+            })
+            .collect();
+        self.chain
+            .mack(prna, gst, self.config.nma_status, &tags)
+            .unwrap()
+    }
+}
+
+// Mixing function of the SplitMix64 PRNG.
+fn mix(x: u64) -> u64 {
+    let mut z = x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// SplitMix64 PRNG. It does not need to be cryptographically secure, since it
+// is only used for simulation.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(mix(seed))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let x = self.next_u64().to_be_bytes();
+            chunk.copy_from_slice(&x[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::DiagnosticKind;
+    use crate::events::Event;
+    use crate::navmessage::TAG_VERIFICATIONS_DEPTH;
+    use crate::storage::FullStorage;
+
+    // Runs a simulation, checking after each subframe that all the
+    // authenticated data matches the broadcast. Returns the number of
+    // subframes in which the data of all the satellites was authenticated,
+    // and the number of key validation failures.
+    fn soak<S: Storage>(config: SimulatorConfig) -> (u32, u32) {
+        let duration = config.duration;
+        let svns = config.svns.clone();
+        let mut simulator = Simulator::new(config);
+        let mut osnma = Osnma::<S>::from_pubkey(simulator.public_key(), false);
+        let mut authenticated = 0;
+        let mut key_failures = 0;
+        for _ in 0..duration {
+            simulator.run(&mut osnma, 1);
+            let mut all = true;
+            for &svn in &svns {
+                match osnma.get_ced_and_status(svn) {
+                    Some(data) => assert!(simulator.check_ced_and_status(svn, &data)),
+                    None => all = false,
+                }
+                if let Some(data) = osnma.get_timing_parameters(svn) {
+                    assert!(simulator.check_timing_parameters(svn, &data));
+                }
+            }
+            authenticated += u32::from(all);
+            while let Some(event) = osnma.poll_event() {
+                if let Event::Diagnostic(d) = event {
+                    key_failures += u32::from(d.kind() == DiagnosticKind::KeyValidationFailed);
+                }
+            }
+            // The events of a subframe fit in the queue
+            assert_eq!(osnma.dropped_events(), 0);
+        }
+        // The authentication is still running at the end
+        let key = osnma.latest_tesla_key().unwrap();
+        assert!(simulator.gst().subframes_difference(key.gst_subframe()) <= 3);
+        (authenticated, key_failures)
+    }
+
+    #[test]
+    fn week_rollover() {
+        let config = SimulatorConfig {
+            // Start 3 hours before the end of the week
+            start: Gst::new(1300, 604800 - 3 * 3600),
+            duration: 2 * 60 * 6,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            navdata_period: 2 * 60,
+            ..SimulatorConfig::default()
+        };
+        let duration = config.duration;
+        let (authenticated, key_failures) = soak::<FullStorage>(config);
+        assert_eq!(key_failures, 0);
+        // The data is not authenticated during the first subframes and after
+        // each change of the navigation data
+        assert!(authenticated > duration * 9 / 10);
+    }
+
+    #[test]
+    fn errors() {
+        let config = SimulatorConfig {
+            duration: 2 * 60 * 3,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            page_loss: 0.02,
+            bit_error: 0.005,
+            navdata_period: 2 * 60,
+            seed: 1,
+            ..SimulatorConfig::default()
+        };
+        let (authenticated, _) = soak::<FullStorage>(config);
+        assert!(authenticated > 0);
+    }
+
+    #[test]
+    fn dyn_storage() {
+        use crate::storage::DynStorage;
+
+        let config = SimulatorConfig {
+            duration: 2 * 60,
+            svns: [2, 4, 7, 11, 19, 24, 26, 30, 33]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            ..SimulatorConfig::default()
+        };
+        let duration = config.duration;
+        let (authenticated, key_failures) = soak::<DynStorage>(config);
+        assert_eq!(key_failures, 0);
+        assert!(authenticated > duration * 9 / 10);
+    }
+
+    #[test]
+    fn hot_start() {
+        use crate::OsnmaBuilder;
+
+        let config = SimulatorConfig {
+            duration: 4,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            ..SimulatorConfig::default()
+        };
+        let svns = config.svns.clone();
+        let mut simulator = Simulator::new(config);
+        // The black box does not have a public key, so it can only
+        // authenticate data using the TESLA key set externally
+        let key = simulator.tesla_key(simulator.gst().add_subframes(-1));
+        let mut osnma = OsnmaBuilder::new()
+            .with_tesla_key(key)
+            .build::<FullStorage>();
+        simulator.run(&mut osnma, 3);
+        for &svn in &svns {
+            let data = osnma.get_ced_and_status(svn).unwrap();
+            assert!(simulator.check_ced_and_status(svn, &data));
+        }
+    }
+
+    #[test]
+    fn nma_dont_use() {
+        use crate::NmaDontUsePolicy;
+
+        let config = SimulatorConfig {
+            duration: 2 * 60,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            ..SimulatorConfig::default()
+        };
+        let svn = config.svns[0];
+        let mut simulator = Simulator::new(config);
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(simulator.public_key(), false);
+        simulator.run(&mut osnma, 20);
+        let state = osnma.nma_state().unwrap();
+        assert_eq!(state.nma_status(), NmaStatus::Operational);
+        assert!(state.is_validated());
+        assert!(!osnma.get_ced_and_status(svn).unwrap().nma_dont_use());
+
+        // Flip the NMA status to don't use in the NMA header, which is
+        // transmitted in the first page of the subframe
+        let gst = simulator.gst();
+        for mut page in simulator.next_subframe() {
+            if page.gst == gst {
+                page.osnma[0] |= 0xc0;
+            }
+            osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+            osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+        }
+        let state = osnma.nma_state().unwrap();
+        assert_eq!(state.nma_status(), NmaStatus::DontUse);
+        assert_eq!(state.gst(), gst);
+        assert!(state.is_dont_use());
+        assert!(!state.is_validated());
+
+        // The data authenticated previously is still stored, and the policy
+        // decides whether it is returned
+        let data = osnma.get_ced_and_status(svn).unwrap();
+        assert!(!data.nma_dont_use());
+        assert!(!osnma.get_health(svn).unwrap().nma_dont_use());
+        assert!(!osnma
+            .get_constellation_timing()
+            .unwrap()
+            .data()
+            .nma_dont_use());
+        assert!(osnma.authenticated_data().all(|(.., d)| !d.nma_dont_use()));
+        osnma.set_nma_dont_use_policy(NmaDontUsePolicy::Flag);
+        let data = osnma.get_ced_and_status(svn).unwrap();
+        assert!(data.nma_dont_use());
+        assert!(simulator.check_ced_and_status(svn, &data));
+        assert!(osnma.get_health(svn).unwrap().nma_dont_use());
+        assert!(osnma
+            .get_constellation_timing()
+            .unwrap()
+            .data()
+            .nma_dont_use());
+        assert!(osnma.authenticated_data().count() > 0);
+        assert!(osnma.authenticated_data().all(|(.., d)| d.nma_dont_use()));
+        osnma.set_nma_dont_use_policy(NmaDontUsePolicy::Withhold);
+        assert!(osnma.get_ced_and_status(svn).is_none());
+        assert!(osnma.get_timing_parameters(svn).is_none());
+        assert!(osnma.get_health(svn).is_none());
+        assert!(osnma.get_constellation_timing().is_none());
+        assert_eq!(osnma.authenticated_data().count(), 0);
+    }
+
+    #[test]
+    fn status() {
+        use crate::status::ChainState;
+
+        let config = SimulatorConfig {
+            duration: 2 * 60,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            ..SimulatorConfig::default()
+        };
+        let svns = config.svns.clone();
+        let mut simulator = Simulator::new(config);
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(simulator.public_key(), false);
+        assert_eq!(osnma.status().chain(), ChainState::NoKey);
+        assert_eq!(osnma.status().satellites().count(), 0);
+        simulator.run(&mut osnma, 20);
+        let status = osnma.status();
+        assert!(matches!(status.chain(), ChainState::InForce { .. }));
+        assert_eq!(status.gst(), Some(simulator.gst().add_subframes(-1)));
+        assert_eq!(status.satellites().count(), svns.len());
+        assert_eq!(status.num_fully_authenticated(), svns.len());
+        for satellite in status.satellites() {
+            assert!(satellite.ced_and_status().unwrap().authbits >= 80);
+            assert!(satellite.timing_parameters().is_some());
+            assert_eq!(satellite.failed_tags(), 0);
+            assert_eq!(satellite.seconds_since_mack(), Some(0));
+        }
+
+        // Corrupt the tag0 of the first satellite, which authenticates its own
+        // navigation data, and stop receiving the last satellite
+        let gst = simulator.gst();
+        for mut page in simulator.next_subframe() {
+            if page.svn == svns[3] {
+                continue;
+            }
+            if page.svn == svns[0] && page.gst == gst {
+                page.osnma[1] ^= 0xff;
+            }
+            osnma.feed_inav(&page.word, page.svn, page.gst, InavBand::E1B);
+            osnma.feed_osnma(&page.osnma, page.svn, page.gst);
+        }
+        let status = osnma.status();
+        assert_eq!(status.satellite(svns[3]).seconds_since_mack(), Some(30));
+        assert_eq!(status.satellite(svns[0]).seconds_since_mack(), Some(0));
+        // The tag is verified when the TESLA key is received in the next
+        // subframe
+        simulator.run(&mut osnma, 1);
+        let status = osnma.status();
+        assert!(status.satellite(svns[0]).failed_tags() >= 1);
+        assert_eq!(status.satellite(svns[1]).failed_tags(), 0);
+        assert_eq!(
+            osnma.failed_tags(svns[0]),
+            status.satellite(svns[0]).failed_tags()
+        );
+    }
+
+    #[test]
+    fn tag_events() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static HOOK_EVENTS: AtomicU32 = AtomicU32::new(0);
+        fn hook(_: &Event) {
+            HOOK_EVENTS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let config = SimulatorConfig {
+            duration: 20,
+            svns: [4, 11, 19, 26]
+                .iter()
+                .map(|&s| Svn::try_from(s).unwrap())
+                .collect(),
+            ..SimulatorConfig::default()
+        };
+        let mut simulator = Simulator::new(config);
+        let mut osnma = Osnma::<FullStorage>::from_pubkey(simulator.public_key(), false);
+        osnma.set_tag_events(true);
+        osnma.set_event_hook(Some(hook));
+        let mut events = 0;
+        let mut authenticated = 0;
+        for _ in 0..20 {
+            simulator.run(&mut osnma, 1);
+            for event in osnma.events() {
+                events += 1;
+                match event {
+                    Event::TagAuthenticated { prnd, gst, .. } => {
+                        assert!(simulator.config.svns.contains(&prnd));
+                        assert_eq!(gst, gst.gst_subframe());
+                        authenticated += 1;
+                    }
+                    Event::TagFailed { .. } => panic!("unexpected {event}"),
+                    _ => (),
+                }
+            }
+        }
+        assert!(authenticated > 0);
+        assert_eq!(
+            osnma.tag_verifications().iter().count(),
+            authenticated.min(TAG_VERIFICATIONS_DEPTH)
+        );
+        for verification in osnma.tag_verifications().iter() {
+            assert!(verification.is_valid());
+            assert!(simulator.config.svns.contains(&verification.prna()));
+            assert!(simulator.config.svns.contains(&verification.prnd()));
+        }
+        assert_eq!(HOOK_EVENTS.load(Ordering::Relaxed), events);
+        assert_eq!(osnma.dropped_events(), 0);
+    }
+
+    // Simulates two weeks of broadcast. This is slow in debug builds, so it
+    // should be run with `cargo test --release --features simulator --
+    // --ignored`.
+    #[test]
+    #[ignore]
+    fn two_weeks() {
+        let config = SimulatorConfig {
+            start: Gst::new(1300, 604800 - 24 * 3600),
+            duration: 2 * 60 * 24 * 14,
+            page_loss: 0.01,
+            bit_error: 0.001,
+            seed: 2,
+            ..SimulatorConfig::default()
+        };
+        let (authenticated, _) = soak::<FullStorage>(config);
+        assert!(authenticated > 0);
+    }
+}
@@ -0,0 +1,335 @@
+//! Per-satellite authentication status.
+//!
+//! This module contains [`OsnmaStatus`], a snapshot of the authentication
+//! status of each satellite and of the TESLA chain held by an [`Osnma`] black
+//! box. It is obtained with [`Osnma::status`], and it gathers the
+//! bookkeeping that client software needs to render a live authentication
+//! dashboard: the last authenticated ADKD=0 and ADKD=4 data of each satellite,
+//! its accumulated authentication bits, the number of failed tags, and how
+//! long ago a MACK message was last received from each satellite.
+//!
+//! Unlike the [`AuthReport`](crate::report::AuthReport), which is intended to
+//! be formatted as text by embedded firmware, this snapshot gives the values
+//! of the fields, so that they can be displayed in any way.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::status::ChainState;
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//! let status = osnma.status();
+//! assert_eq!(status.chain(), ChainState::NoKey);
+//! for satellite in status.satellites() {
+//!     println!("{satellite}");
+//! }
+//! ```
+
+use crate::navmessage::NavMessageData;
+use crate::storage::Storage;
+use crate::types::NUM_SVNS;
+use crate::verifier::Verifier;
+use crate::{Gst, Osnma, Svn};
+use core::fmt;
+
+/// Last authentication of some navigation data.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct LastAuthentication {
+    /// GST at the start of the subframe in which the authenticated navigation
+    /// data was transmitted.
+    pub gst: Gst,
+    /// Accumulated authentication bits of the navigation data.
+    pub authbits: u16,
+}
+
+impl LastAuthentication {
+    fn new(data: NavMessageData<'_>) -> LastAuthentication {
+        LastAuthentication {
+            gst: data.gst(),
+            authbits: data.authbits(),
+        }
+    }
+}
+
+/// Authentication status of a satellite.
+///
+/// This is formatted as a line such as `E11 ADKD=0 TOW 121080 (80 bits)
+/// ADKD=4 NONE FAILED 0 MACK 30 s ago`. The line is not terminated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct SatelliteStatus {
+    svn: Svn,
+    ced_and_status: Option<LastAuthentication>,
+    timing_parameters: Option<LastAuthentication>,
+    failed_tags: u32,
+    last_mack: Option<Gst>,
+    seconds_since_mack: Option<i64>,
+}
+
+impl SatelliteStatus {
+    /// Gives the SVN of the satellite.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Gives the last authentication of the ephemeris, clock and status data
+    /// (ADKD=0 and 12) of the satellite.
+    ///
+    /// This is `None` if there is no authenticated data.
+    pub fn ced_and_status(&self) -> Option<LastAuthentication> {
+        self.ced_and_status
+    }
+
+    /// Gives the last authentication of the timing parameters (ADKD=4) of the
+    /// satellite.
+    ///
+    /// This is `None` if there is no authenticated data.
+    pub fn timing_parameters(&self) -> Option<LastAuthentication> {
+        self.timing_parameters
+    }
+
+    /// Gives the number of tags that have failed to authenticate the
+    /// navigation data of the satellite.
+    ///
+    /// This counts the failed tags since the OSNMA black box was constructed,
+    /// regardless of which satellite transmitted them.
+    pub fn failed_tags(&self) -> u32 {
+        self.failed_tags
+    }
+
+    /// Gives the GST of the last subframe in which a MACK message was received
+    /// from the satellite.
+    pub fn last_mack(&self) -> Option<Gst> {
+        self.last_mack
+    }
+
+    /// Gives the time since the last MACK message was received from the
+    /// satellite, in seconds.
+    ///
+    /// This is measured with respect to [`OsnmaStatus::gst`], so it is zero
+    /// for the satellites that transmitted the most recent MACK message.
+    pub fn seconds_since_mack(&self) -> Option<i64> {
+        self.seconds_since_mack
+    }
+
+    /// Returns `true` if there is any information about the satellite.
+    pub fn is_active(&self) -> bool {
+        self.ced_and_status.is_some()
+            || self.timing_parameters.is_some()
+            || self.failed_tags != 0
+            || self.last_mack.is_some()
+    }
+}
+
+impl fmt::Display for SatelliteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.svn)?;
+        for (adkd, auth) in [(0, self.ced_and_status), (4, self.timing_parameters)] {
+            match auth {
+                Some(auth) => write!(
+                    f,
+                    " ADKD={adkd} TOW {} ({} bits)",
+                    auth.gst.tow(),
+                    auth.authbits
+                )?,
+                None => write!(f, " ADKD={adkd} NONE")?,
+            }
+        }
+        write!(f, " FAILED {}", self.failed_tags)?;
+        match self.seconds_since_mack {
+            Some(seconds) => write!(f, " MACK {seconds} s ago"),
+            None => " MACK NONE".fmt(f),
+        }
+    }
+}
+
+/// State of the TESLA chain.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ChainState {
+    /// No TESLA key has been validated yet.
+    ///
+    /// The OSNMA black box is waiting to receive and verify a DSM-KROOT.
+    NoKey,
+    /// A TESLA chain is in force.
+    InForce {
+        /// CID of the chain in force.
+        cid: u8,
+        /// GST of the most recent validated TESLA key.
+        key_gst: Gst,
+    },
+    /// An end-of-chain transition is in progress.
+    ///
+    /// See [`Osnma::chain_transition`].
+    Transition {
+        /// CID of the chain in force.
+        cid: u8,
+        /// GST of the most recent validated TESLA key.
+        key_gst: Gst,
+        /// CID of the next chain, if its DSM-KROOT has been verified.
+        next_cid: Option<u8>,
+    },
+}
+
+impl fmt::Display for ChainState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainState::NoKey => "no TESLA key".fmt(f),
+            ChainState::InForce { cid, key_gst } => {
+                write!(f, "CID {cid} in force, key at {key_gst}")
+            }
+            ChainState::Transition {
+                cid,
+                key_gst,
+                next_cid,
+            } => {
+                write!(f, "CID {cid} ending, key at {key_gst}, next CID ")?;
+                match next_cid {
+                    Some(next_cid) => next_cid.fmt(f),
+                    None => "unknown".fmt(f),
+                }
+            }
+        }
+    }
+}
+
+/// Authentication status of an [`Osnma`] black box.
+///
+/// This is a snapshot of the [`SatelliteStatus`] of each satellite and of the
+/// [`ChainState`]. See the [module documentation](self).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct OsnmaStatus {
+    gst: Option<Gst>,
+    chain: ChainState,
+    satellites: [SatelliteStatus; NUM_SVNS],
+}
+
+impl OsnmaStatus {
+    /// Constructs a snapshot of the current status of an [`Osnma`] black box.
+    ///
+    /// This is equivalent to [`Osnma::status`].
+    pub fn new<S: Storage, V: Verifier>(osnma: &Osnma<S, V>) -> OsnmaStatus {
+        let gst = Svn::iter()
+            .filter_map(|svn| osnma.last_mack_gst(svn))
+            .reduce(Gst::latest);
+        let chain = match osnma.latest_tesla_key() {
+            None => ChainState::NoKey,
+            Some(key) => {
+                let cid = key.chain().chain_id();
+                let key_gst = key.gst_subframe();
+                match osnma.chain_transition() {
+                    Some(transition) => ChainState::Transition {
+                        cid,
+                        key_gst,
+                        next_cid: transition.next_cid(),
+                    },
+                    None => ChainState::InForce { cid, key_gst },
+                }
+            }
+        };
+        let satellites = core::array::from_fn(|j| {
+            let svn = Svn::try_from(j + 1).unwrap();
+            let last_mack = osnma.last_mack_gst(svn);
+            SatelliteStatus {
+                svn,
+                ced_and_status: osnma.get_ced_and_status(svn).map(LastAuthentication::new),
+                timing_parameters: osnma
+                    .get_timing_parameters(svn)
+                    .map(LastAuthentication::new),
+                failed_tags: osnma.failed_tags(svn),
+                last_mack,
+                seconds_since_mack: gst
+                    .zip(last_mack)
+                    .map(|(gst, last)| gst.difference_seconds(last)),
+            }
+        });
+        OsnmaStatus {
+            gst,
+            chain,
+            satellites,
+        }
+    }
+
+    /// Gives the GST of the most recent MACK message received from any
+    /// satellite.
+    ///
+    /// This is `None` if no MACK messages have been received.
+    pub fn gst(&self) -> Option<Gst> {
+        self.gst
+    }
+
+    /// Gives the state of the TESLA chain.
+    pub fn chain(&self) -> ChainState {
+        self.chain
+    }
+
+    /// Gives the status of a satellite.
+    pub fn satellite(&self, svn: Svn) -> &SatelliteStatus {
+        &self.satellites[svn.index()]
+    }
+
+    /// Returns an iterator over the status of the satellites for which there
+    /// is any information.
+    ///
+    /// See [`SatelliteStatus::is_active`].
+    pub fn satellites(&self) -> impl Iterator<Item = &SatelliteStatus> {
+        self.satellites.iter().filter(|s| s.is_active())
+    }
+
+    /// Gives the number of satellites whose ephemeris, clock and status data
+    /// and timing parameters are both authenticated.
+    pub fn num_fully_authenticated(&self) -> usize {
+        self.satellites
+            .iter()
+            .filter(|s| s.ced_and_status.is_some() && s.timing_parameters.is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let gst = Gst::new(1176, 121080);
+        let mut status = SatelliteStatus {
+            svn: Svn::try_from(11).unwrap(),
+            ced_and_status: Some(LastAuthentication { gst, authbits: 80 }),
+            timing_parameters: None,
+            failed_tags: 2,
+            last_mack: Some(gst),
+            seconds_since_mack: Some(30),
+        };
+        assert!(status.is_active());
+        assert_eq!(
+            status.to_string(),
+            "E11 ADKD=0 TOW 121080 (80 bits) ADKD=4 NONE FAILED 2 MACK 30 s ago"
+        );
+        status.ced_and_status = None;
+        status.failed_tags = 0;
+        status.last_mack = None;
+        status.seconds_since_mack = None;
+        assert!(!status.is_active());
+        assert_eq!(
+            status.to_string(),
+            "E11 ADKD=0 NONE ADKD=4 NONE FAILED 0 MACK NONE"
+        );
+
+        let chain = ChainState::Transition {
+            cid: 1,
+            key_gst: gst,
+            next_cid: None,
+        };
+        assert_eq!(
+            chain.to_string(),
+            format!("CID 1 ending, key at {gst}, next CID unknown")
+        );
+    }
+}
@@ -14,6 +14,9 @@
 //! (300 seconds) ago, so an additional history of data for 10 subframes needs
 //! to be stored if Slow MAC is used. By limiting the history stored and not
 //! using Slow MAC, space can be saved.
+//! Additionally, the `slowmac` feature of this crate can be disabled to
+//! remove the Slow MAC processing code altogether. In this case, storages
+//! with a `MackDepth` of 2 are sufficient.
 //!
 //! A [`StaticStorage`] trait is used to define types that indicate the size of
 //! the storage. In general, these types should be zero-sized. Two types are provided:
@@ -21,8 +24,28 @@
 //! which is a much smaller size that can be used in memory constrained applications.
 //! Users can define additional storage sizes by implementing the [`StaticStorage`]
 //! trait on their own types.
+//!
+//! The storage size is normally chosen at compile time. The [`StorageKind`] enum
+//! can be used together with [`EitherOsnma`](crate::EitherOsnma) to choose
+//! between [`FullStorage`] and [`SmallStorage`] at runtime.
+//!
+//! The types that hold the storage, such as [`Osnma`](crate::Osnma), are
+//! generic over the [`Storage`] trait, which is implemented by all the types
+//! that implement [`StaticStorage`]. When the `alloc` feature is enabled,
+//! `DynStorage` is also available. This is a heap-allocated storage that
+//! grows with the number of satellites in view, which avoids the need to
+//! choose a storage size in applications where memory is not constrained.
 
-use generic_array::ArrayLength;
+#[cfg(feature = "alloc")]
+use crate::types::NUM_SVNS;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use generic_array::{ArrayLength, GenericArray};
+use typenum::Unsigned;
 
 /// Auxiliary trait for generic array sizes.
 ///
@@ -95,22 +118,273 @@ pub trait StaticStorage {
     ///
     /// This type should always equal the product of `NUM_SATS` and `MackDepth`.
     type MackDepthSats: StaticStorageTypenum;
+    /// Number of events in the event queue.
+    ///
+    /// This is the number of [`Event`](crate::events::Event)s that can be
+    /// queued before the oldest ones are discarded. It can be zero if the
+    /// events are not used.
+    type EventQueueDepth: StaticStorageTypenum;
+}
+
+/// Trait defining the storage used for OSNMA data.
+///
+/// This trait is implemented by all the types that implement [`StaticStorage`],
+/// whose storage sizes are fixed at compile time, and by `DynStorage`, whose
+/// number of satellites grows at runtime. The types that hold OSNMA data are
+/// generic over this trait. Users normally implement [`StaticStorage`] rather
+/// than this trait.
+pub trait Storage {
+    /// Number of navigation message subframes to store.
+    ///
+    /// See [`StaticStorage::NavMessageDepth`].
+    type NavMessageDepth: StaticStorageTypenum;
+    /// Number of MACK message subframes to store.
+    ///
+    /// See [`StaticStorage::MackDepth`].
+    type MackDepth: StaticStorageTypenum;
+    /// Number of events in the event queue.
+    ///
+    /// See [`StaticStorage::EventQueueDepth`].
+    type EventQueueDepth: StaticStorageTypenum;
+    /// Container for the navigation message data.
+    ///
+    /// This holds `NavMessageDepth` subframes of data for a number of
+    /// satellites.
+    type NavMessageSlots<T: Clone + Default + Debug + Eq + Hash>: SatelliteSlots<T>;
+    /// Container for the MACK messages.
+    ///
+    /// This holds `MackDepth` subframes of data for a number of satellites.
+    type MackSlots<T: Clone + Default + Debug + Eq + Hash>: SatelliteSlots<T>;
+
+    /// Checks the consistency rules of the storage.
+    ///
+    /// This is called by the constructors of the types that use the storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage does not follow its consistency rules.
+    fn assert_consistent() {}
+}
+
+/// Container of satellite slots.
+///
+/// This container holds a number of subframes of data. The data of each
+/// subframe is stored contiguously and contains one slot for each satellite
+/// that can be stored in parallel. The slots of all the subframes are accessed
+/// as a slice through `Deref`.
+pub trait SatelliteSlots<T>:
+    Deref<Target = [T]> + DerefMut + Default + Clone + Debug + Eq + Hash
+{
+    /// Gives the number of satellites that can be stored in each subframe.
+    fn num_sats(&self) -> usize;
+
+    /// Increases by one the number of satellites that can be stored in each
+    /// subframe.
+    ///
+    /// The new slots are empty. This returns `false` if the number of
+    /// satellites cannot be increased, either because the container has a
+    /// fixed size or because it already holds all the satellites of the
+    /// constellation.
+    fn grow(&mut self) -> bool;
+}
+
+/// Fixed-size container of satellite slots.
+///
+/// This is the [`SatelliteSlots`] used by the [`StaticStorage`] types. It holds
+/// `D` subframes in an array of `N` elements.
+pub struct StaticSlots<T, N: ArrayLength, D> {
+    slots: GenericArray<T, N>,
+    _depth: PhantomData<D>,
+}
+
+// The traits are implemented by hand rather than derived, because the derives
+// would require the typenums N and D to implement them.
+
+impl<T: Default, N: ArrayLength, D> Default for StaticSlots<T, N, D> {
+    fn default() -> Self {
+        StaticSlots {
+            slots: GenericArray::default(),
+            _depth: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, N: ArrayLength, D> Clone for StaticSlots<T, N, D> {
+    fn clone(&self) -> Self {
+        StaticSlots {
+            slots: self.slots.clone(),
+            _depth: PhantomData,
+        }
+    }
+}
+
+impl<T: Debug, N: ArrayLength, D> Debug for StaticSlots<T, N, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.slots.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, N: ArrayLength, D> PartialEq for StaticSlots<T, N, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.slots == other.slots
+    }
+}
+
+impl<T: Eq, N: ArrayLength, D> Eq for StaticSlots<T, N, D> {}
+
+impl<T: Hash, N: ArrayLength, D> Hash for StaticSlots<T, N, D> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.slots.hash(state);
+    }
+}
+
+impl<T, N: ArrayLength, D> Deref for StaticSlots<T, N, D> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.slots
+    }
+}
+
+impl<T, N: ArrayLength, D> DerefMut for StaticSlots<T, N, D> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.slots
+    }
+}
+
+impl<T, N, D> SatelliteSlots<T> for StaticSlots<T, N, D>
+where
+    T: Clone + Default + Debug + Eq + Hash,
+    N: StaticStorageTypenum,
+    D: StaticStorageTypenum,
+{
+    fn num_sats(&self) -> usize {
+        N::USIZE / D::USIZE
+    }
+
+    fn grow(&mut self) -> bool {
+        false
+    }
+}
+
+/// Heap-allocated container of satellite slots.
+///
+/// This is the [`SatelliteSlots`] used by [`DynStorage`]. It holds `D`
+/// subframes, and it starts empty and grows one satellite at a time up to
+/// [`NUM_SVNS`] satellites.
+///
+/// This is only available if the `alloc` feature is enabled.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DynSlots<T, D> {
+    slots: Vec<T>,
+    _depth: PhantomData<D>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D> Deref for DynSlots<T, D> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.slots
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D> DerefMut for DynSlots<T, D> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.slots
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, D> SatelliteSlots<T> for DynSlots<T, D>
+where
+    T: Clone + Default + Debug + Eq + Hash,
+    D: StaticStorageTypenum + Default + Clone + Hash,
+{
+    fn num_sats(&self) -> usize {
+        self.slots.len() / D::USIZE
+    }
+
+    fn grow(&mut self) -> bool {
+        let num_sats = self.num_sats();
+        if num_sats >= NUM_SVNS {
+            return false;
+        }
+        let mut slots = Vec::with_capacity((num_sats + 1) * D::USIZE);
+        for subframe in 0..D::USIZE {
+            slots.extend_from_slice(&self.slots[subframe * num_sats..(subframe + 1) * num_sats]);
+            slots.push(T::default());
+        }
+        self.slots = slots;
+        true
+    }
+}
+
+impl<S: StaticStorage> Storage for S {
+    type NavMessageDepth = <S as StaticStorage>::NavMessageDepth;
+    type MackDepth = <S as StaticStorage>::MackDepth;
+    type EventQueueDepth = <S as StaticStorage>::EventQueueDepth;
+    type NavMessageSlots<T: Clone + Default + Debug + Eq + Hash> =
+        StaticSlots<T, S::NavMessageDepthSats, <S as StaticStorage>::NavMessageDepth>;
+    type MackSlots<T: Clone + Default + Debug + Eq + Hash> =
+        StaticSlots<T, S::MackDepthSats, <S as StaticStorage>::MackDepth>;
+
+    fn assert_consistent() {
+        assert_consistent::<S>();
+    }
+}
+
+// Checks the consistency rules of a StaticStorage. This is called by the
+// constructors of the types that use the storage, so that an inconsistent
+// storage panics early instead of giving wrong results.
+pub(crate) fn assert_consistent<S: StaticStorage>() {
+    use crate::types::NUM_SVNS;
+
+    assert!(
+        (1..=NUM_SVNS).contains(&S::NUM_SATS),
+        "StaticStorage::NUM_SATS must be between 1 and NUM_SVNS"
+    );
+    assert_eq!(
+        S::NavMessageDepthSats::USIZE,
+        S::NUM_SATS * S::NavMessageDepth::USIZE,
+        "StaticStorage::NavMessageDepthSats must equal NUM_SATS * NavMessageDepth"
+    );
+    assert_eq!(
+        S::MackDepthSats::USIZE,
+        S::NUM_SATS * S::MackDepth::USIZE,
+        "StaticStorage::MackDepthSats must equal NUM_SATS * MackDepth"
+    );
+}
+
+/// Kind of storage.
+///
+/// This is used to choose the storage of an [`EitherOsnma`](crate::EitherOsnma)
+/// at runtime.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum StorageKind {
+    /// [`FullStorage`].
+    Full,
+    /// [`SmallStorage`].
+    Small,
 }
 
 /// Storage size for 36 satellites and Slow MAC.
 ///
 /// This is the largest storage size that it makes sense to have.
 /// It has a history of 13 subframes of navigation messages in order
-/// to process Slow MAC, and stores 36 satellites in parallel.
+/// to process Slow MAC, and stores all the [`NUM_SVNS`](crate::types::NUM_SVNS) satellites in parallel.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct FullStorage {}
 
 impl StaticStorage for FullStorage {
-    const NUM_SATS: usize = 36;
+    const NUM_SATS: usize = crate::types::NUM_SVNS;
     type NavMessageDepth = typenum::U13;
     type NavMessageDepthSats = typenum::U468;
     type MackDepth = typenum::U12;
     type MackDepthSats = typenum::U432;
+    type EventQueueDepth = typenum::U64;
 }
 
 /// Storage size for 12 satellites without Slow MAC.
@@ -128,4 +402,78 @@ impl StaticStorage for SmallStorage {
     type NavMessageDepthSats = typenum::U36;
     type MackDepth = typenum::U2;
     type MackDepthSats = typenum::U24;
+    type EventQueueDepth = typenum::U8;
+}
+
+/// Heap-allocated storage that grows with the number of satellites.
+///
+/// This storage has the same history depths as [`FullStorage`], so it can
+/// process Slow MAC, but instead of reserving space for all the satellites of
+/// the constellation, it starts empty and grows each time that the data of a
+/// new satellite needs to be stored. Its memory usage is proportional to the
+/// number of satellites in view, and it never shrinks.
+///
+/// This is only available if the `alloc` feature is enabled.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct DynStorage {}
+
+#[cfg(feature = "alloc")]
+impl Storage for DynStorage {
+    type NavMessageDepth = typenum::U13;
+    type MackDepth = typenum::U12;
+    type EventQueueDepth = typenum::U64;
+    type NavMessageSlots<T: Clone + Default + Debug + Eq + Hash> = DynSlots<T, typenum::U13>;
+    type MackSlots<T: Clone + Default + Debug + Eq + Hash> = DynSlots<T, typenum::U12>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consistent() {
+        assert_consistent::<FullStorage>();
+        assert_consistent::<SmallStorage>();
+    }
+
+    #[test]
+    #[should_panic]
+    fn inconsistent() {
+        struct WrongStorage {}
+        impl StaticStorage for WrongStorage {
+            const NUM_SATS: usize = 8;
+            type NavMessageDepth = typenum::U3;
+            type NavMessageDepthSats = typenum::U36;
+            type MackDepth = typenum::U2;
+            type MackDepthSats = typenum::U16;
+            type EventQueueDepth = typenum::U0;
+        }
+        assert_consistent::<WrongStorage>();
+    }
+
+    #[test]
+    fn static_slots() {
+        let slots = StaticSlots::<u8, typenum::U36, typenum::U3>::default();
+        assert_eq!(slots.num_sats(), 12);
+        assert_eq!(slots.len(), 36);
+        assert!(!slots.clone().grow());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn dyn_slots() {
+        let mut slots = DynSlots::<u8, typenum::U3>::default();
+        assert_eq!(slots.num_sats(), 0);
+        assert!(slots.is_empty());
+        assert!(slots.grow());
+        slots.copy_from_slice(&[1, 2, 3]);
+        assert!(slots.grow());
+        assert_eq!(slots.num_sats(), 2);
+        // The data of each subframe is kept in its slots
+        assert_eq!(&slots[..], &[1, 0, 2, 0, 3, 0]);
+        while slots.grow() {}
+        assert_eq!(slots.num_sats(), NUM_SVNS);
+        assert_eq!(slots.len(), 3 * NUM_SVNS);
+    }
 }
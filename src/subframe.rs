@@ -6,16 +6,148 @@
 //!
 //! The data for the 36 satellites in the Galileo constellation is collected in
 //! parallel.
+//!
+//! The [`CollectSubframe`] also keeps track of which of the 15 pages of each
+//! subframe have been received for each satellite, using a [`PageMap`], and
+//! accumulates page reception statistics in a [`ReceptionStats`]. These can
+//! be used to debug reception problems, such as an antenna placement that
+//! causes too many pages to be lost to ever obtain a complete DSM-KROOT.
+//!
+//! The HKROOT and MACK messages that have been completed in the current
+//! subframe can be obtained as a [`RawSubframe`] using
+//! [`CollectSubframe::completed`], for instance to archive the raw OSNMA data.
 
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::gstguard::{GstGuard, GstGuardPolicy, GstRejectionStats};
 use crate::types::{
     HkrootMessage, HkrootSection, MackMessage, MackSection, OsnmaDataMessage, HKROOT_MESSAGE_BYTES,
     HKROOT_SECTION_BYTES, MACK_MESSAGE_BYTES, MACK_SECTION_BYTES, NUM_SVNS,
 };
-use crate::{Gst, Svn, Tow, Wn};
+use crate::{FeedError, Gst, InavBand, Svn, Tow, Wn};
 
 const WORDS_PER_SUBFRAME: u8 = 15;
 const SECONDS_PER_SUBFRAME: Tow = 30;
 
+/// Raw OSNMA data of a subframe.
+///
+/// This contains the HKROOT and MACK messages transmitted by a satellite in a
+/// subframe, as assembled by [`CollectSubframe`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RawSubframe<'a> {
+    /// SVN of the satellite that transmitted the subframe.
+    pub svn: Svn,
+    /// GST at the start of the subframe.
+    pub gst: Gst,
+    /// HKROOT message (120 bits).
+    pub hkroot: &'a HkrootMessage,
+    /// MACK message (480 bits).
+    pub mack: &'a MackMessage,
+}
+
+/// Map of the pages received in a subframe.
+///
+/// This indicates which of the 15 pages (INAV words) in a subframe have been
+/// received with OSNMA data for a satellite. Pages are numbered from 0 to 14
+/// according to their position in the subframe.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct PageMap(u16);
+
+impl PageMap {
+    /// Constructs a map in which no pages have been received.
+    pub fn empty() -> PageMap {
+        PageMap(0)
+    }
+
+    /// Returns `true` if the page `page` has been received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `page` is not smaller than 15.
+    pub fn is_received(&self, page: u8) -> bool {
+        assert!(page < WORDS_PER_SUBFRAME);
+        self.0 & (1 << page) != 0
+    }
+
+    /// Returns the number of pages that have been received.
+    pub fn num_received(&self) -> u8 {
+        self.0.count_ones() as u8
+    }
+
+    /// Returns the number of pages that have not been received.
+    pub fn num_missing(&self) -> u8 {
+        WORDS_PER_SUBFRAME - self.num_received()
+    }
+
+    /// Returns `true` if all the pages in the subframe have been received.
+    pub fn is_complete(&self) -> bool {
+        self.num_missing() == 0
+    }
+
+    /// Returns `true` if no pages have been received.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the numbers of the pages that have not been
+    /// received.
+    pub fn missing(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..WORDS_PER_SUBFRAME).filter(|&page| !self.is_received(page))
+    }
+
+    fn set_received(&mut self, page: u8) {
+        self.0 |= 1 << page;
+    }
+}
+
+/// Page reception statistics.
+///
+/// These statistics are accumulated for each satellite over all the subframes
+/// in which at least one page with OSNMA data has been received from that
+/// satellite. Subframes in which no pages were received, for instance because
+/// the satellite was not visible, are not counted.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct ReceptionStats {
+    subframes: u32,
+    complete_subframes: u32,
+    pages_received: u32,
+}
+
+impl ReceptionStats {
+    /// Returns the number of subframes in which at least one page was received.
+    pub fn subframes(&self) -> u32 {
+        self.subframes
+    }
+
+    /// Returns the number of subframes in which all the pages were received.
+    pub fn complete_subframes(&self) -> u32 {
+        self.complete_subframes
+    }
+
+    /// Returns the number of pages received.
+    pub fn pages_received(&self) -> u32 {
+        self.pages_received
+    }
+
+    /// Returns the number of pages that were lost.
+    ///
+    /// This only counts the pages missing in subframes in which at least one
+    /// page was received.
+    pub fn pages_missing(&self) -> u32 {
+        self.subframes * u32::from(WORDS_PER_SUBFRAME) - self.pages_received
+    }
+
+    fn add(&mut self, map: PageMap) {
+        if map.is_empty() {
+            return;
+        }
+        self.subframes += 1;
+        if map.is_complete() {
+            self.complete_subframes += 1;
+        }
+        self.pages_received += u32::from(map.num_received());
+    }
+}
+
 /// Subframe collector.
 ///
 /// This struct collects HKROOT and MACK sections from the OSNMA data in INAV
@@ -26,6 +158,11 @@ pub struct CollectSubframe {
     hkroot: [HkrootMessage; NUM_SVNS],
     mack: [MackMessage; NUM_SVNS],
     num_valid: [u8; NUM_SVNS],
+    pages: [PageMap; NUM_SVNS],
+    last_pages: [PageMap; NUM_SVNS],
+    last_subframe: Option<Gst>,
+    stats: [ReceptionStats; NUM_SVNS],
+    gst_guard: Option<GstGuard>,
     wn: Wn,
     subframe: Tow,
 }
@@ -37,6 +174,11 @@ impl CollectSubframe {
             hkroot: [[0; HKROOT_MESSAGE_BYTES]; NUM_SVNS],
             mack: [[0; MACK_MESSAGE_BYTES]; NUM_SVNS],
             num_valid: [0; NUM_SVNS],
+            pages: [PageMap::empty(); NUM_SVNS],
+            last_pages: [PageMap::empty(); NUM_SVNS],
+            last_subframe: None,
+            stats: [ReceptionStats::default(); NUM_SVNS],
+            gst_guard: None,
             wn: 0,
             subframe: 0,
         }
@@ -56,34 +198,140 @@ impl CollectSubframe {
     /// the old subframe is discarded, and collection of data for a new subframe
     /// begins. This assumes that the OSNMA data for different satellites is fed
     /// in chronological order.
+    ///
+    /// The OSNMA data message is assumed to have been received in E1-B. Use
+    /// [`CollectSubframe::feed_band`] for data received in E5b-I.
     pub fn feed(
         &mut self,
         osnma_data: &OsnmaDataMessage,
         svn: Svn,
         gst: Gst,
     ) -> Option<(&HkrootMessage, &MackMessage, Gst)> {
+        self.feed_band(osnma_data, svn, gst, InavBand::E1B)
+    }
+
+    /// Feed a new OSNMA data message received in a particular band into the
+    /// subframe collector.
+    ///
+    /// This is the same as [`CollectSubframe::feed`], but the `band` parameter
+    /// indicates the band in which the INAV page was received. The page timing
+    /// of E5b-I is offset by 1 second with respect to E1-B, so the first E5b-I
+    /// page of a subframe starts in the previous subframe. The subframe and
+    /// the position of the page within the subframe are computed taking this
+    /// into account. See [`InavBand::e1b_page_gst`].
+    pub fn feed_band(
+        &mut self,
+        osnma_data: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+    ) -> Option<(&HkrootMessage, &MackMessage, Gst)> {
+        self.feed_with_diagnostics(osnma_data, svn, gst, band, |_| {})
+            .ok()
+            .flatten()
+    }
+
+    /// Sets the policy used to check the GST of the OSNMA data.
+    ///
+    /// If a policy is given, the OSNMA data whose GST regresses or jumps
+    /// forward unrealistically is rejected, as described in the
+    /// [gstguard](crate::gstguard) module. If `None` is given, the checks are
+    /// disabled. The checks are disabled by default.
+    ///
+    /// Setting a policy resets the statistics of the rejected data.
+    pub fn set_gst_guard(&mut self, policy: Option<GstGuardPolicy>) {
+        self.gst_guard = policy.map(GstGuard::new);
+    }
+
+    /// Returns the statistics of the OSNMA data rejected due to its GST.
+    ///
+    /// See [`CollectSubframe::set_gst_guard`].
+    pub fn gst_rejections(&self) -> GstRejectionStats {
+        self.gst_guard
+            .as_ref()
+            .map(|guard| *guard.stats())
+            .unwrap_or_default()
+    }
+
+    // Same as feed_band, but a DiagnosticKind::IncompleteSubframe is reported
+    // for each satellite whose subframe was not received completely, and a
+    // DiagnosticKind::GstRejected is reported if the GST is rejected. These
+    // problems are also returned as an error. Only the first incomplete
+    // subframe is returned, and the data message is stored nevertheless.
+    pub(crate) fn feed_with_diagnostics(
+        &mut self,
+        osnma_data: &OsnmaDataMessage,
+        svn: Svn,
+        gst: Gst,
+        band: InavBand,
+        mut report: impl FnMut(Diagnostic),
+    ) -> Result<Option<(&HkrootMessage, &MackMessage, Gst)>, FeedError> {
         let hkroot_section: HkrootSection = osnma_data[..HKROOT_SECTION_BYTES].try_into().unwrap();
         let mack_section: MackSection = osnma_data[HKROOT_SECTION_BYTES..].try_into().unwrap();
-        let word_num = (gst.tow() / 2) % Tow::from(WORDS_PER_SUBFRAME);
+        let page_gst = band.e1b_page_gst(gst);
+        if let Some(guard) = &mut self.gst_guard {
+            if let Err(rejection) = guard.check(page_gst) {
+                let diagnostic = Diagnostic::new(
+                    DiagnosticKind::GstRejected { rejection },
+                    Some(svn),
+                    page_gst.gst_subframe(),
+                );
+                log::debug!("{}", diagnostic);
+                report(diagnostic);
+                return Err(FeedError::GstRejected(rejection));
+            }
+        }
+        let word_num = (page_gst.tow() / 2) % Tow::from(WORDS_PER_SUBFRAME);
         log::trace!(
-            "feeding hkroot = {:02x?}, mack = {:02x?} for {} (GST = {:?}, word number = {})",
+            "feeding hkroot = {:02x?}, mack = {:02x?} for {} (GST = {:?}, band = {}, \
+             word number = {})",
             hkroot_section,
             mack_section,
             svn,
             gst,
+            band,
             word_num
         );
-        let subframe = gst.tow() / SECONDS_PER_SUBFRAME;
-        if gst.wn() != self.wn || subframe != self.subframe {
+        let subframe = page_gst.tow() / SECONDS_PER_SUBFRAME;
+        let mut incomplete = None;
+        if page_gst.wn() != self.wn || subframe != self.subframe {
             log::debug!("valid sections per SVN: {:?}", &self.num_valid);
-            log::info!("starting collection of new subframe (GST {:?})", gst);
-            self.wn = gst.wn();
+            log::info!("starting collection of new subframe (GST {:?})", page_gst);
+            if self.pages.iter().any(|p| !p.is_empty()) {
+                self.last_subframe = Some(self.subframe_gst());
+            }
+            for (svn, pages) in Svn::iter().zip(self.pages.iter()) {
+                if !pages.is_empty() && !pages.is_complete() {
+                    let diagnostic = Diagnostic::new(
+                        DiagnosticKind::IncompleteSubframe {
+                            received_pages: pages.num_received(),
+                        },
+                        Some(svn),
+                        self.subframe_gst(),
+                    );
+                    log::debug!("{}", diagnostic);
+                    report(diagnostic);
+                    incomplete.get_or_insert(FeedError::IncompleteSubframe {
+                        svn,
+                        gst: self.subframe_gst(),
+                        received_pages: pages.num_received(),
+                    });
+                }
+            }
+            for s in 0..NUM_SVNS {
+                self.stats[s].add(self.pages[s]);
+            }
+            self.last_pages = self.pages;
+            self.wn = page_gst.wn();
             self.subframe = subframe;
             for s in 0..NUM_SVNS {
                 self.num_valid[s] = 0;
+                self.pages[s] = PageMap::empty();
             }
         }
-        let svn_idx = usize::from(svn) - 1;
+        let svn_idx = svn.index();
+        // word_num < WORDS_PER_SUBFRAME, so the cast does not truncate
+        self.pages[svn_idx].set_received(word_num as u8);
         if word_num != u32::from(self.num_valid[svn_idx]) {
             log::trace!(
                 "there are missing words for {} (GST {:?}), \
@@ -93,7 +341,7 @@ impl CollectSubframe {
                 word_num,
                 self.num_valid[svn_idx]
             );
-            return None;
+            return incomplete.map_or(Ok(None), Err);
         }
         let valid = usize::from(self.num_valid[svn_idx]);
         let hkroot_idx = valid * HKROOT_SECTION_BYTES;
@@ -111,15 +359,68 @@ impl CollectSubframe {
                 self.hkroot[svn_idx],
                 self.mack[svn_idx],
             );
-            Some((
+            Ok(Some((
                 &self.hkroot[svn_idx],
                 &self.mack[svn_idx],
-                Gst::new(self.wn, self.subframe * SECONDS_PER_SUBFRAME),
-            ))
+                self.subframe_gst(),
+            )))
+        } else {
+            incomplete.map_or(Ok(None), Err)
+        }
+    }
+
+    fn subframe_gst(&self) -> Gst {
+        Gst::new(self.wn, self.subframe * SECONDS_PER_SUBFRAME)
+    }
+
+    /// Returns the pages received from a satellite in the current subframe.
+    ///
+    /// The current subframe is the subframe of the most recent OSNMA data
+    /// message that has been fed.
+    pub fn current_pages(&self, svn: Svn) -> PageMap {
+        self.pages[svn.index()]
+    }
+
+    /// Returns the pages received from a satellite in the previous subframe.
+    ///
+    /// The GST at the start of the previous subframe is returned together with
+    /// the page map. If no OSNMA data messages were fed in the previous
+    /// subframe, this returns `None`.
+    ///
+    /// The previous subframe is the last subframe for which OSNMA data was fed
+    /// before the current subframe. This might not be the subframe immediately
+    /// preceding the current subframe if there are gaps in the data.
+    pub fn last_pages(&self, svn: Svn) -> Option<(PageMap, Gst)> {
+        self.last_subframe
+            .map(|gst| (self.last_pages[svn.index()], gst))
+    }
+
+    /// Returns the raw OSNMA data completed by a satellite in the current
+    /// subframe.
+    ///
+    /// If the HKROOT and MACK messages of the satellite have been completed in
+    /// the current subframe, they are returned. Otherwise, this returns `None`.
+    pub fn completed(&self, svn: Svn) -> Option<RawSubframe<'_>> {
+        let svn_idx = svn.index();
+        if self.num_valid[svn_idx] == WORDS_PER_SUBFRAME {
+            Some(RawSubframe {
+                svn,
+                gst: self.subframe_gst(),
+                hkroot: &self.hkroot[svn_idx],
+                mack: &self.mack[svn_idx],
+            })
         } else {
             None
         }
     }
+
+    /// Returns the page reception statistics for a satellite.
+    ///
+    /// The statistics only include subframes that have already finished (the
+    /// current subframe is not included).
+    pub fn reception_stats(&self, svn: Svn) -> ReceptionStats {
+        self.stats[svn.index()]
+    }
 }
 
 impl Default for CollectSubframe {
@@ -183,4 +484,178 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn week_rollover() {
+        let svn = Svn::try_from(1).unwrap();
+        let mut collector = CollectSubframe::new();
+        let data = [0x55; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+        // Last subframe of week 1234
+        let gst0 = Gst::new(1234, 7 * 24 * 3600 - SECONDS_PER_SUBFRAME);
+        for j in 0..WORDS_PER_SUBFRAME {
+            let gst = gst0.add_seconds(2 * i32::from(j));
+            let ret = collector.feed(&data, svn, gst);
+            assert_eq!(ret.is_some(), j == WORDS_PER_SUBFRAME - 1);
+            if let Some((_, _, gst_subframe)) = ret {
+                assert_eq!(gst_subframe, gst0);
+            }
+        }
+        // First subframe of week 1235
+        let gst1 = gst0.add_subframes(1);
+        assert_eq!(gst1, Gst::new(1235, 0));
+        for j in 0..WORDS_PER_SUBFRAME {
+            let gst = gst1.add_seconds(2 * i32::from(j));
+            let ret = collector.feed(&data, svn, gst);
+            assert_eq!(ret.is_some(), j == WORDS_PER_SUBFRAME - 1);
+            if let Some((_, _, gst_subframe)) = ret {
+                assert_eq!(gst_subframe, gst1);
+            }
+        }
+        assert_eq!(collector.last_pages(svn).unwrap().1, gst0);
+    }
+
+    #[test]
+    fn e5b_timing() {
+        let svn = Svn::try_from(7).unwrap();
+        let mut collector = CollectSubframe::new();
+        let gst0 = Gst::new(1234, 300);
+        // The first E5b-I page of the subframe starts 1 second before the
+        // subframe, and the last page starts 3 seconds before its end.
+        let e5b_gst = |j: u8| gst0.add_seconds(2 * i32::from(j) - 1);
+        for j in 0..WORDS_PER_SUBFRAME {
+            let data = [j; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+            let ret = collector.feed_band(&data, svn, e5b_gst(j), InavBand::E5B);
+            assert_eq!(ret.is_some(), j == WORDS_PER_SUBFRAME - 1);
+            if let Some((hkroot, _, gst_subframe)) = ret {
+                assert_eq!(gst_subframe, gst0);
+                assert_eq!(hkroot[0], 0);
+                assert_eq!(hkroot[14], 14);
+            }
+        }
+        assert!(collector.current_pages(svn).is_complete());
+        // E1-B pages of the same subframe are collected together with the
+        // E5b-I pages
+        let other_svn = Svn::try_from(8).unwrap();
+        let data = [0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+        collector.feed(&data, other_svn, gst0.add_seconds(28));
+        assert!(collector.current_pages(svn).is_complete());
+        assert!(collector.current_pages(other_svn).is_received(14));
+        // The first E5b-I page of the next subframe starts a new subframe
+        collector.feed_band(&data, svn, e5b_gst(15), InavBand::E5B);
+        assert_eq!(
+            collector
+                .last_pages(svn)
+                .map(|(pages, gst)| (pages.is_complete(), gst)),
+            Some((true, gst0))
+        );
+        assert!(collector.current_pages(svn).is_received(0));
+    }
+
+    #[test]
+    fn completed() {
+        let svn = Svn::try_from(3).unwrap();
+        let mut collector = CollectSubframe::new();
+        let gst0 = Gst::new(1234, 300);
+        for j in 0..WORDS_PER_SUBFRAME {
+            assert!(collector.completed(svn).is_none());
+            let data = [j; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+            collector.feed(&data, svn, gst0.add_seconds(2 * i32::from(j)));
+        }
+        let raw = collector.completed(svn).unwrap();
+        assert_eq!(raw.svn, svn);
+        assert_eq!(raw.gst, gst0);
+        assert_eq!(raw.hkroot[14], 14);
+        assert_eq!(raw.mack[..4], [0; 4]);
+        assert!(collector.completed(Svn::try_from(4).unwrap()).is_none());
+        // The data is discarded when the next subframe starts
+        let data = [0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+        collector.feed(&data, svn, gst0.add_subframes(1));
+        assert!(collector.completed(svn).is_none());
+    }
+
+    #[test]
+    fn incomplete_subframe_diagnostic() {
+        let mut received = None;
+        let mut report = |diagnostic: Diagnostic| {
+            if let DiagnosticKind::IncompleteSubframe { received_pages } = diagnostic.kind() {
+                received = Some(received_pages);
+            }
+        };
+
+        let svn = Svn::try_from(3).unwrap();
+        let mut collector = CollectSubframe::new();
+        let gst0 = Gst::new(1234, 300);
+        let data = [0; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+        let band = InavBand::E1B;
+        for j in 0..4 {
+            let gst = gst0.add_seconds(2 * j);
+            assert_eq!(
+                collector.feed_with_diagnostics(&data, svn, gst, band, &mut report),
+                Ok(None)
+            );
+        }
+        let gst = gst0.add_subframes(1);
+        let result = collector.feed_with_diagnostics(&data, svn, gst, band, &mut report);
+        assert_eq!(received, Some(4));
+        assert_eq!(
+            result,
+            Err(FeedError::IncompleteSubframe {
+                svn,
+                gst: gst0,
+                received_pages: 4
+            })
+        );
+    }
+
+    #[test]
+    fn page_map() {
+        let svn = Svn::try_from(5).unwrap();
+        let other_svn = Svn::try_from(6).unwrap();
+        let wn = 1234;
+        let tow0 = 123 * SECONDS_PER_SUBFRAME;
+        let mut collector = CollectSubframe::new();
+        let data = [0xaa; HKROOT_SECTION_BYTES + MACK_SECTION_BYTES];
+
+        // First subframe: pages 3 and 7 are lost
+        for page in (0..WORDS_PER_SUBFRAME).filter(|&p| p != 3 && p != 7) {
+            let gst = Gst::new(wn, tow0 + 2 * Tow::from(page));
+            assert!(collector.feed(&data, svn, gst).is_none());
+        }
+        let pages = collector.current_pages(svn);
+        assert_eq!(pages.num_received(), 13);
+        assert_eq!(pages.num_missing(), 2);
+        assert!(!pages.is_complete());
+        assert!(pages.missing().eq([3, 7]));
+        assert!(collector.current_pages(other_svn).is_empty());
+        assert_eq!(collector.last_pages(svn), None);
+        assert_eq!(collector.reception_stats(svn), ReceptionStats::default());
+
+        // Second subframe: all pages received
+        let tow1 = tow0 + SECONDS_PER_SUBFRAME;
+        for page in 0..WORDS_PER_SUBFRAME {
+            let gst = Gst::new(wn, tow1 + 2 * Tow::from(page));
+            let ret = collector.feed(&data, svn, gst);
+            assert_eq!(ret.is_some(), page == WORDS_PER_SUBFRAME - 1);
+        }
+        assert!(collector.current_pages(svn).is_complete());
+        assert_eq!(collector.last_pages(svn), Some((pages, Gst::new(wn, tow0))));
+        let stats = collector.reception_stats(svn);
+        assert_eq!(stats.subframes(), 1);
+        assert_eq!(stats.complete_subframes(), 0);
+        assert_eq!(stats.pages_received(), 13);
+        assert_eq!(stats.pages_missing(), 2);
+
+        // Third subframe: a single page for another satellite
+        let tow2 = tow1 + SECONDS_PER_SUBFRAME;
+        assert!(collector
+            .feed(&data, other_svn, Gst::new(wn, tow2 + 4))
+            .is_none());
+        let stats = collector.reception_stats(svn);
+        assert_eq!(stats.subframes(), 2);
+        assert_eq!(stats.complete_subframes(), 1);
+        assert_eq!(stats.pages_received(), 28);
+        assert_eq!(stats.pages_missing(), 2);
+        assert_eq!(collector.reception_stats(other_svn).subframes(), 0);
+        assert!(collector.current_pages(other_svn).is_received(2));
+    }
 }
@@ -5,7 +5,7 @@ use core::num::NonZeroU8;
 /// Galileo SVN.
 ///
 /// The SVN is the Galileo satellite number, which is a number between 1 and
-/// 36. This struct stores the SVN internally as a `NonZeroU8`, and guarantees
+/// [`NUM_SVNS`] (36). This struct stores the SVN internally as a `NonZeroU8`, and guarantees
 /// at construction that the value is always in range.
 ///
 /// SVNs are typically written as Exx (E24, for instance). The `Display`
@@ -36,6 +36,25 @@ impl Svn {
             .into_iter()
             .map(|x| Svn::try_from(x).unwrap())
     }
+
+    /// Gives the index of the SVN.
+    ///
+    /// The index is a number between 0 and `NUM_SVNS - 1`, which can be used
+    /// to index arrays with one element per satellite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::Svn;
+    /// use galileo_osnma::types::NUM_SVNS;
+    ///
+    /// let mut tracked = [false; NUM_SVNS];
+    /// tracked[Svn::try_from(24).unwrap().index()] = true;
+    /// assert!(tracked[23]);
+    /// ```
+    pub fn index(&self) -> usize {
+        usize::from(self.0.get()) - 1
+    }
 }
 
 macro_rules! impl_conv {
@@ -94,7 +113,7 @@ pub struct SvnError;
 
 impl fmt::Display for SvnError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "SVN out of range 1-36".fmt(f)
+        write!(f, "SVN out of range 1-{NUM_SVNS}")
     }
 }
 
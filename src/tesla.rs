@@ -7,10 +7,13 @@
 //! messages and authenticate the navigation data using the tags in a MACK message.
 
 use crate::bitfields::{
-    self, ChainAndPubkeyStatus, DsmKroot, EcdsaFunction, Mack, NmaStatus, Prnd, TagAndInfo,
+    self, ChainAndPubkeyStatus, DsmKroot, EcdsaFunction, IcdVersion, Mack, NmaStatus, Prnd,
+    TagAndInfo,
 };
-use crate::maclt::{get_flx_indices, get_maclt_entry, AuthObject, MacLTError, MacLTSlot};
-use crate::types::{BitSlice, VerifyingKey, NUM_SVNS};
+use crate::maclt::{
+    get_flx_indices, get_maclt_entry, get_maclt_nt, AuthObject, MacLTError, MacLTSlot,
+};
+use crate::types::{BitSlice, MackLayout, NavDataBits, VerifyingKey, MAX_KEY_SIZE_BYTES, NUM_SVNS};
 use crate::validation::{NotValidated, Validated};
 use crate::{Gst, PublicKey, Svn, Tow};
 use aes::Aes128;
@@ -25,7 +28,21 @@ use sha2::{
 };
 use sha3::Sha3_256;
 
-const MAX_KEY_BYTES: usize = 32;
+/// Maximum number of one-way function derivations used to validate a TESLA
+/// key.
+///
+/// A TESLA key can only be validated with another key of the same chain whose
+/// GST is at most this number of subframes earlier. This is chosen to be
+/// slightly greater than 1 day, and corresponds to a maximum GST difference of
+/// 25 hours. See [`ValidationError::TooManyDerivations`].
+pub const MAX_KEY_DERIVATIONS: i32 = 3000;
+
+/// Maximum size in bytes of the message hashed by the TESLA one-way function.
+///
+/// The message is formed by the TESLA key, the GST of the previous subframe (4
+/// bytes) and the chain parameter alpha (6 bytes). See
+/// [`Key::one_way_function_message`].
+pub const ONE_WAY_FUNCTION_MESSAGE_BYTES: usize = MAX_KEY_SIZE_BYTES + 4 + 6;
 
 /// TESLA chain parameters.
 ///
@@ -55,6 +72,16 @@ pub enum HashFunction {
     Sha3_256,
 }
 
+impl fmt::Display for HashFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashFunction::Sha256 => "SHA-256",
+            HashFunction::Sha3_256 => "SHA3-256",
+        }
+        .fmt(f)
+    }
+}
+
 /// MAC function.
 ///
 /// This gives the MAC function used by the TESLA chain. Its values correspond
@@ -68,6 +95,16 @@ pub enum MacFunction {
     CmacAes,
 }
 
+impl fmt::Display for MacFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacFunction::HmacSha256 => "HMAC-SHA-256",
+            MacFunction::CmacAes => "CMAC-AES",
+        }
+        .fmt(f)
+    }
+}
+
 impl Chain {
     /// Extract the chain parameters from a DSM-KROOT message.
     ///
@@ -92,17 +129,56 @@ impl Chain {
             None => return Err(ChainError::ReservedField),
         };
         let tag_size_bits = dsm_kroot.tag_size().ok_or(ChainError::ReservedField)?;
+        let maclt = dsm_kroot.mac_lookup_table();
+        Self::check_mack_layout(key_size_bytes * 8, tag_size_bits, maclt)?;
         Ok(Chain {
             id: dsm_kroot.kroot_chain_id(),
             hash_function,
             mac_function,
             key_size_bytes,
             tag_size_bits,
-            maclt: dsm_kroot.mac_lookup_table(),
+            maclt,
             alpha: dsm_kroot.alpha(),
         })
     }
 
+    // Number of tags (including tag0) that fit in a MACK message with the
+    // given key and tag sizes. Any remaining bits are padding.
+    fn mack_num_tags(key_size_bits: usize, tag_size_bits: usize) -> usize {
+        MackLayout::new(key_size_bits, tag_size_bits).num_tags()
+    }
+
+    // Checks that the MAC look-up table ID is known and that the MACK layout
+    // given by the key and tag sizes is consistent with the number of tags
+    // given in the MAC look-up table.
+    fn check_mack_layout(
+        key_size_bits: usize,
+        tag_size_bits: usize,
+        maclt: u8,
+    ) -> Result<(), ChainError> {
+        let num_tags = Self::mack_num_tags(key_size_bits, tag_size_bits);
+        if num_tags == 0 {
+            return Err(ChainError::WrongMackLayout);
+        }
+        match get_maclt_nt(maclt) {
+            Ok(nt) if nt != num_tags => {
+                log::error!(
+                    "key size {key_size_bits} bits and tag size {tag_size_bits} bits \
+                     give {num_tags} tags, but MAC look-up table {maclt} has {nt} tags"
+                );
+                Err(ChainError::WrongMackLayout)
+            }
+            Ok(_) => Ok(()),
+            Err(_) => {
+                log::error!(
+                    "MAC look-up table {maclt} is not supported; \
+                     an update of this library is probably needed"
+                );
+                Err(ChainError::UnsupportedMaclt { maclt })
+            }
+        }
+    }
+
     /// Gives the chain ID of the TESLA chain.
     pub fn chain_id(&self) -> u8 {
         self.id
@@ -144,6 +220,16 @@ impl Chain {
         self.maclt
     }
 
+    /// Gives the number of tags in each MACK message.
+    ///
+    /// The number of tags includes tag0, and it is computed according to the
+    /// key and tag sizes. For chains constructed with
+    /// [`Chain::from_dsm_kroot`], this number is guaranteed to match the
+    /// number of tags in the MAC look-up table entry.
+    pub fn num_tags(&self) -> usize {
+        Self::mack_num_tags(self.key_size_bits(), self.tag_size_bits)
+    }
+
     /// Gives the value of the chain random parameter alpha.
     pub fn alpha(&self) -> u64 {
         self.alpha
@@ -182,7 +268,7 @@ impl Chain {
                 if tag.adkd() != adkd {
                     Err(AdkdCheckError::WrongAdkd)
                 } else if let Prnd::GalileoSvid(prnd) = tag.prnd() {
-                    if object == AuthObject::SelfAuth && prnd != prna.into() {
+                    if object == AuthObject::SelfAuth && prnd != u8::from(prna) {
                         Err(AdkdCheckError::WrongPrnd)
                     } else if (1..=NUM_SVNS).contains(&prnd.into()) {
                         Ok(())
@@ -202,6 +288,24 @@ impl Chain {
     }
 }
 
+/// Formats the chain parameters using the names of the DSM-KROOT fields, as in
+/// `"CID=1 HF=SHA-256 MF=HMAC-SHA-256 KS=128 bits TS=40 bits MACLT=33 alpha=0x25d3964da3a2"`.
+impl fmt::Display for Chain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CID={} HF={} MF={} KS={} bits TS={} bits MACLT={} alpha={:#014x}",
+            self.id,
+            self.hash_function,
+            self.mac_function,
+            self.key_size_bits(),
+            self.tag_size_bits,
+            self.maclt,
+            self.alpha
+        )
+    }
+}
+
 /// Errors produced during the extraction of the chain parameters.
 ///
 /// This gives the errors that can happen during the extraction of the TESLA
@@ -211,12 +315,31 @@ pub enum ChainError {
     /// One of the fields holding information about the TESLA chain has a
     /// reserved value.
     ReservedField,
+    /// The number of tags in a MACK message given by the key size and tag size
+    /// does not match the number of tags in the MAC look-up table entry.
+    WrongMackLayout,
+    /// The MAC look-up table ID is not present in the MAC look-up table known
+    /// by this library.
+    ///
+    /// This happens when a new MAC look-up table entry is introduced in an
+    /// update of the OSNMA SIS ICD. An update of this library is needed to
+    /// process the chain.
+    UnsupportedMaclt {
+        /// MAC look-up table ID.
+        maclt: u8,
+    },
 }
 
 impl fmt::Display for ChainError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ChainError::ReservedField => "reserved value present in some field".fmt(f),
+            ChainError::WrongMackLayout => {
+                "key and tag sizes do not match MAC look-up table".fmt(f)
+            }
+            ChainError::UnsupportedMaclt { maclt } => {
+                write!(f, "unsupported MAC look-up table {maclt}")
+            }
         }
     }
 }
@@ -417,6 +540,22 @@ impl<V> NmaHeader<V> {
         }
     }
 
+    /// Gives the value of the CPKS (chain and public key status) field
+    /// according to some version of the OSNMA SIS ICD.
+    ///
+    /// This is the same as [`NmaHeader::chain_and_pubkey_status`], except that
+    /// the New Merkle Tree and Alert Message values are reserved under
+    /// [`IcdVersion::V1_0`].
+    pub fn chain_and_pubkey_status_icd(&self, icd: IcdVersion) -> ChainAndPubkeyStatus {
+        match (self.chain_and_pubkey_status(), icd) {
+            (
+                ChainAndPubkeyStatus::NewMerkleTree | ChainAndPubkeyStatus::AlertMessage,
+                IcdVersion::V1_0,
+            ) => ChainAndPubkeyStatus::Reserved,
+            (cpks, _) => cpks,
+        }
+    }
+
     fn force_valid(self) -> NmaHeader<Validated> {
         NmaHeader {
             data: self.data,
@@ -425,6 +564,20 @@ impl<V> NmaHeader<V> {
     }
 }
 
+/// Formats the NMA header fields as in
+/// `"NMAS=2 (operational), CID=1, CPKS=1 (nominal)"`.
+impl<V> fmt::Display for NmaHeader<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, CID={}, {}",
+            self.nma_status(),
+            self.chain_id(),
+            self.chain_and_pubkey_status()
+        )
+    }
+}
+
 impl<V> fmt::Debug for NmaHeader<V> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("NmaHeader")
@@ -448,7 +601,7 @@ impl<V> fmt::Debug for NmaHeader<V> {
 /// parameters.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Key<V> {
-    data: [u8; MAX_KEY_BYTES],
+    data: [u8; MAX_KEY_SIZE_BYTES],
     chain: Chain,
     gst_subframe: Gst,
     _validated: V,
@@ -471,9 +624,15 @@ pub enum ValidationError {
     /// The distance between the GSTs of both keys is large enough that the
     /// number of derivations to get from one to the other exceeds a certain threshold.
     ///
-    /// The threshold is currently set to 3000 derivations, which corresponds to
-    /// a maximum GST difference of 25 hours.
+    /// The threshold is given by [`MAX_KEY_DERIVATIONS`].
     TooManyDerivations,
+    /// The key to validate is obviously invalid.
+    ///
+    /// This happens if the key is all zeros, if it is equal to the key used
+    /// for the validation, or if the padding of the key data is not zero.
+    /// These checks are done before computing any derivations of the one-way
+    /// function, so that trivially malformed keys do not consume CPU.
+    MalformedKey,
 }
 
 impl fmt::Display for ValidationError {
@@ -483,6 +642,7 @@ impl fmt::Display for ValidationError {
             ValidationError::DifferentChain => "keys belong to different chains".fmt(f),
             ValidationError::DoesNotFollow => "key is older than validating key".fmt(f),
             ValidationError::TooManyDerivations => "time difference between keys too large".fmt(f),
+            ValidationError::MalformedKey => "key is malformed".fmt(f),
         }
     }
 }
@@ -505,9 +665,15 @@ impl<V> Key<V> {
         &self.chain
     }
 
+    // Gives the key data. This is used to generate the OSNMA broadcast.
+    #[cfg(feature = "signer")]
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data[..self.chain.key_size_bytes]
+    }
+
     fn store_gst(buffer: &mut [u8], gst: Gst) {
         let bits = BitSlice::from_slice_mut(buffer);
-        bits[0..12].store_be(gst.wn());
+        bits[0..12].store_be(gst.normalize().wn());
         bits[12..32].store_be(gst.tow());
     }
 }
@@ -524,7 +690,7 @@ impl Key<NotValidated> {
     /// Panics if `slice.len()` does not match the key size indicated in `chain`.
     pub fn from_bitslice(slice: &BitSlice, gst: Gst, chain: &Chain) -> Key<NotValidated> {
         Self::check_gst(gst);
-        let mut data = [0; MAX_KEY_BYTES];
+        let mut data = [0; MAX_KEY_SIZE_BYTES];
         BitSlice::from_slice_mut(&mut data)[..chain.key_size_bytes * 8].copy_from_bitslice(slice);
         Key {
             data,
@@ -545,7 +711,7 @@ impl Key<NotValidated> {
     /// Panics if `slice.len()` does not match the key size indicated in `chain`.
     pub fn from_slice(slice: &[u8], gst: Gst, chain: &Chain) -> Key<NotValidated> {
         Self::check_gst(gst);
-        let mut data = [0; MAX_KEY_BYTES];
+        let mut data = [0; MAX_KEY_SIZE_BYTES];
         data[..chain.key_size_bytes].copy_from_slice(slice);
         Key {
             data,
@@ -557,7 +723,7 @@ impl Key<NotValidated> {
 }
 
 impl<V> Key<V> {
-    fn force_valid(self) -> Key<Validated> {
+    pub(crate) fn force_valid(self) -> Key<Validated> {
         Key {
             data: self.data,
             chain: self.chain,
@@ -593,12 +759,24 @@ impl Key<Validated> {
         dsm_kroot: DsmKroot,
         pubkey: &PublicKey<Validated>,
     ) -> Result<(Key<Validated>, NmaHeader<Validated>), KrootValidationError> {
-        let chain =
-            Chain::from_dsm_kroot(dsm_kroot).map_err(KrootValidationError::WrongDsmKrootChain)?;
+        let chain = match Chain::from_dsm_kroot(dsm_kroot) {
+            // An unsupported MAC look-up table is only reported after the
+            // signature has been checked, since it is a sign that this
+            // library needs to be updated only if the DSM-KROOT is authentic.
+            Err(e @ ChainError::UnsupportedMaclt { .. }) => Err(e),
+            chain => Ok(chain.map_err(KrootValidationError::WrongDsmKrootChain)?),
+        };
+        // If the size of the DSM-KROOT does not match any of the ECDSA
+        // functions, the size of the padding is incorrect
+        let (Ok(kroot), Ok(ecdsa_function)) =
+            (dsm_kroot.try_kroot(), dsm_kroot.try_ecdsa_function())
+        else {
+            return Err(KrootValidationError::WrongDsmKrootPadding);
+        };
         if !dsm_kroot.check_padding(nma_header) {
             return Err(KrootValidationError::WrongDsmKrootPadding);
         }
-        match (pubkey.verifying_key(), dsm_kroot.ecdsa_function()) {
+        match (pubkey.verifying_key(), ecdsa_function) {
             (VerifyingKey::P256(pubkey), EcdsaFunction::P256Sha256) => {
                 if !dsm_kroot.check_signature_p256(nma_header, pubkey) {
                     return Err(KrootValidationError::WrongEcdsa);
@@ -612,13 +790,14 @@ impl Key<Validated> {
             }
             _ => return Err(KrootValidationError::WrongEcdsaKeyType),
         }
+        let chain = chain.map_err(KrootValidationError::WrongDsmKrootChain)?;
         let wn = dsm_kroot.kroot_wn();
         let tow = Tow::from(dsm_kroot.kroot_towh()) * 3600;
         let gst = Gst::new(wn, tow);
         Self::check_gst(gst);
-        let gst = gst.add_seconds(-30);
+        let gst = gst.wrapping_add_seconds(-30);
         Ok((
-            Key::from_slice(dsm_kroot.kroot(), gst, &chain).force_valid(),
+            Key::from_slice(kroot, gst, &chain).force_valid(),
             nma_header.force_valid(),
         ))
     }
@@ -672,6 +851,186 @@ impl std::error::Error for KrootValidationError {
     }
 }
 
+/// Size in bytes of an exported TESLA key.
+///
+/// See [`Key::export`].
+pub const KEY_EXPORT_BYTES: usize = 82;
+
+const KEY_EXPORT_VERSION: u8 = 1;
+const KEY_EXPORT_MAC_START: usize = KEY_EXPORT_BYTES - 32;
+
+impl Key<Validated> {
+    /// Exports the key as an authenticated blob.
+    ///
+    /// This serializes the key, its chain parameters and the GST of the key
+    /// into a blob of [`KEY_EXPORT_BYTES`] bytes that can be transferred to
+    /// another system, where it is loaded with [`Key::import`]. This is
+    /// intended for architectures in which data collection and authentication
+    /// run on different processors.
+    ///
+    /// The blob is authenticated with an HMAC-SHA-256 computed using `secret`,
+    /// which must be shared by the exporting and importing systems. Since the
+    /// imported key is marked as `Validated`, the secret should be kept
+    /// confidential and have enough entropy. The blob is not encrypted, but
+    /// TESLA keys are not secret once they have been broadcast.
+    ///
+    /// The blob has the following format:
+    ///
+    /// | Bytes  | Field                                                  |
+    /// |--------|--------------------------------------------------------|
+    /// | 0      | Format version (currently 1)                           |
+    /// | 1      | Chain ID                                               |
+    /// | 2      | Hash function (same encoding as the HF field)          |
+    /// | 3      | MAC function (same encoding as the MF field)           |
+    /// | 4      | Key size in bytes                                      |
+    /// | 5      | Tag size in bits                                       |
+    /// | 6      | MAC look-up table                                      |
+    /// | 7-12   | Alpha (big-endian)                                     |
+    /// | 13-16  | GST of the key (12-bit WN and 20-bit TOW, big-endian)  |
+    /// | 17-48  | Key (padded with zeros to 32 bytes)                    |
+    /// | 49     | Reserved (zero)                                        |
+    /// | 50-81  | HMAC-SHA-256 of bytes 0-49                             |
+    pub fn export(&self, secret: &[u8]) -> [u8; KEY_EXPORT_BYTES] {
+        let mut blob = [0; KEY_EXPORT_BYTES];
+        blob[0] = KEY_EXPORT_VERSION;
+        blob[1] = self.chain.id;
+        blob[2] = match self.chain.hash_function {
+            HashFunction::Sha256 => 0,
+            HashFunction::Sha3_256 => 2,
+        };
+        blob[3] = match self.chain.mac_function {
+            MacFunction::HmacSha256 => 0,
+            MacFunction::CmacAes => 1,
+        };
+        blob[4] = self.chain.key_size_bytes.try_into().unwrap();
+        blob[5] = self.chain.tag_size_bits.try_into().unwrap();
+        blob[6] = self.chain.maclt;
+        blob[7..13].copy_from_slice(&self.chain.alpha.to_be_bytes()[2..]);
+        Self::store_gst(&mut blob[13..17], self.gst_subframe);
+        blob[17..17 + MAX_KEY_SIZE_BYTES].copy_from_slice(&self.data);
+        let mac = Self::export_mac(secret, &blob[..KEY_EXPORT_MAC_START]).finalize();
+        blob[KEY_EXPORT_MAC_START..].copy_from_slice(&mac.into_bytes());
+        blob
+    }
+
+    /// Imports a key that has been exported with [`Key::export`].
+    ///
+    /// The HMAC-SHA-256 of the blob is checked using `secret`, and then the
+    /// chain parameters and GST are checked for consistency. If all the checks
+    /// are successful, the key is returned as `Validated`. Otherwise, an error
+    /// indicating the problem is returned.
+    pub fn import(
+        blob: &[u8; KEY_EXPORT_BYTES],
+        secret: &[u8],
+    ) -> Result<Key<Validated>, KeyImportError> {
+        Self::export_mac(secret, &blob[..KEY_EXPORT_MAC_START])
+            .verify_slice(&blob[KEY_EXPORT_MAC_START..])
+            .map_err(|_| KeyImportError::WrongMac)?;
+        if blob[0] != KEY_EXPORT_VERSION {
+            return Err(KeyImportError::UnknownVersion);
+        }
+        let hash_function = match blob[2] {
+            0 => HashFunction::Sha256,
+            2 => HashFunction::Sha3_256,
+            _ => return Err(KeyImportError::WrongChain(ChainError::ReservedField)),
+        };
+        let mac_function = match blob[3] {
+            0 => MacFunction::HmacSha256,
+            1 => MacFunction::CmacAes,
+            _ => return Err(KeyImportError::WrongChain(ChainError::ReservedField)),
+        };
+        let key_size_bytes = usize::from(blob[4]);
+        let tag_size_bits = usize::from(blob[5]);
+        if !(1..=MAX_KEY_SIZE_BYTES).contains(&key_size_bytes) || tag_size_bits == 0 {
+            return Err(KeyImportError::WrongChain(ChainError::ReservedField));
+        }
+        let maclt = blob[6];
+        Chain::check_mack_layout(key_size_bytes * 8, tag_size_bits, maclt)
+            .map_err(KeyImportError::WrongChain)?;
+        let mut alpha = [0; 8];
+        alpha[2..].copy_from_slice(&blob[7..13]);
+        let chain = Chain {
+            id: blob[1],
+            hash_function,
+            mac_function,
+            key_size_bytes,
+            tag_size_bits,
+            maclt,
+            alpha: u64::from_be_bytes(alpha),
+        };
+        let gst_bits = BitSlice::from_slice(&blob[13..17]);
+        let wn = gst_bits[0..12].load_be();
+        let tow: Tow = gst_bits[12..32].load_be();
+        if tow >= 7 * 24 * 3600 {
+            return Err(KeyImportError::WrongGst);
+        }
+        let gst = Gst::new(wn, tow);
+        if !gst.is_subframe() {
+            return Err(KeyImportError::WrongGst);
+        }
+        let data = &blob[17..17 + MAX_KEY_SIZE_BYTES];
+        if data[key_size_bytes..].iter().any(|&b| b != 0) {
+            return Err(KeyImportError::WrongKeyPadding);
+        }
+        Ok(Key::from_slice(&data[..key_size_bytes], gst, &chain).force_valid())
+    }
+
+    fn export_mac(secret: &[u8], data: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC can take a key of any size");
+        Mac::update(&mut mac, data);
+        mac
+    }
+}
+
+/// Errors produced during the import of a TESLA key.
+///
+/// This gives the errors that can happen when importing a key using
+/// [`Key::import`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum KeyImportError {
+    /// The HMAC-SHA-256 of the blob is not correct.
+    ///
+    /// This happens if the blob has been modified or if the secret is not the
+    /// one used to export the key.
+    WrongMac,
+    /// The format version of the blob is not supported.
+    UnknownVersion,
+    /// The chain parameters in the blob are not valid.
+    ///
+    /// See [`ChainError`].
+    WrongChain(ChainError),
+    /// The GST of the key is not valid or not at the start of a subframe.
+    WrongGst,
+    /// The key data contains non-zero bytes beyond the key size.
+    WrongKeyPadding,
+}
+
+impl fmt::Display for KeyImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyImportError::WrongMac => "exported key MAC is not correct".fmt(f),
+            KeyImportError::UnknownVersion => "unknown exported key format version".fmt(f),
+            KeyImportError::WrongChain(err) => write!(f, "invalid chain parameters ({err})"),
+            KeyImportError::WrongGst => "invalid key GST".fmt(f),
+            KeyImportError::WrongKeyPadding => "non-zero key padding".fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KeyImportError::WrongChain(e) => Some(e),
+            KeyImportError::WrongMac
+            | KeyImportError::UnknownVersion
+            | KeyImportError::WrongGst
+            | KeyImportError::WrongKeyPadding => None,
+        }
+    }
+}
+
 impl<V: Clone> Key<V> {
     /// Computes the one-way function of a TESLA key.
     ///
@@ -679,22 +1038,53 @@ impl<V: Clone> Key<V> {
     /// chain. The validation status of the returned key is inherited from the
     /// validation status of `self`.
     pub fn one_way_function(&self) -> Key<V> {
+        let mut buffer = [0; ONE_WAY_FUNCTION_MESSAGE_BYTES];
         let mut hash = self.hash_digest();
-        let size = self.chain.key_size_bytes;
-        hash.update(&self.data[..size]);
-        let mut gst = [0; 4];
-        let previous_subframe = self.gst_subframe.add_seconds(-30);
-        Self::store_gst(&mut gst, previous_subframe);
-        hash.update(&gst);
-        hash.update(&self.chain.alpha.to_be_bytes()[2..]);
+        hash.update(self.one_way_function_message(&mut buffer));
         let mut hash_out = GenericArray::default();
         hash.finalize_into(&mut hash_out);
-        let mut new_key = [0; MAX_KEY_BYTES];
-        new_key[..size].copy_from_slice(&hash_out[..size]);
+        self.from_one_way_function_digest(&hash_out)
+    }
+
+    /// Gives the message hashed by the one-way function.
+    ///
+    /// The message is written to the beginning of `buffer`, and the part of
+    /// `buffer` that contains the message is returned. The message should be
+    /// hashed with the hash function of the chain, and the result given to
+    /// [`Key::from_one_way_function_digest`] to obtain the same key as
+    /// [`Key::one_way_function`]. This is useful to compute the hash
+    /// externally, for instance with a cryptographic co-processor.
+    pub fn one_way_function_message<'b>(
+        &self,
+        buffer: &'b mut [u8; ONE_WAY_FUNCTION_MESSAGE_BYTES],
+    ) -> &'b [u8] {
+        let size = self.chain.key_size_bytes;
+        buffer[..size].copy_from_slice(&self.data[..size]);
+        let previous_subframe = self.gst_subframe.wrapping_add_seconds(-30);
+        Self::store_gst(&mut buffer[size..size + 4], previous_subframe);
+        buffer[size + 4..size + 10].copy_from_slice(&self.chain.alpha.to_be_bytes()[2..]);
+        &buffer[..size + 10]
+    }
+
+    /// Constructs the output of the one-way function from a hash digest.
+    ///
+    /// The `digest` should be the hash of the message given by
+    /// [`Key::one_way_function_message`]. This gives the key corresponding to
+    /// the previous subframe in the TESLA chain, which is obtained by
+    /// truncating the digest to the key size. The validation status of the
+    /// returned key is inherited from the validation status of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `digest` is shorter than the key size.
+    pub fn from_one_way_function_digest(&self, digest: &[u8]) -> Key<V> {
+        let size = self.chain.key_size_bytes;
+        let mut new_key = [0; MAX_KEY_SIZE_BYTES];
+        new_key[..size].copy_from_slice(&digest[..size]);
         Key {
             data: new_key,
             chain: self.chain,
-            gst_subframe: previous_subframe,
+            gst_subframe: self.gst_subframe.wrapping_add_seconds(-30),
             _validated: self._validated.clone(),
         }
     }
@@ -732,20 +1122,43 @@ impl Key<Validated> {
         &self,
         other: &Key<V>,
     ) -> Result<Key<Validated>, ValidationError> {
+        let derivations = self.check_follows(other)?;
+        self.check_derived(other, &other.derive(derivations))
+    }
+
+    // Performs the checks of validate_key that do not require computing the
+    // one-way function. This returns the number of derivations needed to
+    // validate other.
+    pub(crate) fn check_follows<V>(&self, other: &Key<V>) -> Result<usize, ValidationError> {
         if self.chain != other.chain {
             return Err(ValidationError::DifferentChain);
         }
-        if self.gst_subframe >= other.gst_subframe {
+        if !other.gst_subframe.is_after(self.gst_subframe) {
             return Err(ValidationError::DoesNotFollow);
         }
+        // Fast sanity checks before computing derivations
+        let size = self.chain.key_size_bytes;
+        if other.data[..size].iter().all(|&b| b == 0)
+            || other.data[size..].iter().any(|&b| b != 0)
+            || other.data[..size] == self.data[..size]
+        {
+            return Err(ValidationError::MalformedKey);
+        }
         let derivations = other.gst_subframe.subframes_difference(self.gst_subframe);
         assert!(derivations >= 1);
-        // Set an arbitrary limit to the number of derivations.
-        // This is chosen to be slightly greater than 1 day.
-        if derivations > 3000 {
+        if derivations > MAX_KEY_DERIVATIONS {
             return Err(ValidationError::TooManyDerivations);
         }
-        let derived_key = other.derive(derivations.try_into().unwrap());
+        Ok(derivations.try_into().unwrap())
+    }
+
+    // Checks that the key derived from other matches self, and returns other
+    // as a validated key if so.
+    pub(crate) fn check_derived<V: Clone>(
+        &self,
+        other: &Key<V>,
+        derived_key: &Key<V>,
+    ) -> Result<Key<Validated>, ValidationError> {
         assert!(derived_key.gst_subframe == self.gst_subframe);
         let size = self.chain.key_size_bytes;
         if derived_key.data[..size] == self.data[..size] {
@@ -771,16 +1184,18 @@ impl Key<Validated> {
     /// header does not need to be validated using the DSM-KROOT, since a forged
     /// or incorrect NMA header will simply make tag validation fail.
     ///
-    /// Note that the navigation data `navdata` must correspond to the previous
-    /// subframe of the tag, and the key `self` must correspond to the next
-    /// subframe of the tag, except when tag is a Slow MAC key (in this case the
-    /// difference between the GSTs of the key and the tag should be 11
-    /// subframes).
+    /// The navigation data `navdata` can be either
+    /// [`CedBits`](crate::types::CedBits) or
+    /// [`TimingBits`](crate::types::TimingBits), according to the ADKD of the
+    /// tag. Note that it must correspond to the previous subframe of the tag,
+    /// and the key `self` must correspond to the next subframe of the tag,
+    /// except when tag is a Slow MAC key (in this case the difference between
+    /// the GSTs of the key and the tag should be 11 subframes).
     ///
     /// This returns `true` if the validation was succesful. Otherwise, it
     /// returns `false`.
     #[allow(clippy::too_many_arguments)]
-    pub fn validate_tag(
+    pub fn validate_tag<'a>(
         &self,
         tag: &BitSlice,
         tag_gst: Gst,
@@ -788,8 +1203,9 @@ impl Key<Validated> {
         prna: Svn,
         ctr: u8,
         nma_status: NmaStatus,
-        navdata: &BitSlice,
+        navdata: impl Into<NavDataBits<'a>>,
     ) -> bool {
+        let navdata = navdata.into().as_bitslice();
         let mut mac = self.mac_digest();
         mac.update(&[prnd]);
         Self::update_mac_with_navdata(&mut mac, tag_gst, prna, ctr, nma_status, navdata);
@@ -836,20 +1252,23 @@ impl Key<Validated> {
     /// DSM-KROOT, since a forged or incorrect NMA header will simply make tag
     /// validation fail.
     ///
-    /// Note that the navigation data `navdata` must correspond to the previous
+    /// The navigation data `navdata` should be
+    /// [`CedBits`](crate::types::CedBits), since the tag0
+    /// always uses ADKD=0. Note that it must correspond to the previous
     /// subframe of the tag0, and the key `self` must correspond to the next
     /// subframe of the tag0.
     ///
     /// This returns `true` if the validation was succesful. Otherwise, it
     /// returns `false`.
-    pub fn validate_tag0(
+    pub fn validate_tag0<'a>(
         &self,
         tag0: &BitSlice,
         tag_gst: Gst,
         prna: Svn,
         nma_status: NmaStatus,
-        navdata: &BitSlice,
+        navdata: impl Into<NavDataBits<'a>>,
     ) -> bool {
+        let navdata = navdata.into().as_bitslice();
         let mut mac = self.mac_digest();
         Self::update_mac_with_navdata(&mut mac, tag_gst, prna, 1, nma_status, navdata);
         self.check_common(mac, tag0)
@@ -947,6 +1366,33 @@ impl Key<Validated> {
         mac.update(&buffer[..message_bytes]);
     }
 
+    // Computes a tag for the navigation data. This is the counterpart of
+    // validate_tag (or of validate_tag0 if `prnd` is `None`), and is used to
+    // generate the OSNMA broadcast. The tag is written to `tag`, whose length
+    // gives the tag size.
+    #[cfg(feature = "signer")]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn compute_tag(
+        &self,
+        tag: &mut BitSlice,
+        tag_gst: Gst,
+        prnd: Option<u8>,
+        prna: Svn,
+        ctr: u8,
+        nma_status: NmaStatus,
+        navdata: &BitSlice,
+    ) {
+        let mut mac = self.mac_digest();
+        if let Some(prnd) = prnd {
+            mac.update(&[prnd]);
+        }
+        Self::update_mac_with_navdata(&mut mac, tag_gst, prna, ctr, nma_status, navdata);
+        let mut mac_out = GenericArray::default();
+        mac.finalize_into(&mut mac_out);
+        let len = tag.len();
+        tag.copy_from_bitslice(&BitSlice::from_slice(&mac_out)[..len]);
+    }
+
     fn check_common(&self, mac: MacDigest, tag: &BitSlice) -> bool {
         let mut mac_out = GenericArray::default();
         mac.finalize_into(&mut mac_out);
@@ -976,6 +1422,22 @@ impl Key<Validated> {
         prna: Svn,
         gst_mack: Gst,
     ) -> Result<(), MacseqCheckError> {
+        if self.compute_macseq(mack, prna, gst_mack)? == mack.macseq() {
+            Ok(())
+        } else {
+            Err(MacseqCheckError::WrongMacseq)
+        }
+    }
+
+    // Computes the MACSEQ field of a MACK message. Only the FLX Tag-Info
+    // fields of `mack` are used, so the MACSEQ field of `mack` does not need
+    // to be filled.
+    pub(crate) fn compute_macseq<V: Clone>(
+        &self,
+        mack: &Mack<V>,
+        prna: Svn,
+        gst_mack: Gst,
+    ) -> Result<u16, MacseqCheckError> {
         let mut mac = self.mac_digest();
         let mut buffer = [0u8; FIXED_SIZE];
         const TAG_INFO_SIZE: usize = 2; // size of tag-info in bytes
@@ -995,16 +1457,7 @@ impl Key<Validated> {
         let mut mac_out = GenericArray::default();
         mac.finalize_into(&mut mac_out);
         const MACSEQ_BITS: usize = 12;
-        let computed = &BitSlice::from_slice(&mac_out)[..MACSEQ_BITS];
-
-        let mut macseq_buffer = [0u8; 2];
-        let macseq_bits = &mut BitSlice::from_slice_mut(&mut macseq_buffer)[..MACSEQ_BITS];
-        macseq_bits.store_be::<u16>(mack.macseq());
-        if computed == macseq_bits {
-            Ok(())
-        } else {
-            Err(MacseqCheckError::WrongMacseq)
-        }
+        Ok(BitSlice::from_slice(&mac_out)[..MACSEQ_BITS].load_be())
     }
 }
 
@@ -1048,6 +1501,9 @@ impl From<MacLTError> for MacseqCheckError {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::bitfields::{AdkdCheckPolicy, MackValidationError};
+    use crate::testing;
+    use crate::types::{CedBits, MackMessage, KEY_SIZES_BITS, MACK_MESSAGE_BITS, TAG_SIZES_BITS};
     use hex_literal::hex;
 
     fn test_chain() -> Chain {
@@ -1076,6 +1532,124 @@ mod test {
         }
     }
 
+    #[test]
+    fn chain_display() {
+        assert_eq!(
+            test_chain().to_string(),
+            "CID=1 HF=SHA-256 MF=HMAC-SHA-256 KS=128 bits TS=40 bits \
+             MACLT=33 alpha=0x25d3964da3a2"
+        );
+    }
+
+    #[test]
+    fn mack_layouts() {
+        for ks in KEY_SIZES_BITS {
+            for ts in TAG_SIZES_BITS {
+                let nt = Chain::mack_num_tags(ks, ts);
+                assert!(nt >= 1);
+                let used = nt * (ts + 16) + ks;
+                assert!(used <= MACK_MESSAGE_BITS);
+                // the padding is not large enough to hold another tag
+                assert!(MACK_MESSAGE_BITS - used < ts + 16);
+            }
+        }
+
+        // (KS, TS, MACLT, number of tags) of layouts that are consistent with
+        // the MAC look-up table in Annex C of the OSNMA SIS ICD
+        let valid = [
+            (128, 40, 27, 6),
+            (96, 20, 28, 10),
+            (112, 20, 28, 10),
+            (160, 40, 31, 5),
+            (128, 40, 33, 6),
+            (128, 40, 34, 6),
+            (128, 40, 35, 6),
+            (192, 40, 36, 5),
+            (224, 32, 37, 5),
+            (160, 40, 38, 5),
+            (224, 40, 39, 4),
+            (256, 40, 40, 4),
+            (256, 40, 41, 4),
+        ];
+        for (ks, ts, maclt, nt) in valid {
+            assert_eq!(Chain::mack_num_tags(ks, ts), nt);
+            assert_eq!(Chain::check_mack_layout(ks, ts, maclt), Ok(()));
+        }
+
+        // 20-bit tags give 9 tags instead of the 6 tags of MAC look-up table 34
+        assert_eq!(Chain::mack_num_tags(128, 20), 9);
+        assert_eq!(
+            Chain::check_mack_layout(128, 20, 34),
+            Err(ChainError::WrongMackLayout)
+        );
+        // 256-bit keys give 4 tags instead of 6
+        assert_eq!(
+            Chain::check_mack_layout(256, 40, 34),
+            Err(ChainError::WrongMackLayout)
+        );
+        // 128-bit keys give 6 tags instead of the 4 tags of MAC look-up
+        // table 41
+        assert_eq!(
+            Chain::check_mack_layout(128, 40, 41),
+            Err(ChainError::WrongMackLayout)
+        );
+        // MAC look-up tables that are not defined
+        for maclt in [0, 26, 29, 30, 32, 42] {
+            assert_eq!(
+                Chain::check_mack_layout(128, 40, maclt),
+                Err(ChainError::UnsupportedMaclt { maclt })
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_mack_layout() {
+        let mut dsm = testing::DSM_KROOT;
+        let chain = Chain::from_dsm_kroot(DsmKroot(&dsm)).unwrap();
+        assert_eq!(chain, test_chain());
+        assert_eq!(chain.num_tags(), 6);
+        // Change tag size to 20 bits, which gives 9 tags instead of the 6 tags
+        // of MAC look-up table 33
+        dsm[2] = 0x45;
+        assert_eq!(
+            Chain::from_dsm_kroot(DsmKroot(&dsm)),
+            Err(ChainError::WrongMackLayout)
+        );
+        // Change MAC look-up table to 42, which is not defined
+        dsm[2] = 0x49;
+        dsm[3] = 0x2a;
+        assert_eq!(
+            Chain::from_dsm_kroot(DsmKroot(&dsm)),
+            Err(ChainError::UnsupportedMaclt { maclt: 42 })
+        );
+    }
+
+    #[test]
+    fn nma_header_icd_version() {
+        // NMAS=2, CID=1, CPKS=6 (new Merkle tree)
+        let nma_header = NmaHeader::new(0x9c);
+        assert_eq!(
+            nma_header.chain_and_pubkey_status(),
+            ChainAndPubkeyStatus::NewMerkleTree
+        );
+        assert_eq!(
+            nma_header.chain_and_pubkey_status_icd(IcdVersion::V1_1),
+            ChainAndPubkeyStatus::NewMerkleTree
+        );
+        assert_eq!(
+            nma_header.chain_and_pubkey_status_icd(IcdVersion::V1_0),
+            ChainAndPubkeyStatus::Reserved
+        );
+        // NMAS=2, CID=1, CPKS=2 (end of chain) is the same in both versions
+        let nma_header = NmaHeader::new(0x94);
+        for icd in [IcdVersion::V1_0, IcdVersion::V1_1] {
+            assert_eq!(
+                nma_header.chain_and_pubkey_status_icd(icd),
+                ChainAndPubkeyStatus::EndOfChain
+            );
+        }
+    }
+
     #[test]
     fn one_way_function() {
         // Keys broadcast on 2022-03-07 ~9:00 UTC
@@ -1091,6 +1665,11 @@ mod test {
             &chain,
         );
         assert_eq!(k1.one_way_function(), k0);
+        let mut buffer = [0; ONE_WAY_FUNCTION_MESSAGE_BYTES];
+        let message = k1.one_way_function_message(&mut buffer);
+        assert_eq!(message.len(), 16 + 10);
+        let digest = Sha256::digest(message);
+        assert_eq!(k1.from_one_way_function_digest(&digest), k0);
     }
 
     #[test]
@@ -1112,6 +1691,46 @@ mod test {
         assert!(kroot.validate_key(&key).is_ok());
     }
 
+    #[test]
+    fn validation_malformed() {
+        let chain = test_chain();
+        let gst = Gst::new(1176, 120930);
+        let key = Key::from_slice(
+            &hex!("42 b4 19 da 6a da 1c 0a 3d 6f 56 a5 e5 dc 59 a7"),
+            gst,
+            &chain,
+        );
+        let previous = key.one_way_function().force_valid();
+        let zero = Key::from_slice(&[0; 16], gst, &chain);
+        let repeated = Key {
+            gst_subframe: gst,
+            ..previous
+        };
+        let mut padding = key;
+        padding.data[16] = 1;
+        for malformed in [zero.force_valid(), repeated, padding.force_valid()] {
+            assert_eq!(
+                previous.validate_key(&malformed),
+                Err(ValidationError::MalformedKey)
+            );
+        }
+        assert!(previous.validate_key(&key).is_ok());
+    }
+
+    #[test]
+    fn validation_week_rollover() {
+        let chain = test_chain();
+        let key = Key::from_slice(
+            &hex!("42 b4 19 da 6a da 1c 0a 3d 6f 56 a5 e5 dc 59 a7"),
+            Gst::new(1177, 30),
+            &chain,
+        );
+        // Key from the previous week, two subframes before
+        let previous = key.one_way_function().one_way_function();
+        assert_eq!(previous.gst_subframe(), Gst::new(1176, 7 * 24 * 3600 - 30));
+        assert!(previous.force_valid().validate_key(&key).is_ok());
+    }
+
     #[test]
     fn tag0() {
         // Data corresponding to E21 on 2022-03-07 ~9:00 UTC
@@ -1125,32 +1744,51 @@ mod test {
             &chain,
         )
         .force_valid();
-        let navdata_adkd0 = &BitSlice::from_slice(&hex!(
-            "
+        let navdata_adkd0 = CedBits::new(
+            &BitSlice::from_slice(&hex!(
+                "
             12 07 d0 ec 19 90 2e 00 1f e1 06 aa 04 ed 97 12
             11 f0 56 1f 49 ea ce 67 88 4d 18 57 81 9f 12 3f
             f0 37 48 93 42 c3 c2 96 c7 65 c3 83 1a c4 85 40
             01 7f fd 87 d0 fe 85 ee 31 ff f6 20 0c 68 0b fe
             48 00 50 14 00"
-        ))[..549];
+            ))[..549],
+        )
+        .unwrap();
         assert!(key.validate_tag0(tag0, tag0_gst, prna, NmaStatus::Test, navdata_adkd0));
     }
 
+    #[test]
+    fn export_import() {
+        let key = testing::tesla_key();
+        let secret = b"shared secret";
+        let blob = key.export(secret);
+        assert_eq!(Key::import(&blob, secret), Ok(key));
+        assert_eq!(
+            Key::import(&blob, b"another secret"),
+            Err(KeyImportError::WrongMac)
+        );
+        let mut tampered = blob;
+        tampered[20] ^= 1;
+        assert_eq!(
+            Key::import(&tampered, secret),
+            Err(KeyImportError::WrongMac)
+        );
+    }
+
+    // Data broadcast by E19 on 2022-03-07 ~9:00 UTC
+    const TEST_MACK: MackMessage = hex!(
+        "
+        7e ff 9e 16 a5 dd f0 04 f0 3c 9b 6b 1b 07 4d 49
+        2e dd 67 0b 02 60 ef 9b 83 36 13 c0 94 a8 72 a7
+        f6 12 05 8f 2e f7 63 24 0e c5 ca 40 0f ad f1 12
+        47 9f 05 44 9a 25 d8 2e 80 c8 00 00"
+    );
+
     fn test_mack() -> Mack<'static, NotValidated> {
-        // Data broadcast by E19 on 2022-03-07 ~9:00 UTC
         let key_size = 128;
         let tag_size = 40;
-        Mack::new(
-            &hex!(
-                "
-                7e ff 9e 16 a5 dd f0 04 f0 3c 9b 6b 1b 07 4d 49
-                2e dd 67 0b 02 60 ef 9b 83 36 13 c0 94 a8 72 a7
-                f6 12 05 8f 2e f7 63 24 0e c5 ca 40 0f ad f1 12
-                47 9f 05 44 9a 25 d8 2e 80 c8 00 00"
-            ),
-            key_size,
-            tag_size,
-        )
+        Mack::new(&TEST_MACK, key_size, tag_size)
     }
 
     fn test_mack_2023() -> Mack<'static, NotValidated> {
@@ -1210,6 +1848,48 @@ mod test {
         }
     }
 
+    #[test]
+    fn mack_adkd_policy() {
+        let key = test_key().force_valid();
+        let prna = Svn::try_from(19).unwrap();
+        let gst = Gst::new(1176, 121050);
+        let mut data = TEST_MACK;
+        // Replace the ADKD of tag2 (which is in a fixed slot) by a reserved value
+        data[2 * 7 + 6] = (data[2 * 7 + 6] & 0x0f) | 0x50;
+        let mack = Mack::new(&data, 128, 40);
+        assert!(matches!(
+            mack.validate(&key, prna, gst),
+            Err(MackValidationError::WrongAdkd { tag_index: 2, .. })
+        ));
+        assert!(mack
+            .validate_with_policy(&key, prna, gst, AdkdCheckPolicy::SkipTag)
+            .is_ok());
+        // Defined ADKDs are never skipped
+        data[2 * 7 + 6] = (data[2 * 7 + 6] & 0x0f) | 0xc0;
+        let mack = Mack::new(&data, 128, 40);
+        assert!(mack
+            .validate_with_policy(&key, prna, gst, AdkdCheckPolicy::SkipTag)
+            .is_err());
+        // Tags preceding the first incorrect ADKD are kept
+        let validated = mack
+            .validate_with_policy(&key, prna, gst, AdkdCheckPolicy::Truncate)
+            .unwrap();
+        assert_eq!(validated.num_tags(), 6);
+        assert_eq!(validated.num_valid_tags(), 2);
+        assert_eq!(
+            mack.validate(&key, prna, gst).unwrap_err(),
+            MackValidationError::WrongAdkd {
+                tag_index: 2,
+                error: AdkdCheckError::WrongAdkd
+            }
+        );
+        let mack = test_mack();
+        let validated = mack
+            .validate_with_policy(&key, prna, gst, AdkdCheckPolicy::Truncate)
+            .unwrap();
+        assert_eq!(validated.num_valid_tags(), validated.num_tags());
+    }
+
     #[test]
     fn macseq() {
         // This does not include FLX entries
@@ -0,0 +1,220 @@
+//! Utilities for testing.
+//!
+//! This module contains test vectors and helpers that can be used by other
+//! crates to write tests involving the [`Osnma`] black box without having to
+//! copy the test vectors used by this crate. It is only available when this
+//! crate is built with the `testing` feature, and it does not require `std`.
+//!
+//! The test vectors were broadcast on 2022-03-07 around 09:00 UTC, during the
+//! OSNMA test phase. The public key used to sign the DSM-KROOT of that time is
+//! not available, so [`pubkey`] gives a dummy key, which cannot be used to
+//! verify the DSM-KROOT. Black boxes that need a validated TESLA key can be
+//! obtained with [`TestOsnmaBuilder::with_tesla_key`] instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::testing::{self, MockStorage, TestOsnmaBuilder};
+//!
+//! let osnma = TestOsnmaBuilder::new()
+//!     .with_tesla_key()
+//!     .build::<MockStorage>();
+//! assert_eq!(osnma.latest_tesla_key(), Some(testing::tesla_key()));
+//! ```
+
+use crate::bitfields::{DsmKroot, Mack};
+use crate::storage::{StaticStorage, Storage};
+use crate::tesla::{Chain, Key};
+use crate::types::{MackMessage, MerkleTreeNode};
+use crate::validation::{NotValidated, Validated};
+use crate::{Gst, Osnma, PublicKey};
+use hex_literal::hex;
+
+/// Tiny storage for tests.
+///
+/// This storage holds only 4 satellites in parallel and a history of 3
+/// subframes of navigation messages, so it cannot process Slow MAC. It is
+/// intended to keep the memory footprint and running time of tests low.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct MockStorage {}
+
+impl StaticStorage for MockStorage {
+    const NUM_SATS: usize = 4;
+    type NavMessageDepth = typenum::U3;
+    type NavMessageDepthSats = typenum::U12;
+    type MackDepth = typenum::U2;
+    type MackDepthSats = typenum::U8;
+    type EventQueueDepth = typenum::U16;
+}
+
+/// DSM-KROOT broadcast on 2022-03-07 09:00 UTC.
+pub const DSM_KROOT: [u8; 104] = hex!(
+    "
+    22 50 49 21 04 98 21 25 d3 96 4d a3 a2 84 1e 1d
+    e4 d4 58 c0 e9 84 24 76 e0 04 66 6c f3 79 58 de
+    28 51 97 a2 63 53 f1 a4 c6 6d 7e 3d 29 18 53 ba
+    5a 13 c9 c3 48 4a 26 77 70 11 2a 13 38 3e a5 2d
+    3a 01 9d 5b 6e 1d d1 87 b9 45 3c df 06 ca 7f 34
+    ea 14 97 52 5a af 18 f1 f9 f1 fc cb 12 29 89 77
+    35 c0 21 b0 41 73 93 b5"
+);
+
+/// MACK message broadcast on 2022-03-07 09:00 UTC.
+///
+/// This message was transmitted in the subframe starting at [`mack_gst`],
+/// and it uses the chain given by [`chain`].
+pub const MACK: MackMessage = hex!(
+    "
+    11 55 d3 71 f2 1f 30 a8 e4 ec e0 c0 1b 07 6d 17
+    7d 64 03 12 05 d4 02 7e 77 13 15 c0 4c ca 1c 16
+    99 1a 05 48 91 07 a7 f7 0e c5 42 b4 19 da 6a da
+    1c 0a 3d 6f 56 a5 e5 dc 59 a7 00 00"
+);
+
+/// TESLA key broadcast on 2022-03-07 09:00 UTC.
+///
+/// This key was transmitted in the subframe starting at [`tesla_key_gst`].
+pub const TESLA_KEY: [u8; 16] = hex!("19 58 e7 76 6f b4 08 cb d6 a8 de fc e4 c7 d5 66");
+
+/// Dummy ECDSA P-256 public key, in SEC1 compressed form.
+pub const PUBKEY_P256: [u8; 33] = hex!(
+    "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+     22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+);
+
+/// Root of the Merkle tree published on 2023-12-13.
+///
+/// This is the root of `OSNMA_MerkleTree_20231213105954_PKID_1.xml`.
+pub const MERKLE_TREE_ROOT: MerkleTreeNode =
+    hex!("0E63F552C8021709043C239032EFFE941BF22C8389032F5F2701E0FBC80148B8");
+
+/// Gives the GST at the start of the subframe in which [`MACK`] was
+/// transmitted.
+pub fn mack_gst() -> Gst {
+    Gst::new(1176, 120930)
+}
+
+/// Gives the GST at the start of the subframe in which [`TESLA_KEY`] was
+/// transmitted.
+pub fn tesla_key_gst() -> Gst {
+    Gst::new(1176, 121080)
+}
+
+/// Gives the TESLA chain defined by [`DSM_KROOT`].
+pub fn chain() -> Chain {
+    Chain::from_dsm_kroot(DsmKroot(&DSM_KROOT)).unwrap()
+}
+
+/// Gives the TESLA key [`TESLA_KEY`] as a validated key.
+pub fn tesla_key() -> Key<Validated> {
+    Key::from_slice(&TESLA_KEY, tesla_key_gst(), &chain()).force_valid()
+}
+
+/// Gives the MACK message [`MACK`] with the key and tag sizes of [`chain`].
+pub fn mack() -> Mack<'static, NotValidated> {
+    let chain = chain();
+    Mack::new(&MACK, chain.key_size_bits(), chain.tag_size_bits())
+}
+
+/// Gives the public key [`PUBKEY_P256`] with PKID 1.
+pub fn pubkey() -> PublicKey<Validated> {
+    let pubkey = p256::ecdsa::VerifyingKey::from_sec1_bytes(&PUBKEY_P256).unwrap();
+    PublicKey::from_p256(pubkey, 1).force_valid()
+}
+
+/// Builder of [`Osnma`] black boxes for tests.
+///
+/// By default, the black box is constructed with the public key given by
+/// [`pubkey`], without a Merkle tree and processing all the ADKDs.
+#[derive(Debug, Clone, Default)]
+pub struct TestOsnmaBuilder {
+    merkle_tree: bool,
+    no_pubkey: bool,
+    tesla_key: bool,
+    only_slowmac: bool,
+}
+
+impl TestOsnmaBuilder {
+    /// Constructs a new builder with the default settings.
+    pub fn new() -> TestOsnmaBuilder {
+        TestOsnmaBuilder::default()
+    }
+
+    /// Loads the Merkle tree root [`MERKLE_TREE_ROOT`] in the black box.
+    pub fn with_merkle_tree(mut self) -> TestOsnmaBuilder {
+        self.merkle_tree = true;
+        self
+    }
+
+    /// Does not load any public key in the black box.
+    ///
+    /// This requires [`TestOsnmaBuilder::with_merkle_tree`], since a black box
+    /// needs either a Merkle tree or a public key.
+    pub fn without_pubkey(mut self) -> TestOsnmaBuilder {
+        self.no_pubkey = true;
+        self
+    }
+
+    /// Imports the key given by [`tesla_key`] in the black box.
+    ///
+    /// This allows to validate the tags of the test vectors without a
+    /// DSM-KROOT.
+    pub fn with_tesla_key(mut self) -> TestOsnmaBuilder {
+        self.tesla_key = true;
+        self
+    }
+
+    /// Only processes Slow MAC in the black box.
+    ///
+    /// See [`Osnma::from_merkle_tree`].
+    pub fn only_slowmac(mut self) -> TestOsnmaBuilder {
+        self.only_slowmac = true;
+        self
+    }
+
+    /// Builds the black box.
+    ///
+    /// # Panics
+    ///
+    /// Panics if neither a Merkle tree nor a public key are used.
+    pub fn build<S: Storage>(&self) -> Osnma<S> {
+        let pubkey = (!self.no_pubkey).then(pubkey);
+        let mut osnma = if self.merkle_tree {
+            Osnma::from_merkle_tree(MERKLE_TREE_ROOT, pubkey, self.only_slowmac)
+        } else {
+            Osnma::from_pubkey(
+                pubkey.expect("a Merkle tree or a public key is needed"),
+                self.only_slowmac,
+            )
+        };
+        if self.tesla_key {
+            osnma
+                .import_tesla_key(tesla_key(), tesla_key_gst())
+                .unwrap();
+        }
+        osnma
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::assert_consistent;
+
+    #[test]
+    fn vectors() {
+        assert_consistent::<MockStorage>();
+        let chain = chain();
+        let mack = mack();
+        assert_eq!(mack.num_tags(), chain.num_tags());
+        // The key in the MACK message is an earlier key of the same chain
+        let mack_key = Key::from_bitslice(mack.key(), mack_gst(), &chain);
+        assert_eq!(tesla_key().derive(5), mack_key.force_valid());
+        let osnma = TestOsnmaBuilder::new()
+            .with_merkle_tree()
+            .without_pubkey()
+            .with_tesla_key()
+            .build::<MockStorage>();
+        assert_eq!(osnma.latest_tesla_key(), Some(tesla_key()));
+    }
+}
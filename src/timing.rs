@@ -0,0 +1,309 @@
+//! Timing parameters and host clock check.
+//!
+//! This module contains the [`GstUtcParameters`] struct, which decodes the
+//! GST-UTC conversion parameters from the timing parameters data (ADKD=4), and
+//! the [`ClockCheck`] struct, which compares the time given by the GST and the
+//! authenticated GST-UTC parameters against the clock of the host.
+//!
+//! A large divergence between the GNSS time and an independent clock is a
+//! common sign of a spoofing attack, since the attacker can control the timing
+//! of the signals even if the navigation data is authenticated. This check is
+//! only meaningful if the host clock is independent from the GNSS receiver (for
+//! instance, if it is synchronized with NTP or kept by an RTC that was
+//! synchronized in the past) and if the GST passed to the check is the GST
+//! computed by the receiver from the signals.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::{Gst, Osnma, PublicKey};
+//! use galileo_osnma::storage::FullStorage;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//!
+//! // GST computed by the receiver, and UTC time of the host clock as
+//! // seconds since the Unix epoch
+//! let gst = Gst::new(1177, 175767);
+//! let host_utc = 1_650_187_749.0;
+//! // Raise an alarm if both times differ by more than 2 seconds
+//! if let Some(check) = osnma.check_host_clock(gst, host_utc, 2.0) {
+//!     if check.is_alarm() {
+//!         println!("clock divergence of {} s", check.divergence());
+//!     }
+//! }
+//! ```
+
+use crate::types::BitSlice;
+use crate::{Gst, Svn};
+use bitvec::prelude::*;
+
+const SECS_IN_WEEK: i64 = 604800;
+const SECS_IN_DAY: i64 = 86400;
+// Unix time at the start of GST week 0, counted in GST. GST is aligned with
+// GPS time, so it was 13 seconds ahead of UTC at this moment (1999-08-21
+// 23:59:47 UTC).
+const GST_EPOCH_UNIX: i64 = 935280000;
+
+/// GST-UTC conversion parameters.
+///
+/// These are the GST-UTC conversion parameters transmitted in word type 6 of
+/// the I/NAV message, as described in Section 5.1.7 of the
+/// [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+/// They are obtained from the timing parameters data (ADKD=4) returned by
+/// [`Osnma::get_timing_parameters`](crate::Osnma::get_timing_parameters).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct GstUtcParameters {
+    a0: i32,
+    a1: i32,
+    delta_t_ls: i8,
+    t0t: u8,
+    wnot: u8,
+    wn_lsf: u8,
+    dn: u8,
+    delta_t_lsf: i8,
+}
+
+impl GstUtcParameters {
+    /// Decodes the GST-UTC conversion parameters from the timing parameters
+    /// data.
+    ///
+    /// The `data` should be the data of a
+    /// [`NavMessageData`](crate::navmessage::NavMessageData) obtained with
+    /// [`Osnma::get_timing_parameters`](crate::Osnma::get_timing_parameters).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is shorter than the 99 bits of the GST-UTC conversion
+    /// parameters.
+    pub fn from_timing_parameters(data: &BitSlice) -> GstUtcParameters {
+        GstUtcParameters {
+            a0: data[..32].load_be(),
+            a1: data[32..56].load_be(),
+            delta_t_ls: data[56..64].load_be(),
+            t0t: data[64..72].load_be(),
+            wnot: data[72..80].load_be(),
+            wn_lsf: data[80..88].load_be(),
+            dn: data[88..91].load_be(),
+            delta_t_lsf: data[91..99].load_be(),
+        }
+    }
+
+    /// Gives the constant term of the GST-UTC polynomial (A0), in seconds.
+    pub fn a0(&self) -> f64 {
+        f64::from(self.a0) / (1u64 << 30) as f64
+    }
+
+    /// Gives the rate of change of the GST-UTC offset (A1), in seconds per
+    /// second.
+    pub fn a1(&self) -> f64 {
+        f64::from(self.a1) / (1u64 << 50) as f64
+    }
+
+    /// Gives the leap second count before the leap second adjustment (ΔtLS).
+    pub fn delta_t_ls(&self) -> i8 {
+        self.delta_t_ls
+    }
+
+    /// Gives the UTC data reference TOW (t0t), in seconds.
+    pub fn t0t(&self) -> u32 {
+        u32::from(self.t0t) * 3600
+    }
+
+    /// Gives the UTC data reference week number modulo 256 (WNot).
+    pub fn wnot(&self) -> u8 {
+        self.wnot
+    }
+
+    /// Gives the week number modulo 256 of the leap second adjustment (WNLSF).
+    pub fn wn_lsf(&self) -> u8 {
+        self.wn_lsf
+    }
+
+    /// Gives the day number at the end of which a leap second adjustment
+    /// becomes effective (DN).
+    pub fn dn(&self) -> u8 {
+        self.dn
+    }
+
+    /// Gives the leap second count after the leap second adjustment (ΔtLSF).
+    pub fn delta_t_lsf(&self) -> i8 {
+        self.delta_t_lsf
+    }
+
+    /// Computes the GST-UTC offset ΔtUTC at a GST, in seconds.
+    ///
+    /// This uses the formulas in Section 5.1.7 of the
+    /// [Galileo OS SIS ICD v2.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OS_SIS_ICD_v2.1.pdf).
+    /// The count after the leap second adjustment, ΔtLSF, is used once the
+    /// adjustment given by WNLSF and DN has happened. The special case for
+    /// the 6 hours around the adjustment (case b in the ICD) is not
+    /// implemented, so the result can be off by up to one second during this
+    /// interval.
+    pub fn utc_offset(&self, gst: Gst) -> f64 {
+        let tow = i64::from(gst.tow());
+        let week_diff = |wn: u8| i64::from((gst.wn() as u8).wrapping_sub(wn) as i8);
+        let lsf_time = week_diff(self.wn_lsf) * SECS_IN_WEEK + tow;
+        let delta_t_ls = if self.delta_t_lsf != self.delta_t_ls
+            && lsf_time >= i64::from(self.dn) * SECS_IN_DAY
+        {
+            self.delta_t_lsf
+        } else {
+            self.delta_t_ls
+        };
+        let dt = (week_diff(self.wnot) * SECS_IN_WEEK + tow - i64::from(self.t0t())) as f64;
+        f64::from(delta_t_ls) + self.a0() + self.a1() * dt
+    }
+
+    /// Converts a GST to UTC, given as seconds since the Unix epoch.
+    ///
+    /// The week number of `gst` should not be truncated (for instance, to the
+    /// 12 bits transmitted in the I/NAV message).
+    pub fn gst_to_unix_utc(&self, gst: Gst) -> f64 {
+        gst_to_unix(gst) as f64 - self.utc_offset(gst)
+    }
+}
+
+// Converts a GST to seconds since the Unix epoch, without applying the GST-UTC
+// offset.
+pub(crate) fn gst_to_unix(gst: Gst) -> i64 {
+    GST_EPOCH_UNIX + i64::from(gst.wn()) * SECS_IN_WEEK + i64::from(gst.tow())
+}
+
+/// Result of the comparison of the GNSS time against the host clock.
+///
+/// This is obtained with [`ClockCheck::new`] or with
+/// [`Osnma::check_host_clock`](crate::Osnma::check_host_clock).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClockCheck {
+    svn: Option<Svn>,
+    gst: Gst,
+    divergence: f64,
+    threshold: f64,
+}
+
+impl ClockCheck {
+    /// Compares a GST against the host clock.
+    ///
+    /// The `gst` is converted to UTC using the GST-UTC conversion parameters
+    /// `params`, and compared with `host_utc`, which gives the UTC time of the
+    /// host clock at the same moment as seconds since the Unix epoch (without
+    /// leap seconds, as usual in Unix time). The check is an alarm if the
+    /// absolute value of the divergence is larger than `threshold` seconds.
+    pub fn new(params: &GstUtcParameters, gst: Gst, host_utc: f64, threshold: f64) -> ClockCheck {
+        ClockCheck {
+            svn: None,
+            gst,
+            divergence: host_utc - params.gst_to_unix_utc(gst),
+            threshold,
+        }
+    }
+
+    pub(crate) fn with_svn(self, svn: Svn) -> ClockCheck {
+        ClockCheck {
+            svn: Some(svn),
+            ..self
+        }
+    }
+
+    /// Gives the SVN of the satellite whose GST-UTC parameters were used.
+    ///
+    /// This is `None` if the check was constructed with [`ClockCheck::new`].
+    pub fn svn(&self) -> Option<Svn> {
+        self.svn
+    }
+
+    /// Gives the GST that was compared against the host clock.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the divergence between the host clock and the GNSS time, in
+    /// seconds.
+    ///
+    /// The divergence is positive if the host clock is ahead of the GNSS time.
+    pub fn divergence(&self) -> f64 {
+        self.divergence
+    }
+
+    /// Gives the alarm threshold, in seconds.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Returns `true` if the divergence exceeds the alarm threshold.
+    pub fn is_alarm(&self) -> bool {
+        // NaN divergences (which can only happen if the host time is NaN)
+        // are also alarms
+        self.divergence.is_nan()
+            || self.divergence > self.threshold
+            || self.divergence < -self.threshold
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(delta_t_ls: i8, delta_t_lsf: i8) -> GstUtcParameters {
+        GstUtcParameters {
+            a0: 0,
+            a1: 0,
+            delta_t_ls,
+            t0t: 0,
+            wnot: 0,
+            wn_lsf: 0,
+            dn: 7,
+            delta_t_lsf,
+        }
+    }
+
+    #[test]
+    fn decode() {
+        let mut data = bitarr![u8, Msb0; 0; 141];
+        data[..32].store_be(-1024i32);
+        data[32..56].store_be(3i32);
+        data[56..64].store_be(18u8);
+        data[64..72].store_be(24u8);
+        data[72..80].store_be(136u8);
+        data[80..88].store_be(137u8);
+        data[88..91].store_be(7u8);
+        data[91..99].store_be(18u8);
+        let params = GstUtcParameters::from_timing_parameters(&data[..]);
+        assert_eq!(params.a0(), -1024.0 / (1u64 << 30) as f64);
+        assert_eq!(params.a1(), 3.0 / (1u64 << 50) as f64);
+        assert_eq!(params.delta_t_ls(), 18);
+        assert_eq!(params.t0t(), 86400);
+        assert_eq!(params.wnot(), 136);
+        assert_eq!(params.wn_lsf(), 137);
+        assert_eq!(params.dn(), 7);
+        assert_eq!(params.delta_t_lsf(), 18);
+    }
+
+    #[test]
+    fn unix_utc() {
+        // 2022-03-07 09:38:00 UTC is WN 1176 TOW 121098 in GST
+        let params = params(18, 18);
+        let gst = Gst::new(1176, 121098);
+        assert_eq!(params.gst_to_unix_utc(gst), 1646645880.0);
+        let check = ClockCheck::new(&params, gst, 1646645881.5, 2.0);
+        assert_eq!(check.divergence(), 1.5);
+        assert!(!check.is_alarm());
+        let check = ClockCheck::new(&params, gst, 1646645877.0, 2.0);
+        assert!(check.is_alarm());
+    }
+
+    #[test]
+    fn leap_second() {
+        // Leap second at the end of week 256 (WN 0 modulo 256)
+        let params = params(18, 19);
+        assert_eq!(params.utc_offset(Gst::new(256, 7 * 86400 - 1)), 18.0);
+        assert_eq!(params.utc_offset(Gst::new(257, 0)), 19.0);
+        assert_eq!(params.utc_offset(Gst::new(255, 0)), 18.0);
+    }
+}
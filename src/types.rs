@@ -3,6 +3,8 @@
 //! This module contains some types that are used throughout the galileo_osnma
 //! crate.
 
+use crate::{Gst, Svn};
+
 /// Size in bytes of the HKROOT section of an OSNMA message.
 pub const HKROOT_SECTION_BYTES: usize = 1;
 /// Size in bytes of the MACK section of an OSNMA message.
@@ -52,6 +54,203 @@ pub const DSM_BLOCK_BYTES: usize = 13;
 /// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
 pub type DsmBlock = [u8; DSM_BLOCK_BYTES];
 
+/// Minimum number of blocks in a DSM-KROOT.
+pub const MIN_DSM_KROOT_BLOCKS: usize = 7;
+/// Maximum number of blocks in a DSM-KROOT.
+pub const MAX_DSM_KROOT_BLOCKS: usize = 14;
+/// Minimum number of blocks in a DSM-PKR.
+pub const MIN_DSM_PKR_BLOCKS: usize = 13;
+/// Maximum number of blocks in a DSM-PKR.
+pub const MAX_DSM_PKR_BLOCKS: usize = 16;
+/// Maximum number of blocks in a DSM of any type.
+pub const MAX_DSM_BLOCKS: usize = MAX_DSM_PKR_BLOCKS;
+
+/// Gives the number of blocks of a DSM-KROOT.
+///
+/// The number is computed according to the value of the NB_DK field and
+/// Table 7 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+///
+/// If `nb_dk` is a reserved value, `None` is returned.
+pub const fn dsm_kroot_blocks(nb_dk: u8) -> Option<usize> {
+    match nb_dk {
+        1..=8 => Some(nb_dk as usize + 6),
+        _ => None,
+    }
+}
+
+/// Gives the number of blocks of a DSM-PKR.
+///
+/// The number is computed according to the value of the NB_DP field and
+/// Table 3 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+///
+/// If `nb_dp` is a reserved value, `None` is returned.
+pub const fn dsm_pkr_blocks(nb_dp: u8) -> Option<usize> {
+    match nb_dp {
+        7..=10 => Some(nb_dp as usize + 6),
+        _ => None,
+    }
+}
+
+const _: () = assert!(matches!(dsm_kroot_blocks(1), Some(MIN_DSM_KROOT_BLOCKS)));
+const _: () = assert!(matches!(dsm_kroot_blocks(8), Some(MAX_DSM_KROOT_BLOCKS)));
+const _: () = assert!(matches!(dsm_pkr_blocks(7), Some(MIN_DSM_PKR_BLOCKS)));
+const _: () = assert!(matches!(dsm_pkr_blocks(10), Some(MAX_DSM_PKR_BLOCKS)));
+const _: () = assert!(MAX_DSM_KROOT_BLOCKS <= MAX_DSM_BLOCKS);
+
+/// TESLA key sizes in bits.
+///
+/// The element in position `n` is the key size corresponding to the value `n`
+/// of the KS field, according to Table 10 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+/// The values of the KS field that are not included are reserved.
+pub const KEY_SIZES_BITS: [usize; 9] = [96, 104, 112, 120, 128, 160, 192, 224, 256];
+/// Maximum TESLA key size in bits.
+pub const MAX_KEY_SIZE_BITS: usize = 256;
+/// Maximum TESLA key size in bytes.
+pub const MAX_KEY_SIZE_BYTES: usize = MAX_KEY_SIZE_BITS / 8;
+
+/// Gives the TESLA key size in bits.
+///
+/// The size corresponds to the value `ks` of the KS field. See
+/// [`KEY_SIZES_BITS`]. If `ks` is a reserved value, `None` is returned.
+pub const fn key_size_bits(ks: u8) -> Option<usize> {
+    let ks = ks as usize;
+    if ks < KEY_SIZES_BITS.len() {
+        Some(KEY_SIZES_BITS[ks])
+    } else {
+        None
+    }
+}
+
+// All the TESLA key sizes are a whole number of bytes.
+const _: () = {
+    let mut j = 0;
+    while j < KEY_SIZES_BITS.len() {
+        assert!(KEY_SIZES_BITS[j] % 8 == 0);
+        assert!(KEY_SIZES_BITS[j] <= MAX_KEY_SIZE_BITS);
+        j += 1;
+    }
+};
+
+/// Tag sizes in bits.
+///
+/// The element in position `n` is the tag size corresponding to the value
+/// `n + 5` of the TS field, according to Table 11 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+/// The values of the TS field that are not included are reserved.
+pub const TAG_SIZES_BITS: [usize; 5] = [20, 24, 28, 32, 40];
+/// Maximum tag size in bits.
+pub const MAX_TAG_SIZE_BITS: usize = 40;
+
+// Value of the TS field corresponding to the first element of TAG_SIZES_BITS.
+const FIRST_TS: u8 = 5;
+
+/// Gives the tag size in bits.
+///
+/// The size corresponds to the value `ts` of the TS field. See
+/// [`TAG_SIZES_BITS`]. If `ts` is a reserved value, `None` is returned.
+pub const fn tag_size_bits(ts: u8) -> Option<usize> {
+    if ts < FIRST_TS {
+        return None;
+    }
+    let idx = (ts - FIRST_TS) as usize;
+    if idx < TAG_SIZES_BITS.len() {
+        Some(TAG_SIZES_BITS[idx])
+    } else {
+        None
+    }
+}
+
+const _: () = {
+    let mut j = 0;
+    while j < TAG_SIZES_BITS.len() {
+        assert!(TAG_SIZES_BITS[j] <= MAX_TAG_SIZE_BITS);
+        j += 1;
+    }
+};
+
+/// Number of bits in a MACK message.
+pub const MACK_MESSAGE_BITS: usize = 8 * MACK_MESSAGE_BYTES;
+/// Number of bits in the Tag-Info field of a MACK message, or in the MACSEQ
+/// and COP fields of the MACK header.
+pub const TAG_INFO_BITS: usize = 16;
+
+/// Layout of a MACK message.
+///
+/// The layout of a MACK message is determined by the TESLA key size and the
+/// tag size. The MACK message contains the MACK header (tag0, MACSEQ and COP),
+/// followed by as many tags and Tag-Info fields as fit, the TESLA key, and
+/// padding. See Figure 8 in the
+/// [OSNMA SIS ICD v1.1](https://www.gsc-europa.eu/sites/default/files/sites/all/files/Galileo_OSNMA_SIS_ICD_v1.1.pdf).
+///
+/// # Examples
+///
+/// ```
+/// use galileo_osnma::types::MackLayout;
+///
+/// let layout = MackLayout::from_ks_ts(4, 9).unwrap();
+/// assert_eq!(layout.key_size(), 128);
+/// assert_eq!(layout.tag_size(), 40);
+/// assert_eq!(layout.num_tags(), 6);
+/// assert_eq!(layout.key_start(), 336);
+/// assert_eq!(layout.padding_bits(), 16);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MackLayout {
+    key_size: usize,
+    tag_size: usize,
+}
+
+impl MackLayout {
+    /// Constructs the layout for a key size and tag size given in bits.
+    pub const fn new(key_size: usize, tag_size: usize) -> MackLayout {
+        MackLayout { key_size, tag_size }
+    }
+
+    /// Constructs the layout for the values of the KS and TS fields.
+    ///
+    /// If any of the fields is a reserved value, `None` is returned.
+    pub const fn from_ks_ts(ks: u8, ts: u8) -> Option<MackLayout> {
+        match (key_size_bits(ks), tag_size_bits(ts)) {
+            (Some(key_size), Some(tag_size)) => Some(MackLayout::new(key_size, tag_size)),
+            _ => None,
+        }
+    }
+
+    /// Gives the TESLA key size in bits.
+    pub const fn key_size(&self) -> usize {
+        self.key_size
+    }
+
+    /// Gives the tag size in bits.
+    pub const fn tag_size(&self) -> usize {
+        self.tag_size
+    }
+
+    /// Gives the number of tags in the MACK message.
+    ///
+    /// This includes the tag0 field in the MACK header.
+    pub const fn num_tags(&self) -> usize {
+        MACK_MESSAGE_BITS.saturating_sub(self.key_size) / (self.tag_size + TAG_INFO_BITS)
+    }
+
+    /// Gives the position in bits of the Key field in the MACK message.
+    pub const fn key_start(&self) -> usize {
+        (self.tag_size + TAG_INFO_BITS) * self.num_tags()
+    }
+
+    /// Gives the number of padding bits at the end of the MACK message.
+    pub const fn padding_bits(&self) -> usize {
+        MACK_MESSAGE_BITS.saturating_sub(self.key_start() + self.key_size)
+    }
+}
+
+// Every valid combination of key size and tag size leaves room for the MACK
+// header and at least one tag.
+const _: () = assert!(MackLayout::new(MAX_KEY_SIZE_BITS, MAX_TAG_SIZE_BITS).num_tags() >= 2);
+
 /// Size of a Merkle tree node in bytes.
 pub const MERKLE_TREE_NODE_BYTES: usize = 32;
 /// Merkle tree node.
@@ -68,6 +267,112 @@ pub type MerkleTreeNode = [u8; MERKLE_TREE_NODE_BYTES];
 /// ordering.
 pub type BitSlice = bitvec::slice::BitSlice<u8, bitvec::order::Msb0>;
 
+/// Fixed-length window of bits.
+///
+/// This wraps a [`BitSlice`] that is known to be exactly `N` bits long. It is
+/// used to represent navigation data with a fixed size, so that passing data
+/// with the wrong length to a function that validates tags is a compile-time
+/// error rather than a failed tag.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BitWindow<'a, const N: usize>(&'a BitSlice);
+
+impl<'a, const N: usize> BitWindow<'a, N> {
+    /// Length of the window in bits.
+    pub const LEN: usize = N;
+
+    /// Constructs a window from a slice of bits.
+    ///
+    /// This returns `None` if the length of `bits` is not `N`.
+    pub fn new(bits: &'a BitSlice) -> Option<BitWindow<'a, N>> {
+        if bits.len() == N {
+            Some(BitWindow(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Gives the bits in the window.
+    pub fn as_bitslice(&self) -> &'a BitSlice {
+        self.0
+    }
+}
+
+impl<const N: usize> core::ops::Deref for BitWindow<'_, N> {
+    type Target = BitSlice;
+
+    fn deref(&self) -> &BitSlice {
+        self.0
+    }
+}
+
+/// Number of bits of ephemeris, clock and status data.
+///
+/// This is the size of the navigation data authenticated by ADKD=0 and ADKD=12
+/// tags.
+pub const CED_AND_STATUS_BITS: usize = 549;
+/// Number of bits of timing parameters.
+///
+/// This is the size of the navigation data authenticated by ADKD=4 tags.
+pub const TIMING_PARAMETERS_BITS: usize = 141;
+
+/// Gives the number of bits of navigation data authenticated by an ADKD.
+///
+/// If `adkd` is a reserved value, `None` is returned.
+pub const fn navdata_bits(adkd: u8) -> Option<usize> {
+    match adkd {
+        0 | 12 => Some(CED_AND_STATUS_BITS),
+        4 => Some(TIMING_PARAMETERS_BITS),
+        _ => None,
+    }
+}
+
+/// Ephemeris, clock and status data bits.
+///
+/// These are the 549 bits of navigation data authenticated by ADKD=0 and
+/// ADKD=12 tags, formed by concatenating the relevant fields of INAV words 1
+/// to 5.
+pub type CedBits<'a> = BitWindow<'a, CED_AND_STATUS_BITS>;
+
+/// Timing parameters bits.
+///
+/// These are the 141 bits of navigation data authenticated by ADKD=4 tags,
+/// formed by concatenating the relevant fields of INAV words 6 and 10.
+pub type TimingBits<'a> = BitWindow<'a, TIMING_PARAMETERS_BITS>;
+
+/// Navigation data bits authenticated by a tag.
+///
+/// This can hold either [`CedBits`] or [`TimingBits`], which can be converted
+/// into `NavDataBits` with [`From`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NavDataBits<'a> {
+    /// Ephemeris, clock and status data (ADKD=0 and 12).
+    CedAndStatus(CedBits<'a>),
+    /// Timing parameters (ADKD=4).
+    TimingParameters(TimingBits<'a>),
+}
+
+impl<'a> NavDataBits<'a> {
+    /// Gives the navigation data bits.
+    pub fn as_bitslice(&self) -> &'a BitSlice {
+        match self {
+            NavDataBits::CedAndStatus(bits) => bits.as_bitslice(),
+            NavDataBits::TimingParameters(bits) => bits.as_bitslice(),
+        }
+    }
+}
+
+impl<'a> From<CedBits<'a>> for NavDataBits<'a> {
+    fn from(bits: CedBits<'a>) -> NavDataBits<'a> {
+        NavDataBits::CedAndStatus(bits)
+    }
+}
+
+impl<'a> From<TimingBits<'a>> for NavDataBits<'a> {
+    fn from(bits: TimingBits<'a>) -> NavDataBits<'a> {
+        NavDataBits::TimingParameters(bits)
+    }
+}
+
 /// Number of bytes in an INAV word.
 pub const INAV_WORD_BYTES: usize = 16;
 /// INAV word.
@@ -78,8 +383,15 @@ pub const INAV_WORD_BYTES: usize = 16;
 pub type InavWord = [u8; INAV_WORD_BYTES];
 
 /// The number of SVNs in the Galileo constellation.
+///
+/// All the per-satellite data in this crate is sized according to this
+/// constant.
 pub const NUM_SVNS: usize = 36;
 
+// SVNs are stored in a u8, and the PRND value 255 is used for Galileo
+// constellation-related information.
+const _: () = assert!(NUM_SVNS < 255);
+
 /// The time of week given in hours, as an 8 bit integer.
 ///
 /// This is used in the DSM-KROOT message.
@@ -97,6 +409,70 @@ pub enum InavBand {
     E5B,
 }
 
+impl InavBand {
+    /// Gives the GST of the E1-B page that is aligned with an INAV page.
+    ///
+    /// The INAV pages are transmitted simultaneously in E1-B and E5b-I, but
+    /// with a 1 second offset: the even part of an E5b-I page is transmitted
+    /// together with the odd part of the previous E1-B page. Therefore, each
+    /// E5b-I page starts 1 second before the E1-B page in the same position in
+    /// the subframe, and the first E5b-I page of a subframe starts in the last
+    /// second of the previous subframe.
+    ///
+    /// Given the GST `gst` at the start of a page received in this band, this
+    /// returns the GST at the start of the E1-B page in the same position in
+    /// the subframe. This GST should be used to determine the subframe and the
+    /// position within the subframe of the page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use galileo_osnma::{Gst, InavBand};
+    ///
+    /// let gst = Gst::new(1177, 175769);
+    /// assert_eq!(InavBand::E1B.e1b_page_gst(gst), gst);
+    /// assert_eq!(InavBand::E5B.e1b_page_gst(gst), Gst::new(1177, 175770));
+    /// ```
+    pub fn e1b_page_gst(&self, gst: Gst) -> Gst {
+        match self {
+            InavBand::E1B => gst,
+            InavBand::E5B => gst.wrapping_add_seconds(1),
+        }
+    }
+}
+
+impl core::fmt::Display for InavBand {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InavBand::E1B => "E1-B",
+            InavBand::E5B => "E5b-I",
+        }
+        .fmt(f)
+    }
+}
+
+/// INAV page.
+///
+/// This groups the INAV word and the OSNMA data message of an INAV page
+/// together with the satellite, GST and band in which they were received. It is
+/// used to feed data in batches with [`Osnma::feed_batch`](crate::Osnma::feed_batch).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct InavPage<'a> {
+    /// INAV word.
+    pub word: &'a InavWord,
+    /// OSNMA data message.
+    ///
+    /// This is `None` if the page does not contain OSNMA data (for instance,
+    /// if it has been received in E5b and only the E1B OSNMA data is used).
+    pub osnma: Option<&'a OsnmaDataMessage>,
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the page transmission.
+    pub gst: Gst,
+    /// Band in which the page was received.
+    pub band: InavBand,
+}
+
 /// ECDSA verifying key.
 ///
 /// This enum is either a P256 ECDSA key or a P521 ECDSA key.
@@ -134,3 +510,50 @@ impl core::fmt::Debug for VerifyingKey {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bit_window() {
+        let data = [0xa5; 70];
+        let bits = BitSlice::from_slice(&data);
+        assert!(CedBits::new(bits).is_none());
+        assert!(CedBits::new(&bits[..TimingBits::LEN]).is_none());
+        let ced = CedBits::new(&bits[..CedBits::LEN]).unwrap();
+        assert_eq!(ced.len(), 549);
+        let timing = TimingBits::new(&bits[..TimingBits::LEN]).unwrap();
+        assert_eq!(NavDataBits::from(timing).as_bitslice(), &bits[..141]);
+        assert_ne!(NavDataBits::from(ced), NavDataBits::from(timing));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(key_size_bits(0), Some(96));
+        assert_eq!(key_size_bits(8), Some(256));
+        assert_eq!(key_size_bits(9), None);
+        assert_eq!(tag_size_bits(4), None);
+        assert_eq!(tag_size_bits(5), Some(20));
+        assert_eq!(tag_size_bits(9), Some(40));
+        assert_eq!(tag_size_bits(10), None);
+        assert_eq!(dsm_kroot_blocks(0), None);
+        assert_eq!(dsm_kroot_blocks(3), Some(9));
+        assert_eq!(dsm_pkr_blocks(6), None);
+        assert_eq!(dsm_pkr_blocks(9), Some(15));
+        assert_eq!(navdata_bits(12), Some(CED_AND_STATUS_BITS));
+        assert_eq!(navdata_bits(4), Some(TIMING_PARAMETERS_BITS));
+        assert_eq!(navdata_bits(1), None);
+        assert_eq!(MackLayout::from_ks_ts(9, 9), None);
+        for ks in KEY_SIZES_BITS {
+            for ts in TAG_SIZES_BITS {
+                let layout = MackLayout::new(ks, ts);
+                assert_eq!(
+                    layout.key_start() + ks + layout.padding_bits(),
+                    MACK_MESSAGE_BITS
+                );
+                assert!(layout.padding_bits() < ts + TAG_INFO_BITS);
+            }
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! u-blox UBX protocol front-end.
+//!
+//! This module converts the Galileo I/NAV data given by u-blox receivers in
+//! the UBX protocol into INAV words and OSNMA data messages that can be fed
+//! into the [`Osnma`] black box. It is intended to be used together with a UBX
+//! parser such as the [ublox](https://crates.io/crates/ublox) crate. To avoid
+//! tying this crate to a particular version of the parser, the
+//! [`UbxFrontEnd`] takes the values of the fields of the relevant UBX
+//! messages rather than the message types of the parser.
+//!
+//! Two UBX messages are needed:
+//!
+//! * UBX-NAV-TIMEGAL, which gives the Galileo System Time of each navigation
+//!   epoch. It is processed with [`UbxFrontEnd::nav_timegal`].
+//! * UBX-RXM-SFRBX, which gives the navigation data of each I/NAV page (an
+//!   even page and an odd page) as eight 32-bit words. It is processed with
+//!   [`UbxFrontEnd::rxm_sfrbx`].
+//!
+//! The RXM-SFRBX message does not include a timestamp, so the GST at the start
+//! of the page is computed from the last GST given by NAV-TIMEGAL. The page
+//! is assumed to have ended at most 1 second before the navigation epoch of
+//! the last NAV-TIMEGAL message, which is the case when the receiver is
+//! configured to output both messages and they are processed in the order in
+//! which they are received. The start of the page is then determined by the
+//! page timing of the band in which it was received (see
+//! [`InavBand::e1b_page_gst`]).
+//!
+//! The CRC of each page is checked, and pages with a wrong CRC, Alert Pages
+//! and Dummy Messages are discarded.
+//!
+//! When this crate is built with the `ublox` feature, the front-end can also
+//! take the message types of the ublox crate, using the
+//! `UbxFrontEnd::rxm_sfrbx_packet`, `UbxFrontEnd::nav_timegps_packet` and
+//! `UbxFrontEnd::packet` methods. The ublox crate does not implement NAV-TIMEGAL, so
+//! the GST is obtained from NAV-TIMEGPS instead. The GST and GPS time are
+//! steered to the same time scale, and the Galileo week number is the GPS
+//! week number minus 1024.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::ubx::UbxFrontEnd;
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false);
+//! let mut ubx = UbxFrontEnd::new();
+//!
+//! // Fields of a NAV-TIMEGAL message: galTow, galWno and valid
+//! ubx.nav_timegal(175770, 1177, 0x03);
+//! assert!(ubx.gst().is_some());
+//!
+//! // Fields of an RXM-SFRBX message: gnssId, svId, sigId and dwrd
+//! let dwrd = [0; 8];
+//! if let Some(page) = ubx.rxm_sfrbx(2, 11, 1, &dwrd) {
+//!     page.feed(&mut osnma);
+//! }
+//! ```
+
+use crate::inav::InavWordType;
+use crate::storage::Storage;
+use crate::types::{BitSlice, InavPage, InavWord, OsnmaDataMessage, INAV_WORD_BYTES};
+use crate::verifier::Verifier;
+use crate::{Gst, InavBand, Osnma, Svn, Tow, Wn};
+use bitvec::prelude::*;
+
+/// GNSS identifier of Galileo in the UBX protocol.
+pub const GNSS_ID_GALILEO: u8 = 2;
+/// Signal identifier of Galileo E1-B in the UBX protocol.
+pub const SIG_ID_E1B: u8 = 1;
+/// Signal identifier of Galileo E5b-I in the UBX protocol.
+pub const SIG_ID_E5BI: u8 = 5;
+/// Number of 32-bit words in an RXM-SFRBX message with Galileo I/NAV data.
+pub const INAV_SFRBX_WORDS: usize = 8;
+
+const SECS_IN_WEEK: Tow = 604800;
+// Difference between the GPS week number and the Galileo week number
+#[cfg(feature = "ublox")]
+const GPS_WN_OFFSET: i64 = 1024;
+// Maximum number of data words in an RXM-SFRBX message
+#[cfg(feature = "ublox")]
+const SFRBX_MAX_WORDS: usize = 16;
+const SECONDS_PER_PAGE: i32 = 2;
+// Bits in an I/NAV page part, excluding padding
+const PAGE_PART_BITS: usize = 120;
+// Bits of a page part in the RXM-SFRBX message, including padding
+const PAGE_PART_SFRBX_BITS: usize = 128;
+// The CRC covers the even page part and the odd page part up to the CRC field
+const CRC_EVEN_BITS: usize = 114;
+const CRC_ODD_BITS: usize = 82;
+const CRC_BITS: usize = 24;
+const CRC24Q_POLY: u32 = 0x86_4cfb;
+
+/// u-blox UBX front-end.
+///
+/// This keeps track of the GST given by NAV-TIMEGAL messages and extracts the
+/// Galileo I/NAV pages from RXM-SFRBX messages. See the [module
+/// documentation](crate::ubx).
+#[derive(Debug, Clone, Default)]
+pub struct UbxFrontEnd {
+    gst: Option<Gst>,
+}
+
+impl UbxFrontEnd {
+    /// Constructs a new front-end.
+    ///
+    /// RXM-SFRBX messages are discarded until a NAV-TIMEGAL message with a
+    /// valid GST has been processed.
+    pub fn new() -> UbxFrontEnd {
+        UbxFrontEnd::default()
+    }
+
+    /// Gives the GST of the last navigation epoch.
+    ///
+    /// This is the GST given by the last NAV-TIMEGAL message, or `None` if no
+    /// NAV-TIMEGAL message with a valid GST has been processed.
+    pub fn gst(&self) -> Option<Gst> {
+        self.gst
+    }
+
+    /// Processes a NAV-TIMEGAL message.
+    ///
+    /// The parameters are the values of the `galTow`, `galWno` and `valid`
+    /// fields of the message. The GST is only used if the `valid` field
+    /// indicates that both `galTow` and `galWno` are valid.
+    pub fn nav_timegal(&mut self, gal_tow: u32, gal_wno: i16, valid: u8) {
+        let tow_valid = valid & 0x01 != 0;
+        let wno_valid = valid & 0x02 != 0;
+        if !(tow_valid && wno_valid) {
+            log::debug!("NAV-TIMEGAL without valid GST (valid = {:#04x})", valid);
+            self.gst = None;
+            return;
+        }
+        self.set_gst(i64::from(gal_wno), gal_tow);
+    }
+
+    // Sets the GST from a week number and TOW, taking into account that a TOW
+    // equal to the number of seconds in a week can appear at the week rollover
+    fn set_gst(&mut self, wn: i64, tow: Tow) {
+        let Ok(gst_wn) = Wn::try_from(wn + i64::from(tow / SECS_IN_WEEK)) else {
+            log::error!("invalid GST week number {}", wn);
+            self.gst = None;
+            return;
+        };
+        self.gst = Some(Gst::new(gst_wn, tow % SECS_IN_WEEK));
+    }
+
+    /// Processes a NAV-TIMEGPS message of the ublox crate.
+    ///
+    /// The GST is obtained from the GPS week number and time of week of the
+    /// navigation epoch, rounded to the nearest second. The GST is only used
+    /// if the message indicates that both the week number and the time of week
+    /// are valid.
+    ///
+    /// This is only available with the `ublox` feature.
+    #[cfg(feature = "ublox")]
+    pub fn nav_timegps_packet(&mut self, packet: &ublox::nav_time_gps::NavTimeGpsRef<'_>) {
+        use ublox::nav_time_gps::NavTimeGpsFlags;
+        let valid = packet.valid();
+        if !valid.contains(NavTimeGpsFlags::VALID_TOW | NavTimeGpsFlags::VALID_WKN) {
+            log::debug!("NAV-TIMEGPS without valid GPS time (valid = {:?})", valid);
+            self.gst = None;
+            return;
+        }
+        let tow_ns = i64::from(packet.itow()) * 1_000_000 + i64::from(packet.ftow());
+        let Ok(tow) = Tow::try_from((tow_ns + 500_000_000).div_euclid(1_000_000_000)) else {
+            log::error!("invalid NAV-TIMEGPS time of week {} ns", tow_ns);
+            self.gst = None;
+            return;
+        };
+        self.set_gst(i64::from(packet.week()) - GPS_WN_OFFSET, tow);
+    }
+
+    /// Processes an RXM-SFRBX message.
+    ///
+    /// The parameters are the values of the `gnssId`, `svId` and `sigId`
+    /// fields of the message, and the data words (the `dwrd` fields).
+    ///
+    /// If the message contains a Galileo I/NAV page with a correct CRC, the
+    /// page is returned. Otherwise, or if the page should be discarded, this
+    /// returns `None`.
+    pub fn rxm_sfrbx(
+        &mut self,
+        gnss_id: u8,
+        sv_id: u8,
+        sig_id: u8,
+        dwrd: &[u32],
+    ) -> Option<UbxPage> {
+        if gnss_id != GNSS_ID_GALILEO {
+            return None;
+        }
+        let band = match sig_id {
+            SIG_ID_E1B => InavBand::E1B,
+            SIG_ID_E5BI => InavBand::E5B,
+            // F/NAV in E5a
+            _ => return None,
+        };
+        let Ok(dwrd) = <&[u32; INAV_SFRBX_WORDS]>::try_from(dwrd) else {
+            log::error!(
+                "RXM-SFRBX with I/NAV data has wrong number of words {}",
+                dwrd.len()
+            );
+            return None;
+        };
+        let Ok(svn) = Svn::try_from(sv_id) else {
+            log::error!("invalid RXM-SFRBX SVN {}", sv_id);
+            return None;
+        };
+        let Some(gst) = self.gst else {
+            log::debug!("discarding RXM-SFRBX for {} {}: GST unknown", svn, band);
+            return None;
+        };
+        let gst = page_start(gst, band);
+
+        let mut bytes = [0; 4 * INAV_SFRBX_WORDS];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(dwrd.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        let bits = BitSlice::from_slice(&bytes);
+        let even = &bits[..PAGE_PART_BITS];
+        let odd = &bits[PAGE_PART_SFRBX_BITS..PAGE_PART_SFRBX_BITS + PAGE_PART_BITS];
+        let (word, osnma) = extract_page(even, odd, svn, gst, band)?;
+
+        Some(UbxPage {
+            word,
+            osnma,
+            svn,
+            gst,
+            band,
+        })
+    }
+
+    /// Processes an RXM-SFRBX message of the ublox crate.
+    ///
+    /// This behaves as [`UbxFrontEnd::rxm_sfrbx`]. The `sigId` field is
+    /// given by the `reserved1` field of the ublox crate message.
+    ///
+    /// This is only available with the `ublox` feature.
+    #[cfg(feature = "ublox")]
+    pub fn rxm_sfrbx_packet(
+        &mut self,
+        packet: &ublox::rxm_sfrbx::RxmSfrbxRef<'_>,
+    ) -> Option<UbxPage> {
+        let mut dwrd = [0; SFRBX_MAX_WORDS];
+        let mut num_words = 0;
+        for (d, word) in dwrd.iter_mut().zip(packet.dwrd()) {
+            *d = word;
+            num_words += 1;
+        }
+        self.rxm_sfrbx(
+            packet.gnss_id(),
+            packet.sv_id(),
+            packet.reserved1(),
+            &dwrd[..num_words],
+        )
+    }
+
+    /// Processes a UBX packet of the ublox crate.
+    ///
+    /// RXM-SFRBX packets are processed with [`UbxFrontEnd::rxm_sfrbx_packet`]
+    /// and NAV-TIMEGPS packets are processed with
+    /// [`UbxFrontEnd::nav_timegps_packet`]. Other packets are ignored. If the
+    /// packet contains a Galileo I/NAV page with a correct CRC, the page is
+    /// returned.
+    ///
+    /// This is only available with the `ublox` feature.
+    #[cfg(feature = "ublox")]
+    pub fn packet(&mut self, packet: &ublox::proto23::PacketRef<'_>) -> Option<UbxPage> {
+        use ublox::proto23::PacketRef;
+        match packet {
+            PacketRef::RxmSfrbx(sfrbx) => self.rxm_sfrbx_packet(sfrbx),
+            PacketRef::NavTimeGps(timegps) => {
+                self.nav_timegps_packet(timegps);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+// Extracts the INAV word and OSNMA data message of an I/NAV page from its
+// even and odd page parts. This checks the CRC and discards Alert Pages and
+// Dummy Messages. This is also used by the SBF front-end.
+pub(crate) fn extract_page(
+    even: &BitSlice,
+    odd: &BitSlice,
+    svn: Svn,
+    gst: Gst,
+    band: InavBand,
+) -> Option<(InavWord, Option<OsnmaDataMessage>)> {
+    if even[0] || !odd[0] {
+        log::error!(
+            "I/NAV data for {} {} at {:?} does not contain an even and an odd page part",
+            svn,
+            band,
+            gst
+        );
+        return None;
+    }
+    let crc = odd[CRC_ODD_BITS..CRC_ODD_BITS + CRC_BITS].load_be::<u32>();
+    if crc24q(
+        even[..CRC_EVEN_BITS]
+            .iter()
+            .by_vals()
+            .chain(odd[..CRC_ODD_BITS].iter().by_vals()),
+    ) != crc
+    {
+        log::warn!("wrong CRC in I/NAV page for {} {} at {:?}", svn, band, gst);
+        return None;
+    }
+    if even[1] || odd[1] {
+        log::debug!("discarding Alert Page for {} {} at {:?}", svn, band, gst);
+        return None;
+    }
+
+    // The INAV word is formed by the 112 bits of Data (even) and the 16
+    // bits of Data (odd)
+    let mut word = [0; INAV_WORD_BYTES];
+    let word_bits = BitSlice::from_slice_mut(&mut word);
+    word_bits[..112].copy_from_bitslice(&even[2..114]);
+    word_bits[112..].copy_from_bitslice(&odd[2..18]);
+    if InavWordType::of(&word) == InavWordType::Dummy {
+        log::debug!(
+            "discarding dummy INAV word from {} {} at {:?}",
+            svn,
+            band,
+            gst
+        );
+        return None;
+    }
+
+    // OSNMA data is only transmitted in the Reserved 1 field of E1-B
+    let osnma = match band {
+        InavBand::E1B => {
+            let mut osnma = [0; 5];
+            BitSlice::from_slice_mut(&mut osnma).copy_from_bitslice(&odd[18..58]);
+            Some(osnma)
+        }
+        InavBand::E5B => None,
+    };
+    Some((word, osnma))
+}
+
+// Computes the GST at the start of a page from the GST of the navigation epoch
+// following its end.
+pub(crate) fn page_start(gst: Gst, band: InavBand) -> Gst {
+    let gst = gst.wrapping_add_seconds(-SECONDS_PER_PAGE);
+    // E1-B pages start at an even TOW, and E5b-I pages start at an odd TOW
+    let parity = match band {
+        InavBand::E1B => 0,
+        InavBand::E5B => 1,
+    };
+    // the number of seconds in a week is even, so this works across week
+    // rollovers
+    let misalignment = (gst.tow() + parity) % 2;
+    gst.wrapping_add_seconds(-(misalignment as i32))
+}
+
+// CRC-24Q used by Galileo I/NAV
+pub(crate) fn crc24q(bits: impl Iterator<Item = bool>) -> u32 {
+    let mut crc = 0;
+    for bit in bits {
+        let feedback = (crc >> 23) & 1 == 1;
+        crc = (crc << 1) & 0xff_ffff;
+        if feedback != bit {
+            crc ^= CRC24Q_POLY;
+        }
+    }
+    crc
+}
+
+/// Galileo I/NAV page extracted from UBX data.
+///
+/// This is produced by [`UbxFrontEnd::rxm_sfrbx`]. It owns the INAV word and
+/// OSNMA data message of the page.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UbxPage {
+    /// INAV word.
+    pub word: InavWord,
+    /// OSNMA data message.
+    ///
+    /// This is `None` for pages received in E5b-I, which do not carry OSNMA
+    /// data.
+    pub osnma: Option<OsnmaDataMessage>,
+    /// SVN of the satellite that transmitted the page.
+    pub svn: Svn,
+    /// GST at the start of the page transmission.
+    pub gst: Gst,
+    /// Band in which the page was received.
+    pub band: InavBand,
+}
+
+impl UbxPage {
+    /// Gives the page as an [`InavPage`].
+    ///
+    /// This can be used to feed pages in batches with
+    /// [`Osnma::feed_batch`]. Note that [`Osnma::feed_batch`] does not mark
+    /// the INAV words as CRC checked.
+    pub fn as_inav_page(&self) -> InavPage<'_> {
+        InavPage {
+            word: &self.word,
+            osnma: self.osnma.as_ref(),
+            svn: self.svn,
+            gst: self.gst,
+            band: self.band,
+        }
+    }
+
+    /// Feeds the page into an [`Osnma`] black box.
+    ///
+    /// The INAV word is fed with [`Osnma::feed_inav_crc_checked`], since the
+    /// CRC of the page has been checked, and the OSNMA data message, if
+    /// present, is fed with [`Osnma::feed_osnma_band`].
+    pub fn feed<S: Storage, V: Verifier>(&self, osnma: &mut Osnma<S, V>) {
+        osnma.feed_inav_crc_checked(&self.word, self.svn, self.gst, self.band);
+        if let Some(data) = &self.osnma {
+            osnma.feed_osnma_band(data, self.svn, self.gst, self.band);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Builds the data words of an RXM-SFRBX message containing a page with a
+    // correct CRC
+    fn sfrbx(word: &InavWord, osnma: &OsnmaDataMessage) -> [u32; INAV_SFRBX_WORDS] {
+        let mut bytes = [0; 4 * INAV_SFRBX_WORDS];
+        let bits = BitSlice::from_slice_mut(&mut bytes);
+        let word = BitSlice::from_slice(word);
+        bits[2..114].copy_from_bitslice(&word[..112]);
+        let odd = &mut bits[PAGE_PART_SFRBX_BITS..];
+        odd.set(0, true);
+        odd[2..18].copy_from_bitslice(&word[112..]);
+        odd[18..58].copy_from_bitslice(BitSlice::from_slice(osnma));
+        let bits = BitSlice::from_slice(&bytes);
+        let crc = crc24q(
+            bits[..CRC_EVEN_BITS].iter().by_vals().chain(
+                bits[PAGE_PART_SFRBX_BITS..][..CRC_ODD_BITS]
+                    .iter()
+                    .by_vals(),
+            ),
+        );
+        BitSlice::from_slice_mut(&mut bytes)[PAGE_PART_SFRBX_BITS..][82..106].store_be(crc);
+        let mut dwrd = [0; INAV_SFRBX_WORDS];
+        for (d, chunk) in dwrd.iter_mut().zip(bytes.chunks_exact(4)) {
+            *d = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        dwrd
+    }
+
+    #[test]
+    fn crc() {
+        // Check value of CRC-24Q
+        let bits = b"123456789";
+        assert_eq!(
+            crc24q(BitSlice::from_slice(bits).iter().by_vals()),
+            0xcd_e703
+        );
+    }
+
+    #[test]
+    fn page_timing() {
+        let gst = Gst::new(1177, 175772);
+        assert_eq!(page_start(gst, InavBand::E1B), Gst::new(1177, 175770));
+        assert_eq!(
+            page_start(gst.add_seconds(1), InavBand::E1B),
+            Gst::new(1177, 175770)
+        );
+        assert_eq!(page_start(gst, InavBand::E5B), Gst::new(1177, 175769));
+        assert_eq!(
+            page_start(gst.add_seconds(-1), InavBand::E5B),
+            Gst::new(1177, 175769)
+        );
+        assert_eq!(
+            page_start(Gst::new(1178, 1), InavBand::E1B),
+            Gst::new(1177, 604798)
+        );
+    }
+
+    #[test]
+    fn front_end() {
+        let mut ubx = UbxFrontEnd::new();
+        // word type 4
+        let mut word = [0x55; INAV_WORD_BYTES];
+        word[0] = 4 << 2;
+        let osnma = [1, 2, 3, 4, 5];
+        let dwrd = sfrbx(&word, &osnma);
+        // No GST yet
+        assert_eq!(ubx.rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dwrd), None);
+        ubx.nav_timegal(604800, 1176, 0x07);
+        assert_eq!(ubx.gst(), Some(Gst::new(1177, 0)));
+        ubx.nav_timegal(175773, 1177, 0x01);
+        assert_eq!(ubx.gst(), None);
+        ubx.nav_timegal(175773, 1177, 0x03);
+
+        let svn = Svn::try_from(11).unwrap();
+        let page = ubx
+            .rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dwrd)
+            .unwrap();
+        assert_eq!(
+            page,
+            UbxPage {
+                word,
+                osnma: Some(osnma),
+                svn,
+                gst: Gst::new(1177, 175770),
+                band: InavBand::E1B,
+            }
+        );
+        let page = ubx
+            .rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E5BI, &dwrd)
+            .unwrap();
+        assert_eq!(page.osnma, None);
+        assert_eq!(page.gst, Gst::new(1177, 175771));
+        assert_eq!(page.as_inav_page().word, &word);
+
+        // Other GNSS and signals are ignored
+        assert_eq!(ubx.rxm_sfrbx(0, 11, SIG_ID_E1B, &dwrd), None);
+        assert_eq!(ubx.rxm_sfrbx(GNSS_ID_GALILEO, 11, 3, &dwrd), None);
+        // Wrong number of words
+        assert_eq!(
+            ubx.rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dwrd[..4]),
+            None
+        );
+        // Wrong CRC
+        let mut bad = dwrd;
+        bad[1] ^= 1;
+        assert_eq!(ubx.rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &bad), None);
+        // Dummy message
+        let dummy = sfrbx(&[0xff; INAV_WORD_BYTES], &osnma);
+        assert_eq!(ubx.rxm_sfrbx(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dummy), None);
+    }
+
+    // Builds a UBX frame
+    #[cfg(feature = "ublox")]
+    fn ubx_frame(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xb5, 0x62, class, id];
+        frame.extend_from_slice(&u16::try_from(payload.len()).unwrap().to_le_bytes());
+        frame.extend_from_slice(payload);
+        let (mut ck_a, mut ck_b) = (0u8, 0u8);
+        for &b in &frame[2..] {
+            ck_a = ck_a.wrapping_add(b);
+            ck_b = ck_b.wrapping_add(ck_a);
+        }
+        frame.extend_from_slice(&[ck_a, ck_b]);
+        frame
+    }
+
+    #[cfg(feature = "ublox")]
+    fn nav_timegps_frame(itow: u32, ftow: i32, week: i16, valid: u8) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&itow.to_le_bytes());
+        payload.extend_from_slice(&ftow.to_le_bytes());
+        payload.extend_from_slice(&week.to_le_bytes());
+        payload.extend_from_slice(&[18, valid]);
+        payload.extend_from_slice(&20u32.to_le_bytes());
+        ubx_frame(0x01, 0x20, &payload)
+    }
+
+    #[cfg(feature = "ublox")]
+    fn rxm_sfrbx_frame(gnss_id: u8, sv_id: u8, sig_id: u8, dwrd: &[u32]) -> Vec<u8> {
+        let num_words = u8::try_from(dwrd.len()).unwrap();
+        let mut payload = vec![gnss_id, sv_id, sig_id, 0, num_words, 0, 2, 0];
+        for word in dwrd {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+        ubx_frame(0x02, 0x13, &payload)
+    }
+
+    #[cfg(feature = "ublox")]
+    #[test]
+    fn ublox_packets() {
+        use ublox::proto23::Proto23;
+        use ublox::{FixedBuffer, Parser, UbxPacket};
+
+        let mut parser = Parser::<FixedBuffer<256>, Proto23>::with_fixed_buffer();
+        let mut ubx = UbxFrontEnd::new();
+        let mut feed = |frame: &[u8]| {
+            let mut it = parser.consume_ubx(frame);
+            let Some(Ok(UbxPacket::Proto23(packet))) = it.next() else {
+                panic!("could not parse UBX frame");
+            };
+            let page = ubx.packet(&packet);
+            (page, ubx.gst())
+        };
+
+        let mut word = [0x55; INAV_WORD_BYTES];
+        word[0] = 4 << 2;
+        let osnma = [1, 2, 3, 4, 5];
+        let dwrd = sfrbx(&word, &osnma);
+        let svn = Svn::try_from(11).unwrap();
+
+        // No GST yet
+        let (page, _) = feed(&rxm_sfrbx_frame(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dwrd));
+        assert_eq!(page, None);
+        // GPS week 2201 is Galileo week 1177. The TOW is rounded to the
+        // nearest second.
+        let (_, gst) = feed(&nav_timegps_frame(175_772_999, 400_000, 2201, 0x07));
+        assert_eq!(gst, Some(Gst::new(1177, 175773)));
+        // Week number not valid
+        let (_, gst) = feed(&nav_timegps_frame(175_773_000, 0, 2201, 0x01));
+        assert_eq!(gst, None);
+        // TOW equal to the number of seconds in a week after rounding
+        let (_, gst) = feed(&nav_timegps_frame(604_799_999, 300_000, 2200, 0x03));
+        assert_eq!(gst, Some(Gst::new(1177, 0)));
+        feed(&nav_timegps_frame(175_773_000, 0, 2201, 0x03));
+
+        let (page, _) = feed(&rxm_sfrbx_frame(GNSS_ID_GALILEO, 11, SIG_ID_E1B, &dwrd));
+        assert_eq!(
+            page,
+            Some(UbxPage {
+                word,
+                osnma: Some(osnma),
+                svn,
+                gst: Gst::new(1177, 175770),
+                band: InavBand::E1B,
+            })
+        );
+        let (page, _) = feed(&rxm_sfrbx_frame(GNSS_ID_GALILEO, 11, SIG_ID_E5BI, &dwrd));
+        assert_eq!(page.unwrap().gst, Gst::new(1177, 175771));
+
+        // Other GNSS are ignored
+        let (page, _) = feed(&rxm_sfrbx_frame(0, 11, 0, &[0; 10]));
+        assert_eq!(page, None);
+        // Wrong number of words
+        let (page, _) = feed(&rxm_sfrbx_frame(
+            GNSS_ID_GALILEO,
+            11,
+            SIG_ID_E1B,
+            &dwrd[..4],
+        ));
+        assert_eq!(page, None);
+    }
+}
@@ -0,0 +1,862 @@
+//! Deferred verification of cryptographic data.
+//!
+//! The verification of the ECDSA signature of a DSM-KROOT message is by far
+//! the most expensive computation done by the [`Osnma`](crate::Osnma) black
+//! box. In a small microcontroller it can take hundreds of milliseconds, which
+//! may be unacceptable if [`Osnma::feed_osnma`](crate::Osnma::feed_osnma) is
+//! called from a high-priority task or an interrupt handler.
+//!
+//! This module contains the [`Verifier`] trait, which is used by `Osnma` to
+//! request these verifications, and two implementations:
+//!
+//! * [`ImmediateVerifier`], which performs the verification synchronously.
+//!   This is the verifier used by default.
+//!
+//! * [`QueuedVerifier`], which stores the verification request so that it can
+//!   be taken by the user with [`QueuedVerifier::take_request`] and run on
+//!   another task, typically with a lower priority. The result of the
+//!   verification is given back with [`QueuedVerifier::submit_result`] and it
+//!   is applied by `Osnma` the next time that a subframe is processed.
+//!
+//! The validation of TESLA keys, which requires computing the one-way function
+//! of the TESLA chain, is also requested through the [`Verifier`] trait. By
+//! default it is done synchronously, but [`CoprocessorVerifier`] queues
+//! [`KeyRequest`]s, so that the hashes can be computed asynchronously, for
+//! instance by a DMA-driven cryptographic co-processor in a microcontroller.
+//!
+//! The module also contains the [`KrootFailureTracker`], which is used by
+//! `Osnma` to detect repeated DSM-KROOT verification failures, which might be
+//! caused by an attacker transmitting forged DSM-KROOT messages in order to
+//! drain the CPU or battery of the receiver, and to rate-limit the
+//! verifications when this happens.
+//!
+//! # Examples
+//!
+//! ```
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::verifier::QueuedVerifier;
+//! use p256::ecdsa::VerifyingKey;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false)
+//!     .with_verifier(QueuedVerifier::new());
+//!
+//! // In the data path: feed data with osnma.feed_inav() and
+//! // osnma.feed_osnma(), and then check for pending verifications.
+//! if let Some(request) = osnma.verifier_mut().take_request() {
+//!     // Send the request to a lower priority task, which runs it.
+//!     let result = request.run();
+//!     // Give the result back. It will be applied by the next call to
+//!     // osnma.feed_osnma() that completes a subframe.
+//!     osnma.verifier_mut().submit_result(result);
+//! }
+//! ```
+//!
+//! The hashes of the TESLA one-way function can be computed outside of the
+//! [`Osnma`](crate::Osnma) black box as follows:
+//!
+//! ```
+//! use galileo_osnma::{Osnma, PublicKey};
+//! use galileo_osnma::storage::FullStorage;
+//! use galileo_osnma::tesla::HashFunction;
+//! use galileo_osnma::verifier::{CoprocessorVerifier, ImmediateVerifier};
+//! use p256::ecdsa::VerifyingKey;
+//! use sha2::{Digest, Sha256};
+//! use sha3::Sha3_256;
+//!
+//! # let pubkey = [3, 154, 36, 205, 5, 122, 110, 166, 187, 238, 33,
+//! #               117, 116, 91, 202, 57, 34, 72, 200, 202, 10, 169,
+//! #               253, 225, 1, 233, 82, 99, 133, 255, 241, 114, 218];
+//! # let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+//! # let pubkey = PublicKey::from_p256(pubkey, 0).force_valid();
+//! let mut osnma = Osnma::<FullStorage>::from_pubkey(pubkey, false)
+//!     .with_verifier(CoprocessorVerifier::new(ImmediateVerifier::new()));
+//!
+//! if let Some(mut request) = osnma.verifier_mut().take_key_request() {
+//!     // The hashes would be computed by the co-processor.
+//!     while let Some(message) = request.hash_message() {
+//!         let digest: [u8; 32] = match request.hash_function() {
+//!             HashFunction::Sha256 => Sha256::digest(message).into(),
+//!             HashFunction::Sha3_256 => Sha3_256::digest(message).into(),
+//!         };
+//!         request.submit_digest(&digest);
+//!     }
+//!     // The result will be applied by the next call to osnma.feed_osnma()
+//!     // that completes a subframe.
+//!     osnma.verifier_mut().submit_key_result(request.finish());
+//! }
+//! ```
+
+use crate::bitfields::{DsmKroot, NmaHeader};
+use crate::dsm::MAX_DSM_BYTES;
+use crate::tesla::{
+    HashFunction, Key, KrootValidationError, ValidationError, ONE_WAY_FUNCTION_MESSAGE_BYTES,
+};
+use crate::validation::{NotValidated, Validated};
+use crate::{Gst, PublicKey, Svn};
+
+/// Verifier of cryptographic data.
+///
+/// This trait is used by [`Osnma`](crate::Osnma) to verify the DSM-KROOT
+/// ECDSA signature. An implementation can either do the verification
+/// immediately, returning the result from [`Verifier::verify_kroot`], or defer
+/// it and return the result later from [`Verifier::poll`].
+///
+/// The trait is also used to validate TESLA keys, in the same way, with
+/// [`Verifier::verify_key`] and [`Verifier::poll_key`]. The default
+/// implementation of these methods validates the keys synchronously.
+pub trait Verifier {
+    /// Requests the verification of a DSM-KROOT.
+    ///
+    /// If the verification is done synchronously, its result is returned.
+    /// Otherwise, `None` is returned, and the result should be returned by
+    /// a later call to [`Verifier::poll`].
+    fn verify_kroot(&mut self, request: KrootRequest) -> Option<KrootResult>;
+
+    /// Returns the result of a deferred verification, if any is available.
+    fn poll(&mut self) -> Option<KrootResult>;
+
+    /// Requests the validation of a TESLA key.
+    ///
+    /// If the validation is done synchronously, its result is returned.
+    /// Otherwise, `None` is returned, and the result should be returned by a
+    /// later call to [`Verifier::poll_key`].
+    fn verify_key(&mut self, request: KeyRequest) -> Option<KeyResult> {
+        Some(request.run())
+    }
+
+    /// Returns the result of a deferred TESLA key validation, if any is
+    /// available.
+    fn poll_key(&mut self) -> Option<KeyResult> {
+        None
+    }
+}
+
+/// Request for the verification of a DSM-KROOT.
+///
+/// This contains a copy of all the data required to check the ECDSA signature
+/// of a DSM-KROOT message, so that it can be moved to another task or thread.
+#[derive(Debug, Clone)]
+pub struct KrootRequest {
+    dsm_id: u8,
+    nma_header: NmaHeader<NotValidated>,
+    dsm_kroot: [u8; MAX_DSM_BYTES],
+    dsm_kroot_len: usize,
+    pubkey: PublicKey<Validated>,
+    gst: Gst,
+}
+
+impl KrootRequest {
+    /// Constructs a new DSM-KROOT verification request.
+    ///
+    /// The `dsm_id` parameter gives the DSM ID of the DSM-KROOT. The `gst` parameter gives the GST at the start of the subframe in which
+    /// the last block of the DSM-KROOT was received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the DSM-KROOT is longer than the maximum DSM size.
+    pub fn new(
+        dsm_id: u8,
+        nma_header: NmaHeader<NotValidated>,
+        dsm_kroot: DsmKroot,
+        pubkey: &PublicKey<Validated>,
+        gst: Gst,
+    ) -> KrootRequest {
+        let mut data = [0; MAX_DSM_BYTES];
+        data[..dsm_kroot.0.len()].copy_from_slice(dsm_kroot.0);
+        KrootRequest {
+            dsm_id,
+            nma_header,
+            dsm_kroot: data,
+            dsm_kroot_len: dsm_kroot.0.len(),
+            pubkey: pubkey.clone(),
+            gst,
+        }
+    }
+
+    /// Gives the DSM ID of the DSM-KROOT.
+    pub fn dsm_id(&self) -> u8 {
+        self.dsm_id
+    }
+
+    /// Gives the GST at which the DSM-KROOT was received.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+
+    /// Gives the ID of the public key used for the verification.
+    pub fn public_key_id(&self) -> u8 {
+        self.pubkey.public_key_id()
+    }
+
+    /// Runs the verification.
+    ///
+    /// This checks the DSM-KROOT using [`Key::from_dsm_kroot`].
+    pub fn run(&self) -> KrootResult {
+        let dsm_kroot = DsmKroot(&self.dsm_kroot[..self.dsm_kroot_len]);
+        KrootResult {
+            result: Key::from_dsm_kroot(self.nma_header, dsm_kroot, &self.pubkey),
+            dsm_id: self.dsm_id,
+            pkid: self.public_key_id(),
+            gst: self.gst,
+        }
+    }
+}
+
+/// Result of the verification of a DSM-KROOT.
+#[derive(Debug, Clone)]
+pub struct KrootResult {
+    result: Result<(Key<Validated>, NmaHeader<Validated>), KrootValidationError>,
+    dsm_id: u8,
+    pkid: u8,
+    gst: Gst,
+}
+
+impl KrootResult {
+    /// Gives the result returned by [`Key::from_dsm_kroot`].
+    pub fn result(&self) -> &Result<(Key<Validated>, NmaHeader<Validated>), KrootValidationError> {
+        &self.result
+    }
+
+    /// Gives the DSM ID of the DSM-KROOT.
+    pub fn dsm_id(&self) -> u8 {
+        self.dsm_id
+    }
+
+    /// Gives the ID of the public key used for the verification.
+    pub fn public_key_id(&self) -> u8 {
+        self.pkid
+    }
+
+    /// Gives the GST at which the DSM-KROOT was received.
+    pub fn gst(&self) -> Gst {
+        self.gst
+    }
+}
+
+/// Request for the validation of a TESLA key.
+///
+/// This contains the TESLA key to validate and the validated key that is used
+/// to validate it. The validation is done by applying the one-way function to
+/// the key until the GST of the validated key is reached. The hashes required
+/// by the one-way function can be computed outside of this crate. The message
+/// to hash is obtained with [`KeyRequest::hash_message`], and the digest is
+/// given back with [`KeyRequest::submit_digest`]. Once all the hashes have been
+/// computed, the result is obtained with [`KeyRequest::finish`].
+#[derive(Debug, Clone)]
+pub struct KeyRequest {
+    validating_key: Key<Validated>,
+    key: Key<NotValidated>,
+    derived_key: Key<NotValidated>,
+    remaining: usize,
+    error: Option<ValidationError>,
+    message: [u8; ONE_WAY_FUNCTION_MESSAGE_BYTES],
+    message_len: usize,
+    svn: Svn,
+}
+
+impl KeyRequest {
+    /// Constructs a new TESLA key validation request.
+    ///
+    /// The request attempts to validate `key` using `validating_key`. The
+    /// `svn` parameter gives the SVN of the satellite that transmitted `key`.
+    ///
+    /// The checks that do not require computing the one-way function are done
+    /// here. If they fail, the request does not require any hashes, and
+    /// [`KeyRequest::finish`] returns the corresponding error.
+    pub fn new(validating_key: &Key<Validated>, key: Key<NotValidated>, svn: Svn) -> KeyRequest {
+        let (remaining, error) = match validating_key.check_follows(&key) {
+            Ok(derivations) => (derivations, None),
+            Err(e) => (0, Some(e)),
+        };
+        let mut request = KeyRequest {
+            validating_key: *validating_key,
+            key,
+            derived_key: key,
+            remaining,
+            error,
+            message: [0; ONE_WAY_FUNCTION_MESSAGE_BYTES],
+            message_len: 0,
+            svn,
+        };
+        request.update_message();
+        request
+    }
+
+    fn update_message(&mut self) {
+        self.message_len = self
+            .derived_key
+            .one_way_function_message(&mut self.message)
+            .len();
+    }
+
+    /// Gives the SVN of the satellite that transmitted the key.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Gives the GST at the start of the subframe in which the key was
+    /// transmitted.
+    pub fn gst(&self) -> Gst {
+        self.key.gst_subframe()
+    }
+
+    /// Gives the hash function that should be used to compute the digests.
+    pub fn hash_function(&self) -> HashFunction {
+        self.key.chain().hash_function()
+    }
+
+    /// Gives the number of hashes that remain to be computed.
+    pub fn remaining_hashes(&self) -> usize {
+        self.remaining
+    }
+
+    /// Gives the next message to hash.
+    ///
+    /// This returns `None` if all the hashes required by the request have
+    /// already been computed.
+    pub fn hash_message(&self) -> Option<&[u8]> {
+        if self.remaining == 0 {
+            None
+        } else {
+            Some(&self.message[..self.message_len])
+        }
+    }
+
+    /// Submits the digest of the message given by [`KeyRequest::hash_message`].
+    ///
+    /// The `digest` should be computed using the hash function given by
+    /// [`KeyRequest::hash_function`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no remaining hashes to compute, or if `digest` is
+    /// shorter than the TESLA key.
+    pub fn submit_digest(&mut self, digest: &[u8]) {
+        assert!(self.remaining > 0);
+        self.derived_key = self.derived_key.from_one_way_function_digest(digest);
+        self.remaining -= 1;
+        self.update_message();
+    }
+
+    /// Finishes the validation.
+    ///
+    /// This compares the key obtained with the submitted digests with the
+    /// validating key and returns the result of the validation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are hashes remaining to be computed.
+    pub fn finish(self) -> KeyResult {
+        assert_eq!(self.remaining, 0);
+        let result = match self.error {
+            Some(e) => Err(e),
+            None => self
+                .validating_key
+                .check_derived(&self.key, &self.derived_key),
+        };
+        KeyResult {
+            result,
+            validating_key: self.validating_key,
+            key: self.key,
+            svn: self.svn,
+        }
+    }
+
+    /// Runs the validation.
+    ///
+    /// This computes the remaining hashes in software and returns the result
+    /// of the validation, which is the same as that of [`Key::validate_key`].
+    pub fn run(mut self) -> KeyResult {
+        while self.remaining > 0 {
+            self.derived_key = self.derived_key.one_way_function();
+            self.remaining -= 1;
+        }
+        self.finish()
+    }
+}
+
+/// Result of the validation of a TESLA key.
+#[derive(Debug, Clone)]
+pub struct KeyResult {
+    result: Result<Key<Validated>, ValidationError>,
+    validating_key: Key<Validated>,
+    key: Key<NotValidated>,
+    svn: Svn,
+}
+
+impl KeyResult {
+    /// Gives the result of the validation.
+    ///
+    /// This is the same as the value returned by [`Key::validate_key`].
+    pub fn result(&self) -> &Result<Key<Validated>, ValidationError> {
+        &self.result
+    }
+
+    /// Gives the key that was used to validate the TESLA key.
+    pub fn validating_key(&self) -> &Key<Validated> {
+        &self.validating_key
+    }
+
+    /// Gives the TESLA key whose validation was requested.
+    pub fn key(&self) -> &Key<NotValidated> {
+        &self.key
+    }
+
+    /// Gives the SVN of the satellite that transmitted the key.
+    pub fn svn(&self) -> Svn {
+        self.svn
+    }
+
+    /// Gives the GST at the start of the subframe in which the key was
+    /// transmitted.
+    pub fn gst(&self) -> Gst {
+        self.key.gst_subframe()
+    }
+}
+
+/// Verifier that performs the verifications synchronously.
+///
+/// This is the default [`Verifier`] used by [`Osnma`](crate::Osnma).
+#[derive(Debug, Default, Clone)]
+pub struct ImmediateVerifier {}
+
+impl ImmediateVerifier {
+    /// Constructs a new immediate verifier.
+    pub fn new() -> ImmediateVerifier {
+        ImmediateVerifier {}
+    }
+}
+
+impl Verifier for ImmediateVerifier {
+    fn verify_kroot(&mut self, request: KrootRequest) -> Option<KrootResult> {
+        Some(request.run())
+    }
+
+    fn poll(&mut self) -> Option<KrootResult> {
+        None
+    }
+}
+
+/// Verifier that queues the verifications to be run elsewhere.
+///
+/// This [`Verifier`] holds at most one pending request and one completed
+/// result. If a new request arrives before the pending one has been taken, the
+/// pending request is replaced. This is not a problem in practice, because the
+/// DSM-KROOT is broadcast periodically.
+#[derive(Debug, Default, Clone)]
+pub struct QueuedVerifier {
+    request: Option<KrootRequest>,
+    result: Option<KrootResult>,
+}
+
+impl QueuedVerifier {
+    /// Constructs a new queued verifier with no pending requests.
+    pub fn new() -> QueuedVerifier {
+        QueuedVerifier::default()
+    }
+
+    /// Returns `true` if there is a request waiting to be taken.
+    pub fn has_request(&self) -> bool {
+        self.request.is_some()
+    }
+
+    /// Takes the pending verification request, if any.
+    ///
+    /// The request should be run with [`KrootRequest::run`], and its result
+    /// given back with [`QueuedVerifier::submit_result`].
+    pub fn take_request(&mut self) -> Option<KrootRequest> {
+        self.request.take()
+    }
+
+    /// Submits the result of a verification request.
+    ///
+    /// The result is returned by the next call to [`Verifier::poll`]. If a
+    /// previous result has not been polled yet, it is replaced.
+    pub fn submit_result(&mut self, result: KrootResult) {
+        self.result = Some(result);
+    }
+}
+
+impl Verifier for QueuedVerifier {
+    fn verify_kroot(&mut self, request: KrootRequest) -> Option<KrootResult> {
+        if self.request.is_some() {
+            log::debug!("replacing pending DSM-KROOT verification request");
+        }
+        self.request = Some(request);
+        None
+    }
+
+    fn poll(&mut self) -> Option<KrootResult> {
+        self.result.take()
+    }
+}
+
+/// Verifier that queues TESLA key validations to be computed elsewhere.
+///
+/// This [`Verifier`] is intended to be used with cryptographic co-processors.
+/// Instead of computing the TESLA one-way function, it stores the
+/// [`KeyRequest`], which can be taken with
+/// [`CoprocessorVerifier::take_key_request`]. The hashes of the request are
+/// then computed externally, and the result is given back with
+/// [`CoprocessorVerifier::submit_key_result`]. The result is applied by
+/// [`Osnma`](crate::Osnma) the next time that a subframe is processed.
+///
+/// The verification of DSM-KROOTs is delegated to the inner verifier `W`, so
+/// this can be combined with a [`QueuedVerifier`] to defer all the
+/// verifications.
+///
+/// As with [`QueuedVerifier`], at most one pending request and one result are
+/// held. A new request replaces the pending request. This is not a problem,
+/// because the same TESLA key is transmitted by all the satellites, and a
+/// new key is transmitted on each subframe.
+#[derive(Debug, Default, Clone)]
+pub struct CoprocessorVerifier<W = ImmediateVerifier> {
+    inner: W,
+    request: Option<KeyRequest>,
+    result: Option<KeyResult>,
+}
+
+impl<W> CoprocessorVerifier<W> {
+    /// Constructs a new co-processor verifier with no pending requests.
+    ///
+    /// The verification of DSM-KROOTs is delegated to `inner`.
+    pub fn new(inner: W) -> CoprocessorVerifier<W> {
+        CoprocessorVerifier {
+            inner,
+            request: None,
+            result: None,
+        }
+    }
+
+    /// Gives a reference to the inner verifier.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gives a mutable reference to the inner verifier.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns `true` if there is a TESLA key validation request waiting to be
+    /// taken.
+    pub fn has_key_request(&self) -> bool {
+        self.request.is_some()
+    }
+
+    /// Takes the pending TESLA key validation request, if any.
+    ///
+    /// The hashes of the request should be computed and submitted with
+    /// [`KeyRequest::submit_digest`], and the result obtained with
+    /// [`KeyRequest::finish`] given back with
+    /// [`CoprocessorVerifier::submit_key_result`].
+    pub fn take_key_request(&mut self) -> Option<KeyRequest> {
+        self.request.take()
+    }
+
+    /// Submits the result of a TESLA key validation request.
+    ///
+    /// The result is returned by the next call to [`Verifier::poll_key`]. If a
+    /// previous result has not been polled yet, it is replaced.
+    pub fn submit_key_result(&mut self, result: KeyResult) {
+        self.result = Some(result);
+    }
+}
+
+impl<W: Verifier> Verifier for CoprocessorVerifier<W> {
+    fn verify_kroot(&mut self, request: KrootRequest) -> Option<KrootResult> {
+        self.inner.verify_kroot(request)
+    }
+
+    fn poll(&mut self) -> Option<KrootResult> {
+        self.inner.poll()
+    }
+
+    fn verify_key(&mut self, request: KeyRequest) -> Option<KeyResult> {
+        if self
+            .request
+            .as_ref()
+            .is_some_and(|pending| pending.gst() == request.gst())
+        {
+            // The pending request already validates this key
+            return None;
+        }
+        if self.request.is_some() {
+            log::debug!("replacing pending TESLA key validation request");
+        }
+        self.request = Some(request);
+        None
+    }
+
+    fn poll_key(&mut self) -> Option<KeyResult> {
+        self.result.take()
+    }
+}
+
+const NUM_DSM_IDS: usize = 16;
+
+/// Number of consecutive DSM-KROOT verification failures for a DSM ID after
+/// which the verification of that DSM ID is rate-limited.
+pub const KROOT_FAILURE_THRESHOLD: u16 = 3;
+
+/// Minimum interval between DSM-KROOT verification attempts for a
+/// rate-limited DSM ID, in subframes.
+pub const KROOT_RETRY_INTERVAL_SUBFRAMES: i32 = 20;
+
+/// Tracker of DSM-KROOT verification failures.
+///
+/// This keeps track, for each DSM ID, of the DSM-KROOT messages whose
+/// verification has failed because of a wrong ECDSA signature or a wrong
+/// padding. Under normal conditions these failures should never happen, so
+/// repeated failures indicate that an attacker may be transmitting forged
+/// DSM-KROOT messages in order to waste CPU time and power in the receiver.
+///
+/// After [`KROOT_FAILURE_THRESHOLD`] consecutive failures for a DSM ID, the
+/// DSM ID becomes rate-limited. A warning is logged, and further DSM-KROOTs with
+/// that DSM ID are only verified once every
+/// [`KROOT_RETRY_INTERVAL_SUBFRAMES`] subframes. The rest are discarded without
+/// verification. A successful verification clears the rate limit.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct KrootFailureTracker {
+    status: [KrootFailureStatus; NUM_DSM_IDS],
+}
+
+/// DSM-KROOT verification failure status of a DSM ID.
+///
+/// This is obtained with [`KrootFailureTracker::status`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct KrootFailureStatus {
+    consecutive_failures: u16,
+    total_failures: u32,
+    suppressed: u32,
+    last_attempt: Option<Gst>,
+}
+
+impl KrootFailureStatus {
+    /// Gives the number of consecutive verification failures.
+    pub fn consecutive_failures(&self) -> u16 {
+        self.consecutive_failures
+    }
+
+    /// Gives the total number of verification failures.
+    pub fn total_failures(&self) -> u32 {
+        self.total_failures
+    }
+
+    /// Gives the number of DSM-KROOTs that have been discarded without
+    /// verification because of the rate limit.
+    pub fn suppressed(&self) -> u32 {
+        self.suppressed
+    }
+
+    /// Returns `true` if the verification of this DSM ID is rate-limited.
+    pub fn is_rate_limited(&self) -> bool {
+        self.consecutive_failures >= KROOT_FAILURE_THRESHOLD
+    }
+}
+
+impl KrootFailureTracker {
+    /// Constructs a new tracker with no failures recorded.
+    pub fn new() -> KrootFailureTracker {
+        KrootFailureTracker::default()
+    }
+
+    /// Gives the failure status of a DSM ID.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dsm_id` is not smaller than 16.
+    pub fn status(&self, dsm_id: u8) -> &KrootFailureStatus {
+        &self.status[usize::from(dsm_id)]
+    }
+
+    /// Returns `true` if the verification of any DSM ID is rate-limited.
+    ///
+    /// This can be used as an indication that the receiver is possibly under
+    /// attack.
+    pub fn any_rate_limited(&self) -> bool {
+        self.status.iter().any(|s| s.is_rate_limited())
+    }
+
+    /// Decides whether a DSM-KROOT should be verified.
+    ///
+    /// This returns `false` if the DSM ID is rate-limited and a verification
+    /// was attempted less than [`KROOT_RETRY_INTERVAL_SUBFRAMES`] before
+    /// `gst`. In this case, the DSM-KROOT is counted as suppressed. Otherwise,
+    /// `gst` is recorded as the time of the last attempt and `true` is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dsm_id` is not smaller than 16.
+    pub fn should_verify(&mut self, dsm_id: u8, gst: Gst) -> bool {
+        let status = &mut self.status[usize::from(dsm_id)];
+        if status.is_rate_limited()
+            && status.last_attempt.is_some_and(|last| {
+                (0..KROOT_RETRY_INTERVAL_SUBFRAMES).contains(&gst.subframes_difference(last))
+            })
+        {
+            status.suppressed = status.suppressed.saturating_add(1);
+            log::debug!(
+                "skipping verification of DSM-KROOT with DSM ID {dsm_id} because of rate limit"
+            );
+            return false;
+        }
+        status.last_attempt = Some(gst);
+        true
+    }
+
+    /// Records the result of a DSM-KROOT verification.
+    pub fn record(&mut self, result: &KrootResult) {
+        let dsm_id = result.dsm_id();
+        let status = &mut self.status[usize::from(dsm_id)];
+        match result.result() {
+            Ok(_) => {
+                if status.is_rate_limited() {
+                    log::info!("DSM-KROOT with DSM ID {dsm_id} verified; clearing rate limit");
+                }
+                status.consecutive_failures = 0;
+            }
+            Err(KrootValidationError::WrongEcdsa | KrootValidationError::WrongDsmKrootPadding) => {
+                status.consecutive_failures = status.consecutive_failures.saturating_add(1);
+                status.total_failures = status.total_failures.saturating_add(1);
+                if status.consecutive_failures == KROOT_FAILURE_THRESHOLD {
+                    log::warn!(
+                        "DSM-KROOT with DSM ID {dsm_id} has failed verification {} \
+                         consecutive times; possible forged DSM-KROOT attack; \
+                         rate-limiting its verification",
+                        status.consecutive_failures
+                    );
+                }
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing;
+    use hex_literal::hex;
+    use p256::ecdsa::VerifyingKey;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn queued_verifier() {
+        let dsm_kroot = testing::DSM_KROOT;
+        // Dummy public key, which does not match the DSM-KROOT signature
+        let pubkey = hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+             22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        );
+        let pubkey = VerifyingKey::from_sec1_bytes(&pubkey).unwrap();
+        let pubkey = PublicKey::from_p256(pubkey, 2).force_valid();
+        let gst = Gst::new(1176, 120990);
+        let request =
+            KrootRequest::new(2, NmaHeader::new(0x52), DsmKroot(&dsm_kroot), &pubkey, gst);
+
+        let mut verifier = QueuedVerifier::new();
+        assert!(verifier.verify_kroot(request.clone()).is_none());
+        assert!(verifier.poll().is_none());
+        let taken = verifier.take_request().unwrap();
+        assert!(!verifier.has_request());
+        let result = taken.run();
+        assert_eq!(result.public_key_id(), 2);
+        assert_eq!(result.gst(), gst);
+        assert!(result.result().is_err());
+        verifier.submit_result(result);
+        assert!(verifier.poll().unwrap().result().is_err());
+        assert!(verifier.poll().is_none());
+
+        let mut verifier = ImmediateVerifier::new();
+        let result = verifier.verify_kroot(request).unwrap();
+        assert!(result.result().is_err());
+    }
+
+    #[test]
+    fn coprocessor_verifier() {
+        let chain = testing::chain();
+        let gst = Gst::new(1176, 121080);
+        let key = Key::from_slice(&testing::TESLA_KEY, gst, &chain);
+        let previous_key = key.derive(3);
+        let validating_key = previous_key.force_valid();
+        let svn = Svn::try_from(19).unwrap();
+
+        let mut verifier = CoprocessorVerifier::new(ImmediateVerifier::new());
+        let request = KeyRequest::new(&validating_key, key, svn);
+        assert!(verifier.verify_key(request.clone()).is_none());
+        assert!(verifier.poll_key().is_none());
+        let mut taken = verifier.take_key_request().unwrap();
+        assert!(!verifier.has_key_request());
+        assert_eq!(taken.remaining_hashes(), 3);
+        while let Some(message) = taken.hash_message() {
+            let digest = Sha256::digest(message);
+            taken.submit_digest(&digest);
+        }
+        verifier.submit_key_result(taken.finish());
+        let result = verifier.poll_key().unwrap();
+        assert_eq!(result.gst(), gst);
+        assert_eq!(result.svn(), svn);
+        assert_eq!(*result.result(), Ok(key.force_valid()));
+        assert!(verifier.poll_key().is_none());
+        assert_eq!(*request.run().result(), Ok(key.force_valid()));
+
+        // Wrong digests make the validation fail
+        let mut request = KeyRequest::new(&validating_key, key, svn);
+        while request.hash_message().is_some() {
+            request.submit_digest(&[0; 32]);
+        }
+        assert_eq!(
+            *request.finish().result(),
+            Err(ValidationError::WrongOneWayFunction)
+        );
+
+        // Checks that do not need hashes are done immediately
+        let request = KeyRequest::new(&key.force_valid(), previous_key, svn);
+        assert!(request.hash_message().is_none());
+        assert_eq!(
+            *request.finish().result(),
+            Err(ValidationError::DoesNotFollow)
+        );
+    }
+    #[test]
+    fn failure_tracker() {
+        let gst = Gst::new(1176, 120990);
+        let failure = |gst| KrootResult {
+            result: Err(KrootValidationError::WrongEcdsa),
+            dsm_id: 3,
+            pkid: 1,
+            gst,
+        };
+        let mut tracker = KrootFailureTracker::new();
+        for j in 0..KROOT_FAILURE_THRESHOLD {
+            let gst = gst.add_subframes(j.into());
+            assert!(tracker.should_verify(3, gst));
+            tracker.record(&failure(gst));
+        }
+        assert!(tracker.status(3).is_rate_limited());
+        assert!(!tracker.status(4).is_rate_limited());
+        assert!(tracker.any_rate_limited());
+
+        let last = gst.add_subframes(i32::from(KROOT_FAILURE_THRESHOLD) - 1);
+        assert!(!tracker.should_verify(3, last.add_subframes(1)));
+        assert!(!tracker.should_verify(3, last.add_subframes(KROOT_RETRY_INTERVAL_SUBFRAMES - 1)));
+        assert_eq!(tracker.status(3).suppressed(), 2);
+        // Other DSM IDs are not affected
+        assert!(tracker.should_verify(4, last.add_subframes(1)));
+        let retry = last.add_subframes(KROOT_RETRY_INTERVAL_SUBFRAMES);
+        assert!(tracker.should_verify(3, retry));
+        tracker.record(&failure(retry));
+        assert_eq!(
+            tracker.status(3).total_failures(),
+            u32::from(KROOT_FAILURE_THRESHOLD) + 1
+        );
+    }
+}
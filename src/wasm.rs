@@ -0,0 +1,343 @@
+//! JavaScript bindings for WebAssembly.
+//!
+//! This module contains [`WasmOsnma`], a thin wrapper around the [`Osnma`]
+//! black box whose methods only use types supported by
+//! [wasm-bindgen](https://rustwasm.github.io/docs/wasm-bindgen/), so that
+//! OSNMA can be processed in a web browser. Byte arrays are passed as
+//! `Uint8Array`, the events are given as JSON strings, and the errors are
+//! thrown as JavaScript strings. The black box uses
+//! [`DynStorage`], which is allocated on the heap,
+//! since the stack of WebAssembly is small.
+//!
+//! This module is only available if the `wasm` feature is enabled. The crate
+//! can be built for the `wasm32-unknown-unknown` target, for instance with
+//! `wasm-pack build --target web --features wasm`.
+//!
+//! # Examples
+//!
+//! ```js
+//! import init, { WasmOsnma } from "./galileo_osnma.js";
+//!
+//! await init();
+//! const osnma = new WasmOsnma(pubkey, pkid, false);
+//! socket.onmessage = (msg) => {
+//!     osnma.feedJson(msg.data);
+//!     for (const event of JSON.parse(osnma.events())) {
+//!         console.log(event.description);
+//!     }
+//!     const ced = osnma.cedAndStatus(11);
+//!     if (ced !== undefined) {
+//!         console.log(`E11 authenticated with ${ced.authbits} bits`);
+//!     }
+//! };
+//! ```
+
+use crate::events::Event;
+use crate::json::JsonSubframe;
+use crate::navmessage::NavMessageData;
+use crate::storage::DynStorage;
+use crate::types::{BitSlice, InavBand, InavWord, MerkleTreeNode, OsnmaDataMessage};
+use crate::validation::Validated;
+use crate::{Gst, Osnma, PublicKey, Svn, Tow, Wn};
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+/// OSNMA black box for JavaScript.
+///
+/// See the [module documentation](crate::wasm).
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct WasmOsnma {
+    osnma: Osnma<DynStorage>,
+}
+
+/// Authenticated navigation data for JavaScript.
+///
+/// This is the equivalent of [`NavMessageData`]. It is returned by
+/// [`WasmOsnma::ced_and_status`] and [`WasmOsnma::timing_parameters`].
+#[wasm_bindgen]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct WasmNavData {
+    data: Vec<u8>,
+    len_bits: usize,
+    authbits: u16,
+    gst: Gst,
+}
+
+#[wasm_bindgen]
+impl WasmOsnma {
+    /// Constructs a new OSNMA black box using an ECDSA public key.
+    ///
+    /// The public key `pubkey` is given in SEC1 format, either compressed or
+    /// uncompressed, and its type (P-256 or P-521) is deduced from its length.
+    /// The `pkid` gives the public key ID. See [`Osnma::from_pubkey`].
+    #[wasm_bindgen(constructor)]
+    pub fn new(pubkey: &[u8], pkid: u8, only_slowmac: bool) -> Result<WasmOsnma, String> {
+        Ok(WasmOsnma {
+            osnma: Osnma::from_pubkey(parse_pubkey(pubkey, pkid)?, only_slowmac),
+        })
+    }
+
+    /// Constructs a new OSNMA black box using the Merkle tree root.
+    ///
+    /// The Merkle tree root is given as 32 bytes. The public key is obtained
+    /// from the DSM-PKR messages. See [`Osnma::from_merkle_tree`].
+    #[wasm_bindgen(js_name = fromMerkleTree)]
+    pub fn from_merkle_tree(
+        merkle_tree_root: &[u8],
+        only_slowmac: bool,
+    ) -> Result<WasmOsnma, String> {
+        let root = MerkleTreeNode::try_from(merkle_tree_root)
+            .map_err(|_| "the Merkle tree root must have 32 bytes".to_string())?;
+        Ok(WasmOsnma {
+            osnma: Osnma::from_merkle_tree(root, None, only_slowmac),
+        })
+    }
+
+    /// Feeds an INAV word into the OSNMA black box.
+    ///
+    /// The `word` must have 16 bytes, and the `band` must be either `"E1B"`
+    /// or `"E5B"`. See [`Osnma::feed_inav`].
+    #[wasm_bindgen(js_name = feedInav)]
+    pub fn feed_inav(
+        &mut self,
+        word: &[u8],
+        svn: u8,
+        wn: Wn,
+        tow: Tow,
+        band: &str,
+    ) -> Result<(), String> {
+        let word =
+            InavWord::try_from(word).map_err(|_| "the INAV word must have 16 bytes".to_string())?;
+        let band = match band {
+            "E1B" => InavBand::E1B,
+            "E5B" => InavBand::E5B,
+            _ => return Err(format!("invalid band {band}")),
+        };
+        self.osnma
+            .feed_inav(&word, parse_svn(svn)?, parse_gst(wn, tow)?, band);
+        Ok(())
+    }
+
+    /// Feeds the OSNMA data message of an E1B page into the OSNMA black box.
+    ///
+    /// The `osnma` data message must have 5 bytes. See [`Osnma::feed_osnma`].
+    #[wasm_bindgen(js_name = feedOsnma)]
+    pub fn feed_osnma(&mut self, osnma: &[u8], svn: u8, wn: Wn, tow: Tow) -> Result<(), String> {
+        let osnma = OsnmaDataMessage::try_from(osnma)
+            .map_err(|_| "the OSNMA data message must have 5 bytes".to_string())?;
+        self.osnma
+            .feed_osnma(&osnma, parse_svn(svn)?, parse_gst(wn, tow)?);
+        Ok(())
+    }
+
+    /// Feeds a JSON record of a subframe into the OSNMA black box.
+    ///
+    /// The format of the record is described in the [json](crate::json)
+    /// module.
+    #[wasm_bindgen(js_name = feedJson)]
+    pub fn feed_json(&mut self, record: &str) -> Result<(), String> {
+        let subframe = JsonSubframe::parse(record).map_err(|e| e.to_string())?;
+        self.osnma.feed_batch(subframe.pages());
+        Ok(())
+    }
+
+    /// Gives the authenticated CED and health status data of a satellite.
+    ///
+    /// This returns `undefined` if there is no authenticated data for the
+    /// satellite. See [`Osnma::get_ced_and_status`].
+    #[wasm_bindgen(js_name = cedAndStatus)]
+    pub fn ced_and_status(&self, svn: u8) -> Result<Option<WasmNavData>, String> {
+        Ok(self
+            .osnma
+            .get_ced_and_status(parse_svn(svn)?)
+            .map(WasmNavData::from))
+    }
+
+    /// Gives the authenticated timing parameters of a satellite.
+    ///
+    /// This returns `undefined` if there is no authenticated data for the
+    /// satellite. See [`Osnma::get_timing_parameters`].
+    #[wasm_bindgen(js_name = timingParameters)]
+    pub fn timing_parameters(&self, svn: u8) -> Result<Option<WasmNavData>, String> {
+        Ok(self
+            .osnma
+            .get_timing_parameters(parse_svn(svn)?)
+            .map(WasmNavData::from))
+    }
+
+    /// Gives the events produced since the last call, as a JSON array.
+    ///
+    /// Each event is a JSON object with an `event` member that gives the
+    /// kind of event, such as `"TeslaKeyValidated"`, a `description` member
+    /// with its text description, and `wn` and `tow` members with the GST of
+    /// the event, when it has one. See [`Osnma::poll_event`].
+    pub fn events(&mut self) -> String {
+        let events: Vec<_> = self.osnma.events().map(|e| event_json(&e)).collect();
+        serde_json::Value::from(events).to_string()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmNavData {
+    /// Gives the navigation data.
+    ///
+    /// The data is packed MSB-first, and padded with zeros to a whole
+    /// number of bytes. See [`WasmNavData::len_bits`].
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    /// Gives the length of the navigation data in bits.
+    #[wasm_bindgen(getter, js_name = lenBits)]
+    pub fn len_bits(&self) -> usize {
+        self.len_bits
+    }
+
+    /// Gives the number of authentication bits of the navigation data.
+    ///
+    /// See [`NavMessageData::authbits`].
+    #[wasm_bindgen(getter)]
+    pub fn authbits(&self) -> u16 {
+        self.authbits
+    }
+
+    /// Gives the week number of the GST of the navigation data.
+    ///
+    /// See [`NavMessageData::gst`].
+    #[wasm_bindgen(getter)]
+    pub fn wn(&self) -> Wn {
+        self.gst.wn()
+    }
+
+    /// Gives the time of week of the GST of the navigation data.
+    ///
+    /// See [`NavMessageData::gst`].
+    #[wasm_bindgen(getter)]
+    pub fn tow(&self) -> Tow {
+        self.gst.tow()
+    }
+}
+
+impl From<NavMessageData<'_>> for WasmNavData {
+    fn from(value: NavMessageData<'_>) -> WasmNavData {
+        let bits = value.data();
+        let mut data = vec![0; (bits.len() + 7) / 8];
+        BitSlice::from_slice_mut(&mut data)[..bits.len()].copy_from_bitslice(bits);
+        WasmNavData {
+            data,
+            len_bits: value.data().len(),
+            authbits: value.authbits(),
+            gst: value.gst(),
+        }
+    }
+}
+
+fn parse_pubkey(pubkey: &[u8], pkid: u8) -> Result<PublicKey<Validated>, String> {
+    let error = |e| format!("invalid public key: {e}");
+    let pubkey = match pubkey.len() {
+        33 | 65 => PublicKey::from_p256(
+            p256::ecdsa::VerifyingKey::from_sec1_bytes(pubkey).map_err(error)?,
+            pkid,
+        ),
+        #[cfg(feature = "p521")]
+        67 | 133 => PublicKey::from_p521(
+            p521::ecdsa::VerifyingKey::from_sec1_bytes(pubkey).map_err(error)?,
+            pkid,
+        ),
+        len => return Err(format!("invalid public key length {len}")),
+    };
+    // The public key is trusted because it is provided by the application.
+    Ok(pubkey.force_valid())
+}
+
+fn parse_svn(svn: u8) -> Result<Svn, String> {
+    Svn::try_from(svn).map_err(|e| e.to_string())
+}
+
+fn parse_gst(wn: Wn, tow: Tow) -> Result<Gst, String> {
+    if tow >= 604800 {
+        return Err(format!("invalid TOW {tow}"));
+    }
+    Ok(Gst::new(wn, tow))
+}
+
+fn event_json(event: &Event) -> serde_json::Value {
+    let (name, gst) = match event {
+        Event::Diagnostic(diagnostic) => ("Diagnostic", Some(diagnostic.gst())),
+        Event::KrootVerified { gst, .. } => ("KrootVerified", Some(*gst)),
+        Event::ChainTransitionScheduled { switch_gst, .. } => {
+            ("ChainTransitionScheduled", Some(*switch_gst))
+        }
+        Event::DsmPkrVerified { gst, .. } => ("DsmPkrVerified", Some(*gst)),
+        Event::TeslaKeyValidated { gst } => ("TeslaKeyValidated", Some(*gst)),
+        Event::TagAuthenticated { gst, .. } => ("TagAuthenticated", Some(*gst)),
+        Event::TagFailed { gst, .. } => ("TagFailed", Some(*gst)),
+        Event::AuditDiscrepancy { gst, .. } => ("AuditDiscrepancy", Some(*gst)),
+        Event::AuthStateChanged { gst, .. } => ("AuthStateChanged", Some(*gst)),
+    };
+    let mut value = json!({
+        "event": name,
+        "description": event.to_string(),
+    });
+    if let Some(gst) = gst {
+        value["wn"] = gst.wn().into();
+        value["tow"] = gst.tow().into();
+    }
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    fn test_osnma() -> WasmOsnma {
+        // Dummy public key
+        let pubkey = hex!(
+            "03 9a 24 cd 05 7a 6e a6 bb ee 21 75 74 5b ca 39
+             22 48 c8 ca 0a a9 fd e1 01 e9 52 63 85 ff f1 72 da"
+        );
+        WasmOsnma::new(&pubkey, 1, false).unwrap()
+    }
+
+    #[test]
+    fn errors() {
+        assert!(WasmOsnma::new(&[0; 10], 1, false).is_err());
+        assert!(WasmOsnma::from_merkle_tree(&[0; 31], false).is_err());
+        let mut osnma = test_osnma();
+        assert!(osnma.feed_inav(&[0; 15], 11, 1177, 0, "E1B").is_err());
+        assert!(osnma.feed_inav(&[0; 16], 0, 1177, 0, "E1B").is_err());
+        assert!(osnma.feed_inav(&[0; 16], 11, 1177, 604800, "E1B").is_err());
+        assert!(osnma.feed_inav(&[0; 16], 11, 1177, 0, "L1").is_err());
+        assert!(osnma.feed_osnma(&[0; 4], 11, 1177, 0).is_err());
+        assert!(osnma.feed_json("{}").is_err());
+        assert!(osnma.ced_and_status(37).is_err());
+    }
+
+    #[test]
+    fn feed() {
+        let mut osnma = test_osnma();
+        osnma.feed_inav(&[0; 16], 11, 1177, 0, "E1B").unwrap();
+        osnma.feed_osnma(&[0; 5], 11, 1177, 0).unwrap();
+        assert_eq!(osnma.ced_and_status(11), Ok(None));
+        assert_eq!(osnma.timing_parameters(11), Ok(None));
+        assert_eq!(osnma.events(), "[]");
+    }
+
+    #[test]
+    fn events() {
+        let event = Event::TeslaKeyValidated {
+            gst: Gst::new(1176, 121080),
+        };
+        assert_eq!(
+            event_json(&event),
+            json!({
+                "event": "TeslaKeyValidated",
+                "description": "TESLA key of WN=1176 TOW=121080 validated",
+                "wn": 1176,
+                "tow": 121080,
+            })
+        );
+    }
+}
@@ -0,0 +1,139 @@
+// Watchdog for stalled authentication.
+//
+// The watchdog keeps track of the last subframe in which tags were validated.
+// If OSNMA data keeps arriving but no tags are validated for a number of
+// subframes, it produces a DiagnosticKind::AuthenticationStalled diagnostic
+// with the most probable cause, based on the problems seen since tags were
+// last validated.
+
+use crate::diagnostics::{DiagnosticKind, StallCause};
+use crate::Gst;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Watchdog {
+    // Number of subframes without validated tags after which the watchdog
+    // triggers. The watchdog is disabled if this is None.
+    subframes: Option<u16>,
+    // GST of the subframe in which tags were last validated, or in which the
+    // watchdog was armed or last triggered.
+    reference: Option<Gst>,
+    // PKID of the last DSM-KROOT that could not be verified because its
+    // public key was not available.
+    wrong_pkid: Option<u8>,
+    // TESLA keys or KROOTs have been rejected due to their GST.
+    time_offset: bool,
+}
+
+impl Watchdog {
+    pub(crate) fn new() -> Watchdog {
+        Watchdog {
+            subframes: None,
+            reference: None,
+            wrong_pkid: None,
+            time_offset: false,
+        }
+    }
+
+    pub(crate) fn set_subframes(&mut self, subframes: Option<u16>) {
+        self.subframes = subframes;
+        self.rearm(None);
+    }
+
+    fn rearm(&mut self, gst: Option<Gst>) {
+        self.reference = gst;
+        self.wrong_pkid = None;
+        self.time_offset = false;
+    }
+
+    pub(crate) fn tags_validated(&mut self, gst: Gst) {
+        self.rearm(Some(gst.gst_subframe()));
+    }
+
+    pub(crate) fn wrong_public_key(&mut self, pkid: u8) {
+        self.wrong_pkid = Some(pkid);
+    }
+
+    pub(crate) fn time_offset(&mut self) {
+        self.time_offset = true;
+    }
+
+    // Called for each subframe of OSNMA data received. Returns a diagnostic if
+    // no tags have been validated during the watchdog period.
+    pub(crate) fn check(
+        &mut self,
+        gst: Gst,
+        have_key: bool,
+        only_slowmac: bool,
+    ) -> Option<DiagnosticKind> {
+        let threshold = self.subframes?;
+        let gst = gst.gst_subframe();
+        let Some(reference) = self.reference else {
+            self.reference = Some(gst);
+            return None;
+        };
+        let elapsed = gst.subframes_difference(reference);
+        if elapsed < i32::from(threshold) {
+            return None;
+        }
+        let cause = match (have_key, self.wrong_pkid) {
+            (false, Some(pkid)) => StallCause::WrongPublicKeyId { pkid },
+            _ if self.time_offset => StallCause::TimeOffset,
+            (false, None) => StallCause::NoKroot,
+            (true, _) if only_slowmac => StallCause::SlowMacOnly,
+            (true, _) => StallCause::MissingNavigationData,
+        };
+        self.rearm(Some(gst));
+        Some(DiagnosticKind::AuthenticationStalled {
+            subframes: elapsed.try_into().unwrap_or(u16::MAX),
+            cause,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stalled(subframes: u16, cause: StallCause) -> Option<DiagnosticKind> {
+        Some(DiagnosticKind::AuthenticationStalled { subframes, cause })
+    }
+
+    #[test]
+    fn watchdog() {
+        let gst = Gst::new(1176, 121080);
+        let mut watchdog = Watchdog::new();
+        assert_eq!(watchdog.check(gst, false, false), None);
+        assert_eq!(watchdog.check(gst.add_subframes(100), false, false), None);
+
+        watchdog.set_subframes(Some(10));
+        assert_eq!(watchdog.check(gst, false, false), None);
+        assert_eq!(watchdog.check(gst.add_subframes(9), false, false), None);
+        assert_eq!(
+            watchdog.check(gst.add_subframes(10), false, false),
+            stalled(10, StallCause::NoKroot)
+        );
+        // The watchdog is re-armed after triggering
+        watchdog.wrong_public_key(1);
+        assert_eq!(watchdog.check(gst.add_subframes(19), false, false), None);
+        assert_eq!(
+            watchdog.check(gst.add_subframes(20), false, false),
+            stalled(10, StallCause::WrongPublicKeyId { pkid: 1 })
+        );
+        watchdog.time_offset();
+        assert_eq!(
+            watchdog.check(gst.add_subframes(30), true, true),
+            stalled(10, StallCause::TimeOffset)
+        );
+        assert_eq!(
+            watchdog.check(gst.add_subframes(40), true, true),
+            stalled(10, StallCause::SlowMacOnly)
+        );
+        // Validated tags reset the watchdog
+        watchdog.tags_validated(gst.add_subframes(45).add_seconds(7));
+        assert_eq!(watchdog.check(gst.add_subframes(50), true, false), None);
+        assert_eq!(
+            watchdog.check(gst.add_subframes(55), true, false),
+            stalled(10, StallCause::MissingNavigationData)
+        );
+    }
+}